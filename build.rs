@@ -0,0 +1,84 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    grpc::compile();
+    #[cfg(feature = "node")]
+    napi_build::setup();
+}
+
+// service code for the hand written messages in src/grpc.rs, without needing protoc. keep in
+// sync with proto/service.proto
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    const CODEC: &str = "tonic_prost::ProstCodec";
+
+    pub fn compile() {
+        let methods = [
+            ("open", "Open", "OpenRequest", "OpenResponse", false, false),
+            (
+                "upload",
+                "Upload",
+                "UploadChunk",
+                "OpenResponse",
+                true,
+                false,
+            ),
+            (
+                "close",
+                "Close",
+                "HeapRequest",
+                "CloseResponse",
+                false,
+                false,
+            ),
+            ("summary", "Summary", "HeapRequest", "Summary", false, false),
+            (
+                "histogram",
+                "Histogram",
+                "HistogramRequest",
+                "HistogramEntry",
+                false,
+                true,
+            ),
+            (
+                "inspect",
+                "Inspect",
+                "ObjectRequest",
+                "Object",
+                false,
+                false,
+            ),
+            (
+                "paths",
+                "Paths",
+                "ObjectRequest",
+                "PathElement",
+                false,
+                true,
+            ),
+            ("find", "Find", "FindRequest", "FoundObject", false, true),
+        ];
+
+        let mut service = Service::builder()
+            .name("Analysis")
+            .package("heapdump_analyzer.v1");
+        for (name, route, input, output, client_streaming, server_streaming) in methods {
+            let mut method = Method::builder()
+                .name(name)
+                .route_name(route)
+                .input_type(format!("crate::grpc::{}", input))
+                .output_type(format!("crate::grpc::{}", output))
+                .codec_path(CODEC);
+            if client_streaming {
+                method = method.client_streaming();
+            }
+            if server_streaming {
+                method = method.server_streaming();
+            }
+            service = service.method(method.build());
+        }
+
+        Builder::new().compile(&[service.build()]);
+    }
+}
@@ -0,0 +1,42 @@
+#![no_main]
+
+use heapdump_analyzer::{
+    error::Policy,
+    parser::{
+        Header, Version,
+        borrowed::{BorrowedRecord, BorrowedRecords, BorrowedSubRecord},
+        timestamp_from_millis,
+    },
+    writer::RecordWriter,
+};
+use libfuzzer_sys::fuzz_target;
+
+// like records, after a valid header, through the zero copy parser
+fuzz_target!(|data: &[u8]| {
+    let header = Header {
+        version: Version::JavaProfile102,
+        timestamp: timestamp_from_millis(0).unwrap(),
+    };
+    let mut dump = RecordWriter::new(Vec::new(), &header)
+        .unwrap()
+        .finish()
+        .unwrap();
+    dump.extend_from_slice(data);
+
+    for policy in [Policy::Lenient, Policy::Strict] {
+        let Ok(records) = BorrowedRecords::new(&dump) else {
+            return;
+        };
+        for record in records.with_policy(policy) {
+            let Ok(BorrowedRecord::HeapDumpSegment { sub_records, .. }) = record else {
+                continue;
+            };
+            for sub_record in sub_records {
+                // decode the ids too, their slices come from lengths in the input
+                if let Ok(BorrowedSubRecord::ObjArrayDump { elements, .. }) = sub_record {
+                    elements.iter().for_each(drop);
+                }
+            }
+        }
+    }
+});
@@ -0,0 +1,36 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use heapdump_analyzer::{
+    RecordReader,
+    error::Policy,
+    parser::{Header, Version, timestamp_from_millis},
+    writer::RecordWriter,
+};
+use libfuzzer_sys::fuzz_target;
+
+// the input follows a valid header so runs aren't spent on finding "JAVA PROFILE 1.0.2"
+fuzz_target!(|data: &[u8]| {
+    let header = Header {
+        version: Version::JavaProfile102,
+        timestamp: timestamp_from_millis(0).unwrap(),
+    };
+    let mut dump = RecordWriter::new(Vec::new(), &header)
+        .unwrap()
+        .finish()
+        .unwrap();
+    dump.extend_from_slice(data);
+
+    for policy in [Policy::Lenient, Policy::Strict] {
+        let Ok(reader) = RecordReader::new(Cursor::new(&dump[..])) else {
+            return;
+        };
+        // errors are fine, panics and hangs aren't
+        for record in reader.with_policy(policy) {
+            if record.is_err() {
+                break;
+            }
+        }
+    }
+});
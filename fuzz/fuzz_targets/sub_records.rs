@@ -0,0 +1,17 @@
+#![no_main]
+
+use heapdump_analyzer::parser::{ReadCtx, sub_record::SubRecord};
+use libfuzzer_sys::fuzz_target;
+
+// sub records back to back, as in a segment body, with 8 and 4 byte ids
+fuzz_target!(|data: &[u8]| {
+    for id_size in [8, 4] {
+        let ctx = ReadCtx::new(id_size).unwrap();
+        let mut rest = data;
+        while !rest.is_empty() {
+            if SubRecord::new(&mut rest, ctx).is_err() {
+                break;
+            }
+        }
+    }
+});
@@ -0,0 +1,104 @@
+#ifndef HEAPDUMP_ANALYZER_H
+#define HEAPDUMP_ANALYZER_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct HdaHeap HdaHeap;
+
+typedef struct HdaHistogram HdaHistogram;
+
+typedef struct HdaHistogramEntry {
+  const char *class_name;
+  uint64_t instance_count;
+  uint64_t shallow_size;
+} HdaHistogramEntry;
+
+typedef struct HdaObject {
+  uint64_t id;
+  const char *class_name;
+  uint64_t shallow_size;
+  uint64_t retained_size;
+} HdaObject;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Analyzes the dump at the given path. Returns null on failure.
+ *
+ * # Safety
+ * `path` has to be a valid nul terminated string.
+ */
+struct HdaHeap *hda_heap_open(const char *path);
+
+/**
+ * # Safety
+ * `heap` has to come from `hda_heap_open` or be null, and isn't valid afterwards.
+ */
+void hda_heap_close(struct HdaHeap *heap);
+
+/**
+ * Computes the dominator tree, which fills in retained sizes of objects.
+ *
+ * # Safety
+ * `heap` has to be an open heap.
+ */
+void hda_heap_compute_dominators(const struct HdaHeap *heap);
+
+/**
+ * Class histogram, largest shallow size first. `include` is an optional class name prefix and
+ * may be null. Returns null on failure.
+ *
+ * # Safety
+ * `heap` has to be an open heap, `include` null or a valid nul terminated string.
+ */
+struct HdaHistogram *hda_histogram(const struct HdaHeap *heap, const char *include);
+
+/**
+ * Writes the next entry to `entry`, false once all entries were returned. Class names stay
+ * valid until the heap is closed.
+ *
+ * # Safety
+ * `histogram` has to come from `hda_histogram`, `entry` has to be writable.
+ */
+bool hda_histogram_next(struct HdaHistogram *histogram, struct HdaHistogramEntry *entry);
+
+/**
+ * # Safety
+ * `histogram` has to come from `hda_histogram` or be null, and isn't valid afterwards.
+ */
+void hda_histogram_free(struct HdaHistogram *histogram);
+
+/**
+ * Looks up an instance or array. False if there is none with this id.
+ *
+ * # Safety
+ * `heap` has to be an open heap, `object` has to be writable.
+ */
+bool hda_object(const struct HdaHeap *heap, uint64_t id, struct HdaObject *object);
+
+/**
+ * Points `ids` at the ids the object references and sets `len`. The array stays valid until
+ * the heap is closed. False if there is no object with this id.
+ *
+ * # Safety
+ * `heap` has to be an open heap, `ids` and `len` have to be writable.
+ */
+bool hda_references(const struct HdaHeap *heap, uint64_t id, const uint64_t **ids, size_t *len);
+
+/**
+ * Message of the last failure on this thread, or null. Valid until the next call failing on
+ * this thread.
+ */
+const char *hda_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* HEAPDUMP_ANALYZER_H */
@@ -0,0 +1,443 @@
+use anyhow::Result;
+
+use crate::{
+    analysis::{Analysis, HeapContext, Report},
+    analyzer::{
+        caches::annotate_caches,
+        encoding::{StringEncoding, string_encodings},
+        leaks::{DEFAULT_THRESHOLD, leak_suspects},
+        maps::map_waste,
+        packages::package_dominators,
+        resources::unclosed_resources,
+        roots::root_summary,
+        sparse::{DEFAULT_NULL_FRACTION, sparse_arrays},
+    },
+    output::{
+        human_bytes,
+        table::{Cell, Column, Table},
+    },
+    parser::Id,
+};
+
+pub fn analyses() -> Vec<Box<dyn Analysis>> {
+    vec![
+        Box::new(Histogram),
+        Box::new(Dominators),
+        Box::new(PackageDominators),
+        Box::new(LeakSuspects),
+        Box::new(UnclosedResources),
+        Box::new(GcRoots),
+        Box::new(StringEncodings),
+        Box::new(SparseArrays),
+        Box::new(MapWaste),
+    ]
+}
+
+// classes with the largest shallow size, honoring the configured filters
+pub struct Histogram;
+
+impl Analysis for Histogram {
+    fn name(&self) -> &str {
+        "histogram"
+    }
+
+    fn run(&self, context: &HeapContext) -> Result<Report> {
+        let heap = context.heap;
+        let mut table = Table::new(vec![
+            Column::flexible("Class"),
+            Column::right("Objects"),
+            Column::right("Shallow"),
+            Column::left("% of heap"),
+        ]);
+        let total = heap.total_shallow_size();
+        for entry in heap
+            .histogram(&context.config.filters)
+            .into_iter()
+            .take(context.config.output.rows)
+        {
+            table.add_row(vec![
+                Cell::Text(entry.class.java_name()),
+                Cell::Count(entry.instance_count),
+                Cell::Bytes(entry.shallow_size),
+                Cell::Percent {
+                    part: entry.shallow_size,
+                    total,
+                },
+            ]);
+        }
+        Ok(Report::new("Class histogram", table))
+    }
+}
+
+// objects only dominated by the gc roots, largest retained size first
+pub struct Dominators;
+
+impl Analysis for Dominators {
+    fn name(&self) -> &str {
+        "dominators"
+    }
+
+    fn needs_dominators(&self) -> bool {
+        true
+    }
+
+    fn run(&self, context: &HeapContext) -> Result<Report> {
+        let heap = context.heap;
+        let dominator_tree = context.dominator_tree();
+        let mut table = Table::new(vec![
+            Column::left("Object"),
+            Column::flexible("Class"),
+            Column::right("Retained"),
+            Column::left("% of reachable heap"),
+        ]);
+        let reachable = dominator_tree.reachable_size();
+        let children = dominator_tree.children();
+        for id in children
+            .get(&Id(0))
+            .into_iter()
+            .flatten()
+            .take(context.config.output.rows)
+        {
+            let retained = dominator_tree.retained_size(*id).unwrap_or(0);
+            table.add_row(vec![
+                Cell::Text(id.to_string()),
+                Cell::Text(heap.class_name_of(*id).unwrap_or_default()),
+                Cell::Bytes(retained),
+                Cell::Percent {
+                    part: retained,
+                    total: reachable,
+                },
+            ]);
+        }
+        Ok(Report::new("Top dominators", table))
+    }
+}
+
+// the biggest object of every package, a starting point per component rather than one global
+// top list. honors the configured filters
+pub struct PackageDominators;
+
+impl Analysis for PackageDominators {
+    fn name(&self) -> &str {
+        "package-dominators"
+    }
+
+    fn needs_dominators(&self) -> bool {
+        true
+    }
+
+    fn run(&self, context: &HeapContext) -> Result<Report> {
+        let dominator_tree = context.dominator_tree();
+        let mut table = Table::new(vec![
+            Column::flexible("Package"),
+            Column::left("Object"),
+            Column::flexible("Class"),
+            Column::right("Retained"),
+            Column::left("% of reachable heap"),
+        ]);
+        let reachable = dominator_tree.reachable_size();
+        for dominator in package_dominators(context.heap, dominator_tree, &context.config.filters)
+            .into_iter()
+            .take(context.config.output.rows)
+        {
+            table.add_row(vec![
+                Cell::Text(dominator.package),
+                Cell::Text(dominator.object_id.to_string()),
+                Cell::Text(dominator.class_name),
+                Cell::Bytes(dominator.retained_size),
+                Cell::Percent {
+                    part: dominator.retained_size,
+                    total: reachable,
+                },
+            ]);
+        }
+        Ok(Report::new("Biggest object per package", table))
+    }
+}
+
+// the findings of `leaks` with the default threshold, caches among them with their entries
+pub struct LeakSuspects;
+
+impl Analysis for LeakSuspects {
+    fn name(&self) -> &str {
+        "leak-suspects"
+    }
+
+    fn needs_dominators(&self) -> bool {
+        true
+    }
+
+    fn run(&self, context: &HeapContext) -> Result<Report> {
+        let dominator_tree = context.dominator_tree();
+        let mut suspects = leak_suspects(context.heap, dominator_tree, DEFAULT_THRESHOLD);
+        annotate_caches(&mut suspects, context.heap, context.contents()?)?;
+        let mut table = Table::new(vec![
+            Column::flexible("Suspect"),
+            Column::right("Retained"),
+            Column::left("% of reachable heap"),
+            Column::right("Entries"),
+            Column::right("Avg entry"),
+        ]);
+        for suspect in &suspects {
+            table.add_row(vec![
+                Cell::Text(suspect.describe()),
+                Cell::Bytes(suspect.retained_size),
+                Cell::Percent {
+                    part: suspect.retained_size,
+                    total: dominator_tree.reachable_size(),
+                },
+                match suspect.cache.as_ref().and_then(|c| c.entries) {
+                    Some(entries) => Cell::Count(entries),
+                    None => Cell::Text(String::new()),
+                },
+                match suspect.average_entry_size() {
+                    Some(size) => Cell::Bytes(size),
+                    None => Cell::Text(String::new()),
+                },
+            ]);
+        }
+
+        let report = Report::new("Leak suspects", table);
+        if suspects.is_empty() {
+            Ok(report.note("No leak suspects found"))
+        } else {
+            Ok(report)
+        }
+    }
+}
+
+// files, sockets and jdbc objects dropped without being closed, see unclosed_resources
+pub struct UnclosedResources;
+
+impl Analysis for UnclosedResources {
+    fn name(&self) -> &str {
+        "unclosed-resources"
+    }
+
+    fn run(&self, context: &HeapContext) -> Result<Report> {
+        let resources = unclosed_resources(context.heap, context.contents()?)?;
+        let mut table = Table::new(vec![
+            Column::flexible("Class"),
+            Column::right("Unreachable"),
+            Column::right("Finalizer only"),
+        ]);
+        for resource in resources.iter().take(context.config.output.rows) {
+            table.add_row(vec![
+                Cell::Text(resource.class.java_name()),
+                Cell::Count(resource.unreachable),
+                Cell::Count(resource.finalizer_only),
+            ]);
+        }
+
+        let report = Report::new("Unclosed resources", table);
+        if resources.is_empty() {
+            Ok(report.note("No unclosed resources found"))
+        } else {
+            Ok(report)
+        }
+    }
+}
+
+// gc root kinds with what only the roots of each kind keep alive, see root_summary
+pub struct GcRoots;
+
+impl Analysis for GcRoots {
+    fn name(&self) -> &str {
+        "gc-roots"
+    }
+
+    fn run(&self, context: &HeapContext) -> Result<Report> {
+        let heap = context.heap;
+        let mut table = Table::new(vec![
+            Column::flexible("Root kind"),
+            Column::right("Roots"),
+            Column::right("Only kept by it"),
+            Column::right("Size"),
+            Column::left("% of heap"),
+        ]);
+        let total = heap.total_shallow_size();
+        for summary in root_summary(heap) {
+            table.add_row(vec![
+                Cell::Text(summary.kind.to_string()),
+                Cell::Count(summary.roots),
+                Cell::Count(summary.exclusive_objects),
+                Cell::Bytes(summary.exclusive_size),
+                Cell::Percent {
+                    part: summary.exclusive_size,
+                    total,
+                },
+            ]);
+        }
+        Ok(Report::new("GC roots", table)
+            .note("Objects reachable from roots of several kinds are counted for none of them"))
+    }
+}
+
+// strings by LATIN1, UTF-16 or char[] storage, see string_encodings
+pub struct StringEncodings;
+
+impl Analysis for StringEncodings {
+    fn name(&self) -> &str {
+        "string-encodings"
+    }
+
+    fn run(&self, context: &HeapContext) -> Result<Report> {
+        let stats = string_encodings(context.heap, context.contents()?)?;
+        let mut table = Table::new(vec![
+            Column::flexible("Encoding"),
+            Column::right("Strings"),
+            Column::right("String size"),
+            Column::right("Array size"),
+            Column::left("% of strings"),
+        ]);
+        let total: u64 = stats.iter().map(|s| s.strings).sum();
+        for stats in &stats {
+            table.add_row(vec![
+                Cell::Text(stats.encoding.to_string()),
+                Cell::Count(stats.strings),
+                Cell::Bytes(stats.string_size),
+                Cell::Bytes(stats.array_size),
+                Cell::Percent {
+                    part: stats.strings,
+                    total,
+                },
+            ]);
+        }
+
+        let mut report = Report::new("String encodings", table);
+        if stats.is_empty() {
+            return Ok(report.note("No strings found"));
+        }
+        for stats in &stats {
+            match stats.encoding {
+                StringEncoding::Utf16 => {
+                    report = report.note(
+                        "UTF-16 strings hold a character outside latin1 and take two bytes for \
+                         each of their characters",
+                    )
+                }
+                StringEncoding::Chars => {
+                    report = report.note(format!(
+                        "char[] strings take two bytes per character, compact strings (jdk 9 and \
+                         later) would save up to {} for those that fit latin1",
+                        human_bytes(stats.array_size / 2)
+                    ))
+                }
+                StringEncoding::Latin1 => {}
+            }
+        }
+        Ok(report)
+    }
+}
+
+// object arrays mostly holding nulls by who holds them, see sparse_arrays
+pub struct SparseArrays;
+
+impl Analysis for SparseArrays {
+    fn name(&self) -> &str {
+        "sparse-arrays"
+    }
+
+    fn needs_dominators(&self) -> bool {
+        true
+    }
+
+    fn run(&self, context: &HeapContext) -> Result<Report> {
+        let groups = sparse_arrays(
+            context.heap,
+            context.contents()?,
+            context.dominator_tree(),
+            DEFAULT_NULL_FRACTION,
+        );
+        let mut table = Table::new(vec![
+            Column::flexible("Array"),
+            Column::flexible("Retained by"),
+            Column::right("Arrays"),
+            Column::left("% null"),
+            Column::right("Savings"),
+        ]);
+        for group in groups.iter().take(context.config.output.rows) {
+            table.add_row(vec![
+                Cell::Text(group.array_class.clone()),
+                Cell::Text(
+                    group
+                        .retainer_class
+                        .clone()
+                        .unwrap_or_else(|| "gc roots".to_string()),
+                ),
+                Cell::Count(group.arrays),
+                Cell::Percent {
+                    part: group.nulls,
+                    total: group.elements,
+                },
+                Cell::Bytes(group.savings),
+            ]);
+        }
+
+        let report = Report::new("Sparse object arrays", table);
+        if groups.is_empty() {
+            Ok(report.note(format!(
+                "No object arrays more than {}% null",
+                DEFAULT_NULL_FRACTION * 100.0
+            )))
+        } else {
+            Ok(report)
+        }
+    }
+}
+
+// hash maps with tables far larger than their entries, by map and owner class
+pub struct MapWaste;
+
+impl Analysis for MapWaste {
+    fn name(&self) -> &str {
+        "map-waste"
+    }
+
+    fn needs_dominators(&self) -> bool {
+        true
+    }
+
+    fn run(&self, context: &HeapContext) -> Result<Report> {
+        let groups = map_waste(context.heap, context.contents()?, context.dominator_tree())?;
+        let mut table = Table::new(vec![
+            Column::flexible("Map"),
+            Column::flexible("Owner"),
+            Column::right("Maps"),
+            Column::right("Entries"),
+            Column::right("Capacity"),
+            Column::left("% empty bins"),
+            Column::right("Wasted"),
+        ]);
+        for group in groups.iter().take(context.config.output.rows) {
+            table.add_row(vec![
+                Cell::Text(group.map_class.clone()),
+                Cell::Text(
+                    group
+                        .owner_class
+                        .clone()
+                        .unwrap_or_else(|| "gc roots".to_string()),
+                ),
+                Cell::Count(group.maps),
+                Cell::Count(group.entries),
+                Cell::Count(group.capacity),
+                Cell::Percent {
+                    part: group.empty_bins,
+                    total: group.capacity,
+                },
+                Cell::Bytes(group.wasted),
+            ]);
+        }
+
+        let report = Report::new("Oversized hash maps", table);
+        if groups.is_empty() {
+            return Ok(report.note("No hash maps with tables larger than their entries need"));
+        }
+        let wasted: u64 = groups.iter().map(|g| g.wasted).sum();
+        Ok(report.note(format!(
+            "{} in empty table slots, maps sized for their entries or trimmed by copying into a \
+             new map would free them",
+            human_bytes(wasted)
+        )))
+    }
+}
@@ -0,0 +1,139 @@
+use anyhow::Result;
+
+use crate::{
+    analysis::{
+        HeapContext, Report,
+        detectors::{
+            Detector, collection_field, describe, instances_extending, int_field, object_field,
+        },
+    },
+    output::table::{Cell, Column, Table},
+};
+
+const GUAVA_CACHE: &str = "com/google/common/cache/LocalCache";
+const CAFFEINE_CACHES: [&str; 2] = [
+    "com/github/benmanes/caffeine/cache/BoundedLocalCache",
+    "com/github/benmanes/caffeine/cache/UnboundedLocalCache",
+];
+
+// guava and caffeine caches with their entries, bounds and retained size
+pub struct LibraryCaches;
+
+struct LibraryCache {
+    name: String,
+    library: &'static str,
+    entries: u64,
+    // None for unbounded caches
+    maximum: Option<u64>,
+    retained_size: u64,
+}
+
+impl Detector for LibraryCaches {
+    fn name(&self) -> &str {
+        "library-caches"
+    }
+
+    fn library(&self) -> &str {
+        "Guava or Caffeine cache"
+    }
+
+    fn title(&self) -> &str {
+        "Guava and Caffeine caches"
+    }
+
+    fn marker_classes(&self) -> &[&'static str] {
+        &[GUAVA_CACHE, CAFFEINE_CACHES[0], CAFFEINE_CACHES[1]]
+    }
+
+    fn needs_dominators(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, context: &HeapContext) -> Result<Report> {
+        let heap = context.heap;
+        let contents = context.contents()?;
+        let dominator_tree = context.dominator_tree();
+        let mut caches = Vec::new();
+
+        // guava counts the entries per segment, maxWeight is the size bound unless weighed
+        for cache in instances_extending(heap, &[GUAVA_CACHE]) {
+            let mut entries = 0;
+            let segments = object_field(heap, contents, cache.id, "segments")?
+                .and_then(|segments| contents.object_array(heap, segments));
+            for segment in segments.into_iter().flatten().filter(|s| s.0 != 0) {
+                entries += int_field(heap, contents, segment, "count")?
+                    .unwrap_or(0)
+                    .max(0) as u64;
+            }
+            let maximum = int_field(heap, contents, cache.id, "maxWeight")?
+                .filter(|max| *max >= 0)
+                .map(|max| max as u64);
+            caches.push(LibraryCache {
+                name: describe(&cache),
+                library: "Guava",
+                entries,
+                maximum,
+                retained_size: dominator_tree.retained_size(cache.id).unwrap_or(0),
+            });
+        }
+
+        // caffeine keeps the entries in a ConcurrentHashMap, bounded caches are generated
+        // subclasses with a maximum field
+        for cache in instances_extending(heap, &CAFFEINE_CACHES) {
+            let entries = collection_field(heap, contents, cache.id, "data")?;
+            let maximum = int_field(heap, contents, cache.id, "maximum")?
+                .filter(|max| *max >= 0)
+                .map(|max| max as u64);
+            caches.push(LibraryCache {
+                name: describe(&cache),
+                library: "Caffeine",
+                entries,
+                maximum,
+                retained_size: dominator_tree.retained_size(cache.id).unwrap_or(0),
+            });
+        }
+
+        caches.sort_by(|a, b| {
+            b.retained_size
+                .cmp(&a.retained_size)
+                .then_with(|| b.entries.cmp(&a.entries))
+        });
+        let mut table = Table::new(vec![
+            Column::flexible("Cache"),
+            Column::left("Library"),
+            Column::right("Entries"),
+            Column::right("Maximum"),
+            Column::right("Retained"),
+            Column::right("Avg entry"),
+        ]);
+        for cache in caches.iter().take(context.config.output.rows) {
+            table.add_row(vec![
+                Cell::Text(cache.name.clone()),
+                Cell::Text(cache.library.to_string()),
+                Cell::Count(cache.entries),
+                match cache.maximum {
+                    Some(maximum) => Cell::Count(maximum),
+                    None => Cell::Text(String::new()),
+                },
+                Cell::Bytes(cache.retained_size),
+                match cache.retained_size.checked_div(cache.entries) {
+                    Some(size) => Cell::Bytes(size),
+                    None => Cell::Text(String::new()),
+                },
+            ]);
+        }
+
+        let report = Report::new(self.title(), table);
+        let unbounded = caches.iter().filter(|c| c.maximum.is_none()).count();
+        if caches.is_empty() {
+            Ok(report.note("No caches found"))
+        } else if unbounded > 0 {
+            Ok(report.note(format!(
+                "{} caches have no size bound and only shrink through expiry or references",
+                unbounded
+            )))
+        } else {
+            Ok(report)
+        }
+    }
+}
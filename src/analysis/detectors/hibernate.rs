@@ -0,0 +1,124 @@
+use anyhow::Result;
+
+use crate::{
+    analysis::{
+        HeapContext, Report,
+        detectors::{Detector, collection_field, describe, instances_extending, object_field},
+    },
+    output::table::{Cell, Column, Table},
+    parser::sub_record::FieldValue,
+};
+
+const SESSION: &str = "org/hibernate/internal/SessionImpl";
+
+// entities from which a first-level cache counts as bloated
+const LARGE_CONTEXT: u64 = 10_000;
+// open sessions from which they look leaked rather than in use by request threads
+const MANY_SESSIONS: usize = 100;
+
+// sessions by the size of their first-level cache, the entities and collections of their
+// persistence context
+pub struct Sessions;
+
+struct Session {
+    name: String,
+    entities: u64,
+    collections: u64,
+    retained_size: u64,
+    open: bool,
+}
+
+impl Detector for Sessions {
+    fn name(&self) -> &str {
+        "hibernate-sessions"
+    }
+
+    fn library(&self) -> &str {
+        "Hibernate"
+    }
+
+    fn title(&self) -> &str {
+        "Hibernate sessions"
+    }
+
+    fn marker_classes(&self) -> &[&'static str] {
+        &[SESSION]
+    }
+
+    fn needs_dominators(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, context: &HeapContext) -> Result<Report> {
+        let heap = context.heap;
+        let contents = context.contents()?;
+        let dominator_tree = context.dominator_tree();
+
+        let mut sessions = Vec::new();
+        for session in instances_extending(heap, &[SESSION]) {
+            let (entities, collections) =
+                match object_field(heap, contents, session.id, "persistenceContext")? {
+                    Some(context) => (
+                        collection_field(heap, contents, context, "entitiesByKey")?,
+                        collection_field(heap, contents, context, "collectionsByKey")?,
+                    ),
+                    None => (0, 0),
+                };
+            let closed = matches!(
+                contents.field(heap, session.id, "closed")?,
+                Some(FieldValue::Boolean(true))
+            );
+            sessions.push(Session {
+                name: describe(&session),
+                entities,
+                collections,
+                retained_size: dominator_tree.retained_size(session.id).unwrap_or(0),
+                open: !closed,
+            });
+        }
+        sessions.sort_by(|a, b| {
+            b.retained_size
+                .cmp(&a.retained_size)
+                .then_with(|| b.entities.cmp(&a.entities))
+        });
+
+        let mut table = Table::new(vec![
+            Column::flexible("Session"),
+            Column::right("Entities"),
+            Column::right("Collections"),
+            Column::right("Retained"),
+        ]);
+        for session in sessions.iter().take(context.config.output.rows) {
+            table.add_row(vec![
+                Cell::Text(session.name.clone()),
+                Cell::Count(session.entities),
+                Cell::Count(session.collections),
+                Cell::Bytes(session.retained_size),
+            ]);
+        }
+
+        let mut report = Report::new(self.title(), table);
+        let bloated = sessions
+            .iter()
+            .filter(|s| s.entities >= LARGE_CONTEXT)
+            .count();
+        if bloated > 0 {
+            report = report.note(format!(
+                "{} sessions hold {} or more entities, flush() and clear() batch work regularly \
+                 or use a StatelessSession",
+                bloated, LARGE_CONTEXT
+            ));
+        }
+        let open = sessions.iter().filter(|s| s.open).count();
+        if open >= MANY_SESSIONS {
+            report = report.note(format!(
+                "{} open sessions, sessions not closed after use keep their entities in memory",
+                open
+            ));
+        }
+        if sessions.is_empty() {
+            report = report.note("No sessions found");
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::Result;
+
+use crate::{
+    analysis::{
+        HeapContext, Report,
+        detectors::{
+            Detector, describe, instances_extending, int_field, object_field, string_field,
+        },
+    },
+    output::table::{Cell, Column, Table},
+};
+
+// appender base classes of logback, log4j 2 and log4j 1
+const APPENDERS: [&str; 4] = [
+    "ch/qos/logback/core/UnsynchronizedAppenderBase",
+    "ch/qos/logback/core/AppenderBase",
+    "org/apache/logging/log4j/core/appender/AbstractAppender",
+    "org/apache/log4j/AppenderSkeleton",
+];
+
+// where async appenders queue events and list appenders keep them
+const BUFFERS: [&str; 5] = ["blockingQueue", "queue", "buffer", "list", "events"];
+// bounds of those, appenders without one buffer without limit
+const CAPACITIES: [&str; 2] = ["queueSize", "bufferSize"];
+
+const FULL_QUEUE: &str = "Full async appender queues mean the appender can't keep up, logging \
+    threads block or events get dropped";
+const UNBOUNDED_LIST: &str = "Appenders keeping every event, like logback's ListAppender, are \
+    meant for tests";
+
+// events buffered by appenders, async appenders with full queues and unbounded list appenders
+// among them
+pub struct AppenderBuffers;
+
+struct Appender {
+    name: String,
+    buffered: u64,
+    // None for unbounded buffers
+    capacity: Option<u64>,
+    retained_size: u64,
+}
+
+impl Appender {
+    fn hint(&self, many_entries: u64) -> Option<&'static str> {
+        match self.capacity {
+            Some(capacity) if capacity > 0 && self.buffered >= capacity => Some(FULL_QUEUE),
+            None if self.buffered >= many_entries => Some(UNBOUNDED_LIST),
+            _ => None,
+        }
+    }
+}
+
+impl Detector for AppenderBuffers {
+    fn name(&self) -> &str {
+        "logging-appenders"
+    }
+
+    fn library(&self) -> &str {
+        "Logback or Log4j"
+    }
+
+    fn title(&self) -> &str {
+        "Logging appender buffers"
+    }
+
+    fn marker_classes(&self) -> &[&'static str] {
+        &APPENDERS
+    }
+
+    fn needs_dominators(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, context: &HeapContext) -> Result<Report> {
+        let heap = context.heap;
+        let contents = context.contents()?;
+        let dominator_tree = context.dominator_tree();
+
+        let mut appenders = Vec::new();
+        for appender in instances_extending(heap, &APPENDERS) {
+            let mut buffered = None;
+            for field in BUFFERS {
+                if let Some(buffer) = object_field(heap, contents, appender.id, field)? {
+                    buffered = contents.collection_size(heap, buffer)?;
+                    break;
+                }
+            }
+            let Some(buffered) = buffered else {
+                continue;
+            };
+            let mut capacity = None;
+            for field in CAPACITIES {
+                if let Some(value) = int_field(heap, contents, appender.id, field)? {
+                    capacity = Some(value.max(0) as u64);
+                    break;
+                }
+            }
+            let name = match string_field(heap, contents, appender.id, "name")? {
+                Some(name) => format!("{} ({})", describe(&appender), name),
+                None => describe(&appender),
+            };
+            appenders.push(Appender {
+                name,
+                buffered,
+                capacity,
+                retained_size: dominator_tree.retained_size(appender.id).unwrap_or(0),
+            });
+        }
+        appenders.sort_by(|a, b| {
+            b.retained_size
+                .cmp(&a.retained_size)
+                .then_with(|| b.buffered.cmp(&a.buffered))
+        });
+
+        let mut table = Table::new(vec![
+            Column::flexible("Appender"),
+            Column::right("Buffered"),
+            Column::right("Capacity"),
+            Column::right("Retained"),
+        ]);
+        for appender in appenders.iter().take(context.config.output.rows) {
+            table.add_row(vec![
+                Cell::Text(appender.name.clone()),
+                Cell::Count(appender.buffered),
+                match appender.capacity {
+                    Some(capacity) => Cell::Count(capacity),
+                    None => Cell::Text(String::new()),
+                },
+                Cell::Bytes(appender.retained_size),
+            ]);
+        }
+
+        let mut report = Report::new(self.title(), table);
+        if appenders.is_empty() {
+            return Ok(report.note("No buffering appenders found"));
+        }
+        for hint in [FULL_QUEUE, UNBOUNDED_LIST] {
+            let many_entries = context.config.detectors.many_entries;
+            if appenders.iter().any(|a| a.hint(many_entries) == Some(hint)) {
+                report = report.note(hint);
+            }
+        }
+        Ok(report)
+    }
+}
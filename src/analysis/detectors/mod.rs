@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use crate::{
+    analysis::{Analysis, HeapContext, Report},
+    analyzer::{AnalyzedHeap, Instance, contents::Contents, handle::Handle},
+    output::table::Table,
+    parser::{Id, sub_record::FieldValue},
+};
+
+mod caches;
+mod hibernate;
+mod logging;
+mod netty;
+mod okhttp;
+mod redeploy;
+mod spring;
+
+// entries from which an unbounded collection counts as growing rather than just big, see
+// DetectorsConfig::many_entries
+pub const DEFAULT_MANY_ENTRIES: u64 = 1000;
+
+pub fn analyses() -> Vec<Box<dyn Analysis>> {
+    vec![
+        Box::new(Detected(netty::PooledBuffers)),
+        Box::new(Detected(caches::LibraryCaches)),
+        Box::new(Detected(okhttp::ConnectionPools)),
+        Box::new(Detected(spring::BeanFactoryCaches)),
+        Box::new(Detected(hibernate::Sessions)),
+        Box::new(Detected(logging::AppenderBuffers)),
+        Box::new(Detected(redeploy::RedeployedStatics)),
+    ]
+}
+
+// an analysis of the objects of one library. it only runs on dumps with classes of the library
+// loaded, the report of other dumps just says the library isn't used
+pub trait Detector: Send + Sync {
+    // unique, kebab-case, see Analysis::name
+    fn name(&self) -> &str;
+
+    // e.g. "Netty", named in the report of dumps without it
+    fn library(&self) -> &str;
+
+    fn title(&self) -> &str;
+
+    // internal names of classes, any of them loaded means the library is in use. detectors
+    // without any run on every dump
+    fn marker_classes(&self) -> &[&'static str];
+
+    fn needs_dominators(&self) -> bool {
+        false
+    }
+
+    fn detect(&self, context: &HeapContext) -> Result<Report>;
+}
+
+// a detector registered as an analysis
+pub struct Detected<D>(pub D);
+
+impl<D: Detector> Analysis for Detected<D> {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn needs_dominators(&self) -> bool {
+        self.0.needs_dominators()
+    }
+
+    fn run(&self, context: &HeapContext) -> Result<Report> {
+        let heap = context.heap;
+        let markers = self.0.marker_classes();
+        let loaded = markers.is_empty()
+            || markers
+                .iter()
+                .any(|name| heap.find_class_by_name(name).is_some());
+        if !loaded {
+            return Ok(Report::new(self.0.title(), Table::new(Vec::new()))
+                .note(format!("No {} classes loaded", self.0.library())));
+        }
+        self.0.detect(context)
+    }
+}
+
+// instances of the classes with one of the internal names and of their subclasses
+fn instances_extending<'a>(heap: &'a AnalyzedHeap, names: &[&str]) -> Vec<Instance<'a>> {
+    let classes: HashSet<Id> = heap
+        .classes
+        .values()
+        .filter(|class| heap.extends_any(class, names))
+        .map(|class| class.id)
+        .collect();
+    if classes.is_empty() {
+        return Vec::new();
+    }
+    (0..heap.instances.len() as u32)
+        .map(|h| heap.instance_at(Handle(h)))
+        .filter(|instance| classes.contains(&instance.class.id))
+        .collect()
+}
+
+// integral fields widened, None when missing or of another type
+fn int_field(heap: &AnalyzedHeap, contents: &Contents, id: Id, name: &str) -> Result<Option<i64>> {
+    Ok(match contents.field(heap, id, name)? {
+        Some(FieldValue::Byte(v)) => Some(v as i64),
+        Some(FieldValue::Short(v)) => Some(v as i64),
+        Some(FieldValue::Int(v)) => Some(v as i64),
+        Some(FieldValue::Long(v)) => Some(v),
+        _ => None,
+    })
+}
+
+// None when missing or null
+fn object_field(
+    heap: &AnalyzedHeap,
+    contents: &Contents,
+    id: Id,
+    name: &str,
+) -> Result<Option<Id>> {
+    Ok(match contents.field(heap, id, name)? {
+        Some(FieldValue::NormalObject { object_id }) if object_id.0 != 0 => Some(object_id),
+        _ => None,
+    })
+}
+
+// entries of a collection field, 0 when missing, null or not a collection
+fn collection_field(heap: &AnalyzedHeap, contents: &Contents, id: Id, name: &str) -> Result<u64> {
+    match object_field(heap, contents, id, name)? {
+        Some(collection) => Ok(contents.collection_size(heap, collection)?.unwrap_or(0)),
+        None => Ok(0),
+    }
+}
+
+// the contents of a String field, None when missing, null or not a string
+fn string_field(
+    heap: &AnalyzedHeap,
+    contents: &Contents,
+    id: Id,
+    name: &str,
+) -> Result<Option<String>> {
+    match object_field(heap, contents, id, name)? {
+        Some(string) => Ok(contents.string_value(heap, string)?),
+        None => Ok(None),
+    }
+}
+
+// how objects of a library are named in its report, the class and the id
+fn describe(instance: &Instance) -> String {
+    format!("{} @ {}", instance.class.java_name(), instance.id)
+}
@@ -0,0 +1,117 @@
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use anyhow::Result;
+
+use crate::{
+    analysis::{
+        HeapContext, Report,
+        detectors::{Detector, instances_extending, int_field, object_field},
+    },
+    output::table::{Cell, Column, Table},
+};
+
+const POOL_CHUNK: &str = "io/netty/buffer/PoolChunk";
+const POOLED_BYTE_BUF: &str = "io/netty/buffer/PooledByteBuf";
+const DIRECT_ARENA: &str = "io/netty/buffer/PoolArena$DirectArena";
+
+// memory of netty 4.1 pooled allocators: chunks reserved by the arenas and the pooled buffers
+// handed out of them
+pub struct PooledBuffers;
+
+#[derive(Default)]
+struct Totals {
+    objects: u64,
+    capacity: u64,
+    used: u64,
+}
+
+impl Detector for PooledBuffers {
+    fn name(&self) -> &str {
+        "netty-buffers"
+    }
+
+    fn library(&self) -> &str {
+        "Netty"
+    }
+
+    fn title(&self) -> &str {
+        "Netty pooled buffers"
+    }
+
+    fn marker_classes(&self) -> &[&'static str] {
+        &[POOL_CHUNK, POOLED_BYTE_BUF]
+    }
+
+    fn detect(&self, context: &HeapContext) -> Result<Report> {
+        let heap = context.heap;
+        let contents = context.contents()?;
+
+        // chunks by arena kind, capacity is the chunk size and used what isn't free in it
+        let mut arenas: BTreeMap<&str, Totals> = BTreeMap::new();
+        for chunk in instances_extending(heap, &[POOL_CHUNK]) {
+            let direct = object_field(heap, contents, chunk.id, "arena")?
+                .and_then(|arena| heap.class_of(arena))
+                .is_some_and(|class| &*class.name == DIRECT_ARENA);
+            let size = int_field(heap, contents, chunk.id, "chunkSize")?.unwrap_or(0);
+            let free = int_field(heap, contents, chunk.id, "freeBytes")?.unwrap_or(0);
+            let kind = if direct {
+                "direct arenas"
+            } else {
+                "heap arenas"
+            };
+            let totals = arenas.entry(kind).or_default();
+            totals.objects += 1;
+            totals.capacity += size.max(0) as u64;
+            totals.used += (size - free).max(0) as u64;
+        }
+
+        // buffers by class, capacity is their length and used up to the writer index. released
+        // buffers lose their chunk and wait in the recycler for reuse
+        let mut buffers: BTreeMap<String, Totals> = BTreeMap::new();
+        let mut recycled = 0;
+        for buffer in instances_extending(heap, &[POOLED_BYTE_BUF]) {
+            if object_field(heap, contents, buffer.id, "chunk")?.is_none() {
+                recycled += 1;
+                continue;
+            }
+            let length = int_field(heap, contents, buffer.id, "length")?.unwrap_or(0);
+            let written = int_field(heap, contents, buffer.id, "writerIndex")?.unwrap_or(0);
+            let totals = buffers.entry(buffer.class.java_name()).or_default();
+            totals.objects += 1;
+            totals.capacity += length.max(0) as u64;
+            totals.used += written.max(0) as u64;
+        }
+
+        let mut table = Table::new(vec![
+            Column::flexible("Pool"),
+            Column::right("Objects"),
+            Column::right("Capacity"),
+            Column::right("Used"),
+        ]);
+        let mut buffers: Vec<(String, Totals)> = buffers.into_iter().collect();
+        buffers.sort_by_key(|(_, t)| Reverse(t.capacity));
+        let rows = arenas
+            .into_iter()
+            .map(|(kind, totals)| (kind.to_string(), totals))
+            .chain(buffers)
+            .take(context.config.output.rows);
+        for (name, totals) in rows {
+            table.add_row(vec![
+                Cell::Text(name),
+                Cell::Count(totals.objects),
+                Cell::Bytes(totals.capacity),
+                Cell::Bytes(totals.used),
+            ]);
+        }
+
+        let report = Report::new(self.title(), table);
+        if recycled > 0 {
+            Ok(report.note(format!(
+                "{} released buffers are kept by the recycler for reuse",
+                recycled
+            )))
+        } else {
+            Ok(report)
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::{
+    analysis::{
+        HeapContext, Report,
+        detectors::{Detector, describe, instances_extending, int_field, object_field},
+    },
+    output::table::{Cell, Column, Table},
+    parser::Id,
+};
+
+// okhttp 3 pools are ConnectionPool itself, from 4 on it delegates to RealConnectionPool
+const POOLS: [&str; 2] = [
+    "okhttp3/ConnectionPool",
+    "okhttp3/internal/connection/RealConnectionPool",
+];
+const CONNECTION: &str = "okhttp3/internal/connection/RealConnection";
+
+// okhttp connection pools with their open and idle connections
+pub struct ConnectionPools;
+
+#[derive(Default)]
+struct Connections {
+    open: u64,
+    // without calls, okhttp 3 calls them allocations
+    idle: u64,
+}
+
+impl Detector for ConnectionPools {
+    fn name(&self) -> &str {
+        "okhttp-pools"
+    }
+
+    fn library(&self) -> &str {
+        "OkHttp"
+    }
+
+    fn title(&self) -> &str {
+        "OkHttp connection pools"
+    }
+
+    fn marker_classes(&self) -> &[&'static str] {
+        &POOLS
+    }
+
+    fn detect(&self, context: &HeapContext) -> Result<Report> {
+        let heap = context.heap;
+        let contents = context.contents()?;
+
+        let mut by_pool: HashMap<Id, Connections> = HashMap::new();
+        for connection in instances_extending(heap, &[CONNECTION]) {
+            let Some(pool) = object_field(heap, contents, connection.id, "connectionPool")? else {
+                continue;
+            };
+            let calls = match object_field(heap, contents, connection.id, "calls")? {
+                Some(calls) => Some(calls),
+                None => object_field(heap, contents, connection.id, "allocations")?,
+            };
+            let busy = match calls {
+                Some(calls) => contents.collection_size(heap, calls)?.unwrap_or(0) > 0,
+                None => false,
+            };
+            let connections = by_pool.entry(pool).or_default();
+            connections.open += 1;
+            if !busy {
+                connections.idle += 1;
+            }
+        }
+
+        // the 4.x ConnectionPool wrappers have no limits of their own and are left out
+        let mut pools = Vec::new();
+        for pool in instances_extending(heap, &POOLS) {
+            let Some(max_idle) = int_field(heap, contents, pool.id, "maxIdleConnections")? else {
+                continue;
+            };
+            let connections = by_pool.remove(&pool.id).unwrap_or_default();
+            pools.push((describe(&pool), connections, max_idle.max(0) as u64));
+        }
+        pools.sort_by(|a, b| b.1.open.cmp(&a.1.open).then_with(|| a.0.cmp(&b.0)));
+
+        let mut table = Table::new(vec![
+            Column::flexible("Pool"),
+            Column::right("Connections"),
+            Column::right("Idle"),
+            Column::right("Max idle"),
+        ]);
+        for (name, connections, max_idle) in pools.iter().take(context.config.output.rows) {
+            table.add_row(vec![
+                Cell::Text(name.clone()),
+                Cell::Count(connections.open),
+                Cell::Count(connections.idle),
+                Cell::Count(*max_idle),
+            ]);
+        }
+
+        let report = Report::new(self.title(), table);
+        if pools.len() > 1 {
+            Ok(report.note(format!(
+                "{} connection pools, clients not derived from a shared one with newBuilder() \
+                 each get their own",
+                pools.len()
+            )))
+        } else {
+            Ok(report)
+        }
+    }
+}
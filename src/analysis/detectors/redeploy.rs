@@ -0,0 +1,121 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashSet},
+};
+
+use anyhow::Result;
+
+use crate::{
+    analysis::{HeapContext, Report, detectors::Detector},
+    analyzer::java_name,
+    output::table::{Cell, Column, Table},
+    parser::{Id, sub_record::FieldValue},
+};
+
+const HINT: &str = "Classes loaded by several class loaders are usually left over from \
+    redeployments whose old loader is still reachable, each copy keeping its statics. Stop \
+    threads, deregister jdbc drivers and clear ThreadLocals when undeploying";
+
+// static collections of classes loaded more than once, summed over the copies
+pub struct RedeployedStatics;
+
+#[derive(Default)]
+struct StaticCollection {
+    copies: u64,
+    entries: u64,
+    retained_size: u64,
+}
+
+impl Detector for RedeployedStatics {
+    fn name(&self) -> &str {
+        "redeployed-statics"
+    }
+
+    fn library(&self) -> &str {
+        "application"
+    }
+
+    fn title(&self) -> &str {
+        "Static collections of redeployed classes"
+    }
+
+    fn marker_classes(&self) -> &[&'static str] {
+        &[]
+    }
+
+    fn needs_dominators(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, context: &HeapContext) -> Result<Report> {
+        let heap = context.heap;
+        let contents = context.contents()?;
+        let dominator_tree = context.dominator_tree();
+
+        // class copies by name, told apart by their loader
+        let mut copies: BTreeMap<&str, Vec<Id>> = BTreeMap::new();
+        for class in heap.classes.values().filter(|c| !c.name.starts_with('[')) {
+            copies.entry(&*class.name).or_default().push(class.id);
+        }
+
+        let mut collections: BTreeMap<(String, String), StaticCollection> = BTreeMap::new();
+        for (name, class_ids) in &copies {
+            let loaders: HashSet<Option<Id>> = class_ids
+                .iter()
+                .map(|id| contents.class_loader(*id))
+                .collect();
+            if loaders.len() < 2 {
+                continue;
+            }
+            for class_id in class_ids {
+                for (field, value) in contents.static_fields(heap, *class_id)?.unwrap_or_default() {
+                    let FieldValue::NormalObject { object_id } = value else {
+                        continue;
+                    };
+                    if object_id.0 == 0 {
+                        continue;
+                    }
+                    let Some(entries) = contents.collection_size(heap, object_id)? else {
+                        continue;
+                    };
+                    let collection = collections
+                        .entry((java_name(name), field.to_string()))
+                        .or_default();
+                    collection.copies += 1;
+                    collection.entries += entries;
+                    collection.retained_size +=
+                        dominator_tree.retained_size(object_id).unwrap_or(0);
+                }
+            }
+        }
+
+        // collections of a single copy were left alone by the redeployments
+        let mut collections: Vec<((String, String), StaticCollection)> = collections
+            .into_iter()
+            .filter(|(_, c)| c.copies > 1 && c.entries >= context.config.detectors.many_entries)
+            .collect();
+        collections.sort_by_key(|(_, c)| Reverse(c.retained_size));
+
+        let mut table = Table::new(vec![
+            Column::flexible("Field"),
+            Column::right("Copies"),
+            Column::right("Entries"),
+            Column::right("Retained"),
+        ]);
+        for ((class, field), collection) in collections.iter().take(context.config.output.rows) {
+            table.add_row(vec![
+                Cell::Text(format!("{}.{}", class, field)),
+                Cell::Count(collection.copies),
+                Cell::Count(collection.entries),
+                Cell::Bytes(collection.retained_size),
+            ]);
+        }
+
+        let report = Report::new(self.title(), table);
+        if collections.is_empty() {
+            Ok(report.note("No growing static collections in classes loaded more than once"))
+        } else {
+            Ok(report.note(HINT))
+        }
+    }
+}
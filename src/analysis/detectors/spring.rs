@@ -0,0 +1,125 @@
+use anyhow::Result;
+
+use crate::{
+    analysis::{
+        HeapContext, Report,
+        detectors::{Detector, collection_field, describe, instances_extending, object_field},
+    },
+    output::table::{Cell, Column, Table},
+};
+
+const BEAN_FACTORY: &str = "org/springframework/beans/factory/support/DefaultSingletonBeanRegistry";
+
+const DEPENDENCIES: &str = "Prototype or scoped beans looked up with getBean() register \
+    dependencies that are never removed, inject an ObjectProvider instead";
+const RUNTIME_SINGLETONS: &str =
+    "Singletons added at runtime with registerSingleton() stay until destroySingleton()";
+const RUNTIME_DEFINITIONS: &str =
+    "Bean definitions registered at runtime, e.g. one per request, are never removed";
+const TYPE_LOOKUPS: &str = "getBeanNamesForType() or getBeansOfType() called with generated \
+    types caches an entry per type";
+
+// bean factory maps and sets that should stay around the number of bean definitions, with what
+// usually makes them grow
+const CACHES: [(&str, &str); 9] = [
+    ("dependentBeanMap", DEPENDENCIES),
+    ("dependenciesForBeanMap", DEPENDENCIES),
+    ("singletonObjects", RUNTIME_SINGLETONS),
+    ("registeredSingletons", RUNTIME_SINGLETONS),
+    ("disposableBeans", RUNTIME_SINGLETONS),
+    ("mergedBeanDefinitions", RUNTIME_DEFINITIONS),
+    ("alreadyCreated", RUNTIME_DEFINITIONS),
+    ("allBeanNamesByType", TYPE_LOOKUPS),
+    ("singletonBeanNamesByType", TYPE_LOOKUPS),
+];
+
+// bean factory caches holding more entries than there are bean definitions
+pub struct BeanFactoryCaches;
+
+struct GrowingCache {
+    factory: String,
+    field: &'static str,
+    entries: u64,
+    retained_size: u64,
+    hint: &'static str,
+}
+
+impl Detector for BeanFactoryCaches {
+    fn name(&self) -> &str {
+        "spring-bean-caches"
+    }
+
+    fn library(&self) -> &str {
+        "Spring"
+    }
+
+    fn title(&self) -> &str {
+        "Growing Spring bean factory caches"
+    }
+
+    fn marker_classes(&self) -> &[&'static str] {
+        &[BEAN_FACTORY]
+    }
+
+    fn needs_dominators(&self) -> bool {
+        true
+    }
+
+    fn detect(&self, context: &HeapContext) -> Result<Report> {
+        let heap = context.heap;
+        let contents = context.contents()?;
+        let dominator_tree = context.dominator_tree();
+
+        let mut growing = Vec::new();
+        for factory in instances_extending(heap, &[BEAN_FACTORY]) {
+            let definitions = collection_field(heap, contents, factory.id, "beanDefinitionMap")?;
+            for (field, hint) in CACHES {
+                let Some(cache) = object_field(heap, contents, factory.id, field)? else {
+                    continue;
+                };
+                let entries = contents.collection_size(heap, cache)?.unwrap_or(0);
+                if entries > definitions && entries >= context.config.detectors.many_entries {
+                    growing.push(GrowingCache {
+                        factory: describe(&factory),
+                        field,
+                        entries,
+                        retained_size: dominator_tree.retained_size(cache).unwrap_or(0),
+                        hint,
+                    });
+                }
+            }
+        }
+        growing.sort_by(|a, b| {
+            b.retained_size
+                .cmp(&a.retained_size)
+                .then_with(|| b.entries.cmp(&a.entries))
+        });
+
+        let mut table = Table::new(vec![
+            Column::flexible("Factory"),
+            Column::left("Cache"),
+            Column::right("Entries"),
+            Column::right("Retained"),
+        ]);
+        for cache in growing.iter().take(context.config.output.rows) {
+            table.add_row(vec![
+                Cell::Text(cache.factory.clone()),
+                Cell::Text(cache.field.to_string()),
+                Cell::Count(cache.entries),
+                Cell::Bytes(cache.retained_size),
+            ]);
+        }
+
+        let mut report = Report::new(self.title(), table);
+        if growing.is_empty() {
+            return Ok(report.note("No bean factory cache outgrew the bean definitions"));
+        }
+        // one note per hint, in the order of the largest cache it applies to
+        for cache in &growing {
+            if !report.notes.iter().any(|n| n == cache.hint) {
+                report = report.note(cache.hint);
+            }
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::OnceLock,
+};
+
+use anyhow::{Result, bail};
+
+use crate::{
+    analyzer::{AnalyzedHeap, contents::Contents, dominator::DominatorTree},
+    config::Config,
+    output::table::{Cell, Column, Table},
+    parser::Header,
+};
+
+pub mod builtin;
+pub mod detectors;
+#[cfg(all(feature = "plugins", not(target_arch = "wasm32")))]
+pub mod plugin;
+#[cfg(feature = "script")]
+pub mod script;
+
+// findings of one analysis, printed by `report` as a titled table
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Report {
+    pub title: String,
+    pub table: Table,
+    // printed below the table, e.g. that nothing was found
+    pub notes: Vec<String>,
+}
+
+impl Report {
+    pub fn new(title: &str, table: Table) -> Self {
+        Self {
+            title: title.to_string(),
+            table,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+}
+
+// what an analysis gets to look at. reports of its dependencies have already been produced
+pub struct HeapContext<'a> {
+    // the local dump the heap was analyzed from
+    pub dump: &'a Path,
+    pub header: &'a Header,
+    pub heap: &'a AnalyzedHeap,
+    pub config: &'a Config,
+    contents: &'a OnceLock<Contents>,
+    reports: &'a HashMap<&'a str, Report>,
+}
+
+impl HeapContext<'_> {
+    pub fn dominator_tree(&self) -> &DominatorTree {
+        self.heap.dominator_tree()
+    }
+
+    // field values and array elements, mapped from the dump by the first analysis asking
+    pub fn contents(&self) -> Result<&Contents> {
+        if let Some(contents) = self.contents.get() {
+            return Ok(contents);
+        }
+        let contents = Contents::open(self.dump, self.heap)?;
+        Ok(self.contents.get_or_init(|| contents))
+    }
+
+    // the report of an analysis listed in the dependencies
+    pub fn report(&self, name: &str) -> Option<&Report> {
+        self.reports.get(name)
+    }
+}
+
+// an analysis run by `report`. organization specific analyses implement this in their own
+// crate, or behind a feature of this one, and are added with Registry::register
+pub trait Analysis: Send + Sync {
+    // unique, kebab-case, used by `report --only`
+    fn name(&self) -> &str;
+
+    // analyses whose reports this one reads through HeapContext::report
+    fn dependencies(&self) -> &[&'static str] {
+        &[]
+    }
+
+    // analyses using the dominator tree say so, `report` computes it up front then
+    fn needs_dominators(&self) -> bool {
+        false
+    }
+
+    fn run(&self, context: &HeapContext) -> Result<Report>;
+}
+
+#[derive(Default)]
+pub struct Registry {
+    analyses: Vec<Box<dyn Analysis>>,
+}
+
+impl Registry {
+    // the analyses shipped with the crate, library detectors last
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        for analysis in builtin::analyses().into_iter().chain(detectors::analyses()) {
+            registry.register(analysis);
+        }
+        registry
+    }
+
+    // the builtin analyses without the detectors the config disables
+    pub fn configured(config: &Config) -> Result<Self> {
+        let detectors: Vec<String> = detectors::analyses()
+            .iter()
+            .map(|d| d.name().to_string())
+            .collect();
+        let disabled = &config.detectors.disabled;
+        if let Some(name) = disabled.iter().find(|name| !detectors.contains(name)) {
+            bail!(
+                "unknown detector {} in the config, expected one of {}",
+                name,
+                detectors.join(", ")
+            );
+        }
+
+        let mut registry = Self::builtin();
+        registry
+            .analyses
+            .retain(|a| !disabled.iter().any(|name| name == a.name()));
+        Ok(registry)
+    }
+
+    // a later analysis with the same name replaces the earlier one
+    pub fn register(&mut self, analysis: Box<dyn Analysis>) {
+        self.analyses.retain(|a| a.name() != analysis.name());
+        self.analyses.push(analysis);
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.analyses.iter().map(|a| a.name()).collect()
+    }
+
+    // the selected analyses, all when empty, plus everything they depend on. dependencies come
+    // before the analyses needing them, otherwise registration order is kept
+    pub fn plan(&self, only: &[String]) -> Result<Vec<&dyn Analysis>> {
+        let by_name: HashMap<&str, &dyn Analysis> = self
+            .analyses
+            .iter()
+            .map(|a| (a.name(), a.as_ref()))
+            .collect();
+        for name in only {
+            if !by_name.contains_key(name.as_str()) {
+                bail!(
+                    "unknown analysis {}, expected one of {}",
+                    name,
+                    self.names().join(", ")
+                );
+            }
+        }
+
+        let mut planned = Vec::new();
+        let mut done = HashSet::new();
+        for analysis in &self.analyses {
+            if only.is_empty() || only.iter().any(|name| name == analysis.name()) {
+                visit(
+                    analysis.as_ref(),
+                    &by_name,
+                    &mut done,
+                    &mut Vec::new(),
+                    &mut planned,
+                )?;
+            }
+        }
+        Ok(planned)
+    }
+
+    // reports in plan order, including those of dependencies that weren't selected themselves
+    pub fn run(
+        &self,
+        only: &[String],
+        dump: &Path,
+        header: &Header,
+        heap: &AnalyzedHeap,
+        config: &Config,
+    ) -> Result<Vec<Report>> {
+        let plan = self.plan(only)?;
+        let contents = OnceLock::new();
+        let mut reports = HashMap::new();
+        for analysis in &plan {
+            let context = HeapContext {
+                dump,
+                header,
+                heap,
+                config,
+                contents: &contents,
+                reports: &reports,
+            };
+            let report = analysis.run(&context)?;
+            reports.insert(analysis.name(), report);
+        }
+        Ok(plan
+            .iter()
+            .filter_map(|a| reports.remove(a.name()))
+            .collect())
+    }
+}
+
+// depth first, `path` holds the analyses currently being visited to catch cycles
+fn visit<'a>(
+    analysis: &'a dyn Analysis,
+    by_name: &HashMap<&str, &'a dyn Analysis>,
+    done: &mut HashSet<&'a str>,
+    path: &mut Vec<&'a str>,
+    planned: &mut Vec<&'a dyn Analysis>,
+) -> Result<()> {
+    if done.contains(analysis.name()) {
+        return Ok(());
+    }
+    if path.contains(&analysis.name()) {
+        bail!(
+            "dependency cycle between analyses: {} -> {}",
+            path.join(" -> "),
+            analysis.name()
+        );
+    }
+
+    path.push(analysis.name());
+    for dependency in analysis.dependencies() {
+        let Some(dependency) = by_name.get(dependency) else {
+            bail!(
+                "analysis {} depends on unknown analysis {}",
+                analysis.name(),
+                dependency
+            );
+        };
+        visit(*dependency, by_name, done, path, planned)?;
+    }
+    path.pop();
+
+    done.insert(analysis.name());
+    planned.push(analysis);
+    Ok(())
+}
+
+// columns of scripts and plugins are declared by name. "Retained:bytes" and "Objects:count" are
+// right aligned and formatted as sizes and counts, anything else is text
+pub fn column(spec: &str) -> Column {
+    match spec.rsplit_once(':') {
+        Some((header, "bytes" | "count")) => Column::right(header),
+        _ => Column::left(spec),
+    }
+}
+
+// a value of a column declared with column, text where no number fits
+pub fn typed_cell(spec: &str, number: Option<u64>, text: String) -> Cell {
+    match (spec.rsplit_once(':'), number) {
+        (Some((_, "bytes")), Some(n)) => Cell::Bytes(n),
+        (Some((_, "count")), Some(n)) => Cell::Count(n),
+        _ => Cell::Text(text),
+    }
+}
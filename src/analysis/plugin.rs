@@ -0,0 +1,339 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, Result, anyhow, bail};
+use wasmtime::{
+    Caller, Config, Engine, Extern, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+use crate::{
+    analysis::{Analysis, HeapContext, Report, column, typed_cell},
+    analyzer::HistogramEntry,
+    output::table::Table,
+    parser::Id,
+};
+
+// instructions a plugin may run, roughly a minute of cpu time
+const FUEL: u64 = 50_000_000_000;
+// linear memory a plugin may grow to
+const MAX_MEMORY: usize = 1 << 30;
+
+// an analysis compiled to a wasm module. plugins run without wasi, all they see of the heap are
+// the read only functions imported from the "hda" module:
+//
+//   class_count() -> i32                    classes with instances, largest shallow size first
+//   class_name(class, ptr, len) -> i32      writes up to len bytes, returns the full length
+//   class_instances(class) -> i64
+//   class_shallow_size(class) -> i64
+//   class_retained_size(class) -> i64
+//   class_of(id) -> i32                     -1 for class objects and unknown ids
+//   shallow_size(id) -> i64                 -1 for class objects and unknown ids
+//   retained_size(id) -> i64                -1 for unreachable objects
+//   immediate_dominator(id) -> i64          0 for objects only dominated by the gc roots
+//   reference_count(id) -> i32
+//   reference(id, i) -> i64
+//   referrer_count(id) -> i32
+//   referrer(id, i) -> i64
+//   instance_count(class) -> i64
+//   instance(class, i) -> i64
+//   columns(ptr, len)                       tab separated column names, see analysis::column
+//   row(ptr, len)                           tab separated values
+//
+// and export their memory and `hda_run() -> i32`, returning 0 on success. object ids are i64
+pub struct Plugin {
+    name: String,
+    title: String,
+    engine: Engine,
+    module: Module,
+}
+
+impl Plugin {
+    // named after the file without its extension
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module = Module::from_file(&engine, path)
+            .with_context(|| format!("failed to load plugin {}", path.display()))?;
+        let name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .with_context(|| format!("invalid plugin name {}", path.display()))?
+            .to_string();
+        Ok(Self {
+            name,
+            title: format!("Plugin {}", path.display()),
+            engine,
+            module,
+        })
+    }
+}
+
+struct State<'a> {
+    context: &'a HeapContext<'a>,
+    classes: Vec<HistogramEntry>,
+    retained_by_class: Option<HashMap<Id, u64>>,
+    // instances of each class, by class index, collected on first use
+    instances: HashMap<usize, Vec<Id>>,
+    columns: Vec<String>,
+    rows: Vec<Vec<String>>,
+    limits: StoreLimits,
+}
+
+impl State<'_> {
+    fn class(&self, class: i32) -> Result<&HistogramEntry> {
+        usize::try_from(class)
+            .ok()
+            .and_then(|i| self.classes.get(i))
+            .with_context(|| format!("invalid class index {}", class))
+    }
+
+    fn retained_by_class(&mut self, class: i32) -> Result<u64> {
+        let class_id = self.class(class)?.class.id;
+        let heap = self.context.heap;
+        let retained_by_class = self
+            .retained_by_class
+            .get_or_insert_with(|| heap.dominator_tree().retained_by_class(heap));
+        Ok(retained_by_class.get(&class_id).copied().unwrap_or(0))
+    }
+
+    fn instances(&mut self, class: i32) -> Result<&[Id]> {
+        let class_id = self.class(class)?.class.id;
+        let heap = self.context.heap;
+        Ok(self
+            .instances
+            .entry(class as usize)
+            .or_insert_with(|| heap.instances_of(class_id).map(|i| i.id).collect()))
+    }
+}
+
+impl Analysis for Plugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&self, context: &HeapContext) -> Result<Report> {
+        let state = State {
+            context,
+            classes: context.heap.histogram(&Default::default()),
+            retained_by_class: None,
+            instances: HashMap::new(),
+            columns: Vec::new(),
+            rows: Vec::new(),
+            limits: StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(FUEL)?;
+
+        let linker = linker(&self.engine)?;
+        let instance = linker
+            .instantiate(&mut store, &self.module)
+            .with_context(|| format!("failed to instantiate plugin {}", self.name))?;
+        let run = instance
+            .get_typed_func::<(), i32>(&mut store, "hda_run")
+            .with_context(|| format!("plugin {} doesn't export hda_run", self.name))?;
+        let status = run
+            .call(&mut store, ())
+            .with_context(|| format!("plugin {} failed", self.name))?;
+        if status != 0 {
+            bail!("plugin {} failed with status {}", self.name, status);
+        }
+
+        let state = store.into_data();
+        let mut table = Table::new(state.columns.iter().map(|c| column(c)).collect());
+        for (i, values) in state.rows.into_iter().enumerate() {
+            if values.len() != state.columns.len() {
+                bail!(
+                    "plugin {}: row {} has {} values for {} columns",
+                    self.name,
+                    i + 1,
+                    values.len(),
+                    state.columns.len()
+                );
+            }
+            table.add_row(
+                state
+                    .columns
+                    .iter()
+                    .zip(values)
+                    .map(|(column, value)| typed_cell(column, value.parse().ok(), value))
+                    .collect(),
+            );
+        }
+        Ok(Report::new(&self.title, table))
+    }
+}
+
+fn linker<'a>(engine: &Engine) -> Result<Linker<State<'a>>> {
+    let mut linker = Linker::new(engine);
+    linker.func_wrap("hda", "class_count", |caller: Caller<'_, State<'a>>| {
+        caller.data().classes.len() as i32
+    })?;
+    linker.func_wrap(
+        "hda",
+        "class_name",
+        |mut caller: Caller<'_, State<'a>>, class: i32, ptr: i32, len: i32| -> Result<i32> {
+            let name = caller.data().class(class)?.class.java_name();
+            let written = name.len().min(len.max(0) as usize);
+            memory(&mut caller)?.write(&mut caller, ptr as usize, &name.as_bytes()[..written])?;
+            Ok(name.len() as i32)
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "class_instances",
+        |caller: Caller<'_, State<'a>>, class: i32| -> Result<i64> {
+            Ok(caller.data().class(class)?.instance_count as i64)
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "class_shallow_size",
+        |caller: Caller<'_, State<'a>>, class: i32| -> Result<i64> {
+            Ok(caller.data().class(class)?.shallow_size as i64)
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "class_retained_size",
+        |mut caller: Caller<'_, State<'a>>, class: i32| -> Result<i64> {
+            Ok(caller.data_mut().retained_by_class(class)? as i64)
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "class_of",
+        |caller: Caller<'_, State<'a>>, id: i64| {
+            let state = caller.data();
+            let Some(instance) = state.context.heap.instance(Id(id as u64)) else {
+                return -1;
+            };
+            state
+                .classes
+                .iter()
+                .position(|e| e.class.id == instance.class.id)
+                .map_or(-1, |i| i as i32)
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "shallow_size",
+        |caller: Caller<'_, State<'a>>, id: i64| {
+            let heap = caller.data().context.heap;
+            heap.instance(Id(id as u64))
+                .map_or(-1, |i| i.shallow_size as i64)
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "retained_size",
+        |caller: Caller<'_, State<'a>>, id: i64| {
+            let heap = caller.data().context.heap;
+            heap.dominator_tree()
+                .retained_size(Id(id as u64))
+                .map_or(-1, |size| size as i64)
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "immediate_dominator",
+        |caller: Caller<'_, State<'a>>, id: i64| {
+            let heap = caller.data().context.heap;
+            heap.dominator_tree()
+                .immediate_dominator(Id(id as u64))
+                .map_or(0, |id| id.0 as i64)
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "reference_count",
+        |caller: Caller<'_, State<'a>>, id: i64| {
+            let heap = caller.data().context.heap;
+            heap.references_of(Id(id as u64))
+                .map_or(0, |r| r.len() as i32)
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "reference",
+        |caller: Caller<'_, State<'a>>, id: i64, i: i32| -> Result<i64> {
+            let heap = caller.data().context.heap;
+            heap.references_of(Id(id as u64))
+                .and_then(|r| r.get(usize::try_from(i).ok()?))
+                .map(|id| id.0 as i64)
+                .ok_or_else(|| anyhow!("reference {} of 0x{:x} out of range", i, id))
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "referrer_count",
+        |caller: Caller<'_, State<'a>>, id: i64| {
+            let heap = caller.data().context.heap;
+            heap.handle(Id(id as u64))
+                .map_or(0, |h| heap.referrers(h).count() as i32)
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "referrer",
+        |caller: Caller<'_, State<'a>>, id: i64, i: i32| -> Result<i64> {
+            let heap = caller.data().context.heap;
+            heap.handle(Id(id as u64))
+                .and_then(|h| heap.referrers(h).nth(usize::try_from(i).ok()?))
+                .map(|h| heap.handles.id(h).0 as i64)
+                .ok_or_else(|| anyhow!("referrer {} of 0x{:x} out of range", i, id))
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "instance_count",
+        |mut caller: Caller<'_, State<'a>>, class: i32| -> Result<i64> {
+            Ok(caller.data_mut().instances(class)?.len() as i64)
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "instance",
+        |mut caller: Caller<'_, State<'a>>, class: i32, i: i64| -> Result<i64> {
+            let instances = caller.data_mut().instances(class)?;
+            usize::try_from(i)
+                .ok()
+                .and_then(|i| instances.get(i))
+                .map(|id| id.0 as i64)
+                .ok_or_else(|| anyhow!("instance {} of class {} out of range", i, class))
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "columns",
+        |mut caller: Caller<'_, State<'a>>, ptr: i32, len: i32| -> Result<()> {
+            let columns = read_string(&mut caller, ptr, len)?;
+            caller.data_mut().columns = columns.split('\t').map(str::to_string).collect();
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "hda",
+        "row",
+        |mut caller: Caller<'_, State<'a>>, ptr: i32, len: i32| -> Result<()> {
+            let row = read_string(&mut caller, ptr, len)?;
+            let values = row.split('\t').map(str::to_string).collect();
+            caller.data_mut().rows.push(values);
+            Ok(())
+        },
+    )?;
+    Ok(linker)
+}
+
+fn memory(caller: &mut Caller<'_, State<'_>>) -> Result<Memory> {
+    match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => Ok(memory),
+        _ => bail!("plugin doesn't export its memory"),
+    }
+}
+
+fn read_string(caller: &mut Caller<'_, State<'_>>, ptr: i32, len: i32) -> Result<String> {
+    let mut buf = vec![0; len.max(0) as usize];
+    memory(caller)?.read(&*caller, ptr as usize, &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
@@ -0,0 +1,308 @@
+use std::{cell::RefCell, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use mlua::{Lua, Value};
+
+use crate::{
+    analysis::{Analysis, HeapContext, Report, column, typed_cell},
+    analyzer::{AnalyzedHeap, contents::Contents},
+    output::table::{Cell, Table},
+    parser::{
+        Id,
+        sub_record::{FieldValue, PrimArray},
+    },
+};
+
+// a lua script run as an analysis. objects are passed around as integer ids, the script reads
+// them through the functions of the global `heap` table and reports rows with `columns` and
+// `row`:
+//
+//   columns("Class", "Objects:count", "Retained:bytes")
+//   for _, class in ipairs(heap.classes()) do
+//     row(class.name, class.instances, class.retained_size)
+//   end
+//
+// see column for how values are formatted
+pub struct Script {
+    name: String,
+    title: String,
+    source: String,
+}
+
+impl Script {
+    // named after the file without its extension
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read script {}", path.display()))?;
+        let name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .with_context(|| format!("invalid script name {}", path.display()))?
+            .to_string();
+        Ok(Self {
+            title: format!("Script {}", path.display()),
+            name,
+            source,
+        })
+    }
+}
+
+impl Analysis for Script {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&self, context: &HeapContext) -> Result<Report> {
+        let lua = Lua::new();
+        let columns: RefCell<Vec<String>> = RefCell::new(Vec::new());
+        let rows: RefCell<Vec<Vec<Value>>> = RefCell::new(Vec::new());
+
+        let result = lua.scope(|scope| {
+            let heap = lua.create_table()?;
+            heap.set(
+                "classes",
+                scope.create_function(|lua, ()| classes(lua, context))?,
+            )?;
+            heap.set(
+                "instances",
+                scope.create_function(|lua, class: String| {
+                    lua.create_sequence_from(instances(context.heap, &class))
+                })?,
+            )?;
+            heap.set(
+                "class",
+                scope.create_function(|_, id: i64| Ok(context.heap.class_name_of(to_id(id))))?,
+            )?;
+            heap.set(
+                "shallow_size",
+                scope.create_function(|_, id: i64| {
+                    Ok(context.heap.instance(to_id(id)).map(|i| i.shallow_size))
+                })?,
+            )?;
+            heap.set(
+                "retained_size",
+                scope.create_function(|_, id: i64| {
+                    Ok(context.dominator_tree().retained_size(to_id(id)))
+                })?,
+            )?;
+            heap.set(
+                "references",
+                scope.create_function(|lua, id: i64| {
+                    let references = context.heap.references_of(to_id(id)).unwrap_or_default();
+                    lua.create_sequence_from(references.iter().map(|id| from_id(*id)))
+                })?,
+            )?;
+            heap.set(
+                "referrers",
+                scope.create_function(|lua, id: i64| {
+                    let heap = context.heap;
+                    let Some(handle) = heap.handle(to_id(id)) else {
+                        return lua.create_table();
+                    };
+                    lua.create_sequence_from(
+                        heap.referrers(handle).map(|h| from_id(heap.handles.id(h))),
+                    )
+                })?,
+            )?;
+            heap.set(
+                "path",
+                scope.create_function(|lua, id: i64| {
+                    let heap = context.heap;
+                    let path = heap
+                        .handle(to_id(id))
+                        .and_then(|handle| heap.path_to_root(handle));
+                    path.map(|path| {
+                        lua.create_sequence_from(
+                            path.into_iter().map(|h| from_id(heap.handles.id(h))),
+                        )
+                    })
+                    .transpose()
+                })?,
+            )?;
+            heap.set(
+                "fields",
+                scope.create_function(|lua, id: i64| {
+                    let contents = contents(context)?;
+                    let Some(fields) = contents
+                        .fields(context.heap, to_id(id))
+                        .map_err(mlua::Error::external)?
+                    else {
+                        return Ok(None);
+                    };
+                    let table = lua.create_table()?;
+                    // subclass fields are listed first and shadow those of superclasses
+                    for (name, value) in fields.into_iter().rev() {
+                        table.set(&*name, field_value(lua, value)?)?;
+                    }
+                    Ok(Some(table))
+                })?,
+            )?;
+            heap.set(
+                "field",
+                scope.create_function(|lua, (id, name): (i64, String)| {
+                    let value = contents(context)?
+                        .field(context.heap, to_id(id), &name)
+                        .map_err(mlua::Error::external)?;
+                    value.map_or(Ok(Value::Nil), |v| field_value(lua, v))
+                })?,
+            )?;
+            heap.set(
+                "string",
+                scope.create_function(|_, id: i64| {
+                    contents(context)?
+                        .string_value(context.heap, to_id(id))
+                        .map_err(mlua::Error::external)
+                })?,
+            )?;
+            heap.set(
+                "array",
+                scope.create_function(|lua, id: i64| array(lua, context, to_id(id)))?,
+            )?;
+            lua.globals().set("heap", heap)?;
+
+            lua.globals().set(
+                "hex",
+                scope.create_function(|_, id: i64| Ok(to_id(id).to_string()))?,
+            )?;
+            lua.globals().set(
+                "columns",
+                scope.create_function(|_, names: mlua::Variadic<String>| {
+                    *columns.borrow_mut() = names.into_iter().collect();
+                    Ok(())
+                })?,
+            )?;
+            lua.globals().set(
+                "row",
+                scope.create_function(|_, values: mlua::Variadic<Value>| {
+                    rows.borrow_mut().push(values.into_iter().collect());
+                    Ok(())
+                })?,
+            )?;
+
+            lua.load(&self.source).set_name(&self.name).exec()
+        });
+        result.map_err(|err| anyhow!("script {} failed: {}", self.name, err))?;
+
+        let columns = columns.into_inner();
+        let mut table = Table::new(columns.iter().map(|c| column(c)).collect());
+        for (i, values) in rows.into_inner().into_iter().enumerate() {
+            if values.len() != columns.len() {
+                return Err(anyhow!(
+                    "script {}: row {} has {} values for {} columns",
+                    self.name,
+                    i + 1,
+                    values.len(),
+                    columns.len()
+                ));
+            }
+            table.add_row(
+                columns
+                    .iter()
+                    .zip(values)
+                    .map(|(column, value)| cell(column, value))
+                    .collect(),
+            );
+        }
+        Ok(Report::new(&self.title, table))
+    }
+}
+
+fn contents<'a>(context: &'a HeapContext) -> mlua::Result<&'a Contents> {
+    context.contents().map_err(mlua::Error::external)
+}
+
+// ids are positive in practice, lua integers are signed 64 bit
+fn to_id(id: i64) -> Id {
+    Id(id as u64)
+}
+
+fn from_id(id: Id) -> i64 {
+    id.0 as i64
+}
+
+// classes with instances, with their totals
+fn classes(lua: &Lua, context: &HeapContext) -> mlua::Result<mlua::Table> {
+    let heap = context.heap;
+    let retained_by_class = context.dominator_tree().retained_by_class(heap);
+    let classes = lua.create_table()?;
+    for entry in heap.histogram(&Default::default()) {
+        let class = lua.create_table()?;
+        class.set("id", from_id(entry.class.id))?;
+        class.set("name", entry.class.java_name())?;
+        class.set("instances", entry.instance_count)?;
+        class.set("shallow_size", entry.shallow_size)?;
+        class.set(
+            "retained_size",
+            retained_by_class.get(&entry.class.id).copied().unwrap_or(0),
+        )?;
+        classes.push(class)?;
+    }
+    Ok(classes)
+}
+
+// ids of the instances of the classes with this name, without subclasses
+fn instances(heap: &AnalyzedHeap, class: &str) -> Vec<i64> {
+    heap.find_classes_by_name(class)
+        .into_iter()
+        .flat_map(|c| heap.instances_of(c.id))
+        .map(|i| from_id(i.id))
+        .collect()
+}
+
+// object array elements as ids with 0 for null, primitive arrays as numbers
+fn array(lua: &Lua, context: &HeapContext, id: Id) -> mlua::Result<Option<mlua::Table>> {
+    let contents = contents(context)?;
+    if let Some(elements) = contents.object_array(context.heap, id) {
+        return lua
+            .create_sequence_from(elements.into_iter().map(from_id))
+            .map(Some);
+    }
+    let Some(elements) = contents
+        .prim_array(context.heap, id)
+        .map_err(mlua::Error::external)?
+    else {
+        return Ok(None);
+    };
+    let values: Vec<Value> = match elements {
+        PrimArray::Bool(v) => v.into_iter().map(Value::Boolean).collect(),
+        PrimArray::Char(v) => v.into_iter().map(|e| Value::Integer(e as i64)).collect(),
+        PrimArray::Float(v) => v.into_iter().map(|e| Value::Number(e as f64)).collect(),
+        PrimArray::Double(v) => v.into_iter().map(Value::Number).collect(),
+        PrimArray::Byte(v) => v.into_iter().map(|e| Value::Integer(e as i64)).collect(),
+        PrimArray::Short(v) => v.into_iter().map(|e| Value::Integer(e as i64)).collect(),
+        PrimArray::Int(v) => v.into_iter().map(|e| Value::Integer(e as i64)).collect(),
+        PrimArray::Long(v) => v.into_iter().map(Value::Integer).collect(),
+    };
+    lua.create_sequence_from(values).map(Some)
+}
+
+// references as ids, nil for null
+fn field_value(lua: &Lua, value: FieldValue) -> mlua::Result<Value> {
+    Ok(match value {
+        FieldValue::NormalObject { object_id } if object_id.0 == 0 => Value::Nil,
+        FieldValue::NormalObject { object_id } => Value::Integer(from_id(object_id)),
+        FieldValue::Boolean(b) => Value::Boolean(b),
+        FieldValue::Char(c) => Value::String(lua.create_string(String::from_utf16_lossy(&[c]))?),
+        FieldValue::Float(f) => Value::Number(f as f64),
+        FieldValue::Double(d) => Value::Number(d),
+        FieldValue::Byte(b) => Value::Integer(b as i64),
+        FieldValue::Short(s) => Value::Integer(s as i64),
+        FieldValue::Int(i) => Value::Integer(i as i64),
+        FieldValue::Long(l) => Value::Integer(l),
+    })
+}
+
+// numbers go into ":bytes" and ":count" columns as they are
+fn cell(column: &str, value: Value) -> Cell {
+    let number = match value {
+        Value::Integer(i) => Some(i.max(0) as u64),
+        Value::Number(n) => Some(n.max(0.0) as u64),
+        _ => None,
+    };
+    let text = match value {
+        Value::Nil => String::new(),
+        Value::String(s) => s.to_string_lossy(),
+        value => value.to_string().unwrap_or_default(),
+    };
+    typed_cell(column, number, text)
+}
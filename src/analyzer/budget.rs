@@ -0,0 +1,431 @@
+use std::{path::Path, str::FromStr};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+
+use crate::{
+    output::human_bytes,
+    parser::{
+        borrowed::{BorrowedRecord, BorrowedSubRecord, BorrowedSubRecords, MappedDump},
+        sub_record::SubRecord,
+    },
+    trace::warn,
+};
+
+// pessimistic guesses of what a dump holds, derived from its size
+const DUMP_BYTES_PER_OBJECT: u64 = 40;
+const DUMP_BYTES_PER_EDGE: u64 = 16;
+
+// handle index, class and size columns and reference offsets of an object
+const OBJECT_BYTES: u64 = 60;
+const EDGE_BYTES: u64 = 8;
+// only the handle index stays in ram when spilling
+const SPILLED_OBJECT_BYTES: u64 = 28;
+// dfs order, spanning tree and predecessor lists of the dominator computation
+const DOMINATOR_OBJECT_BYTES: u64 = 80;
+const DOMINATOR_EDGE_BYTES: u64 = 8;
+// strings, classes and the records being analyzed
+const FIXED_BYTES: u64 = 256 << 20;
+
+// sub records parsed by scan to learn how many objects and references a segment byte holds
+const SAMPLE_BYTES: u64 = 64 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    Memory,
+    // per-object tables in memory mapped files
+    Spill,
+    // class histogram without the reference graph
+    HistogramOnly,
+}
+
+// what to do when a dump looks like it needs more memory than the os has available
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryCheck {
+    #[default]
+    Warn,
+    // fail before the analysis starts instead of getting it killed halfway
+    Refuse,
+    Off,
+}
+
+impl FromStr for MemoryCheck {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "warn" => Ok(MemoryCheck::Warn),
+            "refuse" => Ok(MemoryCheck::Refuse),
+            "off" => Ok(MemoryCheck::Off),
+            _ => Err(format!(
+                "unknown memory check {}, expected warn, refuse or off",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryEstimate {
+    pub objects: u64,
+    pub edges: u64,
+    // content of the utf8 records, names are kept in memory
+    pub string_bytes: u64,
+}
+
+impl MemoryEstimate {
+    // from the file size alone
+    pub fn of(dump: &Path) -> Result<Self> {
+        let size = std::fs::metadata(dump)
+            .with_context(|| format!("failed to read metadata of {}", dump.display()))?
+            .len();
+        Ok(Self {
+            objects: size / DUMP_BYTES_PER_OBJECT,
+            edges: size / DUMP_BYTES_PER_EDGE,
+            string_bytes: 0,
+        })
+    }
+
+    // walks the records, skipping over segments after the first SAMPLE_BYTES of sub records.
+    // the objects and references counted in those are extrapolated to all segments
+    pub fn scan(dump: &Path) -> Result<Self> {
+        let mapped = MappedDump::open(dump)?;
+        let records = mapped.records()?;
+        let id_size = records.ctx.id_size as usize;
+        let mut segment_bytes = 0u64;
+        let mut string_bytes = 0u64;
+        let mut sample = Sample::default();
+        // a damaged dump is estimated from the records before the damage, the analysis itself
+        // reports it
+        for record in records.map_while(|record| record.ok()) {
+            match record {
+                BorrowedRecord::Utf8 { content, .. } => string_bytes += content.len() as u64,
+                BorrowedRecord::HeapDumpSegment {
+                    mut sub_records, ..
+                } => {
+                    segment_bytes += sub_records.remaining() as u64;
+                    if sample.bytes < SAMPLE_BYTES {
+                        sample.add(&mut sub_records, id_size);
+                    }
+                }
+                BorrowedRecord::Other(_) => {}
+            }
+        }
+
+        if sample.bytes == 0 {
+            return Ok(Self {
+                string_bytes,
+                ..Self::of(dump)?
+            });
+        }
+        Ok(sample.extrapolate(segment_bytes, string_bytes))
+    }
+
+    pub fn bytes(&self, strategy: Strategy, dominators: bool) -> u64 {
+        let graph = match strategy {
+            Strategy::Memory => self.objects * OBJECT_BYTES + self.edges * EDGE_BYTES,
+            Strategy::Spill => self.objects * SPILLED_OBJECT_BYTES,
+            Strategy::HistogramOnly => return FIXED_BYTES,
+        };
+        let dominator_tree = if dominators {
+            self.objects * DOMINATOR_OBJECT_BYTES + self.edges * DOMINATOR_EDGE_BYTES
+        } else {
+            0
+        };
+        FIXED_BYTES + self.string_bytes + graph + dominator_tree
+    }
+
+    // warns or fails, as the check says, when the strategy is expected to need more than the
+    // available memory. nothing to compare against without it
+    pub fn preflight(
+        &self,
+        strategy: Strategy,
+        dominators: bool,
+        available: Option<u64>,
+        check: MemoryCheck,
+    ) -> Result<()> {
+        let needed = self.bytes(strategy, dominators);
+        let Some(available) = available.filter(|available| needed > *available) else {
+            return Ok(());
+        };
+        match check {
+            MemoryCheck::Warn => warn!(
+                "the analysis needs about {} of memory, only {} is available. it may be killed \
+                 before it finishes, --max-memory makes it spill to disk",
+                human_bytes(needed),
+                human_bytes(available)
+            ),
+            MemoryCheck::Refuse => bail!(
+                "the analysis needs about {} of memory, only {} is available. pass --max-memory \
+                 to spill to disk, or --memory-check warn to try anyway",
+                human_bytes(needed),
+                human_bytes(available)
+            ),
+            MemoryCheck::Off => {}
+        }
+        Ok(())
+    }
+
+    // the first strategy expected to fit, histogram only if the command can make do with it
+    pub fn choose(&self, budget: u64, dominators: bool, histogram_only: bool) -> Result<Strategy> {
+        let mut strategies = vec![Strategy::Memory, Strategy::Spill];
+        if histogram_only {
+            strategies.push(Strategy::HistogramOnly);
+        }
+        if let Some(strategy) = strategies
+            .into_iter()
+            .find(|s| self.bytes(*s, dominators) <= budget)
+        {
+            return Ok(strategy);
+        }
+
+        let needed = self.bytes(Strategy::Spill, dominators);
+        if dominators {
+            bail!(
+                "the dominator tree of this dump needs about {} of memory even when spilling, \
+                 more than the {} budget. raise --max-memory, or run summary for the class \
+                 histogram only",
+                human_bytes(needed),
+                human_bytes(budget)
+            );
+        }
+        bail!(
+            "this dump needs about {} of memory even when spilling, more than the {} budget. \
+             raise --max-memory",
+            human_bytes(needed),
+            human_bytes(budget)
+        );
+    }
+}
+
+// objects and references in the sub records parsed so far
+#[derive(Default)]
+struct Sample {
+    bytes: u64,
+    objects: u64,
+    edges: u64,
+}
+
+impl Sample {
+    // instance fields are all counted as references, like the size based guesses it's on the
+    // pessimistic side
+    fn add(&mut self, sub_records: &mut BorrowedSubRecords<'_>, id_size: usize) {
+        let start = sub_records.remaining();
+        while let Some(Ok(sub_record)) = sub_records.next() {
+            match sub_record {
+                BorrowedSubRecord::InstanceDump {
+                    raw_field_bytes, ..
+                } => {
+                    self.objects += 1;
+                    self.edges += (raw_field_bytes.len() / id_size) as u64;
+                }
+                BorrowedSubRecord::ObjArrayDump { elements, .. } => {
+                    self.objects += 1;
+                    self.edges += elements.len() as u64;
+                }
+                BorrowedSubRecord::PrimArrayDump { .. }
+                | BorrowedSubRecord::Other(SubRecord::ClassDump { .. }) => self.objects += 1,
+                BorrowedSubRecord::Other(_) => {}
+            }
+            if self.bytes + (start - sub_records.remaining()) as u64 >= SAMPLE_BYTES {
+                break;
+            }
+        }
+        self.bytes += (start - sub_records.remaining()) as u64;
+    }
+
+    // scales what the sample holds to the given bytes of sub records
+    fn extrapolate(&self, segment_bytes: u64, string_bytes: u64) -> MemoryEstimate {
+        let extrapolate = |n: u64| (n as u128 * segment_bytes as u128 / self.bytes as u128) as u64;
+        MemoryEstimate {
+            objects: extrapolate(self.objects),
+            edges: extrapolate(self.edges),
+            string_bytes,
+        }
+    }
+}
+
+// what the os can give the analysis without swapping, the lower of the free memory and what
+// is left under the cgroup limit of a container. None where neither is known
+#[cfg(target_os = "linux")]
+pub fn available_memory() -> Option<u64> {
+    let read = |path: &str| std::fs::read_to_string(path).ok();
+    available(
+        read("/proc/meminfo").as_deref(),
+        read("/sys/fs/cgroup/memory.max").as_deref(),
+        read("/sys/fs/cgroup/memory.current").as_deref(),
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn available_memory() -> Option<u64> {
+    None
+}
+
+// from the contents of /proc/meminfo and the cgroup's memory.max and memory.current
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn available(
+    meminfo: Option<&str>,
+    memory_max: Option<&str>,
+    memory_current: Option<&str>,
+) -> Option<u64> {
+    let meminfo = meminfo.and_then(mem_available);
+    // "max" when there is no limit
+    let read = |file: &str| file.trim().parse::<u64>().ok();
+    let cgroup = memory_max
+        .and_then(read)
+        .map(|max| max.saturating_sub(memory_current.and_then(read).unwrap_or(0)));
+    match (meminfo, cgroup) {
+        (Some(meminfo), Some(cgroup)) => Some(meminfo.min(cgroup)),
+        (meminfo, cgroup) => meminfo.or(cgroup),
+    }
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn mem_available(meminfo: &str) -> Option<u64> {
+    let kb = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::{Id, sub_record::FieldValue, sub_record::PrimArray},
+        testutil::HeapBuilder,
+    };
+
+    const MEMINFO: &str = "MemTotal:       16316412 kB\n\
+                           MemFree:          923948 kB\n\
+                           MemAvailable:    8388608 kB\n\
+                           Buffers:          201392 kB\n";
+
+    #[test]
+    fn available_memory_is_the_lower_of_meminfo_and_the_cgroup() {
+        assert_eq!(mem_available(MEMINFO), Some(8 << 30));
+        assert_eq!(mem_available("MemTotal: 16316412 kB\n"), None);
+
+        assert_eq!(available(Some(MEMINFO), None, None), Some(8 << 30));
+        // no limit
+        assert_eq!(
+            available(Some(MEMINFO), Some("max\n"), Some("1024\n")),
+            Some(8 << 30)
+        );
+        assert_eq!(
+            available(Some(MEMINFO), Some("4294967296\n"), Some("1073741824\n")),
+            Some(3 << 30)
+        );
+        assert_eq!(available(None, Some("4294967296\n"), None), Some(4 << 30));
+        // already over the limit
+        assert_eq!(available(None, Some("1024\n"), Some("2048\n")), Some(0));
+        assert_eq!(available(None, None, None), None);
+    }
+
+    // 256 mb fixed, 68 bytes per object and reference in memory
+    const ESTIMATE: MemoryEstimate = MemoryEstimate {
+        objects: 1 << 20,
+        edges: 1 << 20,
+        string_bytes: 0,
+    };
+
+    #[test]
+    fn preflight_compares_against_the_available_memory() {
+        assert_eq!(ESTIMATE.bytes(Strategy::Memory, false), (256 + 68) << 20);
+        let plenty = Some(1 << 30);
+        let little = Some(300 << 20);
+
+        for check in [MemoryCheck::Warn, MemoryCheck::Refuse, MemoryCheck::Off] {
+            assert!(
+                ESTIMATE
+                    .preflight(Strategy::Memory, false, plenty, check)
+                    .is_ok()
+            );
+            assert!(
+                ESTIMATE
+                    .preflight(Strategy::Memory, false, None, check)
+                    .is_ok()
+            );
+        }
+        assert!(
+            ESTIMATE
+                .preflight(Strategy::Memory, false, little, MemoryCheck::Warn)
+                .is_ok()
+        );
+        assert!(
+            ESTIMATE
+                .preflight(Strategy::Memory, false, little, MemoryCheck::Refuse)
+                .is_err()
+        );
+        assert!(
+            ESTIMATE
+                .preflight(Strategy::Memory, false, little, MemoryCheck::Off)
+                .is_ok()
+        );
+        // spilling keeps 28 bytes per object
+        assert!(
+            ESTIMATE
+                .preflight(Strategy::Spill, false, little, MemoryCheck::Refuse)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn samples_are_scaled_to_all_segments() {
+        let sample = Sample {
+            bytes: 1000,
+            objects: 30,
+            edges: 70,
+        };
+        let estimate = sample.extrapolate(1_000_000, 5);
+        assert_eq!(estimate.objects, 30_000);
+        assert_eq!(estimate.edges, 70_000);
+        assert_eq!(estimate.string_bytes, 5);
+
+        // no overflow for segments of terabytes
+        let estimate = sample.extrapolate(1 << 42, 0);
+        assert_eq!(estimate.objects, (30u128 * (1 << 42) / 1000) as u64);
+    }
+
+    #[test]
+    fn scan_counts_objects_and_references_of_small_dumps() {
+        let mut builder = HeapBuilder::new();
+        let object = builder.class("java/lang/Object", None, &[]);
+        let node = builder.class("Node", Some(object), &[("next", 2), ("value", 10)]);
+        let array_class = builder.class("[LNode;", Some(object), &[]);
+        let tail = builder.instance(
+            node,
+            &[
+                FieldValue::NormalObject { object_id: Id(0) },
+                FieldValue::Int(1),
+            ],
+        );
+        let head = builder.instance(
+            node,
+            &[
+                FieldValue::NormalObject { object_id: tail },
+                FieldValue::Int(2),
+            ],
+        );
+        builder.object_array(array_class, &[head, tail, Id(0)]);
+        builder.prim_array(PrimArray::Byte(vec![0; 64])).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("heap.hprof");
+        builder.write(&path).unwrap();
+
+        let estimate = MemoryEstimate::scan(&path).unwrap();
+        // three class dumps, two instances and two arrays
+        assert_eq!(estimate.objects, 7);
+        // 12 field bytes count as one 8 byte reference
+        assert_eq!(estimate.edges, 2 + 3);
+        // java/lang/Object, Node, next, value, [LNode; and [B
+        assert_eq!(estimate.string_bytes, 16 + 4 + 4 + 5 + 7 + 2);
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use crate::{
+    analyzer::{
+        AnalyzedHeap,
+        contents::Contents,
+        leaks::{LeakSuspect, SuspectKind},
+    },
+    error::Result,
+    parser::{Id, sub_record::FieldValue},
+};
+
+// java name prefixes of cache libraries
+const CACHE_PACKAGES: [&str; 5] = [
+    "com.google.common.cache.",
+    "com.github.benmanes.caffeine.cache.",
+    "org.ehcache.",
+    "net.sf.ehcache.",
+    "org.infinispan.",
+];
+
+// lowercase parts of field names that look like expiry bookkeeping
+const TIME_FIELDS: [&str; 8] = [
+    "expir",
+    "ttl",
+    "timestamp",
+    "lastaccess",
+    "accesstime",
+    "writetime",
+    "created",
+    "evict",
+];
+
+// entries looked at for time fields, the first ones of the table
+const SAMPLED_ENTRIES: usize = 32;
+
+// why an object looks like a cache and how much it holds
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CacheInfo {
+    // None for caches keeping no count of their own
+    pub entries: Option<u64>,
+    pub hint: String,
+}
+
+impl CacheInfo {
+    pub fn average_entry_size(&self, retained_size: u64) -> Option<u64> {
+        self.entries.filter(|n| *n > 0).map(|n| retained_size / n)
+    }
+}
+
+// a map or collection from a cache library, an access ordered LinkedHashMap, a class named like
+// a cache, or a collection whose entries carry time or expiry fields. None for anything else
+pub fn cache_info(heap: &AnalyzedHeap, contents: &Contents, id: Id) -> Result<Option<CacheInfo>> {
+    let Some(class) = heap.class_of(id) else {
+        return Ok(None);
+    };
+    let name = class.java_name();
+    let simple_name = name.rsplit(['.', '$']).next().unwrap_or(&name);
+
+    let hint = if CACHE_PACKAGES.iter().any(|p| name.starts_with(p)) {
+        Some("cache library".to_string())
+    } else if name == "java.util.LinkedHashMap"
+        && matches!(
+            contents.field(heap, id, "accessOrder")?,
+            Some(FieldValue::Boolean(true))
+        )
+    {
+        Some("access ordered, the usual LRU cache".to_string())
+    } else if simple_name.contains("Cache") {
+        Some("named like a cache".to_string())
+    } else {
+        entry_time_field(heap, contents, id)?.map(|field| format!("entries have a {} field", field))
+    };
+
+    let Some(hint) = hint else {
+        return Ok(None);
+    };
+    Ok(Some(CacheInfo {
+        entries: contents.collection_size(heap, id)?,
+        hint,
+    }))
+}
+
+// marks the object suspects that look like caches, see cache_info
+pub fn annotate_caches(
+    suspects: &mut [LeakSuspect],
+    heap: &AnalyzedHeap,
+    contents: &Contents,
+) -> Result<()> {
+    for suspect in suspects {
+        if let SuspectKind::Object { object_id } = suspect.kind {
+            suspect.cache = cache_info(heap, contents, object_id)?;
+        }
+    }
+    Ok(())
+}
+
+// the first time like field of the values of a hash table, or of the elements of a list
+fn entry_time_field(heap: &AnalyzedHeap, contents: &Contents, id: Id) -> Result<Option<Arc<str>>> {
+    let (array, through_value) = match (
+        contents.field(heap, id, "table")?,
+        contents.field(heap, id, "elementData")?,
+    ) {
+        (Some(FieldValue::NormalObject { object_id }), _) => (object_id, true),
+        (_, Some(FieldValue::NormalObject { object_id })) => (object_id, false),
+        _ => return Ok(None),
+    };
+    let Some(elements) = contents.object_array(heap, array) else {
+        return Ok(None);
+    };
+
+    for element in elements
+        .into_iter()
+        .filter(|e| e.0 != 0)
+        .take(SAMPLED_ENTRIES)
+    {
+        let entry = if through_value {
+            match contents.field(heap, element, "value")? {
+                Some(FieldValue::NormalObject { object_id }) if object_id.0 != 0 => object_id,
+                _ => continue,
+            }
+        } else {
+            element
+        };
+        let Some(fields) = contents.fields(heap, entry)? else {
+            continue;
+        };
+        if let Some((name, _)) = fields.into_iter().find(|(name, _)| {
+            let name = name.to_lowercase();
+            TIME_FIELDS.iter().any(|t| name.contains(t))
+        }) {
+            return Ok(Some(name));
+        }
+    }
+    Ok(None)
+}
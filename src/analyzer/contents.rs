@@ -0,0 +1,298 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use crate::{
+    analyzer::{AnalyzedHeap, handle::Handle, prim_size, superclass_chain},
+    error::{HeapError, Result},
+    parser::{
+        Id, ReadCtx,
+        borrowed::{BorrowedRecord, BorrowedSubRecord, MappedDump},
+        sub_record::{Field, FieldValue, PrimArray, SubRecord},
+    },
+    trace::debug_span,
+};
+
+const NONE: u64 = u64::MAX;
+
+// field values by field name, in layout order
+pub type NamedFields = Vec<(Arc<str>, FieldValue)>;
+
+// field values and array elements of every object. the analysis only keeps classes, sizes and
+// references, so they are read from the mapped dump when asked for
+pub struct Contents {
+    dump: MappedDump,
+    ctx: ReadCtx,
+    // where the raw field bytes or array elements of each object start in the dump, by handle.
+    // NONE for class objects
+    offsets: Vec<u64>,
+    lens: Vec<u32>,
+    // loader and static fields of each class with a class dump
+    classes: HashMap<Id, ClassStatics>,
+}
+
+struct ClassStatics {
+    class_loader: Id,
+    fields: Vec<Field>,
+}
+
+// what an object's bytes hold, told apart by its class name
+enum Layout {
+    Instance,
+    ObjectArray,
+    PrimitiveArray(u8),
+}
+
+impl Contents {
+    // one pass over the dump the heap was analyzed from
+    pub fn open(path: &Path, heap: &AnalyzedHeap) -> Result<Self> {
+        let _span = debug_span!("contents", objects = heap.instances.len()).entered();
+        let dump = MappedDump::open(path)?;
+        let start = dump.bytes().as_ptr() as usize;
+        let mut offsets = vec![NONE; heap.instances.len()];
+        let mut lens = vec![0; heap.instances.len()];
+        let mut classes = HashMap::new();
+
+        let records = dump.records()?;
+        let ctx = records.ctx;
+        for record in records {
+            let BorrowedRecord::HeapDumpSegment { sub_records, .. } = record? else {
+                continue;
+            };
+            for sub_record in sub_records {
+                let (object_id, bytes) = match sub_record? {
+                    BorrowedSubRecord::InstanceDump {
+                        object_id,
+                        raw_field_bytes,
+                        ..
+                    } => (object_id, raw_field_bytes),
+                    BorrowedSubRecord::ObjArrayDump {
+                        object_id,
+                        elements,
+                        ..
+                    } => (object_id, elements.bytes()),
+                    BorrowedSubRecord::PrimArrayDump {
+                        object_id,
+                        elements,
+                        ..
+                    } => (object_id, elements),
+                    BorrowedSubRecord::Other(SubRecord::ClassDump {
+                        class_object_id,
+                        class_loader_object_id,
+                        static_fields,
+                        ..
+                    }) => {
+                        classes.insert(
+                            class_object_id,
+                            ClassStatics {
+                                class_loader: class_loader_object_id,
+                                fields: static_fields,
+                            },
+                        );
+                        continue;
+                    }
+                    BorrowedSubRecord::Other(_) => continue,
+                };
+                let Some(handle) = heap.handle(object_id) else {
+                    continue;
+                };
+                if let Some(offset) = offsets.get_mut(handle.index()) {
+                    *offset = (bytes.as_ptr() as usize - start) as u64;
+                    lens[handle.index()] = bytes.len() as u32;
+                }
+            }
+        }
+
+        Ok(Self {
+            dump,
+            ctx,
+            offsets,
+            lens,
+            classes,
+        })
+    }
+
+    fn bytes(&self, handle: Handle) -> Option<&[u8]> {
+        let offset = *self.offsets.get(handle.index())?;
+        if offset == NONE {
+            return None;
+        }
+        let offset = offset as usize;
+        self.dump
+            .bytes()
+            .get(offset..offset + self.lens[handle.index()] as usize)
+    }
+
+    // instance fields by name, the class's own fields first, followed by those of each
+    // superclass. None for arrays, class objects and ids not in the dump
+    pub fn fields(&self, heap: &AnalyzedHeap, id: Id) -> Result<Option<NamedFields>> {
+        let Some((handle, Layout::Instance)) = self.layout(heap, id) else {
+            return Ok(None);
+        };
+        let Some(mut bytes) = self.bytes(handle) else {
+            return Ok(None);
+        };
+
+        let Some(class_id) = heap.instance(id).map(|i| i.class.id) else {
+            return Ok(None);
+        };
+
+        let mut fields = Vec::new();
+        for (_, layout) in superclass_chain(class_id, &heap.layouts) {
+            for field in &layout.instance_fields {
+                let name = heap
+                    .strings
+                    .get(&field.name_id)
+                    .cloned()
+                    .ok_or(HeapError::MissingString { id: field.name_id })?;
+                let value =
+                    FieldValue::read(&mut bytes, self.ctx, field.typ).map_err(|e| match e {
+                        HeapError::Io(_) => HeapError::FieldOverflow { class: class_id },
+                        e => e,
+                    })?;
+                fields.push((name, value));
+            }
+        }
+        Ok(Some(fields))
+    }
+
+    // the loader of a class, Id(0) for the bootstrap loader. None for classes without a class
+    // dump
+    pub fn class_loader(&self, class_id: Id) -> Option<Id> {
+        self.classes.get(&class_id).map(|c| c.class_loader)
+    }
+
+    // static fields of a class by name, without those of superclasses. None for classes without
+    // a class dump
+    pub fn static_fields(&self, heap: &AnalyzedHeap, class_id: Id) -> Result<Option<NamedFields>> {
+        let Some(class) = self.classes.get(&class_id) else {
+            return Ok(None);
+        };
+        let mut fields = Vec::with_capacity(class.fields.len());
+        for field in &class.fields {
+            let name = heap
+                .strings
+                .get(&field.name_id)
+                .cloned()
+                .ok_or(HeapError::MissingString { id: field.name_id })?;
+            fields.push((name, field.value));
+        }
+        Ok(Some(fields))
+    }
+
+    // the first field with this name, fields of subclasses shadow those of superclasses
+    pub fn field(&self, heap: &AnalyzedHeap, id: Id, name: &str) -> Result<Option<FieldValue>> {
+        Ok(self
+            .fields(heap, id)?
+            .and_then(|fields| fields.into_iter().find(|(n, _)| &**n == name))
+            .map(|(_, value)| value))
+    }
+
+    // elements of an object array including nulls, as Id(0)
+    pub fn object_array(&self, heap: &AnalyzedHeap, id: Id) -> Option<Vec<Id>> {
+        let Some((handle, Layout::ObjectArray)) = self.layout(heap, id) else {
+            return None;
+        };
+        let bytes = self.bytes(handle)?;
+        Some(
+            bytes
+                .chunks_exact(self.ctx.id_size as usize)
+                .filter_map(|mut c| self.ctx.read_id(&mut c).ok())
+                .map(Id)
+                .collect(),
+        )
+    }
+
+    // the length of object arrays, or the element count java.util collections keep in a field
+    pub fn collection_size(&self, heap: &AnalyzedHeap, id: Id) -> Result<Option<u64>> {
+        if let Some(elements) = self.object_array(heap, id) {
+            return Ok(Some(elements.len() as u64));
+        }
+        let Some(fields) = self.fields(heap, id)? else {
+            return Ok(None);
+        };
+        Ok(fields
+            .into_iter()
+            .find_map(|(name, value)| match (&*name, value) {
+                ("size" | "count" | "elementCount", FieldValue::Int(n)) => Some(n.max(0) as u64),
+                ("baseCount", FieldValue::Long(n)) => Some(n.max(0) as u64),
+                _ => None,
+            }))
+    }
+
+    pub fn prim_array(&self, heap: &AnalyzedHeap, id: Id) -> Result<Option<PrimArray>> {
+        let Some((handle, Layout::PrimitiveArray(typ))) = self.layout(heap, id) else {
+            return Ok(None);
+        };
+        let Some(mut bytes) = self.bytes(handle) else {
+            return Ok(None);
+        };
+        let len = bytes.len() / prim_size(typ)? as usize;
+        Ok(Some(PrimArray::read(&mut bytes, typ, len)?))
+    }
+
+    // the elements of a primitive array as stored in the dump, big endian. None for anything
+    // else
+    pub fn prim_array_bytes(&self, heap: &AnalyzedHeap, id: Id) -> Option<&[u8]> {
+        let Some((handle, Layout::PrimitiveArray(_))) = self.layout(heap, id) else {
+            return None;
+        };
+        self.bytes(handle)
+    }
+
+    // contents of a java.lang.String. compact strings (jdk 9+) keep a byte[] with a coder of
+    // 0 for latin1 and 1 for utf-16 in the platform's byte order, older jdks a char[]
+    pub fn string_value(&self, heap: &AnalyzedHeap, id: Id) -> Result<Option<String>> {
+        if heap
+            .instance(id)
+            .is_none_or(|i| &*i.class.name != "java/lang/String")
+        {
+            return Ok(None);
+        }
+        let Some(FieldValue::NormalObject { object_id: value }) = self.field(heap, id, "value")?
+        else {
+            return Ok(None);
+        };
+        let coder = match self.field(heap, id, "coder")? {
+            Some(FieldValue::Byte(coder)) => coder,
+            _ => 0,
+        };
+
+        Ok(match self.prim_array(heap, value)? {
+            Some(PrimArray::Byte(bytes)) if coder == 0 => {
+                Some(bytes.iter().map(|&b| b as u8 as char).collect())
+            }
+            Some(PrimArray::Byte(bytes)) => {
+                if !bytes.len().is_multiple_of(2) {
+                    return Err(HeapError::InvalidUtf16 { id });
+                }
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0] as u8, c[1] as u8]))
+                    .collect();
+                Some(String::from_utf16_lossy(&units))
+            }
+            Some(PrimArray::Char(chars)) => Some(String::from_utf16_lossy(&chars)),
+            _ => None,
+        })
+    }
+
+    fn layout(&self, heap: &AnalyzedHeap, id: Id) -> Option<(Handle, Layout)> {
+        let handle = heap.handle(id)?;
+        let instance = heap.instance(id)?;
+        let name = &*instance.class.name;
+        let layout = match name.strip_prefix('[') {
+            None => Layout::Instance,
+            Some(element) => match element {
+                "Z" => Layout::PrimitiveArray(4),
+                "C" => Layout::PrimitiveArray(5),
+                "F" => Layout::PrimitiveArray(6),
+                "D" => Layout::PrimitiveArray(7),
+                "B" => Layout::PrimitiveArray(8),
+                "S" => Layout::PrimitiveArray(9),
+                "I" => Layout::PrimitiveArray(10),
+                "J" => Layout::PrimitiveArray(11),
+                _ => Layout::ObjectArray,
+            },
+        };
+        Some((handle, layout))
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hasher},
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    analyzer::{AnalyzedHeap, contents::Contents},
+    error::Result,
+    parser::{Id, sub_record::FieldValue},
+};
+
+// what deduplicating the strings of a dump would save
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DedupEstimate {
+    pub strings: u64,
+    // shallow size of the backing arrays, arrays shared by several strings counted once
+    pub array_size: u64,
+    // arrays with the same content as another one
+    pub duplicate_arrays: u64,
+    // their size, what -XX:+UseStringDeduplication frees by sharing one array per content
+    pub dedup_savings: u64,
+    // interning also leaves one String per content
+    pub intern_savings: u64,
+}
+
+// the strings sharing one content
+#[derive(Default)]
+struct ContentGroup {
+    strings: u64,
+    string_size: u64,
+    arrays: u64,
+    // of one of the arrays, they're all the same
+    array_size: u64,
+}
+
+// groups the backing arrays of java.lang.String by content. contents are told apart by length
+// and a 64 bit hash, good enough for an estimate
+pub fn dedup_estimate(heap: &AnalyzedHeap, contents: &Contents) -> Result<DedupEstimate> {
+    let strings: Vec<(Id, u64)> = heap
+        .find_classes_by_name("java/lang/String")
+        .iter()
+        .flat_map(|class| heap.instances_of(class.id))
+        .map(|instance| (instance.id, instance.shallow_size))
+        .collect();
+    let values = strings
+        .par_iter()
+        .map(|(id, size)| match contents.field(heap, *id, "value")? {
+            Some(FieldValue::NormalObject { object_id }) if object_id.0 != 0 => {
+                Ok(Some((object_id, *size)))
+            }
+            _ => Ok(None),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut groups: HashMap<(usize, u64), ContentGroup> = HashMap::new();
+    let mut seen = HashSet::new();
+    for (array, string_size) in values.into_iter().flatten() {
+        let Some(bytes) = contents.prim_array_bytes(heap, array) else {
+            continue;
+        };
+        let mut hasher = DefaultHasher::new();
+        hasher.write(bytes);
+        let group = groups.entry((bytes.len(), hasher.finish())).or_default();
+        group.strings += 1;
+        group.string_size += string_size;
+        if seen.insert(array) {
+            group.arrays += 1;
+            group.array_size = heap.instance(array).map_or(0, |i| i.shallow_size);
+        }
+    }
+
+    let mut estimate = DedupEstimate::default();
+    for group in groups.into_values() {
+        let duplicates = group.arrays.saturating_sub(1);
+        let savings = duplicates * group.array_size;
+        estimate.strings += group.strings;
+        estimate.array_size += group.arrays * group.array_size;
+        estimate.duplicate_arrays += duplicates;
+        estimate.dedup_savings += savings;
+        estimate.intern_savings +=
+            savings + group.string_size / group.strings * (group.strings - 1);
+    }
+    Ok(estimate)
+}
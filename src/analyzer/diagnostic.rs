@@ -0,0 +1,96 @@
+use std::fmt::Display;
+
+use crate::{
+    analyzer::{java_name, prim_array_name},
+    error::HeapError,
+    parser::{ClassId, StringId},
+};
+
+// an inconsistency of the dump that analysis worked around instead of failing. truncated dumps
+// and dumps written by broken agents miss records here and there, the rest is still worth a look
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Diagnostic {
+    // a class, method or source file name without its utf8 record
+    MissingString { id: StringId },
+    // objects of a class without a load class record
+    MissingClass { id: ClassId, objects: u64 },
+    // instances of a class without a class dump, sized as if they had no fields
+    MissingClassDump { id: ClassId, instances: u64 },
+    // instances with fields their raw bytes don't hold, only their class is referenced
+    MalformedInstances { id: ClassId, instances: u64 },
+    // a class dump naming a subclass of its own as superclass, the link was dropped
+    SuperclassCycle { id: ClassId },
+    // primitive arrays of a basic type without a load class record for their array class
+    MissingArrayClass { typ: u8, arrays: u64 },
+}
+
+impl Diagnostic {
+    // what a strict policy fails with instead
+    pub fn to_error(&self) -> HeapError {
+        match *self {
+            Diagnostic::MissingString { id } => HeapError::MissingString { id },
+            Diagnostic::MissingClass { id, .. } => HeapError::MissingClass { id },
+            Diagnostic::MissingClassDump { id, .. } => HeapError::MissingClassDump { id },
+            Diagnostic::MalformedInstances { id, .. } => HeapError::FieldOverflow { class: id },
+            Diagnostic::SuperclassCycle { id } => HeapError::SuperclassCycle { class: id },
+            Diagnostic::MissingArrayClass { typ, .. } => {
+                HeapError::MissingArrayClass(prim_array_name(typ).unwrap_or("unknown"))
+            }
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Diagnostic::MissingString { id } => {
+                write!(
+                    f,
+                    "string {} not found, named {}",
+                    id,
+                    unresolved_string(*id)
+                )
+            }
+            Diagnostic::MissingClass { id, objects } => write!(
+                f,
+                "class {} of {} objects not found, named {}",
+                id,
+                objects,
+                unresolved_class(*id)
+            ),
+            Diagnostic::MissingClassDump { id, instances } => write!(
+                f,
+                "class dump of {} not found, its {} instances are sized without fields",
+                id, instances
+            ),
+            Diagnostic::MalformedInstances { id, instances } => write!(
+                f,
+                "{} instances of {} have fields their bytes don't hold, their references are left \
+                 out",
+                instances, id
+            ),
+            Diagnostic::SuperclassCycle { id } => write!(
+                f,
+                "class {} is among its own superclasses, its instances are sized without the \
+                 fields of the classes after it",
+                id
+            ),
+            Diagnostic::MissingArrayClass { typ, arrays } => write!(
+                f,
+                "class of {} {} arrays not found, they got a class of their own",
+                arrays,
+                prim_array_name(*typ).map_or("unknown".into(), java_name)
+            ),
+        }
+    }
+}
+
+// placeholders standing in for what the dump is missing
+pub fn unresolved_string(id: StringId) -> String {
+    format!("<unresolved string {}>", id)
+}
+
+pub fn unresolved_class(id: ClassId) -> String {
+    format!("<unresolved class {}>", id)
+}
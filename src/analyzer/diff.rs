@@ -0,0 +1,335 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analyzer::{AnalyzedHeap, HistogramEntry, contents::Contents},
+    error::Result,
+    parser::{Id, sub_record::FieldValue},
+};
+
+// what a dump holds of one class
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassTotals {
+    pub instances: u64,
+    pub shallow_size: u64,
+    // None when the dump was compared without its dominator tree
+    pub retained_size: Option<u64>,
+}
+
+// totals by java class name, ids don't carry over from one dump to the next. classes loaded by
+// different class loaders share a name and are summed up
+pub fn class_totals(
+    histogram: &[HistogramEntry],
+    retained_by_class: Option<&HashMap<Id, u64>>,
+) -> HashMap<String, ClassTotals> {
+    let mut totals: HashMap<String, ClassTotals> = HashMap::new();
+    for entry in histogram {
+        let retained = retained_by_class.map(|r| r.get(&entry.class.id).copied().unwrap_or(0));
+        let class = totals
+            .entry(entry.class.java_name())
+            .or_insert(ClassTotals {
+                retained_size: retained.map(|_| 0),
+                ..Default::default()
+            });
+        class.instances += entry.instance_count;
+        class.shallow_size += entry.shallow_size;
+        class.retained_size = class.retained_size.zip(retained).map(|(a, b)| a + b);
+    }
+    totals
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClassChange {
+    // no instances before
+    New,
+    // no instances after
+    Gone,
+    Changed,
+}
+
+// one class of two compared histograms. the side a class is missing from has all totals at 0
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassDiff {
+    pub class_name: String,
+    pub before: ClassTotals,
+    pub after: ClassTotals,
+    pub change: ClassChange,
+}
+
+impl ClassDiff {
+    pub fn instance_delta(&self) -> i64 {
+        self.after.instances as i64 - self.before.instances as i64
+    }
+
+    pub fn shallow_delta(&self) -> i64 {
+        self.after.shallow_size as i64 - self.before.shallow_size as i64
+    }
+
+    // None unless both dumps were compared with retained sizes
+    pub fn retained_delta(&self) -> Option<i64> {
+        let before = self.before.retained_size?;
+        let after = self.after.retained_size?;
+        Some(after as i64 - before as i64)
+    }
+}
+
+// the growth rows are sorted by, largest first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiffOrder {
+    #[default]
+    Shallow,
+    Count,
+    // by shallow growth when retained sizes weren't compared
+    Retained,
+}
+
+// classes whose totals differ between the two dumps, sorted by growth and then name
+pub fn histogram_diff(
+    before: &HashMap<String, ClassTotals>,
+    after: &HashMap<String, ClassTotals>,
+    order: DiffOrder,
+) -> Vec<ClassDiff> {
+    let names: HashSet<&String> = before.keys().chain(after.keys()).collect();
+    let mut diffs: Vec<ClassDiff> = names
+        .into_iter()
+        .filter_map(|name| {
+            let (before, after, change) = match (before.get(name), after.get(name)) {
+                (Some(before), Some(after)) if before == after => return None,
+                (Some(before), Some(after)) => (*before, *after, ClassChange::Changed),
+                (None, Some(after)) => (absent(after), *after, ClassChange::New),
+                (Some(before), None) => (*before, absent(before), ClassChange::Gone),
+                (None, None) => return None,
+            };
+            Some(ClassDiff {
+                class_name: name.clone(),
+                before,
+                after,
+                change,
+            })
+        })
+        .collect();
+
+    diffs.sort_by(|a, b| {
+        growth(b, order)
+            .cmp(&growth(a, order))
+            .then_with(|| a.class_name.cmp(&b.class_name))
+    });
+    diffs
+}
+
+// the totals of a class on the side it's missing from
+fn absent(present: &ClassTotals) -> ClassTotals {
+    ClassTotals {
+        retained_size: present.retained_size.map(|_| 0),
+        ..Default::default()
+    }
+}
+
+fn growth(diff: &ClassDiff, order: DiffOrder) -> i64 {
+    match order {
+        DiffOrder::Shallow => diff.shallow_delta(),
+        DiffOrder::Count => diff.instance_delta(),
+        DiffOrder::Retained => diff
+            .retained_delta()
+            .unwrap_or_else(|| diff.shallow_delta()),
+    }
+}
+
+// dominators an object key is built from at most, deeper objects like the tail of a long linked
+// list aren't matched
+const MAX_KEY_DEPTH: usize = 64;
+// string contents in keys are cut off after this many chars
+const MAX_KEY_STRING: usize = 64;
+
+// an object's key and the number of dominators it's made of, None below MAX_KEY_DEPTH
+type Key = Option<(String, usize)>;
+
+// one of the two dumps an object level diff compares
+#[derive(Clone, Copy)]
+pub struct DiffSide<'a> {
+    pub heap: &'a AnalyzedHeap,
+    pub contents: &'a Contents,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectState {
+    pub id: Id,
+    pub retained_size: u64,
+    // length of arrays, the size or count field of collections
+    pub elements: Option<u64>,
+}
+
+// an object found in both dumps
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectDiff {
+    // where the object sits in the dominator tree, see object_diff
+    pub key: String,
+    pub class_name: String,
+    pub before: ObjectState,
+    pub after: ObjectState,
+}
+
+impl ObjectDiff {
+    pub fn retained_delta(&self) -> i64 {
+        self.after.retained_size as i64 - self.before.retained_size as i64
+    }
+
+    pub fn element_delta(&self) -> Option<i64> {
+        Some(self.after.elements? as i64 - self.before.elements? as i64)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectDiffReport {
+    // matched objects that grew, by retained growth and then key
+    pub grown: Vec<ObjectDiff>,
+    pub matched: usize,
+    // keys shared by several objects of a dump, which can't be told apart
+    pub ambiguous: usize,
+}
+
+// matches the objects retaining at least min_retained in either dump. object ids differ between
+// dumps, so objects are matched by a key made of their chain of dominators from a gc root: the
+// class names, the fields each object is referenced through and the contents of strings and
+// string map keys along the way, e.g.
+//
+//   class com.example.Registry / CACHE java.util.HashMap / table java.util.HashMap$Node[]
+pub fn object_diff(
+    before: DiffSide<'_>,
+    after: DiffSide<'_>,
+    min_retained: u64,
+) -> Result<ObjectDiffReport> {
+    let before_keys = candidate_keys(before, min_retained)?;
+    let after_keys = candidate_keys(after, min_retained)?;
+
+    let mut report = ObjectDiffReport {
+        grown: Vec::new(),
+        matched: 0,
+        ambiguous: 0,
+    };
+    for (key, before_ids) in &before_keys {
+        let Some(after_ids) = after_keys.get(key) else {
+            continue;
+        };
+        let ([before_id], [after_id]) = (before_ids.as_slice(), after_ids.as_slice()) else {
+            report.ambiguous += 1;
+            continue;
+        };
+        report.matched += 1;
+
+        let diff = ObjectDiff {
+            key: key.clone(),
+            class_name: after.heap.class_name_of(*after_id).unwrap_or_default(),
+            before: object_state(before, *before_id)?,
+            after: object_state(after, *after_id)?,
+        };
+        if diff.retained_delta() > 0 || diff.element_delta().is_some_and(|d| d > 0) {
+            report.grown.push(diff);
+        }
+    }
+
+    report.grown.sort_by(|a, b| {
+        b.retained_delta()
+            .cmp(&a.retained_delta())
+            .then_with(|| a.key.cmp(&b.key))
+    });
+    Ok(report)
+}
+
+// objects retaining at least min_retained by key
+fn candidate_keys(side: DiffSide<'_>, min_retained: u64) -> Result<HashMap<String, Vec<Id>>> {
+    let tree = side.heap.dominator_tree();
+    // keys of the dominators, shared by everything below them
+    let mut keys: HashMap<Id, Key> = HashMap::new();
+    let mut candidates: HashMap<String, Vec<Id>> = HashMap::new();
+
+    for &id in side.heap.handles.ids() {
+        if tree.retained_size(id).is_none_or(|r| r < min_retained) {
+            continue;
+        }
+        if let Some(key) = object_key(side, id, &mut keys)? {
+            candidates.entry(key).or_default().push(id);
+        }
+    }
+    Ok(candidates)
+}
+
+// None for objects below MAX_KEY_DEPTH dominators
+fn object_key(side: DiffSide<'_>, id: Id, keys: &mut HashMap<Id, Key>) -> Result<Option<String>> {
+    let tree = side.heap.dominator_tree();
+    // the object and its dominators without a key yet, nearest first
+    let mut chain = Vec::new();
+    let mut key = Some((String::new(), 0));
+    let mut current = Some(id);
+    while let Some(object) = current {
+        if let Some(known) = keys.get(&object) {
+            key = known.clone();
+            break;
+        }
+        chain.push(object);
+        current = tree.immediate_dominator(object);
+    }
+
+    for object in chain.into_iter().rev() {
+        key = match key {
+            Some((_, depth)) if depth >= MAX_KEY_DEPTH => None,
+            Some((parent_key, depth)) => {
+                let segment = segment(side, tree.immediate_dominator(object), object)?;
+                let key = if parent_key.is_empty() {
+                    segment
+                } else {
+                    format!("{} / {}", parent_key, segment)
+                };
+                Some((key, depth + 1))
+            }
+            None => None,
+        };
+        keys.insert(object, key.clone());
+    }
+    Ok(key.map(|(key, _)| key))
+}
+
+// the class of an object with the field its dominator references it through, if it does so
+// directly, and what tells it apart from its siblings
+fn segment(side: DiffSide<'_>, parent: Option<Id>, id: Id) -> Result<String> {
+    let DiffSide { heap, contents } = side;
+    let mut segment = heap.class_name_of(id).unwrap_or_default();
+
+    let field = match parent {
+        Some(parent) => contents.fields(heap, parent)?.and_then(|fields| {
+            fields.into_iter().find_map(|(name, value)| match value {
+                FieldValue::NormalObject { object_id } if object_id == id => Some(name),
+                _ => None,
+            })
+        }),
+        None => None,
+    };
+    if let Some(field) = field {
+        segment = format!("{} {}", field, segment);
+    }
+
+    let label = match contents.string_value(heap, id)? {
+        Some(content) => Some(content),
+        // map entries, HashMap$Node and the like
+        None => match contents.field(heap, id, "key")? {
+            Some(FieldValue::NormalObject { object_id }) => {
+                contents.string_value(heap, object_id)?
+            }
+            _ => None,
+        },
+    };
+    if let Some(label) = label {
+        let label: String = label.chars().take(MAX_KEY_STRING).collect();
+        segment = format!("{}[{:?}]", segment, label);
+    }
+    Ok(segment)
+}
+
+fn object_state(side: DiffSide<'_>, id: Id) -> Result<ObjectState> {
+    Ok(ObjectState {
+        id,
+        retained_size: side.heap.dominator_tree().retained_size(id).unwrap_or(0),
+        elements: side.contents.collection_size(side.heap, id)?,
+    })
+}
@@ -0,0 +1,482 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+    },
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    analyzer::{
+        AnalyzedHeap,
+        graph::Csr,
+        handle::{Handle, Handles},
+    },
+    parser::Id,
+    trace::{Empty, debug_span},
+};
+
+const NONE: u32 = u32::MAX;
+
+// smaller graphs aren't worth the synchronization
+const PARALLEL_THRESHOLD: usize = 1 << 16;
+const PARALLEL_CHUNK: usize = 1 << 12;
+
+// dominator tree over all objects reachable from gc roots. nodes are numbered in dfs preorder,
+// node 0 is a virtual root pointing at every gc root.
+pub struct DominatorTree {
+    handles: Arc<Handles>,
+    // handle of every node, NONE for the virtual root
+    nodes: Vec<u32>,
+    // node of every handle, NONE for unreachable objects
+    node_of: Vec<u32>,
+    idom: Vec<u32>,
+    retained: Vec<u64>,
+}
+
+impl DominatorTree {
+    pub fn compute(heap: &AnalyzedHeap) -> Self {
+        let span = debug_span!("dominators", reachable = Empty, edges = Empty).entered();
+        let (nodes, node_of, parent, edges) = Self::dfs(heap);
+        let n = nodes.len();
+        span.record("reachable", n);
+        span.record("edges", edges.len());
+        let predecessors = Csr::build(n, edges.iter().map(|&(v, w)| (w, v)));
+        drop(edges);
+
+        let idom = if n >= PARALLEL_THRESHOLD && rayon::current_num_threads() > 1 {
+            parallel_idom(&parent, &predecessors)
+        } else {
+            lengauer_tarjan(&parent, &predecessors)
+        };
+
+        // dominators always have a smaller preorder number, so a reverse sweep accumulates subtrees
+        let mut retained: Vec<u64> = nodes
+            .iter()
+            .map(|&h| {
+                heap.instances
+                    .shallow_sizes()
+                    .get(h as usize)
+                    .copied()
+                    .unwrap_or(0)
+            })
+            .collect();
+        for w in (1..n).rev() {
+            retained[idom[w] as usize] += retained[w];
+        }
+
+        Self {
+            handles: heap.handles.clone(),
+            nodes,
+            node_of,
+            idom,
+            retained,
+        }
+    }
+
+    // preorder numbering of everything reachable from the roots as node handles and the node of
+    // every handle, with spanning tree parents and all edges as node numbers
+    #[allow(clippy::type_complexity)]
+    fn dfs(heap: &AnalyzedHeap) -> (Vec<u32>, Vec<u32>, Vec<u32>, Vec<(u32, u32)>) {
+        let mut nodes = vec![NONE];
+        let mut node_of = vec![NONE; heap.handles.len()];
+        let mut parent = vec![NONE];
+        let mut edges: Vec<(u32, u32)> = Vec::new();
+
+        // references to objects missing from the dump are dropped
+        let mut stack: Vec<(u32, Handle)> = Vec::new();
+        for root in heap.roots.iter().rev() {
+            if let Some(handle) = heap.handle(root.object_id) {
+                stack.push((0, handle));
+            }
+        }
+
+        while let Some((from, handle)) = stack.pop() {
+            let existing = node_of[handle.index()];
+            if existing != NONE {
+                edges.push((from, existing));
+                continue;
+            }
+
+            let node = nodes.len() as u32;
+            nodes.push(handle.0);
+            node_of[handle.index()] = node;
+            parent.push(from);
+            edges.push((from, node));
+
+            for reference in heap.references.get(handle).iter().rev() {
+                if let Some(handle) = heap.handle(*reference) {
+                    stack.push((node, handle));
+                }
+            }
+        }
+
+        (nodes, node_of, parent, edges)
+    }
+
+    // the node tables, for storing the tree in an index
+    pub(crate) fn parts(&self) -> (&[u32], &[u32], &[u64]) {
+        (&self.nodes, &self.idom, &self.retained)
+    }
+
+    pub(crate) fn from_parts(
+        handles: Arc<Handles>,
+        nodes: Vec<u32>,
+        idom: Vec<u32>,
+        retained: Vec<u64>,
+    ) -> Self {
+        let mut node_of = vec![NONE; handles.len()];
+        for (node, &handle) in nodes.iter().enumerate().skip(1) {
+            node_of[handle as usize] = node as u32;
+        }
+        Self {
+            handles,
+            nodes,
+            node_of,
+            idom,
+            retained,
+        }
+    }
+
+    fn node(&self, id: Id) -> Option<u32> {
+        let handle = self.handles.get(id)?;
+        let node = self.node_of[handle.index()];
+        (node != NONE).then_some(node)
+    }
+
+    // Id(0) for the virtual root
+    fn id(&self, node: u32) -> Id {
+        match self.nodes[node as usize] {
+            NONE => Id(0),
+            handle => self.handles.id(Handle(handle)),
+        }
+    }
+
+    pub fn is_reachable(&self, id: Id) -> bool {
+        self.node(id).is_some()
+    }
+
+    pub fn retained_size(&self, id: Id) -> Option<u64> {
+        self.node(id).map(|i| self.retained[i as usize])
+    }
+
+    // None for unreachable objects and objects only dominated by the virtual root
+    pub fn immediate_dominator(&self, id: Id) -> Option<Id> {
+        let i = self.node(id)?;
+        let idom = self.idom[i as usize];
+        (idom != 0).then(|| self.id(idom))
+    }
+
+    pub fn reachable_size(&self) -> u64 {
+        self.retained[0]
+    }
+
+    pub fn reachable_count(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    // objects only dominated by the virtual root
+    pub fn top_level_count(&self) -> usize {
+        self.idom.iter().skip(1).filter(|&&idom| idom == 0).count()
+    }
+
+    // objects directly dominated by each object, largest retained size first and then by id.
+    // objects only dominated by the virtual root are listed under Id(0)
+    pub fn children(&self) -> HashMap<Id, Vec<Id>> {
+        let mut children: HashMap<Id, Vec<u32>> = HashMap::new();
+        for w in 1..self.nodes.len() {
+            children
+                .entry(self.id(self.idom[w]))
+                .or_default()
+                .push(w as u32);
+        }
+
+        children
+            .into_iter()
+            .map(|(id, mut nodes)| {
+                nodes.sort_by_key(|&w| (std::cmp::Reverse(self.retained[w as usize]), self.id(w)));
+                (id, nodes.into_iter().map(|w| self.id(w)).collect())
+            })
+            .collect()
+    }
+
+    // the objects retaining enough that the predicate accepts and none of whose dominators it
+    // accepts as well. retained sizes only grow towards the root, so one sweep over the nodes
+    // retaining enough, in preorder with dominators before the objects they dominate, finds them
+    pub fn outermost(
+        &self,
+        retains_enough: impl Fn(u64) -> bool,
+        accept: impl Fn(Id) -> bool,
+    ) -> Vec<Id> {
+        // whether the node or one of its dominators was accepted
+        let mut covered = vec![false; self.nodes.len()];
+        let mut outermost = Vec::new();
+        for w in 1..self.nodes.len() {
+            if !retains_enough(self.retained[w]) {
+                continue;
+            }
+            if covered[self.idom[w] as usize] {
+                covered[w] = true;
+            } else if accept(self.id(w as u32)) {
+                covered[w] = true;
+                outermost.push(self.id(w as u32));
+            }
+        }
+        outermost
+    }
+
+    // retained size of all instances of a class, without counting instances twice when one
+    // instance of the class dominates another
+    pub fn retained_by_class(&self, heap: &AnalyzedHeap) -> HashMap<Id, u64> {
+        self.retained_by_class_excluding(heap, &HashSet::new())
+    }
+
+    // like retained_by_class, but ignores the dominator subtrees of the excluded objects
+    pub fn retained_by_class_excluding(
+        &self,
+        heap: &AnalyzedHeap,
+        excluded: &HashSet<Id>,
+    ) -> HashMap<Id, u64> {
+        let n = self.nodes.len();
+        let class_of: Vec<Option<Id>> = self
+            .nodes
+            .iter()
+            .map(|&h| {
+                let class = heap.instances.classes().get(h as usize)?;
+                Some(self.handles.id(Handle(*class)))
+            })
+            .collect();
+
+        let edges = (1..n as u32)
+            .filter(|&w| !excluded.contains(&self.id(w)))
+            .map(|w| (self.idom[w as usize], w));
+        let children = Csr::build(n, edges);
+
+        let mut result: HashMap<Id, u64> = HashMap::new();
+        let mut active: HashMap<Id, u32> = HashMap::new();
+        // (node, exiting)
+        let mut stack = vec![(0u32, false)];
+
+        while let Some((node, exiting)) = stack.pop() {
+            let class = class_of[node as usize];
+
+            if exiting {
+                if let Some(class) = class {
+                    *active.entry(class).or_default() -= 1;
+                }
+                continue;
+            }
+
+            if let Some(class) = class {
+                let count = active.entry(class).or_default();
+                if *count == 0 {
+                    *result.entry(class).or_default() += self.retained[node as usize];
+                }
+                *count += 1;
+            }
+
+            stack.push((node, true));
+            for &child in children.get(node) {
+                stack.push((child, false));
+            }
+        }
+
+        result
+    }
+}
+
+// lengauer-tarjan with path compression
+fn lengauer_tarjan(parent: &[u32], predecessors: &Csr) -> Vec<u32> {
+    let n = parent.len();
+    let mut semi: Vec<u32> = (0..n as u32).collect();
+    let mut label: Vec<u32> = (0..n as u32).collect();
+    let mut ancestor = vec![NONE; n];
+    let mut idom = vec![0u32; n];
+    let mut bucket: Vec<Vec<u32>> = vec![Vec::new(); n];
+
+    for w in (1..n).rev() {
+        for &v in predecessors.get(w as u32) {
+            let u = eval(v, &mut ancestor, &mut label, &semi);
+            if semi[u as usize] < semi[w] {
+                semi[w] = semi[u as usize];
+            }
+        }
+        bucket[semi[w] as usize].push(w as u32);
+
+        let p = parent[w];
+        ancestor[w] = p;
+
+        for v in std::mem::take(&mut bucket[p as usize]) {
+            let u = eval(v, &mut ancestor, &mut label, &semi);
+            idom[v as usize] = if semi[u as usize] < semi[v as usize] {
+                u
+            } else {
+                p
+            };
+        }
+    }
+
+    for w in 1..n {
+        if idom[w] != semi[w] {
+            idom[w] = idom[idom[w] as usize];
+        }
+    }
+
+    idom
+}
+
+// cooper-harvey-kennedy, with chunks of nodes updated concurrently until nothing changes. starts
+// from the dfs spanning tree, whose ancestors include all dominators. every update only moves a
+// node's idom towards the root and stays above the real dominators, so the result doesn't depend
+// on the order threads see each other's updates in
+fn parallel_idom(parent: &[u32], predecessors: &Csr) -> Vec<u32> {
+    let idom: Vec<AtomicU32> = parent.iter().map(|&p| AtomicU32::new(p)).collect();
+    idom[0].store(0, Ordering::Relaxed);
+
+    loop {
+        let changed = AtomicBool::new(false);
+        (1..idom.len())
+            .into_par_iter()
+            .with_min_len(PARALLEL_CHUNK)
+            .for_each(|w| {
+                let mut preds = predecessors.get(w as u32).iter().copied();
+                let Some(first) = preds.next() else {
+                    return;
+                };
+                let new = preds.fold(first, |a, b| intersect(&idom, a, b));
+                if new != idom[w].load(Ordering::Relaxed) {
+                    idom[w].store(new, Ordering::Relaxed);
+                    changed.store(true, Ordering::Relaxed);
+                }
+            });
+        if !changed.into_inner() {
+            break;
+        }
+    }
+
+    idom.into_iter().map(AtomicU32::into_inner).collect()
+}
+
+// nearest common ancestor in the current approximation. idoms have smaller preorder numbers than
+// their nodes, so the node with the larger number can't be an ancestor of the other
+fn intersect(idom: &[AtomicU32], mut a: u32, mut b: u32) -> u32 {
+    while a != b {
+        while a > b {
+            a = idom[a as usize].load(Ordering::Relaxed);
+        }
+        while b > a {
+            b = idom[b as usize].load(Ordering::Relaxed);
+        }
+    }
+    a
+}
+
+fn eval(v: u32, ancestor: &mut [u32], label: &mut [u32], semi: &[u32]) -> u32 {
+    if ancestor[v as usize] == NONE {
+        return v;
+    }
+
+    let mut path = Vec::new();
+    let mut x = v;
+    while ancestor[ancestor[x as usize] as usize] != NONE {
+        path.push(x);
+        x = ancestor[x as usize];
+    }
+
+    while let Some(y) = path.pop() {
+        let a = ancestor[y as usize] as usize;
+        if semi[label[a] as usize] < semi[label[y as usize] as usize] {
+            label[y as usize] = label[a];
+        }
+        ancestor[y as usize] = ancestor[a];
+    }
+
+    label[v as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analyzer::graph::RootKind,
+        parser::{ParsedHeap, sub_record::FieldValue},
+        testutil::HeapBuilder,
+    };
+
+    // a binary tree of nodes with a third reference each: back edges to a grandparent, self loops
+    // and cross edges from right children into the subtree of their left sibling. the tree is
+    // the dfs spanning tree, so it stays shallow
+    #[test]
+    fn parallel_idom_matches_lengauer_tarjan() {
+        let n = PARALLEL_THRESHOLD + 1000;
+        let mut builder = HeapBuilder::new();
+        let object = builder.class("java/lang/Object", None, &[]);
+        let node = builder.class(
+            "Node",
+            Some(object),
+            &[("left", 2), ("right", 2), ("other", 2)],
+        );
+
+        // instances get consecutive ids 8 apart, node i is the i-th after this unreachable one.
+        // children past the last node are null
+        let first = builder.instance(node, &[]);
+        let id = |i: usize| {
+            if i < n {
+                Id(first.0 + 8 * (i as u64 + 1))
+            } else {
+                Id(0)
+            }
+        };
+        let mut seed = 0x2545f4914f6cdd1d_u64;
+        for i in 0..n {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let other = match seed >> 62 {
+                0 => i,
+                1 if i > 0 && i % 2 == 0 => {
+                    let mut other = i - 1;
+                    for _ in 0..(seed >> 32) % 8 {
+                        if 2 * other + 1 < n {
+                            other = 2 * other + 1;
+                        }
+                    }
+                    other
+                }
+                _ => (i.saturating_sub(1) / 2).saturating_sub(1) / 2,
+            };
+            let created = builder.instance(
+                node,
+                &[
+                    FieldValue::NormalObject {
+                        object_id: id(2 * i + 1),
+                    },
+                    FieldValue::NormalObject {
+                        object_id: id(2 * i + 2),
+                    },
+                    FieldValue::NormalObject {
+                        object_id: id(other),
+                    },
+                ],
+            );
+            assert_eq!(created, id(i));
+        }
+        builder.root(RootKind::JniGlobal, id(0)).unwrap();
+        builder.root(RootKind::JniGlobal, id(n / 3)).unwrap();
+
+        let parsed = ParsedHeap::from_bytes(builder.build().unwrap()).unwrap();
+        let heap = AnalyzedHeap::analyze(&parsed).unwrap();
+        let (nodes, _, parent, edges) = DominatorTree::dfs(&heap);
+        assert!(nodes.len() > PARALLEL_THRESHOLD);
+        assert!(edges.iter().any(|&(v, w)| v > w), "no back edges");
+        assert!(edges.iter().any(|&(v, w)| v == w), "no self loops");
+        let predecessors = Csr::build(nodes.len(), edges.iter().map(|&(v, w)| (w, v)));
+
+        let sequential = lengauer_tarjan(&parent, &predecessors);
+        assert_eq!(parallel_idom(&parent, &predecessors), sequential);
+        // the cross edges make dominators of nodes other than their dfs parents
+        assert!((1..nodes.len()).any(|w| sequential[w] != parent[w]));
+    }
+}
@@ -0,0 +1,94 @@
+use std::{cmp::Reverse, collections::HashMap, fmt::Display};
+
+use rayon::prelude::*;
+
+use crate::{
+    analyzer::{AnalyzedHeap, contents::Contents},
+    error::Result,
+    parser::sub_record::FieldValue,
+};
+
+// how a java.lang.String stores its characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StringEncoding {
+    // compact strings, one byte per character
+    Latin1,
+    // compact strings with a character outside latin1, two bytes per character
+    Utf16,
+    // a char[], jdk 8 and older or -XX:-CompactStrings
+    Chars,
+}
+
+impl Display for StringEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StringEncoding::Latin1 => write!(f, "LATIN1"),
+            StringEncoding::Utf16 => write!(f, "UTF-16"),
+            StringEncoding::Chars => write!(f, "char[]"),
+        }
+    }
+}
+
+// the strings of one encoding and their size
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EncodingStats {
+    pub encoding: StringEncoding,
+    pub strings: u64,
+    // of the String objects
+    pub string_size: u64,
+    // of the backing arrays, arrays shared by several strings are counted for each
+    pub array_size: u64,
+}
+
+// strings by encoding, told apart by the type of their value array and their coder field.
+// strings without a value array are left out. most bytes first
+pub fn string_encodings(heap: &AnalyzedHeap, contents: &Contents) -> Result<Vec<EncodingStats>> {
+    let strings: Vec<_> = heap
+        .find_classes_by_name("java/lang/String")
+        .iter()
+        .flat_map(|class| heap.instances_of(class.id))
+        .map(|instance| (instance.id, instance.shallow_size))
+        .collect();
+    let encoded = strings
+        .par_iter()
+        .map(|(id, size)| {
+            let Some(FieldValue::NormalObject { object_id: value }) =
+                contents.field(heap, *id, "value")?
+            else {
+                return Ok(None);
+            };
+            let Some(array) = heap.instance(value) else {
+                return Ok(None);
+            };
+            let encoding = match &*array.class.name {
+                "[C" => StringEncoding::Chars,
+                "[B" => match contents.field(heap, *id, "coder")? {
+                    Some(FieldValue::Byte(1)) => StringEncoding::Utf16,
+                    _ => StringEncoding::Latin1,
+                },
+                _ => return Ok(None),
+            };
+            Ok(Some((encoding, *size, array.shallow_size)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut by_encoding: HashMap<StringEncoding, EncodingStats> = HashMap::new();
+    for (encoding, string_size, array_size) in encoded.into_iter().flatten() {
+        let stats = by_encoding
+            .entry(encoding)
+            .or_insert_with(|| EncodingStats {
+                encoding,
+                strings: 0,
+                string_size: 0,
+                array_size: 0,
+            });
+        stats.strings += 1;
+        stats.string_size += string_size;
+        stats.array_size += array_size;
+    }
+
+    let mut stats: Vec<EncodingStats> = by_encoding.into_values().collect();
+    stats.sort_by_key(|s| Reverse(s.string_size + s.array_size));
+    Ok(stats)
+}
@@ -0,0 +1,19 @@
+use serde::Deserialize;
+
+// include/exclude lists of java class name prefixes, e.g. "com.foo." or "java.util.HashMap"
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClassFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl ClassFilter {
+    pub fn matches(&self, java_name: &str) -> bool {
+        let included =
+            self.include.is_empty() || self.include.iter().any(|p| java_name.starts_with(p));
+        let excluded = self.exclude.iter().any(|p| java_name.starts_with(p));
+
+        included && !excluded
+    }
+}
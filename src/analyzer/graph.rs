@@ -0,0 +1,235 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    analyzer::{
+        ClassLayout,
+        handle::Handle,
+        storage::{Column, Storage},
+        superclass_chain,
+    },
+    error::{HeapError, Result},
+    parser::{
+        Id,
+        sub_record::{FieldValue, SubRecord},
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RootKind {
+    JniGlobal,
+    JniLocal,
+    JavaFrame,
+    StickyClass,
+    ThreadObject,
+}
+
+impl Display for RootKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RootKind::JniGlobal => write!(f, "JNI global"),
+            RootKind::JniLocal => write!(f, "JNI local"),
+            RootKind::JavaFrame => write!(f, "Java frame"),
+            RootKind::StickyClass => write!(f, "sticky class"),
+            RootKind::ThreadObject => write!(f, "thread object"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GcRoot {
+    pub object_id: Id,
+    pub kind: RootKind,
+}
+
+impl GcRoot {
+    pub fn from_sub_record(sub_record: &SubRecord) -> Option<Self> {
+        let (object_id, kind) = match sub_record {
+            SubRecord::JniGlobal { object_id, .. } => (object_id, RootKind::JniGlobal),
+            SubRecord::JniLocal { object_id, .. } => (object_id, RootKind::JniLocal),
+            SubRecord::JavaFrame { object_id, .. } => (object_id, RootKind::JavaFrame),
+            SubRecord::StickyClass { object_id } => (object_id, RootKind::StickyClass),
+            SubRecord::ThreadObj { object_id, .. } => (object_id, RootKind::ThreadObject),
+            _ => return None,
+        };
+
+        Some(Self {
+            object_id: *object_id,
+            kind,
+        })
+    }
+}
+
+// outgoing references of every object by handle, as one flat edge column with the start and
+// length of each object's edges
+pub struct References {
+    starts: Column<u64>,
+    lens: Column<u32>,
+    edges: Column<Id>,
+}
+
+impl References {
+    pub fn memory() -> Self {
+        Self {
+            starts: Column::memory(),
+            lens: Column::memory(),
+            edges: Column::memory(),
+        }
+    }
+
+    pub fn new(storage: &Storage) -> Result<Self> {
+        Ok(Self {
+            starts: Column::new(storage)?,
+            lens: Column::new(storage)?,
+            edges: Column::new(storage)?,
+        })
+    }
+
+    // handles have to be set in order, setting an existing handle again replaces its edges
+    pub fn set(&mut self, handle: Handle, references: &[Id]) -> Result<()> {
+        let start = self.edges.len() as u64;
+        self.edges.extend_from_slice(references)?;
+        if handle.index() < self.starts.len() {
+            self.starts[handle.index()] = start;
+            self.lens[handle.index()] = references.len() as u32;
+        } else {
+            self.starts.push(start)?;
+            self.lens.push(references.len() as u32)?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, handle: Handle) -> &[Id] {
+        let start = self.starts[handle.index()] as usize;
+        &self.edges[start..start + self.lens[handle.index()] as usize]
+    }
+
+    // number of objects
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.starts.is_empty()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn is_spilled(&self) -> bool {
+        self.edges.is_spilled()
+    }
+}
+
+// compressed sparse rows, the targets of node v are targets[offsets[v]..offsets[v + 1]]
+pub struct Csr {
+    offsets: Vec<u64>,
+    targets: Vec<u32>,
+}
+
+impl Csr {
+    // goes over the edges twice: counting the edges of every node, then filling them in at the
+    // prefix sums of the counts
+    pub fn build(nodes: usize, edges: impl Iterator<Item = (u32, u32)> + Clone) -> Self {
+        let mut offsets = vec![0u64; nodes + 1];
+        for (from, _) in edges.clone() {
+            offsets[from as usize + 1] += 1;
+        }
+        for v in 0..nodes {
+            offsets[v + 1] += offsets[v];
+        }
+
+        let mut next = offsets[..nodes].to_vec();
+        let mut targets = vec![0; offsets[nodes] as usize];
+        for (from, to) in edges {
+            let i = &mut next[from as usize];
+            targets[*i as usize] = to;
+            *i += 1;
+        }
+
+        Self { offsets, targets }
+    }
+
+    pub fn get(&self, node: u32) -> &[u32] {
+        let node = node as usize;
+        &self.targets[self.offsets[node] as usize..self.offsets[node + 1] as usize]
+    }
+
+    // number of nodes
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.targets.len()
+    }
+}
+
+// size of a field of the given basic type inside an instance dump
+pub fn dump_field_size(typ: u8) -> Result<usize> {
+    match typ {
+        0x02 => Ok(8),
+        0x04 | 0x08 => Ok(1),
+        0x05 | 0x09 => Ok(2),
+        0x06 | 0x0a => Ok(4),
+        0x07 | 0x0b => Ok(8),
+        _ => Err(HeapError::InvalidType(typ)),
+    }
+}
+
+// instance dumps store the fields of the class first, followed by the fields of each superclass
+pub fn instance_references(
+    class_id: Id,
+    raw_field_bytes: &[u8],
+    layouts: &HashMap<Id, ClassLayout>,
+) -> Result<Vec<Id>> {
+    let mut references = vec![class_id];
+    let mut offset = 0;
+    for (_, layout) in superclass_chain(class_id, layouts) {
+        for field in &layout.instance_fields {
+            let size = dump_field_size(field.typ)?;
+            let Some(bytes) = raw_field_bytes.get(offset..offset + size) else {
+                return Err(HeapError::FieldOverflow { class: class_id });
+            };
+
+            if field.typ == 0x02 {
+                let id = u64::from_be_bytes(bytes.try_into().unwrap());
+                if id != 0 {
+                    references.push(Id(id));
+                }
+            }
+            offset += size;
+        }
+    }
+
+    Ok(references)
+}
+
+pub fn class_references(sub_record: &SubRecord) -> Vec<Id> {
+    let SubRecord::ClassDump {
+        super_class_object_id,
+        class_loader_object_id,
+        static_fields,
+        ..
+    } = sub_record
+    else {
+        return Vec::new();
+    };
+
+    let statics = static_fields.iter().filter_map(|f| match f.value {
+        FieldValue::NormalObject { object_id } => Some(object_id),
+        _ => None,
+    });
+
+    [*super_class_object_id, *class_loader_object_id]
+        .into_iter()
+        .chain(statics)
+        .filter(|id| id.0 != 0)
+        .collect()
+}
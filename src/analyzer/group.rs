@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use crate::{
+    analyzer::{AnalyzedHeap, contents::Contents},
+    error::Result,
+    parser::{Id, sub_record::FieldValue},
+};
+
+// instances of a class sharing a field value
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldBucket {
+    pub value: String,
+    pub instances: u64,
+    // 0 for instances not in the dominator tree
+    pub retained_size: u64,
+}
+
+// distribution of a numeric field over the instances having it
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NumericStats {
+    pub instances: u64,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+// instances bucketed by the value at a field path like "host" or "address.host", each name but
+// the last leading through an object field. largest retained size first, instances without
+// the field are left out
+pub fn group_by_field(
+    heap: &AnalyzedHeap,
+    contents: &Contents,
+    class_ids: &[Id],
+    path: &str,
+) -> Result<Vec<FieldBucket>> {
+    let dominator_tree = heap.dominator_tree();
+    let mut buckets: HashMap<String, (u64, u64)> = HashMap::new();
+
+    for class_id in class_ids {
+        for instance in heap.instances_of(*class_id) {
+            let Some(value) = field_at(heap, contents, instance.id, path)? else {
+                continue;
+            };
+            let bucket = buckets.entry(describe(heap, contents, value)?).or_default();
+            bucket.0 += 1;
+            bucket.1 += dominator_tree.retained_size(instance.id).unwrap_or(0);
+        }
+    }
+
+    let mut buckets: Vec<FieldBucket> = buckets
+        .into_iter()
+        .map(|(value, (instances, retained_size))| FieldBucket {
+            value,
+            instances,
+            retained_size,
+        })
+        .collect();
+    buckets.sort_by(|a, b| {
+        b.retained_size
+            .cmp(&a.retained_size)
+            .then_with(|| b.instances.cmp(&a.instances))
+            .then_with(|| a.value.cmp(&b.value))
+    });
+    Ok(buckets)
+}
+
+// min, max, mean and nearest rank percentiles of a numeric field at a path like in
+// group_by_field, chars count as their code unit. None when no instance has a numeric value
+// there
+pub fn numeric_stats(
+    heap: &AnalyzedHeap,
+    contents: &Contents,
+    class_ids: &[Id],
+    path: &str,
+) -> Result<Option<NumericStats>> {
+    let mut values = Vec::new();
+    for class_id in class_ids {
+        for instance in heap.instances_of(*class_id) {
+            let value = match field_at(heap, contents, instance.id, path)? {
+                Some(FieldValue::Byte(v)) => v as f64,
+                Some(FieldValue::Short(v)) => v as f64,
+                Some(FieldValue::Char(v)) => v as f64,
+                Some(FieldValue::Int(v)) => v as f64,
+                Some(FieldValue::Long(v)) => v as f64,
+                Some(FieldValue::Float(v)) => v as f64,
+                Some(FieldValue::Double(v)) => v,
+                _ => continue,
+            };
+            values.push(value);
+        }
+    }
+    if values.is_empty() {
+        return Ok(None);
+    }
+
+    values.sort_by(f64::total_cmp);
+    let percentile = |p: f64| {
+        let rank = ((p * values.len() as f64).ceil() as usize).clamp(1, values.len());
+        values[rank - 1]
+    };
+    Ok(Some(NumericStats {
+        instances: values.len() as u64,
+        min: values[0],
+        max: values[values.len() - 1],
+        mean: values.iter().sum::<f64>() / values.len() as f64,
+        p50: percentile(0.5),
+        p90: percentile(0.9),
+        p99: percentile(0.99),
+    }))
+}
+
+// None when a field on the way is missing or null
+pub(crate) fn field_at(
+    heap: &AnalyzedHeap,
+    contents: &Contents,
+    id: Id,
+    path: &str,
+) -> Result<Option<FieldValue>> {
+    let mut value = FieldValue::NormalObject { object_id: id };
+    for name in path.split('.') {
+        let FieldValue::NormalObject { object_id } = value else {
+            return Ok(None);
+        };
+        if object_id.0 == 0 {
+            return Ok(None);
+        }
+        match contents.field(heap, object_id, name)? {
+            Some(next) => value = next,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(value))
+}
+
+// strings by their contents, enum constants by their name and other objects by class and id
+fn describe(heap: &AnalyzedHeap, contents: &Contents, value: FieldValue) -> Result<String> {
+    let object_id = match value {
+        FieldValue::NormalObject { object_id } => object_id,
+        FieldValue::Boolean(v) => return Ok(v.to_string()),
+        FieldValue::Char(v) => {
+            return Ok(match char::from_u32(v as u32) {
+                Some(c) => format!("{:?}", c),
+                None => format!("\\u{:04x}", v),
+            });
+        }
+        FieldValue::Float(v) => return Ok(format!("{:?}", v)),
+        FieldValue::Double(v) => return Ok(format!("{:?}", v)),
+        FieldValue::Byte(v) => return Ok(v.to_string()),
+        FieldValue::Short(v) => return Ok(v.to_string()),
+        FieldValue::Int(v) => return Ok(v.to_string()),
+        FieldValue::Long(v) => return Ok(v.to_string()),
+    };
+    if object_id.0 == 0 {
+        return Ok("null".to_string());
+    }
+    if let Some(content) = contents.string_value(heap, object_id)? {
+        return Ok(format!("{:?}", content));
+    }
+
+    let class_name = heap.class_name_of(object_id).unwrap_or_default();
+    Ok(match enum_name(heap, contents, object_id)? {
+        Some(name) => format!("{}.{}", class_name, name),
+        None => format!("{} @ {}", class_name, object_id),
+    })
+}
+
+// the name of an enum constant, None for other objects
+pub(crate) fn enum_name(
+    heap: &AnalyzedHeap,
+    contents: &Contents,
+    id: Id,
+) -> Result<Option<String>> {
+    let is_enum = heap
+        .class_of(id)
+        .and_then(|class| heap.superclass(class.id))
+        .is_some_and(|superclass| &*superclass.name == "java/lang/Enum");
+    match contents.field(heap, id, "name")? {
+        Some(FieldValue::NormalObject { object_id: name }) if is_enum => {
+            contents.string_value(heap, name)
+        }
+        _ => Ok(None),
+    }
+}
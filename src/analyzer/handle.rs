@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::{HeapError, Result},
+    parser::Id,
+};
+
+// dense index of an object in the per-object tables of AnalyzedHeap. instances come first in
+// dump order, followed by class objects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Handle(pub u32);
+
+impl Handle {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+// the only map keyed by object id, everything else is looked up by handle
+#[derive(Default)]
+pub struct Handles {
+    ids: Vec<Id>,
+    index: HashMap<Id, Handle>,
+}
+
+impl Handles {
+    // the existing handle if the id was seen before
+    pub fn insert(&mut self, id: Id) -> Result<Handle> {
+        if let Some(handle) = self.index.get(&id) {
+            return Ok(*handle);
+        }
+        let handle = Handle(u32::try_from(self.ids.len()).map_err(|_| HeapError::TooManyObjects)?);
+        self.ids.push(id);
+        self.index.insert(id, handle);
+        Ok(handle)
+    }
+
+    pub fn get(&self, id: Id) -> Option<Handle> {
+        self.index.get(&id).copied()
+    }
+
+    pub fn id(&self, handle: Handle) -> Id {
+        self.ids[handle.index()]
+    }
+
+    pub fn ids(&self) -> &[Id] {
+        &self.ids
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
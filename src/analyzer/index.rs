@@ -0,0 +1,619 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    analyzer::{
+        AnalyzedHeap, Class, ClassLayout, Frame, Instances, Lazy, Thread,
+        diagnostic::Diagnostic,
+        dominator::DominatorTree,
+        graph::{GcRoot, References, RootKind},
+        handle::{Handle, Handles},
+        insert_layout,
+        options::AnalysisOptions,
+        size::SizeModel,
+        storage::Storage,
+        strings::Interner,
+    },
+    error::Policy,
+    parser::{
+        FrameId, Header, Id, StringId, Version, sub_record::FieldDescriptor, timestamp_from_millis,
+        timestamp_millis,
+    },
+    trace::{debug, debug_span, warn},
+};
+
+const MAGIC: &[u8; 8] = b"HDAINDEX";
+const SNAPSHOT_MAGIC: &[u8; 8] = b"HDASNAPS";
+// bumped whenever the layout below changes, older indexes are rebuilt. snapshots share it
+pub const INDEX_VERSION: u32 = 5;
+pub const INDEX_EXTENSION: &str = "hda-index";
+
+// everything the analysis commands need from a dump, cached in a sidecar file next to it. the
+// dominator tree is only stored once a command needed it
+pub struct HeapIndex {
+    pub header: Header,
+    pub heap: AnalyzedHeap,
+}
+
+// identifies the dump and size model an index was built from
+#[derive(Debug, PartialEq, Eq)]
+struct Fingerprint {
+    dump_size: u64,
+    // nanoseconds since the unix epoch
+    dump_modified: u128,
+    size_model: SizeModel,
+}
+
+impl Fingerprint {
+    fn of(dump: &Path, size_model: SizeModel) -> Result<Self> {
+        let metadata = std::fs::metadata(dump)
+            .with_context(|| format!("failed to read metadata of {}", dump.display()))?;
+        Ok(Self {
+            dump_size: metadata.len(),
+            dump_modified: metadata.modified()?.duration_since(UNIX_EPOCH)?.as_nanos(),
+            size_model,
+        })
+    }
+}
+
+// heap.hprof -> heap.hprof.hda-index
+pub fn index_path(dump: &Path) -> PathBuf {
+    let mut path = dump.as_os_str().to_owned();
+    path.push(".");
+    path.push(INDEX_EXTENSION);
+    PathBuf::from(path)
+}
+
+impl HeapIndex {
+    pub fn build(dump: &Path, options: &AnalysisOptions) -> Result<Self> {
+        let _span = debug_span!("index_build", dump = %dump.display()).entered();
+        let (header, heap) = AnalyzedHeap::analyze_file_with(dump, options)?;
+        Ok(Self { header, heap })
+    }
+
+    // loads the sidecar index if it matches the dump, otherwise analyzes the dump and writes a
+    // new one. with dominators, an index without the dominator tree is extended by it. failing
+    // to write the index isn't an error, the dump may be on a read only mount. a strict policy
+    // always analyzes the dump, an index doesn't keep what lenient parsing skipped
+    pub fn open(dump: &Path, options: &AnalysisOptions) -> Result<Self> {
+        let path = index_path(dump);
+        let loaded = match options.policy {
+            Policy::Strict => Ok(None),
+            Policy::Lenient => Self::load(dump, options.size_model, &options.storage),
+        };
+        match loaded {
+            Ok(Some(index)) if !options.dominators || index.heap.has_dominator_tree() => {
+                options.apply(&index.heap);
+                return Ok(index);
+            }
+            Ok(Some(index)) => {
+                options.apply(&index.heap);
+                if let Err(err) = index.save(dump) {
+                    warn!("failed to write index {}: {:#}", path.display(), err);
+                }
+                return Ok(index);
+            }
+            Ok(None) => {}
+            Err(err) => warn!("ignoring unreadable index {}: {:#}", path.display(), err),
+        }
+
+        let index = Self::build(dump, options)?;
+        if let Err(err) = index.save(dump) {
+            warn!("failed to write index {}: {:#}", path.display(), err);
+        }
+        Ok(index)
+    }
+
+    // None when there is no index or it was built from a different dump, size model or version
+    pub fn load(dump: &Path, size_model: SizeModel, storage: &Storage) -> Result<Option<Self>> {
+        let path = index_path(dump);
+        let _span = debug_span!("index_load", index = %path.display()).entered();
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let mut r = Decoder(BufReader::with_capacity(1 << 20, file));
+
+        let mut magic = [0; 8];
+        r.0.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            bail!("not an index file");
+        }
+        let version = r.u32()?;
+        if version != INDEX_VERSION {
+            debug!(
+                "index {} has version {}, rebuilding",
+                path.display(),
+                version
+            );
+            return Ok(None);
+        }
+        if r.fingerprint()? != Fingerprint::of(dump, size_model)? {
+            debug!("index {} is stale, rebuilding", path.display());
+            return Ok(None);
+        }
+
+        Ok(Some(r.index(size_model, storage)?))
+    }
+
+    // written to a temporary file first, so a concurrent reader never sees half an index
+    pub fn save(&self, dump: &Path) -> Result<()> {
+        let path = index_path(dump);
+        let _span = debug_span!("index_save", index = %path.display()).entered();
+        let fingerprint = Fingerprint::of(dump, self.heap.size_model)?;
+        write_atomically(&path, |w| {
+            w.0.write_all(MAGIC)?;
+            w.u32(INDEX_VERSION)?;
+            w.fingerprint(&fingerprint)?;
+            w.index(self)
+        })
+    }
+
+    // the same analysis as the sidecar index, but standing on its own: not tied to the dump it
+    // was built from, so it can be analyzed on one machine and queried in another process.
+    // field values and array contents still need the dump
+    pub fn save_snapshot(&self, path: &Path) -> Result<()> {
+        let _span = debug_span!("snapshot_save", snapshot = %path.display()).entered();
+        write_atomically(path, |w| {
+            w.0.write_all(SNAPSHOT_MAGIC)?;
+            w.u32(INDEX_VERSION)?;
+            w.size_model(&self.heap.size_model)?;
+            w.index(self)
+        })
+    }
+
+    pub fn load_snapshot(path: &Path, storage: &Storage) -> Result<Self> {
+        let _span = debug_span!("snapshot_load", snapshot = %path.display()).entered();
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let mut r = Decoder(BufReader::with_capacity(1 << 20, file));
+
+        let mut magic = [0; 8];
+        r.0.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC {
+            bail!("{} is not a snapshot", path.display());
+        }
+        let version = r.u32()?;
+        if version != INDEX_VERSION {
+            bail!(
+                "snapshot {} has version {}, this version reads {}",
+                path.display(),
+                version,
+                INDEX_VERSION
+            );
+        }
+        let size_model = r.size_model()?;
+        r.index(size_model, storage)
+    }
+}
+
+fn write_atomically(
+    path: &Path,
+    f: impl FnOnce(&mut Encoder<BufWriter<File>>) -> Result<()>,
+) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let file = File::create(&tmp).with_context(|| format!("failed to create {}", tmp.display()))?;
+    let mut w = Encoder(BufWriter::with_capacity(1 << 20, file));
+    f(&mut w)?;
+    w.0.flush()?;
+    drop(w);
+
+    std::fs::rename(&tmp, path).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(())
+}
+
+struct Encoder<W>(W);
+
+impl<W: Write> Encoder<W> {
+    fn u8(&mut self, v: u8) -> Result<()> {
+        self.0.write_all(&[v])?;
+        Ok(())
+    }
+
+    fn u32(&mut self, v: u32) -> Result<()> {
+        self.0.write_all(&v.to_le_bytes())?;
+        Ok(())
+    }
+
+    fn u64(&mut self, v: u64) -> Result<()> {
+        self.0.write_all(&v.to_le_bytes())?;
+        Ok(())
+    }
+
+    fn id(&mut self, id: &Id) -> Result<()> {
+        self.u64(id.0)
+    }
+
+    fn len(&mut self, len: usize) -> Result<()> {
+        self.u64(len as u64)
+    }
+
+    fn str(&mut self, s: &str) -> Result<()> {
+        self.len(s.len())?;
+        self.0.write_all(s.as_bytes())?;
+        Ok(())
+    }
+
+    fn slice<T>(&mut self, items: &[T], f: fn(&mut Self, T) -> Result<()>) -> Result<()>
+    where
+        T: Copy,
+    {
+        self.len(items.len())?;
+        for item in items {
+            f(self, *item)?;
+        }
+        Ok(())
+    }
+
+    fn ids(&mut self, ids: &[Id]) -> Result<()> {
+        self.len(ids.len())?;
+        for id in ids {
+            self.id(id)?;
+        }
+        Ok(())
+    }
+
+    fn fingerprint(&mut self, fingerprint: &Fingerprint) -> Result<()> {
+        self.u64(fingerprint.dump_size)?;
+        self.0.write_all(&fingerprint.dump_modified.to_le_bytes())?;
+        self.size_model(&fingerprint.size_model)
+    }
+
+    fn size_model(&mut self, size_model: &SizeModel) -> Result<()> {
+        self.u64(size_model.object_header)?;
+        self.u64(size_model.array_header)?;
+        self.u64(size_model.reference_size)?;
+        self.u64(size_model.alignment)
+    }
+
+    // everything after the fingerprint
+    fn index(&mut self, index: &HeapIndex) -> Result<()> {
+        self.u64(timestamp_millis(&index.header.timestamp))?;
+        self.heap(&index.heap)?;
+        match index.heap.lazy.dominator_tree.get() {
+            Some(dominator_tree) => {
+                self.u8(1)?;
+                let (nodes, idom, retained) = dominator_tree.parts();
+                self.slice(nodes, Encoder::u32)?;
+                self.slice(idom, Encoder::u32)?;
+                self.slice(retained, Encoder::u64)
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn heap(&mut self, heap: &AnalyzedHeap) -> Result<()> {
+        self.len(heap.strings.len())?;
+        for (id, content) in &heap.strings {
+            self.u64(id.0)?;
+            self.str(content)?;
+        }
+
+        self.len(heap.classes.len())?;
+        for class in heap.classes.values() {
+            self.id(&class.id)?;
+            self.str(&class.name)?;
+        }
+
+        self.len(heap.frames.len())?;
+        for frame in &heap.frames {
+            self.u64(frame.id.0)?;
+            self.str(&frame.method_name)?;
+            self.str(&frame.method_signature)?;
+            self.str(&frame.source_file_name)?;
+            self.u32(frame.class_serial_number)?;
+            self.u32(frame.line_number as u32)?;
+        }
+
+        self.len(heap.threads.len())?;
+        for thread in &heap.threads {
+            self.id(&thread.object_id)?;
+            self.u32(thread.serial_number)?;
+            self.slice(&thread.stack_frame_ids, |w, id| w.u64(id.0))?;
+        }
+
+        self.ids(heap.handles.ids())?;
+
+        // in handle order, the class is restored from the class table
+        self.len(heap.instances.len())?;
+        let columns = heap.instances.classes().iter();
+        for (class, size) in columns.zip(heap.instances.shallow_sizes()) {
+            self.u32(*class)?;
+            self.u64(*size)?;
+        }
+
+        self.len(heap.layouts.len())?;
+        for (class_id, layout) in &heap.layouts {
+            self.id(class_id)?;
+            self.id(&layout.super_class_id.unwrap_or(Id(0)))?;
+            self.len(layout.instance_fields.len())?;
+            for field in &layout.instance_fields {
+                self.u64(field.name_id.0)?;
+                self.u8(field.typ)?;
+            }
+        }
+
+        self.len(heap.references.len())?;
+        for handle in 0..heap.references.len() {
+            self.ids(heap.references.get(Handle(handle as u32)))?;
+        }
+
+        self.len(heap.roots.len())?;
+        for root in &heap.roots {
+            self.id(&root.object_id)?;
+            self.u8(root_kind_tag(root.kind))?;
+        }
+
+        self.len(heap.diagnostics.len())?;
+        for diagnostic in &heap.diagnostics {
+            let (tag, id, count) = match *diagnostic {
+                Diagnostic::MissingString { id } => (0, id.0, 0),
+                Diagnostic::MissingClass { id, objects } => (1, id.0, objects),
+                Diagnostic::MissingClassDump { id, instances } => (2, id.0, instances),
+                Diagnostic::MalformedInstances { id, instances } => (3, id.0, instances),
+                Diagnostic::SuperclassCycle { id } => (4, id.0, 0),
+                Diagnostic::MissingArrayClass { typ, arrays } => (5, typ as u64, arrays),
+            };
+            self.u8(tag)?;
+            self.u64(id)?;
+            self.u64(count)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Decoder<R>(R);
+
+impl<R: Read> Decoder<R> {
+    fn u8(&mut self) -> Result<u8> {
+        let mut buf = [0; 1];
+        self.0.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let mut buf = [0; 4];
+        self.0.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let mut buf = [0; 8];
+        self.0.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    fn id(&mut self) -> Result<Id> {
+        Ok(Id(self.u64()?))
+    }
+
+    fn len(&mut self) -> Result<usize> {
+        Ok(self.u64()? as usize)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let mut buf = vec![0; self.len()?];
+        self.0.read_exact(&mut buf)?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    fn vec<T>(&mut self, f: fn(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        let len = self.len()?;
+        let mut items = Vec::with_capacity(len);
+        for _ in 0..len {
+            items.push(f(self)?);
+        }
+        Ok(items)
+    }
+
+    fn ids(&mut self) -> Result<Vec<Id>> {
+        self.vec(Self::id)
+    }
+
+    fn fingerprint(&mut self) -> Result<Fingerprint> {
+        let dump_size = self.u64()?;
+        let mut modified = [0; 16];
+        self.0.read_exact(&mut modified)?;
+        Ok(Fingerprint {
+            dump_size,
+            dump_modified: u128::from_le_bytes(modified),
+            size_model: self.size_model()?,
+        })
+    }
+
+    fn size_model(&mut self) -> Result<SizeModel> {
+        Ok(SizeModel {
+            object_header: self.u64()?,
+            array_header: self.u64()?,
+            reference_size: self.u64()?,
+            alignment: self.u64()?,
+        })
+    }
+
+    fn index(&mut self, size_model: SizeModel, storage: &Storage) -> Result<HeapIndex> {
+        let header = Header {
+            version: Version::JavaProfile102,
+            timestamp: timestamp_from_millis(self.u64()?).context("invalid timestamp")?,
+        };
+        let heap = self.heap(size_model, storage)?;
+        if self.u8()? != 0 {
+            let dominator_tree = DominatorTree::from_parts(
+                heap.handles.clone(),
+                self.vec(Decoder::u32)?,
+                self.vec(Decoder::u32)?,
+                self.vec(Decoder::u64)?,
+            );
+            let _ = heap.lazy.dominator_tree.set(dominator_tree);
+        }
+        Ok(HeapIndex { header, heap })
+    }
+
+    fn heap(&mut self, size_model: SizeModel, storage: &Storage) -> Result<AnalyzedHeap> {
+        // strings and the names using them are shared again, like after analyzing the dump
+        let mut interner = Interner::default();
+        let mut strings: HashMap<StringId, Arc<str>> = HashMap::new();
+        for _ in 0..self.len()? {
+            strings.insert(StringId(self.u64()?), interner.intern(&self.string()?));
+        }
+        let mut intern = |name: String| interner.intern(&name);
+
+        let mut classes = HashMap::new();
+        for _ in 0..self.len()? {
+            let id = self.id()?;
+            classes.insert(
+                id,
+                Class {
+                    id,
+                    name: intern(self.string()?),
+                },
+            );
+        }
+
+        let mut frames = Vec::new();
+        for _ in 0..self.len()? {
+            frames.push(Frame {
+                id: FrameId(self.u64()?),
+                method_name: intern(self.string()?),
+                method_signature: intern(self.string()?),
+                source_file_name: intern(self.string()?),
+                class_serial_number: self.u32()?,
+                line_number: self.u32()? as i32,
+            });
+        }
+
+        let threads = self.vec(|r| {
+            Ok(Thread {
+                object_id: r.id()?,
+                serial_number: r.u32()?,
+                stack_frame_ids: r.vec(|r| Ok(FrameId(r.u64()?)))?,
+            })
+        })?;
+
+        let mut handles = Handles::default();
+        for id in self.ids()? {
+            handles.insert(id)?;
+        }
+
+        let mut instances = Instances::new(storage)?;
+        for _ in 0..self.len()? {
+            let class = Handle(self.u32()?);
+            let class_id = *handles
+                .ids()
+                .get(class.index())
+                .context("class without handle")?;
+            if !classes.contains_key(&class_id) {
+                bail!("class not found");
+            }
+            instances.push(class, self.u64()?)?;
+        }
+        if instances.len() > handles.len() {
+            bail!("instance without handle");
+        }
+
+        let mut layouts = HashMap::new();
+        for _ in 0..self.len()? {
+            let class_id = self.id()?;
+            let super_class_id = self.id()?;
+            let instance_fields = self.vec(|r| {
+                Ok(FieldDescriptor {
+                    name_id: StringId(r.u64()?),
+                    typ: r.u8()?,
+                })
+            })?;
+            insert_layout(
+                &mut layouts,
+                class_id,
+                ClassLayout {
+                    super_class_id: (super_class_id.0 != 0).then_some(super_class_id),
+                    instance_fields,
+                },
+            );
+        }
+
+        let mut references = References::new(storage)?;
+        for handle in 0..self.len()? {
+            references.set(Handle(handle as u32), &self.ids()?)?;
+        }
+
+        let roots = self.vec(|r| {
+            Ok(GcRoot {
+                object_id: r.id()?,
+                kind: root_kind(r.u8()?)?,
+            })
+        })?;
+
+        let diagnostics = self.vec(|r| {
+            let (tag, id, count) = (r.u8()?, r.u64()?, r.u64()?);
+            Ok(match tag {
+                0 => Diagnostic::MissingString { id: StringId(id) },
+                1 => Diagnostic::MissingClass {
+                    id: Id(id),
+                    objects: count,
+                },
+                2 => Diagnostic::MissingClassDump {
+                    id: Id(id),
+                    instances: count,
+                },
+                3 => Diagnostic::MalformedInstances {
+                    id: Id(id),
+                    instances: count,
+                },
+                4 => Diagnostic::SuperclassCycle { id: Id(id) },
+                5 => Diagnostic::MissingArrayClass {
+                    typ: id as u8,
+                    arrays: count,
+                },
+                _ => bail!("invalid diagnostic: {}", tag),
+            })
+        })?;
+
+        Ok(AnalyzedHeap {
+            strings,
+            classes,
+            frames,
+            threads,
+            handles: Arc::new(handles),
+            instances,
+            layouts,
+            references,
+            roots,
+            size_model,
+            diagnostics,
+            lazy: Lazy::default(),
+        })
+    }
+}
+
+fn root_kind_tag(kind: RootKind) -> u8 {
+    match kind {
+        RootKind::JniGlobal => 0,
+        RootKind::JniLocal => 1,
+        RootKind::JavaFrame => 2,
+        RootKind::StickyClass => 3,
+        RootKind::ThreadObject => 4,
+    }
+}
+
+fn root_kind(tag: u8) -> Result<RootKind> {
+    match tag {
+        0 => Ok(RootKind::JniGlobal),
+        1 => Ok(RootKind::JniLocal),
+        2 => Ok(RootKind::JavaFrame),
+        3 => Ok(RootKind::StickyClass),
+        4 => Ok(RootKind::ThreadObject),
+        _ => bail!("invalid root kind: {}", tag),
+    }
+}
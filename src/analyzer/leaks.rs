@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analyzer::{AnalyzedHeap, Class, HistogramEntry, caches::CacheInfo, dominator::DominatorTree},
+    output::human_count,
+    parser::Id,
+};
+
+// share of the reachable heap a suspect has to retain
+pub const DEFAULT_THRESHOLD: f64 = 0.10;
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SuspectKind {
+    // a single object, e.g. a cache map, retaining a big part of the heap
+    Object { object_id: Id },
+    // many instances of one class that together retain a big part of the heap
+    Class { instance_count: u64 },
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LeakSuspect {
+    pub kind: SuspectKind,
+    pub class: Class,
+    pub retained_size: u64,
+    pub fraction: f64,
+    // set for object suspects looking like caches, see caches::annotate_caches
+    pub cache: Option<CacheInfo>,
+}
+
+impl LeakSuspect {
+    // None unless the suspect is a cache counting its entries
+    pub fn average_entry_size(&self) -> Option<u64> {
+        self.cache.as_ref()?.average_entry_size(self.retained_size)
+    }
+
+    pub fn describe(&self) -> String {
+        match self.kind {
+            SuspectKind::Object { object_id } => match &self.cache {
+                Some(cache) => format!(
+                    "{} @ {} (cache: {})",
+                    self.class.java_name(),
+                    object_id,
+                    cache.hint
+                ),
+                None => format!("{} @ {}", self.class.java_name(), object_id),
+            },
+            SuspectKind::Class { instance_count } => format!(
+                "{} instances of {}",
+                human_count(instance_count),
+                self.class.java_name()
+            ),
+        }
+    }
+}
+
+// suspects sorted by retained size, then class id and object id. objects dominated by another suspect object are skipped
+pub fn leak_suspects(
+    heap: &AnalyzedHeap,
+    dominator_tree: &DominatorTree,
+    threshold: f64,
+) -> Vec<LeakSuspect> {
+    let reachable = dominator_tree.reachable_size();
+    if reachable == 0 {
+        return Vec::new();
+    }
+    let is_suspect = |size: u64| size as f64 / reachable as f64 >= threshold;
+
+    let mut suspects = Vec::new();
+
+    // class objects aren't reported, so they don't hide the objects they dominate either
+    let is_instance = |id: Id| heap.instance(id).is_some();
+    for object_id in dominator_tree.outermost(is_suspect, is_instance) {
+        let (Some(instance), Some(retained)) = (
+            heap.instance(object_id),
+            dominator_tree.retained_size(object_id),
+        ) else {
+            continue;
+        };
+        suspects.push(LeakSuspect {
+            kind: SuspectKind::Object { object_id },
+            class: instance.class.clone(),
+            retained_size: retained,
+            fraction: retained as f64 / reachable as f64,
+            cache: None,
+        });
+    }
+
+    // what's retained by suspect objects is already explained by them
+    let excluded: HashSet<Id> = suspects
+        .iter()
+        .filter_map(|s| match s.kind {
+            SuspectKind::Object { object_id } => Some(object_id),
+            SuspectKind::Class { .. } => None,
+        })
+        .collect();
+
+    let histogram: HashMap<Id, HistogramEntry> = heap
+        .histogram(&Default::default())
+        .into_iter()
+        .map(|entry| (entry.class.id, entry))
+        .collect();
+    for (class_id, retained) in dominator_tree.retained_by_class_excluding(heap, &excluded) {
+        if !is_suspect(retained) {
+            continue;
+        }
+        let Some(entry) = histogram.get(&class_id) else {
+            continue;
+        };
+        // a single instance is already reported as an object suspect
+        if entry.instance_count < 2 {
+            continue;
+        }
+
+        suspects.push(LeakSuspect {
+            kind: SuspectKind::Class {
+                instance_count: entry.instance_count,
+            },
+            class: entry.class.clone(),
+            retained_size: retained,
+            fraction: retained as f64 / reachable as f64,
+            cache: None,
+        });
+    }
+
+    suspects.sort_by_key(|s| {
+        let object_id = match s.kind {
+            SuspectKind::Object { object_id } => Some(object_id),
+            SuspectKind::Class { .. } => None,
+        };
+        (std::cmp::Reverse(s.retained_size), s.class.id, object_id)
+    });
+    suspects
+}
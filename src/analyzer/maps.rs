@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+
+use crate::{
+    analyzer::{AnalyzedHeap, contents::Contents, dominator::DominatorTree},
+    error::Result,
+    parser::{Id, sub_record::FieldValue},
+};
+
+// hash maps keeping their buckets in an object array field named "table"
+const HASH_MAPS: [&str; 3] = [
+    "java/util/HashMap",
+    "java/util/concurrent/ConcurrentHashMap",
+    "java/util/Hashtable",
+];
+
+// sets wrapping a HashMap, the map is charged to whoever holds the set
+const MAP_WRAPPERS: [&str; 2] = ["java/util/HashSet", "java/util/LinkedHashSet"];
+
+// the table a map gets by default and keeps when cleared
+const DEFAULT_CAPACITY: u64 = 16;
+const LOAD_FACTOR: f64 = 0.75;
+
+// oversized hash maps of one class held by objects of one class
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapWaste {
+    pub map_class: String,
+    // class of the immediate dominator, None for maps only dominated by the gc roots
+    pub owner_class: Option<String>,
+    pub maps: u64,
+    pub entries: u64,
+    // summed table lengths
+    pub capacity: u64,
+    pub empty_bins: u64,
+    // the slots beyond what a table sized for the entries would need
+    pub wasted: u64,
+}
+
+// HashMap, ConcurrentHashMap and Hashtable instances, including subclasses like LinkedHashMap,
+// with a table larger than their entries need at the default load factor. default sized tables
+// don't count. grouped by map class and the class of their owner, most wasted bytes first
+pub fn map_waste(
+    heap: &AnalyzedHeap,
+    contents: &Contents,
+    dominator_tree: &DominatorTree,
+) -> Result<Vec<MapWaste>> {
+    let maps: Vec<(Id, Id)> = heap
+        .classes
+        .values()
+        .filter(|class| heap.extends_any(class, &HASH_MAPS))
+        .flat_map(|class| heap.instances_of(class.id))
+        .map(|instance| (instance.id, instance.class.id))
+        .collect();
+    let oversized = maps
+        .par_iter()
+        .map(|(id, class_id)| {
+            let Some(FieldValue::NormalObject { object_id: table }) =
+                contents.field(heap, *id, "table")?
+            else {
+                return Ok(None);
+            };
+            let Some(bins) = contents.object_array(heap, table) else {
+                return Ok(None);
+            };
+            let capacity = bins.len() as u64;
+            let empty_bins = bins.iter().filter(|b| b.0 == 0).count() as u64;
+            // ConcurrentHashMap's baseCount misses counts still in its counter cells
+            let entries = contents
+                .collection_size(heap, *id)?
+                .unwrap_or(0)
+                .max(capacity - empty_bins);
+            let needed = needed_capacity(entries);
+            Ok((capacity > needed).then_some((
+                *id,
+                *class_id,
+                entries,
+                capacity,
+                empty_bins,
+                capacity - needed,
+            )))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut groups: HashMap<(Id, Option<String>), MapWaste> = HashMap::new();
+    for (id, class_id, entries, capacity, empty_bins, extra) in oversized.into_iter().flatten() {
+        let owner_class = owner(heap, dominator_tree, id).and_then(|o| heap.class_name_of(o));
+        let group = groups
+            .entry((class_id, owner_class.clone()))
+            .or_insert_with(|| MapWaste {
+                map_class: heap
+                    .class(class_id)
+                    .map(|c| c.java_name())
+                    .unwrap_or_default(),
+                owner_class,
+                maps: 0,
+                entries: 0,
+                capacity: 0,
+                empty_bins: 0,
+                wasted: 0,
+            });
+        group.maps += 1;
+        group.entries += entries;
+        group.capacity += capacity;
+        group.empty_bins += empty_bins;
+        group.wasted += extra * heap.size_model.reference_size;
+    }
+
+    let mut groups: Vec<MapWaste> = groups.into_values().collect();
+    groups.sort_by(|a, b| {
+        b.wasted
+            .cmp(&a.wasted)
+            .then_with(|| a.map_class.cmp(&b.map_class))
+    });
+    Ok(groups)
+}
+
+// the power of two table holding `entries` below the load factor, never below DEFAULT_CAPACITY
+fn needed_capacity(entries: u64) -> u64 {
+    let needed = (entries as f64 / LOAD_FACTOR).ceil() as u64;
+    needed.next_power_of_two().max(DEFAULT_CAPACITY)
+}
+
+// the immediate dominator, skipping sets that only wrap the map
+fn owner(heap: &AnalyzedHeap, dominator_tree: &DominatorTree, id: Id) -> Option<Id> {
+    let mut owner = dominator_tree.immediate_dominator(id)?;
+    while heap
+        .class_of(owner)
+        .is_some_and(|class| MAP_WRAPPERS.contains(&&*class.name))
+    {
+        match dominator_tree.immediate_dominator(owner) {
+            Some(dominator) => owner = dominator,
+            None => break,
+        }
+    }
+    Some(owner)
+}
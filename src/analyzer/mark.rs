@@ -0,0 +1,84 @@
+use crate::{
+    analyzer::{AnalyzedHeap, handle::Handle},
+    trace::debug_span,
+};
+
+// one bit per handle
+#[derive(Clone)]
+pub struct Bitset {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bitset {
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    // false if the handle was already set
+    pub fn insert(&mut self, handle: Handle) -> bool {
+        let (word, bit) = (handle.index() / 64, 1 << (handle.index() % 64));
+        let new = self.words[word] & bit == 0;
+        self.words[word] |= bit;
+        new
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        handle.index() < self.len
+            && self.words[handle.index() / 64] & (1 << (handle.index() % 64)) != 0
+    }
+
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Handle> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros();
+                word &= word - 1;
+                Some(Handle(i as u32 * 64 + bit))
+            })
+        })
+    }
+}
+
+// marks the given objects and everything reachable from them. references to objects missing from
+// the dump are dropped, objects rejected by enter are neither marked nor followed
+pub fn mark(
+    heap: &AnalyzedHeap,
+    from: impl IntoIterator<Item = Handle>,
+    enter: impl Fn(Handle) -> bool,
+) -> Bitset {
+    let _span = debug_span!("mark", objects = heap.handles.len()).entered();
+    let mut marked = Bitset::new(heap.handles.len());
+    let mut stack: Vec<Handle> = from.into_iter().filter(|h| marked.insert(*h)).collect();
+
+    while let Some(handle) = stack.pop() {
+        for reference in heap.references.get(handle) {
+            if let Some(handle) = heap.handle(*reference)
+                && enter(handle)
+                && marked.insert(handle)
+            {
+                stack.push(handle);
+            }
+        }
+    }
+
+    marked
+}
+
+impl AnalyzedHeap {
+    // everything reachable from gc roots
+    pub fn live(&self) -> Bitset {
+        let roots = self.roots.iter().filter_map(|r| self.handle(r.object_id));
+        mark(self, roots, |_| true)
+    }
+}
@@ -0,0 +1,705 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    hash::Hash,
+    path::Path,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    analyzer::{
+        diagnostic::Diagnostic,
+        dominator::DominatorTree,
+        filter::ClassFilter,
+        graph::{Csr, GcRoot, References},
+        handle::{Handle, Handles},
+        options::AnalysisOptions,
+        size::SizeModel,
+        storage::{Column, Storage},
+        stream::StreamingAnalyzer,
+        strings::StringIndex,
+    },
+    error::{HeapError, Policy, Result},
+    parser::{
+        FrameId, Header, Id, ParsedHeap, Record, RecordReader, StringId,
+        sub_record::FieldDescriptor,
+    },
+    trace::{Empty, debug_span, metric},
+};
+
+pub mod budget;
+pub mod caches;
+pub mod contents;
+pub mod dedup;
+pub mod diagnostic;
+pub mod diff;
+pub mod dominator;
+pub mod encoding;
+pub mod filter;
+pub mod graph;
+pub mod group;
+pub mod handle;
+pub mod index;
+pub mod leaks;
+pub mod maps;
+pub mod mark;
+pub mod options;
+pub mod packages;
+pub mod paths;
+pub mod predicate;
+pub mod referrers;
+pub mod resources;
+pub mod retainers;
+pub mod roots;
+pub mod sample;
+pub mod size;
+pub mod sparse;
+pub mod storage;
+pub mod stream;
+pub mod strings;
+pub mod timeline;
+pub mod walk;
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Class {
+    pub id: Id,
+    // shared with the heap's strings, every instance holds a copy of its class
+    pub name: Arc<str>,
+}
+
+impl Class {
+    pub fn java_name(&self) -> String {
+        java_name(&self.name)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassLayout {
+    pub super_class_id: Option<Id>,
+    pub instance_fields: Vec<FieldDescriptor>,
+}
+
+impl ClassLayout {
+    pub fn fields_size(&self, size_model: &SizeModel) -> u64 {
+        self.instance_fields
+            .iter()
+            .map(|f| size_model.field_size(f.typ))
+            .sum()
+    }
+}
+
+// an instance or array, borrowed from the heap's columns
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Instance<'a> {
+    pub id: Id,
+    pub class: &'a Class,
+    pub shallow_size: u64,
+}
+
+// per-object columns of instances and arrays, indexed by handle. the first edge of each object
+// is kept with its references
+pub struct Instances {
+    // handle of the class object
+    classes: Column<u32>,
+    shallow_sizes: Column<u64>,
+}
+
+impl Instances {
+    pub fn memory() -> Self {
+        Self {
+            classes: Column::memory(),
+            shallow_sizes: Column::memory(),
+        }
+    }
+
+    pub fn new(storage: &Storage) -> Result<Self> {
+        Ok(Self {
+            classes: Column::new(storage)?,
+            shallow_sizes: Column::new(storage)?,
+        })
+    }
+
+    pub(crate) fn from_parts(classes: Column<u32>, shallow_sizes: Column<u64>) -> Self {
+        Self {
+            classes,
+            shallow_sizes,
+        }
+    }
+
+    pub fn push(&mut self, class: Handle, shallow_size: u64) -> Result<()> {
+        self.classes.push(class.0)?;
+        self.shallow_sizes.push(shallow_size)
+    }
+
+    pub fn class(&self, handle: Handle) -> Handle {
+        Handle(self.classes[handle.index()])
+    }
+
+    pub fn shallow_size(&self, handle: Handle) -> u64 {
+        self.shallow_sizes[handle.index()]
+    }
+
+    pub fn classes(&self) -> &[u32] {
+        &self.classes
+    }
+
+    pub fn shallow_sizes(&self) -> &[u64] {
+        &self.shallow_sizes
+    }
+
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistogramEntry {
+    pub class: Class,
+    pub instance_count: u64,
+    pub shallow_size: u64,
+}
+
+impl HistogramEntry {
+    // largest shallow size first, ties broken by class name and then class id so histograms
+    // and everything built from them come out the same on every run
+    pub fn order(a: &Self, b: &Self) -> Ordering {
+        b.shallow_size
+            .cmp(&a.shallow_size)
+            .then_with(|| a.class.name.cmp(&b.class.name))
+            .then_with(|| a.class.id.cmp(&b.class.id))
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Frame {
+    pub id: FrameId,
+    pub method_name: Arc<str>,
+    pub method_signature: Arc<str>,
+    pub source_file_name: Arc<str>,
+    pub class_serial_number: u32,
+    pub line_number: i32,
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}:{}",
+            self.source_file_name, self.method_name, self.line_number
+        )
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Thread {
+    pub object_id: Id,
+    pub serial_number: u32,
+    // frame ids of the thread's stack trace, innermost frame first
+    pub stack_frame_ids: Vec<FrameId>,
+}
+
+pub struct AnalyzedHeap {
+    pub strings: HashMap<StringId, Arc<str>>,
+    pub classes: HashMap<Id, Class>,
+    pub frames: Vec<Frame>,
+    pub threads: Vec<Thread>,
+    // shared with the dominator tree
+    pub handles: Arc<Handles>,
+    // indexed by handle, class objects have the handles after the last instance. every class
+    // of an instance has a handle, even without a class dump
+    pub instances: Instances,
+    pub layouts: HashMap<Id, ClassLayout>,
+    // outgoing references of instances, arrays and class objects, indexed by handle
+    pub references: References,
+    pub roots: Vec<GcRoot>,
+    pub size_model: SizeModel,
+    // what the dump was missing, placeholders stand in for it
+    pub diagnostics: Vec<Diagnostic>,
+    lazy: Lazy,
+}
+
+// `serve`, grpc and mcp share one heap behind an Arc between the threads answering queries
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<AnalyzedHeap>();
+};
+
+// analyses computed on first use, commands that only need the histogram never pay for them
+#[derive(Default)]
+struct Lazy {
+    dominator_tree: OnceLock<DominatorTree>,
+    string_index: OnceLock<StringIndex>,
+    duplicate_strings: OnceLock<Vec<Vec<StringId>>>,
+    retained_by_class: OnceLock<HashMap<Id, u64>>,
+    dominator_children: OnceLock<HashMap<Id, Vec<Id>>>,
+    // incoming references by handle
+    referrers: OnceLock<Csr>,
+    // instance handles by class handle
+    instances_by_class: OnceLock<Csr>,
+    // positions in frames and threads, the first one where ids repeat
+    frames_by_id: OnceLock<HashMap<FrameId, usize>>,
+    threads_by_serial: OnceLock<HashMap<u32, usize>>,
+    threads_by_object: OnceLock<HashMap<Id, usize>>,
+    gc_roots: OnceLock<HashSet<Id>>,
+}
+
+impl AnalyzedHeap {
+    pub fn analyze(parsed_heap: &ParsedHeap) -> Result<Self> {
+        Self::analyze_with(parsed_heap, SizeModel::default())
+    }
+
+    pub fn analyze_with(parsed_heap: &ParsedHeap, size_model: SizeModel) -> Result<Self> {
+        let mut analyzer = StreamingAnalyzer::new(size_model, &Storage::Memory)?;
+        for record in &parsed_heap.records {
+            analyzer.record(record)?;
+        }
+        analyzer.finish()
+    }
+
+    // analyzes records as they are parsed, without keeping them around
+    pub fn analyze_stream(
+        records: impl IntoIterator<Item = Result<Record>>,
+        size_model: SizeModel,
+        storage: &Storage,
+    ) -> Result<Self> {
+        Self::analyze_stream_with(records, size_model, storage, Policy::default())
+    }
+
+    fn analyze_stream_with(
+        records: impl IntoIterator<Item = Result<Record>>,
+        size_model: SizeModel,
+        storage: &Storage,
+        policy: Policy,
+    ) -> Result<Self> {
+        // parsing happens inside the iterator, so its time is taken around next
+        let span = debug_span!("analyze", records = Empty, parse_ms = Empty).entered();
+        let mut analyzer = StreamingAnalyzer::new(size_model, storage)?.with_policy(policy);
+        let mut records = records.into_iter();
+        let mut count = 0u64;
+        let mut parse_time = Duration::ZERO;
+        loop {
+            let start = Instant::now();
+            let Some(record) = records.next() else {
+                break;
+            };
+            parse_time += start.elapsed();
+            analyzer.record(&record?)?;
+            count += 1;
+        }
+        span.record("records", count);
+        span.record("parse_ms", parse_time.as_millis() as u64);
+        analyzer.finish()
+    }
+
+    pub fn analyze_file(
+        path: &Path,
+        size_model: SizeModel,
+        storage: &Storage,
+    ) -> Result<(Header, Self)> {
+        let options = AnalysisOptions::default()
+            .with_size_model(size_model)
+            .with_storage(storage.clone());
+        Self::analyze_file_with(path, &options)
+    }
+
+    // analyze_file with the options' policy, then whatever they ask to compute up front
+    pub fn analyze_file_with(path: &Path, options: &AnalysisOptions) -> Result<(Header, Self)> {
+        let start = Instant::now();
+        let mut records = RecordReader::open(path)?.with_policy(options.policy);
+        let header = records.header;
+        let heap = Self::analyze_stream_with(
+            &mut records,
+            options.size_model,
+            &options.storage,
+            options.policy,
+        )?;
+        // the whole pass, analysis included, that's what the user waits for
+        let secs = start.elapsed().as_secs_f64();
+        let totals = records.stats().total_records();
+        metric!(
+            "parse",
+            bytes = totals.bytes,
+            records = totals.count,
+            ms = (secs * 1000.0) as u64,
+            mb_per_s = totals.bytes as f64 / (1 << 20) as f64 / secs,
+            records_per_s = totals.count as f64 / secs,
+        );
+        options.apply(&heap);
+        Ok((header, heap))
+    }
+
+    pub fn handle(&self, id: Id) -> Option<Handle> {
+        self.handles.get(id)
+    }
+
+    // None for class objects and ids not in the dump
+    pub fn instance(&self, id: Id) -> Option<Instance<'_>> {
+        let handle = self.handle(id)?;
+        (handle.index() < self.instances.len()).then(|| self.instance_at(handle))
+    }
+
+    // class objects are named "class <name>", None for ids not in the dump
+    pub fn class_name_of(&self, id: Id) -> Option<String> {
+        match self.instance(id) {
+            Some(instance) => Some(instance.class.java_name()),
+            None => self
+                .classes
+                .get(&id)
+                .map(|c| format!("class {}", c.java_name())),
+        }
+    }
+
+    // loaded classes by id, whether or not they have a class dump
+    pub fn class(&self, id: Id) -> Option<&Class> {
+        self.classes.get(&id)
+    }
+
+    // the class of an instance or array, None for class objects and ids not in the dump
+    pub fn class_of(&self, id: Id) -> Option<&Class> {
+        self.instance(id).map(|i| i.class)
+    }
+
+    // the superclass of a dumped class, None for java.lang.Object and interfaces
+    pub fn superclass(&self, class_id: Id) -> Option<&Class> {
+        let super_class_id = self.layouts.get(&class_id)?.super_class_id?;
+        self.class(super_class_id)
+    }
+
+    // whether the class or one of its superclasses has one of the internal names
+    pub fn extends_any(&self, class: &Class, names: &[&str]) -> bool {
+        let mut current = Some(class);
+        while let Some(class) = current {
+            if names.contains(&&*class.name) {
+                return true;
+            }
+            current = self.superclass(class.id);
+        }
+        false
+    }
+
+    // classes with this java name ("java.lang.String", "int[]") or internal name
+    // ("java/lang/String", "[I"), by ascending id. more than one when several class loaders
+    // loaded it
+    pub fn find_classes_by_name(&self, name: &str) -> Vec<&Class> {
+        let mut classes: Vec<&Class> = self
+            .classes
+            .values()
+            .filter(|c| &*c.name == name || c.java_name() == name)
+            .collect();
+        classes.sort_by_key(|c| c.id.0);
+        classes
+    }
+
+    // the first of find_classes_by_name
+    pub fn find_class_by_name(&self, name: &str) -> Option<&Class> {
+        self.find_classes_by_name(name).into_iter().next()
+    }
+
+    // instances of exactly this class, without subclasses
+    pub fn instances_of(&self, class_id: Id) -> impl Iterator<Item = Instance<'_>> {
+        self.instance_handles_of(class_id)
+            .iter()
+            .map(|h| self.instance_at(Handle(*h)))
+    }
+
+    pub fn instance_count(&self, class_id: Id) -> u64 {
+        self.instance_handles_of(class_id).len() as u64
+    }
+
+    // in dump order
+    fn instance_handles_of(&self, class_id: Id) -> &[u32] {
+        let Some(class) = self.handle(class_id) else {
+            return &[];
+        };
+        let instances_by_class = self.lazy.instances_by_class.get_or_init(|| {
+            let classes = self.instances.classes();
+            let edges = (0..classes.len() as u32).map(|h| (classes[h as usize], h));
+            Csr::build(self.handles.len(), edges)
+        });
+        instances_by_class.get(class.0)
+    }
+
+    // content of a utf8 record, like class, field and method names. the contents of
+    // java.lang.String objects are read from the dump by Contents::string_value
+    pub fn string(&self, id: StringId) -> Option<&str> {
+        self.strings.get(&id).map(|s| &**s)
+    }
+
+    pub fn thread_by_serial(&self, serial_number: u32) -> Option<&Thread> {
+        let positions = self
+            .lazy
+            .threads_by_serial
+            .get_or_init(|| positions(self.threads.iter().map(|t| t.serial_number)));
+        positions.get(&serial_number).map(|i| &self.threads[*i])
+    }
+
+    pub fn thread_by_object(&self, object_id: Id) -> Option<&Thread> {
+        let positions = self
+            .lazy
+            .threads_by_object
+            .get_or_init(|| positions(self.threads.iter().map(|t| t.object_id)));
+        positions.get(&object_id).map(|i| &self.threads[*i])
+    }
+
+    pub fn frame(&self, id: FrameId) -> Option<&Frame> {
+        let positions = self
+            .lazy
+            .frames_by_id
+            .get_or_init(|| positions(self.frames.iter().map(|f| f.id)));
+        positions.get(&id).map(|i| &self.frames[*i])
+    }
+
+    // frames of a thread's stack trace, innermost first. frames missing from the dump are left
+    // out
+    pub fn stack_trace(&self, thread: &Thread) -> Vec<&Frame> {
+        thread
+            .stack_frame_ids
+            .iter()
+            .filter_map(|id| self.frame(*id))
+            .collect()
+    }
+
+    pub fn is_gc_root(&self, id: Id) -> bool {
+        self.lazy
+            .gc_roots
+            .get_or_init(|| self.roots.iter().map(|r| r.object_id).collect())
+            .contains(&id)
+    }
+
+    pub fn instance_at(&self, handle: Handle) -> Instance<'_> {
+        Instance {
+            id: self.handles.id(handle),
+            class: self.class_at(self.instances.class(handle)),
+            shallow_size: self.instances.shallow_size(handle),
+        }
+    }
+
+    pub fn iter_instances(&self) -> impl Iterator<Item = Instance<'_>> {
+        (0..self.instances.len() as u32).map(|h| self.instance_at(Handle(h)))
+    }
+
+    // the analyzer only hands out class handles of loaded classes
+    fn class_at(&self, handle: Handle) -> &Class {
+        &self.classes[&self.handles.id(handle)]
+    }
+
+    // None for ids not in the dump
+    pub fn references_of(&self, id: Id) -> Option<&[Id]> {
+        let handle = self.handle(id)?;
+        (handle.index() < self.references.len()).then(|| self.references.get(handle))
+    }
+
+    // see HistogramEntry::order
+    pub fn histogram(&self, filter: &ClassFilter) -> Vec<HistogramEntry> {
+        let totals = self
+            .instances
+            .classes()
+            .par_iter()
+            .zip(self.instances.shallow_sizes())
+            .fold(
+                HashMap::new,
+                |mut totals: HashMap<u32, (u64, u64)>, (class, size)| {
+                    let total = totals.entry(*class).or_default();
+                    total.0 += 1;
+                    total.1 += size;
+                    totals
+                },
+            )
+            .reduce(HashMap::new, |mut a, b| {
+                for (class, (count, size)) in b {
+                    let total = a.entry(class).or_default();
+                    total.0 += count;
+                    total.1 += size;
+                }
+                a
+            });
+
+        let mut entries: Vec<HistogramEntry> = totals
+            .into_iter()
+            .map(|(class, (instance_count, shallow_size))| HistogramEntry {
+                class: self.class_at(Handle(class)).clone(),
+                instance_count,
+                shallow_size,
+            })
+            .filter(|e| filter.matches(&e.class.java_name()))
+            .collect();
+        entries.sort_by(HistogramEntry::order);
+        entries
+    }
+
+    pub fn total_shallow_size(&self) -> u64 {
+        self.instances.shallow_sizes().par_iter().sum()
+    }
+
+    pub fn dominator_tree(&self) -> &DominatorTree {
+        self.lazy
+            .dominator_tree
+            .get_or_init(|| DominatorTree::compute(self))
+    }
+
+    // see DominatorTree::retained_by_class
+    pub fn retained_by_class(&self) -> &HashMap<Id, u64> {
+        self.lazy
+            .retained_by_class
+            .get_or_init(|| self.dominator_tree().retained_by_class(self))
+    }
+
+    // see DominatorTree::children
+    pub fn dominator_children(&self) -> &HashMap<Id, Vec<Id>> {
+        self.lazy
+            .dominator_children
+            .get_or_init(|| self.dominator_tree().children())
+    }
+
+    pub fn has_dominator_tree(&self) -> bool {
+        self.lazy.dominator_tree.get().is_some()
+    }
+
+    pub fn string_index(&self) -> &StringIndex {
+        self.lazy.string_index.get_or_init(|| {
+            let _span = debug_span!("string_index", strings = self.strings.len()).entered();
+            StringIndex::build(self)
+        })
+    }
+
+    pub fn duplicate_strings(&self) -> &[Vec<StringId>] {
+        self.lazy
+            .duplicate_strings
+            .get_or_init(|| self.string_index().duplicates(self))
+    }
+
+    // objects referencing the given one, each once per reference
+    pub fn referrers(&self, handle: Handle) -> impl Iterator<Item = Handle> + '_ {
+        let referrers = self.lazy.referrers.get_or_init(|| {
+            let _span = debug_span!("referrers", objects = self.handles.len()).entered();
+            let edges = (0..self.references.len() as u32).flat_map(|from| {
+                self.references
+                    .get(Handle(from))
+                    .iter()
+                    .filter_map(|id| self.handle(*id))
+                    .map(move |to| (to.0, from))
+            });
+            Csr::build(self.handles.len(), edges)
+        });
+        referrers.get(handle.0).iter().map(|h| Handle(*h))
+    }
+}
+
+// position of each key, the first one of keys that repeat
+fn positions<K: Eq + Hash>(keys: impl Iterator<Item = K>) -> HashMap<K, usize> {
+    let mut positions = HashMap::new();
+    for (i, key) in keys.enumerate() {
+        positions.entry(key).or_insert(i);
+    }
+    positions
+}
+
+// class dumps only list the fields of their own class, so sum up the superclass chain
+pub fn instance_size(
+    class_id: Id,
+    layouts: &HashMap<Id, ClassLayout>,
+    size_model: &SizeModel,
+) -> u64 {
+    let fields_size = superclass_chain(class_id, layouts)
+        .map(|(_, layout)| layout.fields_size(size_model))
+        .sum();
+    size_model.instance_size(fields_size)
+}
+
+// the layouts of a class and its superclasses, the class first. ends at the first class without
+// a class dump. a chain never has more classes than the map, which bounds it should a cycle
+// make it into the map without insert_layout
+pub fn superclass_chain<'a>(
+    class_id: Id,
+    layouts: &'a HashMap<Id, ClassLayout>,
+) -> impl Iterator<Item = (Id, &'a ClassLayout)> + 'a {
+    let mut current = Some(class_id);
+    std::iter::from_fn(move || {
+        let id = current?;
+        let layout = layouts.get(&id)?;
+        current = layout.super_class_id;
+        Some((id, layout))
+    })
+    .take(layouts.len())
+}
+
+// adds a class dump's layout, unless it would make the class its own superclass. broken dumps
+// have been seen with such cycles, the link closing one is dropped so every chain ends. false
+// when it was
+pub fn insert_layout(
+    layouts: &mut HashMap<Id, ClassLayout>,
+    class_id: Id,
+    mut layout: ClassLayout,
+) -> bool {
+    let cycle = layout.super_class_id.is_some_and(|super_class_id| {
+        super_class_id == class_id
+            || superclass_chain(super_class_id, layouts)
+                .any(|(_, layout)| layout.super_class_id == Some(class_id))
+    });
+    if cycle {
+        layout.super_class_id = None;
+    }
+    layouts.insert(class_id, layout);
+    !cycle
+}
+
+pub fn prim_array_name(typ: u8) -> Result<&'static str> {
+    match typ {
+        4 => Ok("[Z"),
+        5 => Ok("[C"),
+        6 => Ok("[F"),
+        7 => Ok("[D"),
+        8 => Ok("[B"),
+        9 => Ok("[S"),
+        10 => Ok("[I"),
+        11 => Ok("[J"),
+        _ => Err(HeapError::InvalidType(typ)),
+    }
+}
+
+pub fn prim_size(typ: u8) -> Result<u64> {
+    match typ {
+        4 | 8 => Ok(1),
+        5 | 9 => Ok(2),
+        6 | 10 => Ok(4),
+        7 | 11 => Ok(8),
+        _ => Err(HeapError::InvalidType(typ)),
+    }
+}
+
+// converts internal class names like "[Ljava/lang/String;" into "java.lang.String[]"
+pub fn java_name(name: &str) -> String {
+    let dimensions = name.chars().take_while(|c| *c == '[').count();
+    if dimensions == 0 {
+        return name.replace('/', ".");
+    }
+
+    let element = &name[dimensions..];
+    let base = match element {
+        "Z" => "boolean".to_string(),
+        "C" => "char".to_string(),
+        "F" => "float".to_string(),
+        "D" => "double".to_string(),
+        "B" => "byte".to_string(),
+        "S" => "short".to_string(),
+        "I" => "int".to_string(),
+        "J" => "long".to_string(),
+        _ => element
+            .trim_start_matches('L')
+            .trim_end_matches(';')
+            .replace('/', "."),
+    };
+
+    format!("{}{}", base, "[]".repeat(dimensions))
+}
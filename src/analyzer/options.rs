@@ -0,0 +1,60 @@
+use crate::{
+    analyzer::{AnalyzedHeap, size::SizeModel, storage::Storage},
+    error::Policy,
+};
+
+// what opening a dump computes up front. the class histogram, references and gc roots are
+// always there, the dominator tree and the string index are otherwise left to their first use:
+//
+//   let options = AnalysisOptions::default()
+//       .with_storage(Storage::Spill(dir))
+//       .with_dominators(true);
+//   let (header, heap) = AnalyzedHeap::analyze_file_with(path, &options)?;
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisOptions {
+    pub size_model: SizeModel,
+    pub storage: Storage,
+    // the most expensive analysis by far, needed for retained sizes and leak suspects
+    pub dominators: bool,
+    // hashes the content of every utf8 record for string lookups and duplicate detection
+    pub string_decode: bool,
+    // for parsing and analyzing alike
+    pub policy: Policy,
+}
+
+impl AnalysisOptions {
+    pub fn with_size_model(mut self, size_model: SizeModel) -> Self {
+        self.size_model = size_model;
+        self
+    }
+
+    pub fn with_storage(mut self, storage: Storage) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    pub fn with_dominators(mut self, dominators: bool) -> Self {
+        self.dominators = dominators;
+        self
+    }
+
+    pub fn with_string_decode(mut self, string_decode: bool) -> Self {
+        self.string_decode = string_decode;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    // computes what was asked for up front on a heap analyzed or loaded with these options
+    pub fn apply(&self, heap: &AnalyzedHeap) {
+        if self.dominators {
+            heap.dominator_tree();
+        }
+        if self.string_decode {
+            heap.duplicate_strings();
+        }
+    }
+}
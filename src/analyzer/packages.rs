@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use crate::{
+    analyzer::{AnalyzedHeap, dominator::DominatorTree, filter::ClassFilter, handle::Handle},
+    parser::Id,
+};
+
+// the object of a package retaining the most
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PackageDominator {
+    pub package: String,
+    pub object_id: Id,
+    pub class_name: String,
+    pub retained_size: u64,
+}
+
+// per package, its instance or array with the largest retained size. arrays count for the
+// package of their element class, primitive arrays and classes without a package are left out.
+// largest retained size first
+pub fn package_dominators(
+    heap: &AnalyzedHeap,
+    dominator_tree: &DominatorTree,
+    filter: &ClassFilter,
+) -> Vec<PackageDominator> {
+    let mut packages: HashMap<Id, Option<String>> = HashMap::new();
+    let mut biggest: HashMap<String, PackageDominator> = HashMap::new();
+    for handle in (0..heap.instances.len() as u32).map(Handle) {
+        let instance = heap.instance_at(handle);
+        let Some(retained_size) = dominator_tree.retained_size(instance.id) else {
+            continue;
+        };
+        let package = packages.entry(instance.class.id).or_insert_with(|| {
+            let name = instance.class.java_name();
+            let package = package_of(&name)?;
+            filter.matches(&name).then(|| package.to_string())
+        });
+        let Some(package) = package.as_deref() else {
+            continue;
+        };
+        if biggest
+            .get(package)
+            .is_some_and(|b| b.retained_size >= retained_size)
+        {
+            continue;
+        }
+        biggest.insert(
+            package.to_string(),
+            PackageDominator {
+                package: package.to_string(),
+                object_id: instance.id,
+                class_name: instance.class.java_name(),
+                retained_size,
+            },
+        );
+    }
+
+    let mut biggest: Vec<PackageDominator> = biggest.into_values().collect();
+    biggest.sort_by(|a, b| {
+        b.retained_size
+            .cmp(&a.retained_size)
+            .then_with(|| a.package.cmp(&b.package))
+    });
+    biggest
+}
+
+// "java.util" for "java.util.HashMap$Node" and "java.util.HashMap[][]", None for primitive
+// arrays and the default package
+pub fn package_of(java_name: &str) -> Option<&str> {
+    let element = java_name.trim_end_matches("[]");
+    element.rsplit_once('.').map(|(package, _)| package)
+}
@@ -0,0 +1,41 @@
+use std::collections::{HashMap, HashSet, VecDeque, hash_map::Entry};
+
+use crate::analyzer::{AnalyzedHeap, handle::Handle};
+
+impl AnalyzedHeap {
+    // shortest chain of references from a gc root to the object, root first. None when no root
+    // reaches it
+    pub fn path_to_root(&self, target: Handle) -> Option<Vec<Handle>> {
+        let roots: HashSet<Handle> = self
+            .roots
+            .iter()
+            .filter_map(|r| self.handle(r.object_id))
+            .collect();
+
+        // breadth first over referrers, remembering the object each one was reached from
+        let mut towards_target: HashMap<Handle, Handle> = HashMap::new();
+        let mut queue = VecDeque::from([target]);
+        towards_target.insert(target, target);
+
+        while let Some(handle) = queue.pop_front() {
+            if roots.contains(&handle) {
+                let mut path = vec![handle];
+                let mut current = handle;
+                while current != target {
+                    current = towards_target[&current];
+                    path.push(current);
+                }
+                return Some(path);
+            }
+
+            for referrer in self.referrers(handle) {
+                if let Entry::Vacant(entry) = towards_target.entry(referrer) {
+                    entry.insert(handle);
+                    queue.push_back(referrer);
+                }
+            }
+        }
+
+        None
+    }
+}
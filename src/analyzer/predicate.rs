@@ -0,0 +1,182 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{Result, bail};
+
+use crate::{
+    analyzer::{
+        AnalyzedHeap,
+        contents::Contents,
+        group::{enum_name, field_at},
+    },
+    parser::{Id, sub_record::FieldValue},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Equals,
+    NotEquals,
+    Contains,
+    StartsWith,
+    EndsWith,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operator::Equals => write!(f, "=="),
+            Operator::NotEquals => write!(f, "!="),
+            Operator::Contains => write!(f, "contains"),
+            Operator::StartsWith => write!(f, "startswith"),
+            Operator::EndsWith => write!(f, "endswith"),
+            Operator::Less => write!(f, "<"),
+            Operator::LessOrEqual => write!(f, "<="),
+            Operator::Greater => write!(f, ">"),
+            Operator::GreaterOrEqual => write!(f, ">="),
+        }
+    }
+}
+
+// a condition on a field like `url contains 'payments'`, `port == 8080` or
+// `address.host startswith db`. strings compare by content, enum constants by name, other
+// objects by id and null by "null". <, <=, > and >= compare numbers
+#[derive(Debug, Clone)]
+pub struct FieldPredicate {
+    // field names separated by dots, see group_by_field
+    pub path: String,
+    pub operator: Operator,
+    pub value: String,
+}
+
+impl FromStr for FieldPredicate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.trim().splitn(3, char::is_whitespace);
+        let (Some(path), Some(operator), Some(value)) = (parts.next(), parts.next(), parts.next())
+        else {
+            bail!("expected <field> <operator> <value>, got {}", s);
+        };
+        let operator = match operator.to_ascii_lowercase().as_str() {
+            "=" | "==" => Operator::Equals,
+            "!=" => Operator::NotEquals,
+            "contains" => Operator::Contains,
+            "startswith" => Operator::StartsWith,
+            "endswith" => Operator::EndsWith,
+            "<" => Operator::Less,
+            "<=" => Operator::LessOrEqual,
+            ">" => Operator::Greater,
+            ">=" => Operator::GreaterOrEqual,
+            _ => bail!(
+                "unknown operator {}, expected one of ==, !=, contains, startswith, endswith, <, \
+                 <=, > or >=",
+                operator
+            ),
+        };
+
+        let value = value.trim();
+        let value = ['\'', '"']
+            .iter()
+            .find_map(|q| value.strip_prefix(*q)?.strip_suffix(*q))
+            .unwrap_or(value);
+        if matches!(
+            operator,
+            Operator::Less | Operator::LessOrEqual | Operator::Greater | Operator::GreaterOrEqual
+        ) && value.parse::<f64>().is_err()
+        {
+            bail!("{} compares numbers, got {}", operator, value);
+        }
+
+        Ok(Self {
+            path: path.to_string(),
+            operator,
+            value: value.to_string(),
+        })
+    }
+}
+
+impl FieldPredicate {
+    // false for objects without the field
+    pub fn matches(&self, heap: &AnalyzedHeap, contents: &Contents, id: Id) -> Result<bool> {
+        let Some(value) = field_at(heap, contents, id, &self.path)? else {
+            return Ok(self.operator == Operator::Equals && self.value == "null");
+        };
+
+        let number = match value {
+            FieldValue::Byte(v) => Some(v as f64),
+            FieldValue::Short(v) => Some(v as f64),
+            FieldValue::Int(v) => Some(v as f64),
+            FieldValue::Long(v) => Some(v as f64),
+            FieldValue::Float(v) => Some(v as f64),
+            FieldValue::Double(v) => Some(v),
+            _ => None,
+        };
+        if let (Some(number), Ok(expected)) = (number, self.value.parse::<f64>()) {
+            return Ok(match self.operator {
+                Operator::Equals => number == expected,
+                Operator::NotEquals => number != expected,
+                Operator::Less => number < expected,
+                Operator::LessOrEqual => number <= expected,
+                Operator::Greater => number > expected,
+                Operator::GreaterOrEqual => number >= expected,
+                _ => text_matches(self.operator, &number.to_string(), &self.value),
+            });
+        }
+
+        let text = match value {
+            FieldValue::NormalObject { object_id } if object_id.0 == 0 => "null".to_string(),
+            FieldValue::NormalObject { object_id } => {
+                match contents.string_value(heap, object_id)? {
+                    Some(content) => content,
+                    None => enum_name(heap, contents, object_id)?
+                        .unwrap_or_else(|| object_id.to_string()),
+                }
+            }
+            FieldValue::Boolean(v) => v.to_string(),
+            FieldValue::Char(v) => char::from_u32(v as u32)
+                .map(String::from)
+                .unwrap_or_default(),
+            _ => return Ok(false),
+        };
+        Ok(text_matches(self.operator, &text, &self.value))
+    }
+}
+
+// instances of the classes matching all predicates, by ascending id
+pub fn find_instances(
+    heap: &AnalyzedHeap,
+    contents: &Contents,
+    class_ids: &[Id],
+    predicates: &[FieldPredicate],
+) -> Result<Vec<Id>> {
+    let mut found = Vec::new();
+    for class_id in class_ids {
+        'instances: for instance in heap.instances_of(*class_id) {
+            for predicate in predicates {
+                if !predicate.matches(heap, contents, instance.id)? {
+                    continue 'instances;
+                }
+            }
+            found.push(instance.id);
+        }
+    }
+    found.sort_unstable();
+    Ok(found)
+}
+
+fn text_matches(operator: Operator, text: &str, value: &str) -> bool {
+    match operator {
+        Operator::Equals => text == value,
+        Operator::NotEquals => text != value,
+        Operator::Contains => text.contains(value),
+        Operator::StartsWith => text.starts_with(value),
+        Operator::EndsWith => text.ends_with(value),
+        // numbers only, checked when parsing
+        Operator::Less | Operator::LessOrEqual | Operator::Greater | Operator::GreaterOrEqual => {
+            false
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analyzer::{AnalyzedHeap, contents::Contents, handle::Handle},
+    error::Result,
+    parser::{Id, sub_record::FieldValue},
+};
+
+// objects of a set sharing the same chain of referrers
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReferrerChain {
+    // the outermost referrer first, down to the field referencing the objects, e.g.
+    // ["com.example.SessionStore.sessions", "java.util.HashMap.table",
+    // "java.util.HashMap$Node[]", "java.util.HashMap$Node.key"]. empty for objects nothing
+    // references, which only the gc roots keep
+    pub referrers: Vec<String>,
+    pub objects: u64,
+    // 0 for objects not in the dominator tree
+    pub retained_size: u64,
+}
+
+// groups the objects by their referrers up to depth levels out, answering which component holds
+// all of them. an object referenced from several places, and each referrer on the way, is
+// counted under its first referrer only. largest retained size first
+pub fn referrer_chains(
+    heap: &AnalyzedHeap,
+    contents: &Contents,
+    objects: &[Id],
+    depth: usize,
+) -> Result<Vec<ReferrerChain>> {
+    let dominator_tree = heap.dominator_tree();
+    let mut chains: HashMap<Vec<String>, (u64, u64)> = HashMap::new();
+
+    for id in objects {
+        let Some(handle) = heap.handle(*id) else {
+            continue;
+        };
+        let mut referrers = Vec::new();
+        let mut visited = HashSet::from([handle]);
+        let mut current = handle;
+        while referrers.len() < depth {
+            let Some(referrer) = heap.referrers(current).find(|h| visited.insert(*h)) else {
+                break;
+            };
+            referrers.push(segment(heap, contents, referrer, current)?);
+            current = referrer;
+        }
+        referrers.reverse();
+
+        let chain = chains.entry(referrers).or_default();
+        chain.0 += 1;
+        chain.1 += dominator_tree.retained_size(*id).unwrap_or(0);
+    }
+
+    let mut chains: Vec<ReferrerChain> = chains
+        .into_iter()
+        .map(|(referrers, (objects, retained_size))| ReferrerChain {
+            referrers,
+            objects,
+            retained_size,
+        })
+        .collect();
+    chains.sort_by(|a, b| {
+        b.retained_size
+            .cmp(&a.retained_size)
+            .then_with(|| b.objects.cmp(&a.objects))
+            .then_with(|| a.referrers.cmp(&b.referrers))
+    });
+    Ok(chains)
+}
+
+// the referrer's class with the instance field referencing the object. array elements and
+// static fields go by the class alone
+fn segment(
+    heap: &AnalyzedHeap,
+    contents: &Contents,
+    referrer: Handle,
+    referenced: Handle,
+) -> Result<String> {
+    let referrer = heap.handles.id(referrer);
+    let referenced = heap.handles.id(referenced);
+    let class_name = heap.class_name_of(referrer).unwrap_or_default();
+
+    let field = contents.fields(heap, referrer)?.and_then(|fields| {
+        fields.into_iter().find_map(|(name, value)| match value {
+            FieldValue::NormalObject { object_id } if object_id == referenced => Some(name),
+            _ => None,
+        })
+    });
+    Ok(match field {
+        Some(field) => format!("{}.{}", class_name, field),
+        None => class_name,
+    })
+}
@@ -0,0 +1,144 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analyzer::{AnalyzedHeap, Class, contents::Contents, handle::Handle, mark::mark},
+    error::Result,
+    parser::{Id, sub_record::FieldValue},
+};
+
+// jdk classes holding a file descriptor or native handle, subclasses included
+const RESOURCE_CLASSES: [&str; 12] = [
+    "java/io/FileInputStream",
+    "java/io/FileOutputStream",
+    "java/io/RandomAccessFile",
+    "java/net/Socket",
+    "java/net/ServerSocket",
+    "java/net/SocketImpl",
+    "java/net/DatagramSocket",
+    "java/util/zip/ZipFile",
+    "sun/nio/ch/FileChannelImpl",
+    "sun/nio/ch/SocketChannelImpl",
+    "sun/nio/ch/ServerSocketChannelImpl",
+    "sun/nio/ch/DatagramChannelImpl",
+];
+
+// packages of jdbc drivers and pools, whose connections, statements and result sets are
+// resources too. interfaces aren't in the dump, so they go by name
+const JDBC_PACKAGES: [&str; 9] = [
+    "com/mysql/",
+    "org/postgresql/",
+    "oracle/jdbc/",
+    "com/microsoft/sqlserver/",
+    "org/mariadb/",
+    "org/h2/",
+    "org/hsqldb/",
+    "com/zaxxer/hikari/",
+    "org/apache/commons/dbcp",
+];
+const JDBC_SUFFIXES: [&str; 6] = [
+    "Connection",
+    "ConnectionImpl",
+    "Statement",
+    "StatementImpl",
+    "ResultSet",
+    "ResultSetImpl",
+];
+
+// references from these only keep objects around until the gc or the finalizer thread gets to
+// them, Finalizer, Cleaner and PhantomCleanable among them
+const PENDING_REFERENCES: [&str; 2] = [
+    "java/lang/ref/FinalReference",
+    "java/lang/ref/PhantomReference",
+];
+
+// instances of one resource class that can't be closed by the application anymore
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnclosedResources {
+    pub class: Class,
+    // not reachable from any gc root, garbage not yet collected
+    pub unreachable: u64,
+    // only reachable through finalizers and cleaners
+    pub finalizer_only: u64,
+}
+
+impl UnclosedResources {
+    pub fn total(&self) -> u64 {
+        self.unreachable + self.finalizer_only
+    }
+}
+
+// resources nothing strongly references anymore and that weren't closed before being dropped,
+// by class, most first. instances with a closed field set are left out. dumps written with
+// -dump:live only contain reachable objects, so only finalizer reachable ones show up there
+pub fn unclosed_resources(
+    heap: &AnalyzedHeap,
+    contents: &Contents,
+) -> Result<Vec<UnclosedResources>> {
+    let resource_classes = classes_where(heap, is_resource_class);
+    if resource_classes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let pending_classes = classes_where(heap, |heap, class| {
+        heap.extends_any(class, &PENDING_REFERENCES)
+    });
+
+    let live = heap.live();
+    let roots = heap.roots.iter().filter_map(|r| heap.handle(r.object_id));
+    let strong = mark(heap, roots, |handle| {
+        !class_of(heap, handle).is_some_and(|class| pending_classes.contains(&class))
+    });
+
+    let mut by_class: HashMap<Id, UnclosedResources> = HashMap::new();
+    for handle in (0..heap.instances.len() as u32).map(Handle) {
+        let instance = heap.instance_at(handle);
+        if !resource_classes.contains(&instance.class.id) || strong.contains(handle) {
+            continue;
+        }
+        if matches!(
+            contents.field(heap, instance.id, "closed")?,
+            Some(FieldValue::Boolean(true))
+        ) {
+            continue;
+        }
+
+        let entry = by_class
+            .entry(instance.class.id)
+            .or_insert_with(|| UnclosedResources {
+                class: instance.class.clone(),
+                unreachable: 0,
+                finalizer_only: 0,
+            });
+        if live.contains(handle) {
+            entry.finalizer_only += 1;
+        } else {
+            entry.unreachable += 1;
+        }
+    }
+
+    let mut resources: Vec<UnclosedResources> = by_class.into_values().collect();
+    resources.sort_by(|a, b| {
+        b.total()
+            .cmp(&a.total())
+            .then_with(|| a.class.name.cmp(&b.class.name))
+    });
+    Ok(resources)
+}
+
+fn is_resource_class(heap: &AnalyzedHeap, class: &Class) -> bool {
+    let is_jdbc = JDBC_PACKAGES.iter().any(|p| class.name.starts_with(p))
+        && JDBC_SUFFIXES.iter().any(|s| class.name.ends_with(s));
+    is_jdbc || heap.extends_any(class, &RESOURCE_CLASSES)
+}
+
+fn classes_where(heap: &AnalyzedHeap, f: impl Fn(&AnalyzedHeap, &Class) -> bool) -> HashSet<Id> {
+    heap.classes
+        .values()
+        .filter(|class| f(heap, class))
+        .map(|class| class.id)
+        .collect()
+}
+
+// None for class objects
+fn class_of(heap: &AnalyzedHeap, handle: Handle) -> Option<Id> {
+    (handle.index() < heap.instances.len()).then(|| heap.instance_at(handle).class.id)
+}
@@ -0,0 +1,108 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analyzer::{AnalyzedHeap, dominator::DominatorTree},
+    parser::Id,
+};
+
+// java name prefixes of the classes coming with the jdk
+pub const JDK_PACKAGES: [&str; 5] = ["java.", "javax.", "jdk.", "sun.", "com.sun."];
+
+// primitive arrays count as jdk classes too, "byte[]" says nothing about who allocated it
+pub fn is_jdk_class(java_name: &str) -> bool {
+    JDK_PACKAGES.iter().any(|p| java_name.starts_with(p))
+        || (java_name.ends_with("[]") && !java_name.contains('.'))
+}
+
+// the objects of one class keeping instances of the inspected classes alive
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Retainer {
+    // class objects, holding instances in static fields, are named "class <name>". None for
+    // instances only the gc roots keep alive
+    pub class_name: Option<String>,
+    // distinct retaining objects
+    pub objects: u64,
+    // the retaining object keeping the most instances alive
+    pub largest: Option<Id>,
+    pub instances: u64,
+    // retained by the kept instances, without counting instances nested in others twice
+    pub retained_size: u64,
+}
+
+#[derive(Default)]
+struct Group {
+    instances_by_retainer: HashMap<Id, u64>,
+    instances: u64,
+    retained_size: u64,
+}
+
+// who keeps the instances of these classes alive: the nearest dominator of each instance that
+// isn't an instance of the classes itself, or with first_non_jdk the nearest one that also isn't
+// of a jdk class. grouped by the class of the retainer, largest retained size first
+pub fn top_retainers(
+    heap: &AnalyzedHeap,
+    dominator_tree: &DominatorTree,
+    class_ids: &[Id],
+    first_non_jdk: bool,
+) -> Vec<Retainer> {
+    let classes: HashSet<Id> = class_ids.iter().copied().collect();
+    let is_inspected = |id: Id| {
+        heap.class_of(id)
+            .is_some_and(|class| classes.contains(&class.id))
+    };
+    let is_skipped = |id: Id| {
+        is_inspected(id)
+            || (first_non_jdk
+                && match heap.class_of(id) {
+                    Some(class) => is_jdk_class(&class.java_name()),
+                    None => heap.class(id).is_some_and(|c| is_jdk_class(&c.java_name())),
+                })
+    };
+
+    let mut groups: HashMap<Option<String>, Group> = HashMap::new();
+    for class_id in &classes {
+        for instance in heap.instances_of(*class_id) {
+            let Some(retained_size) = dominator_tree.retained_size(instance.id) else {
+                continue;
+            };
+            let dominator = dominator_tree.immediate_dominator(instance.id);
+            let nested = dominator.is_some_and(is_inspected);
+
+            let mut retainer = dominator;
+            while let Some(id) = retainer.filter(|id| is_skipped(*id)) {
+                retainer = dominator_tree.immediate_dominator(id);
+            }
+
+            let class_name = retainer.and_then(|id| heap.class_name_of(id));
+            let group = groups.entry(class_name).or_default();
+            group.instances += 1;
+            if !nested {
+                group.retained_size += retained_size;
+            }
+            if let Some(retainer) = retainer {
+                *group.instances_by_retainer.entry(retainer).or_default() += 1;
+            }
+        }
+    }
+
+    let mut retainers: Vec<Retainer> = groups
+        .into_iter()
+        .map(|(class_name, group)| Retainer {
+            class_name,
+            objects: group.instances_by_retainer.len() as u64,
+            largest: group
+                .instances_by_retainer
+                .iter()
+                .max_by_key(|(id, count)| (**count, std::cmp::Reverse(**id)))
+                .map(|(id, _)| *id),
+            instances: group.instances,
+            retained_size: group.retained_size,
+        })
+        .collect();
+    retainers.sort_by(|a, b| {
+        b.retained_size
+            .cmp(&a.retained_size)
+            .then_with(|| a.class_name.cmp(&b.class_name))
+    });
+    retainers
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+
+use crate::analyzer::{
+    AnalyzedHeap,
+    graph::RootKind,
+    handle::Handle,
+    mark::{Bitset, mark},
+};
+
+// the gc roots of one kind and what only they keep alive
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RootKindSummary {
+    pub kind: RootKind,
+    // root entries, an object can be a root more than once
+    pub roots: u64,
+    // objects not reachable anymore once the roots of this kind are gone
+    pub exclusive_objects: u64,
+    pub exclusive_size: u64,
+}
+
+// every root kind found in the dump, largest exclusive size first. what roots of several kinds
+// reach is counted for none of them
+pub fn root_summary(heap: &AnalyzedHeap) -> Vec<RootKindSummary> {
+    let mut roots: HashMap<RootKind, u64> = HashMap::new();
+    for root in &heap.roots {
+        *roots.entry(root.kind).or_default() += 1;
+    }
+
+    let live = heap.live();
+    let mut summaries: Vec<RootKindSummary> = roots
+        .into_par_iter()
+        .map(|(kind, count)| {
+            let others = heap
+                .roots
+                .iter()
+                .filter(|r| r.kind != kind)
+                .filter_map(|r| heap.handle(r.object_id));
+            let without = mark(heap, others, |_| true);
+            let (exclusive_objects, exclusive_size) = exclusive(heap, &live, &without);
+            RootKindSummary {
+                kind,
+                roots: count,
+                exclusive_objects,
+                exclusive_size,
+            }
+        })
+        .collect();
+    summaries.sort_by(|a, b| {
+        b.exclusive_size
+            .cmp(&a.exclusive_size)
+            .then_with(|| b.roots.cmp(&a.roots))
+    });
+    summaries
+}
+
+// objects and shallow size of what's live but unmarked. class objects count, without a size
+fn exclusive(heap: &AnalyzedHeap, live: &Bitset, marked: &Bitset) -> (u64, u64) {
+    live.iter()
+        .filter(|h| !marked.contains(*h))
+        .fold((0, 0), |(objects, size), handle: Handle| {
+            let shallow = if handle.index() < heap.instances.len() {
+                heap.instances.shallow_size(handle)
+            } else {
+                0
+            };
+            (objects + 1, size + shallow)
+        })
+}
@@ -0,0 +1,200 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    analyzer::{
+        Class, ClassLayout, HistogramEntry, filter::ClassFilter, insert_layout, instance_size,
+        prim_array_name, prim_size, size::SizeModel,
+    },
+    parser::{Header, Id, Record, RecordReader, StringId, sub_record::SubRecord},
+};
+
+// class histogram estimated from a sample of the objects. skips the reference graph, so it only
+// takes as long as parsing the dump
+pub struct Sample {
+    pub rate: f64,
+    pub classes: HashMap<Id, Class>,
+    // sampled objects and their shallow size per class, unscaled
+    pub totals: HashMap<Id, (u64, u64)>,
+}
+
+impl Sample {
+    // every object of a dump is sampled with the given probability. the choice only depends on
+    // the object id, so sampling the same dump twice gives the same result
+    pub fn file(path: &Path, size_model: SizeModel, rate: f64) -> Result<(Header, Self)> {
+        if !(rate > 0.0 && rate <= 1.0) {
+            bail!("sample rate has to be in (0, 1], got {}", rate);
+        }
+        let threshold = (rate * u64::MAX as f64) as u64;
+
+        let records = RecordReader::open(path)?;
+        let header = records.header;
+
+        let mut strings: HashMap<StringId, String> = HashMap::new();
+        let mut classes: HashMap<Id, Class> = HashMap::new();
+        let mut layouts: HashMap<Id, ClassLayout> = HashMap::new();
+        let mut instances: HashMap<Id, u64> = HashMap::new();
+        let mut arrays: HashMap<Id, (u64, u64)> = HashMap::new();
+        let mut prim_array_classes: HashMap<u8, Id> = HashMap::new();
+
+        for record in records {
+            match record? {
+                Record::Utf8 {
+                    name_id, content, ..
+                } => {
+                    strings.insert(name_id, content);
+                }
+                Record::LoadClass {
+                    class_object_id,
+                    class_name_id,
+                    ..
+                } => {
+                    let name = strings
+                        .get(&class_name_id)
+                        .context("unknown class name string")?;
+                    classes.insert(
+                        class_object_id,
+                        Class {
+                            id: class_object_id,
+                            name: name.as_str().into(),
+                        },
+                    );
+                }
+                Record::HeapDumpSegment { sub_records, .. } => {
+                    for sub_record in sub_records {
+                        match sub_record {
+                            SubRecord::ClassDump {
+                                class_object_id,
+                                super_class_object_id,
+                                instance_field_descriptors,
+                                ..
+                            } => {
+                                insert_layout(
+                                    &mut layouts,
+                                    class_object_id,
+                                    ClassLayout {
+                                        super_class_id: (super_class_object_id.0 != 0)
+                                            .then_some(super_class_object_id),
+                                        instance_fields: instance_field_descriptors,
+                                    },
+                                );
+                            }
+                            SubRecord::InstanceDump {
+                                object_id,
+                                class_object_id,
+                                ..
+                            } if sampled(object_id, threshold) => {
+                                *instances.entry(class_object_id).or_default() += 1;
+                            }
+                            SubRecord::ObjArrayDump {
+                                object_id,
+                                array_class_id,
+                                elements,
+                                ..
+                            } if sampled(object_id, threshold) => {
+                                let size = size_model.object_array_size(elements.len() as u64);
+                                let total = arrays.entry(array_class_id).or_default();
+                                total.0 += 1;
+                                total.1 += size;
+                            }
+                            SubRecord::PrimArrayDump {
+                                object_id,
+                                typ,
+                                elements,
+                                ..
+                            } if sampled(object_id, threshold) => {
+                                let class_id = match prim_array_classes.get(&typ) {
+                                    Some(class_id) => *class_id,
+                                    None => {
+                                        let name = prim_array_name(typ)?;
+                                        let class_id = classes
+                                            .values()
+                                            .find(|c| &*c.name == name)
+                                            .map(|c| c.id)
+                                            .with_context(|| {
+                                                format!("primitive array class {} not found", name)
+                                            })?;
+                                        prim_array_classes.insert(typ, class_id);
+                                        class_id
+                                    }
+                                };
+                                let size = size_model
+                                    .prim_array_size(elements.len() as u64, prim_size(typ)?);
+                                let total = arrays.entry(class_id).or_default();
+                                total.0 += 1;
+                                total.1 += size;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut totals = arrays;
+        for (class_id, count) in instances {
+            if !layouts.contains_key(&class_id) {
+                bail!("class dump not found");
+            }
+            let size = instance_size(class_id, &layouts, &size_model);
+            let total = totals.entry(class_id).or_default();
+            total.0 += count;
+            total.1 += count * size;
+        }
+        if let Some(class_id) = totals.keys().find(|id| !classes.contains_key(id)) {
+            bail!("class {} not found", class_id);
+        }
+
+        Ok((
+            header,
+            Self {
+                rate,
+                classes,
+                totals,
+            },
+        ))
+    }
+
+    // object counts and sizes scaled up to the whole heap
+    pub fn histogram(&self, filter: &ClassFilter) -> Vec<HistogramEntry> {
+        let mut entries: Vec<HistogramEntry> = self
+            .totals
+            .iter()
+            .map(|(class_id, &(count, size))| HistogramEntry {
+                class: self.classes[class_id].clone(),
+                instance_count: self.scale(count),
+                shallow_size: self.scale(size),
+            })
+            .filter(|e| filter.matches(&e.class.java_name()))
+            .collect();
+        entries.sort_by(HistogramEntry::order);
+        entries
+    }
+
+    pub fn sampled_count(&self) -> u64 {
+        self.totals.values().map(|t| t.0).sum()
+    }
+
+    pub fn estimated_count(&self) -> u64 {
+        self.scale(self.sampled_count())
+    }
+
+    pub fn estimated_shallow_size(&self) -> u64 {
+        self.scale(self.totals.values().map(|t| t.1).sum())
+    }
+
+    fn scale(&self, value: u64) -> u64 {
+        (value as f64 / self.rate).round() as u64
+    }
+}
+
+// splitmix64 finalizer, object ids are aligned addresses and too regular to compare directly
+fn sampled(id: Id, threshold: u64) -> bool {
+    let mut x = id.0;
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^= x >> 31;
+    x <= threshold
+}
@@ -0,0 +1,66 @@
+use serde::Deserialize;
+
+// defaults match a 64bit hotspot vm with compressed oops and class pointers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SizeModel {
+    pub object_header: u64,
+    pub array_header: u64,
+    pub reference_size: u64,
+    pub alignment: u64,
+}
+
+impl Default for SizeModel {
+    fn default() -> Self {
+        Self {
+            object_header: 12,
+            array_header: 16,
+            reference_size: 4,
+            alignment: 8,
+        }
+    }
+}
+
+impl SizeModel {
+    // what visualvm reports: two id sized header words, the array length after them, 8 byte
+    // references and no alignment
+    pub fn visualvm() -> Self {
+        Self {
+            object_header: 16,
+            array_header: 20,
+            reference_size: 8,
+            alignment: 1,
+        }
+    }
+
+    pub fn instance_size(&self, fields_size: u64) -> u64 {
+        self.align(self.object_header + fields_size)
+    }
+
+    pub fn object_array_size(&self, length: u64) -> u64 {
+        self.align(self.array_header + length * self.reference_size)
+    }
+
+    pub fn prim_array_size(&self, length: u64, element_size: u64) -> u64 {
+        self.align(self.array_header + length * element_size)
+    }
+
+    // size of a field of the given basic type inside an object
+    pub fn field_size(&self, typ: u8) -> u64 {
+        match typ {
+            0x02 => self.reference_size,
+            0x04 | 0x08 => 1,
+            0x05 | 0x09 => 2,
+            0x06 | 0x0a => 4,
+            _ => 8,
+        }
+    }
+
+    fn align(&self, size: u64) -> u64 {
+        if self.alignment <= 1 {
+            return size;
+        }
+
+        size.div_ceil(self.alignment) * self.alignment
+    }
+}
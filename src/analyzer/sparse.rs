@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+
+use crate::{
+    analyzer::{AnalyzedHeap, contents::Contents, dominator::DominatorTree, handle::Handle},
+    parser::Id,
+};
+
+// arrays with more than this fraction of null elements count as sparse
+pub const DEFAULT_NULL_FRACTION: f64 = 0.5;
+// shorter arrays are left out, a few free slots are what growable collections are made of
+pub const MIN_LENGTH: usize = 16;
+
+// sparse arrays of one class held by objects of one class
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SparseArrays {
+    pub array_class: String,
+    // class of the immediate dominator, None for arrays only dominated by the gc roots
+    pub retainer_class: Option<String>,
+    pub arrays: u64,
+    pub elements: u64,
+    pub nulls: u64,
+    // the null slots, what sizing the arrays to their contents would save
+    pub savings: u64,
+}
+
+// object arrays of at least MIN_LENGTH elements with more than `null_fraction` of them null,
+// grouped by array class and the class of their retainer. largest savings first
+pub fn sparse_arrays(
+    heap: &AnalyzedHeap,
+    contents: &Contents,
+    dominator_tree: &DominatorTree,
+    null_fraction: f64,
+) -> Vec<SparseArrays> {
+    let sparse: Vec<(Id, Id, u64, u64)> = (0..heap.instances.len() as u32)
+        .into_par_iter()
+        .filter_map(|h| {
+            let instance = heap.instance_at(Handle(h));
+            if !instance.class.name.starts_with("[L") && !instance.class.name.starts_with("[[") {
+                return None;
+            }
+            let elements = contents.object_array(heap, instance.id)?;
+            let nulls = elements.iter().filter(|e| e.0 == 0).count();
+            let sparse = elements.len() >= MIN_LENGTH
+                && nulls as f64 > elements.len() as f64 * null_fraction;
+            sparse.then_some((
+                instance.id,
+                instance.class.id,
+                elements.len() as u64,
+                nulls as u64,
+            ))
+        })
+        .collect();
+
+    let mut groups: HashMap<(Id, Option<String>), SparseArrays> = HashMap::new();
+    for (id, class_id, elements, nulls) in sparse {
+        let retainer_class = dominator_tree
+            .immediate_dominator(id)
+            .and_then(|dominator| heap.class_name_of(dominator));
+        let group = groups
+            .entry((class_id, retainer_class.clone()))
+            .or_insert_with(|| SparseArrays {
+                array_class: heap
+                    .class(class_id)
+                    .map(|c| c.java_name())
+                    .unwrap_or_default(),
+                retainer_class,
+                arrays: 0,
+                elements: 0,
+                nulls: 0,
+                savings: 0,
+            });
+        group.arrays += 1;
+        group.elements += elements;
+        group.nulls += nulls;
+        group.savings += nulls * heap.size_model.reference_size;
+    }
+
+    let mut groups: Vec<SparseArrays> = groups.into_values().collect();
+    groups.sort_by(|a, b| {
+        b.savings
+            .cmp(&a.savings)
+            .then_with(|| a.array_class.cmp(&b.array_class))
+    });
+    groups
+}
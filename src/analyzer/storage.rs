@@ -0,0 +1,145 @@
+use std::{
+    fs::File,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    path::PathBuf,
+};
+
+use memmap2::MmapMut;
+
+use crate::{
+    error::{HeapError, Result},
+    parser::Id,
+};
+
+// initial size of a spilled column's file, doubled whenever it runs full
+const INITIAL_FILE_SIZE: u64 = 1 << 20;
+
+// where the analyzer keeps its large per-object tables
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Storage {
+    #[default]
+    Memory,
+    // memory mapped temporary files in this directory, deleted when the heap is dropped. the
+    // os pages them out under memory pressure, so dumps larger than ram can still be analyzed
+    Spill(PathBuf),
+}
+
+/// Types that are valid for any bit pattern and have no padding, so they can live in a mapped
+/// file.
+///
+/// # Safety
+/// Implementors have to be plain old data.
+pub unsafe trait Plain: Copy + 'static {}
+
+unsafe impl Plain for u32 {}
+unsafe impl Plain for u64 {}
+unsafe impl Plain for Id {}
+
+// append only array, either a vec or a memory mapped temporary file
+pub struct Column<T: Plain> {
+    backing: Backing<T>,
+}
+
+enum Backing<T> {
+    Memory(Vec<T>),
+    File {
+        file: File,
+        map: MmapMut,
+        len: usize,
+        _marker: PhantomData<T>,
+    },
+}
+
+impl<T: Plain> Column<T> {
+    pub fn memory() -> Self {
+        Self {
+            backing: Backing::Memory(Vec::new()),
+        }
+    }
+
+    pub fn new(storage: &Storage) -> Result<Self> {
+        let backing = match storage {
+            Storage::Memory => Backing::Memory(Vec::new()),
+            Storage::Spill(dir) => {
+                let file = tempfile::tempfile_in(dir).map_err(|source| HeapError::Spill {
+                    dir: dir.clone(),
+                    source,
+                })?;
+                file.set_len(INITIAL_FILE_SIZE)?;
+                // the file is private to this column and never truncated while mapped
+                let map = unsafe { MmapMut::map_mut(&file)? };
+                Backing::File {
+                    file,
+                    map,
+                    len: 0,
+                    _marker: PhantomData,
+                }
+            }
+        };
+        Ok(Self { backing })
+    }
+
+    pub fn push(&mut self, value: T) -> Result<()> {
+        self.extend_from_slice(&[value])
+    }
+
+    pub fn extend_from_slice(&mut self, values: &[T]) -> Result<()> {
+        match &mut self.backing {
+            Backing::Memory(vec) => vec.extend_from_slice(values),
+            Backing::File { file, map, len, .. } => {
+                let size = std::mem::size_of::<T>();
+                let needed = (*len + values.len()) * size;
+                if needed > map.len() {
+                    let mut file_size = map.len() as u64;
+                    while (file_size as usize) < needed {
+                        file_size *= 2;
+                    }
+                    map.flush_async()?;
+                    file.set_len(file_size)?;
+                    *map = unsafe { MmapMut::map_mut(&*file)? };
+                }
+
+                let start = *len * size;
+                let bytes = unsafe {
+                    std::slice::from_raw_parts(
+                        values.as_ptr() as *const u8,
+                        std::mem::size_of_val(values),
+                    )
+                };
+                map[start..start + bytes.len()].copy_from_slice(bytes);
+                *len += values.len();
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_spilled(&self) -> bool {
+        matches!(self.backing, Backing::File { .. })
+    }
+}
+
+impl<T: Plain> Deref for Column<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match &self.backing {
+            Backing::Memory(vec) => vec,
+            // mappings are page aligned, which satisfies the alignment of every Plain type
+            Backing::File { map, len, .. } => unsafe {
+                std::slice::from_raw_parts(map.as_ptr() as *const T, *len)
+            },
+        }
+    }
+}
+
+impl<T: Plain> DerefMut for Column<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        match &mut self.backing {
+            Backing::Memory(vec) => vec,
+            Backing::File { map, len, .. } => unsafe {
+                std::slice::from_raw_parts_mut(map.as_mut_ptr() as *mut T, *len)
+            },
+        }
+    }
+}
@@ -0,0 +1,496 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    analyzer::{
+        AnalyzedHeap, Class, ClassLayout, Frame, Instances, Lazy, Thread,
+        diagnostic::{Diagnostic, unresolved_class, unresolved_string},
+        graph::{GcRoot, References, class_references, instance_references},
+        handle::{Handle, Handles},
+        insert_layout, instance_size, prim_array_name, prim_size,
+        size::SizeModel,
+        storage::{Column, Plain, Storage},
+        strings::Interner,
+        superclass_chain,
+    },
+    error::{HeapError, Policy, Result},
+    parser::{FrameId, Id, Record, StringId, sub_record::SubRecord},
+    trace::{debug_span, warn},
+};
+
+// builds an AnalyzedHeap one record at a time, so records can be dropped right after parsing.
+// hprof writes strings, classes and stack traces before the heap dump segments that use them.
+pub struct StreamingAnalyzer {
+    size_model: SizeModel,
+    strings: HashMap<StringId, Arc<str>>,
+    interner: Interner,
+    classes: HashMap<Id, Class>,
+    frames: Vec<Frame>,
+    traces: HashMap<u32, Vec<FrameId>>,
+    threads: Vec<Thread>,
+    layouts: HashMap<Id, ClassLayout>,
+    class_objects: Vec<(Id, Vec<Id>)>,
+    roots: Vec<GcRoot>,
+    handles: Handles,
+    storage: Storage,
+    // class and shallow size of every instance by handle. instance sizes depend on the layouts
+    // of the whole superclass chain, so they are UNKNOWN_SIZE until finish
+    object_classes: Column<Id>,
+    shallow_sizes: Column<u64>,
+    references: References,
+    // instances dumped before the layout of their class, decoded by finish
+    pending: Vec<(Handle, Id, Vec<u8>)>,
+    prim_array_classes: HashMap<u8, Id>,
+    // names and classes the dump is missing, reported as diagnostics by finish
+    missing_strings: HashSet<StringId>,
+    unresolved_classes: HashMap<Id, u64>,
+    // instances by class whose fields didn't fit their bytes
+    malformed_instances: HashMap<Id, u64>,
+    // classes whose class dump closed a superclass cycle
+    superclass_cycles: Vec<Id>,
+    // arrays by element type whose array class was never loaded
+    missing_array_classes: HashMap<u8, u64>,
+    policy: Policy,
+}
+
+impl StreamingAnalyzer {
+    pub fn new(size_model: SizeModel, storage: &Storage) -> Result<Self> {
+        Ok(Self {
+            size_model,
+            strings: HashMap::new(),
+            interner: Interner::default(),
+            classes: HashMap::new(),
+            frames: Vec::new(),
+            traces: HashMap::new(),
+            threads: Vec::new(),
+            layouts: HashMap::new(),
+            class_objects: Vec::new(),
+            roots: Vec::new(),
+            handles: Handles::default(),
+            storage: storage.clone(),
+            object_classes: Column::new(storage)?,
+            shallow_sizes: Column::new(storage)?,
+            references: References::new(storage)?,
+            pending: Vec::new(),
+            prim_array_classes: HashMap::new(),
+            missing_strings: HashSet::new(),
+            unresolved_classes: HashMap::new(),
+            malformed_instances: HashMap::new(),
+            superclass_cycles: Vec::new(),
+            missing_array_classes: HashMap::new(),
+            policy: Policy::default(),
+        })
+    }
+
+    // a strict policy fails on the first inconsistency instead of reporting diagnostics
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn record(&mut self, record: &Record) -> Result<()> {
+        match record {
+            Record::Utf8 {
+                name_id, content, ..
+            } => {
+                self.strings.insert(*name_id, self.interner.intern(content));
+            }
+            Record::Frame {
+                stack_frame_id,
+                method_name_id,
+                method_signature_id,
+                source_file_name_id,
+                class_serial_number,
+                line_number,
+                ..
+            } => {
+                let frame = Frame {
+                    id: *stack_frame_id,
+                    method_name: self.string(*method_name_id),
+                    method_signature: self.string(*method_signature_id),
+                    source_file_name: self.string(*source_file_name_id),
+                    class_serial_number: *class_serial_number,
+                    line_number: *line_number,
+                };
+                self.frames.push(frame);
+            }
+            Record::Trace {
+                stack_trace_serial_number,
+                stack_frame_ids,
+                ..
+            } => {
+                self.traces
+                    .insert(*stack_trace_serial_number, stack_frame_ids.clone());
+            }
+            Record::LoadClass {
+                class_object_id,
+                class_name_id,
+                ..
+            } => {
+                let name = self.string(*class_name_id);
+                self.classes.insert(
+                    *class_object_id,
+                    Class {
+                        id: *class_object_id,
+                        name,
+                    },
+                );
+                // loaded after objects using it, the placeholder is replaced
+                self.unresolved_classes.remove(class_object_id);
+            }
+            Record::HeapDumpSegment { sub_records, .. } => {
+                // class dumps go first, so instances can be decoded against the layouts of
+                // their own segment
+                for sub_record in sub_records {
+                    if let SubRecord::ClassDump { .. } = sub_record {
+                        self.class_dump(sub_record);
+                    }
+                }
+
+                let references = sub_records
+                    .par_iter()
+                    .map(|sub_record| match sub_record {
+                        SubRecord::InstanceDump {
+                            class_object_id,
+                            raw_field_bytes,
+                            ..
+                        } if self.has_layout(*class_object_id) => Some((
+                            *class_object_id,
+                            instance_references(*class_object_id, raw_field_bytes, &self.layouts),
+                        )),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                for (sub_record, references) in sub_records.iter().zip(references) {
+                    let references = match references {
+                        Some((class_id, references)) => Some(self.malformed(class_id, references)?),
+                        None => None,
+                    };
+                    self.sub_record(sub_record, references)?;
+                }
+            }
+            Record::HeapDumpEnd { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    fn class_dump(&mut self, sub_record: &SubRecord) {
+        if let SubRecord::ClassDump {
+            class_object_id,
+            super_class_object_id,
+            instance_field_descriptors,
+            ..
+        } = sub_record
+        {
+            let layout = ClassLayout {
+                super_class_id: (super_class_object_id.0 != 0).then_some(*super_class_object_id),
+                instance_fields: instance_field_descriptors.clone(),
+            };
+            if !insert_layout(&mut self.layouts, *class_object_id, layout) {
+                self.superclass_cycles.push(*class_object_id);
+            }
+            self.class_objects
+                .push((*class_object_id, class_references(sub_record)));
+        }
+    }
+
+    // references of instance dumps come decoded, None when their layout isn't complete yet
+    fn sub_record(&mut self, sub_record: &SubRecord, references: Option<Vec<Id>>) -> Result<()> {
+        match sub_record {
+            SubRecord::ClassDump { .. } => {}
+            SubRecord::InstanceDump {
+                object_id,
+                class_object_id,
+                raw_field_bytes,
+                ..
+            } => {
+                let handle = self.handles.insert(*object_id)?;
+                self.set_object(handle, *class_object_id, UNKNOWN_SIZE)?;
+                match references {
+                    Some(references) => self.references.set(handle, &references)?,
+                    None => {
+                        self.references.set(handle, &[])?;
+                        self.pending
+                            .push((handle, *class_object_id, raw_field_bytes.clone()));
+                    }
+                }
+            }
+            SubRecord::ObjArrayDump {
+                object_id,
+                array_class_id,
+                elements,
+                ..
+            } => {
+                let size = self.size_model.object_array_size(elements.len() as u64);
+                let mut array_references = vec![*array_class_id];
+                array_references.extend(elements.iter().filter(|id| id.0 != 0));
+
+                let handle = self.handles.insert(*object_id)?;
+                self.set_object(handle, *array_class_id, size)?;
+                self.references.set(handle, &array_references)?;
+            }
+            SubRecord::PrimArrayDump {
+                object_id,
+                typ,
+                elements,
+                ..
+            } => {
+                let class_id = self.prim_array_class(*typ)?;
+                let size = self
+                    .size_model
+                    .prim_array_size(elements.len() as u64, prim_size(*typ)?);
+
+                let handle = self.handles.insert(*object_id)?;
+                self.set_object(handle, class_id, size)?;
+                self.references.set(handle, &[])?;
+            }
+            SubRecord::ThreadObj {
+                object_id,
+                sequence_number,
+                stack_trace_sequence_number,
+            } => {
+                self.threads.push(Thread {
+                    object_id: *object_id,
+                    serial_number: *sequence_number,
+                    stack_frame_ids: self
+                        .traces
+                        .get(stack_trace_sequence_number)
+                        .cloned()
+                        .unwrap_or_default(),
+                });
+                self.roots.extend(GcRoot::from_sub_record(sub_record));
+            }
+            _ => self.roots.extend(GcRoot::from_sub_record(sub_record)),
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<AnalyzedHeap> {
+        let _span = debug_span!(
+            "resolve",
+            objects = self.object_classes.len(),
+            pending = self.pending.len()
+        )
+        .entered();
+        let pending = std::mem::take(&mut self.pending);
+        let decoded = pending
+            .par_iter()
+            .map(|(_, class_id, raw_field_bytes)| {
+                instance_references(*class_id, raw_field_bytes, &self.layouts)
+            })
+            .collect::<Vec<_>>();
+        for ((handle, class_id, _), references) in pending.iter().zip(decoded) {
+            let references = self.malformed(*class_id, references)?;
+            self.references.set(*handle, &references)?;
+        }
+
+        let mut references = self.references;
+        for (class_id, class_references) in self.class_objects {
+            let handle = self.handles.insert(class_id)?;
+            references.set(handle, &class_references)?;
+        }
+        // loaded classes without a class dump have no references
+        let mut class_ids: Vec<Id> = self.classes.keys().copied().collect();
+        class_ids.sort_by_key(|id| id.0);
+        for class_id in class_ids {
+            let handle = self.handles.insert(class_id)?;
+            if handle.index() == references.len() {
+                references.set(handle, &[])?;
+            }
+        }
+
+        let instance_sizes: HashMap<Id, u64> = self
+            .layouts
+            .par_iter()
+            .map(|(id, _)| (*id, instance_size(*id, &self.layouts, &self.size_model)))
+            .collect();
+
+        let mut classes = Column::new(&self.storage)?;
+        let mut shallow_sizes = self.shallow_sizes;
+        let mut missing_class_dumps: HashMap<Id, u64> = HashMap::new();
+        let object_chunks = self.object_classes.chunks(FINISH_CHUNK);
+        for (class_ids, sizes) in object_chunks.zip(shallow_sizes.chunks_mut(FINISH_CHUNK)) {
+            let resolved: Vec<(u32, bool)> = class_ids
+                .par_iter()
+                .zip(sizes.par_iter_mut())
+                .map(|(class_id, size)| {
+                    let mut missing_class_dump = false;
+                    if *size == UNKNOWN_SIZE {
+                        *size = match instance_sizes.get(class_id) {
+                            Some(size) => *size,
+                            None => {
+                                missing_class_dump = true;
+                                instance_size(*class_id, &self.layouts, &self.size_model)
+                            }
+                        };
+                    }
+                    // every class of an object got a handle above, unresolved ones included
+                    (self.handles.get(*class_id).unwrap().0, missing_class_dump)
+                })
+                .collect();
+            for (class_id, (_, missing_class_dump)) in class_ids.iter().zip(&resolved) {
+                if *missing_class_dump {
+                    *missing_class_dumps.entry(*class_id).or_default() += 1;
+                }
+            }
+            let class_handles: Vec<u32> = resolved.into_iter().map(|(handle, _)| handle).collect();
+            classes.extend_from_slice(&class_handles)?;
+        }
+        let instances = Instances::from_parts(classes, shallow_sizes);
+
+        let mut diagnostics: Vec<Diagnostic> = self
+            .missing_strings
+            .into_iter()
+            .map(|id| Diagnostic::MissingString { id })
+            .collect();
+        diagnostics.extend(
+            self.unresolved_classes
+                .into_iter()
+                .map(|(id, objects)| Diagnostic::MissingClass { id, objects }),
+        );
+        diagnostics.extend(
+            missing_class_dumps
+                .into_iter()
+                .map(|(id, instances)| Diagnostic::MissingClassDump { id, instances }),
+        );
+        diagnostics.extend(
+            self.malformed_instances
+                .into_iter()
+                .map(|(id, instances)| Diagnostic::MalformedInstances { id, instances }),
+        );
+        diagnostics.extend(
+            self.superclass_cycles
+                .into_iter()
+                .map(|id| Diagnostic::SuperclassCycle { id }),
+        );
+        diagnostics.extend(
+            self.missing_array_classes
+                .into_iter()
+                .map(|(typ, arrays)| Diagnostic::MissingArrayClass { typ, arrays }),
+        );
+        diagnostics.sort_by_key(|d| match *d {
+            Diagnostic::MissingString { id } => (0, id.0),
+            Diagnostic::MissingClass { id, .. } => (1, id.0),
+            Diagnostic::MissingClassDump { id, .. } => (2, id.0),
+            Diagnostic::MalformedInstances { id, .. } => (3, id.0),
+            Diagnostic::SuperclassCycle { id } => (4, id.0),
+            Diagnostic::MissingArrayClass { typ, .. } => (5, typ as u64),
+        });
+        if let Some(diagnostic) = diagnostics.first()
+            && self.policy.is_strict()
+        {
+            return Err(diagnostic.to_error());
+        }
+        if !diagnostics.is_empty() {
+            warn!(
+                "dump is inconsistent, placeholders stand in for {} missing records",
+                diagnostics.len()
+            );
+        }
+
+        Ok(AnalyzedHeap {
+            strings: self.strings,
+            classes: self.classes,
+            frames: self.frames,
+            threads: self.threads,
+            handles: Arc::new(self.handles),
+            instances,
+            layouts: self.layouts,
+            references,
+            roots: self.roots,
+            size_model: self.size_model,
+            diagnostics,
+            lazy: Lazy::default(),
+        })
+    }
+
+    // instances with fields their bytes don't hold keep only their class reference and are
+    // reported, unless the policy is strict
+    fn malformed(&mut self, class_id: Id, references: Result<Vec<Id>>) -> Result<Vec<Id>> {
+        match references {
+            Err(HeapError::FieldOverflow { .. } | HeapError::InvalidType(_))
+                if !self.policy.is_strict() =>
+            {
+                *self.malformed_instances.entry(class_id).or_default() += 1;
+                Ok(vec![class_id])
+            }
+            references => references,
+        }
+    }
+
+    // names missing from the dump get a placeholder instead of failing the analysis
+    fn string(&mut self, id: StringId) -> Arc<str> {
+        if let Some(content) = self.strings.get(&id) {
+            return content.clone();
+        }
+        self.missing_strings.insert(id);
+        self.interner.intern(&unresolved_string(id))
+    }
+
+    fn set_object(&mut self, handle: Handle, class_id: Id, size: u64) -> Result<()> {
+        // objects of classes without a load class record get a placeholder class
+        if let Some(objects) = self.unresolved_classes.get_mut(&class_id) {
+            *objects += 1;
+        } else if !self.classes.contains_key(&class_id) {
+            let name = self.interner.intern(&unresolved_class(class_id));
+            self.classes.insert(class_id, Class { id: class_id, name });
+            self.unresolved_classes.insert(class_id, 1);
+        }
+        set(&mut self.object_classes, handle, class_id)?;
+        set(&mut self.shallow_sizes, handle, size)
+    }
+
+    // true once the class and all its superclasses have been dumped
+    fn has_layout(&self, class_id: Id) -> bool {
+        superclass_chain(class_id, &self.layouts)
+            .last()
+            .is_some_and(|(_, layout)| layout.super_class_id.is_none())
+    }
+
+    // primitive array dumps don't reference their class, so look it up by name once per type.
+    // without a load class record for it the arrays get a class of their own
+    fn prim_array_class(&mut self, typ: u8) -> Result<Id> {
+        if let Some(arrays) = self.missing_array_classes.get_mut(&typ) {
+            *arrays += 1;
+        }
+        if let Some(class_id) = self.prim_array_classes.get(&typ) {
+            return Ok(*class_id);
+        }
+
+        let name = prim_array_name(typ)?;
+        let class_id = match self.classes.values().find(|c| &*c.name == name) {
+            Some(class) => class.id,
+            None if self.policy.is_strict() => return Err(HeapError::MissingArrayClass(name)),
+            None => {
+                // the basic type as id, objects are never allocated in the first page
+                let class_id = Id(typ as u64);
+                let name = self.interner.intern(name);
+                self.classes.insert(class_id, Class { id: class_id, name });
+                self.missing_array_classes.insert(typ, 1);
+                class_id
+            }
+        };
+        self.prim_array_classes.insert(typ, class_id);
+        Ok(class_id)
+    }
+}
+
+const UNKNOWN_SIZE: u64 = u64::MAX;
+// objects resolved at once by finish, bounds the memory a spilled heap needs on top of its files
+const FINISH_CHUNK: usize = 1 << 20;
+
+// a dump listing an object twice keeps the last one
+fn set<T: Plain>(column: &mut Column<T>, handle: Handle, value: T) -> Result<()> {
+    if handle.index() < column.len() {
+        column[handle.index()] = value;
+        Ok(())
+    } else {
+        column.push(value)
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
+
+use rayon::prelude::*;
+
+use crate::{analyzer::AnalyzedHeap, parser::StringId};
+
+// utf8 record ids by content. only hashes are kept, lookups compare against the heap's strings
+// to rule out collisions
+pub struct StringIndex {
+    ids: HashMap<u64, Vec<StringId>>,
+}
+
+impl StringIndex {
+    pub fn build(heap: &AnalyzedHeap) -> Self {
+        let hashes: Vec<(u64, StringId)> = heap
+            .strings
+            .par_iter()
+            .map(|(id, content)| (hash(content), *id))
+            .collect();
+        let mut ids: HashMap<u64, Vec<StringId>> = HashMap::new();
+        for (hash, id) in hashes {
+            ids.entry(hash).or_default().push(id);
+        }
+        Self { ids }
+    }
+
+    // ids of all utf8 records with exactly this content
+    pub fn ids<'a>(
+        &'a self,
+        heap: &'a AnalyzedHeap,
+        content: &'a str,
+    ) -> impl Iterator<Item = StringId> {
+        self.ids
+            .get(&hash(content))
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(move |id| heap.strings.get(id).is_some_and(|s| &**s == content))
+    }
+
+    pub fn contains(&self, heap: &AnalyzedHeap, content: &str) -> bool {
+        self.ids(heap, content).next().is_some()
+    }
+
+    // groups of ids sharing the same content, ids ascending within a group and groups by their
+    // smallest id
+    pub fn duplicates(&self, heap: &AnalyzedHeap) -> Vec<Vec<StringId>> {
+        let mut duplicates = Vec::new();
+        for ids in self.ids.values().filter(|ids| ids.len() > 1) {
+            let mut by_content: HashMap<&str, Vec<StringId>> = HashMap::new();
+            for id in ids {
+                if let Some(content) = heap.strings.get(id) {
+                    by_content.entry(content).or_default().push(*id);
+                }
+            }
+            duplicates.extend(by_content.into_values().filter(|ids| ids.len() > 1));
+        }
+        for ids in &mut duplicates {
+            ids.sort_unstable();
+        }
+        duplicates.sort_unstable_by_key(|ids| ids[0]);
+        duplicates
+    }
+}
+
+// hands out one shared Arc per distinct content. dumps repeat signatures like "()V" and common
+// field names across thousands of utf8 records
+#[derive(Default)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    pub fn intern(&mut self, content: &str) -> Arc<str> {
+        if let Some(shared) = self.strings.get(content) {
+            return shared.clone();
+        }
+        let shared: Arc<str> = content.into();
+        self.strings.insert(shared.clone());
+        shared
+    }
+}
+
+fn hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,233 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+};
+
+use crate::{
+    analyzer::{
+        diagnostic::{Diagnostic, unresolved_string},
+        java_name,
+    },
+    error::{Policy, Result},
+    parser::{
+        Header, Id, Record, StringId,
+        borrowed::{BorrowedRecord, MappedDump},
+    },
+};
+
+// a class and when it was loaded
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassLoad {
+    // since the header timestamp
+    pub micros: u64,
+    pub class_id: Id,
+    pub class_name: String,
+}
+
+// when the classes of a dump were loaded, from the timestamps of its LoadClass records
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassTimeline {
+    // by time of loading
+    pub loads: Vec<ClassLoad>,
+    // of the last record, when the dump was written
+    pub end_micros: u64,
+    // class names without their utf8 record, the loads show placeholders
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+// classes loaded in a slice of the timeline
+pub struct TimelineBucket {
+    pub start_micros: u64,
+    pub end_micros: u64,
+    pub classes: u64,
+}
+
+// recently loaded classes sharing a name but for their numbers, e.g. proxies or lambdas
+pub struct ClassPattern {
+    pub pattern: String,
+    pub classes: u64,
+    pub first_micros: u64,
+}
+
+impl ClassTimeline {
+    // one pass over the small records of a dump, heap dump segments are skipped. record
+    // timestamps are 32 bit and wrap after about 71 minutes, they're unwrapped in record order
+    pub fn read(path: &Path) -> Result<(Header, Self)> {
+        Self::read_with(path, Policy::default())
+    }
+
+    // a strict policy fails on the first class name missing from the dump
+    pub fn read_with(path: &Path, policy: Policy) -> Result<(Header, Self)> {
+        let dump = MappedDump::open(path)?;
+        let records = dump.records()?;
+        let header = records.header;
+
+        let mut strings: HashMap<StringId, String> = HashMap::new();
+        let mut missing_strings = BTreeSet::new();
+        let mut loads = Vec::new();
+        let mut last: u32 = 0;
+        let mut wraps = 0;
+        for record in records {
+            let record = record?;
+            let micros = match &record {
+                BorrowedRecord::Utf8 { micros, .. }
+                | BorrowedRecord::HeapDumpSegment { micros, .. } => *micros,
+                BorrowedRecord::Other(record) => record_micros(record),
+            };
+            // small steps back are writers not keeping order, not a wrap
+            if last.saturating_sub(micros) > u32::MAX / 2 {
+                wraps += 1;
+            }
+            last = micros;
+            let micros = (wraps << 32) + micros as u64;
+
+            match record {
+                BorrowedRecord::Utf8 {
+                    name_id, content, ..
+                } => {
+                    strings.insert(name_id, content.into_owned());
+                }
+                BorrowedRecord::Other(Record::LoadClass {
+                    class_object_id,
+                    class_name_id,
+                    ..
+                }) => {
+                    let class_name = match strings.get(&class_name_id) {
+                        Some(name) => java_name(name),
+                        None => {
+                            missing_strings.insert(class_name_id);
+                            unresolved_string(class_name_id)
+                        }
+                    };
+                    loads.push(ClassLoad {
+                        micros,
+                        class_id: class_object_id,
+                        class_name,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        let diagnostics: Vec<Diagnostic> = missing_strings
+            .into_iter()
+            .map(|id| Diagnostic::MissingString { id })
+            .collect();
+        if let Some(diagnostic) = diagnostics.first()
+            && policy.is_strict()
+        {
+            return Err(diagnostic.to_error());
+        }
+
+        loads.sort_by_key(|l| l.micros);
+        let end_micros = (wraps << 32) + last as u64;
+        Ok((
+            header,
+            Self {
+                loads,
+                end_micros,
+                diagnostics,
+            },
+        ))
+    }
+
+    // hotspot writes 0 for every record, so most dumps have no timeline
+    pub fn has_timestamps(&self) -> bool {
+        self.end_micros > 0
+    }
+
+    // classes loaded in each of `count` equally long slices from the header timestamp up to
+    // the dump
+    pub fn buckets(&self, count: usize) -> Vec<TimelineBucket> {
+        let count = count.max(1) as u64;
+        let width = (self.end_micros + 1).div_ceil(count);
+        let mut buckets: Vec<TimelineBucket> = (0..count)
+            .map(|i| TimelineBucket {
+                start_micros: i * width,
+                end_micros: ((i + 1) * width).min(self.end_micros + 1),
+                classes: 0,
+            })
+            .collect();
+        for load in &self.loads {
+            let i = (load.micros / width).min(count - 1) as usize;
+            buckets[i].classes += 1;
+        }
+        buckets
+    }
+
+    // classes loaded at most `window` micros before the dump
+    pub fn loaded_within(&self, window: u64) -> &[ClassLoad] {
+        let start = self.end_micros.saturating_sub(window);
+        let first = self.loads.partition_point(|l| l.micros < start);
+        &self.loads[first..]
+    }
+}
+
+// loads grouped by name_pattern, most classes first
+pub fn class_patterns(loads: &[ClassLoad]) -> Vec<ClassPattern> {
+    let mut patterns: HashMap<String, ClassPattern> = HashMap::new();
+    for load in loads {
+        let pattern = name_pattern(&load.class_name);
+        let entry = patterns
+            .entry(pattern.clone())
+            .or_insert_with(|| ClassPattern {
+                pattern,
+                classes: 0,
+                first_micros: load.micros,
+            });
+        entry.classes += 1;
+        entry.first_micros = entry.first_micros.min(load.micros);
+    }
+
+    let mut patterns: Vec<ClassPattern> = patterns.into_values().collect();
+    patterns.sort_by(|a, b| {
+        b.classes
+            .cmp(&a.classes)
+            .then_with(|| a.pattern.cmp(&b.pattern))
+    });
+    patterns
+}
+
+// a class name with its numbers replaced by N, generated classes like $Proxy12,
+// GeneratedMethodAccessor7 or Foo$$Lambda/0x0000000801234 share one. runs of 6 or more hex
+// digits, like the hashes of cglib and byte buddy class names, count as a number
+pub fn name_pattern(name: &str) -> String {
+    let is_hex = |c: char| c.is_ascii_digit() || ('a'..='f').contains(&c);
+    let mut pattern = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(c) = rest.chars().next() {
+        if !is_hex(c) {
+            pattern.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let len = rest.find(|c: char| !is_hex(c)).unwrap_or(rest.len());
+        let (run, after) = rest.split_at(len);
+        if run.len() >= 6 && run.contains(|c: char| c.is_ascii_digit()) {
+            pattern.push('N');
+        } else {
+            let mut in_number = false;
+            for c in run.chars() {
+                if !c.is_ascii_digit() {
+                    pattern.push(c);
+                } else if !in_number {
+                    pattern.push('N');
+                }
+                in_number = c.is_ascii_digit();
+            }
+        }
+        rest = after;
+    }
+    pattern
+}
+
+fn record_micros(record: &Record) -> u32 {
+    match record {
+        Record::Utf8 { micros, .. }
+        | Record::LoadClass { micros, .. }
+        | Record::Trace { micros, .. }
+        | Record::Frame { micros, .. }
+        | Record::HeapDumpSegment { micros, .. }
+        | Record::HeapDumpEnd { micros } => *micros,
+    }
+}
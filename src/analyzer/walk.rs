@@ -0,0 +1,141 @@
+use std::{collections::VecDeque, fmt::Display};
+
+use crate::analyzer::{AnalyzedHeap, handle::Handle, mark::Bitset};
+
+// bounds of a walk over the object graph, so a query from an http client or a slice can't end
+// up walking the whole heap. None is unbounded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    // references followed from the starting objects, 0 visits only them
+    pub max_depth: Option<usize>,
+    pub max_objects: Option<usize>,
+    // shallow sizes of the visited objects
+    pub max_bytes: Option<u64>,
+}
+
+impl Limits {
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn with_max_objects(mut self, max_objects: usize) -> Self {
+        self.max_objects = Some(max_objects);
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+}
+
+// the limit a walk stopped at while there was more to visit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Truncation {
+    Depth,
+    Objects,
+    Bytes,
+}
+
+impl Display for Truncation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Truncation::Depth => write!(f, "depth"),
+            Truncation::Objects => write!(f, "objects"),
+            Truncation::Bytes => write!(f, "bytes"),
+        }
+    }
+}
+
+pub struct Walk {
+    // breadth first, the starting objects first
+    pub objects: Vec<Handle>,
+    pub bytes: u64,
+    // None when everything was visited
+    pub truncated: Option<Truncation>,
+}
+
+// visits the starting objects and what successors leads to, breadth first and each object
+// once, so cycles end the walk like anywhere else
+pub fn walk<I>(
+    heap: &AnalyzedHeap,
+    from: impl IntoIterator<Item = Handle>,
+    limits: &Limits,
+    successors: impl Fn(Handle) -> I,
+) -> Walk
+where
+    I: IntoIterator<Item = Handle>,
+{
+    let mut visited = Bitset::new(heap.handles.len());
+    let mut walk = Walk {
+        objects: Vec::new(),
+        bytes: 0,
+        truncated: None,
+    };
+    let mut queue: VecDeque<(Handle, usize)> = from
+        .into_iter()
+        .filter(|h| visited.insert(*h))
+        .map(|h| (h, 0))
+        .collect();
+
+    while let Some((handle, depth)) = queue.pop_front() {
+        if limits
+            .max_objects
+            .is_some_and(|max| walk.objects.len() >= max)
+        {
+            walk.truncated = Some(Truncation::Objects);
+            break;
+        }
+        let size = shallow_size(heap, handle);
+        if limits.max_bytes.is_some_and(|max| walk.bytes + size > max) {
+            walk.truncated = Some(Truncation::Bytes);
+            break;
+        }
+        walk.objects.push(handle);
+        walk.bytes += size;
+
+        let mut successors = successors(handle).into_iter();
+        if limits.max_depth.is_some_and(|max| depth >= max) {
+            // only truncated when there was something left to follow
+            if successors.any(|h| !visited.contains(h)) {
+                walk.truncated.get_or_insert(Truncation::Depth);
+            }
+            continue;
+        }
+        for successor in successors {
+            if visited.insert(successor) {
+                queue.push_back((successor, depth + 1));
+            }
+        }
+    }
+
+    walk
+}
+
+impl AnalyzedHeap {
+    // objects reachable from the given ones through references, see walk. references to
+    // objects missing from the dump are dropped
+    pub fn reachable_within(
+        &self,
+        from: impl IntoIterator<Item = Handle>,
+        limits: &Limits,
+    ) -> Walk {
+        walk(self, from, limits, move |handle| {
+            self.references
+                .get(handle)
+                .iter()
+                .filter_map(move |id| self.handle(*id))
+        })
+    }
+}
+
+// 0 for class objects
+fn shallow_size(heap: &AnalyzedHeap, handle: Handle) -> u64 {
+    if handle.index() < heap.instances.len() {
+        heap.instances.shallow_size(handle)
+    } else {
+        0
+    }
+}
@@ -0,0 +1,342 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analyzer::{filter::ClassFilter, walk::Limits},
+    export::{
+        json::{DominatorNode, HistogramRow, MAX_EDGES, Truncated, dominator_nodes},
+        prometheus::{self, DumpMetrics},
+    },
+    heap::HeapView,
+    parser::Id,
+};
+
+// json endpoints over a heap, for mounting into other axum servers. ids are hex strings like
+// "0x7fec0bd5c648" as in `export --what json`, the dominator tree is computed by the first
+// request needing it. takes a Heap or a HeapView shared with other servers
+pub fn router(heap: impl Into<HeapView>) -> Router {
+    let state: HeapView = heap.into();
+    Router::new()
+        .route("/api/summary", get(summary))
+        .route("/api/histogram", get(histogram))
+        .route("/api/dominators", get(dominators))
+        .route("/api/dominators/{id}", get(dominators_of))
+        .route("/api/objects/{id}", get(object))
+        .route("/api/objects/{id}/path", get(path))
+        .route("/api/objects/{id}/reachable", get(reachable))
+        .with_state(state)
+}
+
+// prometheus gauges of the served dumps on /metrics, rendered like `watch --metrics` does
+pub fn metrics(metrics: Vec<DumpMetrics>) -> Router {
+    let body = prometheus::render(&metrics);
+    Router::new().route(
+        "/metrics",
+        get(|| async move { ([(header::CONTENT_TYPE, prometheus::CONTENT_TYPE)], body) }),
+    )
+}
+
+// single page ui over the endpoints of router, served from / and embedded in the binary
+pub fn ui() -> Router {
+    Router::new().route("/", get(|| async { Html(include_str!("ui/index.html")) }))
+}
+
+#[derive(Serialize)]
+struct HeapSummary {
+    classes: usize,
+    objects: usize,
+    shallow_size: u64,
+    reachable_objects: usize,
+    reachable_size: u64,
+}
+
+#[derive(Serialize)]
+struct ObjectDetails {
+    id: String,
+    class: String,
+    shallow_size: u64,
+    retained_size: u64,
+    // the first MAX_EDGES of each
+    references: Truncated<String>,
+    referrers: Truncated<String>,
+}
+
+#[derive(Serialize)]
+struct PathElement {
+    id: String,
+    class: String,
+}
+
+#[derive(Serialize)]
+struct Reachable {
+    objects: Vec<PathElement>,
+    bytes: u64,
+    // "depth", "objects" or "bytes" when a limit cut the walk short
+    truncated: Option<String>,
+}
+
+// comma separated class name prefixes
+#[derive(Deserialize)]
+struct HistogramQuery {
+    include: Option<String>,
+    exclude: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DominatorQuery {
+    #[serde(default = "default_depth")]
+    depth: usize,
+    #[serde(default = "default_children")]
+    children: usize,
+}
+
+fn default_depth() -> usize {
+    1
+}
+
+fn default_children() -> usize {
+    100
+}
+
+// objects a single reachable or dominator query returns at most, whatever it asks for
+const MAX_REACHABLE: usize = 10_000;
+// and how deep a dominator query goes, single child chains would fit any depth
+const MAX_DEPTH: usize = 32;
+
+impl DominatorQuery {
+    // depth and children with every level of the tree fitting into MAX_REACHABLE nodes
+    fn clamped(&self) -> (usize, usize) {
+        let children = self.children.min(MAX_REACHABLE);
+        let (mut depth, mut level, mut nodes) = (0, 1, 0);
+        while depth < self.depth.min(MAX_DEPTH) {
+            level *= children;
+            if level == 0 || nodes + level > MAX_REACHABLE {
+                break;
+            }
+            nodes += level;
+            depth += 1;
+        }
+        (depth, children)
+    }
+}
+
+#[derive(Deserialize)]
+struct ReachableQuery {
+    #[serde(default = "default_depth")]
+    depth: usize,
+    #[serde(default = "default_children")]
+    objects: usize,
+    bytes: Option<u64>,
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        #[derive(Serialize)]
+        struct Body {
+            error: String,
+        }
+        (self.0, Json(Body { error: self.1 })).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+async fn summary(State(heap): State<HeapView>) -> ApiResult<HeapSummary> {
+    blocking(heap, |heap| {
+        let dominator_tree = heap.dominator_tree();
+        Ok(HeapSummary {
+            classes: heap.classes.len(),
+            objects: heap.instances.len(),
+            shallow_size: heap.total_shallow_size(),
+            reachable_objects: dominator_tree.reachable_count(),
+            reachable_size: dominator_tree.reachable_size(),
+        })
+    })
+    .await
+}
+
+async fn histogram(
+    State(heap): State<HeapView>,
+    Query(query): Query<HistogramQuery>,
+) -> ApiResult<Vec<HistogramRow>> {
+    let prefixes = |list: Option<String>| -> Vec<String> {
+        list.iter()
+            .flat_map(|l| l.split(','))
+            .filter(|p| !p.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    let filter = ClassFilter {
+        include: prefixes(query.include),
+        exclude: prefixes(query.exclude),
+    };
+
+    blocking(heap, move |heap| {
+        let retained_by_class = heap.retained_by_class();
+        Ok(heap
+            .filtered_histogram(&filter)
+            .into_iter()
+            .map(|e| HistogramRow {
+                class: e.class.java_name(),
+                instances: e.instance_count,
+                shallow_size: e.shallow_size,
+                retained_size: retained_by_class.get(&e.class.id).copied().unwrap_or(0),
+            })
+            .collect())
+    })
+    .await
+}
+
+// objects only dominated by the gc roots
+async fn dominators(
+    State(heap): State<HeapView>,
+    Query(query): Query<DominatorQuery>,
+) -> ApiResult<Vec<DominatorNode>> {
+    blocking(heap, move |heap| Ok(dominator_tree(heap, Id(0), &query))).await
+}
+
+async fn dominators_of(
+    State(heap): State<HeapView>,
+    Path(id): Path<String>,
+    Query(query): Query<DominatorQuery>,
+) -> ApiResult<Vec<DominatorNode>> {
+    blocking(heap, move |heap| {
+        let id = lookup(heap, &id)?;
+        Ok(dominator_tree(heap, id, &query))
+    })
+    .await
+}
+
+async fn object(State(heap): State<HeapView>, Path(id): Path<String>) -> ApiResult<ObjectDetails> {
+    blocking(heap, move |heap| {
+        // lookup made sure the object exists
+        let object = heap.object(lookup(heap, &id)?).unwrap();
+        let references = object.references();
+        Ok(ObjectDetails {
+            id: object.id().to_string(),
+            class: object.class_name(),
+            shallow_size: object.shallow_size().unwrap_or(0),
+            retained_size: object.retained_size().unwrap_or(0),
+            references: Truncated {
+                total: references.len(),
+                items: references
+                    .iter()
+                    .take(MAX_EDGES)
+                    .map(|id| id.to_string())
+                    .collect(),
+            },
+            referrers: Truncated {
+                total: object.referrers().count(),
+                items: object
+                    .referrers()
+                    .take(MAX_EDGES)
+                    .map(|id| id.to_string())
+                    .collect(),
+            },
+        })
+    })
+    .await
+}
+
+// shortest path from a gc root to the object, root first. empty when it is unreachable
+async fn path(State(heap): State<HeapView>, Path(id): Path<String>) -> ApiResult<Vec<PathElement>> {
+    blocking(heap, move |heap| {
+        let id = lookup(heap, &id)?;
+        // lookup made sure the object exists
+        Ok(heap
+            .object(id)
+            .unwrap()
+            .path_to_root()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|id| PathElement {
+                id: id.to_string(),
+                class: heap.class_name_of(id).unwrap_or_default(),
+            })
+            .collect())
+    })
+    .await
+}
+
+// objects reachable from the object breadth first, within the limits of the query
+async fn reachable(
+    State(heap): State<HeapView>,
+    Path(id): Path<String>,
+    Query(query): Query<ReachableQuery>,
+) -> ApiResult<Reachable> {
+    blocking(heap, move |heap| {
+        let id = lookup(heap, &id)?;
+        let limits = Limits {
+            max_depth: Some(query.depth),
+            max_objects: Some(query.objects.min(MAX_REACHABLE)),
+            max_bytes: query.bytes,
+        };
+        let walk = heap.reachable_within(heap.handle(id), &limits);
+        Ok(Reachable {
+            objects: walk
+                .objects
+                .iter()
+                .map(|h| {
+                    let id = heap.handles.id(*h);
+                    PathElement {
+                        id: id.to_string(),
+                        class: heap.class_name_of(id).unwrap_or_default(),
+                    }
+                })
+                .collect(),
+            bytes: walk.bytes,
+            truncated: walk.truncated.map(|t| t.to_string()),
+        })
+    })
+    .await
+}
+
+// queries walk the heap synchronously. they run on the blocking pool so a slow one, like the
+// first computing the dominator tree, doesn't hold up the runtime and every other request. the
+// heap's lazy analyses are computed once, concurrent requests wait for them
+async fn blocking<T: Send + 'static>(
+    heap: HeapView,
+    f: impl FnOnce(&HeapView) -> Result<T, ApiError> + Send + 'static,
+) -> ApiResult<T> {
+    match tokio::task::spawn_blocking(move || f(&heap)).await {
+        Ok(result) => result.map(Json),
+        Err(err) => Err(ApiError(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("query failed: {}", err),
+        )),
+    }
+}
+
+fn dominator_tree(heap: &HeapView, parent: Id, query: &DominatorQuery) -> Vec<DominatorNode> {
+    let (depth, children) = query.clamped();
+    dominator_nodes(
+        heap,
+        heap.dominator_tree(),
+        heap.dominator_children(),
+        parent,
+        depth,
+        children,
+    )
+}
+
+// the id of an object in the dump
+fn lookup(heap: &HeapView, id: &str) -> Result<Id, ApiError> {
+    let parsed = id
+        .parse::<Id>()
+        .map_err(|err| ApiError(StatusCode::BAD_REQUEST, err.to_string()))?;
+    match heap.handle(parsed) {
+        Some(_) => Ok(parsed),
+        None => Err(ApiError(
+            StatusCode::NOT_FOUND,
+            format!("object {} not found", id),
+        )),
+    }
+}
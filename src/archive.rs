@@ -0,0 +1,190 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{Context, Result, bail};
+use flate2::read::GzDecoder;
+
+use crate::{remote::cache_name, trace::info};
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+// "bundle.zip!/dumps/heap.hprof" names an entry inside an archive
+pub fn split_entry(location: &str) -> Option<(&str, &str)> {
+    location
+        .split_once("!/")
+        .filter(|(archive, entry)| !archive.is_empty() && !entry.is_empty())
+}
+
+// zip, tar and gzipped tar files are told apart from dumps by their content, not their name
+pub fn is_archive(path: &Path) -> Result<bool> {
+    Ok(kind(path)?.is_some())
+}
+
+// extracts a dump into the cache directory unless it's already there. without an entry the
+// archive has to hold a single .hprof file, or a single file at all
+pub fn extract(archive: &Path, entry: Option<&str>, cache_dir: &Path) -> Result<PathBuf> {
+    let kind = kind(archive)?
+        .with_context(|| format!("{} isn't a zip or tar archive", archive.display()))?;
+    std::fs::create_dir_all(cache_dir)
+        .with_context(|| format!("failed to create {}", cache_dir.display()))?;
+
+    let source = std::fs::canonicalize(archive)?;
+    let metadata = source.metadata()?;
+    let modified = metadata.modified()?.duration_since(UNIX_EPOCH)?.as_secs();
+    let stamp = format!("{} {}", metadata.len(), modified);
+    let key = match entry {
+        Some(entry) => format!("{}!/{}", source.display(), entry),
+        None => source.display().to_string(),
+    };
+    let path = cache_dir.join(cache_name(&key));
+    let stamp_path = path.with_extension("hprof.stamp");
+    let partial_path = path.with_extension("hprof.part");
+
+    if std::fs::read_to_string(&stamp_path).ok().as_deref() == Some(stamp.as_str()) && path.exists()
+    {
+        info!("using cached {}", path.display());
+        return Ok(path);
+    }
+
+    let mut out = File::create(&partial_path)
+        .with_context(|| format!("failed to create {}", partial_path.display()))?;
+    let file = File::open(&source)?;
+    let name = match kind {
+        Kind::Zip => extract_zip(file, entry, &mut out),
+        Kind::Tar => extract_tar(BufReader::new(file), entry, &mut out),
+        Kind::TarGz => extract_tar(GzDecoder::new(BufReader::new(file)), entry, &mut out),
+    };
+    let name = match name {
+        Ok(name) => name,
+        Err(err) => {
+            let _ = std::fs::remove_file(&partial_path);
+            return Err(err.context(format!("failed to extract from {}", archive.display())));
+        }
+    };
+    out.sync_all()?;
+
+    std::fs::rename(&partial_path, &path)?;
+    std::fs::write(&stamp_path, &stamp)?;
+    info!(
+        "extracted {} from {} to {}",
+        name,
+        archive.display(),
+        path.display()
+    );
+    Ok(path)
+}
+
+fn kind(path: &Path) -> Result<Option<Kind>> {
+    let mut file =
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut head = Vec::new();
+    file.by_ref().take(512).read_to_end(&mut head)?;
+    if head.starts_with(b"PK\x03\x04") {
+        return Ok(Some(Kind::Zip));
+    }
+    if is_tar(&head) {
+        return Ok(Some(Kind::Tar));
+    }
+    if head.starts_with(&[0x1f, 0x8b]) {
+        file.rewind()?;
+        let mut tar_head = Vec::new();
+        // a gzipped dump isn't an archive
+        let _ = GzDecoder::new(file).take(512).read_to_end(&mut tar_head);
+        if is_tar(&tar_head) {
+            return Ok(Some(Kind::TarGz));
+        }
+    }
+    Ok(None)
+}
+
+fn is_tar(head: &[u8]) -> bool {
+    head.get(257..262) == Some(b"ustar")
+}
+
+fn is_dump(name: &str) -> bool {
+    name.ends_with(".hprof")
+}
+
+fn extract_zip(file: File, entry: Option<&str>, out: &mut File) -> Result<String> {
+    let mut zip = zip::ZipArchive::new(BufReader::new(file))?;
+    let name = match entry {
+        Some(entry) => entry.to_string(),
+        None => {
+            let files: Vec<&str> = zip.file_names().filter(|n| !n.ends_with('/')).collect();
+            let dumps: Vec<&str> = files.iter().copied().filter(|n| is_dump(n)).collect();
+            match (dumps.as_slice(), files.as_slice()) {
+                ([dump], _) | ([], [dump]) => dump.to_string(),
+                ([], _) => bail!("no .hprof file in the archive"),
+                _ => bail!(ambiguous(&dumps)),
+            }
+        }
+    };
+
+    let mut file = zip
+        .by_name(&name)
+        .with_context(|| format!("no entry named {}", name))?;
+    io::copy(&mut file, out)?;
+    Ok(name)
+}
+
+// a single pass, since rewinding a gzipped tar means decompressing it again. without an entry
+// the first file is kept in case it turns out to be the only one
+fn extract_tar(reader: impl Read, entry: Option<&str>, out: &mut File) -> Result<String> {
+    let mut extracted = None;
+    let mut dumps = Vec::new();
+    let mut files = 0;
+    for file in tar::Archive::new(reader).entries()? {
+        let mut file = file?;
+        if !file.header().entry_type().is_file() {
+            continue;
+        }
+        let name = file.path()?.to_string_lossy().into_owned();
+        let name = name.trim_start_matches("./").to_string();
+        files += 1;
+
+        let wanted = match entry {
+            Some(entry) => name == entry.trim_start_matches("./"),
+            None if is_dump(&name) => {
+                dumps.push(name.clone());
+                dumps.len() == 1
+            }
+            None => dumps.is_empty() && files == 1,
+        };
+        if wanted {
+            out.set_len(0)?;
+            out.rewind()?;
+            io::copy(&mut file, out)?;
+            extracted = Some(name);
+            if entry.is_some() {
+                break;
+            }
+        }
+    }
+
+    match entry {
+        Some(entry) => extracted.with_context(|| format!("no entry named {}", entry)),
+        None => match dumps.len() {
+            1 => Ok(dumps.remove(0)),
+            0 if files == 1 => Ok(extracted.expect("the only file was extracted")),
+            0 => bail!("no .hprof file in the archive"),
+            _ => bail!(ambiguous(&dumps)),
+        },
+    }
+}
+
+fn ambiguous(dumps: &[impl AsRef<str>]) -> String {
+    let names: Vec<&str> = dumps.iter().map(AsRef::as_ref).collect();
+    format!(
+        "more than one dump in the archive ({}), pick one with archive!/entry",
+        names.join(", ")
+    )
+}
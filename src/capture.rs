@@ -0,0 +1,181 @@
+use std::{
+    fs::File,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+};
+
+use anyhow::{Context, Result, bail};
+use flate2::read::GzDecoder;
+
+use crate::trace::{info, warn};
+
+// where the jvm in the pod writes the dump before it's streamed out
+const POD_DUMP_DIR: &str = "/tmp";
+
+// a container reached through `kubectl exec`, the pod either as "name" or "pod/name"
+pub struct Pod {
+    pub name: String,
+    pub namespace: Option<String>,
+    pub container: Option<String>,
+}
+
+// has a running jvm write a heap dump to the path, through jcmd or jmap for jdks without it.
+// `live` only dumps reachable objects, which costs a full gc in the target. the path is
+// resolved by the target process, so it has to be absolute
+pub fn dump_local(pid: u32, live: bool, path: &Path) -> Result<()> {
+    let path = std::path::absolute(path)?;
+    if path.exists() {
+        bail!("{} already exists", path.display());
+    }
+    let file = path.to_str().context("dump path isn't valid utf8")?;
+
+    let mut jcmd = Command::new(java_tool("jcmd"));
+    jcmd.args(jcmd_args(pid, live, file));
+
+    let options = if live {
+        format!("live,format=b,file={}", file)
+    } else {
+        format!("format=b,file={}", file)
+    };
+    let mut jmap = Command::new(java_tool("jmap"));
+    jmap.arg(format!("-dump:{}", options)).arg(pid.to_string());
+
+    info!("dumping the heap of process {} to {}", pid, path.display());
+    let output = match run(&mut jcmd)? {
+        Some(output) => output,
+        None => run(&mut jmap)?.context("neither jcmd nor jmap found, is a jdk on the PATH?")?,
+    };
+    // jcmd exits successfully even when the dump failed, the file tells
+    if !output.status.success() || !path.exists() {
+        bail!(
+            "failed to dump the heap of process {}: {}",
+            pid,
+            command_output(&output)
+        );
+    }
+    Ok(())
+}
+
+// has the jvm in the pod dump its heap with jcmd and copies the dump to the path. `compress`
+// gzips it inside the pod, which pays off when kubectl goes through a slow api server
+pub fn dump_pod(pod: &Pod, pid: u32, live: bool, compress: bool, path: &Path) -> Result<()> {
+    let remote = format!(
+        "{}/heapdump-analyzer-{}-{}.hprof",
+        POD_DUMP_DIR,
+        pid,
+        std::process::id()
+    );
+
+    info!(
+        "dumping the heap of process {} in {} to {}",
+        pid, pod.name, remote
+    );
+    let output = run(pod.exec().arg("jcmd").args(jcmd_args(pid, live, &remote)))?
+        .context("kubectl not found")?;
+    if !output.status.success() {
+        bail!(
+            "failed to dump the heap of process {} in {}: {}",
+            pid,
+            pod.name,
+            command_output(&output)
+        );
+    }
+
+    let copied = copy_from_pod(pod, &remote, compress, path).with_context(|| {
+        format!(
+            "failed to copy the dump out of {}, jcmd said: {}",
+            pod.name,
+            command_output(&output)
+        )
+    });
+    let removed = pod.exec().args(["rm", "-f", &remote]).output();
+    if !removed.is_ok_and(|r| r.status.success()) {
+        warn!("failed to remove {} in {}", remote, pod.name);
+    }
+    copied
+}
+
+impl Pod {
+    fn exec(&self) -> Command {
+        let mut command = Command::new("kubectl");
+        command.arg("exec");
+        if let Some(namespace) = &self.namespace {
+            command.args(["--namespace", namespace]);
+        }
+        command.arg(&self.name);
+        if let Some(container) = &self.container {
+            command.args(["--container", container]);
+        }
+        command.arg("--");
+        command
+    }
+}
+
+fn copy_from_pod(pod: &Pod, remote: &str, compress: bool, path: &Path) -> Result<()> {
+    let mut command = pod.exec();
+    if compress {
+        command.args(["gzip", "-c", remote]);
+    } else {
+        command.args(["cat", remote]);
+    }
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .context("failed to run kubectl")?;
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+
+    let mut file =
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let copied = if compress {
+        io::copy(&mut GzDecoder::new(stdout), &mut file)
+    } else {
+        io::copy(&mut stdout, &mut file)
+    };
+    let status = child.wait()?;
+    let copied = match copied {
+        Ok(copied) if status.success() => copied,
+        copied => {
+            let _ = std::fs::remove_file(path);
+            copied?;
+            bail!("kubectl exited with {}", status);
+        }
+    };
+    info!("copied {} bytes to {}", copied, path.display());
+    Ok(())
+}
+
+fn jcmd_args(pid: u32, live: bool, file: &str) -> Vec<String> {
+    let mut args = vec![pid.to_string(), "GC.heap_dump".to_string()];
+    if !live {
+        args.push("-all".to_string());
+    }
+    args.push(file.to_string());
+    args
+}
+
+// tools of $JAVA_HOME are preferred over the PATH
+pub(crate) fn java_tool(name: &str) -> PathBuf {
+    std::env::var_os("JAVA_HOME")
+        .map(|home| PathBuf::from(home).join("bin").join(name))
+        .filter(|tool| tool.exists())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+// None if the program isn't installed
+fn run(command: &mut Command) -> Result<Option<Output>> {
+    match command.output() {
+        Ok(output) => Ok(Some(output)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => {
+            Err(err).with_context(|| format!("failed to run {}", command.get_program().display()))
+        }
+    }
+}
+
+fn command_output(output: &Output) -> String {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    format!("{}{}", stdout, stderr).trim().to_string()
+}
@@ -0,0 +1,155 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use heapdump_analyzer::{
+    config::Config,
+    output::{OutputFormat, Style},
+};
+use rayon::prelude::*;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::cli::{
+    find_dumps, open_heap,
+    summary::{print_histogram, print_summary},
+};
+
+#[derive(Args)]
+pub struct BatchArgs {
+    /// Directory containing .hprof files
+    dir: PathBuf,
+
+    /// Analyses to run for every dump
+    #[arg(long, value_delimiter = ',', default_value = "summary,histogram")]
+    what: Vec<Analysis>,
+
+    /// Output directory for per dump results and index.json
+    #[arg(long)]
+    out: PathBuf,
+
+    /// Number of dumps analyzed in parallel, defaults to the number of cpus
+    #[arg(long)]
+    jobs: Option<usize>,
+}
+
+#[derive(Clone, Copy, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Analysis {
+    Summary,
+    Histogram,
+}
+
+impl Analysis {
+    fn name(&self) -> &'static str {
+        match self {
+            Analysis::Summary => "summary",
+            Analysis::Histogram => "histogram",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct IndexEntry {
+    dump: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    objects: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shallow_size: Option<u64>,
+    outputs: Vec<PathBuf>,
+}
+
+pub fn run(args: &BatchArgs, config: &Config) -> Result<ExitCode> {
+    let dumps = find_dumps(&args.dir)?;
+    std::fs::create_dir_all(&args.out)
+        .with_context(|| format!("failed to create {}", args.out.display()))?;
+    info!("analyzing {} dumps", dumps.len());
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.jobs.unwrap_or(0))
+        .build()?;
+    let index: Vec<IndexEntry> = pool.install(|| {
+        dumps
+            .par_iter()
+            .map(|dump| match analyze(dump, args, config) {
+                Ok(entry) => entry,
+                Err(err) => {
+                    warn!("failed to analyze {}: {:#}", dump.display(), err);
+                    IndexEntry {
+                        dump: dump.clone(),
+                        error: Some(format!("{:#}", err)),
+                        timestamp: None,
+                        objects: None,
+                        shallow_size: None,
+                        outputs: Vec::new(),
+                    }
+                }
+            })
+            .collect()
+    });
+
+    let index_path = args.out.join("index.json");
+    let mut w = BufWriter::new(File::create(&index_path)?);
+    serde_json::to_writer_pretty(&mut w, &index)?;
+    writeln!(w)?;
+    info!("wrote {}", index_path.display());
+
+    let failed = index.iter().filter(|e| e.error.is_some()).count();
+    if failed > 0 {
+        warn!("{} of {} dumps failed", failed, index.len());
+        return Ok(ExitCode::from(2));
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn analyze(dump: &Path, args: &BatchArgs, config: &Config) -> Result<IndexEntry> {
+    let index = open_heap(dump, config, false)?;
+    let analyzed_heap = &index.heap;
+
+    let stem = dump
+        .file_stem()
+        .context("dump without file name")?
+        .to_string_lossy();
+    let extension = match config.output.format {
+        OutputFormat::Table => "txt",
+        OutputFormat::Tsv => "tsv",
+        OutputFormat::Csv => "csv",
+    };
+
+    let style = Style::plain();
+    let mut outputs = Vec::new();
+    for analysis in &args.what {
+        let path = args
+            .out
+            .join(format!("{}.{}.{}", stem, analysis.name(), extension));
+        let mut w = BufWriter::new(File::create(&path)?);
+        match analysis {
+            Analysis::Summary => {
+                print_summary(&mut w, &style, config, &index.header, analyzed_heap)?
+            }
+            Analysis::Histogram => print_histogram(&mut w, &style, config, analyzed_heap)?,
+        }
+        w.flush()?;
+        outputs.push(path);
+    }
+
+    info!("analyzed {}", dump.display());
+    Ok(IndexEntry {
+        dump: dump.to_path_buf(),
+        error: None,
+        timestamp: Some(index.header.timestamp.to_rfc3339()),
+        objects: Some(analyzed_heap.instances.len()),
+        shallow_size: Some(analyzed_heap.total_shallow_size()),
+        outputs,
+    })
+}
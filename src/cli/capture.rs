@@ -0,0 +1,65 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use anyhow::Result;
+use clap::Args;
+use heapdump_analyzer::{
+    capture::{Pod, dump_local, dump_pod},
+    config::Config,
+};
+
+use crate::cli::summary::{self, SummaryArgs};
+
+#[derive(Args)]
+pub struct CaptureArgs {
+    /// Process id of the JVM to dump, defaults to 1 inside a pod
+    #[arg(long, required_unless_present = "k8s")]
+    pid: Option<u32>,
+
+    /// Dump the JVM in this Kubernetes pod, given as name or pod/name
+    #[arg(long, value_name = "POD")]
+    k8s: Option<String>,
+
+    /// Container of the pod
+    #[arg(short, long, requires = "k8s")]
+    container: Option<String>,
+
+    /// Namespace of the pod
+    #[arg(short, long, requires = "k8s")]
+    namespace: Option<String>,
+
+    /// Gzip the dump inside the pod while copying it out
+    #[arg(long, requires = "k8s")]
+    compress: bool,
+
+    /// Only dump reachable objects, which runs a full GC in the JVM first
+    #[arg(long)]
+    live: bool,
+
+    /// Keep the dump at this path instead of deleting it after the analysis
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: &CaptureArgs, config: Config) -> Result<ExitCode> {
+    // the temporary directory takes the sidecar index with it
+    let dir = tempfile::Builder::new()
+        .prefix("heapdump-analyzer-")
+        .tempdir()?;
+    let dump = match &args.output {
+        Some(output) => output.clone(),
+        None => dir.path().join("capture.hprof"),
+    };
+
+    match &args.k8s {
+        Some(pod) => {
+            let pod = Pod {
+                name: pod.clone(),
+                namespace: args.namespace.clone(),
+                container: args.container.clone(),
+            };
+            dump_pod(&pod, args.pid.unwrap_or(1), args.live, args.compress, &dump)?
+        }
+        None => dump_local(args.pid.expect("required without --k8s"), args.live, &dump)?,
+    }
+    summary::analyze(&SummaryArgs::default(), &dump, config)
+}
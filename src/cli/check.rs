@@ -0,0 +1,123 @@
+use std::{collections::HashMap, path::PathBuf, process::ExitCode};
+
+use anyhow::{Context, Result};
+use clap::Args;
+use heapdump_analyzer::{
+    analyzer::{AnalyzedHeap, dominator::DominatorTree},
+    config::Config,
+    output::parse_bytes,
+};
+use serde::Serialize;
+
+use crate::cli::open_heap;
+
+#[derive(Args)]
+pub struct CheckArgs {
+    dump: PathBuf,
+
+    /// Maximum retained size of all instances of a class, e.g. com.foo.Cache=512MB (repeatable)
+    #[arg(long, value_parser = parse_class_limit)]
+    max_retained: Vec<(String, u64)>,
+
+    /// Maximum retained size of everything reachable from gc roots, e.g. 4GB. garbage the next
+    /// collection would free doesn't count
+    #[arg(long, value_parser = parse_bytes)]
+    max_heap: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct CheckReport {
+    dump: PathBuf,
+    passed: bool,
+    violations: Vec<Violation>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "rule", rename_all = "kebab-case")]
+pub enum Violation {
+    MaxHeap {
+        limit: u64,
+        actual: u64,
+    },
+    MaxRetained {
+        class: String,
+        limit: u64,
+        actual: u64,
+    },
+}
+
+pub fn parse_class_limit(s: &str) -> Result<(String, u64)> {
+    let (class, limit) = s
+        .rsplit_once('=')
+        .context("expected <class>=<size>, e.g. com.foo.Cache=512MB")?;
+    Ok((class.to_string(), parse_bytes(limit)?))
+}
+
+pub fn run(args: &CheckArgs, config: &Config) -> Result<ExitCode> {
+    let dominators = args.max_heap.is_some() || !args.max_retained.is_empty();
+    let index = open_heap(&args.dump, config, dominators)?;
+    let analyzed_heap = &index.heap;
+
+    let violations = violations(analyzed_heap, args.max_heap, &args.max_retained);
+
+    let report = CheckReport {
+        dump: args.dump.clone(),
+        passed: violations.is_empty(),
+        violations,
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    Ok(if report.passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+// the dominator tree is only computed when there are limits
+pub fn violations(
+    analyzed_heap: &AnalyzedHeap,
+    max_heap: Option<u64>,
+    max_retained: &[(String, u64)],
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    if let Some(limit) = max_heap {
+        let actual = analyzed_heap.dominator_tree().reachable_size();
+        if actual > limit {
+            violations.push(Violation::MaxHeap { limit, actual });
+        }
+    }
+
+    if !max_retained.is_empty() {
+        let retained = retained_by_class_name(analyzed_heap, analyzed_heap.dominator_tree());
+        for (class, limit) in max_retained {
+            let actual = retained.get(class).copied().unwrap_or(0);
+            if actual > *limit {
+                violations.push(Violation::MaxRetained {
+                    class: class.clone(),
+                    limit: *limit,
+                    actual,
+                });
+            }
+        }
+    }
+
+    violations
+}
+
+// classes loaded by different class loaders share a name, their sizes are summed up
+fn retained_by_class_name(
+    analyzed_heap: &AnalyzedHeap,
+    dominator_tree: &DominatorTree,
+) -> HashMap<String, u64> {
+    let mut retained = HashMap::new();
+
+    for (class_id, size) in dominator_tree.retained_by_class(analyzed_heap) {
+        if let Some(class) = analyzed_heap.classes.get(&class_id) {
+            *retained.entry(class.java_name()).or_default() += size;
+        }
+    }
+
+    retained
+}
@@ -0,0 +1,247 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use heapdump_analyzer::{
+    analyzer::{
+        contents::Contents,
+        diff::{
+            ClassChange, ClassDiff, ClassTotals, DiffOrder, DiffSide, ObjectDiffReport,
+            class_totals, histogram_diff, object_diff,
+        },
+        sample::Sample,
+    },
+    config::Config,
+    output::{
+        Style, parse_bytes,
+        table::{Cell, Column, Table},
+    },
+};
+
+use crate::cli::{ignore_broken_pipe, local_dump, open_heap};
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// The earlier dump
+    before: PathBuf,
+
+    /// The later dump
+    after: PathBuf,
+
+    /// Compare the class histograms instead of matching objects, without building the
+    /// reference graphs unless --retained is given
+    #[arg(long)]
+    histogram: bool,
+
+    /// Also compare retained sizes, which needs the dominator trees of both dumps
+    #[arg(long, requires = "histogram")]
+    retained: bool,
+
+    /// Sort by the growth of: shallow, count or retained
+    #[arg(long, default_value = "shallow", requires = "histogram")]
+    sort: SortKey,
+
+    /// Only match objects retaining at least this much in either dump, like 1M
+    #[arg(long, default_value = "1M", value_parser = parse_bytes, conflicts_with = "histogram")]
+    min_retained: u64,
+
+    /// Only show classes starting with this prefix (repeatable)
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Hide classes starting with this prefix (repeatable)
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// Number of rows
+    #[arg(long)]
+    rows: Option<usize>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SortKey {
+    Shallow,
+    Count,
+    Retained,
+}
+
+impl From<SortKey> for DiffOrder {
+    fn from(key: SortKey) -> Self {
+        match key {
+            SortKey::Shallow => DiffOrder::Shallow,
+            SortKey::Count => DiffOrder::Count,
+            SortKey::Retained => DiffOrder::Retained,
+        }
+    }
+}
+
+pub fn run(args: &DiffArgs, config: &Config) -> Result<ExitCode> {
+    let mut config = config.clone();
+    if !args.include.is_empty() {
+        config.filters.include = args.include.clone();
+    }
+    if !args.exclude.is_empty() {
+        config.filters.exclude = args.exclude.clone();
+    }
+    if let Some(rows) = args.rows {
+        config.output.rows = rows;
+    }
+    if !args.histogram {
+        return run_objects(args, &config);
+    }
+
+    let (before, after) = rayon::join(
+        || totals(&args.before, &config, args.retained),
+        || totals(&args.after, &config, args.retained),
+    );
+    let diffs = histogram_diff(&before?, &after?, args.sort.into());
+
+    let style = Style::detect(config.output.color);
+    let mut out = std::io::stdout().lock();
+    ignore_broken_pipe(print_histogram_diff(
+        &mut out,
+        &style,
+        &config,
+        &diffs,
+        args.retained,
+    ))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+// objects matched by where they sit in the dominator tree, see object_diff
+fn run_objects(args: &DiffArgs, config: &Config) -> Result<ExitCode> {
+    let open = |dump: &Path| -> Result<_> {
+        let dump = local_dump(dump, config)?;
+        let index = open_heap(&dump, config, true)?;
+        let contents = Contents::open(&dump, &index.heap)?;
+        Ok((index, contents))
+    };
+    let (before, after) = rayon::join(|| open(&args.before), || open(&args.after));
+    let ((before, before_contents), (after, after_contents)) = (before?, after?);
+    let mut report = object_diff(
+        DiffSide {
+            heap: &before.heap,
+            contents: &before_contents,
+        },
+        DiffSide {
+            heap: &after.heap,
+            contents: &after_contents,
+        },
+        args.min_retained,
+    )?;
+    report
+        .grown
+        .retain(|diff| config.filters.matches(&diff.class_name));
+
+    let style = Style::detect(config.output.color);
+    let mut out = std::io::stdout().lock();
+    ignore_broken_pipe(print_object_diff(&mut out, &style, config, &report))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+// a histogram of every object only takes parsing the dump, retained sizes take the whole analysis
+fn totals(dump: &Path, config: &Config, retained: bool) -> Result<HashMap<String, ClassTotals>> {
+    if retained {
+        let index = open_heap(dump, config, true)?;
+        let retained_by_class = index.heap.dominator_tree().retained_by_class(&index.heap);
+        Ok(class_totals(
+            &index.heap.histogram(&config.filters),
+            Some(&retained_by_class),
+        ))
+    } else {
+        let (_, sample) = Sample::file(&local_dump(dump, config)?, config.size_model, 1.0)?;
+        Ok(class_totals(&sample.histogram(&config.filters), None))
+    }
+}
+
+pub fn print_histogram_diff(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    diffs: &[ClassDiff],
+    retained: bool,
+) -> Result<()> {
+    if diffs.is_empty() {
+        writeln!(w, "No differences found")?;
+        return Ok(());
+    }
+
+    let mut columns = vec![
+        Column::flexible("Class"),
+        Column::left("Change"),
+        Column::right("Δ Objects"),
+        Column::right("Δ Shallow"),
+    ];
+    if retained {
+        columns.push(Column::right("Δ Retained"));
+    }
+    columns.push(Column::right("Shallow after"));
+    let mut table = Table::new(columns);
+
+    for diff in diffs.iter().take(config.output.rows) {
+        let change = match diff.change {
+            ClassChange::New => "new",
+            ClassChange::Gone => "gone",
+            ClassChange::Changed => "",
+        };
+        let mut row = vec![
+            Cell::Text(diff.class_name.clone()),
+            Cell::Text(change.to_string()),
+            Cell::CountDelta(diff.instance_delta()),
+            Cell::BytesDelta(diff.shallow_delta()),
+        ];
+        if retained {
+            row.push(Cell::BytesDelta(diff.retained_delta().unwrap_or(0)));
+        }
+        row.push(Cell::Bytes(diff.after.shallow_size));
+        table.add_row(row);
+    }
+
+    table.write(w, style, config.output.format)
+}
+
+pub fn print_object_diff(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    report: &ObjectDiffReport,
+) -> Result<()> {
+    writeln!(
+        w,
+        "{} objects matched, {} ambiguous, {} grew",
+        report.matched,
+        report.ambiguous,
+        report.grown.len()
+    )?;
+    if report.grown.is_empty() {
+        return Ok(());
+    }
+    writeln!(w)?;
+
+    let mut table = Table::new(vec![
+        Column::flexible("Object"),
+        Column::right("Δ Elements"),
+        Column::right("Δ Retained"),
+        Column::right("Retained after"),
+        Column::left("Id after"),
+    ]);
+    for diff in report.grown.iter().take(config.output.rows) {
+        table.add_row(vec![
+            Cell::Text(diff.key.clone()),
+            match diff.element_delta() {
+                Some(delta) => Cell::CountDelta(delta),
+                None => Cell::Text(String::new()),
+            },
+            Cell::BytesDelta(diff.retained_delta()),
+            Cell::Bytes(diff.after.retained_size),
+            Cell::Text(diff.after.id.to_string()),
+        ]);
+    }
+
+    table.write(w, style, config.output.format)
+}
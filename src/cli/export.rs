@@ -0,0 +1,258 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use heapdump_analyzer::{
+    analyzer::{
+        AnalyzedHeap,
+        dominator::DominatorTree,
+        leaks::{DEFAULT_THRESHOLD, leak_suspects},
+    },
+    config::Config,
+    export::{
+        for_each_object, for_each_string,
+        json::{Report, ReportOptions, report},
+        ndjson::RowWriter,
+        proto::analysis_result,
+        sqlite::write_database,
+        xlsx::write_workbook,
+    },
+    output::{
+        Style,
+        table::{Cell, Column, Table},
+    },
+    parser::{Id, RecordReader},
+};
+use prost::Message;
+use tracing::info;
+
+use crate::cli::{ignore_broken_pipe, storage, summary::print_histogram};
+
+#[derive(Args)]
+pub struct ExportArgs {
+    dump: PathBuf,
+
+    /// What to export
+    #[arg(long)]
+    what: ExportData,
+
+    /// Stream one JSON object per line instead of writing a single JSON array
+    #[arg(long)]
+    ndjson: bool,
+
+    /// Levels of the dominator tree included in analysis and xlsx exports
+    #[arg(long, default_value_t = 3)]
+    depth: usize,
+
+    /// Children listed per dominator tree node, defaults to the configured number of rows
+    #[arg(long)]
+    children: Option<usize>,
+
+    /// Output file, defaults to stdout
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportData {
+    /// Every object with its class and shallow size
+    Objects,
+    /// Every string of the dump's string table
+    Strings,
+    /// Summary, histogram, classes, dominator tree and threads as a single JSON document
+    Analysis,
+    /// Full class histogram as a table, see --format
+    Histogram,
+    /// Objects only dominated by the gc roots as a table, see --format
+    Dominators,
+    /// Classes, objects, references, strings, threads and gc roots as an SQLite database, requires --out
+    Sqlite,
+    /// Summary, histogram, dominators and leak suspects as an AnalysisResult protobuf message,
+    /// see proto/analysis.proto
+    Protobuf,
+    /// Summary, histogram, dominator tree, duplicate strings and threads as an Excel workbook,
+    /// requires --out
+    Xlsx,
+}
+
+pub fn run(args: &ExportArgs, config: &Config) -> Result<ExitCode> {
+    // file formats that can't be streamed to stdout
+    match args.what {
+        ExportData::Sqlite => {
+            let path = args
+                .out
+                .as_ref()
+                .context("--out is required for sqlite exports")?;
+            let counts = write_database(RecordReader::open(&args.dump)?, &config.size_model, path)?;
+            info!(
+                "exported {} classes, {} objects and {} references to {}",
+                counts.classes,
+                counts.objects,
+                counts.references,
+                path.display()
+            );
+            return Ok(ExitCode::SUCCESS);
+        }
+        ExportData::Xlsx => {
+            let path = args
+                .out
+                .as_ref()
+                .context("--out is required for xlsx exports")?;
+            let (report, analyzed_heap) = analysis_report(args, config)?;
+            write_workbook(&report, &analyzed_heap, path)?;
+            info!("exported {}", path.display());
+            return Ok(ExitCode::SUCCESS);
+        }
+        _ => {}
+    }
+
+    let w: Box<dyn Write> = match &args.out {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(std::io::stdout().lock())),
+    };
+
+    ignore_broken_pipe(export(args, config, w))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn export(args: &ExportArgs, config: &Config, w: impl Write) -> Result<()> {
+    match args.what {
+        ExportData::Objects | ExportData::Strings => export_rows(args, config, w),
+        ExportData::Analysis => export_analysis(args, config, w),
+        ExportData::Histogram | ExportData::Dominators => export_table(args, config, w),
+        ExportData::Protobuf => export_protobuf(args, config, w),
+        ExportData::Sqlite | ExportData::Xlsx => {
+            unreachable!("file exports don't write to a stream")
+        }
+    }
+}
+
+fn export_rows(args: &ExportArgs, config: &Config, w: impl Write) -> Result<()> {
+    let records = RecordReader::open(&args.dump)?;
+    let mut writer = RowWriter::new(w, args.ndjson);
+
+    match args.what {
+        ExportData::Objects => {
+            for_each_object(records, &config.size_model, |row| writer.write_row(&row))?
+        }
+        _ => for_each_string(records, |row| writer.write_row(&row))?,
+    }
+
+    let rows = writer.finish()?;
+    info!("exported {} rows", rows);
+    Ok(())
+}
+
+// the report and the heap it was made of
+fn analysis_report(args: &ExportArgs, config: &Config) -> Result<(Report, AnalyzedHeap)> {
+    let storage = storage(&args.dump, config, true)?;
+    let (header, analyzed_heap) =
+        AnalyzedHeap::analyze_file(&args.dump, config.size_model, &storage)?;
+    let options = ReportOptions {
+        filter: &config.filters,
+        depth: args.depth,
+        children: args.children.unwrap_or(config.output.rows),
+    };
+
+    let dominator_tree = analyzed_heap.dominator_tree();
+    let report = report(&header, &analyzed_heap, dominator_tree, &options);
+    Ok((report, analyzed_heap))
+}
+
+fn export_analysis(args: &ExportArgs, config: &Config, mut w: impl Write) -> Result<()> {
+    let (report, _) = analysis_report(args, config)?;
+    w.write_all(&serde_json::to_vec_pretty(&report)?)?;
+    writeln!(w)?;
+    w.flush()?;
+    Ok(())
+}
+
+// tables are written in the configured output format, csv being the one meant for spreadsheets
+fn export_table(args: &ExportArgs, config: &Config, mut w: impl Write) -> Result<()> {
+    let dominators = !matches!(args.what, ExportData::Histogram);
+    let storage = storage(&args.dump, config, dominators)?;
+    let (_, analyzed_heap) = AnalyzedHeap::analyze_file(&args.dump, config.size_model, &storage)?;
+    let style = Style::plain();
+
+    match args.what {
+        ExportData::Histogram => {
+            let mut config = config.clone();
+            config.output.rows = usize::MAX;
+            print_histogram(&mut w, &style, &config, &analyzed_heap)?;
+        }
+        _ => {
+            dominators_table(&analyzed_heap, analyzed_heap.dominator_tree()).write(
+                &mut w,
+                &style,
+                config.output.format,
+            )?;
+        }
+    }
+
+    w.flush()?;
+    Ok(())
+}
+
+fn dominators_table(heap: &AnalyzedHeap, dominator_tree: &DominatorTree) -> Table {
+    let mut table = Table::new(vec![
+        Column::left("Object"),
+        Column::flexible("Class"),
+        Column::right("Shallow"),
+        Column::right("Retained"),
+        Column::left("% of reachable heap"),
+    ]);
+
+    let reachable = dominator_tree.reachable_size();
+    let children = dominator_tree.children();
+    for id in children.get(&Id(0)).into_iter().flatten() {
+        let instance = heap.instance(*id);
+        let retained = dominator_tree.retained_size(*id).unwrap_or(0);
+        table.add_row(vec![
+            Cell::Text(id.to_string()),
+            // class objects aren't instances, name them after the class they represent
+            Cell::Text(
+                instance
+                    .map(|i| i.class.java_name())
+                    .or_else(|| {
+                        heap.classes
+                            .get(id)
+                            .map(|c| format!("class {}", c.java_name()))
+                    })
+                    .unwrap_or_default(),
+            ),
+            Cell::Bytes(instance.map_or(0, |i| i.shallow_size)),
+            Cell::Bytes(retained),
+            Cell::Percent {
+                part: retained,
+                total: reachable,
+            },
+        ]);
+    }
+
+    table
+}
+
+fn export_protobuf(args: &ExportArgs, config: &Config, mut w: impl Write) -> Result<()> {
+    let storage = storage(&args.dump, config, true)?;
+    let (header, analyzed_heap) =
+        AnalyzedHeap::analyze_file(&args.dump, config.size_model, &storage)?;
+    let dominator_tree = analyzed_heap.dominator_tree();
+    let suspects = leak_suspects(&analyzed_heap, dominator_tree, DEFAULT_THRESHOLD);
+
+    let result = analysis_result(
+        &header,
+        &analyzed_heap,
+        dominator_tree,
+        &suspects,
+        &config.filters,
+        config.output.rows,
+    );
+    w.write_all(&result.encode_to_vec())?;
+    w.flush()?;
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::{io::Write, path::PathBuf, process::ExitCode};
+
+use anyhow::{Result, bail};
+use clap::Args;
+use heapdump_analyzer::{
+    analyzer::{
+        AnalyzedHeap,
+        contents::Contents,
+        predicate::{FieldPredicate, find_instances},
+    },
+    config::Config,
+    output::{
+        OutputFormat, Style, human_bytes, human_count,
+        table::{Cell, Column, Table},
+    },
+    parser::Id,
+};
+
+use crate::cli::{ignore_broken_pipe, local_dump, open_heap};
+
+#[derive(Args)]
+pub struct FindArgs {
+    dump: PathBuf,
+
+    /// Java or internal class name, e.g. com.example.HttpRequest
+    class: String,
+
+    /// Condition on a field like "url contains 'payments'" or "port == 8080", all have to hold.
+    /// Operators are ==, !=, contains, startswith, endswith, <, <=, > and >=
+    #[arg(long = "where", value_name = "CONDITION", required = true)]
+    predicates: Vec<FieldPredicate>,
+
+    /// Number of rows
+    #[arg(long)]
+    rows: Option<usize>,
+}
+
+pub fn run(args: &FindArgs, config: &Config) -> Result<ExitCode> {
+    let dump = local_dump(&args.dump, config)?;
+    let index = open_heap(&dump, config, true)?;
+    let class_ids: Vec<Id> = index
+        .heap
+        .find_classes_by_name(&args.class)
+        .iter()
+        .map(|c| c.id)
+        .collect();
+    if class_ids.is_empty() {
+        bail!("class {} not found", args.class);
+    }
+    let contents = Contents::open(&dump, &index.heap)?;
+    let found = find_instances(&index.heap, &contents, &class_ids, &args.predicates)?;
+
+    let mut config = config.clone();
+    if let Some(rows) = args.rows {
+        config.output.rows = rows;
+    }
+    let style = Style::detect(config.output.color);
+    let mut out = std::io::stdout().lock();
+    ignore_broken_pipe(print_found(&mut out, &style, &config, &index.heap, &found))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+// largest retained size first
+pub fn print_found(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    heap: &AnalyzedHeap,
+    found: &[Id],
+) -> Result<()> {
+    if found.is_empty() {
+        writeln!(w, "No matching instances found")?;
+        return Ok(());
+    }
+
+    let dominator_tree = heap.dominator_tree();
+    let mut rows: Vec<(Id, String, u64, u64)> = found
+        .iter()
+        .filter_map(|id| heap.instance(*id))
+        .map(|instance| {
+            (
+                instance.id,
+                instance.class.java_name(),
+                instance.shallow_size,
+                dominator_tree.retained_size(instance.id).unwrap_or(0),
+            )
+        })
+        .collect();
+    rows.sort_by(|a, b| b.3.cmp(&a.3).then_with(|| a.0.cmp(&b.0)));
+
+    let mut table = Table::new(vec![
+        Column::left("Object"),
+        Column::flexible("Class"),
+        Column::right("Shallow"),
+        Column::right("Retained"),
+    ]);
+    for (id, class_name, shallow_size, retained_size) in rows.iter().take(config.output.rows) {
+        table.add_row(vec![
+            Cell::Text(id.to_string()),
+            Cell::Text(class_name.clone()),
+            Cell::Bytes(*shallow_size),
+            Cell::Bytes(*retained_size),
+        ]);
+    }
+    table.write(w, style, config.output.format)?;
+
+    // retained sizes aren't summed, one match may retain another. tsv and csv keep to the rows
+    if config.output.format == OutputFormat::Table {
+        writeln!(
+            w,
+            "{} matching instances, {} shallow",
+            human_count(rows.len() as u64),
+            human_bytes(rows.iter().map(|r| r.2).sum()),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use std::{io::Write, path::PathBuf, process::ExitCode};
+
+use anyhow::{Result, bail};
+use clap::Args;
+use heapdump_analyzer::{
+    analyzer::{
+        contents::Contents,
+        group::{FieldBucket, NumericStats, group_by_field, numeric_stats},
+    },
+    config::Config,
+    output::{
+        Style,
+        table::{Cell, Column, Table},
+    },
+    parser::Id,
+};
+
+use crate::cli::{ignore_broken_pipe, local_dump, open_heap};
+
+#[derive(Args)]
+pub struct GroupArgs {
+    dump: PathBuf,
+
+    /// Java or internal class name, e.g. com.example.ConnectionImpl
+    class: String,
+
+    /// Field to group by, or a path through object fields like address.host
+    field: String,
+
+    /// Print min, max, mean and percentiles of a numeric field instead of grouping by it
+    #[arg(long)]
+    stats: bool,
+
+    /// Number of rows
+    #[arg(long)]
+    rows: Option<usize>,
+}
+
+pub fn run(args: &GroupArgs, config: &Config) -> Result<ExitCode> {
+    let dump = local_dump(&args.dump, config)?;
+    let index = open_heap(&dump, config, true)?;
+    let class_ids: Vec<Id> = index
+        .heap
+        .find_classes_by_name(&args.class)
+        .iter()
+        .map(|c| c.id)
+        .collect();
+    if class_ids.is_empty() {
+        bail!("class {} not found", args.class);
+    }
+    let contents = Contents::open(&dump, &index.heap)?;
+
+    let mut config = config.clone();
+    if let Some(rows) = args.rows {
+        config.output.rows = rows;
+    }
+    let style = Style::detect(config.output.color);
+    let mut out = std::io::stdout().lock();
+
+    if args.stats {
+        let Some(stats) = numeric_stats(&index.heap, &contents, &class_ids, &args.field)? else {
+            bail!("no instances with a numeric {} field found", args.field);
+        };
+        ignore_broken_pipe(print_stats(&mut out, &style, &config, &stats))?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let buckets = group_by_field(&index.heap, &contents, &class_ids, &args.field)?;
+    ignore_broken_pipe(print_buckets(
+        &mut out,
+        &style,
+        &config,
+        &args.field,
+        &buckets,
+    ))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn print_buckets(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    field: &str,
+    buckets: &[FieldBucket],
+) -> Result<()> {
+    if buckets.is_empty() {
+        writeln!(w, "No instances with a {} field found", field)?;
+        return Ok(());
+    }
+
+    let mut table = Table::new(vec![
+        Column::flexible(field),
+        Column::right("Instances"),
+        Column::left("% of instances"),
+        Column::right("Retained"),
+    ]);
+    let total = buckets.iter().map(|b| b.instances).sum();
+    for bucket in buckets.iter().take(config.output.rows) {
+        table.add_row(vec![
+            Cell::Text(bucket.value.clone()),
+            Cell::Count(bucket.instances),
+            Cell::Percent {
+                part: bucket.instances,
+                total,
+            },
+            Cell::Bytes(bucket.retained_size),
+        ]);
+    }
+
+    table.write(w, style, config.output.format)
+}
+
+pub fn print_stats(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    stats: &NumericStats,
+) -> Result<()> {
+    let mut table = Table::new(vec![Column::left("Statistic"), Column::right("Value")]);
+    table.add_row(vec![
+        Cell::Text("instances".to_string()),
+        Cell::Count(stats.instances),
+    ]);
+    for (name, value) in [
+        ("min", stats.min),
+        ("max", stats.max),
+        ("mean", stats.mean),
+        ("p50", stats.p50),
+        ("p90", stats.p90),
+        ("p99", stats.p99),
+    ] {
+        // whole numbers like counts and ids without a fraction
+        let value = if value.fract() == 0.0 && value.abs() < 1e15 {
+            format!("{}", value as i64)
+        } else {
+            format!("{:.3}", value)
+        };
+        table.add_row(vec![Cell::Text(name.to_string()), Cell::Text(value)]);
+    }
+
+    table.write(w, style, config.output.format)
+}
@@ -0,0 +1,21 @@
+use std::{net::SocketAddr, process::ExitCode};
+
+use anyhow::Result;
+use clap::Args;
+use heapdump_analyzer::{
+    config::Config,
+    grpc::{AnalysisService, serve},
+};
+
+#[derive(Args)]
+pub struct GrpcArgs {
+    /// Address to serve the analysis service on
+    #[arg(long, default_value = "127.0.0.1:50051")]
+    listen: SocketAddr,
+}
+
+pub fn run(args: &GrpcArgs, config: &Config) -> Result<ExitCode> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(serve(args.listen, AnalysisService::new(config.clone())))?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,39 @@
+use std::{io::Write, path::PathBuf, process::ExitCode, str::FromStr};
+
+use anyhow::{Result, bail};
+use clap::Args;
+use heapdump_analyzer::{Heap, config::Config, display, parser::Id};
+
+use crate::cli::{ignore_broken_pipe, local_dump, open_heap};
+
+#[derive(Args)]
+pub struct InspectArgs {
+    dump: PathBuf,
+
+    /// Object or class id, hex with 0x prefix or decimal
+    #[arg(required = true, value_parser = Id::from_str)]
+    ids: Vec<Id>,
+
+    /// Show retained sizes, computing the dominator tree if the index doesn't have it
+    #[arg(long)]
+    retained: bool,
+}
+
+pub fn run(args: &InspectArgs, config: &Config) -> Result<ExitCode> {
+    let dump = local_dump(&args.dump, config)?;
+    let index = open_heap(&dump, config, args.retained)?;
+    let heap = Heap::from_index(&dump, index);
+
+    let mut rendered = Vec::new();
+    for id in &args.ids {
+        rendered.push(match (heap.class(*id), heap.object(*id)) {
+            (Some(class), _) => display::class(&heap, class).to_string(),
+            (None, Some(object)) => display::object(&object)?.to_string(),
+            (None, None) => bail!("object {} not found", id),
+        });
+    }
+
+    let mut out = std::io::stdout().lock();
+    ignore_broken_pipe(writeln!(out, "{}", rendered.join("\n\n")).map_err(Into::into))?;
+    Ok(ExitCode::SUCCESS)
+}
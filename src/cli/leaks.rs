@@ -0,0 +1,100 @@
+use std::{io::Write, path::PathBuf, process::ExitCode};
+
+use anyhow::Result;
+use clap::Args;
+use heapdump_analyzer::{
+    analyzer::{
+        caches::annotate_caches,
+        contents::Contents,
+        leaks::{DEFAULT_THRESHOLD, LeakSuspect, leak_suspects},
+    },
+    config::Config,
+    output::{
+        Style,
+        table::{Cell, Column, Table},
+    },
+};
+
+use crate::cli::{ignore_broken_pipe, local_dump, open_heap};
+
+#[derive(Args)]
+pub struct LeaksArgs {
+    dump: PathBuf,
+
+    /// Minimum share of the reachable heap a suspect has to retain, in percent
+    #[arg(long, default_value_t = DEFAULT_THRESHOLD * 100.0)]
+    threshold: f64,
+}
+
+pub fn run(args: &LeaksArgs, config: &Config) -> Result<ExitCode> {
+    let dump = local_dump(&args.dump, config)?;
+    let index = open_heap(&dump, config, true)?;
+    let dominator_tree = index.heap.dominator_tree();
+    let mut suspects = leak_suspects(&index.heap, dominator_tree, args.threshold / 100.0);
+    annotate_caches(
+        &mut suspects,
+        &index.heap,
+        &Contents::open(&dump, &index.heap)?,
+    )?;
+
+    let style = Style::detect(config.output.color);
+    let mut out = std::io::stdout().lock();
+    ignore_broken_pipe(print_leak_suspects(
+        &mut out,
+        &style,
+        config,
+        &suspects,
+        dominator_tree.reachable_size(),
+    ))?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn print_leak_suspects(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    suspects: &[LeakSuspect],
+    reachable_size: u64,
+) -> Result<()> {
+    if suspects.is_empty() {
+        writeln!(w, "No leak suspects found")?;
+        return Ok(());
+    }
+
+    // entry counts only for reports with caches among the suspects
+    let caches = suspects.iter().any(|s| s.cache.is_some());
+    let mut columns = vec![
+        Column::flexible("Suspect"),
+        Column::right("Retained"),
+        Column::left("% of reachable heap"),
+    ];
+    if caches {
+        columns.extend([Column::right("Entries"), Column::right("Avg entry")]);
+    }
+    let mut table = Table::new(columns);
+
+    for suspect in suspects {
+        let mut row = vec![
+            Cell::Text(suspect.describe()),
+            Cell::Bytes(suspect.retained_size),
+            Cell::Percent {
+                part: suspect.retained_size,
+                total: reachable_size,
+            },
+        ];
+        if caches {
+            row.push(match suspect.cache.as_ref().and_then(|c| c.entries) {
+                Some(entries) => Cell::Count(entries),
+                None => Cell::Text(String::new()),
+            });
+            row.push(match suspect.average_entry_size() {
+                Some(size) => Cell::Bytes(size),
+                None => Cell::Text(String::new()),
+            });
+        }
+        table.add_row(row);
+    }
+
+    table.write(w, style, config.output.format)
+}
@@ -0,0 +1,24 @@
+use std::{io::BufReader, path::PathBuf, process::ExitCode};
+
+use anyhow::Result;
+use clap::Args;
+use heapdump_analyzer::{config::Config, mcp::serve};
+
+use crate::cli::open_heap;
+
+#[derive(Args)]
+pub struct McpArgs {
+    /// Dump the tools work on
+    dump: PathBuf,
+}
+
+// stdout carries the protocol, logs go to stderr
+pub fn run(args: &McpArgs, config: &Config) -> Result<ExitCode> {
+    let index = open_heap(&args.dump, config, false)?;
+    serve(
+        &index,
+        BufReader::new(std::io::stdin().lock()),
+        std::io::stdout().lock(),
+    )?;
+    Ok(ExitCode::SUCCESS)
+}
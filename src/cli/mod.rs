@@ -0,0 +1,386 @@
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand};
+use heapdump_analyzer::{
+    analyzer::{
+        budget::{MemoryCheck, MemoryEstimate, Strategy, available_memory},
+        index::HeapIndex,
+        storage::Storage,
+    },
+    archive,
+    config::Config,
+    error::Policy,
+    output::{ColorChoice, OutputFormat, parse_bytes},
+    remote,
+};
+use tracing::{info, warn};
+
+mod batch;
+mod capture;
+mod check;
+mod diff;
+mod export;
+mod find;
+mod group;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod inspect;
+mod leaks;
+mod mcp;
+mod referrers;
+mod report;
+mod retainers;
+mod scrub;
+#[cfg(feature = "http")]
+mod serve;
+mod slice;
+mod split;
+mod stats;
+mod summary;
+pub mod time;
+mod timeline;
+mod trend;
+mod visualvm;
+mod watch;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Analyze java heap dumps",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(flatten)]
+    global: GlobalArgs,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    // `heapdump-analyzer <dump>` is a shorthand for `heapdump-analyzer summary <dump>`
+    #[command(flatten)]
+    summary: summary::SummaryArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the heap summary and class histogram
+    Summary(summary::SummaryArgs),
+    /// Check a dump against size thresholds, exits with 1 on violations
+    Check(check::CheckArgs),
+    /// Analyze every dump in a directory
+    Batch(batch::BatchArgs),
+    /// Dump the heap of a running JVM, locally or in a Kubernetes pod, and print its summary
+    Capture(capture::CaptureArgs),
+    /// Stream objects or strings of a dump as JSON
+    Export(export::ExportArgs),
+    /// Print an object or class with its fields and resolved names
+    Inspect(inspect::InspectArgs),
+    /// Print objects and classes retaining a large part of the heap
+    Leaks(leaks::LeaksArgs),
+    /// Print who keeps the instances of a class alive, grouped by the class of their dominators
+    Retainers(retainers::RetainersArgs),
+    /// Group strings matching a regex, or the instances of a class, by the referrers holding them
+    Referrers(referrers::ReferrersArgs),
+    /// Count the instances of a class and what they retain by the value of a field, or with
+    /// --stats print the distribution of a numeric field
+    Group(group::GroupArgs),
+    /// List the instances of a class whose fields match conditions like "url contains 'payments'"
+    Find(find::FindArgs),
+    /// Compare two dumps of the same process, listing the objects that grew or with --histogram
+    /// the classes
+    Diff(diff::DiffArgs),
+    /// Print when the classes of a dump were loaded, or which were loaded shortly before it
+    Timeline(timeline::TimelineArgs),
+    /// Count the records and sub records of a dump by kind with the bytes they take
+    Stats(stats::StatsArgs),
+    /// Run the registered analyses and print their findings
+    Report(report::ReportArgs),
+    /// Analyze new dumps showing up in a directory
+    Watch(watch::WatchArgs),
+    /// Write the sizes of a series of dumps as JSON or CSV time series, e.g. for Grafana, or
+    /// with --leaks the classes growing across them
+    Trend(trend::TrendArgs),
+    /// Write a copy of a dump with string and array contents replaced by placeholders
+    Scrub(scrub::ScrubArgs),
+    /// Write a small dump containing only the given objects and what they reference or retain
+    Slice(slice::SliceArgs),
+    /// Split a dump into one file per heap dump segment
+    Split(split::SplitArgs),
+    /// Merge the parts written by split back into a single dump
+    Merge(split::MergeArgs),
+    /// Serve the analysis of a dump to AI assistants over the Model Context Protocol on stdio
+    Mcp(mcp::McpArgs),
+    /// Serve the analysis operations over gRPC
+    #[cfg(feature = "grpc")]
+    Grpc(grpc::GrpcArgs),
+    /// Serve the analysis of a dump as a JSON http api
+    #[cfg(feature = "http")]
+    Serve(serve::ServeArgs),
+}
+
+#[derive(Args)]
+struct GlobalArgs {
+    /// Config file, defaults to ~/.config/heapdump-analyzer/config.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Output format: table, tsv or csv
+    #[arg(long, global = true)]
+    format: Option<OutputFormat>,
+
+    /// When to use colors: auto, always or never
+    #[arg(long, global = true)]
+    color: Option<ColorChoice>,
+
+    /// Object header size in bytes
+    #[arg(long, global = true)]
+    object_header: Option<u64>,
+
+    /// Array header size in bytes
+    #[arg(long, global = true)]
+    array_header: Option<u64>,
+
+    /// Reference size in bytes, 4 with compressed oops
+    #[arg(long, global = true)]
+    reference_size: Option<u64>,
+
+    /// Object alignment in bytes
+    #[arg(long, global = true)]
+    alignment: Option<u64>,
+
+    /// Don't read or write the .hda-index file cached next to each dump
+    #[arg(long, global = true)]
+    no_index: bool,
+
+    /// Keep the reference graph in memory mapped files in this directory instead of RAM
+    #[arg(long, global = true)]
+    spill_dir: Option<PathBuf>,
+
+    /// Worker threads for the analysis, defaults to the number of cores
+    #[arg(long, global = true)]
+    threads: Option<usize>,
+
+    /// Memory budget like 8G, spills or falls back to a histogram for dumps that won't fit
+    #[arg(long, global = true, value_parser = parse_bytes)]
+    max_memory: Option<u64>,
+
+    /// Fail on records breaking the hprof format and on missing names or classes, instead of
+    /// skipping them with a warning
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// What to do when the dump looks like it needs more memory than is available: warn,
+    /// refuse or off
+    #[arg(long, global = true)]
+    memory_check: Option<MemoryCheck>,
+
+    /// Print the wall time of each phase, parse throughput and peak memory to stderr when done
+    #[arg(long, global = true)]
+    time: bool,
+}
+
+impl GlobalArgs {
+    // command line flags take precedence over the config file
+    fn merge_into(&self, config: &mut Config) {
+        if let Some(format) = self.format {
+            config.output.format = format;
+        }
+        if let Some(color) = self.color {
+            config.output.color = color;
+        }
+        if self.no_index {
+            config.index.enabled = false;
+        }
+        if let Some(dir) = &self.spill_dir {
+            config.analysis.spill_dir = Some(dir.clone());
+        }
+        if let Some(threads) = self.threads {
+            config.analysis.threads = Some(threads);
+        }
+        if let Some(max_memory) = self.max_memory {
+            config.analysis.max_memory = Some(max_memory);
+        }
+        if self.strict {
+            config.analysis.policy = Policy::Strict;
+        }
+        if let Some(memory_check) = self.memory_check {
+            config.analysis.memory_check = memory_check;
+        }
+
+        let size_model = &mut config.size_model;
+        size_model.object_header = self.object_header.unwrap_or(size_model.object_header);
+        size_model.array_header = self.array_header.unwrap_or(size_model.array_header);
+        size_model.reference_size = self.reference_size.unwrap_or(size_model.reference_size);
+        size_model.alignment = self.alignment.unwrap_or(size_model.alignment);
+    }
+}
+
+pub fn run() -> Result<ExitCode> {
+    let cli = Cli::parse();
+    let mut config = Config::load(cli.global.config.as_deref())?;
+    cli.global.merge_into(&mut config);
+    if let Some(threads) = config.analysis.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+
+    let result = match cli.command {
+        Some(Command::Summary(args)) => summary::run(&args, config),
+        Some(Command::Check(args)) => check::run(&args, &config),
+        Some(Command::Batch(args)) => batch::run(&args, &config),
+        Some(Command::Capture(args)) => capture::run(&args, config),
+        Some(Command::Export(args)) => export::run(&args, &config),
+        Some(Command::Inspect(args)) => inspect::run(&args, &config),
+        Some(Command::Leaks(args)) => leaks::run(&args, &config),
+        Some(Command::Retainers(args)) => retainers::run(&args, &config),
+        Some(Command::Referrers(args)) => referrers::run(&args, &config),
+        Some(Command::Group(args)) => group::run(&args, &config),
+        Some(Command::Find(args)) => find::run(&args, &config),
+        Some(Command::Diff(args)) => diff::run(&args, &config),
+        Some(Command::Timeline(args)) => timeline::run(&args, &config),
+        Some(Command::Stats(args)) => stats::run(&args, &config),
+        Some(Command::Report(args)) => report::run(&args, &config),
+        Some(Command::Watch(args)) => watch::run(&args, &config),
+        Some(Command::Trend(args)) => trend::run(&args, &config),
+        Some(Command::Scrub(args)) => scrub::run(&args),
+        Some(Command::Slice(args)) => slice::run(&args, &config),
+        Some(Command::Split(args)) => split::run_split(&args),
+        Some(Command::Merge(args)) => split::run_merge(&args),
+        Some(Command::Mcp(args)) => mcp::run(&args, &config),
+        #[cfg(feature = "grpc")]
+        Some(Command::Grpc(args)) => grpc::run(&args, &config),
+        #[cfg(feature = "http")]
+        Some(Command::Serve(args)) => serve::run(&args, &config),
+        None => summary::run(&cli.summary, config),
+    };
+    if cli.global.time {
+        time::report(&mut std::io::stderr().lock())?;
+    }
+    result
+}
+
+// output piped into e.g. `head` shouldn't end in an error
+fn ignore_broken_pipe(result: Result<()>) -> Result<()> {
+    match result {
+        Err(err)
+            if err
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe) =>
+        {
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+// analyzes the dump, going through the sidecar index unless it's disabled. commands using the
+// dominator tree ask for it up front, so it ends up in the index
+fn open_heap(dump: &Path, config: &Config, dominators: bool) -> Result<HeapIndex> {
+    let dump = local_dump(dump, config)?;
+    let options = config
+        .analysis_options()
+        .with_storage(storage(&dump, config, dominators)?)
+        .with_dominators(dominators);
+    let index = if config.index.enabled {
+        HeapIndex::open(&dump, &options)?
+    } else {
+        HeapIndex::build(&dump, &options)?
+    };
+    for diagnostic in &index.heap.diagnostics {
+        warn!("{}", diagnostic);
+    }
+    Ok(index)
+}
+
+// dumps given as http(s) or s3 urls are downloaded into the cache first, dumps inside zip or tar
+// archives are extracted there. "bundle.zip!/heap.hprof" picks an entry of the archive
+fn local_dump(dump: &Path, config: &Config) -> Result<PathBuf> {
+    let Some(location) = dump.to_str() else {
+        return Ok(dump.to_path_buf());
+    };
+    let (location, entry) = match archive::split_entry(location) {
+        Some((location, entry)) => (location, Some(entry)),
+        None => (location, None),
+    };
+    let path = if remote::is_remote(location) {
+        remote::fetch(location, &config.remote.cache_dir()?)?
+    } else {
+        PathBuf::from(location)
+    };
+    if entry.is_some() || archive::is_archive(&path)? {
+        archive::extract(&path, entry, &config.remote.cache_dir()?)
+    } else {
+        Ok(path)
+    }
+}
+
+// the configured storage, or a spill directory when the dump wouldn't fit the memory budget.
+// warns or fails when even that is expected to need more memory than is available
+fn storage(dump: &Path, config: &Config, dominators: bool) -> Result<Storage> {
+    let check = config.analysis.memory_check;
+    let available = match check {
+        MemoryCheck::Off => None,
+        _ => available_memory(),
+    };
+    if config.analysis.max_memory.is_none() && available.is_none() {
+        return Ok(config.storage());
+    }
+
+    let estimate = MemoryEstimate::scan(dump)?;
+    let strategy = match config.analysis.max_memory {
+        Some(budget) => estimate.choose(budget, dominators, false)?,
+        None => Strategy::Memory,
+    };
+    // a configured spill directory spills without a budget too
+    let stored = match (strategy, config.analysis.spill_dir.is_some()) {
+        (Strategy::Memory, true) => Strategy::Spill,
+        _ => strategy,
+    };
+    estimate.preflight(stored, dominators, available, check)?;
+
+    match strategy {
+        Strategy::Spill if config.analysis.spill_dir.is_none() => {
+            let dir = std::env::temp_dir();
+            info!(
+                "spilling to {} to stay under the memory budget",
+                dir.display()
+            );
+            Ok(Storage::Spill(dir))
+        }
+        _ => Ok(config.storage()),
+    }
+}
+
+// everything in memory unless there is a budget
+fn strategy(
+    dump: &Path,
+    config: &Config,
+    dominators: bool,
+    histogram_only: bool,
+) -> Result<Strategy> {
+    match config.analysis.max_memory {
+        Some(budget) => MemoryEstimate::scan(dump)?.choose(budget, dominators, histogram_only),
+        None => Ok(Strategy::Memory),
+    }
+}
+
+// .hprof files directly inside dir, sorted by path
+fn find_dumps(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut dumps = Vec::new();
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "hprof") {
+            dumps.push(path);
+        }
+    }
+
+    dumps.sort();
+    Ok(dumps)
+}
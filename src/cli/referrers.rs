@@ -0,0 +1,115 @@
+use std::{io::Write, path::PathBuf, process::ExitCode};
+
+use anyhow::{Result, bail};
+use clap::Args;
+use heapdump_analyzer::{
+    analyzer::{
+        contents::Contents,
+        referrers::{ReferrerChain, referrer_chains},
+    },
+    config::Config,
+    output::{
+        Style,
+        table::{Cell, Column, Table},
+    },
+    parser::Id,
+};
+use regex::Regex;
+use tracing::info;
+
+use crate::cli::{ignore_broken_pipe, local_dump, open_heap};
+
+#[derive(Args)]
+pub struct ReferrersArgs {
+    dump: PathBuf,
+
+    /// The java.lang.String objects whose contents match this regex
+    #[arg(long, value_name = "REGEX", required_unless_present = "class")]
+    strings: Option<Regex>,
+
+    /// The instances of this class, java or internal name
+    #[arg(long, conflicts_with = "strings")]
+    class: Option<String>,
+
+    /// Referrers followed out from each object
+    #[arg(long, default_value_t = 4)]
+    depth: usize,
+
+    /// Number of rows
+    #[arg(long)]
+    rows: Option<usize>,
+}
+
+pub fn run(args: &ReferrersArgs, config: &Config) -> Result<ExitCode> {
+    let dump = local_dump(&args.dump, config)?;
+    let index = open_heap(&dump, config, true)?;
+    let heap = &index.heap;
+    let contents = Contents::open(&dump, heap)?;
+
+    let class_name = match (&args.strings, &args.class) {
+        (Some(_), _) => "java.lang.String",
+        (None, Some(class)) => class.as_str(),
+        (None, None) => unreachable!("clap requires one of them"),
+    };
+    let classes = heap.find_classes_by_name(class_name);
+    if classes.is_empty() {
+        bail!("class {} not found", class_name);
+    }
+    let mut objects: Vec<Id> = Vec::new();
+    for class in classes {
+        for instance in heap.instances_of(class.id) {
+            let matches = match &args.strings {
+                Some(regex) => contents
+                    .string_value(heap, instance.id)?
+                    .is_some_and(|s| regex.is_match(&s)),
+                None => true,
+            };
+            if matches {
+                objects.push(instance.id);
+            }
+        }
+    }
+    info!("grouping the referrers of {} objects", objects.len());
+    let chains = referrer_chains(heap, &contents, &objects, args.depth)?;
+
+    let mut config = config.clone();
+    if let Some(rows) = args.rows {
+        config.output.rows = rows;
+    }
+    let style = Style::detect(config.output.color);
+    let mut out = std::io::stdout().lock();
+    ignore_broken_pipe(print_referrer_chains(&mut out, &style, &config, &chains))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn print_referrer_chains(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    chains: &[ReferrerChain],
+) -> Result<()> {
+    if chains.is_empty() {
+        writeln!(w, "No objects found")?;
+        return Ok(());
+    }
+
+    let mut table = Table::new(vec![
+        Column::flexible("Referrers"),
+        Column::right("Objects"),
+        Column::right("Retained"),
+    ]);
+    for chain in chains.iter().take(config.output.rows) {
+        let referrers = if chain.referrers.is_empty() {
+            "<gc roots>".to_string()
+        } else {
+            chain.referrers.join(" -> ")
+        };
+        table.add_row(vec![
+            Cell::Text(referrers),
+            Cell::Count(chain.objects),
+            Cell::Bytes(chain.retained_size),
+        ]);
+    }
+
+    table.write(w, style, config.output.format)
+}
@@ -0,0 +1,178 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result};
+use clap::Args;
+#[cfg(any(feature = "script", feature = "plugins"))]
+use heapdump_analyzer::analysis::Analysis;
+#[cfg(feature = "plugins")]
+use heapdump_analyzer::analysis::plugin::Plugin;
+#[cfg(feature = "script")]
+use heapdump_analyzer::analysis::script::Script;
+use heapdump_analyzer::{
+    analysis::{Registry, Report},
+    analyzer::AnalyzedHeap,
+    config::Config,
+    output::{
+        Color, OutputFormat, Style, human_bytes, human_count,
+        template::{Document, ReportDocument, Section, Templates},
+    },
+    parser::Header,
+};
+
+use crate::cli::{ignore_broken_pipe, local_dump, open_heap};
+
+#[derive(Args)]
+pub struct ReportArgs {
+    dump: PathBuf,
+
+    /// Only run this analysis and the ones it depends on (repeatable)
+    #[arg(long)]
+    only: Vec<String>,
+
+    /// Lua script run as an analysis, only scripts, plugins and --only analyses run when given
+    /// (repeatable)
+    #[cfg(feature = "script")]
+    #[arg(long)]
+    script: Vec<PathBuf>,
+
+    /// Wasm plugin run as an analysis, like --script (repeatable)
+    #[cfg(feature = "plugins")]
+    #[arg(long)]
+    plugin: Vec<PathBuf>,
+
+    /// Render the reports as an html or markdown document instead of printing tables
+    #[arg(long, value_name = "DOCUMENT")]
+    render: Option<Document>,
+
+    /// Directory with report.html or report.md templates replacing the built in ones
+    #[arg(long, requires = "render")]
+    template_dir: Option<PathBuf>,
+
+    /// Write the rendered document to this file instead of stdout
+    #[arg(long, requires = "render")]
+    out: Option<PathBuf>,
+
+    /// List the registered analyses instead of running them
+    #[arg(long)]
+    list: bool,
+}
+
+pub fn run(args: &ReportArgs, config: &Config) -> Result<ExitCode> {
+    #[allow(unused_mut)]
+    let mut registry = Registry::configured(config)?;
+    #[allow(unused_mut)]
+    let mut only = args.only.clone();
+    #[cfg(feature = "script")]
+    for path in &args.script {
+        let script = Script::load(path)?;
+        only.push(script.name().to_string());
+        registry.register(Box::new(script));
+    }
+    #[cfg(feature = "plugins")]
+    for path in &args.plugin {
+        let plugin = Plugin::load(path)?;
+        only.push(plugin.name().to_string());
+        registry.register(Box::new(plugin));
+    }
+    if args.list {
+        for name in registry.names() {
+            println!("{}", name);
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let dominators = registry.plan(&only)?.iter().any(|a| a.needs_dominators());
+    let dump = local_dump(&args.dump, config)?;
+    let index = open_heap(&dump, config, dominators)?;
+    let reports = registry.run(&only, &dump, &index.header, &index.heap, config)?;
+
+    if let Some(document) = args.render {
+        let template_dir = args
+            .template_dir
+            .as_deref()
+            .or(config.output.template_dir.as_deref());
+        let rendered = Templates::new(template_dir)?.render(
+            document,
+            &report_document(&args.dump, &index.header, &index.heap, &reports),
+        )?;
+        match &args.out {
+            Some(path) => std::fs::write(path, rendered)
+                .with_context(|| format!("failed to write {}", path.display()))?,
+            None => ignore_broken_pipe(
+                std::io::stdout()
+                    .lock()
+                    .write_all(rendered.as_bytes())
+                    .map_err(Into::into),
+            )?,
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let style = Style::detect(config.output.color);
+    let mut out = std::io::stdout().lock();
+    ignore_broken_pipe(print_reports(&mut out, &style, config, &reports))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn report_document(
+    dump: &Path,
+    header: &Header,
+    heap: &AnalyzedHeap,
+    reports: &[Report],
+) -> ReportDocument {
+    let shallow_size = heap.total_shallow_size();
+    ReportDocument {
+        dump: dump
+            .file_name()
+            .unwrap_or(dump.as_os_str())
+            .to_string_lossy()
+            .into_owned(),
+        summary: vec![
+            ("Version".to_string(), header.version.to_string()),
+            ("Timestamp".to_string(), header.timestamp.to_rfc3339()),
+            (
+                "Classes".to_string(),
+                human_count(heap.classes.len() as u64),
+            ),
+            (
+                "Objects".to_string(),
+                human_count(heap.instances.len() as u64),
+            ),
+            ("Shallow size".to_string(), human_bytes(shallow_size)),
+        ],
+        sections: reports
+            .iter()
+            .map(|r| Section::new(&r.title, &r.table, &r.notes))
+            .collect(),
+    }
+}
+
+fn print_reports(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    reports: &[Report],
+) -> Result<()> {
+    for (i, report) in reports.iter().enumerate() {
+        if i > 0 {
+            writeln!(w)?;
+        }
+        match config.output.format {
+            OutputFormat::Table => {
+                writeln!(w, "{}", style.paint(&report.title, Some(Color::Bold)))?
+            }
+            OutputFormat::Tsv | OutputFormat::Csv => writeln!(w, "# {}", report.title)?,
+        }
+        if !report.table.is_empty() {
+            report.table.write(w, style, config.output.format)?;
+        }
+        for note in &report.notes {
+            writeln!(w, "{}", note)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::{io::Write, path::PathBuf, process::ExitCode};
+
+use anyhow::{Result, bail};
+use clap::Args;
+use heapdump_analyzer::{
+    analyzer::retainers::{Retainer, top_retainers},
+    config::Config,
+    output::{
+        Style,
+        table::{Cell, Column, Table},
+    },
+    parser::Id,
+};
+
+use crate::cli::{ignore_broken_pipe, open_heap};
+
+#[derive(Args)]
+pub struct RetainersArgs {
+    dump: PathBuf,
+
+    /// Java or internal class name, e.g. java.util.HashMap$Node
+    class: String,
+
+    /// Look past retainers of jdk classes to the first one of application code
+    #[arg(long)]
+    skip_jdk: bool,
+
+    /// Number of rows
+    #[arg(long)]
+    rows: Option<usize>,
+}
+
+pub fn run(args: &RetainersArgs, config: &Config) -> Result<ExitCode> {
+    let index = open_heap(&args.dump, config, true)?;
+    let class_ids: Vec<Id> = index
+        .heap
+        .find_classes_by_name(&args.class)
+        .iter()
+        .map(|c| c.id)
+        .collect();
+    if class_ids.is_empty() {
+        bail!("class {} not found", args.class);
+    }
+    let retainers = top_retainers(
+        &index.heap,
+        index.heap.dominator_tree(),
+        &class_ids,
+        args.skip_jdk,
+    );
+
+    let mut config = config.clone();
+    if let Some(rows) = args.rows {
+        config.output.rows = rows;
+    }
+    let style = Style::detect(config.output.color);
+    let mut out = std::io::stdout().lock();
+    ignore_broken_pipe(print_retainers(&mut out, &style, &config, &retainers))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn print_retainers(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    retainers: &[Retainer],
+) -> Result<()> {
+    if retainers.is_empty() {
+        writeln!(w, "No reachable instances found")?;
+        return Ok(());
+    }
+
+    let mut table = Table::new(vec![
+        Column::flexible("Retainer"),
+        Column::right("Retainers"),
+        Column::right("Instances kept"),
+        Column::right("Retained"),
+        Column::left("Largest retainer"),
+    ]);
+    for retainer in retainers.iter().take(config.output.rows) {
+        table.add_row(vec![
+            Cell::Text(
+                retainer
+                    .class_name
+                    .clone()
+                    .unwrap_or_else(|| "<gc roots>".to_string()),
+            ),
+            Cell::Count(retainer.objects),
+            Cell::Count(retainer.instances),
+            Cell::Bytes(retainer.retained_size),
+            Cell::Text(
+                retainer
+                    .largest
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+            ),
+        ]);
+    }
+
+    table.write(w, style, config.output.format)
+}
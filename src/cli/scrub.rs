@@ -0,0 +1,27 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use anyhow::Result;
+use clap::Args;
+use heapdump_analyzer::transform::scrub::scrub;
+use tracing::info;
+
+#[derive(Args)]
+pub struct ScrubArgs {
+    /// Dump to scrub
+    input: PathBuf,
+
+    /// Path of the scrubbed dump
+    output: PathBuf,
+}
+
+pub fn run(args: &ScrubArgs) -> Result<ExitCode> {
+    let stats = scrub(&args.input, &args.output)?;
+    info!(
+        "replaced {} strings and {} arrays, wrote {}",
+        stats.strings,
+        stats.arrays,
+        args.output.display()
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,60 @@
+use std::{net::SocketAddr, path::PathBuf, process::ExitCode};
+
+use anyhow::Result;
+use clap::Args;
+use heapdump_analyzer::{
+    Heap,
+    analyzer::leaks::{DEFAULT_THRESHOLD, leak_suspects},
+    api::{metrics, router, ui},
+    config::Config,
+    export::prometheus::DumpMetrics,
+};
+use tracing::info;
+
+use crate::cli::open_heap;
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Dump to serve
+    dump: PathBuf,
+
+    /// Address to serve the http api on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: SocketAddr,
+
+    /// Only serve the json api, without the web ui on /
+    #[arg(long)]
+    no_ui: bool,
+}
+
+pub fn run(args: &ServeArgs, config: &Config) -> Result<ExitCode> {
+    let index = open_heap(&args.dump, config, true)?;
+    let name = args
+        .dump
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let dominator_tree = index.heap.dominator_tree();
+    let dump_metrics = DumpMetrics::new(
+        &name,
+        &index.heap,
+        dominator_tree,
+        &leak_suspects(&index.heap, dominator_tree, DEFAULT_THRESHOLD),
+        config.output.rows,
+        index.header.timestamp.timestamp() as u64,
+    );
+
+    let heap = Heap::from_index(&args.dump, index);
+    let mut app = router(heap).merge(metrics(vec![dump_metrics]));
+    if !args.no_ui {
+        app = app.merge(ui());
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(args.listen).await?;
+        info!("serving {} on http://{}", args.dump.display(), args.listen);
+        axum::serve(listener, app).await
+    })?;
+    Ok(ExitCode::SUCCESS)
+}
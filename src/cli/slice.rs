@@ -0,0 +1,94 @@
+use std::{path::PathBuf, process::ExitCode, str::FromStr};
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use heapdump_analyzer::{
+    analyzer::walk::Limits,
+    config::Config,
+    parser::Id,
+    transform::slice::{Closure, slice},
+};
+use tracing::{info, warn};
+
+use crate::cli::storage;
+
+#[derive(Args)]
+pub struct SliceArgs {
+    /// Dump to slice
+    input: PathBuf,
+
+    /// Path of the sliced dump
+    output: PathBuf,
+
+    /// Object id to include, hex with 0x prefix or decimal (repeatable)
+    #[arg(long = "id", required = true, value_parser = Id::from_str)]
+    ids: Vec<Id>,
+
+    /// Objects included besides the given ones
+    #[arg(long, default_value = "retained")]
+    with: With,
+
+    /// References followed from the given objects at most
+    #[arg(long)]
+    max_depth: Option<usize>,
+
+    /// Objects included at most
+    #[arg(long)]
+    max_objects: Option<usize>,
+
+    /// Shallow bytes of the included objects at most
+    #[arg(long)]
+    max_bytes: Option<u64>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum With {
+    /// Only the given objects
+    Nothing,
+    /// Everything reachable from the given objects
+    Reachable,
+    /// Everything retained by the given objects
+    Retained,
+}
+
+pub fn run(args: &SliceArgs, config: &Config) -> Result<ExitCode> {
+    let closure = match args.with {
+        With::Nothing => Closure::None,
+        With::Reachable => Closure::Reachable,
+        With::Retained => Closure::Retained,
+    };
+
+    let limits = Limits {
+        max_depth: args.max_depth,
+        max_objects: args.max_objects,
+        max_bytes: args.max_bytes,
+    };
+
+    let options = config.analysis_options().with_storage(storage(
+        &args.input,
+        config,
+        closure == Closure::Retained,
+    )?);
+    let stats = slice(
+        &args.input,
+        &args.output,
+        &args.ids,
+        closure,
+        &limits,
+        &options,
+    )?;
+    if let Some(truncation) = stats.truncated {
+        warn!(
+            "stopped at the {} limit, the slice is incomplete",
+            truncation
+        );
+    }
+    info!(
+        "wrote {} objects of {} classes to {}",
+        stats.objects,
+        stats.classes,
+        args.output.display()
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,44 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use anyhow::Result;
+use clap::Args;
+use heapdump_analyzer::transform::split::{merge, split};
+use tracing::info;
+
+#[derive(Args)]
+pub struct SplitArgs {
+    /// Dump to split
+    input: PathBuf,
+
+    /// Directory for the parts and their manifest.json
+    out_dir: PathBuf,
+}
+
+#[derive(Args)]
+pub struct MergeArgs {
+    /// Directory written by split
+    dir: PathBuf,
+
+    /// Path of the merged dump
+    output: PathBuf,
+}
+
+pub fn run_split(args: &SplitArgs) -> Result<ExitCode> {
+    let manifest = split(&args.input, &args.out_dir)?;
+    info!(
+        "split {} into {} parts",
+        args.input.display(),
+        manifest.parts.len()
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn run_merge(args: &MergeArgs) -> Result<ExitCode> {
+    let manifest = merge(&args.dir, &args.output)?;
+    info!(
+        "merged {} parts into {}",
+        manifest.parts.len(),
+        args.output.display()
+    );
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,73 @@
+use std::{io::Write, path::PathBuf, process::ExitCode};
+
+use anyhow::Result;
+use clap::Args;
+use heapdump_analyzer::{
+    RecordReader,
+    config::Config,
+    output::{
+        Style,
+        table::{Cell, Column, Table},
+    },
+    parser::stats::{ParseStats, record_name},
+};
+
+use crate::cli::{ignore_broken_pipe, local_dump};
+
+#[derive(Args)]
+pub struct StatsArgs {
+    dump: PathBuf,
+}
+
+pub fn run(args: &StatsArgs, config: &Config) -> Result<ExitCode> {
+    let dump = local_dump(&args.dump, config)?;
+    let mut reader = RecordReader::open(&dump)?.with_policy(config.analysis.policy);
+    // records are counted while parsing, each is dropped right away
+    for record in reader.by_ref() {
+        record?;
+    }
+
+    let style = Style::detect(config.output.color);
+    let mut out = std::io::stdout().lock();
+    ignore_broken_pipe(print_stats(&mut out, &style, config, reader.stats()))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn print_stats(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    stats: &ParseStats,
+) -> Result<()> {
+    let mut table = Table::new(vec![
+        Column::flexible("Record"),
+        Column::right("Count"),
+        Column::right("Bytes"),
+        Column::left("% of dump"),
+    ]);
+    let total = stats.total_records().bytes;
+    for (tag, tag_stats) in &stats.records {
+        table.add_row(vec![
+            Cell::Text(record_name(*tag).to_string()),
+            Cell::Count(tag_stats.count),
+            Cell::Bytes(tag_stats.bytes),
+            Cell::Percent {
+                part: tag_stats.bytes,
+                total,
+            },
+        ]);
+    }
+    // indented under the segments holding them
+    for (kind, kind_stats) in &stats.sub_records {
+        table.add_row(vec![
+            Cell::Text(format!("  {}", kind)),
+            Cell::Count(kind_stats.count),
+            Cell::Bytes(kind_stats.bytes),
+            Cell::Percent {
+                part: kind_stats.bytes,
+                total,
+            },
+        ]);
+    }
+    table.write(w, style, config.output.format)
+}
@@ -0,0 +1,394 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result};
+use clap::Args;
+use heapdump_analyzer::{
+    analyzer::{
+        AnalyzedHeap, HistogramEntry,
+        budget::Strategy,
+        contents::Contents,
+        dedup::{DedupEstimate, dedup_estimate},
+        sample::Sample,
+        size::SizeModel,
+    },
+    config::Config,
+    gclog::{GcLog, Trend},
+    jfr::Allocations,
+    output::{
+        Color, OutputFormat, Style, csv_field, human_bytes, human_count,
+        table::{Cell, Column, Table},
+    },
+    parser::{Header, ParsedHeap},
+};
+use tracing::warn;
+
+use crate::cli::{ignore_broken_pipe, local_dump, open_heap, strategy, visualvm};
+
+#[derive(Args, Default)]
+pub struct SummaryArgs {
+    dump: Option<PathBuf>,
+
+    /// Only show classes starting with this prefix (repeatable)
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Hide classes starting with this prefix (repeatable)
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// Number of histogram rows
+    #[arg(long)]
+    rows: Option<usize>,
+
+    /// Print VisualVM's basic info and classes views, using VisualVM's object sizes
+    #[arg(long)]
+    visualvm: bool,
+
+    /// Estimate the histogram from this fraction of the objects, e.g. 0.01, skipping the
+    /// reference graph
+    #[arg(long, value_name = "RATE", conflicts_with = "visualvm")]
+    sample: Option<f64>,
+
+    /// Flight recording of the same JVM, adds where each class was allocated to the histogram
+    #[arg(long, value_name = "RECORDING", conflicts_with = "visualvm")]
+    jfr: Option<PathBuf>,
+
+    /// Unified GC log of the same JVM, adds the collections leading up to the dump
+    #[arg(long, value_name = "LOG", conflicts_with = "visualvm")]
+    gc_log: Option<PathBuf>,
+}
+
+// recordings of the same jvm shown alongside the dump
+struct Correlations {
+    allocations: Option<Allocations>,
+    gc_log: Option<GcLog>,
+}
+
+impl SummaryArgs {
+    fn merge_into(&self, config: &mut Config) {
+        if !self.include.is_empty() {
+            config.filters.include = self.include.clone();
+        }
+        if !self.exclude.is_empty() {
+            config.filters.exclude = self.exclude.clone();
+        }
+        if let Some(rows) = self.rows {
+            config.output.rows = rows;
+        }
+    }
+}
+
+pub fn run(args: &SummaryArgs, mut config: Config) -> Result<ExitCode> {
+    args.merge_into(&mut config);
+    let dump = args.dump.as_ref().context("no heapdump path provided")?;
+    analyze(args, &local_dump(dump, &config)?, config)
+}
+
+// the summary of a local dump, for commands producing one like capture
+pub fn analyze(args: &SummaryArgs, dump: &Path, mut config: Config) -> Result<ExitCode> {
+    if args.visualvm {
+        config.size_model = SizeModel::visualvm();
+    }
+
+    let correlations = Correlations {
+        allocations: args.jfr.as_deref().map(Allocations::read).transpose()?,
+        gc_log: args.gc_log.as_deref().map(GcLog::read).transpose()?,
+    };
+    let style = Style::detect(config.output.color);
+    if args.visualvm {
+        // the class loader count needs the records, which the index doesn't keep
+        let parsed_heap = ParsedHeap::parse(dump)?;
+        let analyzed_heap = AnalyzedHeap::analyze_with(&parsed_heap, config.size_model)?;
+        ignore_broken_pipe(visualvm::report(
+            &style,
+            &config,
+            &parsed_heap,
+            &analyzed_heap,
+        ))?;
+    } else if let Some(rate) = args.sample.or(histogram_only(dump, &config)?) {
+        let (header, sample) = Sample::file(dump, config.size_model, rate)?;
+        ignore_broken_pipe(report_sample(
+            &style,
+            &config,
+            &header,
+            &sample,
+            &correlations,
+        ))?;
+    } else {
+        let index = open_heap(dump, &config, false)?;
+        let contents = Contents::open(dump, &index.heap)?;
+        let dedup = dedup_estimate(&index.heap, &contents)?;
+        ignore_broken_pipe(report(
+            &style,
+            &config,
+            &index.header,
+            &index.heap,
+            &dedup,
+            &correlations,
+        ))?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+// over the memory budget, a histogram of all objects is still affordable without the graph
+fn histogram_only(dump: &Path, config: &Config) -> Result<Option<f64>> {
+    if strategy(dump, config, false, true)? != Strategy::HistogramOnly {
+        return Ok(None);
+    }
+    warn!("dump too large for the memory budget, only printing the histogram");
+    Ok(Some(1.0))
+}
+
+fn report(
+    style: &Style,
+    config: &Config,
+    header: &Header,
+    analyzed_heap: &AnalyzedHeap,
+    dedup: &DedupEstimate,
+    correlations: &Correlations,
+) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    let mut lines = summary_lines(
+        header,
+        analyzed_heap.classes.len() as u64,
+        analyzed_heap.instances.len() as u64,
+        analyzed_heap.total_shallow_size(),
+    );
+    lines.push(dedup_line(dedup));
+    lines.extend(gc_lines(header, correlations.gc_log.as_ref()));
+    write_summary(&mut out, style, config, lines)?;
+    writeln!(out)?;
+    write_histogram(
+        &mut out,
+        style,
+        config,
+        analyzed_heap.histogram(&config.filters),
+        analyzed_heap.total_shallow_size(),
+        correlations.allocations.as_ref(),
+    )
+}
+
+fn report_sample(
+    style: &Style,
+    config: &Config,
+    header: &Header,
+    sample: &Sample,
+    correlations: &Correlations,
+) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    let mut lines = summary_lines(
+        header,
+        sample.classes.len() as u64,
+        sample.estimated_count(),
+        sample.estimated_shallow_size(),
+    );
+    let percent = format!("{}%", sample.rate * 100.0);
+    lines.push(("Sampled", percent.clone(), percent));
+    lines.extend(gc_lines(header, correlations.gc_log.as_ref()));
+    write_summary(&mut out, style, config, lines)?;
+    writeln!(out)?;
+
+    // shares are relative to the estimated total, like the sizes themselves
+    let total = sample.estimated_shallow_size();
+    write_histogram(
+        &mut out,
+        style,
+        config,
+        sample.histogram(&config.filters),
+        total,
+        correlations.allocations.as_ref(),
+    )
+}
+
+pub fn print_summary(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    header: &Header,
+    analyzed_heap: &AnalyzedHeap,
+) -> Result<()> {
+    let lines = summary_lines(
+        header,
+        analyzed_heap.classes.len() as u64,
+        analyzed_heap.instances.len() as u64,
+        analyzed_heap.total_shallow_size(),
+    );
+    write_summary(w, style, config, lines)
+}
+
+// (key, raw value, human readable value)
+fn summary_lines(
+    header: &Header,
+    classes: u64,
+    objects: u64,
+    total: u64,
+) -> Vec<(&'static str, String, String)> {
+    let timestamp = header.timestamp.to_rfc3339();
+    vec![
+        (
+            "Version",
+            format!("{:?}", header.version),
+            format!("{:?}", header.version),
+        ),
+        ("Timestamp", timestamp.clone(), timestamp),
+        ("Classes", classes.to_string(), human_count(classes)),
+        ("Objects", objects.to_string(), human_count(objects)),
+        ("Shallow size", total.to_string(), human_bytes(total)),
+    ]
+}
+
+// what string deduplication would save, to decide on -XX:+UseStringDeduplication
+fn dedup_line(dedup: &DedupEstimate) -> (&'static str, String, String) {
+    (
+        "String dedup",
+        dedup.dedup_savings.to_string(),
+        format!(
+            "saves {} of {} string arrays, interning {}",
+            human_bytes(dedup.dedup_savings),
+            human_bytes(dedup.array_size),
+            human_bytes(dedup.intern_savings)
+        ),
+    )
+}
+
+fn gc_lines(header: &Header, gc_log: Option<&GcLog>) -> Vec<(&'static str, String, String)> {
+    let Some(gc_log) = gc_log else {
+        return Vec::new();
+    };
+    let Some(context) = gc_log.context(header.timestamp) else {
+        let none = "no collections before the dump".to_string();
+        return vec![("GC pauses", "0".to_string(), none)];
+    };
+
+    let last = &context.last;
+    let collection = if last.cause.is_empty() {
+        last.kind.clone()
+    } else {
+        format!("{} ({})", last.kind, last.cause)
+    };
+    let (first, after) = context.occupancy;
+    let trend = match context.trend {
+        Trend::Rising => "rising",
+        Trend::Falling => "falling",
+        Trend::Stable => "stable",
+    };
+    vec![
+        (
+            "GC pauses",
+            context.collections.to_string(),
+            human_count(context.collections as u64),
+        ),
+        (
+            "Last GC",
+            collection.clone(),
+            format!(
+                "{}, {} -> {}",
+                collection,
+                human_bytes(last.before),
+                human_bytes(last.after)
+            ),
+        ),
+        (
+            "Full GC",
+            context.full_gc_before_dump.to_string(),
+            if context.full_gc_before_dump {
+                "right before the dump".to_string()
+            } else {
+                "not before the dump".to_string()
+            },
+        ),
+        (
+            "Occupancy",
+            format!("{} {}", trend, after),
+            format!(
+                "{}, {} -> {} after GC",
+                trend,
+                human_bytes(first),
+                human_bytes(after)
+            ),
+        ),
+    ]
+}
+
+fn write_summary(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    lines: Vec<(&'static str, String, String)>,
+) -> Result<()> {
+    for (key, raw, human) in lines {
+        match config.output.format {
+            OutputFormat::Tsv => writeln!(w, "{}\t{}", key, raw)?,
+            OutputFormat::Csv => writeln!(w, "{},{}", csv_field(key), csv_field(&raw))?,
+            OutputFormat::Table => writeln!(
+                w,
+                "{} {}",
+                style.paint(&format!("{:<13}", format!("{}:", key)), Some(Color::Bold)),
+                human
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+pub fn print_histogram(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    analyzed_heap: &AnalyzedHeap,
+) -> Result<()> {
+    write_histogram(
+        w,
+        style,
+        config,
+        analyzed_heap.histogram(&config.filters),
+        analyzed_heap.total_shallow_size(),
+        None,
+    )
+}
+
+fn write_histogram(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    entries: Vec<HistogramEntry>,
+    total: u64,
+    allocations: Option<&Allocations>,
+) -> Result<()> {
+    let mut columns = vec![
+        Column::flexible("Class"),
+        Column::right("Objects"),
+        Column::right("Shallow"),
+        Column::left("% of heap"),
+    ];
+    if allocations.is_some() {
+        columns.push(Column::left("Allocated at"));
+    }
+    let mut table = Table::new(columns);
+
+    for entry in entries.into_iter().take(config.output.rows) {
+        let class = entry.class.java_name();
+        let hint = allocations.map(|a| a.hint(&class).unwrap_or_default());
+        let mut row = vec![
+            Cell::Text(class),
+            Cell::Count(entry.instance_count),
+            Cell::Bytes(entry.shallow_size),
+            Cell::Percent {
+                part: entry.shallow_size,
+                total,
+            },
+        ];
+        if let Some(hint) = hint {
+            row.push(Cell::Text(hint));
+        }
+        table.add_row(row);
+    }
+
+    table.write(w, style, config.output.format)
+}
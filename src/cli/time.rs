@@ -0,0 +1,187 @@
+use std::{
+    fmt::Debug,
+    io::Write,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use heapdump_analyzer::{
+    METRICS_TARGET,
+    output::{human_bytes, human_count, human_duration},
+};
+use tracing::{
+    Event, Level, Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+};
+use tracing_subscriber::{Layer, filter::Targets, layer::Context, registry::LookupSpan};
+
+// phase spans as they close and metric events as they arrive, for --time
+static PHASES: Mutex<Vec<Measurement>> = Mutex::new(Vec::new());
+static METRICS: Mutex<Vec<Measurement>> = Mutex::new(Vec::new());
+
+struct Measurement {
+    name: String,
+    wall: Option<Duration>,
+    values: Vec<(&'static str, f64)>,
+}
+
+// collects the crate's spans and metric events whether or not --time is given, there are only a
+// few per phase
+pub fn layer<S: Subscriber + for<'a> LookupSpan<'a>>() -> impl Layer<S> {
+    TimeLayer.with_filter(Targets::new().with_target("heapdump_analyzer", Level::DEBUG))
+}
+
+struct TimeLayer;
+
+struct Timing {
+    start: Instant,
+    fields: Fields,
+}
+
+// numeric fields, the phase of metric events
+#[derive(Default)]
+struct Fields {
+    phase: Option<String>,
+    values: Vec<(&'static str, f64)>,
+}
+
+impl Visit for Fields {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.values.push((field.name(), value as f64));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.values.push((field.name(), value as f64));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.values.push((field.name(), value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "phase" {
+            self.phase = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn Debug) {}
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for TimeLayer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        span.extensions_mut().insert(Timing {
+            start: Instant::now(),
+            fields,
+        });
+    }
+
+    // fields like the record count are filled in once known
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(timing) = span.extensions_mut().get_mut::<Timing>()
+        {
+            values.record(&mut timing.fields);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target() != METRICS_TARGET {
+            return;
+        }
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        METRICS.lock().unwrap().push(Measurement {
+            name: fields.phase.unwrap_or_default(),
+            wall: None,
+            values: fields.values,
+        });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(timing) = span.extensions_mut().remove::<Timing>() else {
+            return;
+        };
+        PHASES.lock().unwrap().push(Measurement {
+            name: span.name().to_string(),
+            wall: Some(timing.start.elapsed()),
+            values: timing.fields.values,
+        });
+    }
+}
+
+// wall time of each phase in the order they finished, then the throughput and memory numbers
+pub fn report(w: &mut impl Write) -> Result<()> {
+    if let Some(peak_rss) = peak_rss() {
+        tracing::info!(target: METRICS_TARGET, phase = "process", peak_rss);
+    }
+
+    writeln!(w, "Phases")?;
+    for phase in PHASES.lock().unwrap().iter() {
+        write_measurement(w, phase)?;
+    }
+    let metrics = METRICS.lock().unwrap();
+    if !metrics.is_empty() {
+        writeln!(w, "Metrics")?;
+        for metric in metrics.iter() {
+            write_measurement(w, metric)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_measurement(w: &mut impl Write, measurement: &Measurement) -> Result<()> {
+    write!(w, "  {:<16}", measurement.name)?;
+    if let Some(wall) = measurement.wall {
+        write!(w, " {:>8}", human_duration(wall))?;
+    }
+    for (name, value) in &measurement.values {
+        write!(
+            w,
+            "  {} {}",
+            name.replace('_', " "),
+            format_value(name, *value)
+        )?;
+    }
+    writeln!(w)?;
+    Ok(())
+}
+
+fn format_value(name: &str, value: f64) -> String {
+    if name == "bytes" || name.ends_with("_rss") {
+        human_bytes(value as u64)
+    } else if value.fract() == 0.0 {
+        human_count(value as u64)
+    } else {
+        format!("{:.1}", value)
+    }
+}
+
+// the high water mark of the resident set, where the os reports it
+#[cfg(target_os = "linux")]
+fn peak_rss() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn peak_rss() -> Option<u64> {
+    None
+}
@@ -0,0 +1,123 @@
+use std::{io::Write, path::PathBuf, process::ExitCode, time::Duration};
+
+use anyhow::Result;
+use clap::Args;
+use heapdump_analyzer::{
+    analyzer::timeline::{ClassTimeline, class_patterns},
+    config::Config,
+    output::{
+        Style, human_count, human_duration,
+        table::{Cell, Column, Table},
+    },
+};
+
+use tracing::warn;
+
+use crate::cli::{ignore_broken_pipe, local_dump};
+
+#[derive(Args)]
+pub struct TimelineArgs {
+    dump: PathBuf,
+
+    /// Number of time slices between the dump's start timestamp and the dump
+    #[arg(long, default_value_t = 20)]
+    buckets: usize,
+
+    /// Only list the classes loaded in the last MINUTES before the dump, grouped by their name
+    /// with numbers left out
+    #[arg(long, value_name = "MINUTES")]
+    last: Option<u64>,
+
+    /// Number of rows
+    #[arg(long)]
+    rows: Option<usize>,
+}
+
+pub fn run(args: &TimelineArgs, config: &Config) -> Result<ExitCode> {
+    let dump = local_dump(&args.dump, config)?;
+    let (_, timeline) = ClassTimeline::read_with(&dump, config.analysis.policy)?;
+    for diagnostic in &timeline.diagnostics {
+        warn!("{}", diagnostic);
+    }
+    if !timeline.has_timestamps() {
+        println!("The dump's records have no timestamps, the jvm wrote 0 for all of them");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut config = config.clone();
+    if let Some(rows) = args.rows {
+        config.output.rows = rows;
+    }
+    let style = Style::detect(config.output.color);
+    let mut out = std::io::stdout().lock();
+    ignore_broken_pipe(match args.last {
+        Some(minutes) => print_recent(&mut out, &style, &config, &timeline, minutes),
+        None => print_timeline(&mut out, &style, &config, &timeline, args.buckets),
+    })?;
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn print_timeline(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    timeline: &ClassTimeline,
+    buckets: usize,
+) -> Result<()> {
+    let mut table = Table::new(vec![
+        Column::left("Since start"),
+        Column::right("Classes"),
+        Column::right("Total"),
+    ]);
+    let mut total = 0;
+    for bucket in timeline.buckets(buckets) {
+        total += bucket.classes;
+        table.add_row(vec![
+            Cell::Text(format!(
+                "{} - {}",
+                human_duration(Duration::from_micros(bucket.start_micros)),
+                human_duration(Duration::from_micros(bucket.end_micros))
+            )),
+            Cell::Count(bucket.classes),
+            Cell::Count(total),
+        ]);
+    }
+    table.write(w, style, config.output.format)
+}
+
+pub fn print_recent(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    timeline: &ClassTimeline,
+    minutes: u64,
+) -> Result<()> {
+    let recent = timeline.loaded_within(minutes * 60_000_000);
+    writeln!(
+        w,
+        "{} classes loaded in the last {} minutes before the dump\n",
+        human_count(recent.len() as u64),
+        minutes
+    )?;
+    if recent.is_empty() {
+        return Ok(());
+    }
+
+    let mut table = Table::new(vec![
+        Column::flexible("Class"),
+        Column::right("Classes"),
+        Column::right("First loaded"),
+    ]);
+    for pattern in class_patterns(recent).into_iter().take(config.output.rows) {
+        let before = timeline.end_micros - pattern.first_micros;
+        table.add_row(vec![
+            Cell::Text(pattern.pattern),
+            Cell::Count(pattern.classes),
+            Cell::Text(format!(
+                "{} before",
+                human_duration(Duration::from_micros(before))
+            )),
+        ]);
+    }
+    table.write(w, style, config.output.format)
+}
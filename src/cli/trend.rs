@@ -0,0 +1,144 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use anyhow::{Result, bail};
+use clap::{Args, ValueEnum};
+use heapdump_analyzer::{
+    config::Config,
+    export::trend::{MIN_LEAK_POINTS, ProbableLeak, Trend, TrendPoint},
+    output::{
+        Style, parse_bytes,
+        table::{Cell, Column, Table},
+    },
+};
+use rayon::prelude::*;
+use tracing::{info, warn};
+
+use crate::cli::{find_dumps, ignore_broken_pipe, open_heap};
+
+#[derive(Args)]
+pub struct TrendArgs {
+    /// Directory containing .hprof files of the same process
+    dir: PathBuf,
+
+    /// Format of the time series
+    #[arg(long, default_value = "json")]
+    emit: SeriesFormat,
+
+    /// Classes with the largest retained size in any dump that get their own column
+    #[arg(long, default_value_t = 20)]
+    classes: usize,
+
+    /// Output file, defaults to stdout
+    #[arg(long)]
+    out: Option<PathBuf>,
+
+    /// Number of dumps analyzed in parallel, defaults to the number of cpus
+    #[arg(long)]
+    jobs: Option<usize>,
+
+    /// Print the classes whose retained size grew in every dump instead of the series, needs
+    /// at least 3 dumps
+    #[arg(long)]
+    leaks: bool,
+
+    /// Growth per hour a class needs to be listed by --leaks, like 1M
+    #[arg(long, default_value = "1M", value_parser = parse_bytes, requires = "leaks")]
+    min_growth: u64,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SeriesFormat {
+    Json,
+    Csv,
+}
+
+pub fn run(args: &TrendArgs, config: &Config) -> Result<ExitCode> {
+    let dumps = find_dumps(&args.dir)?;
+    if dumps.is_empty() {
+        bail!("no .hprof files in {}", args.dir.display());
+    }
+    info!("analyzing {} dumps", dumps.len());
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.jobs.unwrap_or(0))
+        .build()?;
+    // a broken dump leaves a gap in the series instead of failing it
+    let points: Vec<TrendPoint> = pool.install(|| {
+        dumps
+            .par_iter()
+            .filter_map(|dump| {
+                let point = open_heap(dump, config, true).map(|index| {
+                    let name = dump.file_name().unwrap_or_default().to_string_lossy();
+                    TrendPoint::new(&name, &index.header, &index.heap)
+                });
+                match point {
+                    Ok(point) => Some(point),
+                    Err(err) => {
+                        warn!("skipping {}: {:#}", dump.display(), err);
+                        None
+                    }
+                }
+            })
+            .collect()
+    });
+    let trend = Trend::new(points, args.classes);
+
+    let mut w: Box<dyn Write> = match &args.out {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(std::io::stdout().lock())),
+    };
+    if args.leaks {
+        if trend.len() < MIN_LEAK_POINTS {
+            bail!(
+                "finding leaks needs at least {} dumps, {} could be analyzed",
+                MIN_LEAK_POINTS,
+                trend.len()
+            );
+        }
+        let leaks = trend.probable_leaks(args.min_growth as f64);
+        let style = Style::detect(config.output.color);
+        ignore_broken_pipe(print_probable_leaks(&mut w, &style, config, &leaks))?;
+        ignore_broken_pipe(w.flush().map_err(Into::into))?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    ignore_broken_pipe(match args.emit {
+        SeriesFormat::Json => trend.write_json(&mut w),
+        SeriesFormat::Csv => trend.write_csv(&mut w),
+    })?;
+    ignore_broken_pipe(w.flush().map_err(Into::into))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_probable_leaks(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    leaks: &[ProbableLeak],
+) -> Result<()> {
+    if leaks.is_empty() {
+        writeln!(w, "No probable leaks found")?;
+        return Ok(());
+    }
+
+    let mut table = Table::new(vec![
+        Column::flexible("Class"),
+        Column::right("Growth per hour"),
+        Column::right("First"),
+        Column::right("Last"),
+    ]);
+    for leak in leaks.iter().take(config.output.rows) {
+        table.add_row(vec![
+            Cell::Text(leak.class.clone()),
+            Cell::Bytes(leak.bytes_per_hour as u64),
+            Cell::Bytes(leak.first_bytes),
+            Cell::Bytes(leak.last_bytes),
+        ]);
+    }
+
+    table.write(w, style, config.output.format)
+}
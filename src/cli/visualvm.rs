@@ -0,0 +1,99 @@
+use std::{collections::HashSet, io::Write};
+
+use anyhow::Result;
+use heapdump_analyzer::{
+    analyzer::AnalyzedHeap,
+    config::Config,
+    output::{
+        Color, OutputFormat, Style, csv_field, human_count,
+        table::{Cell, Column, Table},
+    },
+    parser::{Id, ParsedHeap, select::ClassDump},
+};
+
+// mirrors the summary and classes views of visualvm's heap dump viewer
+pub fn report(
+    style: &Style,
+    config: &Config,
+    parsed_heap: &ParsedHeap,
+    analyzed_heap: &AnalyzedHeap,
+) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    print_basic_info(&mut out, style, config, parsed_heap, analyzed_heap)?;
+    writeln!(out)?;
+    print_classes(&mut out, style, config, analyzed_heap)
+}
+
+fn print_basic_info(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    parsed_heap: &ParsedHeap,
+    analyzed_heap: &AnalyzedHeap,
+) -> Result<()> {
+    let lines = [
+        ("Total bytes", analyzed_heap.total_shallow_size()),
+        ("Total classes", analyzed_heap.classes.len() as u64),
+        ("Total instances", analyzed_heap.instances.len() as u64),
+        ("Classloaders", class_loaders(parsed_heap)),
+        ("GC roots", analyzed_heap.roots.len() as u64),
+    ];
+
+    if config.output.format == OutputFormat::Table {
+        writeln!(w, "{}", style.paint("Basic info", Some(Color::Bold)))?;
+    }
+    for (key, value) in lines {
+        match config.output.format {
+            OutputFormat::Tsv => writeln!(w, "{}\t{}", key, value)?,
+            OutputFormat::Csv => writeln!(w, "{},{}", csv_field(key), value)?,
+            OutputFormat::Table => {
+                writeln!(w, "  {:<17} {}", format!("{}:", key), human_count(value))?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_classes(
+    w: &mut impl Write,
+    style: &Style,
+    config: &Config,
+    analyzed_heap: &AnalyzedHeap,
+) -> Result<()> {
+    let total_instances = analyzed_heap.instances.len() as u64;
+    let mut table = Table::new(vec![
+        Column::flexible("Class Name"),
+        Column::left("Instances [%]"),
+        Column::right("Instances"),
+        Column::right("Size"),
+    ]);
+
+    // visualvm lists sizes in bytes, not humanized
+    for entry in analyzed_heap
+        .histogram(&config.filters)
+        .into_iter()
+        .take(config.output.rows)
+    {
+        table.add_row(vec![
+            Cell::Text(entry.class.java_name()),
+            Cell::Percent {
+                part: entry.instance_count,
+                total: total_instances,
+            },
+            Cell::Count(entry.instance_count),
+            Cell::Count(entry.shallow_size),
+        ]);
+    }
+
+    table.write(w, style, config.output.format)
+}
+
+// the bootstrap loader is counted like visualvm does, even though it has no object
+fn class_loaders(parsed_heap: &ParsedHeap) -> u64 {
+    let loaders: HashSet<Id> = parsed_heap
+        .iter_sub_records::<ClassDump>()
+        .map(|class| class.class_loader_object_id)
+        .collect();
+    loaders.len() as u64
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow};
+use clap::Args;
+use heapdump_analyzer::{
+    analyzer::leaks::{DEFAULT_THRESHOLD, LeakSuspect, leak_suspects},
+    config::Config,
+    export::prometheus::{self, DumpMetrics},
+    output::{Color, Style, human_bytes, parse_bytes},
+};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::cli::{
+    check::{Violation, parse_class_limit, violations},
+    find_dumps, ignore_broken_pipe,
+    leaks::print_leak_suspects,
+    open_heap,
+    summary::print_summary,
+};
+
+// suspects listed in a notification, the largest ones
+const WEBHOOK_SUSPECTS: usize = 5;
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// Directory to watch for new .hprof files
+    dir: PathBuf,
+
+    /// Seconds between directory scans
+    #[arg(long, default_value_t = 5)]
+    interval: u64,
+
+    /// Also analyze dumps that already exist on startup
+    #[arg(long)]
+    existing: bool,
+
+    /// Post a JSON notification to this URL when a dump has leak suspects or exceeds a limit,
+    /// overrides webhook.url of the config
+    #[arg(long)]
+    webhook: Option<String>,
+
+    /// Maximum retained size of all instances of a class before notifying, e.g.
+    /// com.foo.Cache=512MB (repeatable)
+    #[arg(long, value_parser = parse_class_limit)]
+    max_retained: Vec<(String, u64)>,
+
+    /// Maximum retained size of everything reachable from gc roots before notifying, e.g. 4GB
+    #[arg(long, value_parser = parse_bytes)]
+    max_heap: Option<u64>,
+
+    /// Minimum share of the reachable heap a leak suspect has to retain, in percent
+    #[arg(long, default_value_t = DEFAULT_THRESHOLD * 100.0)]
+    threshold: f64,
+
+    /// Serve prometheus metrics of the analyzed dumps on this address, e.g. 0.0.0.0:9100
+    #[arg(long)]
+    metrics: Option<String>,
+}
+
+// "text" is what slack and compatible chat tools show, the rest is for everything else
+#[derive(Serialize)]
+struct WebhookPayload {
+    text: String,
+    dump: PathBuf,
+    timestamp: String,
+    objects: usize,
+    shallow_size: u64,
+    reachable_size: u64,
+    violations: Vec<Violation>,
+    leak_suspects: Vec<WebhookSuspect>,
+    report_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct WebhookSuspect {
+    description: String,
+    class: String,
+    retained_size: u64,
+    fraction: f64,
+}
+
+pub fn run(args: &WatchArgs, config: &Config) -> Result<ExitCode> {
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    if !args.existing {
+        seen.extend(find_dumps(&args.dir)?);
+    }
+
+    // dumps are only analyzed once their size stopped changing between two scans
+    let mut pending: HashMap<PathBuf, u64> = HashMap::new();
+
+    let metrics: Arc<Mutex<Vec<DumpMetrics>>> = Arc::default();
+    if let Some(addr) = &args.metrics {
+        serve_metrics(addr, metrics.clone())?;
+    }
+
+    info!("watching {}", args.dir.display());
+    loop {
+        for dump in find_dumps(&args.dir)? {
+            if seen.contains(&dump) {
+                continue;
+            }
+
+            // deleted or moved away since the scan
+            let size = match std::fs::metadata(&dump) {
+                Ok(metadata) => metadata.len(),
+                Err(err) => {
+                    warn!("skipping {}: {}", dump.display(), err);
+                    pending.remove(&dump);
+                    continue;
+                }
+            };
+            if pending.get(&dump) != Some(&size) {
+                pending.insert(dump, size);
+                continue;
+            }
+
+            pending.remove(&dump);
+            seen.insert(dump.clone());
+
+            info!("analyzing {}", dump.display());
+            if let Err(err) = process(&dump, args, config, &metrics) {
+                warn!("failed to analyze {}: {:#}", dump.display(), err);
+            }
+        }
+
+        std::thread::sleep(Duration::from_secs(args.interval));
+    }
+}
+
+fn process(
+    dump: &Path,
+    args: &WatchArgs,
+    config: &Config,
+    metrics: &Mutex<Vec<DumpMetrics>>,
+) -> Result<()> {
+    let index = open_heap(dump, config, true)?;
+    let (header, analyzed_heap) = (&index.header, &index.heap);
+    let dominator_tree = analyzed_heap.dominator_tree();
+    let suspects = leak_suspects(analyzed_heap, dominator_tree, args.threshold / 100.0);
+
+    let style = Style::detect(config.output.color);
+    ignore_broken_pipe((|| {
+        let mut out = std::io::stdout().lock();
+        writeln!(
+            out,
+            "{}",
+            style.paint(&format!("== {}", dump.display()), Some(Color::Bold))
+        )?;
+        print_summary(&mut out, &style, config, header, analyzed_heap)?;
+        writeln!(out)?;
+        print_leak_suspects(
+            &mut out,
+            &style,
+            config,
+            &suspects,
+            dominator_tree.reachable_size(),
+        )?;
+        writeln!(out)?;
+        Ok(())
+    })())?;
+
+    if args.metrics.is_some() {
+        let name = dump
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let dump_metrics = DumpMetrics::new(
+            &name,
+            analyzed_heap,
+            dominator_tree,
+            &suspects,
+            config.output.rows,
+            header.timestamp.timestamp() as u64,
+        );
+
+        let mut metrics = metrics.lock().unwrap();
+        metrics.retain(|m| m.dump != name);
+        metrics.push(dump_metrics);
+    }
+
+    let webhook = args.webhook.as_ref().or(config.webhook.url.as_ref());
+    let violations = violations(analyzed_heap, args.max_heap, &args.max_retained);
+    if let Some(url) = webhook
+        && (!suspects.is_empty() || !violations.is_empty())
+    {
+        let name = dump
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let payload = WebhookPayload {
+            text: notification_text(&name, &suspects, &violations),
+            dump: dump.to_path_buf(),
+            timestamp: header.timestamp.to_rfc3339(),
+            objects: analyzed_heap.instances.len(),
+            shallow_size: analyzed_heap.total_shallow_size(),
+            reachable_size: dominator_tree.reachable_size(),
+            violations,
+            leak_suspects: suspects
+                .iter()
+                .take(WEBHOOK_SUSPECTS)
+                .map(|s| WebhookSuspect {
+                    description: s.describe(),
+                    class: s.class.java_name(),
+                    retained_size: s.retained_size,
+                    fraction: s.fraction,
+                })
+                .collect(),
+            report_url: config
+                .webhook
+                .report_url
+                .as_ref()
+                .map(|url| url.replace("{dump}", &name)),
+        };
+
+        ureq::post(url)
+            .header("Content-Type", "application/json")
+            .send(serde_json::to_string(&payload)?)
+            .with_context(|| format!("failed to post webhook to {}", url))?;
+        info!("posted webhook for {}", dump.display());
+    }
+
+    Ok(())
+}
+
+// one line per finding under a headline
+fn notification_text(dump: &str, suspects: &[LeakSuspect], violations: &[Violation]) -> String {
+    let mut text = format!("heap dump {} needs a look", dump);
+    for violation in violations {
+        text.push('\n');
+        text.push_str(&match violation {
+            Violation::MaxHeap { limit, actual } => format!(
+                "heap of {} exceeds {}",
+                human_bytes(*actual),
+                human_bytes(*limit)
+            ),
+            Violation::MaxRetained {
+                class,
+                limit,
+                actual,
+            } => format!(
+                "{} retains {}, more than {}",
+                class,
+                human_bytes(*actual),
+                human_bytes(*limit)
+            ),
+        });
+    }
+    for suspect in suspects.iter().take(WEBHOOK_SUSPECTS) {
+        text.push_str(&format!(
+            "\nleak suspect {} retains {} ({:.1}%)",
+            suspect.describe(),
+            human_bytes(suspect.retained_size),
+            suspect.fraction * 100.0
+        ));
+    }
+    text
+}
+
+// answers GET /metrics from a background thread, everything else is a 404
+fn serve_metrics(addr: &str, metrics: Arc<Mutex<Vec<DumpMetrics>>>) -> Result<()> {
+    let server = tiny_http::Server::http(addr)
+        .map_err(|err| anyhow!("failed to listen on {}: {}", addr, err))?;
+    info!("serving metrics on http://{}/metrics", addr);
+
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = if request.url() == "/metrics" {
+                let body = prometheus::render(&metrics.lock().unwrap());
+                tiny_http::Response::from_string(body).with_header(
+                    tiny_http::Header::from_bytes("Content-Type", prometheus::CONTENT_TYPE)
+                        .unwrap(),
+                )
+            } else {
+                tiny_http::Response::from_string("not found").with_status_code(404)
+            };
+
+            if let Err(err) = request.respond(response) {
+                warn!("failed to answer metrics request: {}", err);
+            }
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    analysis::detectors::DEFAULT_MANY_ENTRIES,
+    analyzer::{
+        budget::MemoryCheck, filter::ClassFilter, options::AnalysisOptions, size::SizeModel,
+        storage::Storage,
+    },
+    error::Policy,
+    output::{ColorChoice, OutputFormat, parse_bytes},
+};
+
+const DEFAULT_ROWS: usize = 25;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub filters: ClassFilter,
+    pub size_model: SizeModel,
+    pub output: OutputConfig,
+    pub index: IndexConfig,
+    pub analysis: AnalysisConfig,
+    pub remote: RemoteConfig,
+    pub webhook: WebhookConfig,
+    pub detectors: DetectorsConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    pub format: OutputFormat,
+    pub color: ColorChoice,
+    pub rows: usize,
+    // templates replacing the built in report.html and report.md of `report --render`
+    pub template_dir: Option<PathBuf>,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::default(),
+            color: ColorChoice::default(),
+            rows: DEFAULT_ROWS,
+            template_dir: None,
+        }
+    }
+}
+
+// the .hda-index sidecar written next to analyzed dumps
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndexConfig {
+    pub enabled: bool,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalysisConfig {
+    // keep the reference graph in memory mapped files in this directory instead of ram
+    pub spill_dir: Option<PathBuf>,
+    // worker threads of the analysis phases, all cores when unset
+    pub threads: Option<usize>,
+    // memory the analysis should stay under, like "8G". switches to spilling, or to a plain
+    // histogram where that is enough, when the dump looks too large
+    #[serde(deserialize_with = "deserialize_bytes")]
+    pub max_memory: Option<u64>,
+    // "strict" fails on dumps breaking the format, "lenient" skips what it can and warns
+    pub policy: Policy,
+    // "warn", "refuse" or "off", for dumps estimated to need more memory than is available
+    pub memory_check: MemoryCheck,
+}
+
+// dumps passed as http(s) or s3 urls
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RemoteConfig {
+    // where downloaded and extracted dumps are kept, $XDG_CACHE_HOME/heapdump-analyzer when unset
+    pub cache_dir: Option<PathBuf>,
+}
+
+// notifications of watch about dumps with leak suspects or exceeded limits
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: Option<String>,
+    // link to the report of a dump sent along, "{dump}" is replaced with its file name
+    pub report_url: Option<String>,
+}
+
+// the library detectors `report` runs next to the builtin analyses
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DetectorsConfig {
+    // names of detectors left out, like "netty-buffers", see `report --list`
+    pub disabled: Vec<String>,
+    // entries from which an unbounded collection counts as growing rather than just big
+    pub many_entries: u64,
+}
+
+impl Default for DetectorsConfig {
+    fn default() -> Self {
+        Self {
+            disabled: Vec::new(),
+            many_entries: DEFAULT_MANY_ENTRIES,
+        }
+    }
+}
+
+impl RemoteConfig {
+    pub fn cache_dir(&self) -> Result<PathBuf> {
+        if let Some(dir) = &self.cache_dir {
+            return Ok(dir.clone());
+        }
+        let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .context("no cache directory, set remote.cache_dir in the config")?;
+        Ok(cache_dir.join("heapdump-analyzer"))
+    }
+}
+
+fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    parse_bytes(&s).map(Some).map_err(serde::de::Error::custom)
+}
+
+impl Config {
+    // an explicitly passed path has to exist, the default location is optional
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Self::from_file(path),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => Self::from_file(&path),
+                _ => Ok(Self::default()),
+            },
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("invalid config {}", path.display()))
+    }
+
+    // the size model and storage of the config, analyses left to their first use
+    pub fn analysis_options(&self) -> AnalysisOptions {
+        AnalysisOptions::default()
+            .with_size_model(self.size_model)
+            .with_storage(self.storage())
+            .with_policy(self.analysis.policy)
+    }
+
+    pub fn storage(&self) -> Storage {
+        match &self.analysis.spill_dir {
+            Some(dir) => Storage::Spill(dir.clone()),
+            None => Storage::Memory,
+        }
+    }
+
+    // $XDG_CONFIG_HOME/heapdump-analyzer/config.toml, falling back to ~/.config
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(config_dir.join("heapdump-analyzer").join("config.toml"))
+    }
+}
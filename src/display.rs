@@ -0,0 +1,500 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
+
+use crate::{
+    analyzer::{AnalyzedHeap, Class, instance_size, java_name, superclass_chain},
+    error::Result,
+    heap::Object,
+    output::{human_bytes, human_count},
+    parser::{
+        FrameId, Id, Record, StringId,
+        sub_record::{Field, FieldDescriptor, FieldValue, PrimArray, SubRecord},
+    },
+};
+
+// array elements and stack frames shown before "... n more"
+const PREVIEW: usize = 16;
+
+// multi-line renderings of records, classes and objects for people, shared by `inspect` and
+// debug output. the first line names the thing, details follow indented by two spaces:
+//
+//   println!("{}", display::record(&record, Some(&heap)));
+//
+// names, classes and frames are resolved through the heap when one is given, without one they
+// are printed as ids
+pub fn record<'a>(record: &'a Record, heap: Option<&'a AnalyzedHeap>) -> PrettyRecord<'a> {
+    PrettyRecord {
+        record,
+        names: Names(heap),
+    }
+}
+
+pub fn sub_record<'a>(
+    sub_record: &'a SubRecord,
+    heap: Option<&'a AnalyzedHeap>,
+) -> PrettySubRecord<'a> {
+    PrettySubRecord {
+        sub_record,
+        names: Names(heap),
+    }
+}
+
+pub fn class<'a>(heap: &'a AnalyzedHeap, class: &'a Class) -> PrettyClass<'a> {
+    PrettyClass { heap, class }
+}
+
+// decodes the fields of the object up front, reading them can fail. the retained size is only
+// shown once the dominator tree is there, inspecting an object shouldn't compute it
+pub fn object(object: &Object) -> Result<PrettyObject> {
+    Ok(PrettyObject {
+        id: object.id(),
+        class_name: object.class_name(),
+        shallow_size: object.shallow_size(),
+        retained_size: object
+            .heap()
+            .analyzed()
+            .has_dominator_tree()
+            .then(|| object.retained_size())
+            .flatten(),
+        fields: object
+            .fields()?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, value)| (name, Names(Some(object.heap().analyzed())).value(&value)))
+            .collect(),
+        string_value: object.string_value()?,
+        references: object.references().len(),
+    })
+}
+
+pub struct PrettyRecord<'a> {
+    record: &'a Record,
+    names: Names<'a>,
+}
+
+impl Display for PrettyRecord<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let names = &self.names;
+        match self.record {
+            Record::Utf8 {
+                micros,
+                name_id,
+                content,
+            } => {
+                writeln!(f, "Utf8 {}", name_id)?;
+                writeln!(f, "  content: {:?}", content)?;
+                write!(f, "  micros: {}", micros)
+            }
+            Record::LoadClass {
+                micros,
+                class_serial_number,
+                class_object_id,
+                stack_trace_serial_number,
+                class_name_id,
+            } => {
+                writeln!(f, "LoadClass {}", names.class_name(*class_name_id))?;
+                writeln!(f, "  class: {}", class_object_id)?;
+                writeln!(f, "  serial number: {}", class_serial_number)?;
+                writeln!(f, "  stack trace: {}", stack_trace_serial_number)?;
+                write!(f, "  micros: {}", micros)
+            }
+            Record::Trace {
+                micros,
+                stack_trace_serial_number,
+                thread_serial_number,
+                stack_frame_ids,
+            } => {
+                writeln!(f, "Trace {}", stack_trace_serial_number)?;
+                writeln!(f, "  thread: {}", thread_serial_number)?;
+                writeln!(f, "  micros: {}", micros)?;
+                write!(f, "  frames: {}", stack_frame_ids.len())?;
+                for id in stack_frame_ids.iter().take(PREVIEW) {
+                    write!(f, "\n    {}", names.frame(*id))?;
+                }
+                more(f, stack_frame_ids.len(), "    ")
+            }
+            Record::Frame {
+                micros,
+                stack_frame_id,
+                method_name_id,
+                method_signature_id,
+                source_file_name_id,
+                class_serial_number,
+                line_number,
+            } => {
+                writeln!(f, "Frame {}", stack_frame_id)?;
+                writeln!(f, "  method: {}", names.string(*method_name_id))?;
+                writeln!(f, "  signature: {}", names.string(*method_signature_id))?;
+                writeln!(f, "  source file: {}", names.string(*source_file_name_id))?;
+                writeln!(f, "  line: {}", line(*line_number))?;
+                writeln!(f, "  class serial number: {}", class_serial_number)?;
+                write!(f, "  micros: {}", micros)
+            }
+            // segments hold millions of sub records, they are rendered one by one
+            Record::HeapDumpSegment {
+                micros,
+                sub_records,
+            } => {
+                writeln!(f, "HeapDumpSegment")?;
+                writeln!(
+                    f,
+                    "  sub records: {}",
+                    human_count(sub_records.len() as u64)
+                )?;
+                write!(f, "  micros: {}", micros)
+            }
+            Record::HeapDumpEnd { micros } => {
+                writeln!(f, "HeapDumpEnd")?;
+                write!(f, "  micros: {}", micros)
+            }
+        }
+    }
+}
+
+pub struct PrettySubRecord<'a> {
+    sub_record: &'a SubRecord,
+    names: Names<'a>,
+}
+
+impl Display for PrettySubRecord<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let names = &self.names;
+        match self.sub_record {
+            SubRecord::ClassDump {
+                class_object_id,
+                super_class_object_id,
+                class_loader_object_id,
+                instance_size,
+                static_fields,
+                instance_field_descriptors,
+                ..
+            } => {
+                writeln!(f, "ClassDump {}", names.object(*class_object_id))?;
+                writeln!(f, "  superclass: {}", names.object(*super_class_object_id))?;
+                writeln!(
+                    f,
+                    "  class loader: {}",
+                    names.object(*class_loader_object_id)
+                )?;
+                write!(f, "  instance size: {}", instance_size)?;
+                write_static_fields(f, names, static_fields)?;
+                write_instance_fields(f, names, instance_field_descriptors)
+            }
+            SubRecord::InstanceDump {
+                object_id,
+                stack_trace_serial_number,
+                class_object_id,
+                raw_field_bytes,
+                ..
+            } => {
+                writeln!(f, "InstanceDump {}", object_id)?;
+                writeln!(f, "  class: {}", names.object(*class_object_id))?;
+                writeln!(f, "  field bytes: {}", raw_field_bytes.len())?;
+                write!(f, "  stack trace: {}", stack_trace_serial_number)
+            }
+            SubRecord::ObjArrayDump {
+                object_id,
+                stack_trace_serial_number,
+                array_class_id,
+                elements,
+            } => {
+                writeln!(f, "ObjArrayDump {}", object_id)?;
+                writeln!(f, "  class: {}", names.object(*array_class_id))?;
+                writeln!(f, "  stack trace: {}", stack_trace_serial_number)?;
+                write!(f, "  length: {}", elements.len())?;
+                for (i, id) in elements.iter().take(PREVIEW).enumerate() {
+                    write!(f, "\n    [{}] {}", i, names.object(*id))?;
+                }
+                more(f, elements.len(), "    ")
+            }
+            SubRecord::PrimArrayDump {
+                object_id,
+                stack_trace_serial_number,
+                typ,
+                elements,
+            } => {
+                writeln!(f, "PrimArrayDump {}", object_id)?;
+                writeln!(f, "  type: {}[]", type_name(*typ))?;
+                writeln!(f, "  stack trace: {}", stack_trace_serial_number)?;
+                write!(f, "  length: {}", elements.len())?;
+                if !elements.is_empty() {
+                    write!(f, "\n    {}", preview(elements))?;
+                }
+                Ok(())
+            }
+            SubRecord::ThreadObj {
+                object_id,
+                sequence_number,
+                stack_trace_sequence_number,
+            } => {
+                writeln!(f, "ThreadObj {}", names.object(*object_id))?;
+                writeln!(f, "  serial number: {}", sequence_number)?;
+                write!(f, "  stack trace: {}", stack_trace_sequence_number)
+            }
+            SubRecord::JavaFrame {
+                object_id,
+                thread_serial_number,
+                frame_number,
+            }
+            | SubRecord::JniLocal {
+                object_id,
+                thread_serial_number,
+                frame_number,
+            } => {
+                writeln!(f, "{} {}", self.sub_record, names.object(*object_id))?;
+                writeln!(f, "  thread: {}", thread_serial_number)?;
+                write!(f, "  frame: {}", frame_number)
+            }
+            SubRecord::JniGlobal {
+                object_id,
+                global_ref_id,
+            } => {
+                writeln!(f, "JniGlobal {}", names.object(*object_id))?;
+                write!(f, "  global ref: {}", global_ref_id)
+            }
+            SubRecord::StickyClass { object_id } => {
+                write!(f, "StickyClass {}", names.object(*object_id))
+            }
+            SubRecord::HeapDumpEnd => write!(f, "HeapDumpEnd"),
+        }
+    }
+}
+
+pub struct PrettyClass<'a> {
+    heap: &'a AnalyzedHeap,
+    class: &'a Class,
+}
+
+impl Display for PrettyClass<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let heap = self.heap;
+        let class = self.class;
+        let names = Names(Some(heap));
+        write!(f, "class {} @ {}", class.java_name(), class.id)?;
+        if let Some(superclass) = heap.superclass(class.id) {
+            write!(f, "\n  superclass: {}", superclass.java_name())?;
+        }
+        write!(
+            f,
+            "\n  instances: {}",
+            human_count(heap.instance_count(class.id))
+        )?;
+        let Some(layout) = heap.layouts.get(&class.id) else {
+            // loaded, but never dumped
+            return write!(f, "\n  no class dump");
+        };
+        write!(
+            f,
+            "\n  instance size: {}",
+            instance_size(class.id, &heap.layouts, &heap.size_model)
+        )?;
+        write_instance_fields(f, &names, &layout.instance_fields)?;
+        for (id, layout) in superclass_chain(class.id, &heap.layouts).skip(1) {
+            if !layout.instance_fields.is_empty() {
+                write!(f, "\n  inherited from {}:", names.class(id))?;
+                for field in &layout.instance_fields {
+                    write!(
+                        f,
+                        "\n    {}: {}",
+                        names.string(field.name_id),
+                        type_name(field.typ)
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct PrettyObject {
+    id: Id,
+    class_name: String,
+    shallow_size: Option<u64>,
+    retained_size: Option<u64>,
+    // rendered values, the class's own fields first
+    fields: Vec<(Arc<str>, String)>,
+    string_value: Option<String>,
+    references: usize,
+}
+
+impl Display for PrettyObject {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} @ {}", self.class_name, self.id)?;
+        if let Some(size) = self.shallow_size {
+            write!(f, "\n  shallow size: {}", human_bytes(size))?;
+        }
+        if let Some(size) = self.retained_size {
+            write!(f, "\n  retained size: {}", human_bytes(size))?;
+        }
+        write!(f, "\n  references: {}", self.references)?;
+        if let Some(value) = &self.string_value {
+            write!(f, "\n  value: {:?}", value)?;
+        }
+        if !self.fields.is_empty() {
+            write!(f, "\n  fields:")?;
+            for (name, value) in &self.fields {
+                write!(f, "\n    {}: {}", name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// resolves ids through the heap when there is one
+struct Names<'a>(Option<&'a AnalyzedHeap>);
+
+impl Names<'_> {
+    fn string(&self, id: StringId) -> String {
+        match self.0.and_then(|heap| heap.strings.get(&id)) {
+            Some(content) => format!("{:?}", content),
+            None => id.to_string(),
+        }
+    }
+
+    // the content of a class name string, in its java form
+    fn class_name(&self, name_id: StringId) -> String {
+        match self.0.and_then(|heap| heap.strings.get(&name_id)) {
+            Some(name) => java_name(name),
+            None => name_id.to_string(),
+        }
+    }
+
+    fn class(&self, id: Id) -> String {
+        match self.0.and_then(|heap| heap.class(id)) {
+            Some(class) => class.java_name(),
+            None => id.to_string(),
+        }
+    }
+
+    // "java.util.HashMap @ 0x7f..." for objects of the heap, class objects are "class ..."
+    fn object(&self, id: Id) -> String {
+        if id.0 == 0 {
+            return "null".to_string();
+        }
+        match self.0.and_then(|heap| heap.class_name_of(id)) {
+            Some(class_name) => format!("{} @ {}", class_name, id),
+            None => id.to_string(),
+        }
+    }
+
+    fn frame(&self, id: FrameId) -> String {
+        match self.0.and_then(|heap| heap.frame(id)) {
+            Some(frame) => frame.to_string(),
+            None => id.to_string(),
+        }
+    }
+
+    fn value(&self, value: &FieldValue) -> String {
+        match *value {
+            FieldValue::NormalObject { object_id } => self.object(object_id),
+            FieldValue::Boolean(v) => v.to_string(),
+            FieldValue::Char(v) => char_value(v),
+            FieldValue::Float(v) => format!("{:?}", v),
+            FieldValue::Double(v) => format!("{:?}", v),
+            FieldValue::Byte(v) => v.to_string(),
+            FieldValue::Short(v) => v.to_string(),
+            FieldValue::Int(v) => v.to_string(),
+            FieldValue::Long(v) => v.to_string(),
+        }
+    }
+}
+
+fn write_static_fields(f: &mut Formatter<'_>, names: &Names, fields: &[Field]) -> fmt::Result {
+    if fields.is_empty() {
+        return Ok(());
+    }
+    write!(f, "\n  static fields:")?;
+    for field in fields {
+        write!(
+            f,
+            "\n    {}: {} = {}",
+            names.string(field.name_id),
+            type_name(field.value.typ()),
+            names.value(&field.value)
+        )?;
+    }
+    Ok(())
+}
+
+fn write_instance_fields(
+    f: &mut Formatter<'_>,
+    names: &Names,
+    fields: &[FieldDescriptor],
+) -> fmt::Result {
+    if fields.is_empty() {
+        return Ok(());
+    }
+    write!(f, "\n  instance fields:")?;
+    for field in fields {
+        write!(
+            f,
+            "\n    {}: {}",
+            names.string(field.name_id),
+            type_name(field.typ)
+        )?;
+    }
+    Ok(())
+}
+
+fn more(f: &mut Formatter<'_>, len: usize, indent: &str) -> fmt::Result {
+    if len > PREVIEW {
+        write!(f, "\n{}... {} more", indent, len - PREVIEW)?;
+    }
+    Ok(())
+}
+
+// the first elements on one line, chars as characters
+fn preview(elements: &PrimArray) -> String {
+    fn join<T>(v: &[T], format: impl Fn(&T) -> String) -> String {
+        let mut out: Vec<String> = v.iter().take(PREVIEW).map(format).collect();
+        if v.len() > PREVIEW {
+            out.push(format!("... {} more", v.len() - PREVIEW));
+        }
+        format!("[{}]", out.join(", "))
+    }
+    match elements {
+        PrimArray::Bool(v) => join(v, |e| e.to_string()),
+        PrimArray::Char(v) => join(v, |e| char_value(*e)),
+        PrimArray::Float(v) => join(v, |e| format!("{:?}", e)),
+        PrimArray::Double(v) => join(v, |e| format!("{:?}", e)),
+        PrimArray::Byte(v) => join(v, |e| e.to_string()),
+        PrimArray::Short(v) => join(v, |e| e.to_string()),
+        PrimArray::Int(v) => join(v, |e| e.to_string()),
+        PrimArray::Long(v) => join(v, |e| e.to_string()),
+    }
+}
+
+// surrogates on their own aren't characters
+fn char_value(unit: u16) -> String {
+    match char::from_u32(unit as u32) {
+        Some(c) => format!("{:?}", c),
+        None => format!("\\u{{{:04x}}}", unit),
+    }
+}
+
+fn line(line_number: i32) -> String {
+    match line_number {
+        -2 => "compiled".to_string(),
+        -3 => "native".to_string(),
+        n if n <= 0 => "unknown".to_string(),
+        n => n.to_string(),
+    }
+}
+
+// java type name of an hprof basic type, "object" for references
+pub fn type_name(typ: u8) -> &'static str {
+    match typ {
+        2 => "object",
+        4 => "boolean",
+        5 => "char",
+        6 => "float",
+        7 => "double",
+        8 => "byte",
+        9 => "short",
+        10 => "int",
+        11 => "long",
+        _ => "unknown",
+    }
+}
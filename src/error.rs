@@ -0,0 +1,119 @@
+use std::{
+    io,
+    path::PathBuf,
+    str::{FromStr, Utf8Error},
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::parser::{Id, StringId};
+
+// why parsing or analyzing a dump failed, for embedders that need to tell failures apart. the
+// command line and the tools built on top wrap these in anyhow like any other error
+#[derive(Debug, Error)]
+pub enum HeapError {
+    #[error("failed to open {}", path.display())]
+    Open {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("unsupported version: {0}")]
+    UnsupportedVersion(String),
+    #[error("unsupported identifier size {0}, only 64bit heapdumps are supported")]
+    UnsupportedIdSize(u32),
+    #[error("invalid timestamp: {0}")]
+    InvalidTimestamp(u64),
+    // offset of the record or sub record the dump ended in
+    #[error("dump is truncated, the record at offset {offset} is incomplete")]
+    Truncated { offset: u64 },
+    #[error("unknown record tag 0x{tag:x} at offset {offset}")]
+    UnknownTag { tag: u8, offset: u64 },
+    #[error("unknown sub record type 0x{typ:x}")]
+    UnknownSubRecord { typ: u8 },
+    // a record whose length doesn't match what it holds
+    #[error("record at offset {offset} is {expected} bytes long, its contents take {actual}")]
+    LengthMismatch {
+        offset: u64,
+        expected: u64,
+        actual: u64,
+    },
+    #[error("invalid basic type 0x{0:x}")]
+    InvalidType(u8),
+    #[error("invalid utf8 string")]
+    InvalidUtf8(#[from] Utf8Error),
+    #[error("utf-16 string {id} has an odd length")]
+    InvalidUtf16 { id: Id },
+    #[error("string {id} not found")]
+    MissingString { id: StringId },
+    #[error("class {id} not found")]
+    MissingClass { id: Id },
+    #[error("class dump of {id} not found")]
+    MissingClassDump { id: Id },
+    #[error("primitive array class {0} not found")]
+    MissingArrayClass(&'static str),
+    // handles are u32, one past them can't be indexed
+    #[error("dump has more than {} objects", u32::MAX as u64 + 1)]
+    TooManyObjects,
+    #[error("instance fields of class {class} exceed the raw field bytes")]
+    FieldOverflow { class: Id },
+    #[error("class {class} is among its own superclasses")]
+    SuperclassCycle { class: Id },
+    #[error("invalid object id {0}, expected hex like 0x7fec0bd5c648 or decimal")]
+    InvalidId(String),
+    #[error("failed to create a spill file in {}", dir.display())]
+    Spill {
+        dir: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl HeapError {
+    // running out of bytes mid record means the dump was cut off
+    pub(crate) fn truncated_at(self, offset: u64) -> Self {
+        match self {
+            HeapError::Io(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                HeapError::Truncated { offset }
+            }
+            err => err,
+        }
+    }
+}
+
+pub type Result<T, E = HeapError> = std::result::Result<T, E>;
+
+// what parsing and analysis do about a dump that breaks the format: unknown record tags,
+// sub records running past their segment, instances with fields their bytes don't hold and
+// names or classes that were never written. lenient skips them and warns or reports
+// diagnostics, strict fails with the matching HeapError
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Policy {
+    // best effort, for looking at truncated and damaged dumps
+    #[default]
+    Lenient,
+    // forensic, nothing about the dump is guessed
+    Strict,
+}
+
+impl Policy {
+    pub fn is_strict(self) -> bool {
+        self == Policy::Strict
+    }
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lenient" => Ok(Policy::Lenient),
+            "strict" => Ok(Policy::Strict),
+            _ => Err(format!("unknown policy {}, expected lenient or strict", s)),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{
+    analyzer::{
+        AnalyzedHeap, Frame, HistogramEntry, dominator::DominatorTree, filter::ClassFilter,
+        instance_size,
+    },
+    display::type_name,
+    parser::{FrameId, Header, Id},
+};
+
+// bumped whenever a field is renamed or removed, adding fields keeps the version
+pub const SCHEMA_VERSION: u32 = 1;
+
+// ids are serialized as hex strings like "0x7fec0bd5c648", sizes are bytes. every list has a
+// fixed order, so exporting the same dump twice gives the same document
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub schema_version: u32,
+    pub summary: Summary,
+    // instances aggregated per class, largest shallow size first, then by class name and id,
+    // honoring the class filter
+    pub histogram: Vec<HistogramRow>,
+    // every loaded class, including classes without instances, by name and then id
+    pub classes: Vec<ClassDetails>,
+    // objects only dominated by the gc roots, largest retained size first, then by id
+    pub dominator_tree: Vec<DominatorNode>,
+    // by serial number
+    pub threads: Vec<ThreadDetails>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub version: String,
+    pub timestamp: String,
+    pub classes: usize,
+    pub objects: usize,
+    pub shallow_size: u64,
+    pub reachable_objects: usize,
+    pub reachable_size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistogramRow {
+    pub class: String,
+    pub instances: u64,
+    pub shallow_size: u64,
+    pub retained_size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClassDetails {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub super_class: Option<String>,
+    pub instance_size: u64,
+    // fields declared by the class itself, superclass fields are listed on the superclass
+    pub fields: Vec<FieldDetails>,
+    pub instances: u64,
+    pub shallow_size: u64,
+    pub retained_size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FieldDetails {
+    pub name: String,
+    // java type name, "object" for references
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DominatorNode {
+    pub id: String,
+    pub class: String,
+    pub shallow_size: u64,
+    pub retained_size: u64,
+    // empty once the depth limit is reached
+    pub children: Vec<DominatorNode>,
+}
+
+// references and referrers the servers list of an object at most
+pub const MAX_EDGES: usize = 100;
+
+// a list cut off by a limit, for the servers answering queries
+#[derive(Debug, Serialize)]
+pub struct Truncated<T> {
+    // matches before the limit was applied
+    pub total: usize,
+    pub items: Vec<T>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ThreadDetails {
+    pub id: String,
+    pub serial_number: u32,
+    pub class: String,
+    pub retained_size: u64,
+    // innermost frame first
+    pub stack_trace: Vec<StackFrame>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StackFrame {
+    pub method: String,
+    pub signature: String,
+    pub source_file: String,
+    // negative values mean unknown or native, see the hprof format
+    pub line: i32,
+}
+
+pub struct ReportOptions<'a> {
+    pub filter: &'a ClassFilter,
+    // levels of the dominator tree below the gc roots
+    pub depth: usize,
+    // children listed per dominator tree node
+    pub children: usize,
+}
+
+pub fn report(
+    header: &Header,
+    heap: &AnalyzedHeap,
+    dominator_tree: &DominatorTree,
+    options: &ReportOptions,
+) -> Report {
+    let retained_by_class = dominator_tree.retained_by_class(heap);
+    let histogram = heap.histogram(&ClassFilter::default());
+
+    let summary = Summary {
+        version: header.version.to_string(),
+        timestamp: header.timestamp.to_rfc3339(),
+        classes: heap.classes.len(),
+        objects: heap.instances.len(),
+        shallow_size: heap.total_shallow_size(),
+        reachable_objects: dominator_tree.reachable_count(),
+        reachable_size: dominator_tree.reachable_size(),
+    };
+
+    let histogram_rows = histogram
+        .iter()
+        .filter(|e| options.filter.matches(&e.class.java_name()))
+        .map(|e| HistogramRow {
+            class: e.class.java_name(),
+            instances: e.instance_count,
+            shallow_size: e.shallow_size,
+            retained_size: retained_by_class.get(&e.class.id).copied().unwrap_or(0),
+        })
+        .collect();
+
+    let by_class: HashMap<Id, &HistogramEntry> =
+        histogram.iter().map(|e| (e.class.id, e)).collect();
+    let mut classes: Vec<ClassDetails> = heap
+        .classes
+        .values()
+        .map(|class| {
+            let layout = heap.layouts.get(&class.id);
+            let entry = by_class.get(&class.id);
+            ClassDetails {
+                id: class.id.to_string(),
+                name: class.java_name(),
+                super_class: layout
+                    .and_then(|l| l.super_class_id)
+                    .and_then(|id| heap.classes.get(&id))
+                    .map(|c| c.java_name()),
+                instance_size: instance_size(class.id, &heap.layouts, &heap.size_model),
+                fields: layout
+                    .map(|l| {
+                        l.instance_fields
+                            .iter()
+                            .map(|f| FieldDetails {
+                                name: heap
+                                    .strings
+                                    .get(&f.name_id)
+                                    .map(|s| s.to_string())
+                                    .unwrap_or_default(),
+                                typ: type_name(f.typ),
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                instances: entry.map_or(0, |e| e.instance_count),
+                shallow_size: entry.map_or(0, |e| e.shallow_size),
+                retained_size: retained_by_class.get(&class.id).copied().unwrap_or(0),
+            }
+        })
+        .collect();
+    classes.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+
+    let children = dominator_tree.children();
+    let dominator_nodes = dominator_nodes(
+        heap,
+        dominator_tree,
+        &children,
+        Id(0),
+        options.depth,
+        options.children,
+    );
+
+    let frames: HashMap<FrameId, &Frame> = heap.frames.iter().map(|f| (f.id, f)).collect();
+    let mut threads: Vec<ThreadDetails> = heap
+        .threads
+        .iter()
+        .map(|thread| ThreadDetails {
+            id: thread.object_id.to_string(),
+            serial_number: thread.serial_number,
+            class: heap
+                .instance(thread.object_id)
+                .map(|i| i.class.java_name())
+                .unwrap_or_default(),
+            retained_size: dominator_tree.retained_size(thread.object_id).unwrap_or(0),
+            stack_trace: thread
+                .stack_frame_ids
+                .iter()
+                .filter_map(|id| frames.get(id))
+                .map(|frame| StackFrame {
+                    method: frame.method_name.to_string(),
+                    signature: frame.method_signature.to_string(),
+                    source_file: frame.source_file_name.to_string(),
+                    line: frame.line_number,
+                })
+                .collect(),
+        })
+        .collect();
+    threads.sort_by_key(|t| t.serial_number);
+
+    Report {
+        schema_version: SCHEMA_VERSION,
+        summary,
+        histogram: histogram_rows,
+        classes,
+        dominator_tree: dominator_nodes,
+        threads,
+    }
+}
+
+// children of parent down to the given depth, at most limit per node
+pub fn dominator_nodes(
+    heap: &AnalyzedHeap,
+    dominator_tree: &DominatorTree,
+    children: &HashMap<Id, Vec<Id>>,
+    parent: Id,
+    depth: usize,
+    limit: usize,
+) -> Vec<DominatorNode> {
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    children
+        .get(&parent)
+        .into_iter()
+        .flatten()
+        .take(limit)
+        .map(|&id| {
+            let instance = heap.instance(id);
+            DominatorNode {
+                id: id.to_string(),
+                class: heap.class_name_of(id).unwrap_or_default(),
+                shallow_size: instance.map_or(0, |i| i.shallow_size),
+                retained_size: dominator_tree.retained_size(id).unwrap_or(0),
+                children: dominator_nodes(heap, dominator_tree, children, id, depth - 1, limit),
+            }
+        })
+        .collect()
+}
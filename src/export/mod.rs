@@ -0,0 +1,193 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Seek},
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{
+    analyzer::{
+        ClassLayout, insert_layout, instance_size, java_name, prim_array_name, prim_size,
+        size::SizeModel,
+    },
+    parser::{Id, Record, RecordReader, StringId, sub_record::SubRecord},
+};
+
+pub mod json;
+pub mod ndjson;
+pub mod prometheus;
+pub mod proto;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sqlite;
+pub mod trend;
+pub mod xlsx;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectKind {
+    Instance,
+    ObjectArray,
+    PrimitiveArray,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ObjectRow {
+    pub id: String,
+    pub kind: ObjectKind,
+    pub class: String,
+    pub shallow_size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StringRow {
+    pub id: String,
+    pub value: String,
+}
+
+// calls f for every object as soon as its record is parsed. nothing but the class tables is
+// kept in memory, the field layouts are read in a first pass over the dump
+pub fn for_each_object<R: Read + Seek>(
+    mut records: RecordReader<R>,
+    size_model: &SizeModel,
+    mut f: impl FnMut(ObjectRow) -> Result<()>,
+) -> Result<()> {
+    let layouts = class_layouts(&mut records)?;
+    let mut strings: HashMap<StringId, String> = HashMap::new();
+    let mut class_names: HashMap<Id, String> = HashMap::new();
+    let mut instance_sizes: HashMap<Id, u64> = HashMap::new();
+
+    for record in records {
+        match record? {
+            Record::Utf8 {
+                name_id, content, ..
+            } => {
+                strings.insert(name_id, content);
+            }
+            Record::LoadClass {
+                class_object_id,
+                class_name_id,
+                ..
+            } => {
+                let name = strings
+                    .get(&class_name_id)
+                    .context("unknown class name string")?;
+                class_names.insert(class_object_id, java_name(name));
+            }
+            Record::HeapDumpSegment { sub_records, .. } => {
+                for sub_record in sub_records {
+                    match sub_record {
+                        SubRecord::InstanceDump {
+                            object_id,
+                            class_object_id,
+                            ..
+                        } => {
+                            let shallow_size =
+                                *instance_sizes.entry(class_object_id).or_insert_with(|| {
+                                    instance_size(class_object_id, &layouts, size_model)
+                                });
+                            f(ObjectRow {
+                                id: object_id.to_string(),
+                                kind: ObjectKind::Instance,
+                                class: class_names
+                                    .get(&class_object_id)
+                                    .cloned()
+                                    .context("class not found")?,
+                                shallow_size,
+                                length: None,
+                            })?;
+                        }
+                        SubRecord::ObjArrayDump {
+                            object_id,
+                            array_class_id,
+                            elements,
+                            ..
+                        } => f(ObjectRow {
+                            id: object_id.to_string(),
+                            kind: ObjectKind::ObjectArray,
+                            class: class_names
+                                .get(&array_class_id)
+                                .cloned()
+                                .context("array class not found")?,
+                            shallow_size: size_model.object_array_size(elements.len() as u64),
+                            length: Some(elements.len() as u64),
+                        })?,
+                        SubRecord::PrimArrayDump {
+                            object_id,
+                            typ,
+                            elements,
+                            ..
+                        } => f(ObjectRow {
+                            id: object_id.to_string(),
+                            kind: ObjectKind::PrimitiveArray,
+                            class: java_name(prim_array_name(typ)?),
+                            shallow_size: size_model
+                                .prim_array_size(elements.len() as u64, prim_size(typ)?),
+                            length: Some(elements.len() as u64),
+                        })?,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+// the field layout of every class. superclasses aren't necessarily dumped before their
+// subclasses or the instances of them, so the sizes and references of instances are only known
+// once all class dumps have been read. rewinds the reader for the pass over the objects
+pub fn class_layouts<R: Read + Seek>(
+    records: &mut RecordReader<R>,
+) -> Result<HashMap<Id, ClassLayout>> {
+    let mut layouts = HashMap::new();
+    for record in records.by_ref() {
+        let Record::HeapDumpSegment { sub_records, .. } = record? else {
+            continue;
+        };
+        for sub_record in sub_records {
+            if let SubRecord::ClassDump {
+                class_object_id,
+                super_class_object_id,
+                instance_field_descriptors,
+                ..
+            } = sub_record
+            {
+                insert_layout(
+                    &mut layouts,
+                    class_object_id,
+                    ClassLayout {
+                        super_class_id: (super_class_object_id.0 != 0)
+                            .then_some(super_class_object_id),
+                        instance_fields: instance_field_descriptors,
+                    },
+                );
+            }
+        }
+    }
+    records.rewind()?;
+    Ok(layouts)
+}
+
+pub fn for_each_string<R: Read + Seek>(
+    records: RecordReader<R>,
+    mut f: impl FnMut(StringRow) -> Result<()>,
+) -> Result<()> {
+    for record in records {
+        if let Record::Utf8 {
+            name_id, content, ..
+        } = record?
+        {
+            f(StringRow {
+                id: name_id.to_string(),
+                value: content,
+            })?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use std::io::Write;
+
+use anyhow::Result;
+use serde::Serialize;
+
+// rows are flushed in batches so consumers see them while the export is still running
+const FLUSH_EVERY: u64 = 1000;
+
+pub struct RowWriter<W: Write> {
+    w: W,
+    ndjson: bool,
+    rows: u64,
+}
+
+impl<W: Write> RowWriter<W> {
+    // writes one json object per line when ndjson is set, a single json array otherwise
+    pub fn new(w: W, ndjson: bool) -> Self {
+        Self { w, ndjson, rows: 0 }
+    }
+
+    pub fn write_row(&mut self, row: &impl Serialize) -> Result<()> {
+        if !self.ndjson {
+            self.w
+                .write_all(if self.rows == 0 { b"[\n" } else { b",\n" })?;
+        }
+
+        // serialized up front so write errors like a broken pipe surface as io errors
+        self.w.write_all(&serde_json::to_vec(row)?)?;
+        if self.ndjson {
+            self.w.write_all(b"\n")?;
+        }
+
+        self.rows += 1;
+        if self.rows.is_multiple_of(FLUSH_EVERY) {
+            self.w.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<u64> {
+        if !self.ndjson {
+            self.w
+                .write_all(if self.rows == 0 { b"[]\n" } else { b"\n]\n" })?;
+        }
+        self.w.flush()?;
+        Ok(self.rows)
+    }
+}
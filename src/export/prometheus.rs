@@ -0,0 +1,134 @@
+use std::fmt::Write;
+
+use crate::analyzer::{AnalyzedHeap, dominator::DominatorTree, leaks::LeakSuspect};
+
+// of the text exposition format render writes
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+// gauges describing one analyzed dump, rendered with a dump label
+#[derive(Debug, Clone)]
+pub struct DumpMetrics {
+    pub dump: String,
+    // unix seconds
+    pub timestamp: u64,
+    pub total_bytes: u64,
+    pub reachable_bytes: u64,
+    pub objects: u64,
+    // objects only dominated by the gc roots
+    pub top_level_dominators: u64,
+    pub leak_suspects: u64,
+    // largest retained sizes per class
+    pub class_retained_bytes: Vec<(String, u64)>,
+}
+
+impl DumpMetrics {
+    pub fn new(
+        dump: &str,
+        heap: &AnalyzedHeap,
+        dominator_tree: &DominatorTree,
+        suspects: &[LeakSuspect],
+        top_classes: usize,
+        timestamp: u64,
+    ) -> Self {
+        let mut class_retained_bytes: Vec<(String, u64)> = dominator_tree
+            .retained_by_class(heap)
+            .into_iter()
+            .filter_map(|(class_id, retained)| {
+                heap.classes
+                    .get(&class_id)
+                    .map(|c| (c.java_name(), retained))
+            })
+            .collect();
+        class_retained_bytes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        class_retained_bytes.truncate(top_classes);
+
+        Self {
+            dump: dump.to_string(),
+            timestamp,
+            total_bytes: heap.total_shallow_size(),
+            reachable_bytes: dominator_tree.reachable_size(),
+            objects: heap.instances.len() as u64,
+            top_level_dominators: dominator_tree.top_level_count() as u64,
+            leak_suspects: suspects.len() as u64,
+            class_retained_bytes,
+        }
+    }
+
+    pub fn unreachable_bytes(&self) -> u64 {
+        self.total_bytes.saturating_sub(self.reachable_bytes)
+    }
+}
+
+// (name, help, value)
+type Gauge = (&'static str, &'static str, fn(&DumpMetrics) -> u64);
+
+// prometheus text exposition format, one metric family per gauge
+pub fn render(metrics: &[DumpMetrics]) -> String {
+    let mut out = String::new();
+
+    let gauges: [Gauge; 7] = [
+        (
+            "heapdump_timestamp_seconds",
+            "Time the dump was taken",
+            |m| m.timestamp,
+        ),
+        ("heapdump_total_bytes", "Shallow size of all objects", |m| {
+            m.total_bytes
+        }),
+        (
+            "heapdump_reachable_bytes",
+            "Shallow size of objects reachable from gc roots",
+            |m| m.reachable_bytes,
+        ),
+        (
+            "heapdump_unreachable_bytes",
+            "Shallow size of garbage",
+            DumpMetrics::unreachable_bytes,
+        ),
+        ("heapdump_objects", "Number of objects", |m| m.objects),
+        (
+            "heapdump_top_level_dominators",
+            "Objects only dominated by the gc roots",
+            |m| m.top_level_dominators,
+        ),
+        ("heapdump_leak_suspects", "Number of leak suspects", |m| {
+            m.leak_suspects
+        }),
+    ];
+
+    for (name, help, value) in gauges {
+        header(&mut out, name, help);
+        for m in metrics {
+            let _ = writeln!(out, "{}{{dump=\"{}\"}} {}", name, escape(&m.dump), value(m));
+        }
+    }
+
+    let name = "heapdump_class_retained_bytes";
+    header(&mut out, name, "Retained size of the largest classes");
+    for m in metrics {
+        for (class, retained) in &m.class_retained_bytes {
+            let _ = writeln!(
+                out,
+                "{}{{dump=\"{}\",class=\"{}\"}} {}",
+                name,
+                escape(&m.dump),
+                escape(class),
+                retained
+            );
+        }
+    }
+
+    out
+}
+
+fn header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,170 @@
+use crate::{
+    analyzer::{
+        AnalyzedHeap,
+        dominator::DominatorTree,
+        filter::ClassFilter,
+        leaks::{self, SuspectKind as Kind},
+    },
+    parser::{Header, Id},
+};
+
+// bumped together with breaking changes to proto/analysis.proto
+pub const SCHEMA_VERSION: u32 = 1;
+
+// hand written counterparts of the messages in proto/analysis.proto, keep the tags in sync
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AnalysisResult {
+    #[prost(uint32, tag = "1")]
+    pub schema_version: u32,
+    #[prost(message, optional, tag = "2")]
+    pub summary: Option<Summary>,
+    #[prost(message, repeated, tag = "3")]
+    pub histogram: Vec<HistogramEntry>,
+    #[prost(message, repeated, tag = "4")]
+    pub dominators: Vec<Dominator>,
+    #[prost(message, repeated, tag = "5")]
+    pub leak_suspects: Vec<LeakSuspect>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Summary {
+    #[prost(string, tag = "1")]
+    pub version: String,
+    #[prost(int64, tag = "2")]
+    pub timestamp_millis: i64,
+    #[prost(uint64, tag = "3")]
+    pub classes: u64,
+    #[prost(uint64, tag = "4")]
+    pub objects: u64,
+    #[prost(uint64, tag = "5")]
+    pub shallow_size: u64,
+    #[prost(uint64, tag = "6")]
+    pub reachable_objects: u64,
+    #[prost(uint64, tag = "7")]
+    pub reachable_size: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HistogramEntry {
+    #[prost(string, tag = "1")]
+    pub class_name: String,
+    #[prost(uint64, tag = "2")]
+    pub instances: u64,
+    #[prost(uint64, tag = "3")]
+    pub shallow_size: u64,
+    #[prost(uint64, tag = "4")]
+    pub retained_size: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Dominator {
+    #[prost(uint64, tag = "1")]
+    pub object_id: u64,
+    #[prost(string, tag = "2")]
+    pub class_name: String,
+    #[prost(uint64, tag = "3")]
+    pub shallow_size: u64,
+    #[prost(uint64, tag = "4")]
+    pub retained_size: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum SuspectKind {
+    Unspecified = 0,
+    Object = 1,
+    Class = 2,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LeakSuspect {
+    #[prost(enumeration = "SuspectKind", tag = "1")]
+    pub kind: i32,
+    #[prost(string, tag = "2")]
+    pub class_name: String,
+    #[prost(uint64, tag = "3")]
+    pub object_id: u64,
+    #[prost(uint64, tag = "4")]
+    pub instance_count: u64,
+    #[prost(uint64, tag = "5")]
+    pub retained_size: u64,
+    #[prost(double, tag = "6")]
+    pub fraction: f64,
+}
+
+// histogram and dominators are limited to rows entries
+pub fn analysis_result(
+    header: &Header,
+    heap: &AnalyzedHeap,
+    dominator_tree: &DominatorTree,
+    suspects: &[leaks::LeakSuspect],
+    filter: &ClassFilter,
+    rows: usize,
+) -> AnalysisResult {
+    let retained_by_class = dominator_tree.retained_by_class(heap);
+
+    let summary = Summary {
+        version: header.version.to_string(),
+        timestamp_millis: header.timestamp.timestamp_millis(),
+        classes: heap.classes.len() as u64,
+        objects: heap.instances.len() as u64,
+        shallow_size: heap.total_shallow_size(),
+        reachable_objects: dominator_tree.reachable_count() as u64,
+        reachable_size: dominator_tree.reachable_size(),
+    };
+
+    let histogram = heap
+        .histogram(filter)
+        .into_iter()
+        .take(rows)
+        .map(|e| HistogramEntry {
+            class_name: e.class.java_name(),
+            instances: e.instance_count,
+            shallow_size: e.shallow_size,
+            retained_size: retained_by_class.get(&e.class.id).copied().unwrap_or(0),
+        })
+        .collect();
+
+    let dominators = dominator_tree
+        .children()
+        .remove(&Id(0))
+        .unwrap_or_default()
+        .into_iter()
+        .take(rows)
+        .map(|id| {
+            let instance = heap.instance(id);
+            Dominator {
+                object_id: id.0,
+                class_name: heap.class_name_of(id).unwrap_or_default(),
+                shallow_size: instance.map_or(0, |i| i.shallow_size),
+                retained_size: dominator_tree.retained_size(id).unwrap_or(0),
+            }
+        })
+        .collect();
+
+    let leak_suspects = suspects
+        .iter()
+        .map(|s| {
+            let (kind, object_id, instance_count) = match s.kind {
+                Kind::Object { object_id } => (SuspectKind::Object, object_id.0, 0),
+                Kind::Class { instance_count } => (SuspectKind::Class, 0, instance_count),
+            };
+            LeakSuspect {
+                kind: kind as i32,
+                class_name: s.class.java_name(),
+                object_id,
+                instance_count,
+                retained_size: s.retained_size,
+                fraction: s.fraction,
+            }
+        })
+        .collect();
+
+    AnalysisResult {
+        schema_version: SCHEMA_VERSION,
+        summary: Some(summary),
+        histogram,
+        dominators,
+        leak_suspects,
+    }
+}
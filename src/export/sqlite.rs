@@ -0,0 +1,255 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Seek},
+    path::Path,
+};
+
+use anyhow::{Context, Result, bail};
+use rusqlite::{Connection, Statement, params};
+
+use crate::{
+    analyzer::{
+        graph::{GcRoot, class_references, instance_references},
+        instance_size, java_name, prim_array_name, prim_size,
+        size::SizeModel,
+    },
+    export::class_layouts,
+    parser::{Id, Record, RecordReader, StringId, sub_record::SubRecord},
+};
+
+// ids are stored as integers, the "refs" table holds one row per outgoing reference including
+// the class reference every object has
+const SCHEMA: &str = "
+CREATE TABLE classes (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    super_class_id INTEGER,
+    instance_size INTEGER
+);
+CREATE TABLE objects (
+    id INTEGER PRIMARY KEY,
+    class_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    shallow_size INTEGER NOT NULL,
+    length INTEGER
+);
+CREATE TABLE refs (
+    from_id INTEGER NOT NULL,
+    to_id INTEGER NOT NULL
+);
+CREATE TABLE strings (
+    id INTEGER PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE threads (
+    object_id INTEGER NOT NULL,
+    serial_number INTEGER NOT NULL,
+    stack_trace_serial_number INTEGER NOT NULL
+);
+CREATE TABLE roots (
+    object_id INTEGER NOT NULL,
+    kind TEXT NOT NULL
+);
+";
+
+// created after loading, maintaining them during the inserts is a lot slower
+const INDEXES: &str = "
+CREATE INDEX objects_class_id ON objects (class_id);
+CREATE INDEX refs_from_id ON refs (from_id);
+CREATE INDEX refs_to_id ON refs (to_id);
+CREATE INDEX roots_object_id ON roots (object_id);
+";
+
+pub struct Counts {
+    pub classes: u64,
+    pub objects: u64,
+    pub references: u64,
+}
+
+// streams the records into a new database at path, only class tables are kept in memory
+pub fn write_database<R: Read + Seek>(
+    mut records: RecordReader<R>,
+    size_model: &SizeModel,
+    path: &Path,
+) -> Result<Counts> {
+    if path.exists() {
+        bail!("{} already exists", path.display());
+    }
+
+    let mut conn = Connection::open(path)
+        .with_context(|| format!("failed to create database {}", path.display()))?;
+    conn.execute_batch("PRAGMA journal_mode = OFF; PRAGMA synchronous = OFF;")?;
+    conn.execute_batch(SCHEMA)?;
+
+    let tx = conn.transaction()?;
+    let mut counts = Counts {
+        classes: 0,
+        objects: 0,
+        references: 0,
+    };
+    {
+        let mut insert_class = tx.prepare("INSERT INTO classes VALUES (?1, ?2, ?3, ?4)")?;
+        let mut insert_object = tx.prepare("INSERT INTO objects VALUES (?1, ?2, ?3, ?4, ?5)")?;
+        let mut insert_ref = tx.prepare("INSERT INTO refs VALUES (?1, ?2)")?;
+        let mut insert_string = tx.prepare("INSERT OR REPLACE INTO strings VALUES (?1, ?2)")?;
+        let mut insert_thread = tx.prepare("INSERT INTO threads VALUES (?1, ?2, ?3)")?;
+        let mut insert_root = tx.prepare("INSERT INTO roots VALUES (?1, ?2)")?;
+
+        let mut strings: HashMap<StringId, String> = HashMap::new();
+        let mut class_names: HashMap<Id, String> = HashMap::new();
+        // primitive array dumps don't reference their class, so look it up by name
+        let mut class_ids: HashMap<String, Id> = HashMap::new();
+        let layouts = class_layouts(&mut records)?;
+        let mut instance_sizes: HashMap<Id, u64> = HashMap::new();
+
+        for record in records {
+            match record? {
+                Record::Utf8 {
+                    name_id, content, ..
+                } => {
+                    insert_string.execute(params![name_id.0 as i64, content])?;
+                    strings.insert(name_id, content);
+                }
+                Record::LoadClass {
+                    class_object_id,
+                    class_name_id,
+                    ..
+                } => {
+                    let name = strings
+                        .get(&class_name_id)
+                        .context("unknown class name string")?;
+                    class_names.insert(class_object_id, java_name(name));
+                    class_ids.insert(name.clone(), class_object_id);
+                }
+                Record::HeapDumpSegment { sub_records, .. } => {
+                    for sub_record in sub_records {
+                        if let Some(root) = GcRoot::from_sub_record(&sub_record) {
+                            insert_root
+                                .execute(params![sql_id(root.object_id), root.kind.to_string()])?;
+                        }
+
+                        match sub_record {
+                            SubRecord::ClassDump {
+                                class_object_id, ..
+                            } => {
+                                counts.references += insert_refs(
+                                    &mut insert_ref,
+                                    class_object_id,
+                                    &class_references(&sub_record),
+                                )?;
+                            }
+                            SubRecord::InstanceDump {
+                                object_id,
+                                class_object_id,
+                                raw_field_bytes,
+                                ..
+                            } => {
+                                let shallow_size =
+                                    *instance_sizes.entry(class_object_id).or_insert_with(|| {
+                                        instance_size(class_object_id, &layouts, size_model)
+                                    });
+                                insert_object.execute(params![
+                                    sql_id(object_id),
+                                    sql_id(class_object_id),
+                                    "instance",
+                                    shallow_size as i64,
+                                    None::<i64>,
+                                ])?;
+                                counts.references += insert_refs(
+                                    &mut insert_ref,
+                                    object_id,
+                                    &instance_references(
+                                        class_object_id,
+                                        &raw_field_bytes,
+                                        &layouts,
+                                    )?,
+                                )?;
+                                counts.objects += 1;
+                            }
+                            SubRecord::ObjArrayDump {
+                                object_id,
+                                array_class_id,
+                                elements,
+                                ..
+                            } => {
+                                insert_object.execute(params![
+                                    sql_id(object_id),
+                                    sql_id(array_class_id),
+                                    "object_array",
+                                    size_model.object_array_size(elements.len() as u64) as i64,
+                                    elements.len() as i64,
+                                ])?;
+                                let mut references = vec![array_class_id];
+                                references.extend(elements.iter().filter(|id| id.0 != 0));
+                                counts.references +=
+                                    insert_refs(&mut insert_ref, object_id, &references)?;
+                                counts.objects += 1;
+                            }
+                            SubRecord::PrimArrayDump {
+                                object_id,
+                                typ,
+                                elements,
+                                ..
+                            } => {
+                                let name = prim_array_name(typ)?;
+                                let class_id = class_ids.get(name).with_context(|| {
+                                    format!("primitive array class {} not found", name)
+                                })?;
+                                insert_object.execute(params![
+                                    sql_id(object_id),
+                                    sql_id(*class_id),
+                                    "primitive_array",
+                                    size_model
+                                        .prim_array_size(elements.len() as u64, prim_size(typ)?)
+                                        as i64,
+                                    elements.len() as i64,
+                                ])?;
+                                counts.objects += 1;
+                            }
+                            SubRecord::ThreadObj {
+                                object_id,
+                                sequence_number,
+                                stack_trace_sequence_number,
+                            } => {
+                                insert_thread.execute(params![
+                                    sql_id(object_id),
+                                    sequence_number,
+                                    stack_trace_sequence_number,
+                                ])?;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // written last, once the names of all classes are known
+        for (class_id, layout) in &layouts {
+            insert_class.execute(params![
+                sql_id(*class_id),
+                class_names.get(class_id).context("class not found")?,
+                layout.super_class_id.map(sql_id),
+                instance_size(*class_id, &layouts, size_model) as i64,
+            ])?;
+            counts.classes += 1;
+        }
+    }
+    tx.commit()?;
+
+    conn.execute_batch(INDEXES)?;
+    Ok(counts)
+}
+
+fn insert_refs(statement: &mut Statement, from: Id, to: &[Id]) -> Result<u64> {
+    for id in to {
+        statement.execute(params![sql_id(from), sql_id(*id)])?;
+    }
+    Ok(to.len() as u64)
+}
+
+// object ids are addresses and fit into sqlite's signed integers
+fn sql_id(id: Id) -> i64 {
+    id.0 as i64
+}
@@ -0,0 +1,230 @@
+use std::{cmp::Reverse, collections::HashMap, io::Write};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+
+use crate::{analyzer::AnalyzedHeap, output::csv_field, parser::Header};
+
+// one dump of a series taken from the same process
+#[derive(Debug, Clone)]
+pub struct TrendPoint {
+    pub dump: String,
+    pub timestamp: DateTime<Utc>,
+    pub total_bytes: u64,
+    pub reachable_bytes: u64,
+    pub objects: u64,
+    pub threads: u64,
+    pub class_retained_bytes: HashMap<String, u64>,
+}
+
+impl TrendPoint {
+    pub fn new(dump: &str, header: &Header, heap: &AnalyzedHeap) -> Self {
+        let dominator_tree = heap.dominator_tree();
+        // classes loaded by different class loaders share a name, their sizes are summed up
+        let mut class_retained_bytes: HashMap<String, u64> = HashMap::new();
+        for (class_id, retained) in dominator_tree.retained_by_class(heap) {
+            if let Some(class) = heap.classes.get(&class_id) {
+                *class_retained_bytes.entry(class.java_name()).or_default() += retained;
+            }
+        }
+
+        Self {
+            dump: dump.to_string(),
+            timestamp: header.timestamp,
+            total_bytes: heap.total_shallow_size(),
+            reachable_bytes: dominator_tree.reachable_size(),
+            objects: heap.instances.len() as u64,
+            threads: heap.threads.len() as u64,
+            class_retained_bytes,
+        }
+    }
+}
+
+// wide rows for grafana, oldest dump first: the time, the dump, the totals and the retained size
+// of the classes retaining the most in any of the dumps, one column each
+pub struct Trend {
+    points: Vec<TrendPoint>,
+    classes: Vec<String>,
+}
+
+const COLUMNS: [&str; 6] = [
+    "time",
+    "dump",
+    "total_bytes",
+    "reachable_bytes",
+    "objects",
+    "threads",
+];
+
+impl Trend {
+    pub fn new(mut points: Vec<TrendPoint>, top_classes: usize) -> Self {
+        points.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.dump.cmp(&b.dump)));
+
+        let mut peaks: HashMap<&str, u64> = HashMap::new();
+        for point in &points {
+            for (class, retained) in &point.class_retained_bytes {
+                let peak = peaks.entry(class).or_default();
+                *peak = (*peak).max(*retained);
+            }
+        }
+        let mut peaks: Vec<(&str, u64)> = peaks.into_iter().collect();
+        peaks.sort_by_key(|(class, peak)| (Reverse(*peak), *class));
+        let classes = peaks
+            .into_iter()
+            .take(top_classes)
+            .map(|(class, _)| class.to_string())
+            .collect();
+
+        Self { points, classes }
+    }
+
+    // dumps in the series
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn write_json(&self, w: &mut impl Write) -> Result<()> {
+        let rows: Vec<Map<String, Value>> = self
+            .points
+            .iter()
+            .map(|point| {
+                let mut row = Map::new();
+                for (column, value) in COLUMNS.iter().zip(values(point)) {
+                    row.insert(column.to_string(), value);
+                }
+                for class in &self.classes {
+                    row.insert(class.clone(), retained(point, class).into());
+                }
+                row
+            })
+            .collect();
+        serde_json::to_writer_pretty(&mut *w, &rows)?;
+        writeln!(w)?;
+        Ok(())
+    }
+
+    pub fn write_csv(&self, w: &mut impl Write) -> Result<()> {
+        let header: Vec<String> = COLUMNS
+            .iter()
+            .map(|c| c.to_string())
+            .chain(self.classes.iter().map(|c| csv_field(c)))
+            .collect();
+        writeln!(w, "{}", header.join(","))?;
+
+        for point in &self.points {
+            let fields: Vec<String> = values(point)
+                .into_iter()
+                .map(|value| match value {
+                    Value::String(s) => csv_field(&s),
+                    value => value.to_string(),
+                })
+                .chain(
+                    self.classes
+                        .iter()
+                        .map(|class| retained(point, class).to_string()),
+                )
+                .collect();
+            writeln!(w, "{}", fields.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+// in the order of COLUMNS
+fn values(point: &TrendPoint) -> [Value; 6] {
+    [
+        point.timestamp.to_rfc3339().into(),
+        point.dump.clone().into(),
+        point.total_bytes.into(),
+        point.reachable_bytes.into(),
+        point.objects.into(),
+        point.threads.into(),
+    ]
+}
+
+// classes without instances in a dump retain nothing there
+fn retained(point: &TrendPoint, class: &str) -> u64 {
+    point.class_retained_bytes.get(class).copied().unwrap_or(0)
+}
+
+// a class whose retained size grew in every dump of a series
+#[derive(Debug, Clone)]
+pub struct ProbableLeak {
+    pub class: String,
+    pub first_bytes: u64,
+    pub last_bytes: u64,
+    // slope of the least squares line through the retained sizes
+    pub bytes_per_hour: f64,
+}
+
+// fewer dumps can't tell a leak from a spike
+pub const MIN_LEAK_POINTS: usize = 3;
+
+impl Trend {
+    // classes retaining at least as much in each dump as in the one before, more in the last
+    // than the first, and growing by at least min_bytes_per_hour. fastest growing first
+    pub fn probable_leaks(&self, min_bytes_per_hour: f64) -> Vec<ProbableLeak> {
+        if self.points.len() < MIN_LEAK_POINTS {
+            return Vec::new();
+        }
+        let start = self.points[0].timestamp;
+        let hours: Vec<f64> = self
+            .points
+            .iter()
+            .map(|p| (p.timestamp - start).num_milliseconds() as f64 / 3_600_000.0)
+            .collect();
+        // dumps taken at the same time leave nothing to fit a line to
+        if hours.iter().all(|h| *h == hours[0]) {
+            return Vec::new();
+        }
+
+        let mut classes: Vec<&String> = self
+            .points
+            .iter()
+            .flat_map(|p| p.class_retained_bytes.keys())
+            .collect();
+        classes.sort();
+        classes.dedup();
+
+        let mut leaks: Vec<ProbableLeak> = classes
+            .into_iter()
+            .filter_map(|class| {
+                let sizes: Vec<u64> = self.points.iter().map(|p| retained(p, class)).collect();
+                let (first, last) = (sizes[0], sizes[sizes.len() - 1]);
+                if last <= first || sizes.windows(2).any(|w| w[1] < w[0]) {
+                    return None;
+                }
+                let bytes_per_hour = slope(&hours, &sizes);
+                (bytes_per_hour >= min_bytes_per_hour).then(|| ProbableLeak {
+                    class: class.clone(),
+                    first_bytes: first,
+                    last_bytes: last,
+                    bytes_per_hour,
+                })
+            })
+            .collect();
+        leaks.sort_by(|a, b| {
+            b.bytes_per_hour
+                .total_cmp(&a.bytes_per_hour)
+                .then_with(|| a.class.cmp(&b.class))
+        });
+        leaks
+    }
+}
+
+fn slope(xs: &[f64], ys: &[u64]) -> f64 {
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().map(|y| *y as f64).sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (x, y) in xs.iter().zip(ys) {
+        covariance += (x - mean_x) * (*y as f64 - mean_y);
+        variance += (x - mean_x) * (x - mean_x);
+    }
+    covariance / variance
+}
@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use rust_xlsxwriter::{Format, Workbook, Worksheet};
+
+use crate::{
+    analyzer::AnalyzedHeap,
+    export::json::{DominatorNode, Report},
+};
+
+// one sheet per view of the json report and one of the duplicate strings of the heap it was
+// made of. numbers are written as numbers so they can be summed
+pub fn write_workbook(report: &Report, heap: &AnalyzedHeap, path: &Path) -> Result<()> {
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+    let bytes = Format::new().set_num_format("#,##0");
+
+    let summary = &report.summary;
+    let sheet = add_sheet(&mut workbook, "Summary", &["Key", "Value"], &bold)?;
+    sheet.write_string(1, 0, "Version")?;
+    sheet.write_string(1, 1, &summary.version)?;
+    sheet.write_string(2, 0, "Timestamp")?;
+    sheet.write_string(2, 1, &summary.timestamp)?;
+    let counts = [
+        ("Classes", summary.classes as u64),
+        ("Objects", summary.objects as u64),
+        ("Shallow size", summary.shallow_size),
+        ("Reachable objects", summary.reachable_objects as u64),
+        ("Reachable size", summary.reachable_size),
+    ];
+    for (i, (key, value)) in counts.into_iter().enumerate() {
+        let row = 3 + i as u32;
+        sheet.write_string(row, 0, key)?;
+        sheet.write_number_with_format(row, 1, value as f64, &bytes)?;
+    }
+    sheet.autofit();
+
+    let sheet = add_sheet(
+        &mut workbook,
+        "Histogram",
+        &["Class", "Objects", "Shallow", "Retained"],
+        &bold,
+    )?;
+    for (i, entry) in report.histogram.iter().enumerate() {
+        let row = 1 + i as u32;
+        sheet.write_string(row, 0, &entry.class)?;
+        sheet.write_number_with_format(row, 1, entry.instances as f64, &bytes)?;
+        sheet.write_number_with_format(row, 2, entry.shallow_size as f64, &bytes)?;
+        sheet.write_number_with_format(row, 3, entry.retained_size as f64, &bytes)?;
+    }
+    sheet.autofit();
+
+    let sheet = add_sheet(
+        &mut workbook,
+        "Dominators",
+        &["Depth", "Object", "Class", "Shallow", "Retained"],
+        &bold,
+    )?;
+    let mut rows = Vec::new();
+    flatten(&report.dominator_tree, 0, &mut rows);
+    for (i, (depth, node)) in rows.into_iter().enumerate() {
+        let row = 1 + i as u32;
+        sheet.write_number(row, 0, depth as f64)?;
+        sheet.write_string(row, 1, &node.id)?;
+        // indented so the tree structure stays visible
+        sheet.write_string(row, 2, format!("{}{}", "  ".repeat(depth), node.class))?;
+        sheet.write_number_with_format(row, 3, node.shallow_size as f64, &bytes)?;
+        sheet.write_number_with_format(row, 4, node.retained_size as f64, &bytes)?;
+    }
+    sheet.autofit();
+
+    // utf8 records sharing their content, the most bytes wasted on copies first
+    let sheet = add_sheet(
+        &mut workbook,
+        "Duplicates",
+        &["String", "Copies", "Wasted"],
+        &bold,
+    )?;
+    let mut duplicates: Vec<(&str, usize)> = heap
+        .duplicate_strings()
+        .iter()
+        .filter_map(|ids| Some((&**heap.strings.get(&ids[0])?, ids.len())))
+        .collect();
+    let wasted = |(content, copies): (&str, usize)| (content.len() * (copies - 1)) as u64;
+    duplicates.sort_by(|a, b| wasted(*b).cmp(&wasted(*a)).then_with(|| a.0.cmp(b.0)));
+    for (i, duplicate) in duplicates.into_iter().enumerate() {
+        let row = 1 + i as u32;
+        sheet.write_string(row, 0, duplicate.0)?;
+        sheet.write_number(row, 1, duplicate.1 as f64)?;
+        sheet.write_number_with_format(row, 2, wasted(duplicate) as f64, &bytes)?;
+    }
+    sheet.autofit();
+
+    let sheet = add_sheet(
+        &mut workbook,
+        "Threads",
+        &["Serial", "Object", "Class", "Retained", "Stack trace"],
+        &bold,
+    )?;
+    for (i, thread) in report.threads.iter().enumerate() {
+        let row = 1 + i as u32;
+        let stack_trace: Vec<String> = thread
+            .stack_trace
+            .iter()
+            .map(|f| format!("{} {}:{}", f.source_file, f.method, f.line))
+            .collect();
+        sheet.write_number(row, 0, thread.serial_number as f64)?;
+        sheet.write_string(row, 1, &thread.id)?;
+        sheet.write_string(row, 2, &thread.class)?;
+        sheet.write_number_with_format(row, 3, thread.retained_size as f64, &bytes)?;
+        sheet.write_string(row, 4, stack_trace.join("\n"))?;
+    }
+    sheet.autofit();
+
+    workbook
+        .save(path)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(())
+}
+
+fn add_sheet<'a>(
+    workbook: &'a mut Workbook,
+    name: &str,
+    headers: &[&str],
+    bold: &Format,
+) -> Result<&'a mut Worksheet> {
+    let sheet = workbook.add_worksheet();
+    sheet.set_name(name)?;
+    for (col, header) in headers.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, bold)?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    Ok(sheet)
+}
+
+// preorder, so children follow their dominator
+fn flatten<'a>(
+    nodes: &'a [DominatorNode],
+    depth: usize,
+    rows: &mut Vec<(usize, &'a DominatorNode)>,
+) {
+    for node in nodes {
+        rows.push((depth, node));
+        flatten(&node.children, depth + 1, rows);
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{CStr, CString, c_char},
+    path::Path,
+    ptr,
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    analyzer::{AnalyzedHeap, filter::ClassFilter, size::SizeModel, storage::Storage},
+    parser::Id,
+};
+
+// c api of the cdylib, declared in include/heapdump_analyzer.h. functions returning a pointer or
+// bool report failures with null or false, hda_last_error tells why
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+pub struct HdaHeap {
+    heap: AnalyzedHeap,
+    // nul terminated class names handed out to callers, valid until the heap is closed
+    class_names: HashMap<Id, CString>,
+}
+
+pub struct HdaHistogram {
+    entries: Vec<HdaHistogramEntry>,
+    next: usize,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct HdaHistogramEntry {
+    pub class_name: *const c_char,
+    pub instance_count: u64,
+    pub shallow_size: u64,
+}
+
+#[repr(C)]
+pub struct HdaObject {
+    pub id: u64,
+    pub class_name: *const c_char,
+    pub shallow_size: u64,
+    // only set once hda_heap_compute_dominators succeeded, otherwise 0
+    pub retained_size: u64,
+}
+
+/// Analyzes the dump at the given path. Returns null on failure.
+///
+/// # Safety
+/// `path` has to be a valid nul terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hda_heap_open(path: *const c_char) -> *mut HdaHeap {
+    let path = unsafe { CStr::from_ptr(path) };
+    let heap = fallible(|| {
+        let path = path.to_str().context("path isn't valid utf8")?;
+        let (_, heap) =
+            AnalyzedHeap::analyze_file(Path::new(path), SizeModel::default(), &Storage::Memory)?;
+        // java writes nul in names as c0 80, which the parser decodes to \0
+        let class_names = heap
+            .classes
+            .values()
+            .map(|c| (c.id, c_string(&c.java_name())))
+            .collect();
+        Ok(HdaHeap { heap, class_names })
+    });
+    heap.map_or(ptr::null_mut(), |heap| Box::into_raw(Box::new(heap)))
+}
+
+/// # Safety
+/// `heap` has to come from `hda_heap_open` or be null, and isn't valid afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hda_heap_close(heap: *mut HdaHeap) {
+    if !heap.is_null() {
+        drop(unsafe { Box::from_raw(heap) });
+    }
+}
+
+/// Computes the dominator tree, which fills in retained sizes of objects.
+///
+/// # Safety
+/// `heap` has to be an open heap.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hda_heap_compute_dominators(heap: *const HdaHeap) {
+    let heap = unsafe { &*heap };
+    heap.heap.dominator_tree();
+}
+
+/// Class histogram, largest shallow size first. `include` is an optional class name prefix and
+/// may be null. Returns null on failure.
+///
+/// # Safety
+/// `heap` has to be an open heap, `include` null or a valid nul terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hda_histogram(
+    heap: *const HdaHeap,
+    include: *const c_char,
+) -> *mut HdaHistogram {
+    let heap = unsafe { &*heap };
+    let include = (!include.is_null()).then(|| unsafe { CStr::from_ptr(include) });
+    let histogram = fallible(|| {
+        let mut filter = ClassFilter::default();
+        if let Some(include) = include {
+            filter
+                .include
+                .push(include.to_str().context("prefix isn't valid utf8")?.into());
+        }
+
+        let entries = heap
+            .heap
+            .histogram(&filter)
+            .into_iter()
+            .map(|e| HdaHistogramEntry {
+                class_name: heap.class_names[&e.class.id].as_ptr(),
+                instance_count: e.instance_count,
+                shallow_size: e.shallow_size,
+            })
+            .collect();
+        Ok(HdaHistogram { entries, next: 0 })
+    });
+    histogram.map_or(ptr::null_mut(), |h| Box::into_raw(Box::new(h)))
+}
+
+/// Writes the next entry to `entry`, false once all entries were returned. Class names stay
+/// valid until the heap is closed.
+///
+/// # Safety
+/// `histogram` has to come from `hda_histogram`, `entry` has to be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hda_histogram_next(
+    histogram: *mut HdaHistogram,
+    entry: *mut HdaHistogramEntry,
+) -> bool {
+    let histogram = unsafe { &mut *histogram };
+    let Some(next) = histogram.entries.get(histogram.next) else {
+        return false;
+    };
+    histogram.next += 1;
+    unsafe { entry.write(*next) };
+    true
+}
+
+/// # Safety
+/// `histogram` has to come from `hda_histogram` or be null, and isn't valid afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hda_histogram_free(histogram: *mut HdaHistogram) {
+    if !histogram.is_null() {
+        drop(unsafe { Box::from_raw(histogram) });
+    }
+}
+
+/// Looks up an instance or array. False if there is none with this id.
+///
+/// # Safety
+/// `heap` has to be an open heap, `object` has to be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hda_object(heap: *const HdaHeap, id: u64, object: *mut HdaObject) -> bool {
+    let heap = unsafe { &*heap };
+    let found = fallible(|| {
+        let instance = heap
+            .heap
+            .instance(Id(id))
+            .with_context(|| format!("object 0x{:x} not found", id))?;
+        let retained_size = if heap.heap.has_dominator_tree() {
+            heap.heap
+                .dominator_tree()
+                .retained_size(Id(id))
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        Ok(HdaObject {
+            id,
+            class_name: heap.class_names[&instance.class.id].as_ptr(),
+            shallow_size: instance.shallow_size,
+            retained_size,
+        })
+    });
+    match found {
+        Some(found) => {
+            unsafe { object.write(found) };
+            true
+        }
+        None => false,
+    }
+}
+
+/// Points `ids` at the ids the object references and sets `len`. The array stays valid until
+/// the heap is closed. False if there is no object with this id.
+///
+/// # Safety
+/// `heap` has to be an open heap, `ids` and `len` have to be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn hda_references(
+    heap: *const HdaHeap,
+    id: u64,
+    ids: *mut *const u64,
+    len: *mut usize,
+) -> bool {
+    let heap = unsafe { &*heap };
+    let references = fallible(|| {
+        heap.heap
+            .references_of(Id(id))
+            .with_context(|| format!("object 0x{:x} not found", id))
+    });
+    match references {
+        Some(references) => {
+            // Id is a transparent u64
+            unsafe {
+                ids.write(references.as_ptr() as *const u64);
+                len.write(references.len());
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Message of the last failure on this thread, or null. Valid until the next call failing on
+/// this thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn hda_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+fn fallible<T>(f: impl FnOnce() -> Result<T>) -> Option<T> {
+    match f() {
+        Ok(value) => Some(value),
+        Err(err) => {
+            let message = c_string(&format!("{:#}", err));
+            LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+            None
+        }
+    }
+}
+
+// nul bytes would end the string early, they are replaced by spaces
+fn c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', " ")).expect("nul bytes were replaced")
+}
@@ -0,0 +1,155 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+
+use crate::output::parse_bytes;
+
+// collections considered for the occupancy trend before the dump
+const TREND_WINDOW: usize = 10;
+// change of the occupancy after gc over the window that counts as rising or falling
+const TREND_THRESHOLD: f64 = 0.1;
+
+// the pauses of a unified jvm gc log, as written with -Xlog:gc
+pub struct GcLog {
+    pub events: Vec<GcEvent>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GcEvent {
+    pub id: u64,
+    // only set when the log is decorated with time or utctime
+    pub time: Option<DateTime<Utc>>,
+    // like "Pause Young (Normal)"
+    pub kind: String,
+    // like "G1 Evacuation Pause", "System.gc()" or "Heap Dump Initiated GC"
+    pub cause: String,
+    pub full: bool,
+    pub before: u64,
+    pub after: u64,
+    pub capacity: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    Rising,
+    Falling,
+    Stable,
+}
+
+// what the gc log tells about the time leading up to a dump
+#[derive(Debug)]
+pub struct GcContext {
+    pub collections: usize,
+    pub last: GcEvent,
+    // the last collection before the dump was a full gc, as dumping only live objects causes
+    pub full_gc_before_dump: bool,
+    // occupancy after gc at the start and the end of the trend window
+    pub occupancy: (u64, u64),
+    pub trend: Trend,
+}
+
+impl GcLog {
+    pub fn read(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        Self::parse(BufReader::new(file))
+    }
+
+    pub fn parse(r: impl BufRead) -> Result<Self> {
+        let mut events = Vec::new();
+        for line in r.lines() {
+            if let Some(event) = parse_line(&line?) {
+                events.push(event);
+            }
+        }
+        Ok(Self { events })
+    }
+
+    // collections before the dump. logs without wall clock times can't be lined up with the
+    // dump, they are assumed to end with it
+    pub fn context(&self, dump_time: DateTime<Utc>) -> Option<GcContext> {
+        let events: Vec<&GcEvent> = self
+            .events
+            .iter()
+            .filter(|e| e.time.is_none_or(|t| t <= dump_time))
+            .collect();
+        let last = *events.last()?;
+
+        let window = &events[events.len().saturating_sub(TREND_WINDOW)..];
+        let first = window[0].after;
+        let change = (last.after as f64 - first as f64) / first.max(1) as f64;
+        let trend = if window.len() < 2 {
+            Trend::Stable
+        } else if change > TREND_THRESHOLD {
+            Trend::Rising
+        } else if change < -TREND_THRESHOLD {
+            Trend::Falling
+        } else {
+            Trend::Stable
+        };
+
+        Some(GcContext {
+            collections: events.len(),
+            last: last.clone(),
+            full_gc_before_dump: last.full,
+            occupancy: (first, last.after),
+            trend,
+        })
+    }
+}
+
+// "[2024-05-01T10:00:00.123+0000][1.234s][info][gc] GC(7) Pause Young (Normal) (G1 Evacuation
+// Pause) 24M->5M(256M) 3.456ms", None for every other line
+fn parse_line(line: &str) -> Option<GcEvent> {
+    let mut rest = line.trim_start();
+    let mut time = None;
+    while let Some(decoration) = rest.strip_prefix('[') {
+        let (decoration, after) = decoration.split_once(']')?;
+        if time.is_none() {
+            time = DateTime::parse_from_str(decoration.trim(), "%Y-%m-%dT%H:%M:%S%.f%z")
+                .ok()
+                .map(|t| t.with_timezone(&Utc));
+        }
+        rest = after;
+    }
+
+    let message = rest.trim().strip_prefix("GC(")?;
+    let (id, message) = message.split_once(") ")?;
+    let id = id.parse().ok()?;
+    if !message.starts_with("Pause ") && !message.starts_with("Garbage Collection ") {
+        return None;
+    }
+
+    // the description runs up to the token with the sizes
+    let tokens: Vec<&str> = message.split_whitespace().collect();
+    let sizes_at = tokens.iter().position(|t| t.contains("->"))?;
+    let description = tokens[..sizes_at].join(" ");
+    let (before, after) = tokens[sizes_at].split_once("->")?;
+    let (after, capacity) = match after.split_once('(') {
+        Some((after, capacity)) => (after, capacity.strip_suffix(')')),
+        None => (after, None),
+    };
+    // zgc adds the share of the heap, "10M(5%)->4M(2%)"
+    let before = before.split('(').next()?;
+    let capacity = capacity.filter(|c| !c.ends_with('%'));
+
+    let (kind, cause) = match description.rsplit_once(" (") {
+        Some((kind, cause)) => (kind, cause.strip_suffix(')').unwrap_or(cause)),
+        None => (description.as_str(), ""),
+    };
+    Some(GcEvent {
+        id,
+        time,
+        kind: kind.to_string(),
+        cause: cause.to_string(),
+        full: kind.starts_with("Pause Full"),
+        before: parse_bytes(before).ok()?,
+        after: parse_bytes(after).ok()?,
+        capacity: capacity.and_then(|c| parse_bytes(c).ok()),
+    })
+}
@@ -0,0 +1,448 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        Arc, OnceLock, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use anyhow::Result;
+use tempfile::NamedTempFile;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status, Streaming, transport::Server};
+
+use crate::{
+    analyzer::{
+        AnalyzedHeap,
+        contents::Contents,
+        filter::ClassFilter,
+        handle::Handle,
+        index::HeapIndex,
+        predicate::{FieldPredicate, find_instances},
+    },
+    config::Config,
+    parser::Id,
+    trace::info,
+};
+
+pub use crate::export::proto::{HistogramEntry, Summary};
+
+mod generated {
+    include!(concat!(
+        env!("OUT_DIR"),
+        "/heapdump_analyzer.v1.Analysis.rs"
+    ));
+}
+
+pub use generated::{analysis_client::AnalysisClient, analysis_server::AnalysisServer};
+
+// hand written counterparts of the messages in proto/service.proto, keep the tags in sync
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OpenRequest {
+    #[prost(string, tag = "1")]
+    pub path: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct UploadChunk {
+    #[prost(bytes = "vec", tag = "1")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OpenResponse {
+    #[prost(uint64, tag = "1")]
+    pub heap_id: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HeapRequest {
+    #[prost(uint64, tag = "1")]
+    pub heap_id: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CloseResponse {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HistogramRequest {
+    #[prost(uint64, tag = "1")]
+    pub heap_id: u64,
+    #[prost(string, repeated, tag = "2")]
+    pub include: Vec<String>,
+    #[prost(string, repeated, tag = "3")]
+    pub exclude: Vec<String>,
+    #[prost(bool, tag = "4")]
+    pub retained: bool,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ObjectRequest {
+    #[prost(uint64, tag = "1")]
+    pub heap_id: u64,
+    #[prost(uint64, tag = "2")]
+    pub object_id: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Object {
+    #[prost(uint64, tag = "1")]
+    pub object_id: u64,
+    #[prost(string, tag = "2")]
+    pub class_name: String,
+    #[prost(uint64, tag = "3")]
+    pub shallow_size: u64,
+    #[prost(uint64, tag = "4")]
+    pub retained_size: u64,
+    #[prost(uint64, repeated, tag = "5")]
+    pub references: Vec<u64>,
+    #[prost(uint64, repeated, tag = "6")]
+    pub referrers: Vec<u64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PathElement {
+    #[prost(uint64, tag = "1")]
+    pub object_id: u64,
+    #[prost(string, tag = "2")]
+    pub class_name: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FindRequest {
+    #[prost(uint64, tag = "1")]
+    pub heap_id: u64,
+    #[prost(string, tag = "2")]
+    pub class_name: String,
+    #[prost(string, repeated, tag = "3")]
+    pub conditions: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FoundObject {
+    #[prost(uint64, tag = "1")]
+    pub object_id: u64,
+    #[prost(string, tag = "2")]
+    pub class_name: String,
+    #[prost(uint64, tag = "3")]
+    pub shallow_size: u64,
+    #[prost(uint64, tag = "4")]
+    pub retained_size: u64,
+}
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+struct LoadedHeap {
+    index: HeapIndex,
+    dump: PathBuf,
+    // field values for find, mapped from the dump on first use
+    contents: OnceLock<Contents>,
+    // uploaded dumps are deleted once the heap is closed
+    _upload: Option<NamedTempFile>,
+}
+
+// heaps stay loaded until they are closed. analyses run on the blocking pool, the dominator
+// tree is computed by the first request needing it
+pub struct AnalysisService {
+    config: Arc<Config>,
+    heaps: RwLock<HashMap<u64, Arc<LoadedHeap>>>,
+    next_id: AtomicU64,
+}
+
+impl AnalysisService {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config: Arc::new(config),
+            heaps: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    fn heap(&self, heap_id: u64) -> Result<Arc<LoadedHeap>, Status> {
+        self.heaps
+            .read()
+            .unwrap()
+            .get(&heap_id)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("heap {} isn't loaded", heap_id)))
+    }
+
+    fn insert(&self, heap: LoadedHeap) -> OpenResponse {
+        let heap_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.heaps.write().unwrap().insert(heap_id, Arc::new(heap));
+        OpenResponse { heap_id }
+    }
+}
+
+// same as the cli, except that uploads never get a sidecar index
+fn open_dump(config: &Config, dump: &Path, use_index: bool) -> Result<HeapIndex> {
+    let options = config.analysis_options();
+    if use_index && config.index.enabled {
+        HeapIndex::open(dump, &options)
+    } else {
+        HeapIndex::build(dump, &options)
+    }
+}
+
+#[tonic::async_trait]
+impl generated::analysis_server::Analysis for AnalysisService {
+    async fn open(&self, request: Request<OpenRequest>) -> Result<Response<OpenResponse>, Status> {
+        let path = request.into_inner().path;
+        let config = self.config.clone();
+        let dump = PathBuf::from(path);
+        let index = blocking({
+            let dump = dump.clone();
+            move || open_dump(&config, &dump, true)
+        })
+        .await?;
+        Ok(Response::new(self.insert(LoadedHeap {
+            index,
+            dump,
+            contents: OnceLock::new(),
+            _upload: None,
+        })))
+    }
+
+    async fn upload(
+        &self,
+        request: Request<Streaming<UploadChunk>>,
+    ) -> Result<Response<OpenResponse>, Status> {
+        let dir = self
+            .config
+            .analysis
+            .spill_dir
+            .clone()
+            .unwrap_or_else(std::env::temp_dir);
+        let mut file = NamedTempFile::with_suffix_in(".hprof", dir).map_err(internal)?;
+        let mut chunks = request.into_inner();
+        while let Some(chunk) = chunks.next().await {
+            file.write_all(&chunk?.data).map_err(internal)?;
+        }
+        file.flush().map_err(internal)?;
+
+        let config = self.config.clone();
+        let (index, file) = blocking(move || {
+            let index = open_dump(&config, file.path(), false)?;
+            Ok((index, file))
+        })
+        .await?;
+        Ok(Response::new(self.insert(LoadedHeap {
+            index,
+            dump: file.path().to_path_buf(),
+            contents: OnceLock::new(),
+            _upload: Some(file),
+        })))
+    }
+
+    async fn close(
+        &self,
+        request: Request<HeapRequest>,
+    ) -> Result<Response<CloseResponse>, Status> {
+        let heap_id = request.into_inner().heap_id;
+        match self.heaps.write().unwrap().remove(&heap_id) {
+            Some(_) => Ok(Response::new(CloseResponse {})),
+            None => Err(Status::not_found(format!("heap {} isn't loaded", heap_id))),
+        }
+    }
+
+    async fn summary(&self, request: Request<HeapRequest>) -> Result<Response<Summary>, Status> {
+        let loaded = self.heap(request.into_inner().heap_id)?;
+        let summary = blocking(move || {
+            let (header, heap) = (&loaded.index.header, &loaded.index.heap);
+            let dominator_tree = heap.dominator_tree();
+            Ok(Summary {
+                version: header.version.to_string(),
+                timestamp_millis: header.timestamp.timestamp_millis(),
+                classes: heap.classes.len() as u64,
+                objects: heap.instances.len() as u64,
+                shallow_size: heap.total_shallow_size(),
+                reachable_objects: dominator_tree.reachable_count() as u64,
+                reachable_size: dominator_tree.reachable_size(),
+            })
+        })
+        .await?;
+        Ok(Response::new(summary))
+    }
+
+    type HistogramStream = ResponseStream<HistogramEntry>;
+
+    async fn histogram(
+        &self,
+        request: Request<HistogramRequest>,
+    ) -> Result<Response<Self::HistogramStream>, Status> {
+        let request = request.into_inner();
+        let loaded = self.heap(request.heap_id)?;
+        let entries = blocking(move || {
+            let heap = &loaded.index.heap;
+            let filter = ClassFilter {
+                include: request.include,
+                exclude: request.exclude,
+            };
+            let retained_by_class = if request.retained {
+                heap.dominator_tree().retained_by_class(heap)
+            } else {
+                HashMap::new()
+            };
+            Ok(heap
+                .histogram(&filter)
+                .into_iter()
+                .map(|e| HistogramEntry {
+                    class_name: e.class.java_name(),
+                    instances: e.instance_count,
+                    shallow_size: e.shallow_size,
+                    retained_size: retained_by_class.get(&e.class.id).copied().unwrap_or(0),
+                })
+                .collect::<Vec<_>>())
+        })
+        .await?;
+        Ok(Response::new(stream(entries)))
+    }
+
+    async fn inspect(&self, request: Request<ObjectRequest>) -> Result<Response<Object>, Status> {
+        let request = request.into_inner();
+        let loaded = self.heap(request.heap_id)?;
+        let id = Id(request.object_id);
+        let handle = object_handle(&loaded.index.heap, id)?;
+        let object = blocking(move || {
+            let heap = &loaded.index.heap;
+            // the dominator tree is left to summary and histogram, it takes long on large heaps
+            let retained_size = if heap.has_dominator_tree() {
+                heap.dominator_tree().retained_size(id).unwrap_or(0)
+            } else {
+                0
+            };
+            Ok(Object {
+                object_id: id.0,
+                class_name: heap.class_name_of(id).unwrap_or_default(),
+                shallow_size: heap.instance(id).map_or(0, |i| i.shallow_size),
+                retained_size,
+                references: heap.references.get(handle).iter().map(|id| id.0).collect(),
+                referrers: heap
+                    .referrers(handle)
+                    .map(|h| heap.handles.id(h).0)
+                    .collect(),
+            })
+        })
+        .await?;
+        Ok(Response::new(object))
+    }
+
+    type PathsStream = ResponseStream<PathElement>;
+
+    async fn paths(
+        &self,
+        request: Request<ObjectRequest>,
+    ) -> Result<Response<Self::PathsStream>, Status> {
+        let request = request.into_inner();
+        let loaded = self.heap(request.heap_id)?;
+        let handle = object_handle(&loaded.index.heap, Id(request.object_id))?;
+        let path = blocking(move || {
+            let heap = &loaded.index.heap;
+            Ok(heap
+                .path_to_root(handle)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|h| {
+                    let id = heap.handles.id(h);
+                    PathElement {
+                        object_id: id.0,
+                        class_name: heap.class_name_of(id).unwrap_or_default(),
+                    }
+                })
+                .collect::<Vec<_>>())
+        })
+        .await?;
+        Ok(Response::new(stream(path)))
+    }
+
+    type FindStream = ResponseStream<FoundObject>;
+
+    async fn find(
+        &self,
+        request: Request<FindRequest>,
+    ) -> Result<Response<Self::FindStream>, Status> {
+        let request = request.into_inner();
+        let predicates = request
+            .conditions
+            .iter()
+            .map(|condition| condition.parse::<FieldPredicate>())
+            .collect::<Result<Vec<_>>>()
+            .map_err(|err| Status::invalid_argument(format!("{:#}", err)))?;
+        let loaded = self.heap(request.heap_id)?;
+        let class_ids: Vec<Id> = loaded
+            .index
+            .heap
+            .find_classes_by_name(&request.class_name)
+            .iter()
+            .map(|c| c.id)
+            .collect();
+        if class_ids.is_empty() {
+            return Err(Status::not_found(format!(
+                "class {} not found",
+                request.class_name
+            )));
+        }
+        let found = blocking(move || {
+            let heap = &loaded.index.heap;
+            let contents = match loaded.contents.get() {
+                Some(contents) => contents,
+                None => {
+                    let contents = Contents::open(&loaded.dump, heap)?;
+                    loaded.contents.get_or_init(|| contents)
+                }
+            };
+            let found = find_instances(heap, contents, &class_ids, &predicates)?;
+            // same as inspect, sizes are only retained once something else computed the tree
+            let dominator_tree = heap.has_dominator_tree().then(|| heap.dominator_tree());
+            Ok(found
+                .into_iter()
+                .map(|id| FoundObject {
+                    object_id: id.0,
+                    class_name: heap.class_name_of(id).unwrap_or_default(),
+                    shallow_size: heap.instance(id).map_or(0, |i| i.shallow_size),
+                    retained_size: dominator_tree
+                        .and_then(|tree| tree.retained_size(id))
+                        .unwrap_or(0),
+                })
+                .collect::<Vec<_>>())
+        })
+        .await?;
+        Ok(Response::new(stream(found)))
+    }
+}
+
+pub async fn serve(addr: SocketAddr, service: AnalysisService) -> Result<()> {
+    info!("serving the analysis service on {}", addr);
+    Server::builder()
+        .add_service(AnalysisServer::new(service))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
+
+fn object_handle(heap: &AnalyzedHeap, id: Id) -> Result<Handle, Status> {
+    heap.handle(id)
+        .ok_or_else(|| Status::not_found(format!("object {} not found", id)))
+}
+
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T, Status> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(internal)?
+        .map_err(|err| Status::internal(format!("{:#}", err)))
+}
+
+fn stream<T: Send + 'static>(items: Vec<T>) -> ResponseStream<T> {
+    Box::pin(tokio_stream::iter(items.into_iter().map(Ok)))
+}
+
+fn internal(err: impl std::fmt::Display) -> Status {
+    Status::internal(err.to_string())
+}
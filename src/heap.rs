@@ -0,0 +1,242 @@
+use std::{
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+
+use crate::{
+    analyzer::{
+        AnalyzedHeap, Class, HistogramEntry, Thread,
+        contents::{Contents, NamedFields},
+        dominator::DominatorTree,
+        filter::ClassFilter,
+        handle::Handle,
+        index::HeapIndex,
+        options::AnalysisOptions,
+    },
+    error::Result,
+    parser::{Header, Id, sub_record::FieldValue},
+};
+
+// a dump opened for querying. analyses beyond the class histogram run on first use:
+//
+//   let heap = Heap::open("app.hprof")?;
+//   for entry in heap.histogram().iter().take(10) { ... }
+//   let object = heap.object(id).unwrap();
+//   println!("{} retains {:?}", object.class_name(), object.retained_size());
+//
+// the AnalyzedHeap underneath is reachable through Deref for everything else
+pub struct Heap {
+    path: PathBuf,
+    header: Header,
+    heap: AnalyzedHeap,
+    histogram: OnceLock<Vec<HistogramEntry>>,
+    contents: OnceLock<Contents>,
+}
+
+impl Heap {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with(path, &AnalysisOptions::default())
+    }
+
+    // with options computing some of the lazy facets up front, for embedders that know they
+    // will need them
+    pub fn open_with(path: impl AsRef<Path>, options: &AnalysisOptions) -> Result<Self> {
+        let path = path.as_ref();
+        let (header, heap) = AnalyzedHeap::analyze_file_with(path, options)?;
+        Ok(Self::new(path, header, heap))
+    }
+
+    // downloads an http(s) or s3 dump like "s3://bucket/dump.hprof" into the cache directory of
+    // the config with range requests, then analyzes it. the download and analysis run on a
+    // thread of their own, the future can be awaited on any runtime
+    #[cfg(all(feature = "remote", not(target_arch = "wasm32")))]
+    pub async fn open_url(location: &str, config: &crate::config::Config) -> anyhow::Result<Self> {
+        crate::remote::open_url(location, config).await
+    }
+
+    // a heap loaded from the sidecar index of the dump at path
+    pub fn from_index(path: impl AsRef<Path>, index: HeapIndex) -> Self {
+        Self::new(path.as_ref(), index.header, index.heap)
+    }
+
+    fn new(path: &Path, header: Header, heap: AnalyzedHeap) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            header,
+            heap,
+            histogram: OnceLock::new(),
+            contents: OnceLock::new(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn analyzed(&self) -> &AnalyzedHeap {
+        &self.heap
+    }
+
+    pub fn into_analyzed(self) -> AnalyzedHeap {
+        self.heap
+    }
+
+    // every class with instances, largest shallow size first
+    pub fn histogram(&self) -> &[HistogramEntry] {
+        self.histogram
+            .get_or_init(|| self.heap.histogram(&Default::default()))
+    }
+
+    // the classes of the histogram the filter matches
+    pub fn filtered_histogram(&self, filter: &ClassFilter) -> Vec<HistogramEntry> {
+        self.histogram()
+            .iter()
+            .filter(|e| filter.matches(&e.class.java_name()))
+            .cloned()
+            .collect()
+    }
+
+    pub fn dominators(&self) -> &DominatorTree {
+        self.heap.dominator_tree()
+    }
+
+    pub fn threads(&self) -> &[Thread] {
+        &self.heap.threads
+    }
+
+    // an instance, array or class object, None for ids not in the dump
+    pub fn object(&self, id: Id) -> Option<Object<'_>> {
+        let handle = self.heap.handle(id)?;
+        Some(Object {
+            heap: self,
+            id,
+            handle,
+        })
+    }
+
+    // field values and array elements, mapped from the dump on first use
+    fn contents(&self) -> Result<&Contents> {
+        if let Some(contents) = self.contents.get() {
+            return Ok(contents);
+        }
+        let contents = Contents::open(&self.path, &self.heap)?;
+        Ok(self.contents.get_or_init(|| contents))
+    }
+}
+
+// shareable behind an Arc like AnalyzedHeap, the lazy facets are computed once
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Heap>();
+    assert_send_sync::<HeapView>();
+};
+
+// a heap shared between the threads of a server. clones are cheap, whatever one of them
+// computed is there for all of them:
+//
+//   let view = HeapView::from(Heap::open("app.hprof")?);
+//   let worker = view.clone();
+//   std::thread::spawn(move || worker.histogram().len());
+#[derive(Clone)]
+pub struct HeapView(Arc<Heap>);
+
+impl From<Heap> for HeapView {
+    fn from(heap: Heap) -> Self {
+        Self(Arc::new(heap))
+    }
+}
+
+impl From<Arc<Heap>> for HeapView {
+    fn from(heap: Arc<Heap>) -> Self {
+        Self(heap)
+    }
+}
+
+impl Deref for HeapView {
+    type Target = Heap;
+
+    fn deref(&self) -> &Heap {
+        &self.0
+    }
+}
+
+impl Deref for Heap {
+    type Target = AnalyzedHeap;
+
+    fn deref(&self) -> &AnalyzedHeap {
+        &self.heap
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Object<'a> {
+    heap: &'a Heap,
+    id: Id,
+    handle: Handle,
+}
+
+impl<'a> Object<'a> {
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    pub fn heap(&self) -> &'a Heap {
+        self.heap
+    }
+
+    // None for class objects
+    pub fn class(&self) -> Option<&'a Class> {
+        self.heap.heap.class_of(self.id)
+    }
+
+    // class objects are named "class <name>"
+    pub fn class_name(&self) -> String {
+        self.heap.heap.class_name_of(self.id).unwrap_or_default()
+    }
+
+    // None for class objects
+    pub fn shallow_size(&self) -> Option<u64> {
+        self.heap.heap.instance(self.id).map(|i| i.shallow_size)
+    }
+
+    // None for unreachable objects
+    pub fn retained_size(&self) -> Option<u64> {
+        self.heap.dominators().retained_size(self.id)
+    }
+
+    pub fn references(&self) -> &'a [Id] {
+        self.heap.heap.references_of(self.id).unwrap_or_default()
+    }
+
+    // once per reference, a class object is referred to by every instance
+    pub fn referrers(&self) -> impl Iterator<Item = Id> + 'a {
+        let heap = &self.heap.heap;
+        heap.referrers(self.handle).map(|h| heap.handles.id(h))
+    }
+
+    // shortest path from a gc root, root first. None when no root reaches the object
+    pub fn path_to_root(&self) -> Option<Vec<Id>> {
+        let heap = &self.heap.heap;
+        let path = heap.path_to_root(self.handle)?;
+        Some(path.into_iter().map(|h| heap.handles.id(h)).collect())
+    }
+
+    // instance fields, see Contents::fields. None for arrays and class objects
+    pub fn fields(&self) -> Result<Option<NamedFields>> {
+        self.heap.contents()?.fields(&self.heap.heap, self.id)
+    }
+
+    pub fn field(&self, name: &str) -> Result<Option<FieldValue>> {
+        self.heap.contents()?.field(&self.heap.heap, self.id, name)
+    }
+
+    // contents of a java.lang.String, None for other objects
+    pub fn string_value(&self) -> Result<Option<String>> {
+        self.heap.contents()?.string_value(&self.heap.heap, self.id)
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+
+use crate::{analyzer::java_name, capture::java_tool};
+
+const EVENTS: &str = "jdk.ObjectAllocationSample,jdk.OldObjectSample";
+
+// frames of these packages are skipped when looking for the code responsible for an allocation
+const LIBRARY_PACKAGES: [&str; 5] = ["java/", "javax/", "jdk/", "sun/", "com/sun/"];
+
+// where the objects of each class were allocated according to a flight recording.
+// ObjectAllocationSample events tell where memory is allocated, OldObjectSample events where
+// the objects that stayed alive came from
+#[derive(Default)]
+pub struct Allocations {
+    // by java class name, most surviving objects and then most allocated bytes first
+    sites: HashMap<String, Vec<Site>>,
+}
+
+#[derive(Debug)]
+pub struct Site {
+    // like "com.example.Cache.put:42", the first frame outside of the jdk
+    pub frame: String,
+    // estimated from the sample weights
+    pub allocated_bytes: u64,
+    pub live_samples: u64,
+}
+
+#[derive(Deserialize)]
+struct Json {
+    recording: Recording,
+}
+
+#[derive(Deserialize)]
+struct Recording {
+    events: Vec<Event>,
+}
+
+#[derive(Deserialize)]
+struct Event {
+    #[serde(rename = "type")]
+    typ: String,
+    values: Values,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Values {
+    object_class: Option<JfrClass>,
+    weight: Option<u64>,
+    object: Option<OldObject>,
+    stack_trace: Option<StackTrace>,
+}
+
+#[derive(Deserialize)]
+struct OldObject {
+    #[serde(rename = "type")]
+    typ: Option<JfrClass>,
+}
+
+#[derive(Deserialize)]
+struct JfrClass {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct StackTrace {
+    frames: Vec<Frame>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Frame {
+    method: Method,
+    line_number: i64,
+}
+
+#[derive(Deserialize)]
+struct Method {
+    #[serde(rename = "type")]
+    typ: JfrClass,
+    name: String,
+}
+
+impl Allocations {
+    // a .jfr recording is converted with the jdk's jfr tool, a .json file is expected to be the
+    // output of `jfr print --json`
+    pub fn read(path: &Path) -> Result<Self> {
+        if path.extension().is_some_and(|e| e == "json") {
+            let file =
+                File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+            return Self::parse(BufReader::new(file));
+        }
+
+        let mut child = Command::new(java_tool("jfr"))
+            .args(["print", "--json", "--events", EVENTS])
+            .arg(path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("failed to run jfr, is a jdk on the PATH?")?;
+        let parsed = Self::parse(BufReader::new(
+            child.stdout.take().expect("stdout is piped"),
+        ));
+        if parsed.is_err() {
+            // jfr would block on the rest of its output
+            let _ = child.kill();
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            bail!(
+                "jfr failed to read {}: {}",
+                path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        parsed.with_context(|| format!("failed to read {}", path.display()))
+    }
+
+    pub fn parse(r: impl Read) -> Result<Self> {
+        let json: Json = serde_json::from_reader(r)?;
+        let mut sites: HashMap<String, HashMap<String, Site>> = HashMap::new();
+        for event in json.recording.events {
+            let values = event.values;
+            let (class, allocated_bytes, live_samples) = match event.typ.as_str() {
+                "jdk.ObjectAllocationSample" => match values.object_class {
+                    Some(class) => (class.name, values.weight.unwrap_or(0), 0),
+                    None => continue,
+                },
+                "jdk.OldObjectSample" => match values.object.and_then(|o| o.typ) {
+                    Some(class) => (class.name, 0, 1),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            let Some(frame) = values.stack_trace.as_ref().and_then(allocation_frame) else {
+                continue;
+            };
+
+            let site = sites
+                .entry(java_name(&class))
+                .or_default()
+                .entry(frame.clone())
+                .or_insert_with(|| Site {
+                    frame,
+                    allocated_bytes: 0,
+                    live_samples: 0,
+                });
+            site.allocated_bytes += allocated_bytes;
+            site.live_samples += live_samples;
+        }
+
+        let sites = sites
+            .into_iter()
+            .map(|(class, sites)| {
+                let mut sites: Vec<Site> = sites.into_values().collect();
+                sites.sort_by(|a, b| {
+                    b.live_samples
+                        .cmp(&a.live_samples)
+                        .then(b.allocated_bytes.cmp(&a.allocated_bytes))
+                        .then_with(|| a.frame.cmp(&b.frame))
+                });
+                (class, sites)
+            })
+            .collect();
+        Ok(Self { sites })
+    }
+
+    pub fn sites(&self, class: &str) -> &[Site] {
+        self.sites.get(class).map_or(&[], Vec::as_slice)
+    }
+
+    // the main allocation site with its share of the samples, like "Cache.put:42 (80%)"
+    pub fn hint(&self, class: &str) -> Option<String> {
+        let sites = self.sites(class);
+        let top = sites.first()?;
+        let (part, total) = if top.live_samples > 0 {
+            (
+                top.live_samples,
+                sites.iter().map(|s| s.live_samples).sum::<u64>(),
+            )
+        } else {
+            (
+                top.allocated_bytes,
+                sites.iter().map(|s| s.allocated_bytes).sum(),
+            )
+        };
+        if sites.len() == 1 || total == 0 {
+            return Some(top.frame.clone());
+        }
+        Some(format!(
+            "{} ({:.0}%)",
+            top.frame,
+            part as f64 * 100.0 / total as f64
+        ))
+    }
+}
+
+fn allocation_frame(stack_trace: &StackTrace) -> Option<String> {
+    let frame = stack_trace
+        .frames
+        .iter()
+        .find(|f| {
+            !LIBRARY_PACKAGES
+                .iter()
+                .any(|p| f.method.typ.name.starts_with(p))
+        })
+        .or(stack_trace.frames.first())?;
+    Some(format!(
+        "{}.{}:{}",
+        java_name(&frame.method.typ.name),
+        frame.method.name,
+        frame.line_number
+    ))
+}
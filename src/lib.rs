@@ -1,2 +1,49 @@
-pub mod analzyer;
+#[cfg(feature = "report")]
+pub mod analysis;
+pub mod analyzer;
+#[cfg(feature = "http")]
+pub mod api;
+#[cfg(all(feature = "remote", not(target_arch = "wasm32")))]
+pub mod archive;
+#[cfg(all(feature = "remote", not(target_arch = "wasm32")))]
+pub mod capture;
+#[cfg(feature = "report")]
+pub mod config;
+pub mod display;
+pub mod error;
+#[cfg(feature = "report")]
+pub mod export;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ffi;
+#[cfg(feature = "report")]
+pub mod gclog;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod heap;
+#[cfg(all(feature = "remote", not(target_arch = "wasm32")))]
+pub mod jfr;
+#[cfg(feature = "report")]
+pub mod mcp;
+#[cfg(all(feature = "node", not(target_arch = "wasm32")))]
+pub mod node;
+pub mod output;
 pub mod parser;
+pub mod prelude;
+#[cfg(all(feature = "remote", not(target_arch = "wasm32")))]
+pub mod remote;
+pub mod testutil;
+mod trace;
+pub mod transform;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+pub mod writer;
+
+pub use analyzer::AnalyzedHeap;
+pub use error::HeapError;
+pub use heap::Heap;
+pub use parser::{ParsedHeap, RecordReader};
+pub use trace::METRICS_TARGET;
+
+// the analyzer module used to be misspelled
+#[doc(hidden)]
+pub use analyzer as analzyer;
@@ -1,24 +1,39 @@
-use anyhow::{Context, Result};
-use heapdump_analyzer::{analzyer::AnalyzedHeap, parser::ParsedHeap};
-use std::path::PathBuf;
-use tracing::info;
-use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
+use std::process::ExitCode;
 
-fn main() -> Result<()> {
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(EnvFilter::from_default_env())
-        .init();
-
-    let path_arg = std::env::args()
-        .nth(1)
-        .context("no heapdump path provided")?;
-    let path = PathBuf::from(path_arg);
+use tracing_subscriber::{
+    EnvFilter, Layer,
+    filter::LevelFilter,
+    fmt::{self, format::FmtSpan},
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+};
 
-    let parsed_heap = ParsedHeap::parse(&path)?;
-    let analyzed_heap = AnalyzedHeap::analyze(&parsed_heap)?;
+mod cli;
 
-    info!("{}", analyzed_heap.instances.len());
+// exit codes: 0 on success, 1 when a check failed, 2 on errors
+fn main() -> ExitCode {
+    tracing_subscriber::registry()
+        .with(
+            fmt::layer()
+                // phase spans report their duration when they close, see RUST_LOG=debug
+                .with_span_events(FmtSpan::CLOSE)
+                .with_writer(std::io::stderr)
+                // warnings about skipped records and diagnostics show without RUST_LOG
+                .with_filter(
+                    EnvFilter::builder()
+                        .with_default_directive(LevelFilter::WARN.into())
+                        .from_env_lossy(),
+                ),
+        )
+        // for --time, regardless of RUST_LOG
+        .with(cli::time::layer())
+        .init();
 
-    Ok(())
+    match cli::run() {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            ExitCode::from(2)
+        }
+    }
 }
@@ -0,0 +1,486 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+
+use crate::{
+    analyzer::{AnalyzedHeap, Instance, filter::ClassFilter, handle::Handle, index::HeapIndex},
+    export::json::{HistogramRow, MAX_EDGES, Summary, Truncated},
+    parser::Id,
+    trace::{debug, warn},
+};
+
+// newest revision first, older clients get the newest one they asked for if it's listed
+const PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+// every list a tool returns is cut off, so a single call can't flood the model's context
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 1000;
+
+// model context protocol server over newline delimited json-rpc, as spoken on stdio. the tools
+// only read the heap, the dominator tree is computed by the first call needing it
+pub fn serve(index: &HeapIndex, input: impl BufRead, mut output: impl Write) -> Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => handle(index, request),
+            Err(err) => Some(error_response(
+                Value::Null,
+                PARSE_ERROR,
+                format!("invalid request: {}", err),
+            )),
+        };
+        if let Some(response) = response {
+            serde_json::to_writer(&mut output, &response)?;
+            writeln!(output)?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+#[derive(Deserialize)]
+struct Request {
+    // notifications come without id and get no response
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct ToolCall {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+fn handle(index: &HeapIndex, request: Request) -> Option<Value> {
+    debug!("{} request", request.method);
+    let id = request.id?;
+    let result = match request.method.as_str() {
+        "initialize" => initialize(&request.params),
+        "ping" => json!({}),
+        "tools/list" => json!({ "tools": tools() }),
+        "tools/call" => match serde_json::from_value::<ToolCall>(request.params) {
+            Ok(call) if TOOLS.iter().any(|t| t.name == call.name) => {
+                call_tool(index, &call.name, call.arguments)
+            }
+            Ok(call) => {
+                return Some(error_response(
+                    id,
+                    INVALID_PARAMS,
+                    format!("unknown tool {}", call.name),
+                ));
+            }
+            Err(err) => return Some(error_response(id, INVALID_PARAMS, err.to_string())),
+        },
+        method => {
+            return Some(error_response(
+                id,
+                METHOD_NOT_FOUND,
+                format!("unknown method {}", method),
+            ));
+        }
+    };
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn initialize(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = PROTOCOL_VERSIONS
+        .into_iter()
+        .find(|v| Some(*v) == requested)
+        .unwrap_or(PROTOCOL_VERSIONS[0]);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": { "name": "heapdump-analyzer", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+// failures of a tool are reported to the model as results, so it can correct the arguments
+fn call_tool(index: &HeapIndex, name: &str, arguments: Value) -> Value {
+    let heap = &index.heap;
+    let result = match name {
+        "summary" => summary(index),
+        "histogram" => args(arguments).and_then(|a| to_value(histogram(heap, a))),
+        "inspect" => args(arguments).and_then(|a| inspect(heap, a)),
+        "paths" => args(arguments).and_then(|a| paths(heap, a)),
+        "search" => args(arguments).and_then(|a| to_value(search(heap, a))),
+        "query" => args(arguments).and_then(|a| query(heap, a)),
+        _ => unreachable!("tool names are checked by the caller"),
+    };
+    match result {
+        Ok(value) => json!({
+            "content": [{ "type": "text", "text": value.to_string() }],
+            "structuredContent": value,
+            "isError": false,
+        }),
+        Err(err) => {
+            warn!("{} failed: {:#}", name, err);
+            json!({
+                "content": [{ "type": "text", "text": format!("{:#}", err) }],
+                "isError": true,
+            })
+        }
+    }
+}
+
+struct Tool {
+    name: &'static str,
+    description: &'static str,
+    properties: fn() -> Value,
+    required: &'static [&'static str],
+}
+
+const TOOLS: [Tool; 6] = [
+    Tool {
+        name: "summary",
+        description: "Dump version and timestamp, class and object counts, total and reachable size in bytes",
+        properties: || json!({}),
+        required: &[],
+    },
+    Tool {
+        name: "histogram",
+        description: "Instance count, shallow and retained size in bytes per class, largest shallow size first",
+        properties: || {
+            json!({
+                "include": { "type": "array", "items": { "type": "string" }, "description": "only classes starting with one of these prefixes, like java.util." },
+                "exclude": { "type": "array", "items": { "type": "string" }, "description": "hide classes starting with one of these prefixes" },
+                "limit": limit_schema(),
+            })
+        },
+        required: &[],
+    },
+    Tool {
+        name: "inspect",
+        description: "Class, shallow and retained size of an object, and the objects it references and is referenced by",
+        properties: || json!({ "id": id_schema() }),
+        required: &["id"],
+    },
+    Tool {
+        name: "paths",
+        description: "Shortest chain of references from a gc root to an object, root first. The path is null when the object is unreachable",
+        properties: || json!({ "id": id_schema() }),
+        required: &["id"],
+    },
+    Tool {
+        name: "search",
+        description: "Classes whose name contains the text, ignoring case, with their instance count and shallow size",
+        properties: || {
+            json!({
+                "text": { "type": "string", "description": "part of a class name like HashMap or com.example" },
+                "limit": limit_schema(),
+            })
+        },
+        required: &["text"],
+    },
+    Tool {
+        name: "query",
+        description: "Objects matching a class prefix and minimum sizes, largest first, with the number of matches",
+        properties: || {
+            json!({
+                "class": { "type": "string", "description": "class name prefix like java.lang.String, arrays are named like byte[]" },
+                "min_shallow_size": { "type": "integer", "minimum": 0, "description": "bytes" },
+                "min_retained_size": { "type": "integer", "minimum": 0, "description": "bytes" },
+                "order_by": { "type": "string", "enum": ["shallow_size", "retained_size"], "default": "retained_size" },
+                "limit": limit_schema(),
+            })
+        },
+        required: &[],
+    },
+];
+
+fn tools() -> Vec<Value> {
+    TOOLS
+        .iter()
+        .map(|tool| {
+            json!({
+                "name": tool.name,
+                "description": tool.description,
+                "inputSchema": {
+                    "type": "object",
+                    "properties": (tool.properties)(),
+                    "required": tool.required,
+                },
+            })
+        })
+        .collect()
+}
+
+fn limit_schema() -> Value {
+    json!({ "type": "integer", "minimum": 1, "maximum": MAX_LIMIT, "default": DEFAULT_LIMIT })
+}
+
+fn id_schema() -> Value {
+    json!({ "type": "string", "description": "object id in hex like 0x7fec0bd5c648" })
+}
+
+fn args<T: DeserializeOwned>(arguments: Value) -> Result<T> {
+    let arguments = if arguments.is_null() {
+        json!({})
+    } else {
+        arguments
+    };
+    serde_json::from_value(arguments).context("invalid arguments")
+}
+
+fn to_value(value: impl Serialize) -> Result<Value> {
+    Ok(serde_json::to_value(value)?)
+}
+
+fn limit(limit: Option<usize>) -> usize {
+    limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+}
+
+fn lookup(heap: &AnalyzedHeap, id: &str) -> Result<(Handle, Id)> {
+    let parsed: Id = id.parse()?;
+    let handle = heap
+        .handle(parsed)
+        .with_context(|| format!("object {} not found", id))?;
+    Ok((handle, parsed))
+}
+
+fn summary(index: &HeapIndex) -> Result<Value> {
+    let (header, heap) = (&index.header, &index.heap);
+    let dominator_tree = heap.dominator_tree();
+    to_value(Summary {
+        version: header.version.to_string(),
+        timestamp: header.timestamp.to_rfc3339(),
+        classes: heap.classes.len(),
+        objects: heap.instances.len(),
+        shallow_size: heap.total_shallow_size(),
+        reachable_objects: dominator_tree.reachable_count(),
+        reachable_size: dominator_tree.reachable_size(),
+    })
+}
+
+#[derive(Deserialize)]
+struct HistogramArgs {
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    limit: Option<usize>,
+}
+
+fn histogram(heap: &AnalyzedHeap, args: HistogramArgs) -> Truncated<HistogramRow> {
+    let filter = ClassFilter {
+        include: args.include,
+        exclude: args.exclude,
+    };
+    let entries = heap.histogram(&filter);
+    // computed by the first call, the heap keeps it for the following ones
+    let retained_by_class = heap.retained_by_class();
+    Truncated {
+        total: entries.len(),
+        items: entries
+            .into_iter()
+            .take(limit(args.limit))
+            .map(|e| HistogramRow {
+                class: e.class.java_name(),
+                instances: e.instance_count,
+                shallow_size: e.shallow_size,
+                retained_size: retained_by_class.get(&e.class.id).copied().unwrap_or(0),
+            })
+            .collect(),
+    }
+}
+
+#[derive(Deserialize)]
+struct ObjectArgs {
+    id: String,
+}
+
+#[derive(Serialize)]
+struct ObjectDetails {
+    id: String,
+    class: String,
+    shallow_size: u64,
+    retained_size: u64,
+    // the first MAX_EDGES of each
+    references: Truncated<String>,
+    referrers: Truncated<String>,
+}
+
+fn inspect(heap: &AnalyzedHeap, args: ObjectArgs) -> Result<Value> {
+    let (handle, id) = lookup(heap, &args.id)?;
+    let references = heap.references.get(handle);
+    let referrers: Vec<Handle> = heap.referrers(handle).collect();
+    to_value(ObjectDetails {
+        id: id.to_string(),
+        class: heap.class_name_of(id).unwrap_or_default(),
+        shallow_size: heap.instance(id).map_or(0, |i| i.shallow_size),
+        retained_size: heap.dominator_tree().retained_size(id).unwrap_or(0),
+        references: Truncated {
+            total: references.len(),
+            items: references
+                .iter()
+                .take(MAX_EDGES)
+                .map(|id| id.to_string())
+                .collect(),
+        },
+        referrers: Truncated {
+            total: referrers.len(),
+            items: referrers
+                .iter()
+                .take(MAX_EDGES)
+                .map(|h| heap.handles.id(*h).to_string())
+                .collect(),
+        },
+    })
+}
+
+#[derive(Serialize)]
+struct PathElement {
+    id: String,
+    class: String,
+}
+
+fn paths(heap: &AnalyzedHeap, args: ObjectArgs) -> Result<Value> {
+    let (handle, _) = lookup(heap, &args.id)?;
+    let path: Option<Vec<PathElement>> = heap.path_to_root(handle).map(|path| {
+        path.into_iter()
+            .map(|h| {
+                let id = heap.handles.id(h);
+                PathElement {
+                    id: id.to_string(),
+                    class: heap.class_name_of(id).unwrap_or_default(),
+                }
+            })
+            .collect()
+    });
+    Ok(json!({ "path": to_value(path)? }))
+}
+
+#[derive(Deserialize)]
+struct SearchArgs {
+    text: String,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct ClassMatch {
+    class: String,
+    instances: u64,
+    shallow_size: u64,
+}
+
+fn search(heap: &AnalyzedHeap, args: SearchArgs) -> Truncated<ClassMatch> {
+    let text = args.text.to_lowercase();
+    let counts: HashMap<Id, (u64, u64)> = heap
+        .histogram(&ClassFilter::default())
+        .into_iter()
+        .map(|e| (e.class.id, (e.instance_count, e.shallow_size)))
+        .collect();
+
+    let mut matches: Vec<ClassMatch> = heap
+        .classes
+        .values()
+        .map(|c| (c, c.java_name()))
+        .filter(|(_, name)| name.to_lowercase().contains(&text))
+        .map(|(c, class)| {
+            let (instances, shallow_size) = counts.get(&c.id).copied().unwrap_or_default();
+            ClassMatch {
+                class,
+                instances,
+                shallow_size,
+            }
+        })
+        .collect();
+    matches.sort_by(|a, b| {
+        b.shallow_size
+            .cmp(&a.shallow_size)
+            .then_with(|| a.class.cmp(&b.class))
+    });
+    let total = matches.len();
+    matches.truncate(limit(args.limit));
+    Truncated {
+        total,
+        items: matches,
+    }
+}
+
+#[derive(Deserialize)]
+struct QueryArgs {
+    class: Option<String>,
+    #[serde(default)]
+    min_shallow_size: u64,
+    #[serde(default)]
+    min_retained_size: u64,
+    #[serde(default)]
+    order_by: OrderBy,
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum OrderBy {
+    ShallowSize,
+    #[default]
+    RetainedSize,
+}
+
+#[derive(Serialize)]
+struct ObjectRow {
+    id: String,
+    class: String,
+    shallow_size: u64,
+    retained_size: u64,
+}
+
+fn query(heap: &AnalyzedHeap, args: QueryArgs) -> Result<Value> {
+    let classes: HashMap<Id, String> = heap
+        .classes
+        .values()
+        .map(|c| (c.id, c.java_name()))
+        .filter(|(_, name)| {
+            args.class
+                .as_deref()
+                .is_none_or(|prefix| name.starts_with(prefix))
+        })
+        .collect();
+    let dominator_tree = heap.dominator_tree();
+    let mut matches: Vec<(Instance, u64)> = heap
+        .iter_instances()
+        .filter(|i| i.shallow_size >= args.min_shallow_size && classes.contains_key(&i.class.id))
+        .map(|i| (i, dominator_tree.retained_size(i.id).unwrap_or(0)))
+        .filter(|(_, retained_size)| *retained_size >= args.min_retained_size)
+        .collect();
+    match args.order_by {
+        OrderBy::ShallowSize => matches.sort_by_key(|(i, _)| (Reverse(i.shallow_size), i.id)),
+        OrderBy::RetainedSize => {
+            matches.sort_by_key(|(i, retained_size)| (Reverse(*retained_size), i.id))
+        }
+    }
+
+    let total = matches.len();
+    let items = matches
+        .into_iter()
+        .take(limit(args.limit))
+        .map(|(i, retained_size)| ObjectRow {
+            id: i.id.to_string(),
+            class: classes[&i.class.id].clone(),
+            shallow_size: i.shallow_size,
+            retained_size,
+        })
+        .collect();
+    to_value(Truncated { total, items })
+}
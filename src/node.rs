@@ -0,0 +1,278 @@
+use std::{cmp::Reverse, collections::HashMap, io::Cursor, path::Path};
+
+use napi::{
+    Env, Error, Result, Task,
+    bindgen_prelude::{AsyncTask, Buffer},
+};
+use napi_derive::napi;
+
+use crate::{
+    analyzer::{
+        AnalyzedHeap, filter::ClassFilter, handle::Handle, index::HeapIndex, size::SizeModel,
+        storage::Storage,
+    },
+    parser::{Id, RecordReader},
+};
+
+// objects returned by query when no limit is given
+const DEFAULT_LIMIT: u32 = 100;
+
+// node.js module for tools that would otherwise run the cli and parse its output, built with
+// `--features node` and loaded from the cdylib renamed to .node. ids are passed as "0x..."
+// strings since they don't fit into a javascript number, sizes as numbers
+#[napi]
+pub struct Heap {
+    index: HeapIndex,
+}
+
+#[napi(object)]
+pub struct HeapSummary {
+    pub version: String,
+    pub timestamp: String,
+    pub classes: u32,
+    pub objects: i64,
+    pub shallow_size: i64,
+    pub reachable_objects: i64,
+    pub reachable_size: i64,
+}
+
+// lists of class name prefixes, as in the filters section of the config
+#[napi(object)]
+pub struct HistogramFilter {
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+}
+
+#[napi(object)]
+pub struct HistogramRow {
+    pub class: String,
+    pub instances: i64,
+    pub shallow_size: i64,
+    pub retained_size: i64,
+}
+
+#[napi(object)]
+pub struct ObjectInfo {
+    pub id: String,
+    pub class: String,
+    pub shallow_size: i64,
+    pub retained_size: i64,
+    pub references: Vec<String>,
+    pub referrers: Vec<String>,
+}
+
+#[napi(object)]
+pub struct PathElement {
+    pub id: String,
+    pub class: String,
+}
+
+#[napi(string_enum = "camelCase")]
+pub enum OrderBy {
+    ShallowSize,
+    RetainedSize,
+}
+
+// objects of classes starting with `class` and at least the given sizes, by retained size
+// unless ordered otherwise
+#[napi(object)]
+#[derive(Default)]
+pub struct Query {
+    pub class: Option<String>,
+    pub min_shallow_size: Option<i64>,
+    pub min_retained_size: Option<i64>,
+    pub order_by: Option<OrderBy>,
+    pub limit: Option<u32>,
+}
+
+#[napi(object)]
+pub struct ObjectRow {
+    pub id: String,
+    pub class: String,
+    pub shallow_size: i64,
+    pub retained_size: i64,
+}
+
+pub struct OpenTask {
+    path: String,
+}
+
+impl Task for OpenTask {
+    type Output = HeapIndex;
+    type JsValue = Heap;
+
+    fn compute(&mut self) -> Result<HeapIndex> {
+        let (header, heap) = AnalyzedHeap::analyze_file(
+            Path::new(&self.path),
+            SizeModel::default(),
+            &Storage::Memory,
+        )
+        .map_err(node_error)?;
+        Ok(HeapIndex { header, heap })
+    }
+
+    fn resolve(&mut self, _: Env, index: HeapIndex) -> Result<Heap> {
+        Ok(Heap { index })
+    }
+}
+
+#[napi]
+impl Heap {
+    // analyzes the dump on the libuv thread pool, so an electron main process stays responsive
+    #[napi]
+    pub fn open(path: String) -> AsyncTask<OpenTask> {
+        AsyncTask::new(OpenTask { path })
+    }
+
+    #[napi]
+    pub fn parse(bytes: Buffer) -> Result<Heap> {
+        let records = RecordReader::new(Cursor::new(bytes.as_ref())).map_err(node_error)?;
+        let header = records.header;
+        let heap = AnalyzedHeap::analyze_stream(records, SizeModel::default(), &Storage::Memory)
+            .map_err(node_error)?;
+        Ok(Heap {
+            index: HeapIndex { header, heap },
+        })
+    }
+
+    // the first call needing retained sizes computes the dominator tree
+    #[napi]
+    pub fn summary(&self) -> HeapSummary {
+        let (header, heap) = (&self.index.header, &self.index.heap);
+        let dominator_tree = heap.dominator_tree();
+        HeapSummary {
+            version: header.version.to_string(),
+            timestamp: header.timestamp.to_rfc3339(),
+            classes: heap.classes.len() as u32,
+            objects: heap.instances.len() as i64,
+            shallow_size: heap.total_shallow_size() as i64,
+            reachable_objects: dominator_tree.reachable_count() as i64,
+            reachable_size: dominator_tree.reachable_size() as i64,
+        }
+    }
+
+    // largest shallow size first
+    #[napi]
+    pub fn histogram(&self, filter: Option<HistogramFilter>) -> Vec<HistogramRow> {
+        let heap = &self.index.heap;
+        let filter = filter.map_or_else(ClassFilter::default, |f| ClassFilter {
+            include: f.include.unwrap_or_default(),
+            exclude: f.exclude.unwrap_or_default(),
+        });
+        let retained_by_class = heap.dominator_tree().retained_by_class(heap);
+        heap.histogram(&filter)
+            .into_iter()
+            .map(|e| HistogramRow {
+                class: e.class.java_name(),
+                instances: e.instance_count as i64,
+                shallow_size: e.shallow_size as i64,
+                retained_size: retained_by_class.get(&e.class.id).copied().unwrap_or(0) as i64,
+            })
+            .collect()
+    }
+
+    #[napi]
+    pub fn inspect(&self, id: String) -> Result<ObjectInfo> {
+        let heap = &self.index.heap;
+        let (handle, id) = self.lookup(&id)?;
+        Ok(ObjectInfo {
+            id: id.to_string(),
+            class: heap.class_name_of(id).unwrap_or_default(),
+            shallow_size: heap.instance(id).map_or(0, |i| i.shallow_size) as i64,
+            retained_size: heap.dominator_tree().retained_size(id).unwrap_or(0) as i64,
+            references: heap
+                .references_of(id)
+                .unwrap_or_default()
+                .iter()
+                .map(|id| id.to_string())
+                .collect(),
+            referrers: heap
+                .referrers(handle)
+                .map(|h| heap.handles.id(h).to_string())
+                .collect(),
+        })
+    }
+
+    // shortest path from a gc root to the object, root first. null when it is unreachable
+    #[napi]
+    pub fn paths(&self, id: String) -> Result<Option<Vec<PathElement>>> {
+        let heap = &self.index.heap;
+        let (handle, _) = self.lookup(&id)?;
+        Ok(heap.path_to_root(handle).map(|path| {
+            path.into_iter()
+                .map(|h| {
+                    let id = heap.handles.id(h);
+                    PathElement {
+                        id: id.to_string(),
+                        class: heap.class_name_of(id).unwrap_or_default(),
+                    }
+                })
+                .collect()
+        }))
+    }
+
+    #[napi]
+    pub fn query(&self, query: Option<Query>) -> Vec<ObjectRow> {
+        let heap = &self.index.heap;
+        let query = query.unwrap_or_default();
+        let classes: HashMap<Id, String> = heap
+            .classes
+            .values()
+            .map(|c| (c.id, c.java_name()))
+            .filter(|(_, name)| {
+                query
+                    .class
+                    .as_deref()
+                    .is_none_or(|prefix| name.starts_with(prefix))
+            })
+            .collect();
+        let min_shallow_size = query.min_shallow_size.unwrap_or(0).max(0) as u64;
+        let min_retained_size = query.min_retained_size.unwrap_or(0).max(0) as u64;
+
+        let dominator_tree = heap.dominator_tree();
+        let mut matches: Vec<(Id, u64, u64)> = heap
+            .iter_instances()
+            .filter(|i| i.shallow_size >= min_shallow_size && classes.contains_key(&i.class.id))
+            .map(|i| {
+                let retained_size = dominator_tree.retained_size(i.id).unwrap_or(0);
+                (i.id, i.shallow_size, retained_size)
+            })
+            .filter(|(_, _, retained_size)| *retained_size >= min_retained_size)
+            .collect();
+        match query.order_by.unwrap_or(OrderBy::RetainedSize) {
+            OrderBy::ShallowSize => {
+                matches.sort_by_key(|(id, shallow_size, _)| (Reverse(*shallow_size), *id))
+            }
+            OrderBy::RetainedSize => {
+                matches.sort_by_key(|(id, _, retained_size)| (Reverse(*retained_size), *id))
+            }
+        }
+
+        matches
+            .into_iter()
+            .take(query.limit.unwrap_or(DEFAULT_LIMIT) as usize)
+            .map(|(id, shallow_size, retained_size)| ObjectRow {
+                id: id.to_string(),
+                class: heap.class_name_of(id).unwrap_or_default(),
+                shallow_size: shallow_size as i64,
+                retained_size: retained_size as i64,
+            })
+            .collect()
+    }
+}
+
+impl Heap {
+    fn lookup(&self, id: &str) -> Result<(Handle, Id)> {
+        let parsed: Id = id.parse().map_err(node_error)?;
+        let handle = self
+            .index
+            .heap
+            .handle(parsed)
+            .ok_or_else(|| Error::from_reason(format!("object {} not found", id)))?;
+        Ok((handle, parsed))
+    }
+}
+
+fn node_error(err: impl Into<anyhow::Error>) -> Error {
+    Error::from_reason(format!("{:#}", err.into()))
+}
@@ -0,0 +1,260 @@
+use std::{io::IsTerminal, str::FromStr, time::Duration};
+
+use anyhow::bail;
+use serde::Deserialize;
+
+pub mod table;
+#[cfg(feature = "report")]
+pub mod template;
+
+// percentages at or above these fractions of the total get highlighted
+const LARGE_FRACTION: f64 = 0.10;
+const MEDIUM_FRACTION: f64 = 0.01;
+
+const LARGE_BYTES: u64 = 1024 * 1024 * 1024;
+const MEDIUM_BYTES: u64 = 100 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Red,
+    Yellow,
+    Green,
+    Dim,
+    Bold,
+}
+
+impl Color {
+    fn code(&self) -> &'static str {
+        match self {
+            Color::Red => "31",
+            Color::Yellow => "33",
+            Color::Green => "32",
+            Color::Dim => "2",
+            Color::Bold => "1",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => bail!("invalid color choice: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Tsv,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "table" => Ok(Self::Table),
+            "tsv" => Ok(Self::Tsv),
+            "csv" => Ok(Self::Csv),
+            _ => bail!("invalid output format: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    pub color: bool,
+    pub unicode: bool,
+    pub width: Option<usize>,
+}
+
+impl Style {
+    // colors, unicode bars and truncation are only used when stdout is a terminal
+    pub fn detect(color: ColorChoice) -> Self {
+        let stdout = std::io::stdout();
+        let mut style = if stdout.is_terminal() {
+            Self {
+                color: std::env::var_os("NO_COLOR").is_none(),
+                unicode: true,
+                width: terminal_width(),
+            }
+        } else {
+            Self::plain()
+        };
+
+        match color {
+            ColorChoice::Auto => {}
+            ColorChoice::Always => style.color = true,
+            ColorChoice::Never => style.color = false,
+        }
+
+        style
+    }
+
+    pub fn plain() -> Self {
+        Self {
+            color: false,
+            unicode: false,
+            width: None,
+        }
+    }
+
+    pub fn paint(&self, text: &str, color: Option<Color>) -> String {
+        match color {
+            Some(color) if self.color => format!("\x1b[{}m{}\x1b[0m", color.code(), text),
+            _ => text.to_string(),
+        }
+    }
+
+    pub fn bar(&self, fraction: f64, width: usize) -> String {
+        let fraction = fraction.clamp(0.0, 1.0);
+
+        if !self.unicode {
+            let filled = (fraction * width as f64).round() as usize;
+            return format!("{}{}", "#".repeat(filled), " ".repeat(width - filled));
+        }
+
+        const PARTIALS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+        let eighths = (fraction * width as f64 * 8.0).round() as usize;
+        let full = eighths / 8;
+        let mut bar = "█".repeat(full);
+        if full < width {
+            bar.push(PARTIALS[eighths % 8]);
+            bar.push_str(&" ".repeat(width - full - 1));
+        }
+        bar
+    }
+}
+
+#[cfg(all(feature = "cli", not(target_arch = "wasm32")))]
+fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size().map(|(w, _)| w.0 as usize)
+}
+
+// browsers and embedders have no terminal to fit
+#[cfg(not(all(feature = "cli", not(target_arch = "wasm32"))))]
+fn terminal_width() -> Option<usize> {
+    None
+}
+
+pub fn fraction_color(fraction: f64) -> Option<Color> {
+    if fraction >= LARGE_FRACTION {
+        Some(Color::Red)
+    } else if fraction >= MEDIUM_FRACTION {
+        Some(Color::Yellow)
+    } else {
+        None
+    }
+}
+
+pub fn bytes_color(bytes: u64) -> Option<Color> {
+    if bytes >= LARGE_BYTES {
+        Some(Color::Red)
+    } else if bytes >= MEDIUM_BYTES {
+        Some(Color::Yellow)
+    } else {
+        None
+    }
+}
+
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+// parses sizes like "512MB", "4g" or "1.5GiB". units are binary, like the jvm's -Xmx
+pub fn parse_bytes(s: &str) -> anyhow::Result<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid size: {}", s))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        _ => bail!("invalid size unit: {}", unit),
+    };
+
+    Ok((number * multiplier as f64).round() as u64)
+}
+
+pub fn human_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// like "350ms", "12.5s", "4m10s" or "1h02m"
+pub fn human_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    let secs = duration.as_secs();
+    if millis < 1000 {
+        format!("{}ms", millis)
+    } else if secs < 60 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else if secs < 3600 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h{:02}m", secs / 3600, secs % 3600 / 60)
+    }
+}
+
+pub fn fraction(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
+
+// rfc 4180 quoting, fields containing separators, quotes or line breaks are wrapped in quotes
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,299 @@
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::output::{
+    Color, OutputFormat, Style, bytes_color, csv_field, fraction, fraction_color, human_bytes,
+    human_count,
+};
+
+const BAR_WIDTH: usize = 12;
+const COLUMN_GAP: usize = 2;
+const MIN_FLEXIBLE_WIDTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Align {
+    Left,
+    Right,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Column {
+    pub header: String,
+    pub align: Align,
+    // the flexible column is truncated when the table doesn't fit the terminal
+    pub flexible: bool,
+}
+
+impl Column {
+    pub fn left(header: &str) -> Self {
+        Self {
+            header: header.to_string(),
+            align: Align::Left,
+            flexible: false,
+        }
+    }
+
+    pub fn right(header: &str) -> Self {
+        Self {
+            header: header.to_string(),
+            align: Align::Right,
+            flexible: false,
+        }
+    }
+
+    pub fn flexible(header: &str) -> Self {
+        Self {
+            header: header.to_string(),
+            align: Align::Left,
+            flexible: true,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Cell {
+    Text(String),
+    Count(u64),
+    Bytes(u64),
+    Percent { part: u64, total: u64 },
+    // changes between two dumps, shown with their sign
+    CountDelta(i64),
+    BytesDelta(i64),
+}
+
+impl Cell {
+    // unformatted value for machine readable output
+    fn raw(&self) -> String {
+        match self {
+            Cell::Text(text) => text.clone(),
+            Cell::Count(count) => count.to_string(),
+            Cell::Bytes(bytes) => bytes.to_string(),
+            Cell::Percent { part, total } => format!("{:.4}", fraction(*part, *total) * 100.0),
+            Cell::CountDelta(delta) | Cell::BytesDelta(delta) => delta.to_string(),
+        }
+    }
+
+    // human readable, without colors or bars
+    pub fn human(&self) -> String {
+        match self {
+            Cell::Percent { part, total } => format!("{:.1}%", fraction(*part, *total) * 100.0),
+            cell => cell.render(&Style::plain()).0,
+        }
+    }
+
+    fn render(&self, style: &Style) -> (String, Option<Color>) {
+        match self {
+            Cell::Text(text) => (text.clone(), None),
+            Cell::Count(count) => (human_count(*count), None),
+            Cell::Bytes(bytes) => (human_bytes(*bytes), bytes_color(*bytes)),
+            Cell::Percent { part, total } => {
+                let fraction = fraction(*part, *total);
+                (
+                    format!(
+                        "{:>5.1}% {}",
+                        fraction * 100.0,
+                        style.bar(fraction, BAR_WIDTH)
+                    ),
+                    fraction_color(fraction),
+                )
+            }
+            Cell::CountDelta(delta) => (
+                format!("{}{}", sign(*delta), human_count(delta.unsigned_abs())),
+                None,
+            ),
+            // growth is highlighted like sizes, shrinking is good news
+            Cell::BytesDelta(delta) => (
+                format!("{}{}", sign(*delta), human_bytes(delta.unsigned_abs())),
+                if *delta < 0 {
+                    Some(Color::Green)
+                } else {
+                    bytes_color(delta.unsigned_abs())
+                },
+            ),
+        }
+    }
+}
+
+fn sign(delta: i64) -> &'static str {
+    match delta {
+        0 => "",
+        ..0 => "-",
+        _ => "+",
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    pub fn new(columns: Vec<Column>) -> Self {
+        Self {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn add_row(&mut self, row: Vec<Cell>) {
+        assert_eq!(row.len(), self.columns.len(), "row width mismatch");
+        self.rows.push(row);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    pub fn rows(&self) -> &[Vec<Cell>] {
+        &self.rows
+    }
+
+    pub fn render(&self, w: &mut impl Write, style: &Style) -> Result<()> {
+        let rows: Vec<Vec<(String, Option<Color>)>> = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.render(style)).collect())
+            .collect();
+
+        let mut widths: Vec<usize> = self.columns.iter().map(|c| text_width(&c.header)).collect();
+        for row in &rows {
+            for (i, (text, _)) in row.iter().enumerate() {
+                widths[i] = widths[i].max(text_width(text));
+            }
+        }
+
+        if let Some(max_width) = style.width {
+            self.fit(&mut widths, max_width);
+        }
+
+        let header: Vec<(String, Option<Color>)> = self
+            .columns
+            .iter()
+            .map(|c| (c.header.clone(), Some(Color::Bold)))
+            .collect();
+        self.write_row(w, style, &widths, &header)?;
+
+        let total_width =
+            widths.iter().sum::<usize>() + COLUMN_GAP * widths.len().saturating_sub(1);
+        let rule = if style.unicode { "─" } else { "-" };
+        writeln!(
+            w,
+            "{}",
+            style.paint(&rule.repeat(total_width), Some(Color::Dim))
+        )?;
+
+        for row in &rows {
+            self.write_row(w, style, &widths, row)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn render_tsv(&self, w: &mut impl Write) -> Result<()> {
+        let header: Vec<&str> = self.columns.iter().map(|c| c.header.as_str()).collect();
+        writeln!(w, "{}", header.join("\t"))?;
+
+        for row in &self.rows {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|cell| cell.raw().replace(['\t', '\n'], " "))
+                .collect();
+            writeln!(w, "{}", cells.join("\t"))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn render_csv(&self, w: &mut impl Write) -> Result<()> {
+        let header: Vec<String> = self.columns.iter().map(|c| csv_field(&c.header)).collect();
+        writeln!(w, "{}", header.join(","))?;
+
+        for row in &self.rows {
+            let cells: Vec<String> = row.iter().map(|cell| csv_field(&cell.raw())).collect();
+            writeln!(w, "{}", cells.join(","))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write(&self, w: &mut impl Write, style: &Style, format: OutputFormat) -> Result<()> {
+        match format {
+            OutputFormat::Table => self.render(w, style),
+            OutputFormat::Tsv => self.render_tsv(w),
+            OutputFormat::Csv => self.render_csv(w),
+        }
+    }
+
+    fn fit(&self, widths: &mut [usize], max_width: usize) {
+        let total = widths.iter().sum::<usize>() + COLUMN_GAP * widths.len().saturating_sub(1);
+        if total <= max_width {
+            return;
+        }
+
+        if let Some(i) = self.columns.iter().position(|c| c.flexible) {
+            let excess = total - max_width;
+            let min = MIN_FLEXIBLE_WIDTH.min(widths[i]);
+            widths[i] = widths[i].saturating_sub(excess).max(min);
+        }
+    }
+
+    fn write_row(
+        &self,
+        w: &mut impl Write,
+        style: &Style,
+        widths: &[usize],
+        cells: &[(String, Option<Color>)],
+    ) -> Result<()> {
+        let mut line = String::new();
+        for (i, (text, color)) in cells.iter().enumerate() {
+            let width = widths[i];
+            let text = truncate(text, width, style.unicode);
+            let padding = " ".repeat(width - text_width(&text));
+            let painted = style.paint(&text, *color);
+
+            match self.columns[i].align {
+                Align::Left => {
+                    line.push_str(&painted);
+                    // no trailing whitespace after the last column
+                    if i < cells.len() - 1 {
+                        line.push_str(&padding);
+                    }
+                }
+                Align::Right => {
+                    line.push_str(&padding);
+                    line.push_str(&painted);
+                }
+            }
+
+            if i < cells.len() - 1 {
+                line.push_str(&" ".repeat(COLUMN_GAP));
+            }
+        }
+
+        writeln!(w, "{}", line.trim_end())?;
+        Ok(())
+    }
+}
+
+fn text_width(text: &str) -> usize {
+    text.chars().count()
+}
+
+fn truncate(text: &str, width: usize, unicode: bool) -> String {
+    if text_width(text) <= width {
+        return text.to_string();
+    }
+
+    let ellipsis = if unicode { "…" } else { "..." };
+    let keep = width.saturating_sub(text_width(ellipsis));
+    let mut out: String = text.chars().take(keep).collect();
+    out.push_str(ellipsis);
+    out
+}
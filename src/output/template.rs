@@ -0,0 +1,133 @@
+use std::{path::Path, str::FromStr};
+
+use anyhow::{Context, Result, bail};
+use minijinja::{Environment, path_loader};
+use serde::Serialize;
+
+use crate::output::table::{Align, Table};
+
+// templates shipped with the crate, a file of the same name in the template directory replaces
+// them. the directory can hold further templates they include or extend
+const BUILTIN: [(&str, &str); 2] = [
+    ("report.html", include_str!("templates/report.html")),
+    ("report.md", include_str!("templates/report.md")),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Document {
+    Html,
+    Markdown,
+}
+
+impl Document {
+    fn template(&self) -> &'static str {
+        match self {
+            Document::Html => "report.html",
+            Document::Markdown => "report.md",
+        }
+    }
+}
+
+impl FromStr for Document {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "html" => Ok(Self::Html),
+            "markdown" | "md" => Ok(Self::Markdown),
+            _ => bail!("invalid document type: {}", s),
+        }
+    }
+}
+
+// what templates get to render, as `dump`, `summary` and `sections`
+#[derive(Debug, Serialize)]
+pub struct ReportDocument {
+    pub dump: String,
+    // (label, human readable value)
+    pub summary: Vec<(String, String)>,
+    pub sections: Vec<Section>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Section {
+    pub title: String,
+    pub columns: Vec<SectionColumn>,
+    // cells in their human readable form, like the table output
+    pub rows: Vec<Vec<String>>,
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SectionColumn {
+    pub header: String,
+    // "left" or "right"
+    pub align: &'static str,
+}
+
+impl Section {
+    pub fn new(title: &str, table: &Table, notes: &[String]) -> Self {
+        Self {
+            title: title.to_string(),
+            columns: table
+                .columns()
+                .iter()
+                .map(|c| SectionColumn {
+                    header: c.header.clone(),
+                    align: match c.align {
+                        Align::Left => "left",
+                        Align::Right => "right",
+                    },
+                })
+                .collect(),
+            rows: table
+                .rows()
+                .iter()
+                .map(|row| row.iter().map(|cell| cell.human()).collect())
+                .collect(),
+            notes: notes.to_vec(),
+        }
+    }
+}
+
+pub struct Templates {
+    env: Environment<'static>,
+}
+
+impl Templates {
+    pub fn new(dir: Option<&Path>) -> Result<Self> {
+        let mut env = Environment::new();
+        // block tags on their own line don't leave blank lines behind
+        env.set_trim_blocks(true);
+        env.set_lstrip_blocks(true);
+        env.add_filter("md", escape_markdown);
+        for (name, source) in BUILTIN {
+            let custom = dir.map(|dir| dir.join(name)).filter(|path| path.exists());
+            match custom {
+                Some(path) => {
+                    let source = std::fs::read_to_string(&path)
+                        .with_context(|| format!("failed to read template {}", path.display()))?;
+                    env.add_template_owned(name, source)
+                        .with_context(|| format!("invalid template {}", path.display()))?;
+                }
+                None => env.add_template(name, source)?,
+            }
+        }
+        if let Some(dir) = dir {
+            env.set_loader(path_loader(dir));
+        }
+        Ok(Self { env })
+    }
+
+    pub fn render(&self, document: Document, report: &ReportDocument) -> Result<String> {
+        let template = self.env.get_template(document.template())?;
+        template
+            .render(report)
+            .with_context(|| format!("failed to render {}", document.template()))
+    }
+}
+
+// table cells can't contain pipes or line breaks
+fn escape_markdown(value: String) -> String {
+    value.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
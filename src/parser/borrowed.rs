@@ -0,0 +1,389 @@
+use std::{borrow::Cow, fs::File, path::Path};
+
+use memmap2::Mmap;
+
+use crate::{
+    error::{HeapError, Policy, Result},
+    parser::{
+        ClassId, Header, Id, Record, StringId,
+        sub_record::{PrimArray, SubRecord},
+        util::{ReadCtx, read_u8, read_u32},
+    },
+    trace::warn,
+};
+
+// a dump mapped into memory, parsed into records borrowing from the mapping. meant for single
+// pass tools, nothing but class dumps and the small records allocates
+pub struct MappedDump {
+    map: Mmap,
+}
+
+impl MappedDump {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|source| HeapError::Open {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        // dumps aren't expected to change while they are analyzed
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(Self { map })
+    }
+
+    pub fn records(&self) -> Result<BorrowedRecords<'_>> {
+        BorrowedRecords::new(&self.map)
+    }
+
+    // the whole file, slices of the borrowed records point into it
+    pub fn bytes(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+pub struct BorrowedRecords<'a> {
+    pub header: Header,
+    pub ctx: ReadCtx,
+    rest: &'a [u8],
+    // of the whole dump, for error offsets
+    len: usize,
+    done: bool,
+}
+
+impl<'a> BorrowedRecords<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        let mut rest = bytes;
+        let (header, ctx) = Header::parse(&mut rest)?;
+        Ok(Self {
+            header,
+            ctx,
+            rest,
+            len: bytes.len(),
+            done: false,
+        })
+    }
+
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.ctx.policy = policy;
+        self
+    }
+
+    fn offset(&self) -> u64 {
+        (self.len - self.rest.len()) as u64
+    }
+
+    // None for records skipped for their unknown tag
+    fn parse(&mut self) -> Result<Option<BorrowedRecord<'a>>> {
+        let offset = self.offset();
+        let ctx = self.ctx;
+        let r = &mut self.rest;
+        let tag = read_u8(r)?;
+        let micros = read_u32(r)?;
+        let length = read_u32(r)? as usize;
+        let body = take(r, length)?;
+
+        match tag {
+            0x01 => {
+                let mut body = body;
+                Ok(Some(BorrowedRecord::Utf8 {
+                    micros,
+                    name_id: ctx.read_id(&mut body)?.into(),
+                    content: java_utf8(body)?,
+                }))
+            }
+            0x1c => Ok(Some(BorrowedRecord::HeapDumpSegment {
+                micros,
+                sub_records: BorrowedSubRecords {
+                    ctx,
+                    rest: body,
+                    end: offset + 9 + length as u64,
+                },
+            })),
+            0x2c => Ok(Some(BorrowedRecord::Other(Record::HeapDumpEnd { micros }))),
+            _ => {
+                let mut body = body;
+                let record = match tag {
+                    0x02 => Record::load_class(&mut body, ctx, micros)?,
+                    0x04 => Record::frame(&mut body, ctx, micros)?,
+                    0x05 => Record::trace(&mut body, ctx, micros)?,
+                    _ if !ctx.policy.is_strict() => {
+                        warn!(
+                            "skipping record with unknown tag 0x{:x} at offset {}",
+                            tag, offset
+                        );
+                        return Ok(None);
+                    }
+                    _ => return Err(HeapError::UnknownTag { tag, offset }),
+                };
+                Ok(Some(BorrowedRecord::Other(record)))
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for BorrowedRecords<'a> {
+    type Item = Result<BorrowedRecord<'a>>;
+
+    // ends after the HeapDumpEnd record
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let record = loop {
+            let offset = self.offset();
+            match self.parse().map_err(|e| e.truncated_at(offset)) {
+                Ok(None) => continue,
+                Ok(Some(record)) => break Ok(record),
+                Err(err) => break Err(err),
+            }
+        };
+        self.done = matches!(
+            record,
+            Err(_) | Ok(BorrowedRecord::Other(Record::HeapDumpEnd { .. }))
+        );
+        Some(record)
+    }
+}
+
+pub enum BorrowedRecord<'a> {
+    Utf8 {
+        micros: u32,
+        name_id: StringId,
+        // only owned when java's encoding of nul had to be fixed
+        content: Cow<'a, str>,
+    },
+    // sub records are parsed while iterating
+    HeapDumpSegment {
+        micros: u32,
+        sub_records: BorrowedSubRecords<'a>,
+    },
+    // load class, frame, trace and heap dump end records are small, they are parsed as usual
+    Other(Record),
+}
+
+pub struct BorrowedSubRecords<'a> {
+    ctx: ReadCtx,
+    rest: &'a [u8],
+    // offset of the end of the segment in the dump
+    end: u64,
+}
+
+impl<'a> BorrowedSubRecords<'a> {
+    // bytes of the segment not parsed yet
+    pub fn remaining(&self) -> usize {
+        self.rest.len()
+    }
+
+    fn parse(&mut self) -> Result<BorrowedSubRecord<'a>> {
+        let ctx = self.ctx;
+        let id_size = ctx.id_size as usize;
+        let r = &mut self.rest;
+        let typ = r[0];
+        match typ {
+            0x21 => {
+                *r = &r[1..];
+                let object_id = ctx.read_id(r)?.into();
+                let stack_trace_serial_number = read_u32(r)?;
+                let class_object_id = ctx.read_id(r)?.into();
+                let number_of_bytes = read_u32(r)? as usize;
+                Ok(BorrowedSubRecord::InstanceDump {
+                    object_id,
+                    stack_trace_serial_number,
+                    class_object_id,
+                    raw_field_bytes: take(r, number_of_bytes)?,
+                })
+            }
+            0x22 => {
+                *r = &r[1..];
+                let object_id = ctx.read_id(r)?.into();
+                let stack_trace_serial_number = read_u32(r)?;
+                let number_of_elements = read_u32(r)? as usize;
+                let array_class_id = ctx.read_id(r)?.into();
+                Ok(BorrowedSubRecord::ObjArrayDump {
+                    object_id,
+                    stack_trace_serial_number,
+                    array_class_id,
+                    elements: Ids {
+                        bytes: take(r, number_of_elements.saturating_mul(id_size))?,
+                        id_size,
+                    },
+                })
+            }
+            0x23 => {
+                *r = &r[1..];
+                let object_id = ctx.read_id(r)?.into();
+                let stack_trace_serial_number = read_u32(r)?;
+                let number_of_elements = read_u32(r)? as usize;
+                let typ = read_u8(r)?;
+                let element_size = PrimArray::element_size(typ)?;
+                Ok(BorrowedSubRecord::PrimArrayDump {
+                    object_id,
+                    stack_trace_serial_number,
+                    typ,
+                    elements: take(r, number_of_elements.saturating_mul(element_size))?,
+                })
+            }
+            _ => Ok(BorrowedSubRecord::Other(SubRecord::new(r, ctx)?)),
+        }
+    }
+}
+
+impl<'a> Iterator for BorrowedSubRecords<'a> {
+    type Item = Result<BorrowedSubRecord<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+
+        let offset = self.end - self.rest.len() as u64;
+        let sub_record = self.parse().map_err(|e| e.truncated_at(offset));
+        if sub_record.is_err() {
+            self.rest = &[];
+        }
+        // like the owned parser, the rest of the segment is skipped unless the policy is strict
+        if let Err(HeapError::UnknownSubRecord { typ }) = sub_record
+            && !self.ctx.policy.is_strict()
+        {
+            warn!(
+                "skipping the rest of the segment after unknown sub record type 0x{:x} at offset \
+                 {}",
+                typ, offset
+            );
+            return None;
+        }
+        Some(sub_record)
+    }
+}
+
+pub enum BorrowedSubRecord<'a> {
+    InstanceDump {
+        object_id: Id,
+        stack_trace_serial_number: u32,
+        class_object_id: ClassId,
+        raw_field_bytes: &'a [u8],
+    },
+    ObjArrayDump {
+        object_id: Id,
+        stack_trace_serial_number: u32,
+        array_class_id: ClassId,
+        elements: Ids<'a>,
+    },
+    PrimArrayDump {
+        object_id: Id,
+        stack_trace_serial_number: u32,
+        typ: u8,
+        // big endian, as in the dump
+        elements: &'a [u8],
+    },
+    // class dumps and gc roots, parsed as usual
+    Other(SubRecord),
+}
+
+impl BorrowedSubRecord<'_> {
+    // copies what the sub record borrows, for the few that are kept around
+    pub fn into_owned(self) -> Result<SubRecord> {
+        Ok(match self {
+            BorrowedSubRecord::InstanceDump {
+                object_id,
+                stack_trace_serial_number,
+                class_object_id,
+                raw_field_bytes,
+            } => SubRecord::InstanceDump {
+                object_id,
+                stack_trace_serial_number,
+                class_object_id,
+                number_of_bytes: raw_field_bytes.len() as u32,
+                raw_field_bytes: raw_field_bytes.to_vec(),
+            },
+            BorrowedSubRecord::ObjArrayDump {
+                object_id,
+                stack_trace_serial_number,
+                array_class_id,
+                elements,
+            } => SubRecord::ObjArrayDump {
+                object_id,
+                stack_trace_serial_number,
+                array_class_id,
+                elements: elements.iter().collect(),
+            },
+            BorrowedSubRecord::PrimArrayDump {
+                object_id,
+                stack_trace_serial_number,
+                typ,
+                mut elements,
+            } => {
+                let len = elements.len() / PrimArray::element_size(typ)?;
+                SubRecord::PrimArrayDump {
+                    object_id,
+                    stack_trace_serial_number,
+                    typ,
+                    elements: PrimArray::read(&mut elements, typ, len)?,
+                }
+            }
+            BorrowedSubRecord::Other(sub_record) => sub_record,
+        })
+    }
+}
+
+// big endian ids, decoded while iterating
+#[derive(Clone, Copy)]
+pub struct Ids<'a> {
+    bytes: &'a [u8],
+    id_size: usize,
+}
+
+impl<'a> Ids<'a> {
+    pub fn len(&self) -> usize {
+        self.bytes.len() / self.id_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    // the ids as stored in the dump
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Id> + 'a {
+        let id_size = self.id_size;
+        self.bytes
+            .chunks_exact(id_size)
+            .map(move |c| match id_size {
+                4 => Id(u32::from_be_bytes(c.try_into().unwrap()) as u64),
+                _ => Id(u64::from_be_bytes(c.try_into().unwrap())),
+            })
+    }
+}
+
+fn take<'a>(r: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    if r.len() < n {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    let (taken, rest) = r.split_at(n);
+    *r = rest;
+    Ok(taken)
+}
+
+// java writes nul as 0xc0 0x80, which isn't valid utf8
+fn java_utf8(bytes: &[u8]) -> Result<Cow<'_, str>> {
+    if !bytes.windows(2).any(|w| w == [0xc0, 0x80]) {
+        return Ok(Cow::Borrowed(std::str::from_utf8(bytes)?));
+    }
+
+    let mut fixed = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == 0xc0 && bytes.get(i + 1) == Some(&0x80) {
+            fixed.push(0);
+            i += 2;
+        } else {
+            fixed.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(Cow::Owned(
+        String::from_utf8(fixed).map_err(|e| e.utf8_error())?,
+    ))
+}
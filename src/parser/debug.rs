@@ -0,0 +1,332 @@
+use std::fmt::{self, Debug, Formatter};
+
+use crate::parser::{
+    ParsedHeap, Record,
+    sub_record::{PrimArray, SubRecord},
+};
+
+// elements Debug shows of arrays, field bytes, frames and record lists before "... (len=n)". a
+// dump has arrays with hundreds of millions of elements, logging one shouldn't print them all
+pub const DEBUG_PREVIEW: usize = 16;
+
+// Debug with a limit on the elements of each list, nested lists included
+pub trait LimitedDebug {
+    fn fmt_limited(&self, f: &mut Formatter<'_>, limit: usize) -> fmt::Result;
+}
+
+// Debug with every element, for when all of them are wanted:
+//
+//   println!("{:?}", Full(&sub_record));
+pub struct Full<'a, T: ?Sized>(pub &'a T);
+
+impl<T: LimitedDebug + ?Sized> Debug for Full<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.fmt_limited(f, usize::MAX)
+    }
+}
+
+struct Limited<'a, T: ?Sized> {
+    value: &'a T,
+    limit: usize,
+}
+
+impl<T: LimitedDebug + ?Sized> Debug for Limited<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.value.fmt_limited(f, self.limit)
+    }
+}
+
+// the first limit items of a list, each formatted by entry
+struct Elements<'a, T, D> {
+    items: &'a [T],
+    limit: usize,
+    entry: fn(&'a T, usize) -> D,
+}
+
+impl<'a, T, D: Debug> Debug for Elements<'a, T, D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut list = f.debug_list();
+        for item in self.items.iter().take(self.limit) {
+            list.entry(&(self.entry)(item, self.limit));
+        }
+        if self.items.len() > self.limit {
+            list.entry(&More(self.items.len()));
+        }
+        list.finish()
+    }
+}
+
+struct More(usize);
+
+impl Debug for More {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "... (len={})", self.0)
+    }
+}
+
+fn elements<T: Debug>(items: &[T], limit: usize) -> Elements<'_, T, &T> {
+    Elements {
+        items,
+        limit,
+        entry: |item, _| item,
+    }
+}
+
+fn nested<T: LimitedDebug>(items: &[T], limit: usize) -> Elements<'_, T, Limited<'_, T>> {
+    Elements {
+        items,
+        limit,
+        entry: |value, limit| Limited { value, limit },
+    }
+}
+
+impl Debug for PrimArray {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.fmt_limited(f, DEBUG_PREVIEW)
+    }
+}
+
+impl LimitedDebug for PrimArray {
+    fn fmt_limited(&self, f: &mut Formatter<'_>, limit: usize) -> fmt::Result {
+        match self {
+            PrimArray::Bool(v) => f.debug_tuple("Bool").field(&elements(v, limit)).finish(),
+            PrimArray::Char(v) => f.debug_tuple("Char").field(&elements(v, limit)).finish(),
+            PrimArray::Float(v) => f.debug_tuple("Float").field(&elements(v, limit)).finish(),
+            PrimArray::Double(v) => f.debug_tuple("Double").field(&elements(v, limit)).finish(),
+            PrimArray::Byte(v) => f.debug_tuple("Byte").field(&elements(v, limit)).finish(),
+            PrimArray::Short(v) => f.debug_tuple("Short").field(&elements(v, limit)).finish(),
+            PrimArray::Int(v) => f.debug_tuple("Int").field(&elements(v, limit)).finish(),
+            PrimArray::Long(v) => f.debug_tuple("Long").field(&elements(v, limit)).finish(),
+        }
+    }
+}
+
+impl Debug for SubRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.fmt_limited(f, DEBUG_PREVIEW)
+    }
+}
+
+impl LimitedDebug for SubRecord {
+    fn fmt_limited(&self, f: &mut Formatter<'_>, limit: usize) -> fmt::Result {
+        match self {
+            SubRecord::ClassDump {
+                class_object_id,
+                stack_trace_serial_number,
+                super_class_object_id,
+                class_loader_object_id,
+                signers_object_id,
+                protection_domain_object_id,
+                reserved1,
+                reserved2,
+                instance_size,
+                constant_pool_size,
+                number_of_static_fields,
+                static_fields,
+                number_of_instance_fields,
+                instance_field_descriptors,
+            } => f
+                .debug_struct("ClassDump")
+                .field("class_object_id", class_object_id)
+                .field("stack_trace_serial_number", stack_trace_serial_number)
+                .field("super_class_object_id", super_class_object_id)
+                .field("class_loader_object_id", class_loader_object_id)
+                .field("signers_object_id", signers_object_id)
+                .field("protection_domain_object_id", protection_domain_object_id)
+                .field("reserved1", reserved1)
+                .field("reserved2", reserved2)
+                .field("instance_size", instance_size)
+                .field("constant_pool_size", constant_pool_size)
+                .field("number_of_static_fields", number_of_static_fields)
+                .field("static_fields", &elements(static_fields, limit))
+                .field("number_of_instance_fields", number_of_instance_fields)
+                .field(
+                    "instance_field_descriptors",
+                    &elements(instance_field_descriptors, limit),
+                )
+                .finish(),
+            SubRecord::InstanceDump {
+                object_id,
+                stack_trace_serial_number,
+                class_object_id,
+                number_of_bytes,
+                raw_field_bytes,
+            } => f
+                .debug_struct("InstanceDump")
+                .field("object_id", object_id)
+                .field("stack_trace_serial_number", stack_trace_serial_number)
+                .field("class_object_id", class_object_id)
+                .field("number_of_bytes", number_of_bytes)
+                .field("raw_field_bytes", &elements(raw_field_bytes, limit))
+                .finish(),
+            SubRecord::ObjArrayDump {
+                object_id,
+                stack_trace_serial_number,
+                array_class_id,
+                elements: array_elements,
+            } => f
+                .debug_struct("ObjArrayDump")
+                .field("object_id", object_id)
+                .field("stack_trace_serial_number", stack_trace_serial_number)
+                .field("array_class_id", array_class_id)
+                .field("elements", &elements(array_elements, limit))
+                .finish(),
+            SubRecord::PrimArrayDump {
+                object_id,
+                stack_trace_serial_number,
+                typ,
+                elements: array_elements,
+            } => f
+                .debug_struct("PrimArrayDump")
+                .field("object_id", object_id)
+                .field("stack_trace_serial_number", stack_trace_serial_number)
+                .field("typ", typ)
+                .field(
+                    "elements",
+                    &Limited {
+                        value: array_elements,
+                        limit,
+                    },
+                )
+                .finish(),
+            SubRecord::ThreadObj {
+                object_id,
+                sequence_number,
+                stack_trace_sequence_number,
+            } => f
+                .debug_struct("ThreadObj")
+                .field("object_id", object_id)
+                .field("sequence_number", sequence_number)
+                .field("stack_trace_sequence_number", stack_trace_sequence_number)
+                .finish(),
+            SubRecord::JavaFrame {
+                object_id,
+                thread_serial_number,
+                frame_number,
+            } => f
+                .debug_struct("JavaFrame")
+                .field("object_id", object_id)
+                .field("thread_serial_number", thread_serial_number)
+                .field("frame_number", frame_number)
+                .finish(),
+            SubRecord::JniLocal {
+                object_id,
+                thread_serial_number,
+                frame_number,
+            } => f
+                .debug_struct("JniLocal")
+                .field("object_id", object_id)
+                .field("thread_serial_number", thread_serial_number)
+                .field("frame_number", frame_number)
+                .finish(),
+            SubRecord::JniGlobal {
+                object_id,
+                global_ref_id,
+            } => f
+                .debug_struct("JniGlobal")
+                .field("object_id", object_id)
+                .field("global_ref_id", global_ref_id)
+                .finish(),
+            SubRecord::StickyClass { object_id } => f
+                .debug_struct("StickyClass")
+                .field("object_id", object_id)
+                .finish(),
+            SubRecord::HeapDumpEnd => f.write_str("HeapDumpEnd"),
+        }
+    }
+}
+
+impl Debug for Record {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.fmt_limited(f, DEBUG_PREVIEW)
+    }
+}
+
+impl LimitedDebug for Record {
+    fn fmt_limited(&self, f: &mut Formatter<'_>, limit: usize) -> fmt::Result {
+        match self {
+            Record::Utf8 {
+                micros,
+                name_id,
+                content,
+            } => f
+                .debug_struct("Utf8")
+                .field("micros", micros)
+                .field("name_id", name_id)
+                .field("content", content)
+                .finish(),
+            Record::LoadClass {
+                micros,
+                class_serial_number,
+                class_object_id,
+                stack_trace_serial_number,
+                class_name_id,
+            } => f
+                .debug_struct("LoadClass")
+                .field("micros", micros)
+                .field("class_serial_number", class_serial_number)
+                .field("class_object_id", class_object_id)
+                .field("stack_trace_serial_number", stack_trace_serial_number)
+                .field("class_name_id", class_name_id)
+                .finish(),
+            Record::Trace {
+                micros,
+                stack_trace_serial_number,
+                thread_serial_number,
+                stack_frame_ids,
+            } => f
+                .debug_struct("Trace")
+                .field("micros", micros)
+                .field("stack_trace_serial_number", stack_trace_serial_number)
+                .field("thread_serial_number", thread_serial_number)
+                .field("stack_frame_ids", &elements(stack_frame_ids, limit))
+                .finish(),
+            Record::Frame {
+                micros,
+                stack_frame_id,
+                method_name_id,
+                method_signature_id,
+                source_file_name_id,
+                class_serial_number,
+                line_number,
+            } => f
+                .debug_struct("Frame")
+                .field("micros", micros)
+                .field("stack_frame_id", stack_frame_id)
+                .field("method_name_id", method_name_id)
+                .field("method_signature_id", method_signature_id)
+                .field("source_file_name_id", source_file_name_id)
+                .field("class_serial_number", class_serial_number)
+                .field("line_number", line_number)
+                .finish(),
+            Record::HeapDumpSegment {
+                micros,
+                sub_records,
+            } => f
+                .debug_struct("HeapDumpSegment")
+                .field("micros", micros)
+                .field("sub_records", &nested(sub_records, limit))
+                .finish(),
+            Record::HeapDumpEnd { micros } => f
+                .debug_struct("HeapDumpEnd")
+                .field("micros", micros)
+                .finish(),
+        }
+    }
+}
+
+impl Debug for ParsedHeap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.fmt_limited(f, DEBUG_PREVIEW)
+    }
+}
+
+impl LimitedDebug for ParsedHeap {
+    fn fmt_limited(&self, f: &mut Formatter<'_>, limit: usize) -> fmt::Result {
+        f.debug_struct("ParsedHeap")
+            .field("version", &self.version)
+            .field("timestamp", &self.timestamp)
+            .field("records", &nested(&self.records, limit))
+            .finish()
+    }
+}
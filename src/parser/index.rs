@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use crate::parser::{
+    Id, ParsedHeap,
+    select::FromSubRecord,
+    sub_record::{SubRecord, SubRecordKind},
+};
+
+// the sub records of all heap dump segments as one heap, looked up by the object they dump or
+// by kind:
+//
+//   let index = parsed_heap.index();
+//   let instance: Option<InstanceDump> = index.get_as(id);
+//   println!("{} classes", index.count(SubRecordKind::ClassDump));
+pub struct SubRecordIndex<'a> {
+    // dump order, across segments
+    sub_records: Vec<&'a SubRecord>,
+    // positions of class, instance and array dumps. an id dumped twice keeps its first dump
+    objects: HashMap<Id, u32>,
+    kinds: HashMap<SubRecordKind, Vec<u32>>,
+}
+
+impl<'a> SubRecordIndex<'a> {
+    pub fn new(parsed_heap: &'a ParsedHeap) -> Self {
+        let sub_records: Vec<&SubRecord> = parsed_heap.sub_records().collect();
+        let mut objects = HashMap::with_capacity(sub_records.len());
+        let mut kinds: HashMap<SubRecordKind, Vec<u32>> = HashMap::new();
+        for (i, sub_record) in sub_records.iter().enumerate() {
+            if let Some(id) = sub_record.dumped_object_id() {
+                objects.entry(id).or_insert(i as u32);
+            }
+            kinds.entry(sub_record.kind()).or_default().push(i as u32);
+        }
+        Self {
+            sub_records,
+            objects,
+            kinds,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sub_records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sub_records.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a SubRecord> + '_ {
+        self.sub_records.iter().copied()
+    }
+
+    // the class, instance or array dump of an object
+    pub fn get(&self, id: Id) -> Option<&'a SubRecord> {
+        self.objects.get(&id).map(|i| self.sub_records[*i as usize])
+    }
+
+    pub fn get_as<T: FromSubRecord<'a>>(&self, id: Id) -> Option<T> {
+        self.get(id).and_then(T::from_sub_record)
+    }
+
+    // in dump order
+    pub fn of_kind(&self, kind: SubRecordKind) -> impl Iterator<Item = &'a SubRecord> + '_ {
+        self.kinds
+            .get(&kind)
+            .into_iter()
+            .flatten()
+            .map(|i| self.sub_records[*i as usize])
+    }
+
+    pub fn count(&self, kind: SubRecordKind) -> usize {
+        self.kinds.get(&kind).map_or(0, Vec::len)
+    }
+}
+
+impl ParsedHeap {
+    // builds the index, worth it for more than a few lookups. the iterators in select scan
+    pub fn index(&self) -> SubRecordIndex<'_> {
+        SubRecordIndex::new(self)
+    }
+}
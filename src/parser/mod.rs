@@ -1,115 +1,189 @@
-use anyhow::{Context, Result, anyhow, bail};
-use chrono::{DateTime, Utc};
 use std::{
     fmt::Display,
-    io::{Cursor, Read, Seek},
+    io::{Cursor, Read, Seek, SeekFrom},
     path::Path,
+    str::FromStr,
+    sync::OnceLock,
 };
 
-use crate::parser::{
-    sub_record::SubRecord,
-    util::{read_i32, read_u8, read_u32, read_u64, read_utf8},
+use crate::{
+    error::{HeapError, Result},
+    parser::{
+        select::Lookup,
+        stats::ParseStats,
+        sub_record::SubRecord,
+        util::{read_i32, read_u8, read_u32, read_utf8},
+    },
+    trace::warn,
 };
 
+pub mod borrowed;
+pub mod debug;
+pub mod index;
+mod reader;
+pub mod select;
+pub mod stats;
 pub mod sub_record;
 mod util;
 
-#[derive(Debug)]
+pub use reader::{
+    Header, PositionTracking, RecordReader, Timestamp, timestamp_from_millis, timestamp_millis,
+};
+pub use util::ReadCtx;
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Version {
     JavaProfile102,
 }
 
 impl Version {
-    fn new(version_str: &str) -> Result<Self> {
+    pub fn new(version_str: &str) -> Result<Self> {
         match version_str {
             "JAVA PROFILE 1.0.2" => Ok(Self::JavaProfile102),
-            _ => Err(anyhow!("Invalid version: {}", version_str)),
+            _ => Err(HeapError::UnsupportedVersion(version_str.to_string())),
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Version::JavaProfile102 => write!(f, "JAVA PROFILE 1.0.2"),
         }
     }
 }
 
 // https://github.com/openjdk/jdk17/blob/4afbcaf55383ec2f5da53282a1547bac3d099e9d/src/hotspot/share/services/heapDumper.cpp#L62
-#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ParsedHeap {
     pub version: Version,
-    pub timestamp: DateTime<Utc>,
+    pub timestamp: Timestamp,
     pub records: Vec<Record>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    stats: ParseStats,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    lookup: OnceLock<Lookup>,
 }
 
 impl ParsedHeap {
     pub fn parse(path: &Path) -> Result<Self> {
-        let contents = std::fs::read(path)?;
-        let mut cursor = Cursor::new(contents);
+        let contents = std::fs::read(path).map_err(|source| HeapError::Open {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_bytes(contents)
+    }
 
-        let version = read_utf8(&mut cursor, 18)?;
+    // an owned buffer or a borrowed slice, like a fuzzer's input
+    pub fn from_bytes(contents: impl AsRef<[u8]>) -> Result<Self> {
+        let mut reader = RecordReader::new(Cursor::new(contents.as_ref()))?;
+        let version = reader.header.version;
+        let timestamp = reader.header.timestamp;
 
-        // skip 0-byte
-        read_u8(&mut cursor)?;
+        let records = reader.by_ref().collect::<Result<Vec<_>>>()?;
 
-        let identifier_size = read_u32(&mut cursor)?;
+        Ok(Self {
+            version,
+            timestamp,
+            records,
+            stats: reader.stats().clone(),
+            lookup: OnceLock::new(),
+        })
+    }
 
-        if identifier_size != 8 {
-            bail!("only 64bit heapdumps supported");
+    // records and bytes per tag, as counted while parsing
+    pub fn stats(&self) -> &ParseStats {
+        &self.stats
+    }
+
+    pub fn header(&self) -> Header {
+        Header {
+            version: self.version,
+            timestamp: self.timestamp,
         }
+    }
+}
 
-        let timestamp = DateTime::from_timestamp_millis(read_u64(&mut cursor)? as i64)
-            .context("invalid timestamp")?;
+// ids are printed as hex like MAT and the jdk tools do, 0x7fec0bd5c648
+macro_rules! id_type {
+    ($name:ident) => {
+        #[derive(Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
+        #[repr(transparent)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub struct $name(pub u64);
 
-        let mut records = Vec::new();
-        loop {
-            let record = Record::parse(&mut cursor)?;
-
-            if matches!(record, Record::HeapDumpEnd { .. }) {
-                records.push(record);
-                break;
+        impl From<u64> for $name {
+            fn from(value: u64) -> Self {
+                Self(value)
             }
+        }
 
-            records.push(record);
+        impl From<$name> for u64 {
+            fn from(id: $name) -> Self {
+                id.0
+            }
         }
 
-        Ok(Self {
-            version: Version::new(&version)?,
-            timestamp,
-            records,
-        })
-    }
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "0x{:x}", self.0)
+            }
+        }
+    };
 }
 
-#[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
-pub struct Id(pub u64);
+// an object on the heap
+id_type!(Id);
+// a utf8 record, names of classes, fields, methods and source files
+id_type!(StringId);
+// a stack frame record
+id_type!(FrameId);
+
+// strings and frames live next to the heap with ids of their own, class objects are objects on
+// the heap and referenced like any other. the aliases name which one a field expects
+pub type ObjectId = Id;
+pub type ClassId = Id;
+
+// hex with 0x prefix as printed, or decimal
+impl FromStr for Id {
+    type Err = HeapError;
 
-impl From<u64> for Id {
-    fn from(value: u64) -> Self {
-        Self(value)
+    fn from_str(s: &str) -> Result<Self> {
+        let id = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => s.parse(),
+        };
+        id.map(Id).map_err(|_| HeapError::InvalidId(s.to_string()))
     }
 }
 
-#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Record {
     Utf8 {
         micros: u32,
-        name_id: Id,
+        name_id: StringId,
         content: String,
     },
     LoadClass {
         micros: u32,
         class_serial_number: u32,
-        class_object_id: Id,
+        class_object_id: ClassId,
         stack_trace_serial_number: u32,
-        class_name_id: Id,
+        class_name_id: StringId,
     },
     Trace {
         micros: u32,
         stack_trace_serial_number: u32,
         thread_serial_number: u32,
-        stack_frame_ids: Vec<Id>,
+        stack_frame_ids: Vec<FrameId>,
     },
     Frame {
         micros: u32,
-        stack_frame_id: Id,
-        method_name_id: Id,
-        method_signature_id: Id,
-        source_file_name_id: Id,
+        stack_frame_id: FrameId,
+        method_name_id: StringId,
+        method_signature_id: StringId,
+        source_file_name_id: StringId,
         class_serial_number: u32,
         line_number: i32,
     },
@@ -136,25 +210,62 @@ impl Display for Record {
 }
 
 impl Record {
-    fn parse(r: &mut (impl Read + Seek)) -> Result<Record> {
-        let tag = read_u8(r)?;
-        let micros = read_u32(r)?;
-        let bytes_remaining = read_u32(r)? as usize;
-
-        match tag {
-            0x01 => Self::utf8(r, micros, bytes_remaining),
-            0x02 => Self::load_class(r, micros),
-            0x04 => Self::frame(r, micros),
-            0x05 => Self::trace(r, micros),
-            0x1c => Self::heap_dump_segment(r, micros, bytes_remaining),
-            0x2c => Ok(Self::HeapDumpEnd { micros }),
-            _ => Err(anyhow!("invalid tag: 0x{:x}", tag)),
+    // offset is where the record starts, for errors. records with unknown tags are skipped
+    // unless the policy is strict
+    fn parse(
+        r: &mut (impl Read + Seek),
+        ctx: ReadCtx,
+        mut offset: u64,
+        stats: &mut ParseStats,
+    ) -> Result<Record> {
+        loop {
+            let tag = read_u8(r)?;
+            let micros = read_u32(r)?;
+            let bytes_remaining = read_u32(r)? as usize;
+            // tag, micros and length
+            let record_size = 9 + bytes_remaining as u64;
+
+            let record = match tag {
+                0x01 => Self::utf8(r, ctx, offset, micros, bytes_remaining),
+                0x02 => Self::load_class(r, ctx, micros),
+                0x04 => Self::frame(r, ctx, micros),
+                0x05 => Self::trace(r, ctx, micros),
+                0x1c => Self::heap_dump_segment(r, ctx, offset, micros, bytes_remaining, stats),
+                0x2c => Ok(Self::HeapDumpEnd { micros }),
+                _ if !ctx.policy.is_strict() => {
+                    warn!(
+                        "skipping record with unknown tag 0x{:x} at offset {}",
+                        tag, offset
+                    );
+                    r.seek(SeekFrom::Current(bytes_remaining as i64))?;
+                    stats.record(tag, record_size);
+                    offset += record_size;
+                    continue;
+                }
+                _ => Err(HeapError::UnknownTag { tag, offset }),
+            }?;
+            stats.record(tag, record_size);
+            return Ok(record);
         }
     }
 
-    fn utf8(r: &mut impl Read, micros: u32, bytes_remaining: usize) -> Result<Self> {
-        let name_id = read_u64(r)?.into();
-        let content = read_utf8(r, bytes_remaining - 8)?;
+    fn utf8(
+        r: &mut impl Read,
+        ctx: ReadCtx,
+        offset: u64,
+        micros: u32,
+        bytes_remaining: usize,
+    ) -> Result<Self> {
+        let name_id = ctx.read_id(r)?.into();
+        let content_size =
+            bytes_remaining
+                .checked_sub(ctx.id_size as usize)
+                .ok_or(HeapError::LengthMismatch {
+                    offset,
+                    expected: bytes_remaining as u64,
+                    actual: ctx.id_size as u64,
+                })?;
+        let content = read_utf8(r, content_size)?;
         Ok(Self::Utf8 {
             micros,
             name_id,
@@ -162,24 +273,24 @@ impl Record {
         })
     }
 
-    fn load_class(r: &mut impl Read, micros: u32) -> Result<Self> {
+    fn load_class(r: &mut impl Read, ctx: ReadCtx, micros: u32) -> Result<Self> {
         Ok(Self::LoadClass {
             micros,
             class_serial_number: read_u32(r)?,
-            class_object_id: read_u64(r)?.into(),
+            class_object_id: ctx.read_id(r)?.into(),
             stack_trace_serial_number: read_u32(r)?,
-            class_name_id: read_u64(r)?.into(),
+            class_name_id: ctx.read_id(r)?.into(),
         })
     }
 
-    fn trace(r: &mut impl Read, micros: u32) -> Result<Self> {
+    fn trace(r: &mut impl Read, ctx: ReadCtx, micros: u32) -> Result<Self> {
         let stack_trace_serial_number = read_u32(r)?;
         let thread_serial_number = read_u32(r)?;
         let number_of_frames = read_u32(r)?;
 
         let mut stack_frame_ids = Vec::new();
         for _ in 0..number_of_frames {
-            stack_frame_ids.push(read_u64(r)?.into());
+            stack_frame_ids.push(ctx.read_id(r)?.into());
         }
 
         Ok(Self::Trace {
@@ -190,11 +301,11 @@ impl Record {
         })
     }
 
-    fn frame(r: &mut impl Read, micros: u32) -> Result<Self> {
-        let stack_frame_id = read_u64(r)?.into();
-        let method_name_id = read_u64(r)?.into();
-        let method_signature_id = read_u64(r)?.into();
-        let source_file_name_id = read_u64(r)?.into();
+    fn frame(r: &mut impl Read, ctx: ReadCtx, micros: u32) -> Result<Self> {
+        let stack_frame_id = ctx.read_id(r)?.into();
+        let method_name_id = ctx.read_id(r)?.into();
+        let method_signature_id = ctx.read_id(r)?.into();
+        let source_file_name_id = ctx.read_id(r)?.into();
         let class_serial_number = read_u32(r)?;
         let line_number = read_i32(r)?;
 
@@ -209,22 +320,51 @@ impl Record {
         })
     }
 
+    // a sub record of unknown type has no length to skip it by, so the rest of the segment is
+    // skipped unless the policy is strict. a sub record running past the end of the segment
+    // fails in strict mode, otherwise the next record is read from where it ended
     fn heap_dump_segment(
         r: &mut (impl Read + Seek),
+        ctx: ReadCtx,
+        offset: u64,
         micros: u32,
         bytes_remaining: usize,
+        stats: &mut ParseStats,
     ) -> Result<Self> {
         let start_position = r.stream_position()?;
+        let end = start_position + bytes_remaining as u64;
+        let mut position = start_position;
         let mut sub_records = Vec::new();
         loop {
-            let sub_record = SubRecord::new(r)?;
+            let sub_record = match SubRecord::new(r, ctx) {
+                Err(HeapError::UnknownSubRecord { typ }) if !ctx.policy.is_strict() => {
+                    warn!(
+                        "skipping the rest of the segment at offset {} after unknown sub record \
+                         type 0x{:x} at offset {}",
+                        offset, typ, position
+                    );
+                    r.seek(SeekFrom::Start(end))?;
+                    break;
+                }
+                sub_record => sub_record?,
+            };
+            let end_position = r.stream_position()?;
+            stats.sub_record(sub_record.kind(), end_position - position);
+            position = end_position;
             if matches!(sub_record, SubRecord::HeapDumpEnd) {
                 sub_records.push(sub_record);
                 break;
             }
             sub_records.push(sub_record);
 
-            if r.stream_position()? - start_position == bytes_remaining as u64 {
+            if position >= end {
+                if position > end && ctx.policy.is_strict() {
+                    return Err(HeapError::LengthMismatch {
+                        offset,
+                        expected: bytes_remaining as u64,
+                        actual: position - start_position,
+                    });
+                }
                 break;
             }
         }
@@ -0,0 +1,186 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::{
+    error::{HeapError, Policy, Result},
+    parser::{
+        Record, Version,
+        stats::ParseStats,
+        util::{ReadCtx, read_u8, read_u32, read_u64, read_utf8},
+    },
+};
+
+// when the dump was written. a chrono DateTime with the chrono feature, milliseconds since the
+// epoch without it
+#[cfg(feature = "chrono")]
+pub type Timestamp = chrono::DateTime<chrono::Utc>;
+#[cfg(not(feature = "chrono"))]
+pub type Timestamp = u64;
+
+#[cfg(feature = "chrono")]
+pub fn timestamp_from_millis(millis: u64) -> Option<Timestamp> {
+    chrono::DateTime::from_timestamp_millis(millis as i64)
+}
+
+#[cfg(not(feature = "chrono"))]
+pub fn timestamp_from_millis(millis: u64) -> Option<Timestamp> {
+    Some(millis)
+}
+
+#[cfg(feature = "chrono")]
+pub fn timestamp_millis(timestamp: &Timestamp) -> u64 {
+    timestamp.timestamp_millis() as u64
+}
+
+#[cfg(not(feature = "chrono"))]
+pub fn timestamp_millis(timestamp: &Timestamp) -> u64 {
+    *timestamp
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Header {
+    pub version: Version,
+    pub timestamp: Timestamp,
+}
+
+impl Header {
+    // the context for reading the records after it
+    pub(super) fn parse(r: &mut impl Read) -> Result<(Self, ReadCtx)> {
+        let version = read_utf8(r, 18)?;
+
+        // skip 0-byte
+        read_u8(r)?;
+
+        let identifier_size = read_u32(r)?;
+        let ctx = ReadCtx::new(identifier_size)?;
+        // the readers take 4 byte ids, instance field layouts and the analyzer don't yet
+        if ctx.id_size != 8 {
+            return Err(HeapError::UnsupportedIdSize(identifier_size));
+        }
+
+        let millis = read_u64(r)?;
+        let timestamp = timestamp_from_millis(millis).ok_or(HeapError::InvalidTimestamp(millis))?;
+
+        let header = Self {
+            version: Version::new(&version)?,
+            timestamp,
+        };
+        Ok((header, ctx))
+    }
+}
+
+// parses records one at a time, for consumers that don't need the whole heap in memory
+pub struct RecordReader<R> {
+    r: R,
+    pub header: Header,
+    pub ctx: ReadCtx,
+    stats: ParseStats,
+    done: bool,
+    // where the first record starts
+    start: u64,
+}
+
+impl RecordReader<PositionTracking<BufReader<File>>> {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|source| HeapError::Open {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::new(PositionTracking::new(BufReader::with_capacity(
+            1 << 20,
+            file,
+        )))
+    }
+}
+
+impl<R: Read + Seek> RecordReader<R> {
+    pub fn new(mut r: R) -> Result<Self> {
+        let (header, ctx) = Header::parse(&mut r)?;
+        let start = r.stream_position()?;
+        Ok(Self {
+            r,
+            header,
+            ctx,
+            stats: ParseStats::default(),
+            done: false,
+            start,
+        })
+    }
+
+    // back to the first record, for consumers making more than one pass
+    pub fn rewind(&mut self) -> Result<()> {
+        self.r.seek(SeekFrom::Start(self.start))?;
+        self.stats = ParseStats::default();
+        self.done = false;
+        Ok(())
+    }
+
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.ctx.policy = policy;
+        self
+    }
+
+    // of the records read so far
+    pub fn stats(&self) -> &ParseStats {
+        &self.stats
+    }
+}
+
+impl<R: Read + Seek> Iterator for RecordReader<R> {
+    type Item = Result<Record>;
+
+    // ends after the HeapDumpEnd record
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let record = match self.r.stream_position() {
+            Ok(offset) => Record::parse(&mut self.r, self.ctx, offset, &mut self.stats)
+                .map_err(|e| e.truncated_at(offset)),
+            Err(err) => Err(err.into()),
+        };
+        if !matches!(record, Ok(Record::HeapDumpEnd { .. })) {
+            self.done = record.is_err();
+            return Some(record);
+        }
+
+        self.done = true;
+        Some(record)
+    }
+}
+
+// answers stream_position without a seek syscall, segments ask for it after every sub record
+pub struct PositionTracking<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R> PositionTracking<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, position: 0 }
+    }
+}
+
+impl<R: Read> Read for PositionTracking<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for PositionTracking<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = self.inner.seek(pos)?;
+        Ok(self.position)
+    }
+
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        Ok(self.position)
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+
+use crate::parser::{
+    ClassId, FrameId, Id, ParsedHeap, Record, StringId,
+    sub_record::{Field, FieldDescriptor, PrimArray, SubRecord},
+};
+
+// typed views of records and sub records, for traversals that only care about one kind:
+//
+//   for dump in parsed_heap.iter_sub_records::<InstanceDump>() {
+//       println!("{} {} bytes", dump.object_id, dump.raw_field_bytes.len());
+//   }
+pub trait FromRecord<'a>: Sized {
+    fn from_record(record: &'a Record) -> Option<Self>;
+}
+
+pub trait FromSubRecord<'a>: Sized {
+    fn from_sub_record(sub_record: &'a SubRecord) -> Option<Self>;
+}
+
+pub struct Utf8<'a> {
+    pub name_id: StringId,
+    pub content: &'a str,
+}
+
+impl<'a> FromRecord<'a> for Utf8<'a> {
+    fn from_record(record: &'a Record) -> Option<Self> {
+        match record {
+            Record::Utf8 {
+                name_id, content, ..
+            } => Some(Self {
+                name_id: *name_id,
+                content,
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub struct LoadClass {
+    pub class_serial_number: u32,
+    pub class_object_id: ClassId,
+    pub class_name_id: StringId,
+}
+
+impl FromRecord<'_> for LoadClass {
+    fn from_record(record: &Record) -> Option<Self> {
+        match record {
+            Record::LoadClass {
+                class_serial_number,
+                class_object_id,
+                class_name_id,
+                ..
+            } => Some(Self {
+                class_serial_number: *class_serial_number,
+                class_object_id: *class_object_id,
+                class_name_id: *class_name_id,
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub struct Trace<'a> {
+    pub stack_trace_serial_number: u32,
+    pub thread_serial_number: u32,
+    pub stack_frame_ids: &'a [FrameId],
+}
+
+impl<'a> FromRecord<'a> for Trace<'a> {
+    fn from_record(record: &'a Record) -> Option<Self> {
+        match record {
+            Record::Trace {
+                stack_trace_serial_number,
+                thread_serial_number,
+                stack_frame_ids,
+                ..
+            } => Some(Self {
+                stack_trace_serial_number: *stack_trace_serial_number,
+                thread_serial_number: *thread_serial_number,
+                stack_frame_ids,
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub struct Frame {
+    pub stack_frame_id: FrameId,
+    pub method_name_id: StringId,
+    pub method_signature_id: StringId,
+    pub source_file_name_id: StringId,
+    pub class_serial_number: u32,
+    pub line_number: i32,
+}
+
+impl FromRecord<'_> for Frame {
+    fn from_record(record: &Record) -> Option<Self> {
+        match record {
+            Record::Frame {
+                stack_frame_id,
+                method_name_id,
+                method_signature_id,
+                source_file_name_id,
+                class_serial_number,
+                line_number,
+                ..
+            } => Some(Self {
+                stack_frame_id: *stack_frame_id,
+                method_name_id: *method_name_id,
+                method_signature_id: *method_signature_id,
+                source_file_name_id: *source_file_name_id,
+                class_serial_number: *class_serial_number,
+                line_number: *line_number,
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub struct ClassDump<'a> {
+    pub class_object_id: ClassId,
+    pub super_class_object_id: ClassId,
+    pub class_loader_object_id: Id,
+    pub instance_size: u32,
+    pub static_fields: &'a [Field],
+    pub instance_fields: &'a [FieldDescriptor],
+}
+
+impl<'a> FromSubRecord<'a> for ClassDump<'a> {
+    fn from_sub_record(sub_record: &'a SubRecord) -> Option<Self> {
+        match sub_record {
+            SubRecord::ClassDump {
+                class_object_id,
+                super_class_object_id,
+                class_loader_object_id,
+                instance_size,
+                static_fields,
+                instance_field_descriptors,
+                ..
+            } => Some(Self {
+                class_object_id: *class_object_id,
+                super_class_object_id: *super_class_object_id,
+                class_loader_object_id: *class_loader_object_id,
+                instance_size: *instance_size,
+                static_fields,
+                instance_fields: instance_field_descriptors,
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub struct InstanceDump<'a> {
+    pub object_id: Id,
+    pub stack_trace_serial_number: u32,
+    pub class_object_id: ClassId,
+    pub raw_field_bytes: &'a [u8],
+}
+
+impl<'a> FromSubRecord<'a> for InstanceDump<'a> {
+    fn from_sub_record(sub_record: &'a SubRecord) -> Option<Self> {
+        match sub_record {
+            SubRecord::InstanceDump {
+                object_id,
+                stack_trace_serial_number,
+                class_object_id,
+                raw_field_bytes,
+                ..
+            } => Some(Self {
+                object_id: *object_id,
+                stack_trace_serial_number: *stack_trace_serial_number,
+                class_object_id: *class_object_id,
+                raw_field_bytes,
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub struct ObjArrayDump<'a> {
+    pub object_id: Id,
+    pub array_class_id: ClassId,
+    // nulls included, as Id(0)
+    pub elements: &'a [Id],
+}
+
+impl<'a> FromSubRecord<'a> for ObjArrayDump<'a> {
+    fn from_sub_record(sub_record: &'a SubRecord) -> Option<Self> {
+        match sub_record {
+            SubRecord::ObjArrayDump {
+                object_id,
+                array_class_id,
+                elements,
+                ..
+            } => Some(Self {
+                object_id: *object_id,
+                array_class_id: *array_class_id,
+                elements,
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub struct PrimArrayDump<'a> {
+    pub object_id: Id,
+    pub elements: &'a PrimArray,
+}
+
+impl<'a> FromSubRecord<'a> for PrimArrayDump<'a> {
+    fn from_sub_record(sub_record: &'a SubRecord) -> Option<Self> {
+        match sub_record {
+            SubRecord::PrimArrayDump {
+                object_id,
+                elements,
+                ..
+            } => Some(Self {
+                object_id: *object_id,
+                elements,
+            }),
+            _ => None,
+        }
+    }
+}
+
+// any gc root sub record, the kind is left to the sub record
+pub struct Root<'a> {
+    pub object_id: Id,
+    pub sub_record: &'a SubRecord,
+}
+
+impl<'a> FromSubRecord<'a> for Root<'a> {
+    fn from_sub_record(sub_record: &'a SubRecord) -> Option<Self> {
+        let object_id = match sub_record {
+            SubRecord::JniGlobal { object_id, .. }
+            | SubRecord::JniLocal { object_id, .. }
+            | SubRecord::JavaFrame { object_id, .. }
+            | SubRecord::StickyClass { object_id }
+            | SubRecord::ThreadObj { object_id, .. } => *object_id,
+            _ => return None,
+        };
+        Some(Self {
+            object_id,
+            sub_record,
+        })
+    }
+}
+
+impl ParsedHeap {
+    pub fn iter_records<'a, T: FromRecord<'a> + 'a>(&'a self) -> impl Iterator<Item = T> + 'a {
+        self.records.iter().filter_map(T::from_record)
+    }
+
+    // sub records of every heap dump segment, in dump order
+    pub fn sub_records(&self) -> impl Iterator<Item = &SubRecord> {
+        self.segments().flatten()
+    }
+
+    pub fn iter_sub_records<'a, T: FromSubRecord<'a> + 'a>(
+        &'a self,
+    ) -> impl Iterator<Item = T> + 'a {
+        self.sub_records().filter_map(T::from_sub_record)
+    }
+
+    // the sub records of each heap dump segment. large dumps are split into segments of up to
+    // 4gb, older jvms write a single one
+    pub fn segments(&self) -> impl Iterator<Item = &[SubRecord]> {
+        self.records.iter().filter_map(|record| match record {
+            Record::HeapDumpSegment { sub_records, .. } => Some(sub_records.as_slice()),
+            _ => None,
+        })
+    }
+
+    pub fn iter_segment_sub_records<'a, T: FromSubRecord<'a> + 'a>(
+        &'a self,
+        segment: usize,
+    ) -> impl Iterator<Item = T> + 'a {
+        self.segments()
+            .nth(segment)
+            .into_iter()
+            .flatten()
+            .filter_map(T::from_sub_record)
+    }
+
+    // instance dumps of exactly this class, without subclasses
+    pub fn instances_of(&self, class_id: ClassId) -> impl Iterator<Item = InstanceDump<'_>> {
+        self.iter_sub_records::<InstanceDump>()
+            .filter(move |i| i.class_object_id == class_id)
+    }
+
+    // class dumps and strings are looked up in a map of their positions, built on the first
+    // lookup. the records are not expected to change after it
+    pub fn class_dump(&self, class_id: ClassId) -> Option<ClassDump<'_>> {
+        let (record, sub_record) = *self.lookup().classes.get(&class_id)?;
+        match &self.records[record as usize] {
+            Record::HeapDumpSegment { sub_records, .. } => {
+                ClassDump::from_sub_record(&sub_records[sub_record as usize])
+            }
+            _ => None,
+        }
+    }
+
+    pub fn string(&self, id: StringId) -> Option<&str> {
+        let record = *self.lookup().strings.get(&id)?;
+        Utf8::from_record(&self.records[record as usize]).map(|s| s.content)
+    }
+
+    fn lookup(&self) -> &Lookup {
+        self.lookup.get_or_init(|| Lookup::new(&self.records))
+    }
+}
+
+// record and sub record positions of class dumps, record positions of strings. an id dumped
+// twice keeps its first position, like the scans it replaces
+#[derive(Default)]
+pub(crate) struct Lookup {
+    classes: HashMap<ClassId, (u32, u32)>,
+    strings: HashMap<StringId, u32>,
+}
+
+impl Lookup {
+    fn new(records: &[Record]) -> Self {
+        let mut lookup = Self::default();
+        for (i, record) in records.iter().enumerate() {
+            match record {
+                Record::Utf8 { name_id, .. } => {
+                    lookup.strings.entry(*name_id).or_insert(i as u32);
+                }
+                Record::HeapDumpSegment { sub_records, .. } => {
+                    for (j, sub_record) in sub_records.iter().enumerate() {
+                        if let SubRecord::ClassDump {
+                            class_object_id, ..
+                        } = sub_record
+                        {
+                            lookup
+                                .classes
+                                .entry(*class_object_id)
+                                .or_insert((i as u32, j as u32));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        lookup
+    }
+}
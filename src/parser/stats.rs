@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+
+use crate::parser::sub_record::SubRecordKind;
+
+// how many records of each tag a parse read and how many bytes they took, counted while
+// parsing so callers don't need a second pass
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseStats {
+    // by record tag, headers included
+    pub records: BTreeMap<u8, TagStats>,
+    // inside heap dump segments, type bytes included
+    pub sub_records: BTreeMap<SubRecordKind, TagStats>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TagStats {
+    pub count: u64,
+    pub bytes: u64,
+}
+
+impl ParseStats {
+    pub(super) fn record(&mut self, tag: u8, bytes: u64) {
+        add(&mut self.records, tag, bytes);
+    }
+
+    pub(super) fn sub_record(&mut self, kind: SubRecordKind, bytes: u64) {
+        add(&mut self.sub_records, kind, bytes);
+    }
+
+    pub fn total_records(&self) -> TagStats {
+        total(&self.records)
+    }
+
+    pub fn total_sub_records(&self) -> TagStats {
+        total(&self.sub_records)
+    }
+}
+
+fn add<K: Ord>(tags: &mut BTreeMap<K, TagStats>, tag: K, bytes: u64) {
+    let stats = tags.entry(tag).or_default();
+    stats.count += 1;
+    stats.bytes += bytes;
+}
+
+fn total<K>(tags: &BTreeMap<K, TagStats>) -> TagStats {
+    tags.values()
+        .fold(TagStats::default(), |total, stats| TagStats {
+            count: total.count + stats.count,
+            bytes: total.bytes + stats.bytes,
+        })
+}
+
+// the name of a record tag, as in Record's variants
+pub fn record_name(tag: u8) -> &'static str {
+    match tag {
+        0x01 => "Utf8",
+        0x02 => "LoadClass",
+        0x04 => "Frame",
+        0x05 => "Trace",
+        0x1c => "HeapDumpSegment",
+        0x2c => "HeapDumpEnd",
+        _ => "Unknown",
+    }
+}
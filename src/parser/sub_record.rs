@@ -1,79 +1,217 @@
 use std::{fmt::Display, io::Read};
 
-use anyhow::{Result, bail};
-
-use crate::parser::{
-    Id,
-    util::{read_u8, read_u16, read_u32, read_u64},
+use crate::{
+    error::{HeapError, Result},
+    parser::{
+        ClassId, Id, StringId,
+        util::{ReadCtx, read_bytes, read_u8, read_u16, read_u32, read_u64},
+    },
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FieldValue {
     NormalObject { object_id: Id },
-    Boolean(u8),
+    Boolean(bool),
+    // a utf-16 code unit, not necessarily a whole character
     Char(u16),
-    Float(u32),
-    Double(u64),
-    Byte(u8),
-    Short(u16),
-    Int(u32),
-    Long(u64),
+    Float(f32),
+    Double(f64),
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Field {
-    pub name_id: Id,
+    pub name_id: StringId,
     pub value: FieldValue,
 }
 
 impl Field {
-    fn new(r: &mut impl Read) -> Result<Self> {
-        let name_id = read_u64(r)?.into();
+    fn new(r: &mut impl Read, ctx: ReadCtx) -> Result<Self> {
+        let name_id = ctx.read_id(r)?.into();
         let typ = read_u8(r)?;
+        let value = FieldValue::read(r, ctx, typ)?;
 
-        let value = match typ {
+        Ok(Self { name_id, value })
+    }
+}
+
+impl FieldValue {
+    // a value of the given basic type, as stored in static fields and instance dumps
+    pub fn read(r: &mut impl Read, ctx: ReadCtx, typ: u8) -> Result<Self> {
+        Ok(match typ {
             0x02 => FieldValue::NormalObject {
-                object_id: read_u64(r)?.into(),
+                object_id: ctx.read_id(r)?.into(),
             },
-            0x04 => FieldValue::Boolean(read_u8(r)?),
+            0x04 => FieldValue::Boolean(read_u8(r)? != 0),
             0x05 => FieldValue::Char(read_u16(r)?),
-            0x06 => FieldValue::Float(read_u32(r)?),
-            0x07 => FieldValue::Double(read_u64(r)?),
-            0x08 => FieldValue::Byte(read_u8(r)?),
-            0x09 => FieldValue::Short(read_u16(r)?),
-            0x0a => FieldValue::Int(read_u32(r)?),
-            0x0b => FieldValue::Long(read_u64(r)?),
-            _ => bail!("invalid field type: 0x{:x}", typ),
-        };
+            0x06 => FieldValue::Float(f32::from_bits(read_u32(r)?)),
+            0x07 => FieldValue::Double(f64::from_bits(read_u64(r)?)),
+            0x08 => FieldValue::Byte(read_u8(r)? as i8),
+            0x09 => FieldValue::Short(read_u16(r)? as i16),
+            0x0a => FieldValue::Int(read_u32(r)? as i32),
+            0x0b => FieldValue::Long(read_u64(r)? as i64),
+            _ => return Err(HeapError::InvalidType(typ)),
+        })
+    }
 
-        Ok(Self { name_id, value })
+    // the hprof basic type
+    pub fn typ(&self) -> u8 {
+        match self {
+            FieldValue::NormalObject { .. } => 0x02,
+            FieldValue::Boolean(_) => 0x04,
+            FieldValue::Char(_) => 0x05,
+            FieldValue::Float(_) => 0x06,
+            FieldValue::Double(_) => 0x07,
+            FieldValue::Byte(_) => 0x08,
+            FieldValue::Short(_) => 0x09,
+            FieldValue::Int(_) => 0x0a,
+            FieldValue::Long(_) => 0x0b,
+        }
+    }
+
+    // the value as stored in the dump, zero extended. floats keep their exact bits, nan
+    // payloads included
+    pub fn raw_bits(&self) -> u64 {
+        match *self {
+            FieldValue::NormalObject { object_id } => object_id.0,
+            FieldValue::Boolean(v) => v as u64,
+            FieldValue::Char(v) => v as u64,
+            FieldValue::Float(v) => v.to_bits() as u64,
+            FieldValue::Double(v) => v.to_bits(),
+            FieldValue::Byte(v) => v as u8 as u64,
+            FieldValue::Short(v) => v as u16 as u64,
+            FieldValue::Int(v) => v as u32 as u64,
+            FieldValue::Long(v) => v as u64,
+        }
+    }
+
+    // appends the value in big endian, as stored in instance dumps and static fields
+    pub fn write_be(&self, buf: &mut Vec<u8>) {
+        let bits = self.raw_bits();
+        let size = match self.typ() {
+            0x04 | 0x08 => 1,
+            0x05 | 0x09 => 2,
+            0x06 | 0x0a => 4,
+            _ => 8,
+        };
+        buf.extend_from_slice(&bits.to_be_bytes()[8 - size..]);
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FieldDescriptor {
-    pub name_id: Id,
+    pub name_id: StringId,
     pub typ: u8,
 }
 
-#[derive(Debug)]
-pub enum PrimArrayElement {
-    Bool(u8),
-    Byte(u8),
-    Char(u16),
-    Float(u32),
-    Double(u64),
-    Short(u16),
-    Int(u32),
-    Long(u64),
+// primitive array contents, chars as utf-16 code units
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PrimArray {
+    Bool(Vec<bool>),
+    Char(Vec<u16>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    Byte(Vec<i8>),
+    Short(Vec<i16>),
+    Int(Vec<i32>),
+    Long(Vec<i64>),
 }
 
-#[derive(Debug)]
+impl PrimArray {
+    // a single read for the whole array, elements are converted from big endian afterwards
+    pub fn read(r: &mut impl Read, typ: u8, len: usize) -> Result<Self> {
+        let bytes = read_bytes(r, len.saturating_mul(Self::element_size(typ)?))?;
+
+        Ok(match typ {
+            4 => Self::Bool(bytes.iter().map(|b| *b != 0).collect()),
+            5 => Self::Char(decode(&bytes, u16::from_be_bytes)),
+            6 => Self::Float(decode(&bytes, f32::from_be_bytes)),
+            7 => Self::Double(decode(&bytes, f64::from_be_bytes)),
+            8 => Self::Byte(bytes.iter().map(|b| *b as i8).collect()),
+            9 => Self::Short(decode(&bytes, i16::from_be_bytes)),
+            10 => Self::Int(decode(&bytes, i32::from_be_bytes)),
+            _ => Self::Long(decode(&bytes, i64::from_be_bytes)),
+        })
+    }
+
+    // bytes per element of the given basic type in a dump
+    pub fn element_size(typ: u8) -> Result<usize> {
+        Ok(match typ {
+            4 | 8 => 1,
+            5 | 9 => 2,
+            6 | 10 => 4,
+            7 | 11 => 8,
+            _ => return Err(HeapError::InvalidType(typ)),
+        })
+    }
+
+    // the hprof basic type
+    pub fn typ(&self) -> u8 {
+        match self {
+            Self::Bool(_) => 4,
+            Self::Char(_) => 5,
+            Self::Float(_) => 6,
+            Self::Double(_) => 7,
+            Self::Byte(_) => 8,
+            Self::Short(_) => 9,
+            Self::Int(_) => 10,
+            Self::Long(_) => 11,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Bool(v) => v.len(),
+            Self::Char(v) => v.len(),
+            Self::Float(v) => v.len(),
+            Self::Double(v) => v.len(),
+            Self::Byte(v) => v.len(),
+            Self::Short(v) => v.len(),
+            Self::Int(v) => v.len(),
+            Self::Long(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // appends the elements in big endian, as they are stored in a dump. floats keep their
+    // exact bits
+    pub fn write_be(&self, buf: &mut Vec<u8>) {
+        match self {
+            Self::Bool(v) => buf.extend(v.iter().map(|e| *e as u8)),
+            Self::Char(v) => buf.extend(v.iter().flat_map(|e| e.to_be_bytes())),
+            Self::Float(v) => buf.extend(v.iter().flat_map(|e| e.to_be_bytes())),
+            Self::Double(v) => buf.extend(v.iter().flat_map(|e| e.to_be_bytes())),
+            Self::Byte(v) => buf.extend(v.iter().map(|e| *e as u8)),
+            Self::Short(v) => buf.extend(v.iter().flat_map(|e| e.to_be_bytes())),
+            Self::Int(v) => buf.extend(v.iter().flat_map(|e| e.to_be_bytes())),
+            Self::Long(v) => buf.extend(v.iter().flat_map(|e| e.to_be_bytes())),
+        }
+    }
+}
+
+fn decode<const N: usize, T>(bytes: &[u8], from_be_bytes: fn([u8; N]) -> T) -> Vec<T> {
+    bytes
+        .chunks_exact(N)
+        .map(|c| from_be_bytes(c.try_into().unwrap()))
+        .collect()
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SubRecord {
     ClassDump {
-        class_object_id: Id,
+        class_object_id: ClassId,
         stack_trace_serial_number: u32,
-        super_class_object_id: Id,
+        super_class_object_id: ClassId,
         class_loader_object_id: Id,
         signers_object_id: Id,
         protection_domain_object_id: Id,
@@ -89,21 +227,21 @@ pub enum SubRecord {
     InstanceDump {
         object_id: Id,
         stack_trace_serial_number: u32,
-        class_object_id: Id,
+        class_object_id: ClassId,
         number_of_bytes: u32,
         raw_field_bytes: Vec<u8>,
     },
     ObjArrayDump {
         object_id: Id,
         stack_trace_serial_number: u32,
-        array_class_id: Id,
+        array_class_id: ClassId,
         elements: Vec<Id>,
     },
     PrimArrayDump {
         object_id: Id,
         stack_trace_serial_number: u32,
         typ: u8,
-        elements: Vec<PrimArrayElement>,
+        elements: PrimArray,
     },
     ThreadObj {
         object_id: Id,
@@ -131,63 +269,115 @@ pub enum SubRecord {
 }
 
 impl Display for SubRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind())
+    }
+}
+
+// the variant of a sub record without its contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SubRecordKind {
+    ClassDump,
+    InstanceDump,
+    ObjArrayDump,
+    PrimArrayDump,
+    ThreadObj,
+    JavaFrame,
+    JniLocal,
+    JniGlobal,
+    StickyClass,
+    HeapDumpEnd,
+}
+
+impl Display for SubRecordKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            SubRecord::ClassDump { .. } => write!(f, "ClassDump"),
-            SubRecord::InstanceDump { .. } => write!(f, "InstanceDump"),
-            SubRecord::ObjArrayDump { .. } => write!(f, "ObjArrayDump"),
-            SubRecord::PrimArrayDump { .. } => write!(f, "PrimArrayDump"),
-            SubRecord::ThreadObj { .. } => write!(f, "ThreadObj"),
-            SubRecord::JavaFrame { .. } => write!(f, "JavaFrame"),
-            SubRecord::JniLocal { .. } => write!(f, "JniLocal"),
-            SubRecord::JniGlobal { .. } => write!(f, "JniGlobal"),
-            SubRecord::StickyClass { .. } => write!(f, "StickyClass"),
-            SubRecord::HeapDumpEnd => write!(f, "HeapDumpEnd"),
+            SubRecordKind::ClassDump => write!(f, "ClassDump"),
+            SubRecordKind::InstanceDump => write!(f, "InstanceDump"),
+            SubRecordKind::ObjArrayDump => write!(f, "ObjArrayDump"),
+            SubRecordKind::PrimArrayDump => write!(f, "PrimArrayDump"),
+            SubRecordKind::ThreadObj => write!(f, "ThreadObj"),
+            SubRecordKind::JavaFrame => write!(f, "JavaFrame"),
+            SubRecordKind::JniLocal => write!(f, "JniLocal"),
+            SubRecordKind::JniGlobal => write!(f, "JniGlobal"),
+            SubRecordKind::StickyClass => write!(f, "StickyClass"),
+            SubRecordKind::HeapDumpEnd => write!(f, "HeapDumpEnd"),
         }
     }
 }
 
 impl SubRecord {
-    pub fn new(r: &mut impl Read) -> Result<Self> {
+    pub fn kind(&self) -> SubRecordKind {
+        match self {
+            SubRecord::ClassDump { .. } => SubRecordKind::ClassDump,
+            SubRecord::InstanceDump { .. } => SubRecordKind::InstanceDump,
+            SubRecord::ObjArrayDump { .. } => SubRecordKind::ObjArrayDump,
+            SubRecord::PrimArrayDump { .. } => SubRecordKind::PrimArrayDump,
+            SubRecord::ThreadObj { .. } => SubRecordKind::ThreadObj,
+            SubRecord::JavaFrame { .. } => SubRecordKind::JavaFrame,
+            SubRecord::JniLocal { .. } => SubRecordKind::JniLocal,
+            SubRecord::JniGlobal { .. } => SubRecordKind::JniGlobal,
+            SubRecord::StickyClass { .. } => SubRecordKind::StickyClass,
+            SubRecord::HeapDumpEnd => SubRecordKind::HeapDumpEnd,
+        }
+    }
+
+    // the object a class, instance or array dump describes. gc roots only point at one
+    pub fn dumped_object_id(&self) -> Option<Id> {
+        match self {
+            SubRecord::ClassDump {
+                class_object_id, ..
+            } => Some(*class_object_id),
+            SubRecord::InstanceDump { object_id, .. }
+            | SubRecord::ObjArrayDump { object_id, .. }
+            | SubRecord::PrimArrayDump { object_id, .. } => Some(*object_id),
+            _ => None,
+        }
+    }
+
+    pub fn new(r: &mut impl Read, ctx: ReadCtx) -> Result<Self> {
         let sub_record_type = read_u8(r)?;
 
         match sub_record_type {
-            0x01 => Self::jni_global(r),
-            0x02 => Self::jni_local(r),
-            0x03 => Self::java_frame(r),
-            0x05 => Self::sticky_class(r),
-            0x08 => Self::thread_obj(r),
-            0x20 => Self::class_dump(r),
-            0x21 => Self::instance_dump(r),
-            0x22 => Self::obj_array_dump(r),
-            0x23 => Self::prim_array_dump(r),
-            _ => bail!("unknown sub record type: 0x{:x}", sub_record_type),
+            0x01 => Self::jni_global(r, ctx),
+            0x02 => Self::jni_local(r, ctx),
+            0x03 => Self::java_frame(r, ctx),
+            0x05 => Self::sticky_class(r, ctx),
+            0x08 => Self::thread_obj(r, ctx),
+            0x20 => Self::class_dump(r, ctx),
+            0x21 => Self::instance_dump(r, ctx),
+            0x22 => Self::obj_array_dump(r, ctx),
+            0x23 => Self::prim_array_dump(r, ctx),
+            _ => Err(HeapError::UnknownSubRecord {
+                typ: sub_record_type,
+            }),
         }
     }
 
-    fn class_dump(r: &mut impl Read) -> Result<Self> {
-        let class_object_id = read_u64(r)?.into();
+    fn class_dump(r: &mut impl Read, ctx: ReadCtx) -> Result<Self> {
+        let class_object_id = ctx.read_id(r)?.into();
         let stack_trace_serial_number = read_u32(r)?;
-        let super_class_object_id = read_u64(r)?.into();
-        let class_loader_object_id = read_u64(r)?.into();
-        let signers_object_id = read_u64(r)?.into();
-        let protection_domain_object_id = read_u64(r)?.into();
-        let reserved1 = read_u64(r)?;
-        let reserved2 = read_u64(r)?;
+        let super_class_object_id = ctx.read_id(r)?.into();
+        let class_loader_object_id = ctx.read_id(r)?.into();
+        let signers_object_id = ctx.read_id(r)?.into();
+        let protection_domain_object_id = ctx.read_id(r)?.into();
+        let reserved1 = ctx.read_id(r)?;
+        let reserved2 = ctx.read_id(r)?;
         let instance_size = read_u32(r)?;
         let constant_pool_size = read_u16(r)?;
 
         let number_of_static_fields = read_u16(r)?;
         let mut static_fields = Vec::new();
         for _ in 0..number_of_static_fields {
-            static_fields.push(Field::new(r)?);
+            static_fields.push(Field::new(r, ctx)?);
         }
 
         let number_of_instance_fields = read_u16(r)?;
         let mut instance_field_descriptors = Vec::new();
         for _ in 0..number_of_instance_fields {
             instance_field_descriptors.push(FieldDescriptor {
-                name_id: read_u64(r)?.into(),
+                name_id: ctx.read_id(r)?.into(),
                 typ: read_u8(r)?,
             });
         }
@@ -210,13 +400,12 @@ impl SubRecord {
         })
     }
 
-    fn instance_dump(r: &mut impl Read) -> Result<Self> {
-        let object_id = read_u64(r)?.into();
+    fn instance_dump(r: &mut impl Read, ctx: ReadCtx) -> Result<Self> {
+        let object_id = ctx.read_id(r)?.into();
         let stack_trace_serial_number = read_u32(r)?;
-        let class_object_id = read_u64(r)?.into();
+        let class_object_id = ctx.read_id(r)?.into();
         let number_of_bytes = read_u32(r)?;
-        let mut raw_field_bytes = vec![0; number_of_bytes as usize];
-        r.read_exact(&mut raw_field_bytes)?;
+        let raw_field_bytes = read_bytes(r, number_of_bytes as usize)?;
 
         Ok(Self::InstanceDump {
             object_id,
@@ -227,14 +416,14 @@ impl SubRecord {
         })
     }
 
-    fn obj_array_dump(r: &mut impl Read) -> Result<Self> {
-        let object_id = read_u64(r)?.into();
+    fn obj_array_dump(r: &mut impl Read, ctx: ReadCtx) -> Result<Self> {
+        let object_id = ctx.read_id(r)?.into();
         let stack_trace_serial_number = read_u32(r)?;
         let number_of_elements = read_u32(r)?;
-        let array_class_id = read_u64(r)?.into();
+        let array_class_id = ctx.read_id(r)?.into();
         let mut elements = Vec::new();
         for _ in 0..number_of_elements {
-            elements.push(read_u64(r)?.into());
+            elements.push(ctx.read_id(r)?.into());
         }
 
         Ok(Self::ObjArrayDump {
@@ -245,28 +434,13 @@ impl SubRecord {
         })
     }
 
-    fn prim_array_dump(r: &mut impl Read) -> Result<Self> {
-        let object_id = read_u64(r)?.into();
+    fn prim_array_dump(r: &mut impl Read, ctx: ReadCtx) -> Result<Self> {
+        let object_id = ctx.read_id(r)?.into();
         let stack_trace_serial_number = read_u32(r)?;
         let number_of_elements = read_u32(r)?;
         let typ = read_u8(r)?;
 
-        let mut elements = Vec::new();
-        for _ in 0..number_of_elements {
-            let element = match typ {
-                4 => PrimArrayElement::Bool(read_u8(r)?),
-                5 => PrimArrayElement::Char(read_u16(r)?),
-                6 => PrimArrayElement::Float(read_u32(r)?),
-                7 => PrimArrayElement::Double(read_u64(r)?),
-                8 => PrimArrayElement::Byte(read_u8(r)?),
-                9 => PrimArrayElement::Short(read_u16(r)?),
-                10 => PrimArrayElement::Int(read_u32(r)?),
-                11 => PrimArrayElement::Long(read_u64(r)?),
-                _ => bail!("invalid array type: {}", typ),
-            };
-
-            elements.push(element);
-        }
+        let elements = PrimArray::read(r, typ, number_of_elements as usize)?;
 
         Ok(Self::PrimArrayDump {
             object_id,
@@ -276,40 +450,40 @@ impl SubRecord {
         })
     }
 
-    fn thread_obj(r: &mut impl Read) -> Result<Self> {
+    fn thread_obj(r: &mut impl Read, ctx: ReadCtx) -> Result<Self> {
         Ok(Self::ThreadObj {
-            object_id: read_u64(r)?.into(),
+            object_id: ctx.read_id(r)?.into(),
             sequence_number: read_u32(r)?,
             stack_trace_sequence_number: read_u32(r)?,
         })
     }
 
-    fn java_frame(r: &mut impl Read) -> Result<Self> {
+    fn java_frame(r: &mut impl Read, ctx: ReadCtx) -> Result<Self> {
         Ok(Self::JavaFrame {
-            object_id: read_u64(r)?.into(),
+            object_id: ctx.read_id(r)?.into(),
             thread_serial_number: read_u32(r)?,
             frame_number: read_u32(r)?,
         })
     }
 
-    fn jni_local(r: &mut impl Read) -> Result<Self> {
+    fn jni_local(r: &mut impl Read, ctx: ReadCtx) -> Result<Self> {
         Ok(Self::JniLocal {
-            object_id: read_u64(r)?.into(),
+            object_id: ctx.read_id(r)?.into(),
             thread_serial_number: read_u32(r)?,
             frame_number: read_u32(r)?,
         })
     }
 
-    fn jni_global(r: &mut impl Read) -> Result<Self> {
+    fn jni_global(r: &mut impl Read, ctx: ReadCtx) -> Result<Self> {
         Ok(Self::JniGlobal {
-            object_id: read_u64(r)?.into(),
-            global_ref_id: read_u64(r)?.into(),
+            object_id: ctx.read_id(r)?.into(),
+            global_ref_id: ctx.read_id(r)?.into(),
         })
     }
 
-    fn sticky_class(r: &mut impl Read) -> Result<Self> {
+    fn sticky_class(r: &mut impl Read, ctx: ReadCtx) -> Result<Self> {
         Ok(Self::StickyClass {
-            object_id: read_u64(r)?.into(),
+            object_id: ctx.read_id(r)?.into(),
         })
     }
 }
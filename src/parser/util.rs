@@ -1,6 +1,45 @@
-use anyhow::Result;
 use std::io::Read;
 
+use crate::error::{HeapError, Policy, Result};
+
+// what reading records takes besides the bytes, the identifier size from the header and what
+// to do about records breaking the format. ids of objects, classes, strings and frames all
+// share the size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadCtx {
+    pub id_size: u32,
+    pub policy: Policy,
+}
+
+impl ReadCtx {
+    pub fn new(id_size: u32) -> Result<Self> {
+        match id_size {
+            4 | 8 => Ok(Self {
+                id_size,
+                policy: Policy::default(),
+            }),
+            _ => Err(HeapError::UnsupportedIdSize(id_size)),
+        }
+    }
+
+    pub fn read_id(&self, r: &mut impl Read) -> Result<u64> {
+        match self.id_size {
+            4 => Ok(read_u32(r)? as u64),
+            _ => read_u64(r),
+        }
+    }
+}
+
+// 64 bit hotspot dumps, for bytes that don't come with a header
+impl Default for ReadCtx {
+    fn default() -> Self {
+        Self {
+            id_size: 8,
+            policy: Policy::default(),
+        }
+    }
+}
+
 pub fn read_i32(r: &mut impl Read) -> Result<i32> {
     let mut buf = [0; 4];
     r.read_exact(&mut buf)?;
@@ -31,9 +70,21 @@ pub fn read_u64(r: &mut impl Read) -> Result<u64> {
     Ok(u64::from_be_bytes(buf))
 }
 
+// buffers grow as bytes arrive up to this, so a corrupted length runs into the end of the input
+// instead of allocating gigabytes up front
+const MAX_PREALLOC: usize = 1 << 20;
+
+pub fn read_bytes(r: &mut impl Read, len: usize) -> Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(len.min(MAX_PREALLOC));
+    r.by_ref().take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() < len {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(buf)
+}
+
 pub fn read_utf8(r: &mut impl Read, size: usize) -> Result<String> {
-    let mut buf = vec![0; size];
-    r.read_exact(&mut buf)?;
+    let buf = read_bytes(r, size)?;
 
     // fix java utf8 quirks
     let mut fixed_buf = Vec::new();
@@ -54,5 +105,54 @@ pub fn read_utf8(r: &mut impl Read, size: usize) -> Result<String> {
         i += 1;
     }
 
-    Ok(String::from_utf8(fixed_buf.to_vec())?)
+    String::from_utf8(fixed_buf).map_err(|e| e.utf8_error().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::sub_record::SubRecord;
+
+    fn is_eof(result: Result<impl std::fmt::Debug>) -> bool {
+        matches!(result, Err(HeapError::Io(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof)
+    }
+
+    // a capacity of usize::MAX would abort the process
+    #[test]
+    fn huge_lengths_run_into_the_end_of_the_input() {
+        assert!(is_eof(read_bytes(&mut &[1, 2, 3][..], usize::MAX)));
+        assert!(is_eof(read_utf8(&mut &b"abc"[..], usize::MAX)));
+    }
+
+    #[test]
+    fn huge_array_lengths_run_into_the_end_of_the_input() {
+        // a long array of u32::MAX elements and an instance of as many bytes, 16 of them there
+        let mut long_array = vec![0x23];
+        long_array.extend(1u64.to_be_bytes());
+        long_array.extend(0u32.to_be_bytes());
+        long_array.extend(u32::MAX.to_be_bytes());
+        long_array.push(11);
+        long_array.extend([0; 16]);
+        assert!(is_eof(SubRecord::new(
+            &mut &long_array[..],
+            ReadCtx::default()
+        )));
+
+        let mut instance = vec![0x21];
+        instance.extend(1u64.to_be_bytes());
+        instance.extend(0u32.to_be_bytes());
+        instance.extend(2u64.to_be_bytes());
+        instance.extend(u32::MAX.to_be_bytes());
+        instance.extend([0; 16]);
+        assert!(is_eof(SubRecord::new(
+            &mut &instance[..],
+            ReadCtx::default()
+        )));
+    }
+
+    #[test]
+    fn reads_past_the_preallocation() {
+        let bytes = vec![7; 3 * MAX_PREALLOC + 1];
+        assert_eq!(read_bytes(&mut &bytes[..], bytes.len()).unwrap(), bytes);
+    }
 }
@@ -0,0 +1,16 @@
+// `use heapdump_analyzer::prelude::*` brings in what most code reading dumps needs. the owned
+// Record and SubRecord are the parser's, the zero copy ones stay in parser::borrowed
+pub use crate::{
+    analyzer::{
+        AnalyzedHeap, Class, Instance, contents::Contents, dominator::DominatorTree,
+        filter::ClassFilter, options::AnalysisOptions, size::SizeModel, storage::Storage,
+    },
+    error::{HeapError, Policy, Result as HeapResult},
+    heap::{Heap, HeapView, Object},
+    parser::{
+        ClassId, FrameId, Header, Id, ObjectId, ParsedHeap, Record, RecordReader, StringId,
+        debug::{Full, LimitedDebug},
+        index::SubRecordIndex,
+        sub_record::{FieldValue, PrimArray, SubRecord, SubRecordKind},
+    },
+};
@@ -0,0 +1,384 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    panic::{AssertUnwindSafe, catch_unwind, resume_unwind},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context as TaskContext, Poll, Waker},
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::{Heap, analyzer::index::HeapIndex, config::Config, trace::info};
+
+// bytes requested per range request, also how much is lost when a download is interrupted
+const CHUNK_SIZE: u64 = 64 << 20;
+
+const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+// dumps behind http(s) or in s3, e.g. "s3://bucket/dumps/heap.hprof"
+pub fn is_remote(location: &str) -> bool {
+    ["http://", "https://", "s3://"]
+        .iter()
+        .any(|scheme| location.starts_with(scheme))
+}
+
+// downloads the dump into the cache directory unless it's already there. the download is
+// resumed after an interruption as long as the object didn't change, which is told by its size
+// and etag
+pub fn fetch(location: &str, cache_dir: &Path) -> Result<PathBuf> {
+    let source = Source::parse(location)?;
+    std::fs::create_dir_all(cache_dir)
+        .with_context(|| format!("failed to create {}", cache_dir.display()))?;
+
+    let (size, etag) = source.head()?;
+    let stamp = format!("{} {}", size, etag);
+    let path = cache_dir.join(cache_name(location));
+    let stamp_path = path.with_extension("hprof.stamp");
+    let partial_path = path.with_extension("hprof.part");
+
+    let cached_stamp = std::fs::read_to_string(&stamp_path).ok();
+    if cached_stamp.as_deref() == Some(stamp.as_str()) && path.exists() {
+        info!("using cached {}", path.display());
+        return Ok(path);
+    }
+    if cached_stamp.as_deref() != Some(stamp.as_str()) {
+        // the object changed, or this is the first download
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&partial_path);
+        std::fs::write(&stamp_path, &stamp)?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&partial_path)
+        .with_context(|| format!("failed to open {}", partial_path.display()))?;
+    let mut offset = file.metadata()?.len();
+    if offset > 0 {
+        info!("resuming download of {} at {} bytes", location, offset);
+    }
+    while offset < size {
+        let end = (offset + CHUNK_SIZE).min(size) - 1;
+        offset += source.get_range(offset, end, &mut file)?;
+    }
+    file.sync_all()?;
+
+    std::fs::rename(&partial_path, &path)?;
+    info!("downloaded {} to {}", location, path.display());
+    Ok(path)
+}
+
+// fetches the dump and analyzes it like a local one, see Heap::open_url
+pub async fn open_url(location: &str, config: &Config) -> Result<Heap> {
+    let location = location.to_string();
+    let config = config.clone();
+    Blocking::spawn(move || {
+        let dump = fetch(&location, &config.remote.cache_dir()?)?;
+        let options = config.analysis_options();
+        let index = if config.index.enabled {
+            HeapIndex::open(&dump, &options)?
+        } else {
+            HeapIndex::build(&dump, &options)?
+        };
+        Ok(Heap::from_index(dump, index))
+    })
+    .await
+}
+
+// a closure run on a thread of its own, done once the thread is. doesn't need any particular
+// async runtime, the thread wakes whoever polled last
+struct Blocking<T>(Arc<Mutex<BlockingState<T>>>);
+
+struct BlockingState<T> {
+    // Err if the closure panicked, the panic is passed on to the awaiting task
+    result: Option<std::thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+impl<T: Send + 'static> Blocking<T> {
+    fn spawn(f: impl FnOnce() -> T + Send + 'static) -> Self {
+        let state = Arc::new(Mutex::new(BlockingState {
+            result: None,
+            waker: None,
+        }));
+        let shared = state.clone();
+        std::thread::spawn(move || {
+            let result = catch_unwind(AssertUnwindSafe(f));
+            let mut state = shared.lock().unwrap();
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+        Self(state)
+    }
+}
+
+impl<T> Future for Blocking<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<T> {
+        let mut state = self.0.lock().unwrap();
+        match state.result.take() {
+            Some(Ok(value)) => Poll::Ready(value),
+            Some(Err(panic)) => resume_unwind(panic),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+enum Source {
+    Http(String),
+    S3(S3Object),
+}
+
+struct S3Object {
+    scheme: &'static str,
+    host: String,
+    // percent encoded, starting with a slash
+    path: String,
+    region: String,
+    credentials: Option<Credentials>,
+}
+
+struct Credentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+impl Source {
+    fn parse(location: &str) -> Result<Self> {
+        let Some(rest) = location.strip_prefix("s3://") else {
+            return Ok(Self::Http(location.to_string()));
+        };
+        let Some((bucket, key)) = rest
+            .split_once('/')
+            .filter(|(b, k)| !b.is_empty() && !k.is_empty())
+        else {
+            bail!("expected s3://bucket/key, got {}", location);
+        };
+
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let region = env("AWS_REGION")
+            .or_else(|| env("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| "us-east-1".to_string());
+        // path style requests against custom endpoints like minio
+        let (scheme, host, path) = match env("AWS_ENDPOINT_URL") {
+            Some(endpoint) => {
+                let (scheme, host) = match endpoint.strip_prefix("http://") {
+                    Some(host) => ("http", host),
+                    None => ("https", endpoint.trim_start_matches("https://")),
+                };
+                (
+                    scheme,
+                    host.trim_end_matches('/').to_string(),
+                    format!("/{}/{}", bucket, uri_encode(key)),
+                )
+            }
+            None => (
+                "https",
+                format!("{}.s3.{}.amazonaws.com", bucket, region),
+                format!("/{}", uri_encode(key)),
+            ),
+        };
+        // public objects can be read without credentials
+        let credentials = env("AWS_ACCESS_KEY_ID")
+            .zip(env("AWS_SECRET_ACCESS_KEY"))
+            .map(|(access_key_id, secret_access_key)| Credentials {
+                access_key_id,
+                secret_access_key,
+                session_token: env("AWS_SESSION_TOKEN"),
+            });
+
+        Ok(Self::S3(S3Object {
+            scheme,
+            host,
+            path,
+            region,
+            credentials,
+        }))
+    }
+
+    fn url(&self) -> String {
+        match self {
+            Self::Http(url) => url.clone(),
+            Self::S3(object) => format!("{}://{}{}", object.scheme, object.host, object.path),
+        }
+    }
+
+    fn headers(&self, method: &str, range: Option<String>) -> Vec<(String, String)> {
+        let mut headers: Vec<(String, String)> = range
+            .into_iter()
+            .map(|r| ("range".to_string(), r))
+            .collect();
+        if let Self::S3(object) = self {
+            headers.extend(object.sign(method, headers.clone(), Utc::now()));
+        }
+        headers
+    }
+
+    // size and etag of the object
+    fn head(&self) -> Result<(u64, String)> {
+        let url = self.url();
+        let mut request = ureq::head(&url);
+        for (name, value) in self.headers("HEAD", None) {
+            request = request.header(name, value);
+        }
+        let response = request
+            .call()
+            .with_context(|| format!("failed to request {}", url))?;
+
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let size = header("content-length")
+            .and_then(|l| l.parse().ok())
+            .with_context(|| format!("{} didn't send a content length", url))?;
+        if header("accept-ranges").as_deref() != Some("bytes") {
+            bail!("{} doesn't support range requests", url);
+        }
+        Ok((size, header("etag").unwrap_or_default()))
+    }
+
+    // appends bytes start..=end to the file, returns how many were written
+    fn get_range(&self, start: u64, end: u64, file: &mut File) -> Result<u64> {
+        let url = self.url();
+        let mut request = ureq::get(&url);
+        for (name, value) in self.headers("GET", Some(format!("bytes={}-{}", start, end))) {
+            request = request.header(name, value);
+        }
+        let response = request
+            .call()
+            .with_context(|| format!("failed to request {}", url))?;
+        if response.status() != 206 {
+            bail!(
+                "{} answered a range request with {}",
+                url,
+                response.status()
+            );
+        }
+
+        let written = std::io::copy(&mut response.into_body().into_reader(), file)?;
+        file.flush()?;
+        if written != end - start + 1 {
+            bail!(
+                "{} sent {} bytes of a {} byte range",
+                url,
+                written,
+                end - start + 1
+            );
+        }
+        Ok(written)
+    }
+}
+
+impl S3Object {
+    // aws signature version 4 headers for a request without body
+    fn sign(
+        &self,
+        method: &str,
+        mut headers: Vec<(String, String)>,
+        now: DateTime<Utc>,
+    ) -> Vec<(String, String)> {
+        let Some(credentials) = &self.credentials else {
+            return Vec::new();
+        };
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+
+        let mut added = vec![
+            ("x-amz-content-sha256".to_string(), EMPTY_SHA256.to_string()),
+            ("x-amz-date".to_string(), amz_date.clone()),
+        ];
+        if let Some(token) = &credentials.session_token {
+            added.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        headers.extend(added.iter().cloned());
+        headers.push(("host".to_string(), self.host.clone()));
+        headers.sort();
+
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_request = format!(
+            "{}\n{}\n\n{}\n{}\n{}",
+            method, self.path, canonical_headers, signed_headers, EMPTY_SHA256
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let mut key = hmac(
+            format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+            &date,
+        );
+        for part in [self.region.as_str(), "s3", "aws4_request"] {
+            key = hmac(&key, part);
+        }
+        let signature = hex(&hmac(&key, &string_to_sign));
+
+        added.push((
+            "authorization".to_string(),
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                credentials.access_key_id, scope, signed_headers, signature
+            ),
+        ));
+        added
+    }
+}
+
+fn hmac(key: &[u8], message: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac takes keys of any length");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// percent encoding of s3 object keys, slashes are kept
+fn uri_encode(key: &str) -> String {
+    key.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+// fnv-1a of the location, so different objects with the same file name don't collide
+pub(crate) fn cache_name(location: &str) -> String {
+    let hash = location.bytes().fold(0xcbf29ce484222325u64, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    });
+    let name = location.rsplit('/').next().unwrap_or_default();
+    let stem = name.strip_suffix(".hprof").unwrap_or(name);
+    format!("{:016x}-{}.hprof", hash, stem)
+}
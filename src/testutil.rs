@@ -0,0 +1,303 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Result, bail};
+
+use crate::{
+    analyzer::{graph::RootKind, prim_array_name},
+    parser::{
+        FrameId, Header, Id, Record, StringId, Timestamp, Version,
+        sub_record::{Field, FieldDescriptor, FieldValue, PrimArray, SubRecord},
+        timestamp_from_millis,
+    },
+    writer::RecordWriter,
+};
+
+// ids handed out by the builder, aligned like real object addresses
+const FIRST_ID: u64 = 0x1000;
+
+// builds small, valid hprof files in memory for tests and fuzzing. instance field values are
+// given in dump order: the fields of the class itself first, then those of each superclass.
+pub struct HeapBuilder {
+    timestamp: Timestamp,
+    next_id: u64,
+    next_serial: u32,
+    strings: HashMap<String, StringId>,
+    records: Vec<Record>,
+    classes: Vec<SubRecord>,
+    roots: Vec<SubRecord>,
+    objects: Vec<SubRecord>,
+    prim_array_classes: HashMap<u8, Id>,
+}
+
+impl Default for HeapBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeapBuilder {
+    pub fn new() -> Self {
+        Self {
+            timestamp: timestamp_from_millis(0).unwrap(),
+            next_id: FIRST_ID,
+            next_serial: 1,
+            strings: HashMap::new(),
+            records: Vec::new(),
+            classes: Vec::new(),
+            roots: Vec::new(),
+            objects: Vec::new(),
+            prim_array_classes: HashMap::new(),
+        }
+    }
+
+    pub fn timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    // interned, every distinct string gets one utf8 record
+    pub fn string(&mut self, content: &str) -> StringId {
+        if let Some(id) = self.strings.get(content) {
+            return *id;
+        }
+
+        let id = StringId(self.id().0);
+        self.strings.insert(content.to_string(), id);
+        self.records.push(Record::Utf8 {
+            micros: 0,
+            name_id: id,
+            content: content.to_string(),
+        });
+        id
+    }
+
+    // name in internal form like "java/lang/String", fields as (name, basic type) pairs
+    pub fn class(&mut self, name: &str, super_class: Option<Id>, fields: &[(&str, u8)]) -> Id {
+        self.class_with_statics(name, super_class, fields, Vec::new())
+    }
+
+    pub fn class_with_statics(
+        &mut self,
+        name: &str,
+        super_class: Option<Id>,
+        fields: &[(&str, u8)],
+        statics: Vec<(&str, FieldValue)>,
+    ) -> Id {
+        let class_id = self.load_class(name);
+
+        let static_fields: Vec<Field> = statics
+            .into_iter()
+            .map(|(name, value)| Field {
+                name_id: self.string(name),
+                value,
+            })
+            .collect();
+        let instance_field_descriptors: Vec<FieldDescriptor> = fields
+            .iter()
+            .map(|(name, typ)| FieldDescriptor {
+                name_id: self.string(name),
+                typ: *typ,
+            })
+            .collect();
+
+        self.classes.push(SubRecord::ClassDump {
+            class_object_id: class_id,
+            stack_trace_serial_number: 0,
+            super_class_object_id: super_class.unwrap_or(Id(0)),
+            class_loader_object_id: Id(0),
+            signers_object_id: Id(0),
+            protection_domain_object_id: Id(0),
+            reserved1: 0,
+            reserved2: 0,
+            instance_size: 0,
+            constant_pool_size: 0,
+            number_of_static_fields: static_fields.len() as u16,
+            static_fields,
+            number_of_instance_fields: instance_field_descriptors.len() as u16,
+            instance_field_descriptors,
+        });
+        class_id
+    }
+
+    // loaded by the given class loader object instead of the bootstrap loader
+    pub fn class_loader(&mut self, class: Id, loader: Id) {
+        for record in &mut self.classes {
+            if let SubRecord::ClassDump {
+                class_object_id,
+                class_loader_object_id,
+                ..
+            } = record
+                && *class_object_id == class
+            {
+                *class_loader_object_id = loader;
+            }
+        }
+    }
+
+    pub fn instance(&mut self, class: Id, values: &[FieldValue]) -> Id {
+        let mut raw_field_bytes = Vec::new();
+        for value in values {
+            encode_value(&mut raw_field_bytes, value);
+        }
+
+        let object_id = self.id();
+        self.objects.push(SubRecord::InstanceDump {
+            object_id,
+            stack_trace_serial_number: 0,
+            class_object_id: class,
+            number_of_bytes: raw_field_bytes.len() as u32,
+            raw_field_bytes,
+        });
+        object_id
+    }
+
+    // array_class is a class named like "[Ljava/lang/String;", null elements are Id(0)
+    pub fn object_array(&mut self, array_class: Id, elements: &[Id]) -> Id {
+        let object_id = self.id();
+        self.objects.push(SubRecord::ObjArrayDump {
+            object_id,
+            stack_trace_serial_number: 0,
+            array_class_id: array_class,
+            elements: elements.to_vec(),
+        });
+        object_id
+    }
+
+    // the array class is loaded on first use
+    pub fn prim_array(&mut self, elements: PrimArray) -> Result<Id> {
+        let typ = elements.typ();
+        if !self.prim_array_classes.contains_key(&typ) {
+            let class_id = self.load_class(prim_array_name(typ)?);
+            self.prim_array_classes.insert(typ, class_id);
+        }
+
+        let object_id = self.id();
+        self.objects.push(SubRecord::PrimArrayDump {
+            object_id,
+            stack_trace_serial_number: 0,
+            typ,
+            elements,
+        });
+        Ok(object_id)
+    }
+
+    // thread objects get a stack trace through thread()
+    pub fn root(&mut self, kind: RootKind, object_id: Id) -> Result<()> {
+        let root = match kind {
+            RootKind::JniGlobal => SubRecord::JniGlobal {
+                object_id,
+                global_ref_id: Id(0),
+            },
+            RootKind::JniLocal => SubRecord::JniLocal {
+                object_id,
+                thread_serial_number: 0,
+                frame_number: 0,
+            },
+            RootKind::JavaFrame => SubRecord::JavaFrame {
+                object_id,
+                thread_serial_number: 0,
+                frame_number: 0,
+            },
+            RootKind::StickyClass => SubRecord::StickyClass { object_id },
+            RootKind::ThreadObject => bail!("thread roots are added with thread()"),
+        };
+
+        self.roots.push(root);
+        Ok(())
+    }
+
+    // frames as (method, signature, source file, line), innermost first. returns the thread serial
+    pub fn thread(&mut self, object_id: Id, frames: &[(&str, &str, &str, i32)]) -> u32 {
+        let mut stack_frame_ids = Vec::new();
+        for (method, signature, source_file, line) in frames {
+            let stack_frame_id = FrameId(self.id().0);
+            let frame = Record::Frame {
+                micros: 0,
+                stack_frame_id,
+                method_name_id: self.string(method),
+                method_signature_id: self.string(signature),
+                source_file_name_id: self.string(source_file),
+                class_serial_number: 0,
+                line_number: *line,
+            };
+            self.records.push(frame);
+            stack_frame_ids.push(stack_frame_id);
+        }
+
+        let serial = self.serial();
+        let stack_trace_serial_number = self.serial();
+        self.records.push(Record::Trace {
+            micros: 0,
+            stack_trace_serial_number,
+            thread_serial_number: serial,
+            stack_frame_ids,
+        });
+        self.roots.push(SubRecord::ThreadObj {
+            object_id,
+            sequence_number: serial,
+            stack_trace_sequence_number: stack_trace_serial_number,
+        });
+        serial
+    }
+
+    pub fn build(self) -> Result<Vec<u8>> {
+        let header = Header {
+            version: Version::JavaProfile102,
+            timestamp: self.timestamp,
+        };
+        let mut writer = RecordWriter::new(Vec::new(), &header)?;
+
+        for record in &self.records {
+            writer.write_record(record)?;
+        }
+
+        let mut sub_records = self.classes;
+        sub_records.extend(self.roots);
+        sub_records.extend(self.objects);
+        // the parser expects at least one sub record per segment
+        if !sub_records.is_empty() {
+            writer.write_record(&Record::HeapDumpSegment {
+                micros: 0,
+                sub_records,
+            })?;
+        }
+        writer.write_record(&Record::HeapDumpEnd { micros: 0 })?;
+
+        writer.finish()
+    }
+
+    pub fn write(self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.build()?)?;
+        Ok(())
+    }
+
+    fn load_class(&mut self, name: &str) -> Id {
+        let class_name_id = self.string(name);
+        let class_object_id = self.id();
+        let class_serial_number = self.serial();
+        self.records.push(Record::LoadClass {
+            micros: 0,
+            class_serial_number,
+            class_object_id,
+            stack_trace_serial_number: 0,
+            class_name_id,
+        });
+        class_object_id
+    }
+
+    fn id(&mut self) -> Id {
+        let id = Id(self.next_id);
+        self.next_id += 8;
+        id
+    }
+
+    fn serial(&mut self) -> u32 {
+        let serial = self.next_serial;
+        self.next_serial += 1;
+        serial
+    }
+}
+
+fn encode_value(buf: &mut Vec<u8>, value: &FieldValue) {
+    value.write_be(buf);
+}
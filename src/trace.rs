@@ -0,0 +1,77 @@
+// tracing is optional for embedders. without the tracing feature spans and events compile to
+// nothing, their fields and format arguments are still type checked
+#[cfg(feature = "tracing")]
+pub(crate) use tracing::{debug, debug_span, field::Empty, info, warn};
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) use noop::{Empty, debug, debug_span, metric, warn};
+// only downloads, captures and the grpc service report progress
+#[cfg(all(
+    not(feature = "tracing"),
+    any(all(feature = "remote", not(target_arch = "wasm32")), feature = "grpc")
+))]
+pub(crate) use noop::info;
+
+// target of the events with throughput numbers, `heapdump-analyzer --time` prints them. they
+// show up in logs with RUST_LOG=heapdump_analyzer::metrics=info
+pub const METRICS_TARGET: &str = "heapdump_analyzer::metrics";
+
+// an info event under METRICS_TARGET with the phase it measures and numeric fields:
+//
+//   metric!("parse", bytes = len, records = count);
+#[cfg(feature = "tracing")]
+macro_rules! metric {
+    ($phase:literal $(, $field:ident = $value:expr)* $(,)?) => {
+        tracing::info!(
+            target: $crate::trace::METRICS_TARGET,
+            phase = $phase
+            $(, $field = $value)*
+        )
+    };
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) use metric;
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) mod noop {
+    pub(crate) struct Span;
+
+    impl Span {
+        pub(crate) fn entered(self) -> Self {
+            self
+        }
+
+        pub(crate) fn record<V>(&self, _field: &str, _value: V) -> &Self {
+            self
+        }
+    }
+
+    // stands in for tracing::field::Empty in span fields
+    pub(crate) struct Empty;
+
+    macro_rules! debug_span {
+        ($name:literal $(, $field:ident = $(%)? $value:expr)* $(,)?) => {{
+            $(let _ = &$value;)*
+            $crate::trace::noop::Span
+        }};
+    }
+
+    macro_rules! event {
+        ($($arg:tt)+) => {
+            if false {
+                let _ = format_args!($($arg)+);
+            }
+        };
+    }
+
+    macro_rules! metric {
+        ($phase:literal $(, $field:ident = $value:expr)* $(,)?) => {{
+            $(let _ = &$value;)*
+        }};
+    }
+
+    #[cfg(any(all(feature = "remote", not(target_arch = "wasm32")), feature = "grpc"))]
+    pub(crate) use event as info;
+    pub(crate) use {debug_span, event as debug, event as warn, metric};
+}
@@ -0,0 +1,3 @@
+pub mod scrub;
+pub mod slice;
+pub mod split;
@@ -0,0 +1,209 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use anyhow::Result;
+
+use crate::{
+    parser::{
+        ClassId, Id, Record, RecordReader, StringId,
+        borrowed::{BorrowedRecord, BorrowedSubRecord, MappedDump},
+        select::{ClassDump, FromSubRecord},
+        sub_record::{FieldDescriptor, PrimArray, SubRecord},
+    },
+    writer::RecordWriter,
+};
+
+const PLACEHOLDER: u8 = b'x';
+
+#[derive(Debug, Default)]
+pub struct ScrubStats {
+    pub strings: u64,
+    pub arrays: u64,
+}
+
+// rewrites a dump with the values of java.lang.String instances and the string table entries
+// that don't name a class, field or method replaced by placeholders of the same length, so
+// sizes, the object graph and the names tools look classes up by stay intact
+pub fn scrub(input: &Path, output: &Path) -> Result<ScrubStats> {
+    let dump = MappedDump::open(input)?;
+    let names = Names::collect(&dump)?;
+    let values = string_values(&dump, &names)?;
+
+    let records = RecordReader::open(input)?;
+    let mut writer = RecordWriter::create(output, &records.header)?;
+    let mut stats = ScrubStats::default();
+
+    for record in records {
+        let mut record = record?;
+        match &mut record {
+            Record::Utf8 {
+                name_id, content, ..
+            } if !names.kept.contains(name_id) => {
+                *content = String::from_utf8(vec![PLACEHOLDER; content.len()])?;
+                stats.strings += 1;
+            }
+            Record::HeapDumpSegment { sub_records, .. } => {
+                for sub_record in sub_records {
+                    if let SubRecord::PrimArrayDump {
+                        object_id,
+                        elements,
+                        ..
+                    } = sub_record
+                        && values.contains(object_id)
+                        && scrub_elements(elements)
+                    {
+                        stats.arrays += 1;
+                    }
+                }
+            }
+            _ => {}
+        }
+        writer.write_record(&record)?;
+    }
+
+    writer.finish()?;
+    Ok(stats)
+}
+
+// what the string table is used for, records can come in any order so they are only put together
+// once the whole dump was seen
+#[derive(Default)]
+struct Names {
+    // names of classes, fields and methods, and source files of frames
+    kept: HashSet<StringId>,
+    // of every loaded class
+    classes: Vec<(ClassId, StringId)>,
+    // ids of utf8 records reading "java/lang/String" or "value"
+    string: HashSet<StringId>,
+    value: HashSet<StringId>,
+    // instance fields of every class dump
+    fields: HashMap<ClassId, Vec<FieldDescriptor>>,
+}
+
+impl Names {
+    fn collect(dump: &MappedDump) -> Result<Self> {
+        let mut names = Self::default();
+        for record in dump.records()? {
+            match record? {
+                BorrowedRecord::Utf8 {
+                    name_id, content, ..
+                } => match &*content {
+                    "java/lang/String" => {
+                        names.string.insert(name_id);
+                    }
+                    "value" => {
+                        names.value.insert(name_id);
+                    }
+                    _ => {}
+                },
+                BorrowedRecord::Other(Record::LoadClass {
+                    class_object_id,
+                    class_name_id,
+                    ..
+                }) => {
+                    names.kept.insert(class_name_id);
+                    names.classes.push((class_object_id, class_name_id));
+                }
+                BorrowedRecord::Other(Record::Frame {
+                    method_name_id,
+                    method_signature_id,
+                    source_file_name_id,
+                    ..
+                }) => {
+                    names
+                        .kept
+                        .extend([method_name_id, method_signature_id, source_file_name_id]);
+                }
+                BorrowedRecord::HeapDumpSegment { sub_records, .. } => {
+                    for sub_record in sub_records {
+                        if let BorrowedSubRecord::Other(sub_record) = sub_record?
+                            && let Some(class) = ClassDump::from_sub_record(&sub_record)
+                        {
+                            names
+                                .kept
+                                .extend(class.static_fields.iter().map(|f| f.name_id));
+                            names
+                                .kept
+                                .extend(class.instance_fields.iter().map(|f| f.name_id));
+                            names
+                                .fields
+                                .insert(class.class_object_id, class.instance_fields.to_vec());
+                        }
+                    }
+                }
+                BorrowedRecord::Other(_) => {}
+            }
+        }
+        Ok(names)
+    }
+
+    // offset of the value field in the instance dumps of each java.lang.String class. a
+    // string's own fields come first, before those of its superclasses
+    fn value_offsets(&self, id_size: u32) -> Result<HashMap<ClassId, usize>> {
+        let mut offsets = HashMap::new();
+        for (class_id, name_id) in &self.classes {
+            if !self.string.contains(name_id) {
+                continue;
+            }
+            let Some(fields) = self.fields.get(class_id) else {
+                continue;
+            };
+            let mut offset = 0;
+            for field in fields {
+                if field.typ == 2 && self.value.contains(&field.name_id) {
+                    offsets.insert(*class_id, offset);
+                    break;
+                }
+                offset += match field.typ {
+                    2 => id_size as usize,
+                    typ => PrimArray::element_size(typ)?,
+                };
+            }
+        }
+        Ok(offsets)
+    }
+}
+
+// the arrays referenced by the value field of java.lang.String instances
+fn string_values(dump: &MappedDump, names: &Names) -> Result<HashSet<Id>> {
+    let records = dump.records()?;
+    let id_size = records.ctx.id_size;
+    let offsets = names.value_offsets(id_size)?;
+    let mut values = HashSet::new();
+
+    for record in records {
+        let BorrowedRecord::HeapDumpSegment { sub_records, .. } = record? else {
+            continue;
+        };
+        for sub_record in sub_records {
+            if let BorrowedSubRecord::InstanceDump {
+                class_object_id,
+                raw_field_bytes,
+                ..
+            } = sub_record?
+                && let Some(offset) = offsets.get(&class_object_id)
+                && let Some(id) = raw_field_bytes.get(*offset..*offset + id_size as usize)
+            {
+                let id = match id_size {
+                    4 => u32::from_be_bytes(id.try_into()?) as u64,
+                    _ => u64::from_be_bytes(id.try_into()?),
+                };
+                values.insert(Id(id));
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+// strings store their value in byte arrays, or char arrays before java 9
+fn scrub_elements(elements: &mut PrimArray) -> bool {
+    match elements {
+        PrimArray::Byte(bytes) => bytes.fill(PLACEHOLDER as i8),
+        PrimArray::Char(chars) => chars.fill(PLACEHOLDER as u16),
+        _ => return false,
+    }
+    !elements.is_empty()
+}
@@ -0,0 +1,244 @@
+use std::{collections::HashSet, path::Path};
+
+use anyhow::{Result, bail};
+
+use crate::{
+    analyzer::{
+        AnalyzedHeap,
+        options::AnalysisOptions,
+        walk::{Limits, Truncation, Walk, walk},
+    },
+    parser::{
+        Id, Record, StringId,
+        borrowed::{BorrowedRecord, BorrowedSubRecord, MappedDump},
+        select::{ClassDump, FromSubRecord, Root},
+        sub_record::SubRecord,
+    },
+    writer::RecordWriter,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Closure {
+    // only the given objects
+    None,
+    // everything reachable from the given objects, without following class statics
+    Reachable,
+    // the given objects and everything they dominate
+    Retained,
+}
+
+#[derive(Debug)]
+pub struct SliceStats {
+    pub objects: usize,
+    pub classes: usize,
+    // the closure was cut short by the limits
+    pub truncated: Option<Truncation>,
+}
+
+// writes a dump containing the selected objects, their classes with superclasses and the strings
+// naming them. the dump is analyzed for the closure, then copied from its mapping with whatever
+// isn't part of the slice left out. gc roots of the objects in the slice are kept, selected objects
+// that weren't roots become jni global roots, references leaving the slice dangle. the closure
+// stops at the limits, the selected objects are always included
+pub fn slice(
+    input: &Path,
+    output: &Path,
+    ids: &[Id],
+    closure: Closure,
+    limits: &Limits,
+    options: &AnalysisOptions,
+) -> Result<SliceStats> {
+    let (_, heap) = AnalyzedHeap::analyze_file_with(input, options)?;
+
+    for id in ids {
+        if heap.instance(*id).is_none() && !heap.classes.contains_key(id) {
+            bail!("object {} not found", id);
+        }
+    }
+
+    let (objects, truncated): (HashSet<Id>, _) = match closure {
+        Closure::None => (ids.iter().copied().collect(), None),
+        Closure::Reachable => closure_ids(&heap, ids, reachable(&heap, ids, limits)),
+        Closure::Retained => closure_ids(&heap, ids, retained(&heap, ids, limits)),
+    };
+
+    let mut classes: HashSet<Id> = objects
+        .iter()
+        .filter_map(|id| match heap.instance(*id) {
+            Some(instance) => Some(instance.class.id),
+            None => heap.classes.contains_key(id).then_some(*id),
+        })
+        .collect();
+    let mut pending: Vec<Id> = classes.iter().copied().collect();
+    while let Some(class_id) = pending.pop() {
+        if let Some(super_class_id) = heap.layouts.get(&class_id).and_then(|l| l.super_class_id)
+            && classes.insert(super_class_id)
+        {
+            pending.push(super_class_id);
+        }
+    }
+    drop(heap);
+
+    let dump = MappedDump::open(input)?;
+    let names = required_names(&dump, &classes)?;
+    let records = dump.records()?;
+    let mut writer = RecordWriter::create(output, &records.header)?;
+    let mut rooted: HashSet<Id> = HashSet::new();
+    let mut end = None;
+
+    for record in records {
+        match record? {
+            BorrowedRecord::Utf8 {
+                micros,
+                name_id,
+                content,
+            } => {
+                if names.contains(&name_id) {
+                    writer.write_record(&Record::Utf8 {
+                        micros,
+                        name_id,
+                        content: content.into_owned(),
+                    })?;
+                }
+            }
+            BorrowedRecord::HeapDumpSegment {
+                micros,
+                sub_records,
+            } => {
+                let mut kept: Vec<SubRecord> = Vec::new();
+                for sub_record in sub_records {
+                    let sub_record = sub_record?;
+                    let keep = match &sub_record {
+                        BorrowedSubRecord::InstanceDump { object_id, .. }
+                        | BorrowedSubRecord::ObjArrayDump { object_id, .. }
+                        | BorrowedSubRecord::PrimArrayDump { object_id, .. } => {
+                            objects.contains(object_id)
+                        }
+                        BorrowedSubRecord::Other(sub_record) => {
+                            if let Some(class) = ClassDump::from_sub_record(sub_record) {
+                                classes.contains(&class.class_object_id)
+                            } else if let Some(root) = Root::from_sub_record(sub_record) {
+                                let keep = objects.contains(&root.object_id)
+                                    || classes.contains(&root.object_id);
+                                if keep {
+                                    rooted.insert(root.object_id);
+                                }
+                                keep
+                            } else {
+                                false
+                            }
+                        }
+                    };
+                    if keep {
+                        kept.push(sub_record.into_owned()?);
+                    }
+                }
+
+                // the parser expects at least one sub record per segment
+                if !kept.is_empty() {
+                    writer.write_record(&Record::HeapDumpSegment {
+                        micros,
+                        sub_records: kept,
+                    })?;
+                }
+            }
+            BorrowedRecord::Other(record) => match record {
+                Record::LoadClass {
+                    class_object_id, ..
+                } if !classes.contains(&class_object_id) => {}
+                // stack traces would reference threads that aren't part of the slice
+                Record::Frame { .. } | Record::Trace { .. } => {}
+                Record::HeapDumpEnd { micros } => end = Some(micros),
+                record => writer.write_record(&record)?,
+            },
+        }
+    }
+
+    // without a root, the selected objects would be garbage in the slice
+    let roots: Vec<SubRecord> = ids
+        .iter()
+        .filter(|id| !rooted.contains(id))
+        .map(|id| SubRecord::JniGlobal {
+            object_id: *id,
+            global_ref_id: Id(0),
+        })
+        .collect();
+    if !roots.is_empty() {
+        writer.write_record(&Record::HeapDumpSegment {
+            micros: end.unwrap_or(0),
+            sub_records: roots,
+        })?;
+    }
+    if let Some(micros) = end {
+        writer.write_record(&Record::HeapDumpEnd { micros })?;
+    }
+
+    writer.finish()?;
+    Ok(SliceStats {
+        objects: objects.len(),
+        classes: classes.len(),
+        truncated,
+    })
+}
+
+// class objects aren't entered, their statics would pull in most of the heap
+fn reachable(heap: &AnalyzedHeap, ids: &[Id], limits: &Limits) -> Walk {
+    let from = ids.iter().filter_map(|id| heap.handle(*id));
+    walk(heap, from, limits, move |handle| {
+        heap.references
+            .get(handle)
+            .iter()
+            .filter_map(move |id| heap.handle(*id))
+            .filter(move |h| h.index() < heap.instances.len())
+    })
+}
+
+fn retained(heap: &AnalyzedHeap, ids: &[Id], limits: &Limits) -> Walk {
+    let children = &heap.dominator_tree().children();
+    let from = ids.iter().filter_map(|id| heap.handle(*id));
+    walk(heap, from, limits, move |handle| {
+        children
+            .get(&heap.handles.id(handle))
+            .into_iter()
+            .flatten()
+            .filter_map(move |id| heap.handle(*id))
+    })
+}
+
+// the selected objects and what the walk visited
+fn closure_ids(heap: &AnalyzedHeap, ids: &[Id], walk: Walk) -> (HashSet<Id>, Option<Truncation>) {
+    let mut objects: HashSet<Id> = ids.iter().copied().collect();
+    objects.extend(walk.objects.iter().map(|h| heap.handles.id(*h)));
+    (objects, walk.truncated)
+}
+
+// class names plus the names of their static and instance fields
+fn required_names(dump: &MappedDump, classes: &HashSet<Id>) -> Result<HashSet<StringId>> {
+    let mut names = HashSet::new();
+
+    for record in dump.records()? {
+        match record? {
+            BorrowedRecord::Other(Record::LoadClass {
+                class_object_id,
+                class_name_id,
+                ..
+            }) if classes.contains(&class_object_id) => {
+                names.insert(class_name_id);
+            }
+            BorrowedRecord::HeapDumpSegment { sub_records, .. } => {
+                for sub_record in sub_records {
+                    if let BorrowedSubRecord::Other(sub_record) = sub_record?
+                        && let Some(class) = ClassDump::from_sub_record(&sub_record)
+                        && classes.contains(&class.class_object_id)
+                    {
+                        names.extend(class.static_fields.iter().map(|f| f.name_id));
+                        names.extend(class.instance_fields.iter().map(|f| f.name_id));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(names)
+}
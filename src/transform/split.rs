@@ -0,0 +1,199 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::parser::RecordReader;
+
+// version string with its nul terminator, id size and timestamp
+const HEADER_SIZE: usize = 19 + 4 + 8;
+const HEAP_DUMP_SEGMENT: u8 = 0x1c;
+// tag, micros and a zero length
+const HEAP_DUMP_END: [u8; 9] = [0x2c, 0, 0, 0, 0, 0, 0, 0, 0];
+pub const MANIFEST: &str = "manifest.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub source: String,
+    pub parts: Vec<Part>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Part {
+    pub file: String,
+    // file size including the copied header
+    pub size: u64,
+    pub records: u64,
+    pub segment: bool,
+    // a heap dump end record was appended so the part parses on its own, merge drops it
+    #[serde(default)]
+    pub end_added: bool,
+}
+
+// splits a dump into one file per heap dump segment, with everything before the first segment
+// and after the last one in separate parts. records are copied byte for byte, every part starts
+// with the dump's header and ends with a heap dump end record, so each part is a dump of its
+// own. merge tells parts of different dumps apart by their headers.
+pub fn split(input: &Path, out_dir: &Path) -> Result<Manifest> {
+    // validates the header
+    RecordReader::open(input)?;
+
+    let mut r = BufReader::with_capacity(
+        1 << 20,
+        File::open(input).with_context(|| format!("failed to open {}", input.display()))?,
+    );
+    let mut header = [0; HEADER_SIZE];
+    r.read_exact(&mut header)?;
+
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("failed to create {}", out_dir.display()))?;
+    let stem = input
+        .file_stem()
+        .context("dump without file name")?
+        .to_string_lossy()
+        .to_string();
+
+    let mut manifest = Manifest {
+        source: input
+            .file_name()
+            .context("dump without file name")?
+            .to_string_lossy()
+            .to_string(),
+        parts: Vec::new(),
+    };
+    // with the tag of the last record copied into the part
+    let mut current: Option<(BufWriter<File>, Part, u8)> = None;
+
+    loop {
+        let mut record_header = [0; 9];
+        match r.read_exact(&mut record_header) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err.into()),
+        }
+        let tag = record_header[0];
+        let length = u32::from_be_bytes(record_header[5..9].try_into()?) as u64;
+
+        // segments always get a part of their own
+        let starts_part =
+            tag == HEAP_DUMP_SEGMENT || current.as_ref().is_none_or(|(_, part, _)| part.segment);
+        if starts_part {
+            if let Some((w, part, last_tag)) = current.take() {
+                finish_part(w, part, last_tag, &mut manifest)?;
+            }
+            let file = format!("{}.{:04}.part", stem, manifest.parts.len());
+            let mut w = BufWriter::new(File::create(out_dir.join(&file))?);
+            w.write_all(&header)?;
+            current = Some((
+                w,
+                Part {
+                    file,
+                    size: HEADER_SIZE as u64,
+                    records: 0,
+                    segment: tag == HEAP_DUMP_SEGMENT,
+                    end_added: false,
+                },
+                tag,
+            ));
+        }
+
+        let (w, part, last_tag) = current.as_mut().context("no open part")?;
+        w.write_all(&record_header)?;
+        let copied = std::io::copy(&mut (&mut r).take(length), w)?;
+        if copied != length {
+            bail!("truncated record at the end of {}", input.display());
+        }
+        part.size += record_header.len() as u64 + length;
+        part.records += 1;
+        *last_tag = tag;
+    }
+
+    if let Some((w, part, last_tag)) = current.take() {
+        finish_part(w, part, last_tag, &mut manifest)?;
+    }
+
+    let mut w = BufWriter::new(File::create(out_dir.join(MANIFEST))?);
+    serde_json::to_writer_pretty(&mut w, &manifest)?;
+    writeln!(w)?;
+    w.flush()?;
+
+    Ok(manifest)
+}
+
+// concatenates the parts listed in the manifest without the heap dump end records split appended,
+// the result is identical to the split dump
+pub fn merge(dir: &Path, output: &Path) -> Result<Manifest> {
+    let manifest_path = dir.join(MANIFEST);
+    let manifest: Manifest = serde_json::from_reader(BufReader::new(
+        File::open(&manifest_path)
+            .with_context(|| format!("failed to open {}", manifest_path.display()))?,
+    ))?;
+
+    let mut w = BufWriter::with_capacity(
+        1 << 20,
+        File::create(output).with_context(|| format!("failed to create {}", output.display()))?,
+    );
+    let mut first_header: Option<[u8; HEADER_SIZE]> = None;
+
+    for part in &manifest.parts {
+        let path = dir.join(&part.file);
+        let file =
+            File::open(&path).with_context(|| format!("failed to open {}", path.display()))?;
+        let size = file.metadata()?.len();
+        if size != part.size {
+            bail!(
+                "{} has {} bytes, the manifest expects {}",
+                path.display(),
+                size,
+                part.size
+            );
+        }
+
+        let mut r = BufReader::with_capacity(1 << 20, file);
+        let mut header = [0; HEADER_SIZE];
+        r.read_exact(&mut header)?;
+        match first_header {
+            None => {
+                w.write_all(&header)?;
+                first_header = Some(header);
+            }
+            Some(first) if first != header => {
+                bail!("{} belongs to a different dump", path.display())
+            }
+            Some(_) => {}
+        }
+
+        let added = if part.end_added {
+            HEAP_DUMP_END.len() as u64
+        } else {
+            0
+        };
+        std::io::copy(
+            &mut (&mut r).take(part.size.saturating_sub(HEADER_SIZE as u64 + added)),
+            &mut w,
+        )?;
+    }
+
+    w.flush()?;
+    Ok(manifest)
+}
+
+fn finish_part(
+    mut w: BufWriter<File>,
+    mut part: Part,
+    last_tag: u8,
+    manifest: &mut Manifest,
+) -> Result<()> {
+    if last_tag != HEAP_DUMP_END[0] {
+        w.write_all(&HEAP_DUMP_END)?;
+        part.size += HEAP_DUMP_END.len() as u64;
+        part.end_added = true;
+    }
+    w.flush()?;
+    manifest.parts.push(part);
+    Ok(())
+}
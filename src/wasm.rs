@@ -0,0 +1,133 @@
+use std::io::Cursor;
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    analyzer::{
+        AnalyzedHeap, filter::ClassFilter, handle::Handle, size::SizeModel, storage::Storage,
+    },
+    parser::{Id, RecordReader},
+};
+
+// javascript bindings for a client side viewer. ids are passed as "0x..." strings since they
+// don't fit into a javascript number
+#[wasm_bindgen]
+pub struct Heap {
+    heap: AnalyzedHeap,
+}
+
+#[derive(Serialize)]
+struct HistogramRow {
+    class: String,
+    instance_count: u64,
+    shallow_size: u64,
+}
+
+#[derive(Serialize)]
+struct ObjectInfo {
+    id: String,
+    class: String,
+    shallow_size: u64,
+    retained_size: Option<u64>,
+    references: Vec<String>,
+    referrers: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct PathElement {
+    id: String,
+    class: String,
+}
+
+#[wasm_bindgen]
+impl Heap {
+    // records are analyzed as they are parsed, the bytes of an ArrayBuffer are expected wrapped
+    // in a Uint8Array
+    pub fn parse(bytes: &[u8]) -> Result<Heap, JsError> {
+        let records = RecordReader::new(Cursor::new(bytes)).map_err(js_error)?;
+        let heap = AnalyzedHeap::analyze_stream(records, SizeModel::default(), &Storage::Memory)
+            .map_err(js_error)?;
+        Ok(Heap { heap })
+    }
+
+    // rows of class, instance_count and shallow_size, largest first. the filter is an optional
+    // object with include and exclude lists of class name prefixes
+    pub fn histogram(&self, filter: JsValue) -> Result<JsValue, JsError> {
+        let filter: ClassFilter = if filter.is_undefined() || filter.is_null() {
+            ClassFilter::default()
+        } else {
+            serde_wasm_bindgen::from_value(filter)?
+        };
+
+        let rows: Vec<HistogramRow> = self
+            .heap
+            .histogram(&filter)
+            .into_iter()
+            .map(|e| HistogramRow {
+                class: e.class.java_name(),
+                instance_count: e.instance_count,
+                shallow_size: e.shallow_size,
+            })
+            .collect();
+        Ok(serde_wasm_bindgen::to_value(&rows)?)
+    }
+
+    // the first call computes the dominator tree for the retained size
+    pub fn inspect(&self, id: &str) -> Result<JsValue, JsError> {
+        let handle = self.lookup(id)?;
+        let id = self.heap.handles.id(handle);
+
+        let info = ObjectInfo {
+            id: id.to_string(),
+            class: self.class_name(handle),
+            shallow_size: self.heap.instance(id).map_or(0, |i| i.shallow_size),
+            retained_size: self.heap.dominator_tree().retained_size(id),
+            references: self
+                .heap
+                .references
+                .get(handle)
+                .iter()
+                .map(|id| id.to_string())
+                .collect(),
+            referrers: self
+                .heap
+                .referrers(handle)
+                .map(|h| self.heap.handles.id(h).to_string())
+                .collect(),
+        };
+        Ok(serde_wasm_bindgen::to_value(&info)?)
+    }
+
+    // shortest path from a gc root to the object, root first. null when it is unreachable
+    pub fn paths(&self, id: &str) -> Result<JsValue, JsError> {
+        let handle = self.lookup(id)?;
+        let path: Option<Vec<PathElement>> = self.heap.path_to_root(handle).map(|path| {
+            path.into_iter()
+                .map(|h| PathElement {
+                    id: self.heap.handles.id(h).to_string(),
+                    class: self.class_name(h),
+                })
+                .collect()
+        });
+        Ok(serde_wasm_bindgen::to_value(&path)?)
+    }
+}
+
+impl Heap {
+    fn lookup(&self, id: &str) -> Result<Handle, JsError> {
+        let parsed: Id = id.parse().map_err(js_error)?;
+        self.heap
+            .handle(parsed)
+            .ok_or_else(|| JsError::new(&format!("object {} not found", id)))
+    }
+
+    fn class_name(&self, handle: Handle) -> String {
+        let id = self.heap.handles.id(handle);
+        self.heap.class_name_of(id).unwrap_or_default()
+    }
+}
+
+fn js_error(err: impl Into<anyhow::Error>) -> JsError {
+    JsError::new(&format!("{:#}", err.into()))
+}
@@ -0,0 +1,131 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+
+use crate::parser::{Header, Record, timestamp_millis};
+
+mod sub_record;
+
+// writes records in the same layout the parser reads, with 8 byte ids
+pub struct RecordWriter<W: Write> {
+    w: W,
+}
+
+impl RecordWriter<BufWriter<File>> {
+    pub fn create(path: &Path, header: &Header) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+        Self::new(BufWriter::with_capacity(1 << 20, file), header)
+    }
+}
+
+impl<W: Write> RecordWriter<W> {
+    pub fn new(mut w: W, header: &Header) -> Result<Self> {
+        w.write_all(header.version.to_string().as_bytes())?;
+        w.write_all(&[0])?;
+        w.write_all(&8u32.to_be_bytes())?;
+        w.write_all(&timestamp_millis(&header.timestamp).to_be_bytes())?;
+        Ok(Self { w })
+    }
+
+    pub fn write_record(&mut self, record: &Record) -> Result<()> {
+        let mut body = Vec::new();
+        let (tag, micros) = match record {
+            Record::Utf8 {
+                micros,
+                name_id,
+                content,
+            } => {
+                write_id(&mut body, *name_id);
+                write_utf8(&mut body, content);
+                (0x01, micros)
+            }
+            Record::LoadClass {
+                micros,
+                class_serial_number,
+                class_object_id,
+                stack_trace_serial_number,
+                class_name_id,
+            } => {
+                body.extend(class_serial_number.to_be_bytes());
+                write_id(&mut body, *class_object_id);
+                body.extend(stack_trace_serial_number.to_be_bytes());
+                write_id(&mut body, *class_name_id);
+                (0x02, micros)
+            }
+            Record::Frame {
+                micros,
+                stack_frame_id,
+                method_name_id,
+                method_signature_id,
+                source_file_name_id,
+                class_serial_number,
+                line_number,
+            } => {
+                write_id(&mut body, *stack_frame_id);
+                write_id(&mut body, *method_name_id);
+                write_id(&mut body, *method_signature_id);
+                write_id(&mut body, *source_file_name_id);
+                body.extend(class_serial_number.to_be_bytes());
+                body.extend(line_number.to_be_bytes());
+                (0x04, micros)
+            }
+            Record::Trace {
+                micros,
+                stack_trace_serial_number,
+                thread_serial_number,
+                stack_frame_ids,
+            } => {
+                body.extend(stack_trace_serial_number.to_be_bytes());
+                body.extend(thread_serial_number.to_be_bytes());
+                body.extend((stack_frame_ids.len() as u32).to_be_bytes());
+                for id in stack_frame_ids {
+                    write_id(&mut body, *id);
+                }
+                (0x05, micros)
+            }
+            Record::HeapDumpSegment {
+                micros,
+                sub_records,
+            } => {
+                for sub_record in sub_records {
+                    sub_record::write(&mut body, sub_record)?;
+                }
+                (0x1c, micros)
+            }
+            Record::HeapDumpEnd { micros } => (0x2c, micros),
+        };
+
+        let length = u32::try_from(body.len()).context("record exceeds 4 GiB")?;
+        self.w.write_all(&[tag])?;
+        self.w.write_all(&micros.to_be_bytes())?;
+        self.w.write_all(&length.to_be_bytes())?;
+        self.w.write_all(&body)?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W> {
+        self.w.flush()?;
+        Ok(self.w)
+    }
+}
+
+// object, string and frame ids alike
+fn write_id(buf: &mut Vec<u8>, id: impl Into<u64>) {
+    buf.extend(id.into().to_be_bytes());
+}
+
+// java's modified utf8 encodes nul as two bytes, the parser undoes that
+fn write_utf8(buf: &mut Vec<u8>, content: &str) {
+    for b in content.bytes() {
+        if b == 0 {
+            buf.extend([0xC0, 0x80]);
+        } else {
+            buf.push(b);
+        }
+    }
+}
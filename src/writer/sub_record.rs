@@ -0,0 +1,143 @@
+use anyhow::{Result, bail};
+
+use crate::{
+    parser::sub_record::{FieldValue, SubRecord},
+    writer::write_id,
+};
+
+pub fn write(buf: &mut Vec<u8>, sub_record: &SubRecord) -> Result<()> {
+    match sub_record {
+        SubRecord::JniGlobal {
+            object_id,
+            global_ref_id,
+        } => {
+            buf.push(0x01);
+            write_id(buf, *object_id);
+            write_id(buf, *global_ref_id);
+        }
+        SubRecord::JniLocal {
+            object_id,
+            thread_serial_number,
+            frame_number,
+        } => {
+            buf.push(0x02);
+            write_id(buf, *object_id);
+            buf.extend(thread_serial_number.to_be_bytes());
+            buf.extend(frame_number.to_be_bytes());
+        }
+        SubRecord::JavaFrame {
+            object_id,
+            thread_serial_number,
+            frame_number,
+        } => {
+            buf.push(0x03);
+            write_id(buf, *object_id);
+            buf.extend(thread_serial_number.to_be_bytes());
+            buf.extend(frame_number.to_be_bytes());
+        }
+        SubRecord::StickyClass { object_id } => {
+            buf.push(0x05);
+            write_id(buf, *object_id);
+        }
+        SubRecord::ThreadObj {
+            object_id,
+            sequence_number,
+            stack_trace_sequence_number,
+        } => {
+            buf.push(0x08);
+            write_id(buf, *object_id);
+            buf.extend(sequence_number.to_be_bytes());
+            buf.extend(stack_trace_sequence_number.to_be_bytes());
+        }
+        SubRecord::ClassDump {
+            class_object_id,
+            stack_trace_serial_number,
+            super_class_object_id,
+            class_loader_object_id,
+            signers_object_id,
+            protection_domain_object_id,
+            reserved1,
+            reserved2,
+            instance_size,
+            constant_pool_size,
+            static_fields,
+            instance_field_descriptors,
+            ..
+        } => {
+            buf.push(0x20);
+            write_id(buf, *class_object_id);
+            buf.extend(stack_trace_serial_number.to_be_bytes());
+            write_id(buf, *super_class_object_id);
+            write_id(buf, *class_loader_object_id);
+            write_id(buf, *signers_object_id);
+            write_id(buf, *protection_domain_object_id);
+            buf.extend(reserved1.to_be_bytes());
+            buf.extend(reserved2.to_be_bytes());
+            buf.extend(instance_size.to_be_bytes());
+            buf.extend(constant_pool_size.to_be_bytes());
+
+            // the counts are derived from the vectors so edited records stay consistent
+            buf.extend((static_fields.len() as u16).to_be_bytes());
+            for field in static_fields {
+                write_id(buf, field.name_id);
+                write_field_value(buf, &field.value);
+            }
+
+            buf.extend((instance_field_descriptors.len() as u16).to_be_bytes());
+            for descriptor in instance_field_descriptors {
+                write_id(buf, descriptor.name_id);
+                buf.push(descriptor.typ);
+            }
+        }
+        SubRecord::InstanceDump {
+            object_id,
+            stack_trace_serial_number,
+            class_object_id,
+            raw_field_bytes,
+            ..
+        } => {
+            buf.push(0x21);
+            write_id(buf, *object_id);
+            buf.extend(stack_trace_serial_number.to_be_bytes());
+            write_id(buf, *class_object_id);
+            buf.extend((raw_field_bytes.len() as u32).to_be_bytes());
+            buf.extend(raw_field_bytes);
+        }
+        SubRecord::ObjArrayDump {
+            object_id,
+            stack_trace_serial_number,
+            array_class_id,
+            elements,
+        } => {
+            buf.push(0x22);
+            write_id(buf, *object_id);
+            buf.extend(stack_trace_serial_number.to_be_bytes());
+            buf.extend((elements.len() as u32).to_be_bytes());
+            write_id(buf, *array_class_id);
+            for element in elements {
+                write_id(buf, *element);
+            }
+        }
+        SubRecord::PrimArrayDump {
+            object_id,
+            stack_trace_serial_number,
+            typ,
+            elements,
+        } => {
+            buf.push(0x23);
+            write_id(buf, *object_id);
+            buf.extend(stack_trace_serial_number.to_be_bytes());
+            buf.extend((elements.len() as u32).to_be_bytes());
+            buf.push(*typ);
+            elements.write_be(buf);
+        }
+        SubRecord::HeapDumpEnd => bail!("heap dump end isn't a sub record"),
+    }
+
+    Ok(())
+}
+
+fn write_field_value(buf: &mut Vec<u8>, value: &FieldValue) {
+    buf.push(value.typ());
+    value.write_be(buf);
+}
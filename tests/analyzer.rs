@@ -0,0 +1,162 @@
+use heapdump_analyzer::{
+    Heap,
+    analyzer::{
+        AnalyzedHeap, diagnostic::Diagnostic, size::SizeModel, storage::Storage,
+        stream::StreamingAnalyzer,
+    },
+    error::Policy,
+    heap::HeapView,
+    parser::{
+        Id, ParsedHeap, Record,
+        sub_record::{FieldValue, PrimArray, SubRecord},
+    },
+    testutil::HeapBuilder,
+};
+
+// A extends B extends A, with an int field each. the instance of B only has the field of B, as
+// its superclass link is the one dropped
+fn heap_with_superclass_cycle() -> (ParsedHeap, Id, Id) {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let a = builder.class("A", Some(object), &[("a", 10)]);
+    let b = builder.class("B", Some(a), &[("b", 10)]);
+    builder.instance(b, &[FieldValue::Int(1)]);
+    let mut parsed = ParsedHeap::from_bytes(builder.build().unwrap()).unwrap();
+
+    for record in &mut parsed.records {
+        if let Record::HeapDumpSegment { sub_records, .. } = record {
+            for sub_record in sub_records {
+                if let SubRecord::ClassDump {
+                    class_object_id,
+                    super_class_object_id,
+                    ..
+                } = sub_record
+                    && *class_object_id == a
+                {
+                    *super_class_object_id = b;
+                }
+            }
+        }
+    }
+    (parsed, a, b)
+}
+
+#[test]
+fn superclass_cycles_are_cut_and_diagnosed() {
+    let (parsed, a, b) = heap_with_superclass_cycle();
+    let heap = AnalyzedHeap::analyze(&parsed).unwrap();
+
+    // B closed the cycle, it keeps its own field only
+    assert_eq!(
+        heap.diagnostics,
+        vec![Diagnostic::SuperclassCycle { id: b }]
+    );
+    assert_eq!(heap.layouts[&b].super_class_id, None);
+    assert_eq!(heap.layouts[&a].super_class_id, Some(b));
+    let histogram = heap.histogram(&Default::default());
+    assert_eq!(histogram[0].class.id, b);
+    // 12 byte header and an int
+    assert_eq!(histogram[0].shallow_size, 16);
+
+    let mut strict = StreamingAnalyzer::new(SizeModel::default(), &Storage::Memory)
+        .unwrap()
+        .with_policy(Policy::Strict);
+    for record in &parsed.records {
+        strict.record(record).unwrap();
+    }
+    assert!(strict.finish().is_err());
+}
+
+#[test]
+fn primitive_arrays_without_their_class_get_one_of_their_own() {
+    let mut builder = HeapBuilder::new();
+    builder.prim_array(PrimArray::Byte(vec![0; 8])).unwrap();
+    builder.prim_array(PrimArray::Byte(vec![0; 16])).unwrap();
+    let mut parsed = ParsedHeap::from_bytes(builder.build().unwrap()).unwrap();
+    parsed
+        .records
+        .retain(|record| !matches!(record, Record::LoadClass { .. }));
+
+    let heap = AnalyzedHeap::analyze(&parsed).unwrap();
+    assert_eq!(
+        heap.diagnostics,
+        vec![Diagnostic::MissingArrayClass { typ: 8, arrays: 2 }]
+    );
+    let histogram = heap.histogram(&Default::default());
+    assert_eq!(histogram.len(), 1);
+    assert_eq!(histogram[0].class.java_name(), "byte[]");
+    assert_eq!(histogram[0].instance_count, 2);
+
+    let mut strict = StreamingAnalyzer::new(SizeModel::default(), &Storage::Memory)
+        .unwrap()
+        .with_policy(Policy::Strict);
+    assert!(
+        parsed
+            .records
+            .iter()
+            .any(|record| strict.record(record).is_err())
+    );
+}
+
+#[test]
+fn heap_view_clones_share_what_one_of_them_computed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("heap.hprof");
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("Node", Some(object), &[("value", 10)]);
+    builder.instance(node, &[FieldValue::Int(1)]);
+    builder.write(&path).unwrap();
+
+    let view = HeapView::from(Heap::open(&path).unwrap());
+    let worker = view.clone();
+    let histogram = std::thread::spawn(move || worker.histogram().as_ptr() as usize)
+        .join()
+        .unwrap();
+    assert_eq!(view.histogram().as_ptr() as usize, histogram);
+    assert_eq!(view.histogram()[0].class.java_name(), "Node");
+}
+
+#[test]
+fn threads_frames_roots_and_instances_are_looked_up_by_id() {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let thread_class = builder.class("java/lang/Thread", Some(object), &[]);
+    let node = builder.class("Node", Some(object), &[("value", 10)]);
+    let first = builder.instance(node, &[FieldValue::Int(1)]);
+    let main = builder.instance(thread_class, &[]);
+    let second = builder.instance(node, &[FieldValue::Int(2)]);
+    let worker = builder.instance(thread_class, &[]);
+    builder.thread(main, &[("main", "()V", "Main.java", 3)]);
+    let serial = builder.thread(
+        worker,
+        &[
+            ("run", "()V", "Worker.java", 7),
+            ("call", "()V", "Worker.java", 12),
+        ],
+    );
+    let parsed = ParsedHeap::from_bytes(builder.build().unwrap()).unwrap();
+    let heap = AnalyzedHeap::analyze(&parsed).unwrap();
+
+    let thread = heap.thread_by_serial(serial).unwrap();
+    assert_eq!(thread.object_id, worker);
+    assert_eq!(
+        heap.thread_by_object(main).unwrap().stack_frame_ids.len(),
+        1
+    );
+    let frames: Vec<(&str, i32)> = heap
+        .stack_trace(thread)
+        .iter()
+        .map(|f| (&*f.method_name, f.line_number))
+        .collect();
+    assert_eq!(frames, [("run", 7), ("call", 12)]);
+    assert!(heap.thread_by_serial(serial + 100).is_none());
+
+    assert!(heap.is_gc_root(worker));
+    assert!(!heap.is_gc_root(first));
+
+    let nodes: Vec<Id> = heap.instances_of(node).map(|i| i.id).collect();
+    assert_eq!(nodes, [first, second]);
+    assert_eq!(heap.instance_count(node), 2);
+    assert_eq!(heap.instance_count(Id(1)), 0);
+}
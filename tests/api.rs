@@ -0,0 +1,94 @@
+#![cfg(feature = "http")]
+
+use axum::{
+    Router,
+    body::{Body, to_bytes},
+    http::{Request, StatusCode},
+};
+use std::path::Path;
+
+use heapdump_analyzer::{
+    Heap,
+    analyzer::graph::RootKind,
+    api::{metrics, router},
+    export::{json::MAX_EDGES, prometheus::DumpMetrics},
+    parser::{Id, sub_record::FieldValue},
+    testutil::HeapBuilder,
+};
+use serde_json::Value;
+use tower::ServiceExt;
+
+// a class with as many rooted instances, dumped into dir
+fn heap(dir: &Path, instances: usize) -> (Heap, Id) {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("Node", Some(object), &[("value", 10)]);
+    for i in 0..instances {
+        let instance = builder.instance(node, &[FieldValue::Int(i as i32)]);
+        builder.root(RootKind::JniGlobal, instance).unwrap();
+    }
+    let path = dir.join("heap.hprof");
+    builder.write(&path).unwrap();
+    (Heap::open(&path).unwrap(), node)
+}
+
+fn request(app: Router, uri: &str) -> (StatusCode, String) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    })
+}
+
+fn get(heap: Heap, uri: &str) -> (StatusCode, Value) {
+    let (status, body) = request(router(heap), uri);
+    (status, serde_json::from_str(&body).unwrap())
+}
+
+#[test]
+fn class_objects_list_a_bounded_number_of_referrers() {
+    let dir = tempfile::tempdir().unwrap();
+    let (heap, node) = heap(dir.path(), MAX_EDGES + 20);
+    let (status, object) = get(heap, &format!("/api/objects/{}", node));
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(object["class"], "class Node");
+    assert_eq!(object["referrers"]["total"], MAX_EDGES + 20);
+    assert_eq!(
+        object["referrers"]["items"].as_array().unwrap().len(),
+        MAX_EDGES
+    );
+}
+
+#[test]
+fn dominator_queries_are_clamped() {
+    let dir = tempfile::tempdir().unwrap();
+    let (heap, _) = heap(dir.path(), 20_000);
+    let (status, nodes) = get(heap, "/api/dominators?depth=1&children=1000000");
+    assert_eq!(status, StatusCode::OK);
+    // each instance is only dominated by its root, the list stops at MAX_REACHABLE nodes
+    assert_eq!(nodes.as_array().unwrap().len(), 10_000);
+}
+
+#[test]
+fn unknown_objects_are_not_found() {
+    let dir = tempfile::tempdir().unwrap();
+    let (heap, _) = heap(dir.path(), 1);
+    let (status, error) = get(heap, "/api/objects/0x1");
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(error["error"], "object 0x1 not found");
+}
+
+#[test]
+fn metrics_are_served_next_to_the_api() {
+    let dir = tempfile::tempdir().unwrap();
+    let (heap, _) = heap(dir.path(), 3);
+    let dump_metrics = DumpMetrics::new("heap.hprof", &heap, heap.dominator_tree(), &[], 10, 0);
+    let app = router(heap).merge(metrics(vec![dump_metrics]));
+    let (status, body) = request(app, "/metrics");
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("heapdump_objects{dump=\"heap.hprof\"} 3\n"));
+    assert!(body.contains("heapdump_class_retained_bytes{dump=\"heap.hprof\",class=\"Node\"}"));
+}
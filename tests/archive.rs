@@ -0,0 +1,103 @@
+#![cfg(feature = "remote")]
+
+use std::{fs::File, io::Write, path::Path};
+
+use flate2::{Compression, write::GzEncoder};
+use heapdump_analyzer::{
+    ParsedHeap,
+    archive::{extract, is_archive, split_entry},
+    testutil::HeapBuilder,
+};
+
+fn dump() -> Vec<u8> {
+    let mut builder = HeapBuilder::new();
+    builder.class("java/lang/Object", None, &[]);
+    builder.build().unwrap()
+}
+
+fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+    let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+    for (name, contents) in files {
+        zip.start_file(*name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(contents).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+fn write_tar_gz(path: &Path, files: &[(&str, &[u8])]) {
+    let mut tar = tar::Builder::new(GzEncoder::new(
+        File::create(path).unwrap(),
+        Compression::default(),
+    ));
+    for (name, contents) in files {
+        let mut header = tar::Header::new_ustar();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, name, *contents).unwrap();
+    }
+    tar.into_inner().unwrap().finish().unwrap();
+}
+
+#[test]
+fn the_only_dump_of_a_zip_is_extracted_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("bundle.zip");
+    let dump = dump();
+    write_zip(
+        &archive,
+        &[
+            ("README.txt", b"heap of the crash"),
+            ("dumps/heap.hprof", &dump),
+        ],
+    );
+    assert!(is_archive(&archive).unwrap());
+    let cache = dir.path().join("cache");
+
+    let extracted = extract(&archive, None, &cache).unwrap();
+    assert_eq!(std::fs::read(&extracted).unwrap(), dump);
+    ParsedHeap::parse(&extracted).unwrap();
+
+    // cached until the archive changes
+    std::fs::write(&extracted, b"stale").unwrap();
+    assert_eq!(extract(&archive, None, &cache).unwrap(), extracted);
+    assert_eq!(std::fs::read(&extracted).unwrap(), b"stale");
+}
+
+#[test]
+fn tarballs_with_several_dumps_need_an_entry() {
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("dumps.tar.gz");
+    let (first, second) = (dump(), b"not a dump".to_vec());
+    write_tar_gz(&archive, &[("a.hprof", &first), ("b.hprof", &second)]);
+    assert!(is_archive(&archive).unwrap());
+    let cache = dir.path().join("cache");
+
+    let err = extract(&archive, None, &cache).unwrap_err();
+    assert!(
+        format!("{:#}", err).contains("more than one dump in the archive (a.hprof, b.hprof)"),
+        "{:#}",
+        err
+    );
+
+    let (path, entry) = split_entry("dumps.tar.gz!/b.hprof").unwrap();
+    assert_eq!((path, entry), ("dumps.tar.gz", "b.hprof"));
+    let extracted = extract(&archive, Some(entry), &cache).unwrap();
+    assert_eq!(std::fs::read(extracted).unwrap(), second);
+    assert!(extract(&archive, Some("c.hprof"), &cache).is_err());
+}
+
+#[test]
+fn dumps_and_gzipped_dumps_are_not_archives() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("heap.hprof");
+    std::fs::write(&path, dump()).unwrap();
+    assert!(!is_archive(&path).unwrap());
+
+    let gzipped = dir.path().join("heap.hprof.gz");
+    let mut encoder = GzEncoder::new(File::create(&gzipped).unwrap(), Compression::default());
+    encoder.write_all(&dump()).unwrap();
+    encoder.finish().unwrap();
+    assert!(!is_archive(&gzipped).unwrap());
+    assert!(split_entry("heap.hprof").is_none());
+}
@@ -0,0 +1,249 @@
+#![cfg(feature = "cli")]
+
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use heapdump_analyzer::{
+    analyzer::graph::RootKind,
+    parser::sub_record::{FieldValue, PrimArray},
+    testutil::HeapBuilder,
+};
+
+// the binary with its caches in dir
+fn command(dir: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_heapdump-analyzer"));
+    command
+        .args(args)
+        .env("HOME", dir)
+        .env("XDG_CACHE_HOME", dir.join("cache"))
+        .env("XDG_CONFIG_HOME", dir.join("config"))
+        .env_remove("RUST_LOG");
+    command
+}
+
+fn heapdump_analyzer(dir: &Path, args: &[&str]) -> std::process::Output {
+    command(dir, args).output().unwrap()
+}
+
+#[test]
+fn max_heap_only_counts_reachable_objects() {
+    let dir = tempfile::tempdir().unwrap();
+    let dump = dir.path().join("heap.hprof");
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("Node", Some(object), &[("value", 10)]);
+    let rooted = builder.instance(node, &[FieldValue::Int(1)]);
+    builder.root(RootKind::JniGlobal, rooted).unwrap();
+    // garbage, 16 bytes of header and 4096 elements
+    builder.prim_array(PrimArray::Byte(vec![0; 4096])).unwrap();
+    builder.write(&dump).unwrap();
+    let dump = dump.to_str().unwrap();
+
+    let output = heapdump_analyzer(dir.path(), &["check", dump, "--max-heap", "1KB"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let output = heapdump_analyzer(dir.path(), &["check", dump, "--max-heap", "8B"]);
+    assert!(!output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["violations"][0]["rule"], "max-heap");
+    // 12 byte header and an int
+    assert_eq!(report["violations"][0]["actual"], 16);
+}
+
+#[test]
+fn max_retained_sums_the_instances_of_a_class() {
+    let dir = tempfile::tempdir().unwrap();
+    let dump = dir.path().join("heap.hprof");
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("com/example/Node", Some(object), &[("value", 10)]);
+    for value in 0..4 {
+        let instance = builder.instance(node, &[FieldValue::Int(value)]);
+        builder.root(RootKind::JniGlobal, instance).unwrap();
+    }
+    builder.write(&dump).unwrap();
+    let dump = dump.to_str().unwrap();
+
+    let output = heapdump_analyzer(
+        dir.path(),
+        &["check", dump, "--max-retained", "com.example.Node=64B"],
+    );
+    assert!(output.status.success());
+
+    let output = heapdump_analyzer(
+        dir.path(),
+        &[
+            "check",
+            dump,
+            "--max-retained",
+            "com.example.Node=63B",
+            "--max-retained",
+            "com.example.Missing=1B",
+        ],
+    );
+    assert!(!output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["passed"], false);
+    let violations = report["violations"].as_array().unwrap();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0]["rule"], "max-retained");
+    assert_eq!(violations[0]["class"], "com.example.Node");
+    assert_eq!(violations[0]["actual"], 64);
+}
+
+#[test]
+fn skipped_records_are_warned_about_without_rust_log() {
+    let dir = tempfile::tempdir().unwrap();
+    let dump = dir.path().join("heap.hprof");
+    let mut builder = HeapBuilder::new();
+    builder.class("java/lang/Object", None, &[]);
+    let mut bytes = builder.build().unwrap();
+    // a record of an unknown tag in front of the heap dump end record
+    let end = bytes.split_off(bytes.len() - 9);
+    bytes.extend([0x7f, 0, 0, 0, 0, 0, 0, 0, 1, 0]);
+    bytes.extend(end);
+    std::fs::write(&dump, bytes).unwrap();
+
+    let output = heapdump_analyzer(dir.path(), &["summary", dump.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown tag 0x7f"), "{}", stderr);
+}
+
+#[test]
+fn detectors_disabled_in_the_config_are_left_out() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.toml");
+    std::fs::write(&config, "[detectors]\ndisabled = [\"netty-buffers\"]\n").unwrap();
+    let config = config.to_str().unwrap();
+
+    let output = heapdump_analyzer(
+        dir.path(),
+        &["report", "unused.hprof", "--list", "--config", config],
+    );
+    assert!(output.status.success());
+    let names = String::from_utf8(output.stdout).unwrap();
+    assert!(names.lines().any(|name| name == "okhttp-pools"));
+    assert!(!names.lines().any(|name| name == "netty-buffers"));
+
+    std::fs::write(
+        dir.path().join("config.toml"),
+        "[detectors]\ndisabled = [\"netty\"]\n",
+    )
+    .unwrap();
+    let output = heapdump_analyzer(
+        dir.path(),
+        &["report", "unused.hprof", "--list", "--config", config],
+    );
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("unknown detector netty in the config")
+    );
+}
+
+#[test]
+fn watch_posts_a_webhook_for_dumps_over_a_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    let dumps = dir.path().join("dumps");
+    std::fs::create_dir(&dumps).unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("Node", Some(object), &[("value", 10)]);
+    let rooted = builder.instance(node, &[FieldValue::Int(1)]);
+    builder.root(RootKind::JniGlobal, rooted).unwrap();
+    builder.write(&dumps.join("heap.hprof")).unwrap();
+
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let webhook = format!("http://{}/hook", server.server_addr());
+    let mut watch = command(
+        dir.path(),
+        &[
+            "watch",
+            dumps.to_str().unwrap(),
+            "--existing",
+            "--interval",
+            "1",
+            "--webhook",
+            &webhook,
+            "--max-heap",
+            "8B",
+        ],
+    )
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .spawn()
+    .unwrap();
+
+    // the dump is analyzed once its size didn't change between two scans
+    let request = server.recv_timeout(Duration::from_secs(60));
+    watch.kill().unwrap();
+    watch.wait().unwrap();
+    let mut request = request.unwrap().expect("no webhook posted");
+    let mut body = String::new();
+    request.as_reader().read_to_string(&mut body).unwrap();
+    assert_eq!(request.url(), "/hook");
+    request
+        .respond(tiny_http::Response::from_string("ok"))
+        .unwrap();
+
+    let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(payload["dump"].as_str().unwrap().ends_with("heap.hprof"));
+    assert_eq!(payload["reachable_size"], 16);
+    assert_eq!(payload["violations"][0]["rule"], "max-heap");
+    let text = payload["text"].as_str().unwrap();
+    assert!(
+        text.contains("heap dump heap.hprof needs a look"),
+        "{}",
+        text
+    );
+}
+
+#[test]
+fn histograms_and_dominators_are_exported_as_csv() {
+    let dir = tempfile::tempdir().unwrap();
+    let dump = dir.path().join("heap.hprof");
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("com/example/Node", Some(object), &[("value", 10)]);
+    let nodes: Vec<_> = (0..3)
+        .map(|value| builder.instance(node, &[FieldValue::Int(value)]))
+        .collect();
+    builder.root(RootKind::JniGlobal, nodes[0]).unwrap();
+    builder.write(&dump).unwrap();
+    let dump = dump.to_str().unwrap();
+
+    let output = heapdump_analyzer(
+        dir.path(),
+        &["export", dump, "--what", "histogram", "--format", "csv"],
+    );
+    assert!(output.status.success());
+    let csv = String::from_utf8(output.stdout).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("Class,Objects,Shallow,% of heap"));
+    assert_eq!(lines.next(), Some("com.example.Node,3,48,100.0000"));
+    assert_eq!(lines.next(), None);
+
+    let output = heapdump_analyzer(
+        dir.path(),
+        &["export", dump, "--what", "dominators", "--format", "csv"],
+    );
+    assert!(output.status.success());
+    let csv = String::from_utf8(output.stdout).unwrap();
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        rows,
+        vec![
+            "Object,Class,Shallow,Retained,% of reachable heap",
+            &format!("{},com.example.Node,16,16,100.0000", nodes[0]),
+        ]
+    );
+}
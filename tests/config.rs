@@ -0,0 +1,62 @@
+#![cfg(feature = "report")]
+
+use std::path::Path;
+
+use heapdump_analyzer::{
+    AnalyzedHeap,
+    analyzer::size::SizeModel,
+    config::{Config, DetectorsConfig},
+    parser::sub_record::FieldValue,
+    testutil::HeapBuilder,
+};
+
+fn write_config(dir: &Path, contents: &str) -> Config {
+    let path = dir.join("config.toml");
+    std::fs::write(&path, contents).unwrap();
+    Config::from_file(&path).unwrap()
+}
+
+#[test]
+fn tables_left_out_of_the_config_keep_their_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = write_config(
+        dir.path(),
+        "[filters]\nexclude = [\"java.\"]\n\n[analysis]\nmax_memory = \"1.5G\"\n",
+    );
+    assert_eq!(config.filters.exclude, vec!["java.".to_string()]);
+    assert_eq!(config.analysis.max_memory, Some(3 << 29));
+    assert_eq!(config.size_model, SizeModel::default());
+    assert_eq!(config.output.rows, Config::default().output.rows);
+    assert_eq!(config.detectors, DetectorsConfig::default());
+}
+
+#[test]
+fn unknown_keys_and_sizes_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "[output]\nrow = 10\n").unwrap();
+    assert!(Config::from_file(&path).is_err());
+    std::fs::write(&path, "[analysis]\nmax_memory = \"8 parsecs\"\n").unwrap();
+    assert!(Config::from_file(&path).is_err());
+    assert!(Config::load(Some(&dir.path().join("missing.toml"))).is_err());
+}
+
+#[test]
+fn the_size_model_of_the_config_sizes_instances() {
+    let dir = tempfile::tempdir().unwrap();
+    let dump = dir.path().join("heap.hprof");
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("Node", Some(object), &[("value", 10)]);
+    let instance = builder.instance(node, &[FieldValue::Int(1)]);
+    builder.write(&dump).unwrap();
+
+    // uncompressed class pointers, 16 bytes of header and the int aligned to 24
+    let config = write_config(dir.path(), "[size_model]\nobject_header = 16\n");
+    let (_, heap) = AnalyzedHeap::analyze_file_with(&dump, &config.analysis_options()).unwrap();
+    assert_eq!(heap.instance(instance).unwrap().shallow_size, 24);
+
+    let (_, heap) =
+        AnalyzedHeap::analyze_file_with(&dump, &Config::default().analysis_options()).unwrap();
+    assert_eq!(heap.instance(instance).unwrap().shallow_size, 16);
+}
@@ -0,0 +1,434 @@
+#![cfg(feature = "report")]
+
+use std::path::Path;
+
+use heapdump_analyzer::{
+    AnalyzedHeap,
+    analysis::{Registry, Report},
+    analyzer::graph::RootKind,
+    config::Config,
+    parser::{
+        Id,
+        sub_record::{FieldValue, PrimArray},
+    },
+    testutil::HeapBuilder,
+};
+
+// the reports of the named analyses on the dump
+fn reports(builder: HeapBuilder, dir: &Path, names: &[&str], config: &Config) -> Vec<Report> {
+    let path = dir.join("heap.hprof");
+    builder.write(&path).unwrap();
+    let (header, heap) =
+        AnalyzedHeap::analyze_file_with(&path, &config.analysis_options()).unwrap();
+    let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+    Registry::builtin()
+        .run(&names, &path, &header, &heap, config)
+        .unwrap()
+}
+
+// the rows of a report tab separated, sizes and counts unformatted
+fn rows(report: &Report) -> Vec<String> {
+    let mut tsv = Vec::new();
+    report.table.render_tsv(&mut tsv).unwrap();
+    String::from_utf8(tsv)
+        .unwrap()
+        .lines()
+        .skip(1)
+        .map(str::to_string)
+        .collect()
+}
+
+fn reference(object_id: Id) -> FieldValue {
+    FieldValue::NormalObject { object_id }
+}
+
+#[test]
+fn netty_chunks_are_summed_by_arena_and_buffers_by_class() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let arena = builder.class("io/netty/buffer/PoolArena", Some(object), &[]);
+    let direct_arena = builder.class("io/netty/buffer/PoolArena$DirectArena", Some(arena), &[]);
+    let heap_arena = builder.class("io/netty/buffer/PoolArena$HeapArena", Some(arena), &[]);
+    let chunk = builder.class(
+        "io/netty/buffer/PoolChunk",
+        Some(object),
+        &[("arena", 2), ("chunkSize", 10), ("freeBytes", 10)],
+    );
+    let pooled = builder.class(
+        "io/netty/buffer/PooledByteBuf",
+        Some(object),
+        &[("chunk", 2), ("length", 10), ("writerIndex", 10)],
+    );
+    let direct_buffer = builder.class(
+        "io/netty/buffer/PooledUnsafeDirectByteBuf",
+        Some(pooled),
+        &[],
+    );
+
+    let direct = builder.instance(direct_arena, &[]);
+    let used = builder.instance(
+        chunk,
+        &[
+            reference(direct),
+            FieldValue::Int(1024),
+            FieldValue::Int(256),
+        ],
+    );
+    let heap = builder.instance(heap_arena, &[]);
+    builder.instance(
+        chunk,
+        &[
+            reference(heap),
+            FieldValue::Int(1024),
+            FieldValue::Int(1024),
+        ],
+    );
+    for written in [100, 50] {
+        builder.instance(
+            direct_buffer,
+            &[
+                reference(used),
+                FieldValue::Int(256),
+                FieldValue::Int(written),
+            ],
+        );
+    }
+    // released
+    builder.instance(
+        direct_buffer,
+        &[reference(Id(0)), FieldValue::Int(0), FieldValue::Int(0)],
+    );
+
+    let reports = reports(builder, dir.path(), &["netty-buffers"], &Config::default());
+    assert_eq!(
+        rows(&reports[0]),
+        vec![
+            "direct arenas\t1\t1024\t768",
+            "heap arenas\t1\t1024\t0",
+            "io.netty.buffer.PooledUnsafeDirectByteBuf\t2\t512\t150",
+        ]
+    );
+    assert_eq!(
+        reports[0].notes,
+        vec!["1 released buffers are kept by the recycler for reuse"]
+    );
+}
+
+#[test]
+fn guava_and_caffeine_caches_have_their_entries_and_bounds() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let array_class = builder.class("[Ljava/lang/Object;", Some(object), &[]);
+    let local_cache = builder.class(
+        "com/google/common/cache/LocalCache",
+        Some(object),
+        &[("segments", 2), ("maxWeight", 11)],
+    );
+    let segment = builder.class(
+        "com/google/common/cache/LocalCache$Segment",
+        Some(object),
+        &[("count", 10)],
+    );
+    let unbounded = builder.class(
+        "com/github/benmanes/caffeine/cache/UnboundedLocalCache",
+        Some(object),
+        &[("data", 2)],
+    );
+    let concurrent_map = builder.class(
+        "java/util/concurrent/ConcurrentHashMap",
+        Some(object),
+        &[("baseCount", 11)],
+    );
+
+    let segments: Vec<Id> = [3, 4]
+        .into_iter()
+        .map(|count| builder.instance(segment, &[FieldValue::Int(count)]))
+        .collect();
+    let segments = builder.object_array(array_class, &segments);
+    let guava = builder.instance(local_cache, &[reference(segments), FieldValue::Long(100)]);
+    let data = builder.instance(concurrent_map, &[FieldValue::Long(5)]);
+    let caffeine = builder.instance(unbounded, &[reference(data)]);
+    for id in [guava, caffeine] {
+        builder.root(RootKind::JniGlobal, id).unwrap();
+    }
+
+    let reports = reports(builder, dir.path(), &["library-caches"], &Config::default());
+    // the guava cache retains its segments array of 24 bytes and two segments
+    assert_eq!(
+        rows(&reports[0]),
+        vec![
+            format!(
+                "com.google.common.cache.LocalCache @ {}\tGuava\t7\t100\t80\t11",
+                guava
+            ),
+            format!(
+                "com.github.benmanes.caffeine.cache.UnboundedLocalCache @ {}\tCaffeine\t5\t\t40\t8",
+                caffeine
+            ),
+        ]
+    );
+    assert_eq!(
+        reports[0].notes,
+        vec!["1 caches have no size bound and only shrink through expiry or references"]
+    );
+}
+
+#[test]
+fn okhttp_pools_count_their_open_and_idle_connections() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let real_pool = builder.class(
+        "okhttp3/internal/connection/RealConnectionPool",
+        Some(object),
+        &[("maxIdleConnections", 10)],
+    );
+    let pool = builder.class("okhttp3/ConnectionPool", Some(object), &[("delegate", 2)]);
+    let connection = builder.class(
+        "okhttp3/internal/connection/RealConnection",
+        Some(object),
+        &[("connectionPool", 2), ("calls", 2)],
+    );
+    let array_list = builder.class("java/util/ArrayList", Some(object), &[("size", 10)]);
+
+    let shared = builder.instance(real_pool, &[FieldValue::Int(5)]);
+    builder.instance(pool, &[reference(shared)]);
+    for calls in [1, 0, 0] {
+        let calls = builder.instance(array_list, &[FieldValue::Int(calls)]);
+        builder.instance(connection, &[reference(shared), reference(calls)]);
+    }
+    let other = builder.instance(real_pool, &[FieldValue::Int(5)]);
+
+    // reports come in registration order, netty first
+    let names = ["okhttp-pools", "netty-buffers"];
+    let reports = reports(builder, dir.path(), &names, &Config::default());
+    assert!(reports[0].table.is_empty());
+    assert_eq!(reports[0].notes, vec!["No Netty classes loaded"]);
+
+    // the ConnectionPool wrapper has no limits of its own
+    let pool = |id: Id| format!("okhttp3.internal.connection.RealConnectionPool @ {}", id);
+    assert_eq!(
+        rows(&reports[1]),
+        vec![
+            format!("{}\t3\t2\t5", pool(shared)),
+            format!("{}\t0\t0\t5", pool(other)),
+        ]
+    );
+    assert!(reports[1].notes[0].starts_with("2 connection pools"));
+}
+
+fn string(builder: &mut HeapBuilder, class: Id, content: &str) -> Id {
+    let bytes = PrimArray::Byte(content.bytes().map(|b| b as i8).collect());
+    let value = builder.prim_array(bytes).unwrap();
+    builder.instance(class, &[reference(value), FieldValue::Byte(0)])
+}
+
+// few entries count as many, for the detectors to find something in small dumps
+fn config() -> Config {
+    let mut config = Config::default();
+    config.detectors.many_entries = 3;
+    config
+}
+
+#[test]
+fn spring_caches_outgrowing_the_bean_definitions_are_growing() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let hash_map = builder.class("java/util/HashMap", Some(object), &[("size", 10)]);
+    let registry = builder.class(
+        "org/springframework/beans/factory/support/DefaultSingletonBeanRegistry",
+        Some(object),
+        &[("dependentBeanMap", 2), ("singletonObjects", 2)],
+    );
+    let factory = builder.class(
+        "org/springframework/beans/factory/support/DefaultListableBeanFactory",
+        Some(registry),
+        &[("beanDefinitionMap", 2)],
+    );
+
+    let maps: Vec<FieldValue> = [2, 5, 2]
+        .into_iter()
+        .map(|size| reference(builder.instance(hash_map, &[FieldValue::Int(size)])))
+        .collect();
+    let factory = builder.instance(factory, &maps);
+    builder.root(RootKind::JniGlobal, factory).unwrap();
+
+    let reports = reports(builder, dir.path(), &["spring-bean-caches"], &config());
+    // the singletons are as many as the definitions
+    assert_eq!(
+        rows(&reports[0]),
+        vec![format!(
+            "org.springframework.beans.factory.support.DefaultListableBeanFactory @ {}\t\
+             dependentBeanMap\t5\t16",
+            factory
+        )]
+    );
+    assert_eq!(reports[0].notes.len(), 1);
+    assert!(reports[0].notes[0].contains("inject an ObjectProvider"));
+}
+
+#[test]
+fn hibernate_sessions_have_their_persistence_context() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let hash_map = builder.class("java/util/HashMap", Some(object), &[("size", 10)]);
+    let persistence_context = builder.class(
+        "org/hibernate/engine/internal/StatefulPersistenceContext",
+        Some(object),
+        &[("entitiesByKey", 2), ("collectionsByKey", 2)],
+    );
+    let session = builder.class(
+        "org/hibernate/internal/SessionImpl",
+        Some(object),
+        &[("persistenceContext", 2), ("closed", 4)],
+    );
+
+    let entities = builder.instance(hash_map, &[FieldValue::Int(3)]);
+    let collections = builder.instance(hash_map, &[FieldValue::Int(1)]);
+    let context = builder.instance(
+        persistence_context,
+        &[reference(entities), reference(collections)],
+    );
+    let open = builder.instance(session, &[reference(context), FieldValue::Boolean(false)]);
+    let closed = builder.instance(session, &[reference(Id(0)), FieldValue::Boolean(true)]);
+    for id in [open, closed] {
+        builder.root(RootKind::JniGlobal, id).unwrap();
+    }
+
+    let reports = reports(builder, dir.path(), &["hibernate-sessions"], &config());
+    // the open session retains its context and both maps
+    let session = |id: Id| format!("org.hibernate.internal.SessionImpl @ {}", id);
+    assert_eq!(
+        rows(&reports[0]),
+        vec![
+            format!("{}\t3\t1\t{}", session(open), 24 + 24 + 2 * 16),
+            format!("{}\t0\t0\t24", session(closed)),
+        ]
+    );
+    assert!(reports[0].notes.is_empty());
+}
+
+#[test]
+fn appenders_with_full_queues_or_every_event_are_flagged() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let string_class = builder.class(
+        "java/lang/String",
+        Some(object),
+        &[("value", 2), ("coder", 8)],
+    );
+    let queue = builder.class(
+        "java/util/concurrent/ArrayBlockingQueue",
+        Some(object),
+        &[("count", 10)],
+    );
+    let array_list = builder.class("java/util/ArrayList", Some(object), &[("size", 10)]);
+    let unsynchronized = builder.class(
+        "ch/qos/logback/core/UnsynchronizedAppenderBase",
+        Some(object),
+        &[("name", 2)],
+    );
+    let async_base = builder.class(
+        "ch/qos/logback/core/AsyncAppenderBase",
+        Some(unsynchronized),
+        &[("blockingQueue", 2), ("queueSize", 10)],
+    );
+    let async_appender = builder.class(
+        "ch/qos/logback/classic/AsyncAppender",
+        Some(async_base),
+        &[],
+    );
+    let console = builder.class(
+        "ch/qos/logback/core/ConsoleAppender",
+        Some(unsynchronized),
+        &[],
+    );
+    let base = builder.class(
+        "ch/qos/logback/core/AppenderBase",
+        Some(object),
+        &[("name", 2)],
+    );
+    let list_appender = builder.class(
+        "ch/qos/logback/core/read/ListAppender",
+        Some(base),
+        &[("list", 2)],
+    );
+
+    let events = builder.instance(queue, &[FieldValue::Int(256)]);
+    let name = string(&mut builder, string_class, "ASYNC");
+    let full = builder.instance(
+        async_appender,
+        &[reference(events), FieldValue::Int(256), reference(name)],
+    );
+    let events = builder.instance(array_list, &[FieldValue::Int(5)]);
+    let list = builder.instance(list_appender, &[reference(events), reference(Id(0))]);
+    let console = builder.instance(console, &[reference(Id(0))]);
+    for id in [full, list, console] {
+        builder.root(RootKind::JniGlobal, id).unwrap();
+    }
+
+    let reports = reports(builder, dir.path(), &["logging-appenders"], &config());
+    // the console appender buffers nothing. the async one retains its name, 24 bytes of string
+    // and 24 of array
+    assert_eq!(
+        rows(&reports[0]),
+        vec![
+            format!(
+                "ch.qos.logback.classic.AsyncAppender @ {} (ASYNC)\t256\t256\t{}",
+                full,
+                24 + 16 + 24 + 24
+            ),
+            format!("ch.qos.logback.core.read.ListAppender @ {}\t5\t\t40", list),
+        ]
+    );
+    let notes = &reports[0].notes;
+    assert_eq!(notes.len(), 2);
+    assert!(notes[0].starts_with("Full async appender queues"));
+    assert!(notes[1].starts_with("Appenders keeping every event"));
+}
+
+#[test]
+fn statics_of_classes_loaded_more_than_once_are_summed() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let hash_map = builder.class("java/util/HashMap", Some(object), &[("size", 10)]);
+    let loader = builder.class(
+        "org/apache/catalina/loader/WebappClassLoader",
+        Some(object),
+        &[],
+    );
+
+    // one copy per deployment, each with a map of its own
+    for _ in 0..2 {
+        let deployment = builder.instance(loader, &[]);
+        let instances = builder.instance(hash_map, &[FieldValue::Int(3)]);
+        let registry = builder.class_with_statics(
+            "com/example/Registry",
+            Some(object),
+            &[],
+            vec![("instances", reference(instances))],
+        );
+        builder.class_loader(registry, deployment);
+        builder.root(RootKind::StickyClass, registry).unwrap();
+    }
+    let all = builder.instance(hash_map, &[FieldValue::Int(10)]);
+    builder.class_with_statics(
+        "com/example/Single",
+        Some(object),
+        &[],
+        vec![("all", reference(all))],
+    );
+
+    let reports = reports(builder, dir.path(), &["redeployed-statics"], &config());
+    assert_eq!(
+        rows(&reports[0]),
+        vec!["com.example.Registry.instances\t2\t6\t32"]
+    );
+    assert!(reports[0].notes[0].starts_with("Classes loaded by several class loaders"));
+}
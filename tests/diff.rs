@@ -0,0 +1,142 @@
+use std::path::Path;
+
+use heapdump_analyzer::{
+    AnalyzedHeap,
+    analyzer::{
+        contents::Contents,
+        diff::{ClassChange, DiffOrder, DiffSide, class_totals, histogram_diff, object_diff},
+        graph::RootKind,
+        options::AnalysisOptions,
+    },
+    parser::{Id, sub_record::FieldValue},
+    testutil::HeapBuilder,
+};
+
+// a rooted registry with a cache holding an array of nodes, two rooted sessions and an
+// instance of a class of its own
+fn write_dump(path: &Path, nodes: i32, own_class: &str) {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let array_class = builder.class("[Ljava/lang/Object;", Some(object), &[]);
+    let node = builder.class("Node", Some(object), &[("value", 10)]);
+    let cache = builder.class("Cache", Some(object), &[("entries", 2), ("size", 10)]);
+    let registry = builder.class("Registry", Some(object), &[("cache", 2)]);
+    let session = builder.class("Session", Some(object), &[("id", 10)]);
+    let own_class = builder.class(own_class, Some(object), &[("id", 10)]);
+
+    let entries: Vec<Id> = (0..nodes)
+        .map(|value| builder.instance(node, &[FieldValue::Int(value)]))
+        .collect();
+    let entries = builder.object_array(array_class, &entries);
+    let cache = builder.instance(
+        cache,
+        &[
+            FieldValue::NormalObject { object_id: entries },
+            FieldValue::Int(nodes),
+        ],
+    );
+    let registry = builder.instance(registry, &[FieldValue::NormalObject { object_id: cache }]);
+    builder.root(RootKind::JniGlobal, registry).unwrap();
+    for id in 0..2 {
+        let session = builder.instance(session, &[FieldValue::Int(id)]);
+        builder.root(RootKind::JniGlobal, session).unwrap();
+    }
+    builder.instance(own_class, &[FieldValue::Int(0)]);
+    builder.write(path).unwrap();
+}
+
+fn analyze(path: &Path) -> AnalyzedHeap {
+    AnalyzedHeap::analyze_file_with(path, &AnalysisOptions::default())
+        .unwrap()
+        .1
+}
+
+#[test]
+fn histogram_diffs_list_changed_classes_by_growth() {
+    let dir = tempfile::tempdir().unwrap();
+    let (before, after) = (
+        dir.path().join("before.hprof"),
+        dir.path().join("after.hprof"),
+    );
+    write_dump(&before, 2, "Before");
+    write_dump(&after, 4, "After");
+    let (before, after) = (analyze(&before), analyze(&after));
+    let totals = |heap: &AnalyzedHeap| class_totals(&heap.histogram(&Default::default()), None);
+
+    let diffs = histogram_diff(&totals(&before), &totals(&after), DiffOrder::Shallow);
+    let rows: Vec<(&str, i64, i64, ClassChange)> = diffs
+        .iter()
+        .map(|d| {
+            (
+                d.class_name.as_str(),
+                d.instance_delta(),
+                d.shallow_delta(),
+                d.change,
+            )
+        })
+        .collect();
+    // two more nodes and references to them, the unchanged classes are left out
+    assert_eq!(
+        rows,
+        vec![
+            ("Node", 2, 32, ClassChange::Changed),
+            ("After", 1, 16, ClassChange::New),
+            ("java.lang.Object[]", 0, 8, ClassChange::Changed),
+            ("Before", -1, -16, ClassChange::Gone),
+        ]
+    );
+    assert!(diffs.iter().all(|d| d.retained_delta().is_none()));
+
+    let by_count = histogram_diff(&totals(&before), &totals(&after), DiffOrder::Count);
+    assert_eq!(by_count[1].class_name, "After");
+    assert_eq!(by_count[2].class_name, "java.lang.Object[]");
+}
+
+#[test]
+fn objects_are_matched_by_their_dominator_chain() {
+    let dir = tempfile::tempdir().unwrap();
+    let (before_path, after_path) = (
+        dir.path().join("before.hprof"),
+        dir.path().join("after.hprof"),
+    );
+    write_dump(&before_path, 2, "Before");
+    write_dump(&after_path, 4, "After");
+    let (before, after) = (analyze(&before_path), analyze(&after_path));
+    let before_contents = Contents::open(&before_path, &before).unwrap();
+    let after_contents = Contents::open(&after_path, &after).unwrap();
+
+    let report = object_diff(
+        DiffSide {
+            heap: &before,
+            contents: &before_contents,
+        },
+        DiffSide {
+            heap: &after,
+            contents: &after_contents,
+        },
+        0,
+    )
+    .unwrap();
+    // the registry, its cache and array and the six classes in both dumps. the nodes and the
+    // sessions can't be told apart
+    assert_eq!(report.matched, 9);
+    assert_eq!(report.ambiguous, 2);
+
+    let grown: Vec<(&str, i64, Option<i64>)> = report
+        .grown
+        .iter()
+        .map(|d| (d.key.as_str(), d.retained_delta(), d.element_delta()))
+        .collect();
+    assert_eq!(
+        grown,
+        vec![
+            ("Registry", 40, None),
+            ("Registry / cache Cache", 40, Some(2)),
+            (
+                "Registry / cache Cache / entries java.lang.Object[]",
+                40,
+                Some(2)
+            ),
+        ]
+    );
+}
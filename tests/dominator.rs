@@ -0,0 +1,146 @@
+use heapdump_analyzer::{
+    analyzer::{
+        AnalyzedHeap,
+        dominator::DominatorTree,
+        graph::{Csr, RootKind},
+    },
+    parser::{Id, ParsedHeap, sub_record::FieldValue},
+    testutil::HeapBuilder,
+};
+
+// nodes with two references, 24 bytes each: a 12 byte header and two 4 byte references
+const NODE: u64 = 24;
+
+struct Graph {
+    builder: HeapBuilder,
+    node: Id,
+    first: Id,
+}
+
+// node i references the nodes given for it, ids are known up front as the builder hands them
+// out in order
+impl Graph {
+    fn new() -> Self {
+        let mut builder = HeapBuilder::new();
+        let object = builder.class("java/lang/Object", None, &[]);
+        let node = builder.class("Node", Some(object), &[("a", 2), ("b", 2)]);
+        let first = builder.instance(node, &[null(), null()]);
+        Self {
+            builder,
+            node,
+            first,
+        }
+    }
+
+    fn id(&self, i: usize) -> Id {
+        Id(self.first.0 + 8 * (i as u64 + 1))
+    }
+
+    fn nodes(mut self, references: &[(Option<usize>, Option<usize>)], roots: &[usize]) -> Tree {
+        let ids: Vec<Id> = (0..references.len()).map(|i| self.id(i)).collect();
+        let field = |i: Option<usize>| FieldValue::NormalObject {
+            object_id: i.map_or(Id(0), |i| ids[i]),
+        };
+        for (i, (a, b)) in references.iter().enumerate() {
+            let id = self.builder.instance(self.node, &[field(*a), field(*b)]);
+            assert_eq!(id, ids[i]);
+        }
+        for root in roots {
+            self.builder.root(RootKind::JniGlobal, ids[*root]).unwrap();
+        }
+
+        let parsed = ParsedHeap::from_bytes(self.builder.build().unwrap()).unwrap();
+        let heap = AnalyzedHeap::analyze(&parsed).unwrap();
+        Tree {
+            tree: DominatorTree::compute(&heap),
+            ids,
+        }
+    }
+}
+
+struct Tree {
+    tree: DominatorTree,
+    ids: Vec<Id>,
+}
+
+impl Tree {
+    // None for nodes only dominated by the virtual root
+    fn idom(&self, i: usize) -> Option<usize> {
+        let idom = self.tree.immediate_dominator(self.ids[i])?;
+        self.ids.iter().position(|id| *id == idom)
+    }
+
+    fn retained(&self, i: usize) -> Option<u64> {
+        self.tree.retained_size(self.ids[i])
+    }
+}
+
+fn null() -> FieldValue {
+    FieldValue::NormalObject { object_id: Id(0) }
+}
+
+#[test]
+fn diamond() {
+    // 0 -> 1, 2 -> 3
+    let tree = Graph::new().nodes(
+        &[
+            (Some(1), Some(2)),
+            (Some(3), None),
+            (Some(3), None),
+            (None, None),
+        ],
+        &[0],
+    );
+    assert_eq!(tree.idom(0), None);
+    assert_eq!(tree.idom(1), Some(0));
+    assert_eq!(tree.idom(2), Some(0));
+    assert_eq!(tree.idom(3), Some(0));
+    assert_eq!(tree.retained(0), Some(4 * NODE));
+    assert_eq!(tree.retained(1), Some(NODE));
+    assert_eq!(tree.retained(3), Some(NODE));
+}
+
+#[test]
+fn cycle() {
+    // 0 -> 1 -> 2 -> 0, 2 -> 2
+    let tree = Graph::new().nodes(
+        &[(Some(1), None), (Some(2), None), (Some(0), Some(2))],
+        &[0],
+    );
+    assert_eq!(tree.idom(1), Some(0));
+    assert_eq!(tree.idom(2), Some(1));
+    assert_eq!(tree.retained(0), Some(3 * NODE));
+    assert_eq!(tree.retained(1), Some(2 * NODE));
+    assert_eq!(tree.retained(2), Some(NODE));
+}
+
+#[test]
+fn unreachable_nodes_dominate_nothing() {
+    // 1 -> 2 <- 0, only 0 is a root
+    let tree = Graph::new().nodes(&[(Some(2), None), (Some(2), None), (None, None)], &[0]);
+    assert!(!tree.tree.is_reachable(tree.ids[1]));
+    assert_eq!(tree.retained(1), None);
+    assert_eq!(tree.idom(2), Some(0));
+    assert_eq!(tree.retained(0), Some(2 * NODE));
+    assert_eq!(tree.tree.reachable_size(), 2 * NODE);
+}
+
+#[test]
+fn two_roots_share_what_both_reach() {
+    // 0 -> 2 <- 1, both roots
+    let tree = Graph::new().nodes(&[(Some(2), None), (Some(2), None), (None, None)], &[0, 1]);
+    assert_eq!(tree.idom(2), None);
+    assert_eq!(tree.retained(0), Some(NODE));
+    // the three nodes and their class, which every instance references
+    assert_eq!(tree.tree.top_level_count(), 4);
+}
+
+#[test]
+fn csr_keeps_the_edges_of_every_node_in_order() {
+    let edges = [(2, 0), (0, 1), (2, 2), (0, 3), (2, 1)];
+    let csr = Csr::build(4, edges.iter().copied());
+    assert_eq!(csr.get(0), &[1, 3]);
+    assert!(csr.get(1).is_empty());
+    assert_eq!(csr.get(2), &[0, 2, 1]);
+    assert!(csr.get(3).is_empty());
+}
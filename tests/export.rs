@@ -0,0 +1,382 @@
+#![cfg(feature = "report")]
+
+use std::io::{Cursor, Read};
+
+use chrono::DateTime;
+
+use heapdump_analyzer::{
+    analyzer::{AnalyzedHeap, filter::ClassFilter, graph::RootKind, size::SizeModel},
+    export::{
+        for_each_object, for_each_string,
+        json::{ReportOptions, report},
+        ndjson::RowWriter,
+        sqlite::write_database,
+        trend::{Trend, TrendPoint},
+        xlsx::write_workbook,
+    },
+    parser::{
+        Id, ParsedHeap, Record, RecordReader, StringId,
+        sub_record::{FieldValue, PrimArray},
+    },
+    testutil::HeapBuilder,
+    writer::RecordWriter,
+};
+
+// a Node with a reference, extending a Base with a reference and an int. the class dumps are
+// moved behind the instances, like writers dumping classes as they come across them
+fn dump_with_classes_last() -> (Vec<u8>, Id, Id) {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let base = builder.class("Base", Some(object), &[("parent", 2), ("size", 10)]);
+    let node = builder.class("Node", Some(base), &[("next", 2)]);
+    let tail = builder.instance(
+        node,
+        &[
+            FieldValue::NormalObject { object_id: Id(0) },
+            FieldValue::NormalObject { object_id: Id(0) },
+            FieldValue::Int(1),
+        ],
+    );
+    let head = builder.instance(
+        node,
+        &[
+            FieldValue::NormalObject { object_id: tail },
+            FieldValue::NormalObject { object_id: tail },
+            FieldValue::Int(2),
+        ],
+    );
+    let parsed = ParsedHeap::from_bytes(builder.build().unwrap()).unwrap();
+
+    let mut writer = RecordWriter::new(Vec::new(), &parsed.header()).unwrap();
+    for record in parsed.records {
+        let record = match record {
+            Record::HeapDumpSegment {
+                micros,
+                sub_records,
+            } => {
+                let (classes, mut sub_records): (Vec<_>, Vec<_>) = sub_records
+                    .into_iter()
+                    .partition(|s| s.dumped_object_id().is_some_and(|id| id < tail));
+                sub_records.extend(classes);
+                Record::HeapDumpSegment {
+                    micros,
+                    sub_records,
+                }
+            }
+            record => record,
+        };
+        writer.write_record(&record).unwrap();
+    }
+    (writer.finish().unwrap(), head, tail)
+}
+
+#[test]
+fn instances_dumped_before_their_classes_are_sized_with_all_fields() {
+    let (dump, _, _) = dump_with_classes_last();
+    let records = RecordReader::new(Cursor::new(dump)).unwrap();
+    let mut rows = Vec::new();
+    for_each_object(records, &SizeModel::default(), |row| {
+        rows.push(row);
+        Ok(())
+    })
+    .unwrap();
+
+    assert_eq!(rows.len(), 2);
+    for row in rows {
+        assert_eq!(row.class, "Node");
+        // 12 byte header, two 4 byte references and an int
+        assert_eq!(row.shallow_size, 24);
+    }
+}
+
+#[test]
+fn sqlite_export_has_the_references_of_superclass_fields() {
+    let (dump, head, tail) = dump_with_classes_last();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("heap.db");
+    let records = RecordReader::new(Cursor::new(dump)).unwrap();
+    let counts = write_database(records, &SizeModel::default(), &path).unwrap();
+    assert_eq!(counts.objects, 2);
+    assert_eq!(counts.classes, 3);
+
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    let shallow_size: i64 = conn
+        .query_row(
+            "SELECT shallow_size FROM objects WHERE id = ?1",
+            [head.0 as i64],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(shallow_size, 24);
+    let references: i64 = conn
+        .query_row(
+            "SELECT count(*) FROM refs WHERE from_id = ?1 AND to_id = ?2",
+            [head.0 as i64, tail.0 as i64],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(references, 2);
+}
+
+#[test]
+fn sqlite_export_has_arrays_threads_roots_and_strings() {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let array_class = builder.class("[Ljava/lang/Object;", Some(object), &[]);
+    let bytes = builder.prim_array(PrimArray::Byte(vec![1, 2, 3])).unwrap();
+    let array = builder.object_array(array_class, &[bytes, Id(0)]);
+    let thread = builder.instance(object, &[]);
+    builder.thread(thread, &[("run", "()V", "Main.java", 7)]);
+    builder.root(RootKind::JniGlobal, array).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("heap.db");
+    let records = RecordReader::new(Cursor::new(builder.build().unwrap())).unwrap();
+    let counts = write_database(records, &SizeModel::default(), &path).unwrap();
+    assert_eq!(counts.objects, 3);
+
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    let object = |id: Id| -> (String, i64, i64, Option<i64>) {
+        conn.query_row(
+            "SELECT kind, class_id, shallow_size, length FROM objects WHERE id = ?1",
+            [id.0 as i64],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .unwrap()
+    };
+    // 16 byte headers, two references and three bytes aligned to 8
+    assert_eq!(
+        object(array),
+        ("object_array".into(), array_class.0 as i64, 24, Some(2))
+    );
+    let (kind, _, shallow_size, length) = object(bytes);
+    assert_eq!(
+        (kind.as_str(), shallow_size, length),
+        ("primitive_array", 24, Some(3))
+    );
+
+    // the class and the element, nulls aren't references
+    let references: Vec<i64> = conn
+        .prepare("SELECT to_id FROM refs WHERE from_id = ?1 ORDER BY to_id")
+        .unwrap()
+        .query_map([array.0 as i64], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(references, vec![array_class.0 as i64, bytes.0 as i64]);
+
+    let roots: Vec<(i64, String)> = conn
+        .prepare("SELECT object_id, kind FROM roots ORDER BY object_id")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        roots,
+        vec![
+            (array.0 as i64, "JNI global".to_string()),
+            (thread.0 as i64, "thread object".to_string())
+        ]
+    );
+    let threads: i64 = conn
+        .query_row(
+            "SELECT count(*) FROM threads WHERE object_id = ?1",
+            [thread.0 as i64],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(threads, 1);
+    let method: i64 = conn
+        .query_row(
+            "SELECT count(*) FROM strings WHERE value = 'run'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(method, 1);
+}
+
+fn export_objects(dump: &[u8], ndjson: bool) -> Vec<u8> {
+    let records = RecordReader::new(Cursor::new(dump)).unwrap();
+    let mut out = Vec::new();
+    let mut writer = RowWriter::new(&mut out, ndjson);
+    for_each_object(records, &SizeModel::default(), |row| writer.write_row(&row)).unwrap();
+    assert_eq!(writer.finish().unwrap(), 3);
+    out
+}
+
+#[test]
+fn ndjson_has_one_object_per_line() {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("Node", Some(object), &[("value", 10)]);
+    let array_class = builder.class("[LNode;", Some(object), &[]);
+    let instance = builder.instance(node, &[FieldValue::Int(1)]);
+    builder.object_array(array_class, &[instance]);
+    builder.prim_array(PrimArray::Int(vec![1, 2, 3])).unwrap();
+    let dump = builder.build().unwrap();
+
+    let ndjson = String::from_utf8(export_objects(&dump, true)).unwrap();
+    let rows: Vec<serde_json::Value> = ndjson
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0]["id"], instance.to_string());
+    assert_eq!(rows[0]["kind"], "instance");
+    assert_eq!(rows[0]["class"], "Node");
+    assert_eq!(rows[0]["shallow_size"], 16);
+    assert!(rows[0].get("length").is_none());
+    assert_eq!(rows[1]["kind"], "object_array");
+    assert_eq!(rows[1]["length"], 1);
+    assert_eq!(rows[2]["kind"], "primitive_array");
+    assert_eq!(rows[2]["class"], "int[]");
+    assert_eq!(rows[2]["length"], 3);
+
+    // the same rows as one array without the flag
+    let array: Vec<serde_json::Value> =
+        serde_json::from_slice(&export_objects(&dump, false)).unwrap();
+    assert_eq!(array, rows);
+
+    let records = RecordReader::new(Cursor::new(&dump)).unwrap();
+    let mut out = Vec::new();
+    let mut writer = RowWriter::new(&mut out, true);
+    for_each_string(records, |row| writer.write_row(&row)).unwrap();
+    // java/lang/Object, Node, value, [LNode; and [I
+    assert_eq!(writer.finish().unwrap(), 5);
+    let strings: Vec<serde_json::Value> = out
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice(line).unwrap())
+        .collect();
+    assert!(strings.iter().any(|row| row["value"] == "Node"));
+}
+
+#[test]
+fn workbooks_have_a_sheet_of_duplicate_strings() {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    builder.instance(object, &[]);
+    let parsed = ParsedHeap::from_bytes(builder.build().unwrap()).unwrap();
+
+    // the builder interns its strings, the copies are written as records of their own
+    let mut writer = RecordWriter::new(Vec::new(), &parsed.header()).unwrap();
+    let (end, records) = parsed.records.split_last().unwrap();
+    for record in records {
+        writer.write_record(record).unwrap();
+    }
+    for name_id in [0x9000, 0x9008, 0x9010] {
+        let record = Record::Utf8 {
+            micros: 0,
+            name_id: StringId(name_id),
+            content: "copied".into(),
+        };
+        writer.write_record(&record).unwrap();
+    }
+    writer.write_record(end).unwrap();
+    let parsed = ParsedHeap::from_bytes(writer.finish().unwrap()).unwrap();
+    let heap = AnalyzedHeap::analyze(&parsed).unwrap();
+
+    let options = ReportOptions {
+        filter: &ClassFilter::default(),
+        depth: 1,
+        children: 10,
+    };
+    let report = report(&parsed.header(), &heap, heap.dominator_tree(), &options);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("report.xlsx");
+    write_workbook(&report, &heap, &path).unwrap();
+
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    let mut read = |name: &str| {
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    };
+    assert!(read("xl/workbook.xml").contains("name=\"Duplicates\""));
+    assert!(read("xl/sharedStrings.xml").contains("<t>copied</t>"));
+    // three copies of six bytes, the two extra ones are wasted
+    let sheet = read("xl/worksheets/sheet4.xml");
+    assert!(sheet.contains("<v>3</v>"));
+    assert!(sheet.contains("<v>12</v>"));
+}
+
+// a dump of a series, hours after the first one
+fn point(dump: &str, hours: i64, classes: &[(&str, u64)]) -> TrendPoint {
+    TrendPoint {
+        dump: dump.to_string(),
+        timestamp: DateTime::from_timestamp(1_700_000_000 + hours * 3600, 0).unwrap(),
+        total_bytes: 1000,
+        reachable_bytes: 800,
+        objects: 10,
+        threads: 2,
+        class_retained_bytes: classes
+            .iter()
+            .map(|(class, retained)| (class.to_string(), *retained))
+            .collect(),
+    }
+}
+
+#[test]
+fn trends_have_a_column_per_top_class_and_a_row_per_dump_by_time() {
+    let trend = Trend::new(
+        vec![
+            point("b.hprof", 1, &[("com.example.Cache", 300), ("a,b", 5)]),
+            point("a.hprof", 0, &[("com.example.Cache", 100), ("byte[]", 200)]),
+        ],
+        2,
+    );
+
+    let mut csv = Vec::new();
+    trend.write_csv(&mut csv).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "time,dump,total_bytes,reachable_bytes,objects,threads,com.example.Cache,byte[]\n\
+         2023-11-14T22:13:20+00:00,a.hprof,1000,800,10,2,100,200\n\
+         2023-11-14T23:13:20+00:00,b.hprof,1000,800,10,2,300,0\n"
+    );
+
+    let mut json = Vec::new();
+    trend.write_json(&mut json).unwrap();
+    let rows: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(rows[1]["dump"], "b.hprof");
+    assert_eq!(rows[1]["com.example.Cache"], 300);
+    assert!(rows[1].get("a,b").is_none());
+}
+
+#[test]
+fn classes_growing_in_every_dump_are_probable_leaks() {
+    let points = |sizes: [u64; 3]| -> Vec<TrendPoint> {
+        sizes
+            .iter()
+            .enumerate()
+            .map(|(i, size)| {
+                point(
+                    &format!("{}.hprof", i),
+                    i as i64,
+                    &[
+                        ("com.example.Cache", *size),
+                        ("com.example.Pool", 100),
+                        ("com.example.Buffer", [100, 50, 400][i]),
+                    ],
+                )
+            })
+            .collect()
+    };
+
+    let trend = Trend::new(points([100, 200, 300]), 10);
+    let leaks = trend.probable_leaks(50.0);
+    // the pool stays the same, the buffer shrank in between
+    assert_eq!(leaks.len(), 1);
+    assert_eq!(leaks[0].class, "com.example.Cache");
+    assert_eq!((leaks[0].first_bytes, leaks[0].last_bytes), (100, 300));
+    assert_eq!(leaks[0].bytes_per_hour, 100.0);
+    assert!(trend.probable_leaks(150.0).is_empty());
+
+    let short = Trend::new(points([100, 200, 300])[..2].to_vec(), 10);
+    assert!(short.probable_leaks(0.0).is_empty());
+}
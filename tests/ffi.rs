@@ -0,0 +1,50 @@
+use std::{
+    ffi::{CStr, CString},
+    mem::MaybeUninit,
+    ptr,
+};
+
+use heapdump_analyzer::{
+    ffi::{
+        HdaHistogramEntry, hda_heap_close, hda_heap_open, hda_histogram, hda_histogram_free,
+        hda_histogram_next, hda_last_error,
+    },
+    parser::sub_record::FieldValue,
+    testutil::HeapBuilder,
+};
+
+#[test]
+fn class_names_with_nul_bytes_are_handed_out_with_spaces() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("heap.hprof");
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let class = builder.class("Odd\0Name", Some(object), &[("value", 10)]);
+    builder.instance(class, &[FieldValue::Int(1)]);
+    builder.write(&path).unwrap();
+
+    let path = CString::new(path.to_str().unwrap()).unwrap();
+    let heap = unsafe { hda_heap_open(path.as_ptr()) };
+    assert!(!heap.is_null(), "{:?}", unsafe {
+        CStr::from_ptr(hda_last_error())
+    });
+
+    let mut names = Vec::new();
+    unsafe {
+        let histogram = hda_histogram(heap, ptr::null());
+        assert!(!histogram.is_null());
+        let mut entry = MaybeUninit::<HdaHistogramEntry>::uninit();
+        while hda_histogram_next(histogram, entry.as_mut_ptr()) {
+            let entry = entry.assume_init();
+            names.push(
+                CStr::from_ptr(entry.class_name)
+                    .to_str()
+                    .unwrap()
+                    .to_owned(),
+            );
+        }
+        hda_histogram_free(histogram);
+        hda_heap_close(heap);
+    }
+    assert_eq!(names, ["Odd Name"]);
+}
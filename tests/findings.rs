@@ -0,0 +1,478 @@
+use std::path::Path;
+
+use heapdump_analyzer::{
+    AnalyzedHeap,
+    analyzer::{
+        caches::cache_info,
+        contents::Contents,
+        dedup::dedup_estimate,
+        encoding::{StringEncoding, string_encodings},
+        filter::ClassFilter,
+        graph::RootKind,
+        maps::map_waste,
+        options::AnalysisOptions,
+        packages::{package_dominators, package_of},
+        resources::unclosed_resources,
+        roots::root_summary,
+        sparse::{DEFAULT_NULL_FRACTION, sparse_arrays},
+    },
+    parser::{
+        Id,
+        sub_record::{FieldValue, PrimArray},
+    },
+    testutil::HeapBuilder,
+};
+
+fn analyze(builder: HeapBuilder, dir: &Path) -> (AnalyzedHeap, Contents) {
+    let path = dir.join("heap.hprof");
+    builder.write(&path).unwrap();
+    let (_, heap) = AnalyzedHeap::analyze_file_with(&path, &AnalysisOptions::default()).unwrap();
+    let contents = Contents::open(&path, &heap).unwrap();
+    (heap, contents)
+}
+
+fn reference(object_id: Id) -> FieldValue {
+    FieldValue::NormalObject { object_id }
+}
+
+#[test]
+fn caches_are_told_by_library_access_order_name_or_entry_fields() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let hash_map = builder.class(
+        "java/util/HashMap",
+        Some(object),
+        &[("table", 2), ("size", 10)],
+    );
+    let linked_hash_map = builder.class(
+        "java/util/LinkedHashMap",
+        Some(hash_map),
+        &[("accessOrder", 4)],
+    );
+    let node = builder.class(
+        "java/util/HashMap$Node",
+        Some(object),
+        &[("key", 2), ("value", 2)],
+    );
+    let table = builder.class("[Ljava/util/HashMap$Node;", Some(object), &[]);
+    let token = builder.class("com/example/Token", Some(object), &[("expiresAt", 11)]);
+    let session_cache = builder.class("com/example/SessionCache", Some(object), &[("size", 10)]);
+    let local_cache = builder.class("com/google/common/cache/LocalCache", Some(object), &[]);
+
+    let lru = builder.instance(
+        linked_hash_map,
+        &[
+            FieldValue::Boolean(true),
+            reference(Id(0)),
+            FieldValue::Int(3),
+        ],
+    );
+    let named = builder.instance(session_cache, &[FieldValue::Int(7)]);
+    let library = builder.instance(local_cache, &[]);
+    let map = |builder: &mut HeapBuilder, value: Id| {
+        let key = builder.instance(object, &[]);
+        let entry = builder.instance(node, &[reference(key), reference(value)]);
+        let bins = builder.object_array(table, &[Id(0), entry]);
+        builder.instance(hash_map, &[reference(bins), FieldValue::Int(1)])
+    };
+    let value = builder.instance(token, &[FieldValue::Long(0)]);
+    let timed = map(&mut builder, value);
+    let value = builder.instance(object, &[]);
+    let plain = map(&mut builder, value);
+    let (heap, contents) = analyze(builder, dir.path());
+
+    let info = |id: Id| cache_info(&heap, &contents, id).unwrap();
+    let lru = info(lru).unwrap();
+    assert_eq!(lru.hint, "access ordered, the usual LRU cache");
+    assert_eq!(lru.entries, Some(3));
+    assert_eq!(lru.average_entry_size(300), Some(100));
+    let named = info(named).unwrap();
+    assert_eq!(
+        (named.hint.as_str(), named.entries),
+        ("named like a cache", Some(7))
+    );
+    let library = info(library).unwrap();
+    assert_eq!(
+        (library.hint.as_str(), library.entries),
+        ("cache library", None)
+    );
+    assert_eq!(library.average_entry_size(300), None);
+    let timed = info(timed).unwrap();
+    assert_eq!(
+        (timed.hint.as_str(), timed.entries),
+        ("entries have a expiresAt field", Some(1))
+    );
+    assert!(info(plain).is_none());
+}
+
+#[test]
+fn resources_nothing_strongly_references_are_unclosed() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let reference_class =
+        builder.class("java/lang/ref/Reference", Some(object), &[("referent", 2)]);
+    let final_reference = builder.class("java/lang/ref/FinalReference", Some(reference_class), &[]);
+    let finalizer = builder.class("java/lang/ref/Finalizer", Some(final_reference), &[]);
+    let input_stream = builder.class("java/io/InputStream", Some(object), &[]);
+    let file_input_stream = builder.class(
+        "java/io/FileInputStream",
+        Some(input_stream),
+        &[("closed", 4)],
+    );
+    let connection = builder.class("org/postgresql/jdbc/PgConnection", Some(object), &[]);
+    let array_class = builder.class("[Ljava/lang/Object;", Some(object), &[]);
+
+    let open = builder.instance(file_input_stream, &[FieldValue::Boolean(false)]);
+    builder.root(RootKind::JniGlobal, open).unwrap();
+    builder.instance(file_input_stream, &[FieldValue::Boolean(false)]);
+    builder.instance(file_input_stream, &[FieldValue::Boolean(true)]);
+    let finalized = builder.instance(file_input_stream, &[FieldValue::Boolean(false)]);
+    let finalizer = builder.instance(finalizer, &[reference(finalized)]);
+    // the queue of finalizers still to run
+    let queue = builder.object_array(array_class, &[finalizer]);
+    builder.root(RootKind::JniGlobal, queue).unwrap();
+    builder.instance(connection, &[]);
+    let (heap, contents) = analyze(builder, dir.path());
+
+    let resources = unclosed_resources(&heap, &contents).unwrap();
+    let rows: Vec<(String, u64, u64)> = resources
+        .iter()
+        .map(|r| (r.class.java_name(), r.unreachable, r.finalizer_only))
+        .collect();
+    // the rooted stream is still in use, the closed one was closed
+    assert_eq!(
+        rows,
+        vec![
+            ("java.io.FileInputStream".to_string(), 1, 1),
+            ("org.postgresql.jdbc.PgConnection".to_string(), 1, 0),
+        ]
+    );
+}
+
+#[test]
+fn root_kinds_are_summarized_with_what_only_they_keep_alive() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("Node", Some(object), &[("value", 10)]);
+    let holder = builder.class("Holder", Some(object), &[("first", 2), ("second", 2)]);
+
+    let shared = builder.instance(node, &[FieldValue::Int(0)]);
+    let own = builder.instance(node, &[FieldValue::Int(1)]);
+    let global = builder.instance(holder, &[reference(shared), reference(own)]);
+    let local = builder.instance(holder, &[reference(shared), reference(Id(0))]);
+    builder.root(RootKind::JniGlobal, global).unwrap();
+    builder.root(RootKind::JniGlobal, global).unwrap();
+    builder.root(RootKind::JavaFrame, local).unwrap();
+    let (heap, _) = analyze(builder, dir.path());
+
+    let summary = root_summary(&heap);
+    let rows: Vec<(RootKind, u64, u64, u64)> = summary
+        .iter()
+        .map(|s| (s.kind, s.roots, s.exclusive_objects, s.exclusive_size))
+        .collect();
+    // the shared node and the classes are reached by both kinds, so by neither alone
+    assert_eq!(
+        rows,
+        vec![
+            (RootKind::JniGlobal, 2, 2, 24 + 16),
+            (RootKind::JavaFrame, 1, 1, 24),
+        ]
+    );
+}
+
+// a java.lang.String with a compact value, coder 0 for latin1 and 1 for utf-16
+fn string(builder: &mut HeapBuilder, class: Id, value: Id, coder: i8) -> Id {
+    builder.instance(class, &[reference(value), FieldValue::Byte(coder)])
+}
+
+fn bytes(content: &str) -> PrimArray {
+    PrimArray::Byte(content.bytes().map(|b| b as i8).collect())
+}
+
+#[test]
+fn duplicate_string_arrays_are_what_deduplication_saves() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let class = builder.class(
+        "java/lang/String",
+        Some(object),
+        &[("value", 2), ("coder", 8)],
+    );
+    let hello = builder.prim_array(bytes("hello")).unwrap();
+    let copy = builder.prim_array(bytes("hello")).unwrap();
+    let world = builder.prim_array(bytes("world")).unwrap();
+    string(&mut builder, class, hello, 0);
+    string(&mut builder, class, copy, 0);
+    // shares the array of the first one already
+    string(&mut builder, class, hello, 0);
+    string(&mut builder, class, world, 0);
+    let (heap, contents) = analyze(builder, dir.path());
+
+    let estimate = dedup_estimate(&heap, &contents).unwrap();
+    assert_eq!(estimate.strings, 4);
+    // 16 byte headers and 5 bytes aligned to 24, the strings are 24 bytes too
+    assert_eq!(estimate.array_size, 3 * 24);
+    assert_eq!(estimate.duplicate_arrays, 1);
+    assert_eq!(estimate.dedup_savings, 24);
+    // interning leaves a single hello string
+    assert_eq!(estimate.intern_savings, 24 + 2 * 24);
+}
+
+#[test]
+fn strings_are_counted_by_encoding() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let class = builder.class(
+        "java/lang/String",
+        Some(object),
+        &[("value", 2), ("coder", 8)],
+    );
+    for latin1 in ["abc", "de"] {
+        let value = builder.prim_array(bytes(latin1)).unwrap();
+        string(&mut builder, class, value, 0);
+    }
+    let value = builder
+        .prim_array(PrimArray::Byte(vec![0x41, 0, 0x42, 0]))
+        .unwrap();
+    string(&mut builder, class, value, 1);
+    let value = builder
+        .prim_array(PrimArray::Char("pre-jdk9".encode_utf16().collect()))
+        .unwrap();
+    string(&mut builder, class, value, 0);
+    let (heap, contents) = analyze(builder, dir.path());
+
+    let stats = string_encodings(&heap, &contents).unwrap();
+    let rows: Vec<(String, u64, u64, u64)> = stats
+        .iter()
+        .map(|s| {
+            (
+                s.encoding.to_string(),
+                s.strings,
+                s.string_size,
+                s.array_size,
+            )
+        })
+        .collect();
+    // 16 byte array headers, eight chars take 16 bytes
+    assert_eq!(
+        rows,
+        vec![
+            ("LATIN1".to_string(), 2, 48, 48),
+            ("char[]".to_string(), 1, 24, 32),
+            ("UTF-16".to_string(), 1, 24, 24),
+        ]
+    );
+    assert_eq!(stats[2].encoding, StringEncoding::Utf16);
+}
+
+#[test]
+fn each_package_has_its_biggest_dominator() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("com/example/Node", Some(object), &[("value", 10)]);
+    let holder = builder.class("com/example/Holder", Some(object), &[("node", 2)]);
+    let thing = builder.class("org/other/Thing", Some(object), &[("value", 10)]);
+    let plain = builder.class("Plain", Some(object), &[("value", 10)]);
+
+    let held = builder.instance(node, &[FieldValue::Int(0)]);
+    let holder = builder.instance(holder, &[reference(held)]);
+    let small = builder.instance(node, &[FieldValue::Int(1)]);
+    let thing = builder.instance(thing, &[FieldValue::Int(2)]);
+    let plain = builder.instance(plain, &[FieldValue::Int(3)]);
+    let array = builder.prim_array(PrimArray::Byte(vec![0; 1024])).unwrap();
+    for id in [holder, small, thing, plain, array] {
+        builder.root(RootKind::JniGlobal, id).unwrap();
+    }
+    let (heap, _) = analyze(builder, dir.path());
+    let tree = heap.dominator_tree();
+
+    let biggest = package_dominators(&heap, tree, &ClassFilter::default());
+    let rows: Vec<(&str, Id, &str, u64)> = biggest
+        .iter()
+        .map(|b| {
+            (
+                b.package.as_str(),
+                b.object_id,
+                b.class_name.as_str(),
+                b.retained_size,
+            )
+        })
+        .collect();
+    // the byte array and the class in the default package have no package
+    assert_eq!(
+        rows,
+        vec![
+            ("com.example", holder, "com.example.Holder", 16 + 16),
+            ("org.other", thing, "org.other.Thing", 16),
+        ]
+    );
+
+    let filter = ClassFilter {
+        include: Vec::new(),
+        exclude: vec!["org.".to_string()],
+    };
+    assert_eq!(package_dominators(&heap, tree, &filter).len(), 1);
+    assert_eq!(package_of("java.util.HashMap$Node[][]"), Some("java.util"));
+    assert_eq!(package_of("int[]"), None);
+}
+
+#[test]
+fn mostly_null_arrays_are_grouped_by_retainer() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let array_class = builder.class("[Ljava/lang/Object;", Some(object), &[]);
+    let holder = builder.class(
+        "com/example/Holder",
+        Some(object),
+        &[("sparse", 2), ("dense", 2)],
+    );
+    // length elements of which nulls are null
+    let array = |builder: &mut HeapBuilder, length: usize, nulls: usize| {
+        let elements: Vec<Id> = (0..length)
+            .map(|i| {
+                if i < nulls {
+                    Id(0)
+                } else {
+                    builder.instance(object, &[])
+                }
+            })
+            .collect();
+        builder.object_array(array_class, &elements)
+    };
+
+    let sparse = array(&mut builder, 20, 15);
+    let dense = array(&mut builder, 20, 5);
+    let holder = builder.instance(holder, &[reference(sparse), reference(dense)]);
+    let rooted = array(&mut builder, 16, 12);
+    let short = array(&mut builder, 4, 4);
+    for id in [holder, rooted, short] {
+        builder.root(RootKind::JniGlobal, id).unwrap();
+    }
+    let (heap, contents) = analyze(builder, dir.path());
+
+    let groups = sparse_arrays(
+        &heap,
+        &contents,
+        heap.dominator_tree(),
+        DEFAULT_NULL_FRACTION,
+    );
+    let rows: Vec<(Option<&str>, u64, u64, u64, u64)> = groups
+        .iter()
+        .map(|g| {
+            (
+                g.retainer_class.as_deref(),
+                g.arrays,
+                g.elements,
+                g.nulls,
+                g.savings,
+            )
+        })
+        .collect();
+    // 4 byte references, the short array is left out
+    assert_eq!(
+        rows,
+        vec![
+            (Some("com.example.Holder"), 1, 20, 15, 60),
+            (None, 1, 16, 12, 48),
+        ]
+    );
+    assert!(groups.iter().all(|g| g.array_class == "java.lang.Object[]"));
+}
+
+#[test]
+fn oversized_hash_maps_are_charged_to_their_owner() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let hash_map = builder.class(
+        "java/util/HashMap",
+        Some(object),
+        &[("table", 2), ("size", 10)],
+    );
+    let linked_hash_map = builder.class(
+        "java/util/LinkedHashMap",
+        Some(hash_map),
+        &[("accessOrder", 4)],
+    );
+    let hash_set = builder.class("java/util/HashSet", Some(object), &[("map", 2)]);
+    let node = builder.class(
+        "java/util/HashMap$Node",
+        Some(object),
+        &[("key", 2), ("value", 2)],
+    );
+    let table = builder.class("[Ljava/util/HashMap$Node;", Some(object), &[]);
+    let registry = builder.class(
+        "com/example/Registry",
+        Some(object),
+        &[("byId", 2), ("names", 2)],
+    );
+    // a table of capacity bins with the first entries filled
+    let bins = |builder: &mut HeapBuilder, capacity: usize, entries: usize| {
+        let bins: Vec<Id> = (0..capacity)
+            .map(|i| {
+                if i < entries {
+                    let key = builder.instance(object, &[]);
+                    builder.instance(node, &[reference(key), reference(Id(0))])
+                } else {
+                    Id(0)
+                }
+            })
+            .collect();
+        builder.object_array(table, &bins)
+    };
+
+    let table = bins(&mut builder, 64, 2);
+    let by_id = builder.instance(hash_map, &[reference(table), FieldValue::Int(2)]);
+    let table = bins(&mut builder, 32, 1);
+    let names_map = builder.instance(
+        linked_hash_map,
+        &[
+            FieldValue::Boolean(false),
+            reference(table),
+            FieldValue::Int(1),
+        ],
+    );
+    let names = builder.instance(hash_set, &[reference(names_map)]);
+    let registry = builder.instance(registry, &[reference(by_id), reference(names)]);
+    builder.root(RootKind::JniGlobal, registry).unwrap();
+    // empty, but of the default size
+    let table = bins(&mut builder, 16, 0);
+    let empty = builder.instance(hash_map, &[reference(table), FieldValue::Int(0)]);
+    builder.root(RootKind::JniGlobal, empty).unwrap();
+    let (heap, contents) = analyze(builder, dir.path());
+
+    let waste = map_waste(&heap, &contents, heap.dominator_tree()).unwrap();
+    let rows: Vec<(&str, u64, u64, u64, u64)> = waste
+        .iter()
+        .map(|w| {
+            (
+                w.map_class.as_str(),
+                w.entries,
+                w.capacity,
+                w.empty_bins,
+                w.wasted,
+            )
+        })
+        .collect();
+    // tables of 16 would do for both
+    assert_eq!(
+        rows,
+        vec![
+            ("java.util.HashMap", 2, 64, 62, 48 * 4),
+            ("java.util.LinkedHashMap", 1, 32, 31, 16 * 4),
+        ]
+    );
+    // the set only wraps its map, the registry is what owns both
+    assert!(
+        waste
+            .iter()
+            .all(|w| w.maps == 1 && w.owner_class.as_deref() == Some("com.example.Registry"))
+    );
+}
@@ -0,0 +1,66 @@
+use heapdump_analyzer::{
+    analyzer::{AnalyzedHeap, dominator::DominatorTree, graph::RootKind},
+    parser::{
+        ParsedHeap,
+        sub_record::{FieldValue, PrimArray},
+    },
+    testutil::HeapBuilder,
+};
+
+#[test]
+fn generated_heap_round_trips() {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("Node", Some(object), &[("next", 2), ("value", 10)]);
+
+    let tail = builder.instance(
+        node,
+        &[
+            FieldValue::NormalObject {
+                object_id: 0.into(),
+            },
+            FieldValue::Int(2),
+        ],
+    );
+    let head = builder.instance(
+        node,
+        &[
+            FieldValue::NormalObject { object_id: tail },
+            FieldValue::Int(1),
+        ],
+    );
+    builder.root(RootKind::JniGlobal, head).unwrap();
+    builder.thread(head, &[("main", "([Ljava/lang/String;)V", "Main.java", 3)]);
+
+    let parsed = ParsedHeap::from_bytes(builder.build().unwrap()).unwrap();
+    let heap = AnalyzedHeap::analyze(&parsed).unwrap();
+
+    let histogram = heap.histogram(&Default::default());
+    assert_eq!(histogram.len(), 1);
+    assert_eq!(histogram[0].class.java_name(), "Node");
+    assert_eq!(histogram[0].instance_count, 2);
+    // 12 byte header, 4 byte reference and 4 byte int
+    assert_eq!(histogram[0].shallow_size, 2 * 24);
+
+    assert_eq!(heap.threads.len(), 1);
+    assert_eq!(heap.threads[0].stack_frame_ids.len(), 1);
+
+    let tree = DominatorTree::compute(&heap);
+    assert_eq!(tree.retained_size(head), Some(48));
+    assert_eq!(tree.immediate_dominator(tail), Some(head));
+}
+
+#[test]
+fn prim_arrays_get_their_class() {
+    let mut builder = HeapBuilder::new();
+    let array = builder.prim_array(PrimArray::Byte(vec![1; 10])).unwrap();
+    builder.root(RootKind::StickyClass, array).unwrap();
+
+    let parsed = ParsedHeap::from_bytes(builder.build().unwrap()).unwrap();
+    let heap = AnalyzedHeap::analyze(&parsed).unwrap();
+
+    let instance = heap.instance(array).unwrap();
+    assert_eq!(instance.class.java_name(), "byte[]");
+    // 16 byte header plus 10 bytes, aligned to 8
+    assert_eq!(instance.shallow_size, 32);
+}
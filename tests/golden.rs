@@ -0,0 +1,234 @@
+use std::{fmt::Write, path::Path};
+
+use heapdump_analyzer::{
+    analyzer::{AnalyzedHeap, graph::RootKind, size::SizeModel, storage::Storage},
+    parser::{
+        Id, ParsedHeap, Record,
+        debug::Full,
+        sub_record::{FieldValue, PrimArray},
+    },
+    testutil::HeapBuilder,
+};
+
+// snapshots of what the parser and the analyzer make of a dump with every record and sub record
+// type. after an intended change, rewrite them with
+//
+//   UPDATE_GOLDEN=1 cargo test --test golden
+//
+// and review the diff of tests/golden like any other change
+
+#[test]
+fn parsed_records_match_snapshot() {
+    let parsed = ParsedHeap::from_bytes(fixture()).unwrap();
+
+    let stats = parsed.stats();
+    assert_eq!(
+        stats.records.len(),
+        6,
+        "a record type is missing from the fixture"
+    );
+    assert_eq!(
+        stats.sub_records.len(),
+        9,
+        "a sub record type is missing from the fixture"
+    );
+    assert_golden("parsed.txt", &render_parsed(&parsed));
+}
+
+#[test]
+fn analysis_matches_snapshot() {
+    let parsed = ParsedHeap::from_bytes(fixture()).unwrap();
+    let heap = AnalyzedHeap::analyze(&parsed).unwrap();
+    assert_golden("analysis.txt", &render_analysis(&heap));
+}
+
+// the streaming analysis of a file has to agree with the one of parsed records
+#[test]
+fn analyzed_file_matches_snapshot() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), fixture()).unwrap();
+    let (_, heap) =
+        AnalyzedHeap::analyze_file(file.path(), SizeModel::default(), &Storage::Memory).unwrap();
+    assert_golden("analysis.txt", &render_analysis(&heap));
+}
+
+// ids are handed out in call order, the snapshots depend on it. new calls go at the end
+fn fixture() -> Vec<u8> {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    builder.class_with_statics(
+        "com/example/Constants",
+        Some(object),
+        &[],
+        vec![
+            ("FLAG", FieldValue::Boolean(true)),
+            ("LETTER", FieldValue::Char(65)),
+            ("RATIO", FieldValue::Float(1.5)),
+            ("SCALE", FieldValue::Double(0.25)),
+            ("TAG", FieldValue::Byte(-1)),
+            ("PORT", FieldValue::Short(8080)),
+            ("COUNT", FieldValue::Int(2)),
+            ("SEED", FieldValue::Long(-42)),
+            ("EMPTY", FieldValue::NormalObject { object_id: Id(0) }),
+        ],
+    );
+    let node = builder.class(
+        "com/example/Node",
+        Some(object),
+        &[("next", 2), ("value", 10), ("weight", 11)],
+    );
+    let node_array = builder.class("[Lcom/example/Node;", Some(object), &[]);
+    let thread_class = builder.class("java/lang/Thread", Some(object), &[]);
+
+    let tail = builder.instance(
+        node,
+        &[
+            FieldValue::NormalObject { object_id: Id(0) },
+            FieldValue::Int(2),
+            FieldValue::Long(20),
+        ],
+    );
+    let head = builder.instance(
+        node,
+        &[
+            FieldValue::NormalObject { object_id: tail },
+            FieldValue::Int(1),
+            FieldValue::Long(10),
+        ],
+    );
+    let nodes = builder.object_array(node_array, &[head, tail, Id(0)]);
+    let thread = builder.instance(thread_class, &[]);
+
+    let bools = builder
+        .prim_array(PrimArray::Bool(vec![true, false]))
+        .unwrap();
+    let chars = builder.prim_array(PrimArray::Char(vec![104, 105])).unwrap();
+    builder.prim_array(PrimArray::Float(vec![0.5])).unwrap();
+    builder
+        .prim_array(PrimArray::Double(vec![2.0, -1.0]))
+        .unwrap();
+    let bytes = builder.prim_array(PrimArray::Byte(vec![1, 2, 3])).unwrap();
+    builder.prim_array(PrimArray::Short(vec![-2])).unwrap();
+    builder
+        .prim_array(PrimArray::Int(vec![7, 8, 9, 10]))
+        .unwrap();
+    builder.prim_array(PrimArray::Long(vec![1])).unwrap();
+
+    builder.root(RootKind::JniGlobal, nodes).unwrap();
+    builder.root(RootKind::JniLocal, bools).unwrap();
+    builder.root(RootKind::JavaFrame, chars).unwrap();
+    builder.root(RootKind::StickyClass, bytes).unwrap();
+    builder.thread(
+        thread,
+        &[
+            ("run", "()V", "Main.java", 12),
+            ("main", "([Ljava/lang/String;)V", "Main.java", 3),
+        ],
+    );
+
+    builder.build().unwrap()
+}
+
+// one line per record, the sub records of segments indented below them
+fn render_parsed(parsed: &ParsedHeap) -> String {
+    let mut out = String::new();
+    writeln!(out, "{}", parsed.version).unwrap();
+    for record in &parsed.records {
+        match record {
+            Record::HeapDumpSegment {
+                micros,
+                sub_records,
+            } => {
+                writeln!(out, "HeapDumpSegment {{ micros: {} }}", micros).unwrap();
+                for sub_record in sub_records {
+                    writeln!(out, "  {:?}", Full(sub_record)).unwrap();
+                }
+            }
+            record => writeln!(out, "{:?}", Full(record)).unwrap(),
+        }
+    }
+    out
+}
+
+fn render_analysis(heap: &AnalyzedHeap) -> String {
+    let mut out = String::new();
+    let histogram = heap.histogram(&Default::default());
+    writeln!(out, "histogram").unwrap();
+    for entry in &histogram {
+        writeln!(
+            out,
+            "  {} count {} shallow {}",
+            entry.class.java_name(),
+            entry.instance_count,
+            entry.shallow_size
+        )
+        .unwrap();
+    }
+
+    let mut ids: Vec<Id> = histogram
+        .iter()
+        .flat_map(|entry| heap.instances_of(entry.class.id).map(|i| i.id))
+        .collect();
+    ids.sort();
+    let tree = heap.dominator_tree();
+    writeln!(out, "objects").unwrap();
+    for id in ids {
+        let instance = heap.instance(id).unwrap();
+        write!(
+            out,
+            "  {} {} shallow {}",
+            id,
+            instance.class.java_name(),
+            instance.shallow_size
+        )
+        .unwrap();
+        match tree.retained_size(id) {
+            Some(retained) => {
+                let dominator = tree
+                    .immediate_dominator(id)
+                    .map_or("root".to_string(), |d| d.to_string());
+                writeln!(out, " retained {} dominator {}", retained, dominator).unwrap();
+            }
+            None => writeln!(out, " unreachable").unwrap(),
+        }
+    }
+
+    writeln!(out, "threads").unwrap();
+    for thread in &heap.threads {
+        writeln!(
+            out,
+            "  {} serial {}",
+            thread.object_id, thread.serial_number
+        )
+        .unwrap();
+        for frame_id in &thread.stack_frame_ids {
+            let frame = heap.frame(*frame_id).unwrap();
+            writeln!(out, "    {}", frame).unwrap();
+        }
+    }
+    out
+}
+
+// compares against the snapshot in tests/golden, or rewrites it with UPDATE_GOLDEN set
+fn assert_golden(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "reading {}: {}, create it with UPDATE_GOLDEN=1",
+            path.display(),
+            e
+        )
+    });
+    assert_eq!(
+        actual, expected,
+        "{} changed, rerun with UPDATE_GOLDEN=1 if that's intended",
+        name
+    );
+}
@@ -0,0 +1,97 @@
+#![cfg(feature = "grpc")]
+
+use std::path::Path;
+
+use heapdump_analyzer::{
+    analyzer::graph::RootKind,
+    config::Config,
+    grpc::{AnalysisClient, AnalysisServer, AnalysisService, FindRequest, OpenRequest},
+    parser::{Id, sub_record::FieldValue},
+    testutil::HeapBuilder,
+};
+use tokio_stream::StreamExt;
+use tonic::{
+    Code,
+    transport::{Channel, Server, server::TcpIncoming},
+};
+
+// rooted nodes with the values 0 to 9
+fn write_heap(path: &Path) -> Vec<Id> {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("Node", Some(object), &[("value", 10)]);
+    let nodes = (0..10)
+        .map(|i| {
+            let instance = builder.instance(node, &[FieldValue::Int(i)]);
+            builder.root(RootKind::JniGlobal, instance).unwrap();
+            instance
+        })
+        .collect();
+    builder.write(path).unwrap();
+    nodes
+}
+
+async fn client() -> AnalysisClient<Channel> {
+    let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = incoming.local_addr().unwrap();
+    tokio::spawn(
+        Server::builder()
+            .add_service(AnalysisServer::new(AnalysisService::new(Config::default())))
+            .serve_with_incoming(incoming),
+    );
+    AnalysisClient::connect(format!("http://{}", addr))
+        .await
+        .unwrap()
+}
+
+fn find_request(heap_id: u64, class_name: &str, conditions: &[&str]) -> FindRequest {
+    FindRequest {
+        heap_id,
+        class_name: class_name.to_string(),
+        conditions: conditions.iter().map(|c| c.to_string()).collect(),
+    }
+}
+
+#[test]
+fn find_streams_the_instances_matching_every_condition() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("heap.hprof");
+    let nodes = write_heap(&path);
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let mut client = client().await;
+        let heap_id = client
+            .open(OpenRequest {
+                path: path.to_string_lossy().into_owned(),
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .heap_id;
+
+        let found: Vec<_> = client
+            .find(find_request(heap_id, "Node", &["value >= 3", "value < 5"]))
+            .await
+            .unwrap()
+            .into_inner()
+            .map(|found| found.unwrap())
+            .collect()
+            .await;
+        let ids: Vec<u64> = found.iter().map(|f| f.object_id).collect();
+        assert_eq!(ids, vec![nodes[3].0, nodes[4].0]);
+        assert!(found.iter().all(|f| f.class_name == "Node"));
+        assert!(found.iter().all(|f| f.shallow_size == 16));
+
+        let status = client
+            .find(find_request(heap_id, "Node", &["value is 3"]))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let status = client
+            .find(find_request(heap_id, "Missing", &["value == 3"]))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    });
+}
@@ -0,0 +1,76 @@
+use heapdump_analyzer::{
+    analyzer::{
+        AnalyzedHeap,
+        graph::RootKind,
+        leaks::{DEFAULT_THRESHOLD, SuspectKind, leak_suspects},
+    },
+    parser::{
+        Id, ParsedHeap,
+        sub_record::{FieldValue, PrimArray},
+    },
+    testutil::HeapBuilder,
+};
+
+// a cache holding 50 kb in an array of byte arrays, and 40 sessions with 200 bytes each
+fn heap() -> (AnalyzedHeap, Id, Id) {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let cache_class = builder.class("Cache", Some(object), &[("entries", 2)]);
+    let session_class = builder.class("Session", Some(object), &[("data", 2)]);
+    let array_class = builder.class("[[B", Some(object), &[]);
+
+    let entries: Vec<Id> = (0..50)
+        .map(|_| builder.prim_array(PrimArray::Byte(vec![0; 1000])).unwrap())
+        .collect();
+    let array = builder.object_array(array_class, &entries);
+    let cache = builder.instance(
+        cache_class,
+        &[FieldValue::NormalObject { object_id: array }],
+    );
+    builder.root(RootKind::JniGlobal, cache).unwrap();
+
+    for _ in 0..40 {
+        let data = builder.prim_array(PrimArray::Byte(vec![0; 200])).unwrap();
+        let session = builder.instance(
+            session_class,
+            &[FieldValue::NormalObject { object_id: data }],
+        );
+        builder.root(RootKind::JniGlobal, session).unwrap();
+    }
+
+    let parsed = ParsedHeap::from_bytes(builder.build().unwrap()).unwrap();
+    (AnalyzedHeap::analyze(&parsed).unwrap(), cache, array)
+}
+
+#[test]
+fn suspects_are_the_outermost_objects_and_classes_retaining_enough() {
+    let (heap, cache, array) = heap();
+    let suspects = leak_suspects(&heap, heap.dominator_tree(), DEFAULT_THRESHOLD);
+
+    // the array retains nearly as much as the cache, but the cache explains it
+    assert!(matches!(
+        suspects[0].kind,
+        SuspectKind::Object { object_id } if object_id == cache
+    ));
+    assert!(!suspects.iter().any(|s| matches!(
+        s.kind,
+        SuspectKind::Object { object_id } if object_id == array
+    )));
+
+    let sessions = suspects
+        .iter()
+        .find(|s| s.class.name.as_ref() == "Session")
+        .unwrap();
+    assert!(matches!(
+        sessions.kind,
+        SuspectKind::Class { instance_count: 40 }
+    ));
+    // 16 bytes for each session and 216 for its array
+    assert_eq!(sessions.retained_size, 40 * (16 + 216));
+}
+
+#[test]
+fn nothing_is_suspect_above_the_whole_heap() {
+    let (heap, _, _) = heap();
+    assert!(leak_suspects(&heap, heap.dominator_tree(), 1.01).is_empty());
+}
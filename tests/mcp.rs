@@ -0,0 +1,90 @@
+#![cfg(feature = "report")]
+
+use heapdump_analyzer::{
+    analyzer::{graph::RootKind, index::HeapIndex, options::AnalysisOptions},
+    export::json::MAX_EDGES,
+    mcp::serve,
+    parser::{Id, sub_record::FieldValue},
+    testutil::HeapBuilder,
+};
+use serde_json::{Value, json};
+
+// a class with more rooted instances than inspect lists referrers
+fn index() -> (HeapIndex, Id) {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("Node", Some(object), &[("value", 10)]);
+    for i in 0..MAX_EDGES + 5 {
+        let instance = builder.instance(node, &[FieldValue::Int(i as i32)]);
+        builder.root(RootKind::JniGlobal, instance).unwrap();
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("heap.hprof");
+    builder.write(&path).unwrap();
+    (
+        HeapIndex::build(&path, &AnalysisOptions::default()).unwrap(),
+        node,
+    )
+}
+
+// the results of the requests, one per line
+fn call(index: &HeapIndex, requests: &[Value]) -> Vec<Value> {
+    let input: String = requests.iter().map(|r| format!("{}\n", r)).collect();
+    let mut output = Vec::new();
+    serve(index, input.as_bytes(), &mut output).unwrap();
+    String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .collect()
+}
+
+fn tool(id: u64, name: &str, arguments: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": "tools/call",
+        "params": { "name": name, "arguments": arguments },
+    })
+}
+
+#[test]
+fn histograms_have_retained_sizes_on_every_call() {
+    let (index, _) = index();
+    let responses = call(
+        &index,
+        &[
+            tool(1, "histogram", json!({ "include": ["Node"] })),
+            tool(2, "histogram", json!({ "include": ["Node"] })),
+        ],
+    );
+    assert_eq!(responses.len(), 2);
+    for response in responses {
+        let histogram = &response["result"]["structuredContent"];
+        assert_eq!(histogram["total"], 1);
+        assert_eq!(histogram["items"][0]["instances"], MAX_EDGES + 5);
+        assert_eq!(histogram["items"][0]["retained_size"], (MAX_EDGES + 5) * 16);
+    }
+}
+
+#[test]
+fn inspect_lists_the_first_referrers_of_a_class() {
+    let (index, node) = index();
+    let responses = call(
+        &index,
+        &[
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+            tool(1, "inspect", json!({ "id": node.to_string() })),
+            tool(2, "inspect", json!({ "id": "0x1" })),
+        ],
+    );
+    // notifications get no response
+    assert_eq!(responses.len(), 2);
+    let object = &responses[0]["result"]["structuredContent"];
+    assert_eq!(object["referrers"]["total"], MAX_EDGES + 5);
+    assert_eq!(
+        object["referrers"]["items"].as_array().unwrap().len(),
+        MAX_EDGES
+    );
+    assert_eq!(responses[1]["result"]["isError"], true);
+}
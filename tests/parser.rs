@@ -0,0 +1,244 @@
+use std::io::Cursor;
+
+use heapdump_analyzer::{
+    error::{HeapError, Policy},
+    parser::{
+        Id, ParsedHeap, ReadCtx, Record, RecordReader, StringId,
+        borrowed::{BorrowedRecord, BorrowedRecords, BorrowedSubRecord},
+        select::InstanceDump,
+        sub_record::{FieldValue, PrimArray, SubRecord},
+    },
+    testutil::HeapBuilder,
+};
+
+#[test]
+fn class_dumps_and_strings_are_looked_up_by_id() {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("Node", Some(object), &[("value", 10)]);
+    builder.instance(node, &[FieldValue::Int(1)]);
+    builder.instance(node, &[FieldValue::Int(2)]);
+    let parsed = ParsedHeap::from_bytes(builder.build().unwrap()).unwrap();
+
+    let class = parsed.class_dump(node).unwrap();
+    assert_eq!(class.super_class_object_id, object);
+    let field = &class.instance_fields[0];
+    assert_eq!(parsed.string(field.name_id), Some("value"));
+
+    assert_eq!(parsed.instances_of(node).count(), 2);
+    assert_eq!(parsed.iter_sub_records::<InstanceDump>().count(), 2);
+    assert!(parsed.class_dump(Id(0xdead)).is_none());
+    assert!(parsed.string(StringId(0xdead)).is_none());
+}
+
+fn every_prim_array() -> Vec<PrimArray> {
+    vec![
+        PrimArray::Bool(vec![true, false, true]),
+        PrimArray::Char(vec![0x68, 0xd83d, 0xffff]),
+        PrimArray::Float(vec![1.5, -0.0, f32::NAN, f32::MIN_POSITIVE]),
+        PrimArray::Double(vec![
+            -2.25,
+            f64::INFINITY,
+            f64::from_bits(0x7ff8_0000_0000_0001),
+        ]),
+        PrimArray::Byte(vec![i8::MIN, -1, 0, i8::MAX]),
+        PrimArray::Short(vec![i16::MIN, -1, i16::MAX]),
+        PrimArray::Int(vec![i32::MIN, -1, 0x0102_0304, i32::MAX]),
+        PrimArray::Long(vec![i64::MIN, -1, 0x0102_0304_0506_0708]),
+        PrimArray::Byte(Vec::new()),
+    ]
+}
+
+// compared as stored in the dump, so nan payloads and negative zero count
+fn be_bytes(elements: &PrimArray) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    elements.write_be(&mut bytes);
+    bytes
+}
+
+#[test]
+fn prim_arrays_of_every_type_are_decoded_in_bulk() {
+    let mut builder = HeapBuilder::new();
+    let arrays: Vec<(Id, PrimArray)> = every_prim_array()
+        .into_iter()
+        .map(|elements| (builder.prim_array(elements.clone()).unwrap(), elements))
+        .collect();
+    let dump = builder.build().unwrap();
+
+    let parsed = ParsedHeap::from_bytes(&dump).unwrap();
+    for (id, expected) in &arrays {
+        let elements = parsed
+            .sub_records()
+            .find_map(|sub_record| match sub_record {
+                SubRecord::PrimArrayDump {
+                    object_id,
+                    typ,
+                    elements,
+                    ..
+                } if object_id == id => Some((*typ, elements)),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(elements.0, expected.typ());
+        assert_eq!(elements.1.len(), expected.len());
+        assert_eq!(be_bytes(elements.1), be_bytes(expected));
+    }
+
+    // the zero copy parser hands out the same bytes
+    let mut found = 0;
+    for record in BorrowedRecords::new(&dump).unwrap() {
+        let BorrowedRecord::HeapDumpSegment { sub_records, .. } = record.unwrap() else {
+            continue;
+        };
+        for sub_record in sub_records {
+            if let BorrowedSubRecord::PrimArrayDump {
+                object_id,
+                elements,
+                ..
+            } = sub_record.unwrap()
+            {
+                let (_, expected) = arrays.iter().find(|(id, _)| *id == object_id).unwrap();
+                assert_eq!(elements, be_bytes(expected));
+                found += 1;
+            }
+        }
+    }
+    assert_eq!(found, arrays.len());
+}
+
+#[test]
+fn sub_records_are_read_with_the_id_size_of_the_dump() {
+    // an object array of two elements and a jni global, with 4 byte ids
+    let mut bytes = vec![0x22];
+    bytes.extend(0x10u32.to_be_bytes());
+    bytes.extend(7u32.to_be_bytes());
+    bytes.extend(2u32.to_be_bytes());
+    bytes.extend(0x20u32.to_be_bytes());
+    bytes.extend(0x30u32.to_be_bytes());
+    bytes.extend(0x40u32.to_be_bytes());
+    bytes.push(0x01);
+    bytes.extend(0x10u32.to_be_bytes());
+    bytes.extend(0x50u32.to_be_bytes());
+
+    let ctx = ReadCtx::new(4).unwrap();
+    let mut r = &bytes[..];
+    let SubRecord::ObjArrayDump {
+        object_id,
+        stack_trace_serial_number,
+        array_class_id,
+        elements,
+    } = SubRecord::new(&mut r, ctx).unwrap()
+    else {
+        panic!("expected an object array");
+    };
+    assert_eq!(object_id, Id(0x10));
+    assert_eq!(stack_trace_serial_number, 7);
+    assert_eq!(array_class_id, Id(0x20));
+    assert_eq!(elements, vec![Id(0x30), Id(0x40)]);
+    assert!(matches!(
+        SubRecord::new(&mut r, ctx).unwrap(),
+        SubRecord::JniGlobal { object_id, global_ref_id }
+            if object_id == Id(0x10) && global_ref_id == Id(0x50)
+    ));
+    assert!(r.is_empty());
+
+    assert!(matches!(
+        ReadCtx::new(2),
+        Err(HeapError::UnsupportedIdSize(2))
+    ));
+}
+
+// the dump of HeapBuilder with a record of tag 0x7f in front of the heap dump end record, or a
+// sub record of type 0x7f at the end of the segment
+fn dump_with_unknown(record: bool) -> Vec<u8> {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    builder.instance(object, &[]);
+    let mut dump = builder.build().unwrap();
+    let end = dump.split_off(dump.len() - 9);
+    assert_eq!(end[0], 0x2c);
+
+    if record {
+        dump.extend([0x7f, 0, 0, 0, 0, 0, 0, 0, 3, 1, 2, 3]);
+    } else {
+        // the segment is the last record, after the header, the strings and the classes
+        let mut offset = 31;
+        loop {
+            let length = u32::from_be_bytes(dump[offset + 5..offset + 9].try_into().unwrap());
+            if dump[offset] == 0x1c {
+                dump[offset + 5..offset + 9].copy_from_slice(&(length + 2).to_be_bytes());
+                break;
+            }
+            offset += 9 + length as usize;
+        }
+        dump.extend([0x7f, 0]);
+    }
+    dump.extend(end);
+    dump
+}
+
+fn read(dump: &[u8], policy: Policy) -> Result<Vec<Record>, HeapError> {
+    RecordReader::new(Cursor::new(dump))
+        .unwrap()
+        .with_policy(policy)
+        .collect()
+}
+
+fn read_borrowed(dump: &[u8], policy: Policy) -> Result<usize, HeapError> {
+    let mut count = 0;
+    for record in BorrowedRecords::new(dump).unwrap().with_policy(policy) {
+        if let BorrowedRecord::HeapDumpSegment { sub_records, .. } = record? {
+            for sub_record in sub_records {
+                sub_record?;
+            }
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[test]
+fn unknown_records_are_skipped_unless_strict() {
+    let dump = dump_with_unknown(true);
+    let records = read(&dump, Policy::Lenient).unwrap();
+    assert!(matches!(records.last(), Some(Record::HeapDumpEnd { .. })));
+    assert!(matches!(
+        read(&dump, Policy::Strict),
+        Err(HeapError::UnknownTag { tag: 0x7f, .. })
+    ));
+
+    assert_eq!(
+        read_borrowed(&dump, Policy::Lenient).unwrap(),
+        records.len()
+    );
+    assert!(matches!(
+        read_borrowed(&dump, Policy::Strict),
+        Err(HeapError::UnknownTag { tag: 0x7f, .. })
+    ));
+}
+
+#[test]
+fn unknown_sub_records_end_their_segment_unless_strict() {
+    let dump = dump_with_unknown(false);
+    let records = read(&dump, Policy::Lenient).unwrap();
+    assert_eq!(
+        records
+            .iter()
+            .filter(|r| matches!(r, Record::HeapDumpSegment { .. }))
+            .count(),
+        1
+    );
+    assert!(matches!(
+        read(&dump, Policy::Strict),
+        Err(HeapError::UnknownSubRecord { typ: 0x7f })
+    ));
+
+    assert_eq!(
+        read_borrowed(&dump, Policy::Lenient).unwrap(),
+        records.len()
+    );
+    assert!(matches!(
+        read_borrowed(&dump, Policy::Strict),
+        Err(HeapError::UnknownSubRecord { typ: 0x7f })
+    ));
+}
@@ -0,0 +1,253 @@
+use std::path::Path;
+
+use heapdump_analyzer::{
+    AnalyzedHeap,
+    analyzer::{
+        contents::Contents,
+        graph::RootKind,
+        group::{group_by_field, numeric_stats},
+        options::AnalysisOptions,
+        predicate::{FieldPredicate, find_instances},
+        referrers::referrer_chains,
+        retainers::top_retainers,
+    },
+    parser::{
+        Id,
+        sub_record::{FieldValue, PrimArray},
+    },
+    testutil::HeapBuilder,
+};
+
+struct Sessions {
+    heap: AnalyzedHeap,
+    contents: Contents,
+    class: Id,
+    store: Id,
+    // the first three are held by the store, the last one by a gc root
+    sessions: [Id; 4],
+    users: [Id; 4],
+}
+
+// a rooted com.example.SessionStore keeping sessions in an array, and a session of its own
+fn sessions(dir: &Path) -> Sessions {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let string = builder.class(
+        "java/lang/String",
+        Some(object),
+        &[("value", 2), ("coder", 8)],
+    );
+    let array_class = builder.class("[Ljava/lang/Object;", Some(object), &[]);
+    let session = builder.class(
+        "com/example/Session",
+        Some(object),
+        &[("user", 2), ("port", 10)],
+    );
+    let store = builder.class("com/example/SessionStore", Some(object), &[("sessions", 2)]);
+
+    let mut users = [Id(0); 4];
+    let mut sessions = [Id(0); 4];
+    for (i, (user, port)) in [("alice", 80), ("bob", 80), ("alice", 443), ("carol", 8080)]
+        .into_iter()
+        .enumerate()
+    {
+        let bytes = PrimArray::Byte(user.bytes().map(|b| b as i8).collect());
+        let value = builder.prim_array(bytes).unwrap();
+        users[i] = builder.instance(
+            string,
+            &[
+                FieldValue::NormalObject { object_id: value },
+                FieldValue::Byte(0),
+            ],
+        );
+        sessions[i] = builder.instance(
+            session,
+            &[
+                FieldValue::NormalObject {
+                    object_id: users[i],
+                },
+                FieldValue::Int(port),
+            ],
+        );
+    }
+    let array = builder.object_array(array_class, &sessions[..3]);
+    let store = builder.instance(store, &[FieldValue::NormalObject { object_id: array }]);
+    builder.root(RootKind::JniGlobal, store).unwrap();
+    builder.root(RootKind::JniGlobal, sessions[3]).unwrap();
+
+    let path = dir.join("heap.hprof");
+    builder.write(&path).unwrap();
+    let (_, heap) = AnalyzedHeap::analyze_file_with(&path, &AnalysisOptions::default()).unwrap();
+    let contents = Contents::open(&path, &heap).unwrap();
+    Sessions {
+        heap,
+        contents,
+        class: session,
+        store,
+        sessions,
+        users,
+    }
+}
+
+#[test]
+fn retainers_are_the_nearest_dominators_of_another_class() {
+    let dir = tempfile::tempdir().unwrap();
+    let Sessions {
+        heap,
+        class,
+        store,
+        sessions,
+        ..
+    } = sessions(dir.path());
+    let tree = heap.dominator_tree();
+    let retained =
+        |ids: &[Id]| -> u64 { ids.iter().map(|id| tree.retained_size(*id).unwrap()).sum() };
+
+    let retainers = top_retainers(&heap, tree, &[class], false);
+    let rows: Vec<(Option<&str>, u64, u64, u64)> = retainers
+        .iter()
+        .map(|r| {
+            (
+                r.class_name.as_deref(),
+                r.objects,
+                r.instances,
+                r.retained_size,
+            )
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            (Some("java.lang.Object[]"), 1, 3, retained(&sessions[..3])),
+            (None, 0, 1, retained(&sessions[3..])),
+        ]
+    );
+
+    // the array comes with the jdk, the store is what holds on to the sessions
+    let retainers = top_retainers(&heap, tree, &[class], true);
+    assert_eq!(
+        retainers[0].class_name.as_deref(),
+        Some("com.example.SessionStore")
+    );
+    assert_eq!(retainers[0].largest, Some(store));
+}
+
+#[test]
+fn referrer_chains_group_objects_by_what_holds_them() {
+    let dir = tempfile::tempdir().unwrap();
+    let Sessions {
+        heap,
+        contents,
+        users,
+        ..
+    } = sessions(dir.path());
+
+    let chains = referrer_chains(&heap, &contents, &users, 2).unwrap();
+    let rows: Vec<(Vec<&str>, u64)> = chains
+        .iter()
+        .map(|c| (c.referrers.iter().map(String::as_str).collect(), c.objects))
+        .collect();
+    // the session of its own has no referrers, its chain ends early
+    assert_eq!(
+        rows,
+        vec![
+            (vec!["java.lang.Object[]", "com.example.Session.user"], 3),
+            (vec!["com.example.Session.user"], 1),
+        ]
+    );
+    let tree = heap.dominator_tree();
+    assert_eq!(
+        chains[1].retained_size,
+        tree.retained_size(users[3]).unwrap()
+    );
+}
+
+#[test]
+fn instances_are_grouped_by_field_value() {
+    let dir = tempfile::tempdir().unwrap();
+    let Sessions {
+        heap,
+        contents,
+        class,
+        sessions,
+        ..
+    } = sessions(dir.path());
+
+    let buckets = group_by_field(&heap, &contents, &[class], "user").unwrap();
+    let rows: Vec<(&str, u64)> = buckets
+        .iter()
+        .map(|b| (b.value.as_str(), b.instances))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![("\"alice\"", 2), ("\"bob\"", 1), ("\"carol\"", 1)]
+    );
+    let tree = heap.dominator_tree();
+    assert_eq!(
+        buckets[0].retained_size,
+        tree.retained_size(sessions[0]).unwrap() + tree.retained_size(sessions[2]).unwrap()
+    );
+
+    let buckets = group_by_field(&heap, &contents, &[class], "port").unwrap();
+    assert_eq!(buckets[0].value, "80");
+    assert!(
+        group_by_field(&heap, &contents, &[class], "user.missing")
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn numeric_fields_have_nearest_rank_percentiles() {
+    let dir = tempfile::tempdir().unwrap();
+    let Sessions {
+        heap,
+        contents,
+        class,
+        ..
+    } = sessions(dir.path());
+
+    let stats = numeric_stats(&heap, &contents, &[class], "port")
+        .unwrap()
+        .unwrap();
+    assert_eq!(stats.instances, 4);
+    assert_eq!((stats.min, stats.max), (80.0, 8080.0));
+    assert_eq!(stats.mean, 2170.75);
+    assert_eq!((stats.p50, stats.p90, stats.p99), (80.0, 8080.0, 8080.0));
+    // strings aren't numbers
+    assert!(
+        numeric_stats(&heap, &contents, &[class], "user")
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn instances_are_found_by_conditions_on_their_fields() {
+    let dir = tempfile::tempdir().unwrap();
+    let Sessions {
+        heap,
+        contents,
+        class,
+        sessions,
+        ..
+    } = sessions(dir.path());
+    let find = |conditions: &[&str]| -> Vec<Id> {
+        let predicates: Vec<FieldPredicate> =
+            conditions.iter().map(|c| c.parse().unwrap()).collect();
+        find_instances(&heap, &contents, &[class], &predicates).unwrap()
+    };
+
+    assert_eq!(find(&["user == 'alice'"]), vec![sessions[0], sessions[2]]);
+    assert_eq!(find(&["user == 'alice'", "port < 100"]), vec![sessions[0]]);
+    assert_eq!(find(&["user startswith ca"]), vec![sessions[3]]);
+    assert_eq!(
+        find(&["port >= 443", "user != \"carol\""]),
+        vec![sessions[2]]
+    );
+    assert!(find(&["user contains dave"]).is_empty());
+
+    assert!("port > many".parse::<FieldPredicate>().is_err());
+    assert!("port is 80".parse::<FieldPredicate>().is_err());
+    assert!("port".parse::<FieldPredicate>().is_err());
+}
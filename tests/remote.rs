@@ -0,0 +1,102 @@
+#![cfg(feature = "cli")]
+
+use std::{
+    pin::pin,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread::Thread,
+};
+
+use heapdump_analyzer::{
+    Heap, config::Config, parser::sub_record::FieldValue, testutil::HeapBuilder,
+};
+
+// the least an executor does, Heap::open_url doesn't need a particular one
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct Unpark(Thread);
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        std::thread::park();
+    }
+}
+
+// answers head and range requests for the dump, counting the range requests
+fn serve(dump: Vec<u8>) -> (String, Arc<AtomicUsize>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/heap.hprof", server.server_addr());
+    let ranges = Arc::new(AtomicUsize::new(0));
+    let counter = ranges.clone();
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let header = |name: &str, value: &str| {
+                tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+            };
+            let range = request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv("range"))
+                .map(|h| h.value.to_string());
+            let response = match range.as_deref().and_then(|r| r.strip_prefix("bytes=")) {
+                Some(range) => {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let (start, end) = range.split_once('-').unwrap();
+                    let (start, end): (usize, usize) =
+                        (start.parse().unwrap(), end.parse().unwrap());
+                    tiny_http::Response::from_data(dump[start..=end].to_vec()).with_status_code(206)
+                }
+                None => tiny_http::Response::from_data(dump.clone()),
+            };
+            let response = response
+                .with_header(header("Accept-Ranges", "bytes"))
+                .with_header(header("ETag", "\"1\""));
+            request.respond(response).unwrap();
+        }
+    });
+    (url, ranges)
+}
+
+#[test]
+fn dumps_behind_urls_are_downloaded_once_and_analyzed() {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("Node", Some(object), &[("value", 10)]);
+    builder.instance(node, &[FieldValue::Int(1)]);
+    let (url, ranges) = serve(builder.build().unwrap());
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = Config::default();
+    config.remote.cache_dir = Some(dir.path().to_path_buf());
+
+    let heap = block_on(Heap::open_url(&url, &config)).unwrap();
+    assert!(heap.path().starts_with(dir.path()));
+    assert_eq!(heap.histogram()[0].class.java_name(), "Node");
+    assert_eq!(ranges.load(Ordering::SeqCst), 1);
+
+    // the second open finds the dump in the cache
+    block_on(Heap::open_url(&url, &config)).unwrap();
+    assert_eq!(ranges.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn unreachable_urls_fail_to_open() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = Config::default();
+    config.remote.cache_dir = Some(dir.path().to_path_buf());
+    // nothing listens on the discard port
+    let result = block_on(Heap::open_url("http://127.0.0.1:9/heap.hprof", &config));
+    assert!(result.is_err());
+}
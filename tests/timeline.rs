@@ -0,0 +1,55 @@
+use heapdump_analyzer::{
+    analyzer::{diagnostic::Diagnostic, timeline::ClassTimeline},
+    error::Policy,
+    parser::{ParsedHeap, Record},
+    testutil::HeapBuilder,
+    writer::RecordWriter,
+};
+
+// a dump whose Node class lost its name
+fn dump_without_class_name() -> (tempfile::NamedTempFile, ParsedHeap) {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    builder.class("Node", Some(object), &[]);
+    let parsed = ParsedHeap::from_bytes(builder.build().unwrap()).unwrap();
+
+    let mut writer = RecordWriter::new(Vec::new(), &parsed.header()).unwrap();
+    for record in &parsed.records {
+        if !matches!(record, Record::Utf8 { content, .. } if content == "Node") {
+            writer.write_record(record).unwrap();
+        }
+    }
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), writer.finish().unwrap()).unwrap();
+    (file, parsed)
+}
+
+#[test]
+fn missing_class_names_are_diagnosed() {
+    let (file, parsed) = dump_without_class_name();
+    let name_id = parsed
+        .records
+        .iter()
+        .find_map(|record| match record {
+            Record::Utf8 {
+                name_id, content, ..
+            } if content == "Node" => Some(*name_id),
+            _ => None,
+        })
+        .unwrap();
+
+    let (_, timeline) = ClassTimeline::read(file.path()).unwrap();
+    assert_eq!(timeline.loads.len(), 2);
+    assert_eq!(timeline.loads[0].class_name, "java.lang.Object");
+    assert!(
+        timeline.loads[1]
+            .class_name
+            .starts_with("<unresolved string")
+    );
+    assert_eq!(
+        timeline.diagnostics,
+        vec![Diagnostic::MissingString { id: name_id }]
+    );
+
+    assert!(ClassTimeline::read_with(file.path(), Policy::Strict).is_err());
+}
@@ -0,0 +1,193 @@
+use std::path::Path;
+
+use heapdump_analyzer::{
+    analyzer::{
+        AnalyzedHeap,
+        graph::{GcRoot, RootKind},
+        options::AnalysisOptions,
+        walk::Limits,
+    },
+    parser::{
+        Id, ParsedHeap, Record,
+        sub_record::{FieldValue, PrimArray, SubRecord},
+    },
+    testutil::HeapBuilder,
+    transform::{
+        scrub::scrub,
+        slice::{Closure, slice},
+        split::{merge, split},
+    },
+};
+
+struct Objects {
+    holder: Id,
+    nodes: [Id; 2],
+    garbage: Id,
+    other: Id,
+}
+
+// a holder on a java frame retaining two nodes, an unreachable node and another rooted node
+fn write_heap(path: &Path) -> Objects {
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let node = builder.class("Node", Some(object), &[("next", 2)]);
+    let holder_class = builder.class("Holder", Some(object), &[("head", 2)]);
+    let tail = builder.instance(node, &[FieldValue::NormalObject { object_id: Id(0) }]);
+    let head = builder.instance(node, &[FieldValue::NormalObject { object_id: tail }]);
+    let holder = builder.instance(
+        holder_class,
+        &[FieldValue::NormalObject { object_id: head }],
+    );
+    builder.root(RootKind::JavaFrame, holder).unwrap();
+    let garbage = builder.instance(node, &[FieldValue::NormalObject { object_id: Id(0) }]);
+    let other = builder.instance(node, &[FieldValue::NormalObject { object_id: Id(0) }]);
+    builder.root(RootKind::JniGlobal, other).unwrap();
+    builder.write(path).unwrap();
+
+    Objects {
+        holder,
+        nodes: [head, tail],
+        garbage,
+        other,
+    }
+}
+
+#[test]
+fn slices_keep_the_roots_of_their_objects() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("heap.hprof");
+    let output = dir.path().join("slice.hprof");
+    let objects = write_heap(&input);
+
+    let stats = slice(
+        &input,
+        &output,
+        &[objects.holder, objects.garbage],
+        Closure::Retained,
+        &Limits::default(),
+        &AnalysisOptions::default(),
+    )
+    .unwrap();
+    // the holder is the only instance of its class, it dominates the class object too
+    assert_eq!(stats.objects, 5);
+    assert_eq!(stats.classes, 3);
+
+    let parsed = ParsedHeap::parse(&output).unwrap();
+    let heap = AnalyzedHeap::analyze(&parsed).unwrap();
+    assert_eq!(heap.class_name_of(objects.holder).unwrap(), "Holder");
+    for id in objects.nodes {
+        assert_eq!(heap.class_name_of(id).unwrap(), "Node");
+    }
+    assert!(heap.instance(objects.other).is_none());
+
+    // the holder keeps its frame, the garbage node had no root and gets a jni global one
+    let roots: Vec<(Id, RootKind)> = parsed
+        .sub_records()
+        .filter_map(GcRoot::from_sub_record)
+        .map(|root| (root.object_id, root.kind))
+        .collect();
+    assert_eq!(
+        roots,
+        vec![
+            (objects.holder, RootKind::JavaFrame),
+            (objects.garbage, RootKind::JniGlobal)
+        ]
+    );
+    assert_eq!(heap.dominator_tree().reachable_size(), 4 * 16);
+}
+
+#[test]
+fn split_parts_are_dumps_of_their_own() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("heap.hprof");
+    write_heap(&input);
+    let parts = dir.path().join("parts");
+
+    let manifest = split(&input, &parts).unwrap();
+    // the strings and classes, the segment and the heap dump end record
+    assert_eq!(manifest.parts.len(), 3);
+    for part in &manifest.parts {
+        let parsed = ParsedHeap::parse(&parts.join(&part.file)).unwrap();
+        assert_eq!(
+            parsed.records.len() as u64,
+            part.records + part.end_added as u64
+        );
+        assert!(matches!(
+            parsed.records.last(),
+            Some(Record::HeapDumpEnd { .. })
+        ));
+    }
+    assert!(!manifest.parts[2].end_added);
+
+    let merged = dir.path().join("merged.hprof");
+    merge(&parts, &merged).unwrap();
+    assert_eq!(
+        std::fs::read(&merged).unwrap(),
+        std::fs::read(&input).unwrap()
+    );
+}
+
+fn bytes(content: &str) -> PrimArray {
+    PrimArray::Byte(content.bytes().map(|b| b as i8).collect())
+}
+
+#[test]
+fn scrub_replaces_string_values_and_unused_strings_only() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("heap.hprof");
+    let output = dir.path().join("scrubbed.hprof");
+    let mut builder = HeapBuilder::new();
+    let object = builder.class("java/lang/Object", None, &[]);
+    let string = builder.class(
+        "java/lang/String",
+        Some(object),
+        &[("hash", 10), ("value", 2), ("coder", 8)],
+    );
+    let secret = builder.prim_array(bytes("secret")).unwrap();
+    let payload = builder.prim_array(bytes("payload")).unwrap();
+    builder.instance(
+        string,
+        &[
+            FieldValue::Int(0),
+            FieldValue::NormalObject { object_id: secret },
+            FieldValue::Byte(0),
+        ],
+    );
+    builder.string("leftover");
+    builder.write(&input).unwrap();
+
+    let stats = scrub(&input, &output).unwrap();
+    assert_eq!(stats.strings, 1);
+    assert_eq!(stats.arrays, 1);
+
+    let parsed = ParsedHeap::parse(&output).unwrap();
+    let array = |id: Id| {
+        parsed
+            .sub_records()
+            .find_map(|sub_record| match sub_record {
+                SubRecord::PrimArrayDump {
+                    object_id,
+                    elements,
+                    ..
+                } if *object_id == id => Some(elements.clone()),
+                _ => None,
+            })
+            .unwrap()
+    };
+    assert!(array(secret) == bytes("xxxxxx"));
+    assert!(array(payload) == bytes("payload"));
+
+    let strings: Vec<&str> = parsed
+        .records
+        .iter()
+        .filter_map(|record| match record {
+            Record::Utf8 { content, .. } => Some(content.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert!(strings.contains(&"java/lang/String"));
+    assert!(strings.contains(&"value"));
+    assert!(strings.contains(&"[B"));
+    assert!(strings.contains(&"xxxxxxxx"));
+    assert!(!strings.contains(&"leftover"));
+}